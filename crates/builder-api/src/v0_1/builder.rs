@@ -9,7 +9,7 @@ use std::path::PathBuf;
 use clap::Args;
 use committable::Committable;
 use derive_more::From;
-use futures::FutureExt;
+use futures::{FutureExt, StreamExt, TryFutureExt};
 use hotshot_types::{traits::node_implementation::NodeType, utils::BuilderCommitment};
 use serde::{Deserialize, Serialize};
 use tagged_base64::TaggedBase64;
@@ -18,7 +18,7 @@ use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, RequestPar
 use vbs::version::StaticVersionType;
 
 use super::{
-    data_source::{AcceptsTxnSubmits, BuilderDataSource},
+    data_source::{AcceptsTxnSubmits, BuilderDataSource, StreamingBuilderDataSource},
     Version,
 };
 use crate::api::load_api;
@@ -215,6 +215,43 @@ where
     Ok(api)
 }
 
+/// Define the builder API, including the route for streaming block assembly.
+pub fn define_streaming_api<State, Types: NodeType>(
+    options: &Options,
+) -> Result<Api<State, Error, Version>, ApiError>
+where
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + StreamingBuilderDataSource<Types>,
+{
+    let mut api = define_api::<State, Types>(options)?;
+    api.stream("available_blocks_stream", |req, state| {
+        async move {
+            let hash = req.blob_param("parent_hash")?;
+            let view_number = req.integer_param("view_number")?;
+            let signature = try_extract_param(&req, "signature")?;
+            let sender = try_extract_param(&req, "sender")?;
+            state
+                .read(|state| {
+                    async move {
+                        state
+                            .available_blocks_stream(&hash, view_number, sender, &signature)
+                            .await
+                            .map(|blocks| blocks.map(Ok))
+                            .map_err(|source| Error::BlockAvailable {
+                                source,
+                                resource: hash.to_string(),
+                            })
+                    }
+                    .boxed()
+                })
+                .await
+        }
+        .try_flatten_stream()
+        .boxed()
+    })?;
+    Ok(api)
+}
+
 pub fn submit_api<State, Types: NodeType, Ver: StaticVersionType + 'static>(
     options: &Options,
 ) -> Result<Api<State, Error, Ver>, ApiError>
@@ -6,6 +6,7 @@
 
 use async_trait::async_trait;
 use committable::Commitment;
+use futures::stream::BoxStream;
 use hotshot_types::{
     traits::{node_implementation::NodeType, signature_key::SignatureKey},
     utils::BuilderCommitment,
@@ -61,6 +62,19 @@ pub trait BuilderDataSource<TYPES: NodeType> {
     async fn builder_address(&self) -> Result<TYPES::BuilderSignatureKey, BuildError>;
 }
 
+#[async_trait]
+pub trait StreamingBuilderDataSource<TYPES: NodeType>: BuilderDataSource<TYPES> {
+    /// To get a stream of available blocks, yielding a new candidate every time the builder
+    /// has assembled a better one. The stream ends when the builder has nothing more to offer.
+    async fn available_blocks_stream(
+        &self,
+        for_parent: &VidCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<BoxStream<'static, AvailableBlockInfo<TYPES>>, BuildError>;
+}
+
 #[async_trait]
 pub trait AcceptsTxnSubmits<I>
 where
@@ -0,0 +1,279 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Verification of `HotShot` certificates without the standard library.
+//!
+//! Quorum, timeout and DA certificates are all an aggregate signature over a commitment to the
+//! certified data, with a bit vector of which stake table members signed. This crate holds the
+//! checks on them: that the signers cover the stake table, that they hold at least the threshold
+//! of stake, and that the aggregate signature verifies against their keys. It needs only `alloc`,
+//! so that embedded verifiers and on-chain verification programs run the same checks as the
+//! nodes, which reach them through `hotshot_types::qc::BitVectorQc`.
+//!
+//! The commitment a certificate signs is computed by the caller.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+use bitvec::slice::BitSlice;
+use jf_signature::{AggregateableSignatureSchemes, SignatureError};
+use primitive_types::U256;
+
+/// A member of the stake table a certificate is verified against
+pub trait StakeEntry {
+    /// Type of the verification key
+    type Key;
+
+    /// The member's verification key
+    fn key(&self) -> &Self::Key;
+    /// The member's stake
+    fn stake(&self) -> U256;
+}
+
+impl<K> StakeEntry for (K, U256) {
+    type Key = K;
+
+    fn key(&self) -> &K {
+        &self.0
+    }
+
+    fn stake(&self) -> U256 {
+        self.1
+    }
+}
+
+/// Why a certificate failed verification
+#[derive(Debug)]
+pub enum CertError {
+    /// The signers bit vector is not the length of the stake table
+    SignersLength {
+        /// Length of the bit vector
+        signers: usize,
+        /// Number of members of the stake table
+        stake_table: usize,
+    },
+    /// The signers hold less than the threshold of stake
+    InsufficientStake {
+        /// Stake of the signers
+        signed: U256,
+        /// Stake needed for the certificate
+        threshold: U256,
+    },
+    /// The aggregate signature does not verify against the signers' keys
+    Signature(SignatureError),
+}
+
+impl Display for CertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SignersLength {
+                signers,
+                stake_table,
+            } => write!(
+                f,
+                "signers bit vector len {signers} != the number of stake entries {stake_table}"
+            ),
+            Self::InsufficientStake { signed, threshold } => {
+                write!(f, "total_weight {signed} less than threshold {threshold}")
+            }
+            Self::Signature(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CertError {}
+
+impl From<CertError> for SignatureError {
+    fn from(e: CertError) -> Self {
+        match e {
+            CertError::Signature(e) => e,
+            e => SignatureError::ParameterError(alloc::format!("{e}")),
+        }
+    }
+}
+
+/// The stake of the `signers` of a certificate, who must hold at least `threshold` of it
+///
+/// # Errors
+/// If `signers` is not the length of `stake_table`, or the signers hold too little stake
+pub fn signed_stake<E: StakeEntry>(
+    stake_table: &[E],
+    signers: &BitSlice,
+    threshold: U256,
+) -> Result<U256, CertError> {
+    if signers.len() != stake_table.len() {
+        return Err(CertError::SignersLength {
+            signers: signers.len(),
+            stake_table: stake_table.len(),
+        });
+    }
+    let signed = stake_table
+        .iter()
+        .zip(signers.iter())
+        .filter(|(_, signed)| **signed)
+        .fold(U256::zero(), |total, (entry, _)| total + entry.stake());
+    if signed < threshold {
+        return Err(CertError::InsufficientStake { signed, threshold });
+    }
+
+    Ok(signed)
+}
+
+/// The keys of the `signers` in `stake_table`, in stake table order
+#[must_use]
+pub fn signer_keys<E>(stake_table: &[E], signers: &BitSlice) -> Vec<E::Key>
+where
+    E: StakeEntry,
+    E::Key: Clone,
+{
+    stake_table
+        .iter()
+        .zip(signers.iter())
+        .filter(|(_, signed)| **signed)
+        .map(|(entry, _)| entry.key().clone())
+        .collect()
+}
+
+/// Verify a certificate: `signature` is the aggregate of the signatures on `message` of the
+/// `signers` in `stake_table`, who hold at least `threshold` of stake. Returns their stake.
+///
+/// # Errors
+/// If the signers do not cover the stake table, hold too little stake, or the signature does not
+/// verify
+pub fn verify_certificate<A, E, M>(
+    agg_sig_pp: &A::PublicParameter,
+    stake_table: &[E],
+    threshold: U256,
+    message: M,
+    signature: &A::Signature,
+    signers: &BitSlice,
+) -> Result<U256, CertError>
+where
+    A: AggregateableSignatureSchemes,
+    E: StakeEntry<Key = A::VerificationKey>,
+    M: AsRef<[A::MessageUnit]>,
+{
+    let signed = signed_stake(stake_table, signers, threshold)?;
+    let keys = signer_keys(stake_table, signers);
+    A::multi_sig_verify(agg_sig_pp, &keys, message, signature).map_err(CertError::Signature)?;
+
+    Ok(signed)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use bitvec::{bitvec, slice::BitSlice};
+    use jf_signature::{
+        bls_over_bn254::{BLSOverBN254CurveSignatureScheme as Bls, KeyPair, VerKey},
+        AggregateableSignatureSchemes, SignatureScheme,
+    };
+    use primitive_types::U256;
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+    use super::{verify_certificate, CertError};
+
+    const MESSAGE: &[u8] = b"certified data commitment";
+
+    /// A stake table of `stakes`, with the key pairs of its members
+    fn stake_table(stakes: &[u64]) -> (Vec<KeyPair>, Vec<(VerKey, U256)>) {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let key_pairs: Vec<_> = stakes.iter().map(|_| KeyPair::generate(&mut rng)).collect();
+        let entries = key_pairs
+            .iter()
+            .zip(stakes)
+            .map(|(key_pair, stake)| (key_pair.ver_key(), U256::from(*stake)))
+            .collect();
+        (key_pairs, entries)
+    }
+
+    /// The aggregate signature on `message` of the `key_pairs` with `signers` set
+    fn sign(
+        key_pairs: &[KeyPair],
+        signers: &[bool],
+        message: &[u8],
+    ) -> <Bls as SignatureScheme>::Signature {
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        let (keys, signatures): (Vec<_>, Vec<_>) = key_pairs
+            .iter()
+            .zip(signers)
+            .filter(|(_, signed)| **signed)
+            .map(|(key_pair, _)| {
+                let signature = Bls::sign(&(), key_pair.sign_key_ref(), message, &mut rng).unwrap();
+                (key_pair.ver_key(), signature)
+            })
+            .collect();
+        Bls::aggregate(&(), &keys, &signatures).unwrap()
+    }
+
+    #[test]
+    fn certificate_with_threshold_of_stake_verifies() {
+        let (key_pairs, entries) = stake_table(&[10, 20, 30, 40]);
+        let signers = bitvec![0, 1, 1, 1];
+        let signature = sign(&key_pairs, &[false, true, true, true], MESSAGE);
+
+        let signed = verify_certificate::<Bls, _, _>(
+            &(),
+            &entries,
+            U256::from(67u64),
+            MESSAGE,
+            &signature,
+            &signers,
+        )
+        .unwrap();
+        assert_eq!(signed, U256::from(90u64));
+    }
+
+    #[test]
+    fn invalid_certificates_are_rejected() {
+        let (key_pairs, entries) = stake_table(&[10, 20, 30, 40]);
+        let signature = sign(&key_pairs, &[true, false, false, true], MESSAGE);
+        let verify = |threshold: u64, message: &[u8], signers: &BitSlice| {
+            verify_certificate::<Bls, _, _>(
+                &(),
+                &entries,
+                U256::from(threshold),
+                message,
+                &signature,
+                signers,
+            )
+        };
+
+        // Too little stake
+        assert!(matches!(
+            verify(67, MESSAGE, &bitvec![1, 0, 0, 1]),
+            Err(CertError::InsufficientStake { signed, .. }) if signed == U256::from(50u64)
+        ));
+        // Signers which do not cover the stake table
+        assert!(matches!(
+            verify(50, MESSAGE, &bitvec![1, 0, 0]),
+            Err(CertError::SignersLength {
+                signers: 3,
+                stake_table: 4
+            })
+        ));
+        // Signers other than those who signed
+        assert!(matches!(
+            verify(50, MESSAGE, &bitvec![0, 1, 1, 0]),
+            Err(CertError::Signature(_))
+        ));
+        // A different message
+        assert!(matches!(
+            verify(50, b"other commitment", &bitvec![1, 0, 0, 1]),
+            Err(CertError::Signature(_))
+        ));
+
+        assert!(verify(50, MESSAGE, &bitvec![1, 0, 0, 1]).is_ok());
+    }
+}
@@ -13,7 +13,12 @@ use std::{
 use async_trait::async_trait;
 use committable::{Commitment, Committable, RawCommitmentBuilder};
 use hotshot_types::{
+    chain_config::ChainConfig,
+    clock::Clock,
     data::{BlockError, Leaf2},
+    fee_market::expected_base_fee,
+    header_extension::HeaderExtension,
+    stake_table::StakeTableCommitment,
     traits::{
         block_contents::{BlockHeader, BuilderFee, EncodeBytes, TestableBlock, Transaction},
         node_implementation::NodeType,
@@ -26,7 +31,6 @@ use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use thiserror::Error;
-use time::OffsetDateTime;
 use vbs::version::Version;
 
 use crate::{
@@ -268,6 +272,21 @@ pub struct TestBlockHeader {
     pub timestamp: u64,
     /// random
     pub random: u64,
+    /// Base fee, if the chain has a fee market
+    #[serde(default)]
+    pub base_fee: Option<u64>,
+    /// Total fee claimed by the builder(s) of the block
+    #[serde(default)]
+    pub builder_fee: Option<u64>,
+    /// Commitment to the chain configuration of the leader which built the block
+    #[serde(default)]
+    pub chain_config: Option<Commitment<ChainConfig>>,
+    /// Commitment to the stake tables of the block's epoch and the next
+    #[serde(default)]
+    pub stake_tables: Option<StakeTableCommitment>,
+    /// Application-defined header fields
+    #[serde(default)]
+    pub extension: Option<HeaderExtension>,
 }
 
 impl TestBlockHeader {
@@ -276,10 +295,11 @@ impl TestBlockHeader {
         payload_commitment: VidCommitment,
         builder_commitment: BuilderCommitment,
         metadata: TestMetadata,
+        clock: &Clock,
     ) -> Self {
         let parent = parent_leaf.block_header();
 
-        let mut timestamp = OffsetDateTime::now_utc().unix_timestamp() as u64;
+        let mut timestamp = u64::try_from(clock.unix_timestamp()).unwrap_or_default();
         if timestamp < parent.timestamp {
             // Prevent decreasing timestamps.
             timestamp = parent.timestamp;
@@ -294,6 +314,11 @@ impl TestBlockHeader {
             metadata,
             timestamp,
             random,
+            base_fee: None,
+            builder_fee: None,
+            chain_config: None,
+            stake_tables: None,
+            extension: None,
         }
     }
 }
@@ -316,17 +341,23 @@ impl<
         payload_commitment: VidCommitment,
         builder_commitment: BuilderCommitment,
         metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
-        _builder_fee: BuilderFee<TYPES>,
+        builder_fee: BuilderFee<TYPES>,
         _vid_common: VidCommon,
         _version: Version,
     ) -> Result<Self, Self::Error> {
         Self::run_delay_settings_from_config(&instance_state.delay_config).await;
-        Ok(Self::new(
-            parent_leaf,
-            payload_commitment,
-            builder_commitment,
-            metadata,
-        ))
+        Ok(Self {
+            base_fee: expected_base_fee::<TYPES>(instance_state, parent_leaf.block_header()),
+            builder_fee: Some(builder_fee.fee_amount),
+            chain_config: Some(ChainConfig::new::<TYPES>(instance_state).commit()),
+            ..Self::new(
+                parent_leaf,
+                payload_commitment,
+                builder_commitment,
+                metadata,
+                &instance_state.clock,
+            )
+        })
     }
 
     async fn new_marketplace(
@@ -336,19 +367,25 @@ impl<
         payload_commitment: VidCommitment,
         builder_commitment: BuilderCommitment,
         metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
-        _builder_fee: Vec<BuilderFee<TYPES>>,
+        builder_fee: Vec<BuilderFee<TYPES>>,
         _view_number: u64,
         _vid_common: VidCommon,
         _auction_results: Option<TYPES::AuctionResult>,
         _version: Version,
     ) -> Result<Self, Self::Error> {
         Self::run_delay_settings_from_config(&instance_state.delay_config).await;
-        Ok(Self::new(
-            parent_leaf,
-            payload_commitment,
-            builder_commitment,
-            metadata,
-        ))
+        Ok(Self {
+            base_fee: expected_base_fee::<TYPES>(instance_state, parent_leaf.block_header()),
+            builder_fee: Some(builder_fee.iter().map(|fee| fee.fee_amount).sum()),
+            chain_config: Some(ChainConfig::new::<TYPES>(instance_state).commit()),
+            ..Self::new(
+                parent_leaf,
+                payload_commitment,
+                builder_commitment,
+                metadata,
+                &instance_state.clock,
+            )
+        })
     }
 
     fn genesis(
@@ -368,6 +405,11 @@ impl<
             metadata,
             timestamp: 0,
             random: 0,
+            base_fee: None,
+            builder_fee: None,
+            chain_config: None,
+            stake_tables: None,
+            extension: None,
         }
     }
 
@@ -390,11 +432,49 @@ impl<
     fn get_auction_results(&self) -> Option<TYPES::AuctionResult> {
         Some(TYPES::AuctionResult { urls: vec![] })
     }
+
+    fn builder_fee_amount(&self) -> Option<u64> {
+        self.builder_fee
+    }
+
+    fn base_fee(&self) -> Option<u64> {
+        self.base_fee
+    }
+
+    fn block_usage(&self) -> u64 {
+        self.metadata.num_transactions
+    }
+
+    fn chain_config_commitment(&self) -> Option<Commitment<ChainConfig>> {
+        self.chain_config
+    }
+
+    fn stake_table_commitment(&self) -> Option<StakeTableCommitment> {
+        self.stake_tables
+    }
+
+    fn with_stake_table_commitment(self, commitment: StakeTableCommitment) -> Self {
+        Self {
+            stake_tables: Some(commitment),
+            ..self
+        }
+    }
+
+    fn header_extension(&self) -> Option<&HeaderExtension> {
+        self.extension.as_ref()
+    }
+
+    fn with_header_extension(self, extension: HeaderExtension) -> Self {
+        Self {
+            extension: Some(extension),
+            ..self
+        }
+    }
 }
 
 impl Committable for TestBlockHeader {
     fn commit(&self) -> Commitment<Self> {
-        RawCommitmentBuilder::new("Header Comm")
+        let builder = RawCommitmentBuilder::new("Header Comm")
             .u64_field(
                 "block number",
                 <TestBlockHeader as BlockHeader<TestTypes>>::block_number(self),
@@ -404,8 +484,31 @@ impl Committable for TestBlockHeader {
                 <TestBlockHeader as BlockHeader<TestTypes>>::payload_commitment(self)
                     .as_ref()
                     .as_ref(),
-            )
-            .finalize()
+            );
+
+        // Only commit to the fees, chain config, stake tables and extension if there are any, so
+        // headers without them keep their commitments
+        let builder = match self.base_fee {
+            Some(base_fee) => builder.u64_field("base fee", base_fee),
+            None => builder,
+        };
+        let builder = match self.builder_fee {
+            Some(builder_fee) => builder.u64_field("builder fee", builder_fee),
+            None => builder,
+        };
+        let builder = match self.chain_config {
+            Some(chain_config) => builder.field("chain config", chain_config),
+            None => builder,
+        };
+        let builder = match self.stake_tables {
+            Some(stake_tables) => builder.field("stake tables", stake_tables.commit()),
+            None => builder,
+        };
+        match &self.extension {
+            Some(extension) => builder.field("extension", extension.commit()),
+            None => builder,
+        }
+        .finalize()
     }
 
     fn tag() -> String {
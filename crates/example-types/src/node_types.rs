@@ -13,7 +13,7 @@ use hotshot::traits::{
     election::{
         helpers::QuorumFilterConfig, randomized_committee::RandomizedCommittee,
         randomized_committee_members::RandomizedCommitteeMembers,
-        static_committee::StaticCommittee,
+        sampled_committee::SampledCommittee, static_committee::StaticCommittee,
         static_committee_leader_two_views::StaticCommitteeLeaderForTwoViews,
         two_static_committees::TwoStaticCommittees,
     },
@@ -23,7 +23,10 @@ use hotshot::traits::{
 use hotshot_types::{
     data::{EpochNumber, ViewNumber},
     signature_key::{BLSPubKey, BuilderKey},
-    traits::node_implementation::{NodeType, Versions},
+    traits::{
+        commitment_scheme::Keccak256Scheme,
+        node_implementation::{NodeType, Versions},
+    },
 };
 use serde::{Deserialize, Serialize};
 use vbs::version::StaticVersion;
@@ -65,6 +68,7 @@ impl NodeType for TestTypes {
     type InstanceState = TestInstanceState;
     type Membership = StaticCommittee<TestTypes>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = Keccak256Scheme;
 }
 
 #[derive(
@@ -97,6 +101,7 @@ impl NodeType for TestTypesRandomizedLeader {
     type InstanceState = TestInstanceState;
     type Membership = RandomizedCommittee<TestTypesRandomizedLeader>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = Keccak256Scheme;
 }
 
 #[derive(
@@ -133,6 +138,7 @@ impl<CONFIG: QuorumFilterConfig> NodeType for TestTypesRandomizedCommitteeMember
     type Membership =
         RandomizedCommitteeMembers<TestTypesRandomizedCommitteeMembers<CONFIG>, CONFIG>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = Keccak256Scheme;
 }
 
 #[derive(
@@ -165,6 +171,7 @@ impl NodeType for TestConsecutiveLeaderTypes {
     type InstanceState = TestInstanceState;
     type Membership = StaticCommitteeLeaderForTwoViews<TestConsecutiveLeaderTypes>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = Keccak256Scheme;
 }
 
 #[derive(
@@ -197,6 +204,40 @@ impl NodeType for TestTwoStakeTablesTypes {
     type InstanceState = TestInstanceState;
     type Membership = TwoStaticCommittees<TestTwoStakeTablesTypes>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = Keccak256Scheme;
+}
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+/// filler struct to implement node type and allow us
+/// to select our traits
+pub struct TestTypesSampledCommittee;
+impl NodeType for TestTypesSampledCommittee {
+    const EPOCH_HEIGHT: u64 = 10;
+
+    type AuctionResult = TestAuctionResult;
+    type View = ViewNumber;
+    type Epoch = EpochNumber;
+    type BlockHeader = TestBlockHeader;
+    type BlockPayload = TestBlockPayload;
+    type SignatureKey = BLSPubKey;
+    type Transaction = TestTransaction;
+    type ValidatedState = TestValidatedState;
+    type InstanceState = TestInstanceState;
+    type Membership = SampledCommittee<TestTypesSampledCommittee, 7>;
+    type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = Keccak256Scheme;
 }
 
 /// The Push CDN implementation
@@ -260,6 +301,12 @@ impl Versions for TestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type CompactVotes = StaticVersion<0, 5>;
+
+    type CommitmentScheme = StaticVersion<0, 6>;
+
+    type ChainId = StaticVersion<0, 1>;
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -276,6 +323,34 @@ impl Versions for MarketplaceUpgradeTestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type CompactVotes = StaticVersion<0, 5>;
+
+    type CommitmentScheme = StaticVersion<0, 6>;
+
+    type ChainId = StaticVersion<0, 1>;
+}
+
+#[derive(Clone, Debug, Copy)]
+pub struct EpochsUpgradeTestVersions {}
+
+impl Versions for EpochsUpgradeTestVersions {
+    type Base = StaticVersion<0, 3>;
+    type Upgrade = StaticVersion<0, 4>;
+    const UPGRADE_HASH: [u8; 32] = [
+        1, 0, 1, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
+        0, 0,
+    ];
+
+    type Marketplace = StaticVersion<0, 3>;
+
+    type Epochs = StaticVersion<0, 4>;
+
+    type CompactVotes = StaticVersion<0, 5>;
+
+    type CommitmentScheme = StaticVersion<0, 6>;
+
+    type ChainId = StaticVersion<0, 1>;
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -292,6 +367,12 @@ impl Versions for MarketplaceTestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type CompactVotes = StaticVersion<0, 5>;
+
+    type CommitmentScheme = StaticVersion<0, 6>;
+
+    type ChainId = StaticVersion<0, 1>;
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -308,6 +389,56 @@ impl Versions for EpochsTestVersions {
     type Marketplace = StaticVersion<0, 3>;
 
     type Epochs = StaticVersion<0, 4>;
+
+    type CompactVotes = StaticVersion<0, 5>;
+
+    type CommitmentScheme = StaticVersion<0, 6>;
+
+    type ChainId = StaticVersion<0, 1>;
+}
+
+#[derive(Clone, Debug, Copy)]
+pub struct CompactVotesTestVersions {}
+
+impl Versions for CompactVotesTestVersions {
+    type Base = StaticVersion<0, 4>;
+    type Upgrade = StaticVersion<0, 4>;
+    const UPGRADE_HASH: [u8; 32] = [
+        1, 0, 1, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
+        0, 0,
+    ];
+
+    type Marketplace = StaticVersion<0, 3>;
+
+    type Epochs = StaticVersion<0, 4>;
+
+    type CompactVotes = StaticVersion<0, 4>;
+
+    type CommitmentScheme = StaticVersion<0, 6>;
+
+    type ChainId = StaticVersion<0, 1>;
+}
+
+#[derive(Clone, Debug, Copy)]
+pub struct ChainIdUpgradeTestVersions {}
+
+impl Versions for ChainIdUpgradeTestVersions {
+    type Base = StaticVersion<0, 1>;
+    type Upgrade = StaticVersion<0, 2>;
+    const UPGRADE_HASH: [u8; 32] = [
+        1, 0, 1, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
+        0, 0,
+    ];
+
+    type Marketplace = StaticVersion<0, 3>;
+
+    type Epochs = StaticVersion<0, 4>;
+
+    type CompactVotes = StaticVersion<0, 5>;
+
+    type CommitmentScheme = StaticVersion<0, 6>;
+
+    type ChainId = StaticVersion<0, 2>;
 }
 
 #[cfg(test)]
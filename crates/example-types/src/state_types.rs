@@ -5,12 +5,14 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! Implementations for examples and tests only
-use std::fmt::Debug;
+use std::{collections::HashSet, fmt::Debug};
 
 use async_trait::async_trait;
 use committable::{Commitment, Committable};
 use hotshot_types::{
+    clock::Clock,
     data::{fake_commitment, BlockError, Leaf2, ViewNumber},
+    fee_market::BaseFeeParams,
     traits::{
         block_contents::BlockHeader,
         node_implementation::NodeType,
@@ -33,13 +35,33 @@ use crate::{
 #[derive(Clone, Debug, Default)]
 pub struct TestInstanceState {
     pub delay_config: DelayConfig,
+    /// Base fee parameters, if the test chain has a fee market
+    pub base_fee_params: Option<BaseFeeParams>,
+    /// Wall clock of the node, skewed to simulate clock drift
+    pub clock: Clock,
+    /// Heights of the blocks which fail to apply to the validated state, to simulate invalid
+    /// state transitions
+    pub invalid_block_heights: HashSet<u64>,
 }
 
-impl InstanceState for TestInstanceState {}
+impl InstanceState for TestInstanceState {
+    fn base_fee_params(&self) -> Option<BaseFeeParams> {
+        self.base_fee_params
+    }
+
+    fn clock(&self) -> Clock {
+        self.clock
+    }
+}
 
 impl TestInstanceState {
     pub fn new(delay_config: DelayConfig) -> Self {
-        TestInstanceState { delay_config }
+        TestInstanceState {
+            delay_config,
+            base_fee_params: None,
+            clock: Clock::default(),
+            invalid_block_heights: HashSet::new(),
+        }
     }
 }
 
@@ -104,12 +126,21 @@ impl<TYPES: NodeType> ValidatedState<TYPES> for TestValidatedState {
         &self,
         instance: &Self::Instance,
         _parent_leaf: &Leaf2<TYPES>,
-        _proposed_header: &TYPES::BlockHeader,
+        proposed_header: &TYPES::BlockHeader,
         _vid_common: VidCommon,
         _version: Version,
         _view_number: u64,
     ) -> Result<(Self, Self::Delta), Self::Error> {
         Self::run_delay_settings_from_config(&instance.delay_config).await;
+        if instance
+            .invalid_block_heights
+            .contains(&proposed_header.block_number())
+        {
+            return Err(BlockError::InvalidBlockHeader(format!(
+                "block {} is configured to be invalid",
+                proposed_header.block_number()
+            )));
+        }
         Ok((
             TestValidatedState {
                 block_height: self.block_height + 1,
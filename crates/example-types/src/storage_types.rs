@@ -5,8 +5,12 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    collections::{BTreeMap, HashMap},
-    sync::Arc,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use anyhow::{bail, Result};
@@ -16,10 +20,11 @@ use hotshot_types::{
     consensus::CommitmentMap,
     data::{
         DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare,
-        VidDisperseShare2,
+        VidDisperseShare2, ViewChangeEvidence, ViewSyncCertificate,
     },
     event::HotShotAction,
-    message::Proposal,
+    governance::ParameterChange,
+    message::{OutboundMessage, Proposal},
     simple_certificate::{NextEpochQuorumCertificate2, QuorumCertificate2, UpgradeCertificate},
     traits::{
         node_implementation::{ConsensusTime, NodeType},
@@ -30,6 +35,7 @@ use hotshot_types::{
     vote::HasViewNumber,
 };
 use jf_vid::VidScheme;
+use tokio::time::sleep;
 
 use crate::testable_delay::{DelayConfig, SupportedTraitTypesForAsyncDelay, TestableDelay};
 
@@ -50,6 +56,15 @@ pub struct TestStorageState<TYPES: NodeType> {
     da2s: HashMap<TYPES::View, Proposal<TYPES, DaProposal2<TYPES>>>,
     proposals: BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal<TYPES>>>,
     proposals2: BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>,
+    view_change_evidence: BTreeMap<TYPES::View, ViewChangeEvidence<TYPES>>,
+    outbound_messages: BTreeMap<TYPES::View, Vec<OutboundMessage<TYPES>>>,
+    view_sync_certificate: Option<ViewSyncCertificate<TYPES>>,
+    execution_cursor: Option<u64>,
+    decided_leaves: BTreeMap<u64, Leaf2<TYPES>>,
+    parameter_changes: Vec<(TYPES::View, ParameterChange)>,
+    sampling_beacons: BTreeMap<TYPES::Epoch, [u8; 32]>,
+    invalid_state_transitions: BTreeMap<TYPES::View, (Leaf2<TYPES>, String)>,
+    migrations: BTreeSet<u32>,
     high_qc: Option<hotshot_types::simple_certificate::QuorumCertificate<TYPES>>,
     high_qc2: Option<hotshot_types::simple_certificate::QuorumCertificate2<TYPES>>,
     next_epoch_high_qc2:
@@ -67,6 +82,15 @@ impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
             da2s: HashMap::new(),
             proposals: BTreeMap::new(),
             proposals2: BTreeMap::new(),
+            view_change_evidence: BTreeMap::new(),
+            outbound_messages: BTreeMap::new(),
+            view_sync_certificate: None,
+            execution_cursor: None,
+            decided_leaves: BTreeMap::new(),
+            parameter_changes: Vec::new(),
+            sampling_beacons: BTreeMap::new(),
+            invalid_state_transitions: BTreeMap::new(),
+            migrations: BTreeSet::new(),
             high_qc: None,
             next_epoch_high_qc2: None,
             high_qc2: None,
@@ -76,11 +100,169 @@ impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
     }
 }
 
+/// The kinds of writes to a [`TestStorage`], to target faults at
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StorageWrite {
+    /// `append_vid` and `append_vid2`
+    Vid,
+    /// `append_da` and `append_da2`
+    Da,
+    /// `append_proposal` and `append_proposal2`
+    Proposal,
+    /// `append_view_change_evidence` and `remove_view_change_evidence`
+    ViewChangeEvidence,
+    /// `append_outbound_message` and `remove_outbound_message`
+    OutboundMessage,
+    /// `update_view_sync_certificate`
+    ViewSyncCertificate,
+    /// `update_execution_cursor`
+    ExecutionCursor,
+    /// `append_decided_leaves`
+    DecidedLeaves,
+    /// `append_parameter_change`
+    ParameterChange,
+    /// `append_sampling_beacon`
+    SamplingBeacon,
+    /// `record_invalid_state_transition`
+    InvalidStateTransition,
+    /// `record_action`
+    Action,
+    /// `update_high_qc`, `update_high_qc2` and `update_next_epoch_high_qc2`
+    HighQc,
+    /// `update_undecided_state` and `update_undecided_state2`
+    UndecidedState,
+    /// `update_decided_upgrade_certificate`
+    UpgradeCertificate,
+    /// `record_migration`
+    Migration,
+}
+
+/// Faults injected into the writes of a [`TestStorage`], to test how consensus copes with
+/// unreliable storage.
+///
+/// Writes are counted from 1, and only the targeted writes count. Clones share their counters, so
+/// the faults stay in step across every clone of the storage.
+#[derive(Clone, Debug, Default)]
+pub struct StorageFaults {
+    /// writes the faults apply to, all of them if empty
+    targets: HashSet<StorageWrite>,
+    /// fail every write whose number is a multiple of this
+    fail_every_nth_write: Option<u64>,
+    /// time every write takes before it is applied
+    write_latency: Duration,
+    /// number of the write which crashes the storage
+    crash_at_write: Option<u64>,
+    /// number of targeted writes so far
+    num_writes: Arc<AtomicU64>,
+    /// whether the storage has crashed
+    crashed: Arc<AtomicBool>,
+}
+
+impl StorageFaults {
+    /// Only inject faults into writes of the given kinds
+    #[must_use]
+    pub fn targeting(mut self, targets: impl IntoIterator<Item = StorageWrite>) -> Self {
+        self.targets = targets.into_iter().collect();
+        self
+    }
+
+    /// Fail every `n`th write without applying it
+    #[must_use]
+    pub fn fail_every_nth_write(mut self, n: u64) -> Self {
+        self.fail_every_nth_write = Some(n);
+        self
+    }
+
+    /// Delay every write by `latency`
+    #[must_use]
+    pub fn with_write_latency(mut self, latency: Duration) -> Self {
+        self.write_latency = latency;
+        self
+    }
+
+    /// Crash in the middle of the `n`th write: the write is applied but reported as failed, and
+    /// every operation after it fails
+    #[must_use]
+    pub fn crash_at_write(mut self, n: u64) -> Self {
+        self.crash_at_write = Some(n);
+        self
+    }
+
+    /// Whether the storage has crashed
+    #[must_use]
+    pub fn crashed(&self) -> bool {
+        self.crashed.load(Ordering::SeqCst)
+    }
+
+    /// Fail if the storage has crashed
+    ///
+    /// # Errors
+    /// If the storage has crashed
+    pub fn ensure_running(&self) -> Result<()> {
+        if self.crashed() {
+            bail!("Storage has crashed");
+        }
+
+        Ok(())
+    }
+
+    /// Inject the faults into a write of kind `write`, before the write is applied
+    ///
+    /// # Errors
+    /// If the write is to fail without being applied
+    pub async fn begin_write(&self, write: StorageWrite) -> Result<PendingWrite> {
+        self.ensure_running()?;
+        if !self.targets.is_empty() && !self.targets.contains(&write) {
+            return Ok(PendingWrite { torn: false });
+        }
+
+        let n = self.num_writes.fetch_add(1, Ordering::SeqCst) + 1;
+        if self
+            .fail_every_nth_write
+            .is_some_and(|every| every > 0 && n % every == 0)
+        {
+            bail!("Injected failure of write {n} ({write:?})");
+        }
+        if !self.write_latency.is_zero() {
+            sleep(self.write_latency).await;
+        }
+        let torn = self.crash_at_write == Some(n);
+        if torn {
+            self.crashed.store(true, Ordering::SeqCst);
+        }
+
+        Ok(PendingWrite { torn })
+    }
+}
+
+/// A write which is being applied, see [`StorageFaults::begin_write`]
+#[must_use]
+pub struct PendingWrite {
+    /// whether the storage crashed during the write
+    torn: bool,
+}
+
+impl PendingWrite {
+    /// Report the outcome of the write once it has been applied
+    ///
+    /// # Errors
+    /// If the storage crashed during the write
+    pub fn finish(self) -> Result<()> {
+        if self.torn {
+            bail!("Storage crashed during the write");
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TestStorage<TYPES: NodeType> {
     inner: Arc<RwLock<TestStorageState<TYPES>>>,
     /// `should_return_err` is a testing utility to validate negative cases.
     pub should_return_err: bool,
+    /// Faults injected into writes, to validate negative cases.
+    pub faults: StorageFaults,
     pub delay_config: DelayConfig,
     pub decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
 }
@@ -90,6 +272,7 @@ impl<TYPES: NodeType> Default for TestStorage<TYPES> {
         Self {
             inner: Arc::new(RwLock::new(TestStorageState::default())),
             should_return_err: false,
+            faults: StorageFaults::default(),
             delay_config: DelayConfig::default(),
             decided_upgrade_certificate: Arc::new(RwLock::new(None)),
         }
@@ -121,6 +304,27 @@ impl<TYPES: NodeType> TestStorage<TYPES> {
     pub async fn decided_upgrade_certificate(&self) -> Option<UpgradeCertificate<TYPES>> {
         self.decided_upgrade_certificate.read().await.clone()
     }
+    pub async fn outbound_messages_cloned(&self) -> Vec<OutboundMessage<TYPES>> {
+        self.inner
+            .read()
+            .await
+            .outbound_messages
+            .values()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+    pub async fn view_sync_certificate_cloned(&self) -> Option<ViewSyncCertificate<TYPES>> {
+        self.inner.read().await.view_sync_certificate.clone()
+    }
+    pub async fn execution_cursor(&self) -> Option<u64> {
+        self.inner.read().await.execution_cursor
+    }
+    pub async fn invalid_state_transitions_cloned(
+        &self,
+    ) -> BTreeMap<TYPES::View, (Leaf2<TYPES>, String)> {
+        self.inner.read().await.invalid_state_transitions.clone()
+    }
     pub async fn last_actioned_view(&self) -> TYPES::View {
         self.inner.read().await.action
     }
@@ -135,6 +339,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to append VID proposal to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::Vid).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         inner
@@ -142,7 +347,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
             .entry(proposal.data.view_number)
             .or_default()
             .insert(proposal.data.recipient_key.clone(), proposal.clone());
-        Ok(())
+        write.finish()
     }
 
     async fn append_vid2(
@@ -152,6 +357,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to append VID proposal to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::Vid).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         inner
@@ -159,7 +365,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
             .entry(proposal.data.view_number)
             .or_default()
             .insert(proposal.data.recipient_key.clone(), proposal.clone());
-        Ok(())
+        write.finish()
     }
 
     async fn append_da(
@@ -170,12 +376,13 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to append VID proposal to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::Da).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         inner
             .das
             .insert(proposal.data.view_number, proposal.clone());
-        Ok(())
+        write.finish()
     }
     async fn append_da2(
         &self,
@@ -185,12 +392,13 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to append VID proposal to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::Da).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         inner
             .da2s
             .insert(proposal.data.view_number, proposal.clone());
-        Ok(())
+        write.finish()
     }
     async fn append_proposal(
         &self,
@@ -199,12 +407,13 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to append VID proposal to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::Proposal).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         inner
             .proposals
             .insert(proposal.data.view_number, proposal.clone());
-        Ok(())
+        write.finish()
     }
     async fn append_proposal2(
         &self,
@@ -213,12 +422,287 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to append VID proposal to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::Proposal).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         inner
             .proposals2
             .insert(proposal.data.view_number, proposal.clone());
-        Ok(())
+        write.finish()
+    }
+
+    async fn append_view_change_evidence(
+        &self,
+        view: TYPES::View,
+        evidence: &ViewChangeEvidence<TYPES>,
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to append view change evidence to storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::ViewChangeEvidence)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        inner.view_change_evidence.insert(view, evidence.clone());
+        write.finish()
+    }
+
+    async fn load_view_change_evidence(
+        &self,
+        start: TYPES::View,
+        end: TYPES::View,
+    ) -> Result<BTreeMap<TYPES::View, ViewChangeEvidence<TYPES>>> {
+        if self.should_return_err {
+            bail!("Failed to load view change evidence from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        if start > end {
+            return Ok(BTreeMap::new());
+        }
+        let inner = self.inner.read().await;
+        Ok(inner
+            .view_change_evidence
+            .range(start..=end)
+            .map(|(view, evidence)| (*view, evidence.clone()))
+            .collect())
+    }
+
+    async fn remove_view_change_evidence(&self, view: TYPES::View) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to remove view change evidence from storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::ViewChangeEvidence)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        inner.view_change_evidence.remove(&view);
+        write.finish()
+    }
+
+    async fn append_outbound_message(&self, message: &OutboundMessage<TYPES>) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to append outbound message to storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::OutboundMessage)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        inner
+            .outbound_messages
+            .entry(message.view_number())
+            .or_default()
+            .push(message.clone());
+        write.finish()
+    }
+
+    async fn remove_outbound_message(&self, message: &OutboundMessage<TYPES>) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to remove outbound message from storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::OutboundMessage)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        let view = message.view_number();
+        if let Some(messages) = inner.outbound_messages.get_mut(&view) {
+            messages.retain(|pending| pending.message != message.message);
+            if messages.is_empty() {
+                inner.outbound_messages.remove(&view);
+            }
+        }
+        write.finish()
+    }
+
+    async fn load_outbound_messages(
+        &self,
+        start: TYPES::View,
+    ) -> Result<Vec<OutboundMessage<TYPES>>> {
+        if self.should_return_err {
+            bail!("Failed to load outbound messages from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        inner.outbound_messages = inner.outbound_messages.split_off(&start);
+        Ok(inner
+            .outbound_messages
+            .values()
+            .flatten()
+            .cloned()
+            .collect())
+    }
+
+    async fn update_view_sync_certificate(
+        &self,
+        certificate: &ViewSyncCertificate<TYPES>,
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to update view sync certificate in storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::ViewSyncCertificate)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        if inner
+            .view_sync_certificate
+            .as_ref()
+            .map_or(true, |latest| certificate.supersedes(latest))
+        {
+            inner.view_sync_certificate = Some(certificate.clone());
+        }
+        write.finish()
+    }
+
+    async fn load_view_sync_certificate(&self) -> Result<Option<ViewSyncCertificate<TYPES>>> {
+        if self.should_return_err {
+            bail!("Failed to load view sync certificate from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        Ok(self.inner.read().await.view_sync_certificate.clone())
+    }
+
+    async fn update_execution_cursor(&self, height: u64) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to update execution cursor in storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::ExecutionCursor)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        self.inner.write().await.execution_cursor = Some(height);
+        write.finish()
+    }
+
+    async fn load_execution_cursor(&self) -> Result<Option<u64>> {
+        if self.should_return_err {
+            bail!("Failed to load execution cursor from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        Ok(self.inner.read().await.execution_cursor)
+    }
+
+    async fn append_decided_leaves(&self, leaves: &[Leaf2<TYPES>]) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to append decided leaves to storage");
+        }
+        let write = self.faults.begin_write(StorageWrite::DecidedLeaves).await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        for leaf in leaves {
+            inner.decided_leaves.insert(leaf.height(), leaf.clone());
+        }
+        write.finish()
+    }
+
+    async fn load_decided_leaves(&self, height: u64) -> Result<Vec<Leaf2<TYPES>>> {
+        if self.should_return_err {
+            bail!("Failed to load decided leaves from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        Ok(self
+            .inner
+            .read()
+            .await
+            .decided_leaves
+            .range(height..)
+            .map(|(_, leaf)| leaf.clone())
+            .collect())
+    }
+
+    async fn append_parameter_change(
+        &self,
+        activation_view: TYPES::View,
+        change: ParameterChange,
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to append parameter change to storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::ParameterChange)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        self.inner
+            .write()
+            .await
+            .parameter_changes
+            .push((activation_view, change));
+        write.finish()
+    }
+
+    async fn load_parameter_changes(&self) -> Result<Vec<(TYPES::View, ParameterChange)>> {
+        if self.should_return_err {
+            bail!("Failed to load parameter changes from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        Ok(self.inner.read().await.parameter_changes.clone())
+    }
+
+    async fn append_sampling_beacon(
+        &self,
+        from_epoch: TYPES::Epoch,
+        beacon: [u8; 32],
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to append sampling beacon to storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::SamplingBeacon)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        self.inner
+            .write()
+            .await
+            .sampling_beacons
+            .insert(from_epoch, beacon);
+        write.finish()
+    }
+
+    async fn load_sampling_beacons(&self) -> Result<BTreeMap<TYPES::Epoch, [u8; 32]>> {
+        if self.should_return_err {
+            bail!("Failed to load sampling beacons from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        Ok(self.inner.read().await.sampling_beacons.clone())
+    }
+
+    async fn record_invalid_state_transition(
+        &self,
+        leaf: &Leaf2<TYPES>,
+        error: &str,
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to record invalid state transition to storage");
+        }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::InvalidStateTransition)
+            .await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        self.inner
+            .write()
+            .await
+            .invalid_state_transitions
+            .insert(leaf.view_number(), (leaf.clone(), error.to_string()));
+        write.finish()
     }
 
     async fn record_action(
@@ -229,12 +713,13 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to append Action to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::Action).await?;
         let mut inner = self.inner.write().await;
         if view > inner.action && matches!(action, HotShotAction::Vote | HotShotAction::Propose) {
             inner.action = view;
         }
         Self::run_delay_settings_from_config(&self.delay_config).await;
-        Ok(())
+        write.finish()
     }
 
     async fn update_high_qc(
@@ -244,6 +729,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::HighQc).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         if let Some(ref current_high_qc) = inner.high_qc {
@@ -253,7 +739,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         } else {
             inner.high_qc = Some(new_high_qc);
         }
-        Ok(())
+        write.finish()
     }
 
     async fn update_high_qc2(
@@ -263,6 +749,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::HighQc).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         if let Some(ref current_high_qc) = inner.high_qc2 {
@@ -272,7 +759,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         } else {
             inner.high_qc2 = Some(new_high_qc);
         }
-        Ok(())
+        write.finish()
     }
     async fn update_next_epoch_high_qc2(
         &self,
@@ -283,6 +770,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to update next epoch high qc to storage");
         }
+        let write = self.faults.begin_write(StorageWrite::HighQc).await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
         let mut inner = self.inner.write().await;
         if let Some(ref current_next_epoch_high_qc) = inner.next_epoch_high_qc2 {
@@ -292,7 +780,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         } else {
             inner.next_epoch_high_qc2 = Some(new_next_epoch_high_qc);
         }
-        Ok(())
+        write.finish()
     }
     async fn update_undecided_state(
         &self,
@@ -302,8 +790,12 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::UndecidedState)
+            .await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
-        Ok(())
+        write.finish()
     }
     async fn update_undecided_state2(
         &self,
@@ -313,16 +805,43 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
+        let write = self
+            .faults
+            .begin_write(StorageWrite::UndecidedState)
+            .await?;
         Self::run_delay_settings_from_config(&self.delay_config).await;
-        Ok(())
+        write.finish()
     }
     async fn update_decided_upgrade_certificate(
         &self,
         decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
     ) -> Result<()> {
+        let write = self
+            .faults
+            .begin_write(StorageWrite::UpgradeCertificate)
+            .await?;
         *self.decided_upgrade_certificate.write().await = decided_upgrade_certificate;
 
-        Ok(())
+        write.finish()
+    }
+
+    async fn load_applied_migrations(&self) -> Result<BTreeSet<u32>> {
+        if self.should_return_err {
+            bail!("Failed to load applied migrations from storage");
+        }
+        self.faults.ensure_running()?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        Ok(self.inner.read().await.migrations.clone())
+    }
+
+    async fn record_migration(&self, version: u32) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to record migration in storage");
+        }
+        let write = self.faults.begin_write(StorageWrite::Migration).await?;
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        self.inner.write().await.migrations.insert(version);
+        write.finish()
     }
 
     async fn migrate_consensus(
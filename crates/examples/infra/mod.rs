@@ -8,9 +8,9 @@
 use std::{
     collections::HashMap,
     fmt::Debug,
-    fs,
-    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     num::NonZeroUsize,
+    path::Path,
     sync::Arc,
     time::Instant,
 };
@@ -49,6 +49,7 @@ use hotshot_testing::block_builder::{
     TestBuilderImplementation,
 };
 use hotshot_types::{
+    config_loader::load_network_config_file,
     consensus::ConsensusMetricsValue,
     data::{Leaf, TestableLeaf},
     event::{Event, EventType},
@@ -62,7 +63,9 @@ use hotshot_types::{
     },
     HotShotConfig, PeerConfig, ValidatorConfig,
 };
-use libp2p_networking::network::{GossipConfig, RequestResponseConfig};
+use libp2p_networking::network::{
+    compression::CompressionConfig, GossipConfig, RequestResponseConfig,
+};
 use rand::{rngs::StdRng, SeedableRng};
 use surf_disco::Url;
 use tracing::{debug, error, info, warn};
@@ -251,9 +254,10 @@ pub fn read_orchestrator_init_config<TYPES: NodeType>() -> (NetworkConfig<TYPES:
     (config, orchestrator_url)
 }
 
-/// Reads a network configuration from a given filepath
+/// Reads a network configuration from a given filepath, overridden by `HOTSHOT_` environment
+/// variables as described in [`hotshot_types::config_loader`]
 /// # Panics
-/// if unable to convert the config file into toml
+/// if unable to load the config file
 /// # Note
 /// This derived config is used for initialization of orchestrator,
 /// therefore `known_nodes_with_stake` will be an initialized
@@ -262,13 +266,11 @@ pub fn read_orchestrator_init_config<TYPES: NodeType>() -> (NetworkConfig<TYPES:
 pub fn load_config_from_file<TYPES: NodeType>(
     config_file: &str,
 ) -> NetworkConfig<TYPES::SignatureKey> {
-    let config_file_as_string: String = fs::read_to_string(config_file)
-        .unwrap_or_else(|_| panic!("Could not read config file located at {config_file}"));
-    let config_toml: NetworkConfigFile<TYPES::SignatureKey> =
-        toml::from_str::<NetworkConfigFile<TYPES::SignatureKey>>(&config_file_as_string)
-            .expect("Unable to convert config file to TOML");
+    let config_file: NetworkConfigFile<TYPES::SignatureKey> =
+        load_network_config_file(Path::new(config_file))
+            .unwrap_or_else(|e| panic!("Could not load config file located at {config_file}: {e}"));
 
-    let mut config: NetworkConfig<TYPES::SignatureKey> = config_toml.into();
+    let mut config: NetworkConfig<TYPES::SignatureKey> = config_file.into();
 
     // initialize it with size for better assignment of peers' config
     config.config.known_nodes_with_stake =
@@ -639,7 +641,8 @@ where
             keypair,
             CdnMetricsValue::default(),
         )
-        .expect("failed to create network");
+        .expect("failed to create network")
+        .with_wire_format(config.wire_format);
 
         // Wait for the network to be ready
         network.wait_for_ready().await;
@@ -711,16 +714,16 @@ where
         // In an example, we can calculate the libp2p bind address as a function
         // of the advertise address.
         let bind_address = if let Some(libp2p_advertise_address) = libp2p_advertise_address {
-            let libp2p_advertise_address: SocketAddrV4 = libp2p_advertise_address
+            let libp2p_advertise_address: SocketAddr = libp2p_advertise_address
                 .parse()
                 .expect("failed to parse advertise address");
 
-            // If we have supplied one, use it
-            SocketAddr::new(
-                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
-                libp2p_advertise_address.port(),
-            )
-            .to_string()
+            // If we have supplied one, use it, listening on every interface of its address family
+            let unspecified = match libp2p_advertise_address {
+                SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            };
+            SocketAddr::new(unspecified, libp2p_advertise_address.port()).to_string()
         } else {
             // If not, index a base port with our node index
             SocketAddr::new(
@@ -739,9 +742,11 @@ where
         let libp2p_network = Libp2pNetwork::from_config(
             config.clone(),
             Arc::clone(membership),
-            GossipConfig::default(),
+            GossipConfig::from(config.config.gossip),
             RequestResponseConfig::default(),
+            CompressionConfig::default(),
             bind_address,
+            Vec::new(),
             public_key,
             private_key,
             Libp2pMetricsValue::default(),
@@ -977,7 +982,7 @@ pub async fn main_entry_point<
     };
     let membership = Arc::new(RwLock::new(<TYPES as NodeType>::Membership::new(
         all_nodes,
-        run_config.config.known_da_nodes.clone(),
+        run_config.config.da_committee(),
     )));
 
     info!("Initializing networking");
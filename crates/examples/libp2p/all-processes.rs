@@ -0,0 +1,92 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Runs every libp2p validator as its own process, coordinated by an orchestrator in this process.
+//!
+//! Unlike `all-libp2p`, the nodes only talk to each other over real sockets, so this catches
+//! serialization and networking bugs which a single process hides. The validators are started
+//! from the `validator-libp2p` example, which must be built next to this one, e.g. with
+//! `just example_processes --config_file ./crates/orchestrator/run-config.toml`.
+//! Exits with an error if any validator fails.
+
+use futures::future::join_all;
+use hotshot::helpers::initialize_logging;
+use hotshot_example_types::state_types::TestTypes;
+use infra::{gen_local_address, BUILDER_BASE_PORT, VALIDATOR_BASE_PORT};
+use tokio::{
+    process::{Child, Command},
+    spawn,
+};
+use tracing::{error, info, instrument};
+
+use crate::infra::{read_orchestrator_init_config, run_orchestrator, OrchestratorArgs};
+
+/// general infra used for this example
+#[path = "../infra/mod.rs"]
+pub mod infra;
+
+/// Name of the validator executable, which is built next to this one
+const VALIDATOR: &str = "validator-libp2p";
+
+#[tokio::main]
+#[instrument]
+async fn main() {
+    // Initialize logging
+    initialize_logging();
+
+    // use configfile args
+    let (config, orchestrator_url) = read_orchestrator_init_config::<TestTypes>();
+
+    // orchestrator
+    spawn(run_orchestrator::<TestTypes>(OrchestratorArgs {
+        url: orchestrator_url.clone(),
+        config: config.clone(),
+    }));
+
+    let validator = std::env::current_exe()
+        .expect("failed to find the current executable")
+        .with_file_name(format!("{VALIDATOR}{}", std::env::consts::EXE_SUFFIX));
+
+    // nodes
+    let mut nodes = Vec::new();
+    for i in 0..config.config.num_nodes_with_stake.into() {
+        let advertise_address = gen_local_address::<VALIDATOR_BASE_PORT>(i);
+        let builder_address = gen_local_address::<BUILDER_BASE_PORT>(i);
+        let node = Command::new(&validator)
+            .arg(orchestrator_url.as_str())
+            .arg(advertise_address.to_string())
+            .arg(builder_address.to_string())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap_or_else(|e| {
+                panic!(
+                    "failed to start validator {i} from {}: {e}",
+                    validator.display()
+                )
+            });
+        nodes.push(node);
+    }
+
+    let statuses = join_all(nodes.iter_mut().map(Child::wait)).await;
+    let mut failed = false;
+    for (i, status) in statuses.into_iter().enumerate() {
+        match status {
+            Ok(status) if status.success() => info!("Validator {i} finished"),
+            Ok(status) => {
+                error!("Validator {i} failed: {status}");
+                failed = true;
+            }
+            Err(e) => {
+                error!("Failed to wait for validator {i}: {e}");
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
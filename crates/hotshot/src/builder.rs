@@ -0,0 +1,604 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A typed builder for [`SystemContext`].
+//!
+//! [`SystemContext::init`] takes ten positional arguments, several of which must agree with each
+//! other. [`SystemContextBuilder`] takes them one at a time by name instead. The keys, config,
+//! network, initializer and storage are required, and [`SystemContextBuilder::build`] and
+//! [`SystemContextBuilder::init`] only exist once all of them have been given. The memberships
+//! are derived from the config unless given, and so is the marketplace config if the node's
+//! auction results provider has a default. Before creating the node, the builder checks that the
+//! keys form a pair and that the config and memberships describe the same network.
+
+use std::{collections::HashSet, marker::PhantomData, sync::Arc};
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    traits::{
+        election::Membership,
+        node_implementation::{NodeImplementation, NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    HotShotConfig,
+};
+
+use crate::{
+    types::SystemContextHandle, HotShotError, HotShotInitializer, MarketplaceConfig, SystemContext,
+};
+
+/// A required component which has not been given to a [`SystemContextBuilder`] yet
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Missing;
+
+/// A node's public key and the private key it pairs with
+pub type Keys<TYPES> = (
+    <TYPES as NodeType>::SignatureKey,
+    <<TYPES as NodeType>::SignatureKey as SignatureKey>::PrivateKey,
+);
+
+/// The memberships given to a [`SystemContextBuilder`], or [`Missing`] to derive them from the
+/// config
+pub trait MembershipsComponent<TYPES: NodeType> {
+    /// The memberships the node is created with
+    fn into_memberships(
+        self,
+        config: &HotShotConfig<TYPES::SignatureKey>,
+    ) -> Arc<RwLock<TYPES::Membership>>;
+}
+
+impl<TYPES: NodeType> MembershipsComponent<TYPES> for Missing {
+    fn into_memberships(
+        self,
+        config: &HotShotConfig<TYPES::SignatureKey>,
+    ) -> Arc<RwLock<TYPES::Membership>> {
+        Arc::new(RwLock::new(TYPES::Membership::new(
+            config.known_nodes_with_stake.clone(),
+            config.da_committee(),
+        )))
+    }
+}
+
+impl<TYPES: NodeType> MembershipsComponent<TYPES> for Arc<RwLock<TYPES::Membership>> {
+    fn into_memberships(
+        self,
+        _config: &HotShotConfig<TYPES::SignatureKey>,
+    ) -> Arc<RwLock<TYPES::Membership>> {
+        self
+    }
+}
+
+/// The marketplace config given to a [`SystemContextBuilder`], or [`Missing`] to use the default
+/// auction results provider and fall back to the first builder in the config
+pub trait MarketplaceComponent<TYPES: NodeType, I: NodeImplementation<TYPES>> {
+    /// The marketplace config the node is created with
+    fn into_marketplace_config(
+        self,
+        config: &HotShotConfig<TYPES::SignatureKey>,
+    ) -> MarketplaceConfig<TYPES, I>;
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> MarketplaceComponent<TYPES, I> for Missing
+where
+    I::AuctionResultsProvider: Default,
+{
+    fn into_marketplace_config(
+        self,
+        config: &HotShotConfig<TYPES::SignatureKey>,
+    ) -> MarketplaceConfig<TYPES, I> {
+        MarketplaceConfig {
+            auction_results_provider: Arc::default(),
+            fallback_builder_url: config.builder_urls.first().clone(),
+        }
+    }
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> MarketplaceComponent<TYPES, I>
+    for MarketplaceConfig<TYPES, I>
+{
+    fn into_marketplace_config(
+        self,
+        _config: &HotShotConfig<TYPES::SignatureKey>,
+    ) -> MarketplaceConfig<TYPES, I> {
+        self
+    }
+}
+
+/// Builds a [`SystemContext`] from named components. Each type parameter after `V` is the type
+/// of a component, or [`Missing`] until it has been given.
+#[must_use]
+pub struct SystemContextBuilder<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    V: Versions,
+    KEYS = Missing,
+    CONFIG = Missing,
+    NETWORK = Missing,
+    INITIALIZER = Missing,
+    STORAGE = Missing,
+    MEMBERSHIPS = Missing,
+    MARKETPLACE = Missing,
+> {
+    /// The node's key pair
+    keys: KEYS,
+    /// The node's config
+    config: CONFIG,
+    /// The network the node sends and receives on
+    network: NETWORK,
+    /// The state the node starts from
+    initializer: INITIALIZER,
+    /// The node's storage
+    storage: STORAGE,
+    /// The node's memberships
+    memberships: MEMBERSHIPS,
+    /// The node's marketplace config
+    marketplace_config: MARKETPLACE,
+    /// The node's id, used in logs
+    node_id: u64,
+    /// Metrics the node reports to
+    metrics: ConsensusMetricsValue,
+    /// The node's types, implementation and versions
+    _pd: PhantomData<fn() -> (TYPES, I, V)>,
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<TYPES, I, V> {
+    /// Start building a [`SystemContext`] from named components
+    pub fn builder() -> SystemContextBuilder<TYPES, I, V> {
+        SystemContextBuilder::new()
+    }
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Default
+    for SystemContextBuilder<TYPES, I, V>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContextBuilder<TYPES, I, V> {
+    /// A builder with no components yet, node id 0 and default metrics
+    pub fn new() -> Self {
+        Self {
+            keys: Missing,
+            config: Missing,
+            network: Missing,
+            initializer: Missing,
+            storage: Missing,
+            memberships: Missing,
+            marketplace_config: Missing,
+            node_id: 0,
+            metrics: ConsensusMetricsValue::default(),
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<
+        TYPES: NodeType,
+        I: NodeImplementation<TYPES>,
+        V: Versions,
+        KEYS,
+        CONFIG,
+        NETWORK,
+        INITIALIZER,
+        STORAGE,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    >
+    SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        KEYS,
+        CONFIG,
+        NETWORK,
+        INITIALIZER,
+        STORAGE,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    >
+{
+    /// Set the node's key pair
+    pub fn keys(
+        self,
+        public_key: TYPES::SignatureKey,
+        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    ) -> SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        Keys<TYPES>,
+        CONFIG,
+        NETWORK,
+        INITIALIZER,
+        STORAGE,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    > {
+        SystemContextBuilder {
+            keys: (public_key, private_key),
+            config: self.config,
+            network: self.network,
+            initializer: self.initializer,
+            storage: self.storage,
+            memberships: self.memberships,
+            marketplace_config: self.marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the node's config
+    pub fn config(
+        self,
+        config: HotShotConfig<TYPES::SignatureKey>,
+    ) -> SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        KEYS,
+        HotShotConfig<TYPES::SignatureKey>,
+        NETWORK,
+        INITIALIZER,
+        STORAGE,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    > {
+        SystemContextBuilder {
+            keys: self.keys,
+            config,
+            network: self.network,
+            initializer: self.initializer,
+            storage: self.storage,
+            memberships: self.memberships,
+            marketplace_config: self.marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the network the node sends and receives on
+    pub fn network(
+        self,
+        network: Arc<I::Network>,
+    ) -> SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        KEYS,
+        CONFIG,
+        Arc<I::Network>,
+        INITIALIZER,
+        STORAGE,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    > {
+        SystemContextBuilder {
+            keys: self.keys,
+            config: self.config,
+            network,
+            initializer: self.initializer,
+            storage: self.storage,
+            memberships: self.memberships,
+            marketplace_config: self.marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the state the node starts from
+    pub fn initializer(
+        self,
+        initializer: HotShotInitializer<TYPES>,
+    ) -> SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        KEYS,
+        CONFIG,
+        NETWORK,
+        HotShotInitializer<TYPES>,
+        STORAGE,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    > {
+        SystemContextBuilder {
+            keys: self.keys,
+            config: self.config,
+            network: self.network,
+            initializer,
+            storage: self.storage,
+            memberships: self.memberships,
+            marketplace_config: self.marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the node's storage
+    pub fn storage(
+        self,
+        storage: I::Storage,
+    ) -> SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        KEYS,
+        CONFIG,
+        NETWORK,
+        INITIALIZER,
+        I::Storage,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    > {
+        SystemContextBuilder {
+            keys: self.keys,
+            config: self.config,
+            network: self.network,
+            initializer: self.initializer,
+            storage,
+            memberships: self.memberships,
+            marketplace_config: self.marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the node's memberships, instead of deriving them from the config
+    pub fn memberships(
+        self,
+        memberships: Arc<RwLock<TYPES::Membership>>,
+    ) -> SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        KEYS,
+        CONFIG,
+        NETWORK,
+        INITIALIZER,
+        STORAGE,
+        Arc<RwLock<TYPES::Membership>>,
+        MARKETPLACE,
+    > {
+        SystemContextBuilder {
+            keys: self.keys,
+            config: self.config,
+            network: self.network,
+            initializer: self.initializer,
+            storage: self.storage,
+            memberships,
+            marketplace_config: self.marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the node's marketplace config, instead of using the default one
+    pub fn marketplace_config(
+        self,
+        marketplace_config: MarketplaceConfig<TYPES, I>,
+    ) -> SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        KEYS,
+        CONFIG,
+        NETWORK,
+        INITIALIZER,
+        STORAGE,
+        MEMBERSHIPS,
+        MarketplaceConfig<TYPES, I>,
+    > {
+        SystemContextBuilder {
+            keys: self.keys,
+            config: self.config,
+            network: self.network,
+            initializer: self.initializer,
+            storage: self.storage,
+            memberships: self.memberships,
+            marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Set the node's id, which is 0 by default
+    pub fn node_id(mut self, node_id: u64) -> Self {
+        self.node_id = node_id;
+        self
+    }
+
+    /// Set the metrics the node reports to, which are discarded by default
+    pub fn metrics(mut self, metrics: ConsensusMetricsValue) -> Self {
+        self.metrics = metrics;
+        self
+    }
+}
+
+/// Every component of a [`SystemContext`], checked for consistency
+struct Components<TYPES: NodeType, I: NodeImplementation<TYPES>> {
+    /// The node's key pair
+    keys: Keys<TYPES>,
+    /// The node's config
+    config: HotShotConfig<TYPES::SignatureKey>,
+    /// The network the node sends and receives on
+    network: Arc<I::Network>,
+    /// The state the node starts from
+    initializer: HotShotInitializer<TYPES>,
+    /// The node's storage
+    storage: I::Storage,
+    /// The node's memberships
+    memberships: Arc<RwLock<TYPES::Membership>>,
+    /// The node's marketplace config
+    marketplace_config: MarketplaceConfig<TYPES, I>,
+    /// The node's id
+    node_id: u64,
+    /// Metrics the node reports to
+    metrics: ConsensusMetricsValue,
+}
+
+impl<
+        TYPES: NodeType,
+        I: NodeImplementation<TYPES>,
+        V: Versions,
+        MEMBERSHIPS: MembershipsComponent<TYPES>,
+        MARKETPLACE: MarketplaceComponent<TYPES, I>,
+    >
+    SystemContextBuilder<
+        TYPES,
+        I,
+        V,
+        Keys<TYPES>,
+        HotShotConfig<TYPES::SignatureKey>,
+        Arc<I::Network>,
+        HotShotInitializer<TYPES>,
+        I::Storage,
+        MEMBERSHIPS,
+        MARKETPLACE,
+    >
+{
+    /// Check the components are consistent, then create the node without starting its tasks, as
+    /// [`SystemContext::new`] does
+    ///
+    /// # Errors
+    /// If the components are inconsistent, see [`SystemContextBuilder::init`]
+    pub async fn build(self) -> Result<Arc<SystemContext<TYPES, I, V>>, HotShotError<TYPES>> {
+        let components = self.into_components().await?;
+        let (public_key, private_key) = components.keys;
+
+        Ok(SystemContext::new(
+            public_key,
+            private_key,
+            components.node_id,
+            components.config,
+            components.memberships,
+            components.network,
+            components.initializer,
+            components.metrics,
+            components.storage,
+            components.marketplace_config,
+        )
+        .await)
+    }
+
+    /// Check the components are consistent, then create the node and start its tasks, as
+    /// [`SystemContext::init`] does
+    ///
+    /// # Errors
+    /// If the private key does not pair with the public key, the config fails
+    /// [`HotShotConfig::validate`], or the memberships of the initial epoch contain a node the
+    /// config does not know
+    pub async fn init(
+        self,
+    ) -> Result<
+        (
+            SystemContextHandle<TYPES, I, V>,
+            Sender<Arc<HotShotEvent<TYPES>>>,
+            Receiver<Arc<HotShotEvent<TYPES>>>,
+        ),
+        HotShotError<TYPES>,
+    > {
+        let components = self.into_components().await?;
+        let (public_key, private_key) = components.keys;
+
+        SystemContext::init(
+            public_key,
+            private_key,
+            components.node_id,
+            components.config,
+            components.memberships,
+            components.network,
+            components.initializer,
+            components.metrics,
+            components.storage,
+            components.marketplace_config,
+        )
+        .await
+    }
+
+    /// Fill in the optional components and check they are all consistent
+    async fn into_components(self) -> Result<Components<TYPES, I>, HotShotError<TYPES>> {
+        let invalid = |reason: String| Err(HotShotError::InvalidConfig(reason));
+        let memberships = self.memberships.into_memberships(&self.config);
+        let marketplace_config = self
+            .marketplace_config
+            .into_marketplace_config(&self.config);
+        let (public_key, private_key) = &self.keys;
+        let config = &self.config;
+
+        // Any message will do, it is only signed to check the keys
+        let message = b"system context builder key check";
+        let pairs = TYPES::SignatureKey::sign(private_key, message)
+            .is_ok_and(|signature| public_key.validate(&signature, message));
+        if !pairs {
+            return invalid(format!(
+                "The private key does not pair with public key {public_key}"
+            ));
+        }
+
+        if let Err(e) = config.validate() {
+            return invalid(e.to_string());
+        }
+
+        let known_nodes: HashSet<_> = config
+            .known_nodes_with_stake
+            .iter()
+            .map(|peer| TYPES::SignatureKey::public_key(&peer.stake_table_entry))
+            .collect();
+        let known_da_nodes: HashSet<_> = config
+            .da_committee()
+            .iter()
+            .chain(&config.known_da_nodes)
+            .map(|peer| TYPES::SignatureKey::public_key(&peer.stake_table_entry))
+            .collect();
+        let epoch = self.initializer.start_epoch;
+        let membership = memberships.read().await;
+        let unknown = membership
+            .stake_table(epoch)
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .find(|key| !known_nodes.contains(key));
+        if let Some(key) = unknown {
+            return invalid(format!(
+                "Node {key} is in the stake table of epoch {epoch} but not in the config"
+            ));
+        }
+        let unknown_da = membership
+            .da_stake_table(epoch)
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .find(|key| !known_da_nodes.contains(key));
+        if let Some(key) = unknown_da {
+            return invalid(format!(
+                "Node {key} is in the DA committee of epoch {epoch} but not in the config"
+            ));
+        }
+        drop(membership);
+
+        Ok(Components {
+            keys: self.keys,
+            config: self.config,
+            network: self.network,
+            initializer: self.initializer,
+            storage: self.storage,
+            memberships,
+            marketplace_config,
+            node_id: self.node_id,
+            metrics: self.metrics,
+        })
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Streaming decided leaves from a given height.
+//!
+//! The stream yields the leaves decided before it started from
+//! [`Storage`](hotshot_types::traits::storage::Storage), and then the leaves of each decide as it
+//! is reported. Decided leaves are persisted before the decide is reported, and the stream
+//! subscribes to decides before it reads storage, so every leaf is found in one or the other.
+//! Leaves are yielded in order of height, each once.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use async_broadcast::{Receiver, RecvError};
+use async_lock::RwLock;
+use futures::Stream;
+use hotshot_types::{
+    data::Leaf2,
+    event::{Event, EventType, LeafInfo},
+    traits::{node_implementation::NodeType, storage::Storage},
+};
+
+/// The state of a stream of decided leaves
+struct DecidedLeaves<TYPES: NodeType, S> {
+    /// Storage to backfill from
+    storage: Arc<RwLock<S>>,
+    /// Events of the node, for its decides
+    events: Receiver<Event<TYPES>>,
+    /// Height of the next leaf to yield
+    next_height: u64,
+    /// Leaves ready to be yielded, in order of height
+    ready: VecDeque<Leaf2<TYPES>>,
+}
+
+impl<TYPES: NodeType, S: Storage<TYPES>> DecidedLeaves<TYPES, S> {
+    /// Queue the leaves in storage from the next height on, up to the first one missing
+    async fn backfill(&mut self) {
+        let leaves = match self
+            .storage
+            .read()
+            .await
+            .load_decided_leaves(self.next_height)
+            .await
+        {
+            Ok(leaves) => leaves,
+            Err(e) => {
+                tracing::warn!("Failed to load decided leaves from storage: {e:#}");
+                return;
+            }
+        };
+
+        for leaf in leaves {
+            if leaf.height() != self.next_height {
+                break;
+            }
+            self.next_height += 1;
+            self.ready.push_back(leaf);
+        }
+    }
+
+    /// Queue the leaves of a decided leaf chain not yielded yet, filling any gap before them from
+    /// storage
+    async fn push_leaf_chain(&mut self, leaf_chain: &[LeafInfo<TYPES>]) {
+        // The leaf chain is newest first
+        for info in leaf_chain.iter().rev() {
+            let height = info.leaf.height();
+            if height > self.next_height {
+                self.backfill().await;
+                if height > self.next_height {
+                    tracing::warn!(
+                        "Decided leaves {} to {} are not in storage, and are skipped",
+                        self.next_height,
+                        height - 1
+                    );
+                }
+            }
+            if height < self.next_height {
+                continue;
+            }
+
+            self.next_height = height + 1;
+            self.ready.push_back(info.leaf.clone());
+        }
+    }
+
+    /// The next decided leaf, or `None` once the node's events end
+    async fn next(&mut self) -> Option<Leaf2<TYPES>> {
+        loop {
+            if let Some(leaf) = self.ready.pop_front() {
+                return Some(leaf);
+            }
+
+            match self.events.recv_direct().await {
+                Ok(Event {
+                    event: EventType::Decide { leaf_chain, .. },
+                    ..
+                }) => self.push_leaf_chain(&leaf_chain).await,
+                Ok(_) => {}
+                Err(RecvError::Closed) => return None,
+                // The missed leaves are in storage
+                Err(RecvError::Overflowed(missed)) => {
+                    tracing::warn!("Decided leaf stream missed {missed} events");
+                    self.backfill().await;
+                }
+            }
+        }
+    }
+}
+
+/// Stream the decided leaves from `height` on, backfilling from `storage` and then following the
+/// decides in `events`. `events` must be subscribed before this is called.
+pub(crate) async fn stream_decided_leaves<TYPES: NodeType, S: Storage<TYPES>>(
+    storage: Arc<RwLock<S>>,
+    events: Receiver<Event<TYPES>>,
+    height: u64,
+) -> impl Stream<Item = Leaf2<TYPES>> {
+    let mut leaves = DecidedLeaves {
+        storage,
+        events,
+        next_height: height,
+        ready: VecDeque::new(),
+    };
+    leaves.backfill().await;
+
+    futures::stream::unfold(leaves, |mut leaves| async move {
+        let leaf = leaves.next().await?;
+        Some((leaf, leaves))
+    })
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Executing decided blocks in order.
+//!
+//! An application which runs a state machine on top of consensus registers an [`ExecutionHook`]
+//! with [`SystemContextHandle::add_execution_hook`](crate::types::SystemContextHandle::add_execution_hook).
+//! The hook is handed each decided block once, in order of height, with its payload, metadata and
+//! the validated state after it. The height of the last block executed is persisted through
+//! [`Storage`](hotshot_types::traits::storage::Storage), so that after a restart the hook carries
+//! on from the block after it.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use hotshot_types::{
+    data::Leaf2,
+    event::LeafInfo,
+    traits::{
+        block_contents::{BlockHeader, BlockPayload},
+        node_implementation::NodeType,
+        states::ValidatedState,
+    },
+};
+
+/// A decided block, as handed to an [`ExecutionHook`]
+#[derive(Clone, Debug)]
+pub struct DecidedBlock<TYPES: NodeType> {
+    /// Height of the block
+    pub height: u64,
+    /// The decided leaf, with the header of the block
+    pub leaf: Leaf2<TYPES>,
+    /// The payload of the block, if we have it
+    pub payload: Option<TYPES::BlockPayload>,
+    /// The metadata of the payload
+    pub metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    /// The validated state after the block
+    pub state: Arc<TYPES::ValidatedState>,
+    /// The changes the block made to the state, if known
+    pub delta: Option<Arc<<TYPES::ValidatedState as ValidatedState<TYPES>>::Delta>>,
+}
+
+impl<TYPES: NodeType> From<&LeafInfo<TYPES>> for DecidedBlock<TYPES> {
+    fn from(info: &LeafInfo<TYPES>) -> Self {
+        Self {
+            height: info.leaf.height(),
+            leaf: info.leaf.clone(),
+            payload: info.leaf.block_payload(),
+            metadata: info.leaf.block_header().metadata().clone(),
+            state: Arc::clone(&info.state),
+            delta: info.delta.clone(),
+        }
+    }
+}
+
+/// Executes decided blocks for the application
+#[async_trait]
+pub trait ExecutionHook<TYPES: NodeType>: Send + 'static {
+    /// Execute a decided block.
+    ///
+    /// Blocks are executed one at a time in order of height, and a block is only handed to the
+    /// hook once every earlier block has executed. A block is handed to the hook again only if
+    /// executing it failed, or if the node went down before the height of the block was
+    /// persisted.
+    ///
+    /// # Errors
+    /// If the block could not be executed, in which case it is retried after a delay
+    async fn execute(&mut self, block: &DecidedBlock<TYPES>) -> anyhow::Result<()>;
+}
+
+/// The decided blocks waiting for an [`ExecutionHook`], in order of height
+#[derive(Debug)]
+pub struct ExecutionQueue<TYPES: NodeType> {
+    /// Height of the last block executed, if any
+    cursor: Option<u64>,
+    /// Decided blocks not executed yet, by height
+    pending: BTreeMap<u64, DecidedBlock<TYPES>>,
+}
+
+impl<TYPES: NodeType> ExecutionQueue<TYPES> {
+    /// A queue for a hook which has executed the blocks up to `cursor`
+    #[must_use]
+    pub fn new(cursor: Option<u64>) -> Self {
+        Self {
+            cursor,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Height of the last block executed, if any
+    #[must_use]
+    pub fn cursor(&self) -> Option<u64> {
+        self.cursor
+    }
+
+    /// Number of blocks waiting to be executed
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no blocks are waiting to be executed
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Queue the blocks of a decided leaf chain, skipping those executed or queued already
+    pub fn push_leaf_chain(&mut self, leaf_chain: &[LeafInfo<TYPES>]) {
+        for info in leaf_chain {
+            let height = info.leaf.height();
+            if self.cursor.is_some_and(|cursor| height <= cursor) {
+                continue;
+            }
+            self.pending
+                .entry(height)
+                .or_insert_with(|| DecidedBlock::from(info));
+        }
+    }
+
+    /// Take the lowest block waiting to be executed
+    pub fn pop_next(&mut self) -> Option<DecidedBlock<TYPES>> {
+        let (height, block) = self.pending.pop_first()?;
+        if let Some(cursor) = self.cursor {
+            if height > cursor + 1 {
+                tracing::warn!(
+                    "Blocks {} to {} were decided before execution started, and are skipped",
+                    cursor + 1,
+                    height - 1
+                );
+            }
+        }
+
+        Some(block)
+    }
+
+    /// Record that the block at `height` has executed
+    pub fn mark_executed(&mut self, height: u64) {
+        self.cursor = Some(height);
+    }
+}
@@ -1,6 +1,362 @@
-use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Stdout, Write},
+    sync::{Mutex, OnceLock, RwLock},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use tracing::{level_filters::LevelFilter, Subscriber};
+use tracing_subscriber::{
+    fmt::{format::FmtSpan, MakeWriter},
+    reload, EnvFilter,
+};
+
+/// Tags of the tagged base64 encoding of public keys
+const KEY_TAGS: [&str; 2] = ["BLS_VER_KEY", "SCHNORR_VER_KEY"];
+
+/// Tags of the tagged base64 encoding of signatures
+const SIGNATURE_TAGS: [&str; 2] = ["BLS_SIG", "SCHNORR_SIG"];
+
+/// Number of hex characters of a payload kept when payloads are redacted without a limit
+const DEFAULT_PAYLOAD_HEX_LIMIT: usize = 16;
+
+/// Hex strings shorter than this are left alone, as they are more likely numbers or short hashes
+/// than payloads
+const MIN_PAYLOAD_HEX_LEN: usize = 32;
+
+/// Which sensitive data to redact from logs, see [`set_log_redaction`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogRedaction {
+    /// replace public keys with a short hash, which still tells keys apart
+    pub hash_keys: bool,
+    /// truncate hex strings, such as payload bytes, to this many characters
+    pub payload_hex_limit: Option<usize>,
+    /// replace signatures with a placeholder
+    pub omit_signatures: bool,
+}
+
+/// Redaction applied to every log line written by the subscriber of [`initialize_logging`]
+static LOG_REDACTION: RwLock<LogRedaction> = RwLock::new(LogRedaction::NONE);
+
+/// Directives of the log filter of the subscriber of [`initialize_logging`]
+static LOG_FILTERS: Mutex<LogFilters> = Mutex::new(LogFilters {
+    base: String::new(),
+    overrides: BTreeMap::new(),
+    next_id: 0,
+});
+
+/// Replaces the log filter of the subscriber of [`initialize_logging`], once it is installed
+#[allow(clippy::type_complexity)]
+static LOG_FILTER_RELOAD: OnceLock<Box<dyn Fn(EnvFilter) -> Result<()> + Send + Sync>> =
+    OnceLock::new();
+
+impl LogRedaction {
+    /// Redaction of nothing
+    pub const NONE: Self = Self {
+        hash_keys: false,
+        payload_hex_limit: None,
+        omit_signatures: false,
+    };
+
+    /// Parse a comma-separated list of `keys`, `signatures`, `payloads` or `payloads=<limit>`,
+    /// or `all`. Unknown entries are ignored.
+    #[must_use]
+    pub fn parse(value: &str) -> Self {
+        value
+            .split(',')
+            .fold(Self::NONE, |redaction, entry| match entry.trim() {
+                "all" => Self {
+                    hash_keys: true,
+                    payload_hex_limit: Some(DEFAULT_PAYLOAD_HEX_LIMIT),
+                    omit_signatures: true,
+                },
+                "keys" => Self {
+                    hash_keys: true,
+                    ..redaction
+                },
+                "signatures" => Self {
+                    omit_signatures: true,
+                    ..redaction
+                },
+                "payloads" => Self {
+                    payload_hex_limit: Some(DEFAULT_PAYLOAD_HEX_LIMIT),
+                    ..redaction
+                },
+                entry => match entry
+                    .strip_prefix("payloads=")
+                    .and_then(|limit| limit.parse().ok())
+                {
+                    Some(limit) => Self {
+                        payload_hex_limit: Some(limit),
+                        ..redaction
+                    },
+                    None => redaction,
+                },
+            })
+    }
+
+    /// Whether anything is redacted
+    #[must_use]
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Redact `text`
+    #[must_use]
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.is_none() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut changed = false;
+        let mut rest = text;
+        while !rest.is_empty() {
+            // Tokens are runs of the characters of tagged base64 and hex
+            let start = rest.find(is_token_char).unwrap_or(rest.len());
+            redacted.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c: char| !is_token_char(c)).unwrap_or(rest.len());
+            let token = &rest[..end];
+            match self.redact_token(token) {
+                Some(replacement) => {
+                    redacted.push_str(&replacement);
+                    changed = true;
+                }
+                None => redacted.push_str(token),
+            }
+            rest = &rest[end..];
+        }
+
+        if changed {
+            Cow::Owned(redacted)
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// The replacement for `token`, if it is redacted
+    fn redact_token(&self, token: &str) -> Option<String> {
+        if let Some((tag, _)) = token.split_once('~') {
+            if self.hash_keys && KEY_TAGS.contains(&tag) {
+                let digest = Sha256::digest(token.as_bytes());
+                let mut hashed = format!("{tag}~#");
+                for byte in &digest[..4] {
+                    let _ = write!(hashed, "{byte:02x}");
+                }
+                return Some(hashed);
+            }
+            if self.omit_signatures && SIGNATURE_TAGS.contains(&tag) {
+                return Some(format!("{tag}~<omitted>"));
+            }
+            return None;
+        }
+
+        let limit = self.payload_hex_limit?;
+        let hex = token.strip_prefix("0x").unwrap_or(token);
+        (hex.len() > limit.max(MIN_PAYLOAD_HEX_LEN) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| {
+                format!(
+                    "{}{}...<{} hex chars>",
+                    &token[..token.len() - hex.len()],
+                    &hex[..limit],
+                    hex.len()
+                )
+            })
+    }
+}
+
+/// Whether `c` can be part of a key, signature or hex payload
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '~' | '_' | '-')
+}
+
+/// Set the redaction of logs written from now on
+pub fn set_log_redaction(redaction: LogRedaction) {
+    *LOG_REDACTION
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = redaction;
+}
+
+/// The redaction of logs currently in effect
+pub fn log_redaction() -> LogRedaction {
+    *LOG_REDACTION
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Writes log lines to stdout after applying the current [`LogRedaction`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RedactingStdout;
+
+impl<'a> MakeWriter<'a> for RedactingStdout {
+    type Writer = RedactingWriter<Stdout>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: io::stdout(),
+            redaction: log_redaction(),
+        }
+    }
+}
+
+/// Writer which redacts everything written to it before passing it on
+pub struct RedactingWriter<W: Write> {
+    /// the writer to pass redacted output on to
+    inner: W,
+    /// what to redact
+    redaction: LogRedaction,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The formatter writes whole log lines at once, so tokens are never split between writes
+        match std::str::from_utf8(buf) {
+            Ok(text) if !self.redaction.is_none() => {
+                self.inner
+                    .write_all(self.redaction.redact(text).as_bytes())?;
+                Ok(buf.len())
+            }
+            _ => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The directives logs are filtered by: those of `RUST_LOG`, followed by the overrides
+#[derive(Debug)]
+struct LogFilters {
+    /// directives of `RUST_LOG` when logging was initialized
+    base: String,
+    /// overrides in the order they were added, so later ones take precedence
+    overrides: BTreeMap<u64, String>,
+    /// id of the next override
+    next_id: u64,
+}
+
+impl LogFilters {
+    /// All directives, comma-separated
+    fn directives(&self) -> String {
+        std::iter::once(&self.base)
+            .chain(self.overrides.values())
+            .filter(|directives| !directives.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Replace the filter of the subscriber with one of the current directives
+    fn apply(&self) -> Result<()> {
+        let Some(reload) = LOG_FILTER_RELOAD.get() else {
+            return Ok(());
+        };
+        let filter = EnvFilter::builder()
+            .with_default_directive(LevelFilter::ERROR.into())
+            .parse(self.directives())
+            .context("Invalid log filter")?;
+
+        reload(filter)
+    }
+}
+
+/// An override of the log filter, see [`add_log_filter_override`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LogFilterOverride(u64);
+
+/// When an override of the log filter is reverted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFilterRevert {
+    /// only when it is removed
+    Never,
+    /// once the node reaches the view this many views after the current one
+    AfterViews(u64),
+    /// after this long
+    After(Duration),
+}
+
+/// The lock on the log filter directives
+fn log_filters() -> std::sync::MutexGuard<'static, LogFilters> {
+    LOG_FILTERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Filter logs by `directives`, in the syntax of `RUST_LOG`, on top of the current filter, e.g.
+/// `hotshot_task_impls::consensus=debug` to log the consensus task at debug level. The directives
+/// take precedence over those of `RUST_LOG` and of earlier overrides for the same targets, until
+/// the override is removed with [`remove_log_filter_override`].
+///
+/// The filter only applies to the subscriber of [`initialize_logging`]; if logging was initialized
+/// otherwise, the override is recorded but has no effect.
+///
+/// # Errors
+/// If `directives` are invalid, or the filter could not be replaced
+pub fn add_log_filter_override(directives: &str) -> Result<LogFilterOverride> {
+    EnvFilter::builder()
+        .parse(directives)
+        .with_context(|| format!("Invalid log filter directives {directives:?}"))?;
+
+    let mut filters = log_filters();
+    let id = filters.next_id;
+    filters.next_id += 1;
+    filters.overrides.insert(id, directives.to_string());
+    if let Err(e) = filters.apply() {
+        filters.overrides.remove(&id);
+        return Err(e);
+    }
+
+    Ok(LogFilterOverride(id))
+}
+
+/// Remove an override of the log filter. Returns whether it was still in effect.
+pub fn remove_log_filter_override(id: LogFilterOverride) -> bool {
+    let mut filters = log_filters();
+    if filters.overrides.remove(&id.0).is_none() {
+        return false;
+    }
+    if let Err(e) = filters.apply() {
+        tracing::warn!("Failed to revert log filter override: {e:#}");
+    }
+
+    true
+}
+
+/// The directives logs are currently filtered by, comma-separated
+pub fn log_filter() -> String {
+    log_filters().directives()
+}
+
+/// Record the directives of `RUST_LOG`, and replace the filter through `handle` when they change
+fn install_log_filter_reload<S: Subscriber + 'static>(handle: reload::Handle<EnvFilter, S>) {
+    let mut filters = log_filters();
+    filters.base = std::env::var("RUST_LOG").unwrap_or_default();
+    let _ = LOG_FILTER_RELOAD.set(Box::new(move |filter| {
+        handle
+            .reload(filter)
+            .map_err(|e| anyhow!("Failed to replace the log filter: {e}"))
+    }));
+
+    // Overrides added before logging was initialized take effect now
+    if !filters.overrides.is_empty() {
+        if let Err(e) = filters.apply() {
+            tracing::warn!("Failed to apply log filter overrides: {e:#}");
+        }
+    }
+}
 
 /// Initializes logging
+///
+/// Sensitive data is redacted as configured in the `RUST_LOG_REDACT` environment variable, see
+/// [`LogRedaction::parse`], and can be changed later with [`set_log_redaction`]. Logs are filtered
+/// as configured in `RUST_LOG`, which can be overridden at runtime with
+/// [`add_log_filter_override`].
 pub fn initialize_logging() {
     // Parse the `RUST_LOG_SPAN_EVENTS` environment variable
     let span_event_filter = match std::env::var("RUST_LOG_SPAN_EVENTS") {
@@ -19,17 +375,32 @@ pub fn initialize_logging() {
         Err(_) => FmtSpan::NONE,
     };
 
+    // Parse the `RUST_LOG_REDACT` environment variable
+    if let Ok(val) = std::env::var("RUST_LOG_REDACT") {
+        set_log_redaction(LogRedaction::parse(&val));
+    }
+
     // Conditionally initialize in `json` mode
     if std::env::var("RUST_LOG_FORMAT") == Ok("json".to_string()) {
-        let _ = tracing_subscriber::fmt()
+        let builder = tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::from_default_env())
             .with_span_events(span_event_filter)
+            .with_writer(RedactingStdout)
             .json()
-            .try_init();
+            .with_filter_reloading();
+        let handle = builder.reload_handle();
+        if builder.try_init().is_ok() {
+            install_log_filter_reload(handle);
+        }
     } else {
-        let _ = tracing_subscriber::fmt()
+        let builder = tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::from_default_env())
             .with_span_events(span_event_filter)
-            .try_init();
+            .with_writer(RedactingStdout)
+            .with_filter_reloading();
+        let handle = builder.reload_handle();
+        if builder.try_init().is_ok() {
+            install_log_filter_reload(handle);
+        }
     };
 }
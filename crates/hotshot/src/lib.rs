@@ -20,6 +20,8 @@ use hotshot_types::{
 use rand::Rng;
 use url::Url;
 
+/// Typed builder for [`SystemContext`]
+pub mod builder;
 /// Contains traits consumed by [`SystemContext`]
 pub mod traits;
 /// Contains types used by the crate
@@ -30,6 +32,16 @@ pub mod tasks;
 /// Contains helper functions for the crate
 pub mod helpers;
 
+pub mod pool;
+
+pub mod execution;
+
+/// Streaming decided leaves with backfill from storage
+mod decided_stream;
+
+/// Feeds of validated proposals, certificates and decides for read replicas
+mod replica_feed;
+
 use std::{
     collections::{BTreeMap, HashMap},
     num::NonZeroUsize,
@@ -40,31 +52,46 @@ use std::{
 use async_broadcast::{broadcast, InactiveReceiver, Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
-use futures::join;
+use futures::{join, Stream};
 use hotshot_task::task::{ConsensusTaskRegistry, NetworkTaskRegistry};
-use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    helpers::{broadcast_event, schedule_parameter_change},
+    transactions::InjectedBlockSlot,
+    work_scheduler::WorkScheduler,
+};
 // Internal
 /// Reexport error type
 pub use hotshot_types::error::HotShotError;
 use hotshot_types::{
+    alert::{AlertSink, AlertSinks},
     consensus::{Consensus, ConsensusMetricsValue, OuterConsensus, View, ViewInner},
-    constants::{EVENT_CHANNEL_SIZE, EXTERNAL_EVENT_CHANNEL_SIZE},
-    data::{Leaf2, QuorumProposal, QuorumProposal2},
+    constants::EVENT_CHANNEL_SIZE,
+    data::{Leaf2, QuorumProposal2},
     event::{EventType, LeafInfo},
-    message::{convert_proposal, DataMessage, Message, MessageKind, Proposal},
+    governance::{GovernedParameters, RuntimeParameters},
+    hotshot_config_file::HotShotConfigFile,
+    message::{DataMessage, Message, MessageKind, Proposal},
+    message_ttl::TtlHeader,
+    migration::{MigrationMode, Migrations},
+    peer_score::PeerScoreboard,
+    read_replica::SignedReplicaFeedMessage,
     simple_certificate::{NextEpochQuorumCertificate2, QuorumCertificate2, UpgradeCertificate},
+    snapshot::ChainSnapshot,
     traits::{
+        block_contents::TransactionPriority,
         consensus_api::ConsensusApi,
         election::Membership,
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeType},
         signature_key::SignatureKey,
-        states::ValidatedState,
+        states::{InstanceState, ValidatedState},
         storage::Storage,
         EncodeBytes,
     },
     utils::epoch_from_block_number,
-    HotShotConfig,
+    view_tracker::ViewTracker,
+    HotShotConfig, ValidatorConfig,
 };
 /// Reexport rand crate
 pub use rand;
@@ -74,6 +101,8 @@ use tracing::{debug, instrument, trace};
 // -- Rexports
 // External
 use crate::{
+    decided_stream::stream_decided_leaves,
+    replica_feed::open_replica_feed,
     tasks::{add_consensus_tasks, add_network_tasks},
     traits::NodeImplementation,
     types::{Event, SystemContextHandle},
@@ -152,6 +181,24 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
 
     /// Marketplace config for this instance of HotShot
     pub marketplace_config: MarketplaceConfig<TYPES, I>,
+
+    /// The view each task is in, shared between the tasks
+    pub view_tracker: Arc<ViewTracker<TYPES>>,
+
+    /// Block to propose in our next view as leader instead of asking the builders, set by tests
+    pub(crate) injected_block: InjectedBlockSlot<TYPES>,
+
+    /// Scores of misbehaving peers, and the peers banned for it
+    pub peer_scores: Arc<PeerScoreboard<TYPES>>,
+
+    /// Budget of background work in each view, shared between the tasks
+    pub work_scheduler: Arc<WorkScheduler>,
+
+    /// Runtime parameters and the decided changes to them, shared between the tasks
+    pub governed_parameters: Arc<GovernedParameters<TYPES>>,
+
+    /// Sinks for alerts on conditions which need the operator's attention
+    pub alerts: AlertSinks<TYPES>,
 }
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
     for SystemContext<TYPES, I, V>
@@ -177,6 +224,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
             storage: Arc::clone(&self.storage),
             upgrade_lock: self.upgrade_lock.clone(),
             marketplace_config: self.marketplace_config.clone(),
+            view_tracker: Arc::clone(&self.view_tracker),
+            injected_block: Arc::clone(&self.injected_block),
+            peer_scores: Arc::clone(&self.peer_scores),
+            work_scheduler: Arc::clone(&self.work_scheduler),
+            governed_parameters: Arc::clone(&self.governed_parameters),
+            alerts: self.alerts.clone(),
         }
     }
 }
@@ -192,7 +245,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     ///
     /// # Panics
     ///
-    /// Panics if storage migration fails.
+    /// Panics if storage migration fails, or if the channel capacities in `config` are invalid.
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
         public_key: TYPES::SignatureKey,
@@ -207,21 +260,28 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         marketplace_config: MarketplaceConfig<TYPES, I>,
     ) -> Arc<Self> {
         #[allow(clippy::panic)]
-        match storage
-            .migrate_consensus(
-                Into::<Leaf2<TYPES>>::into,
-                convert_proposal::<TYPES, QuorumProposal<TYPES>, QuorumProposal2<TYPES>>,
-            )
+        if let Err(e) = Migrations::<TYPES, I::Storage>::consensus()
+            .run(&storage, MigrationMode::Apply)
             .await
         {
-            Ok(()) => {}
-            Err(e) => {
-                panic!("Failed to migrate consensus storage: {e}");
-            }
+            panic!("Failed to migrate consensus storage: {e:#}");
         }
 
-        let internal_chan = broadcast(EVENT_CHANNEL_SIZE);
-        let external_chan = broadcast(EXTERNAL_EVENT_CHANNEL_SIZE);
+        #[allow(clippy::panic)]
+        if let Err(e) = config.channel_capacities.validate() {
+            panic!("Invalid channel capacities: {e}");
+        }
+        let internal_chan = broadcast(config.channel_capacities.internal_events);
+        let external_chan = broadcast(config.channel_capacities.output_events);
+
+        let mut membership_writer = memberships.write().await;
+        if let Some(thresholds) = config.thresholds() {
+            membership_writer.set_thresholds(thresholds);
+        }
+        if let Some(beacon) = config.committee_sampling_beacon {
+            membership_writer.set_sampling_beacon(TYPES::Epoch::genesis(), beacon);
+        }
+        drop(membership_writer);
 
         Self::new_from_channels(
             public_key,
@@ -274,7 +334,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         let (mut external_tx, mut external_rx) = external_channel;
 
         let upgrade_lock =
-            UpgradeLock::<TYPES, V>::from_certificate(&initializer.decided_upgrade_certificate);
+            UpgradeLock::<TYPES, V>::from_certificate(&initializer.decided_upgrade_certificate)
+                .with_chain_id(config.chain_id);
 
         // Allow overflow on the external channel, otherwise sending to it may block.
         external_rx.set_overflow(true);
@@ -333,7 +394,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         } else {
             TYPES::Epoch::new(anchored_leaf.height() / config.epoch_height + 1)
         };
-        let consensus = Consensus::new(
+        let mut consensus = Consensus::new(
             validated_state_map,
             anchored_leaf.view_number(),
             anchored_epoch,
@@ -348,6 +409,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             Arc::clone(&consensus_metrics),
             config.epoch_height,
         );
+        consensus.set_fork_choice(config.fork_choice.fork_choice());
+        if config.speculative_execution {
+            consensus.enable_speculative_execution();
+        }
 
         let consensus = Arc::new(RwLock::new(consensus));
 
@@ -355,6 +420,20 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         // Our own copy of the receiver is inactive so it doesn't count.
         external_tx.set_await_active(false);
 
+        let view_tracker = Arc::new(ViewTracker::new(config.max_task_view_lag));
+        let alerts = AlertSinks::default();
+        let peer_scores =
+            Arc::new(PeerScoreboard::new(config.peer_scoring).with_alerts(alerts.clone()));
+        let work_scheduler = Arc::new(WorkScheduler::new(
+            config.background_work_budget,
+            Arc::clone(&consensus_metrics),
+        ));
+        let governed_parameters = Arc::new(GovernedParameters::new(RuntimeParameters {
+            view_timeout: config.next_view_timeout,
+            max_block_size: instance_state.max_block_size(),
+            da_committee_size: None,
+        }));
+
         let inner: Arc<SystemContext<TYPES, I, V>> = Arc::new(SystemContext {
             id: nonce,
             consensus: OuterConsensus::new(consensus),
@@ -374,11 +453,54 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             storage: Arc::new(RwLock::new(storage)),
             upgrade_lock,
             marketplace_config,
+            view_tracker,
+            injected_block: Arc::default(),
+            peer_scores,
+            work_scheduler,
+            governed_parameters,
+            alerts,
         });
 
         inner
     }
 
+    /// Schedule again the parameter changes decided before a restart, as persisted in storage,
+    /// so that they apply from the same views as on the nodes which kept running
+    pub async fn reload_parameter_changes(&self) {
+        let changes = match self.storage.read().await.load_parameter_changes().await {
+            Ok(changes) => changes,
+            Err(e) => {
+                tracing::warn!("Failed to load the parameter changes from storage: {e:#}");
+                return;
+            }
+        };
+        for (activation_view, change) in changes {
+            schedule_parameter_change(
+                &self.governed_parameters,
+                &self.memberships,
+                activation_view,
+                change,
+            )
+            .await;
+        }
+    }
+
+    /// Set again the sampling beacons decided before a restart, as persisted in storage, so that
+    /// quorums are sampled as on the nodes which kept running
+    pub async fn reload_sampling_beacons(&self) {
+        let beacons = match self.storage.read().await.load_sampling_beacons().await {
+            Ok(beacons) => beacons,
+            Err(e) => {
+                tracing::warn!("Failed to load the sampling beacons from storage: {e:#}");
+                return;
+            }
+        };
+        let mut membership_writer = self.memberships.write().await;
+        for (from_epoch, beacon) in beacons {
+            membership_writer.set_sampling_beacon(from_epoch, beacon);
+        }
+    }
+
     /// "Starts" consensus by sending a `Qc2Formed`, `ViewChange` events
     ///
     /// # Panics
@@ -389,6 +511,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         compile_error!("Cannot run rewind in production builds!");
 
         debug!("Starting Consensus");
+        self.reload_parameter_changes().await;
+        self.reload_sampling_beacons().await;
         let consensus = self.consensus.read().await;
 
         #[allow(clippy::panic)]
@@ -441,17 +565,27 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         {
             // Some applications seem to expect a leaf decide event for the genesis leaf,
             // which contains only that leaf and nothing else.
+            // This is the built-in genesis leaf or one derived from a chain snapshot, whose state
+            // and certificate we were initialized with.
             if self.anchored_leaf.view_number() == TYPES::View::genesis() {
-                let (validated_state, state_delta) =
-                    TYPES::ValidatedState::genesis(&self.instance_state);
+                if let Err(e) = self
+                    .storage
+                    .write()
+                    .await
+                    .append_decided_leaves(&[self.anchored_leaf.clone()])
+                    .await
+                {
+                    tracing::warn!("Failed to store the genesis leaf: {e:#}");
+                }
 
-                let qc = Arc::new(
-                    QuorumCertificate2::genesis::<V>(
-                        &validated_state,
-                        self.instance_state.as_ref(),
-                    )
-                    .await,
-                );
+                let (validated_state, state_delta) =
+                    consensus.state_and_delta(self.anchored_leaf.view_number());
+                let validated_state = validated_state.unwrap_or_else(|| {
+                    Arc::new(TYPES::ValidatedState::from_header(
+                        self.anchored_leaf.block_header(),
+                    ))
+                });
+                let qc = Arc::new(consensus.high_qc().clone());
 
                 broadcast_event(
                     Event {
@@ -459,8 +593,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
                         event: EventType::Decide {
                             leaf_chain: Arc::new(vec![LeafInfo::new(
                                 self.anchored_leaf.clone(),
-                                Arc::new(validated_state),
-                                Some(Arc::new(state_delta)),
+                                validated_state,
+                                state_delta,
                                 None,
                             )]),
                             qc,
@@ -489,6 +623,21 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     pub async fn publish_transaction_async(
         &self,
         transaction: TYPES::Transaction,
+    ) -> Result<(), HotShotError<TYPES>> {
+        self.publish_prioritized_transaction_async(transaction, TransactionPriority::Normal)
+            .await
+    }
+
+    /// Publishes a transaction tagged with a priority class asynchronously to the network.
+    ///
+    /// # Errors
+    ///
+    /// Always returns Ok; does not return an error if the transaction couldn't be published to the network
+    #[instrument(skip(self), err, target = "SystemContext", fields(id = self.id))]
+    pub async fn publish_prioritized_transaction_async(
+        &self,
+        transaction: TYPES::Transaction,
+        priority: TransactionPriority,
     ) -> Result<(), HotShotError<TYPES>> {
         trace!("Adding transaction to our own queue");
 
@@ -499,17 +648,44 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         let epoch = consensus_reader.cur_epoch();
         drop(consensus_reader);
 
-        // Wrap up a message
-        let message_kind: DataMessage<TYPES> =
-            DataMessage::SubmitTransaction(transaction.clone(), view_number);
+        // Wrap up a message. Untagged transactions use the original message and event, so nodes
+        // and builders which don't know about priorities keep working.
+        let (message_kind, event) = match priority {
+            TransactionPriority::Normal => (
+                DataMessage::SubmitTransaction(transaction.clone(), view_number),
+                EventType::Transactions {
+                    transactions: vec![transaction],
+                },
+            ),
+            TransactionPriority::High => (
+                DataMessage::SubmitPrioritizedTransaction(
+                    transaction.clone(),
+                    priority,
+                    view_number,
+                ),
+                EventType::PrioritizedTransactions {
+                    transactions: vec![(transaction, priority)],
+                },
+            ),
+        };
         let message = Message {
             sender: api.public_key.clone(),
             kind: MessageKind::from(message_kind),
         };
 
-        let serialized_message = self.upgrade_lock.serialize(&message).await.map_err(|err| {
-            HotShotError::FailedToSerialize(format!("failed to serialize transaction: {err}"))
-        })?;
+        let serialized_message = self
+            .upgrade_lock
+            .serialize_with(&message, api.network.wire_format())
+            .await
+            .map_err(|err| {
+                HotShotError::FailedToSerialize(format!("failed to serialize transaction: {err}"))
+            })?;
+        let serialized_message = TtlHeader::new(
+            &message.kind,
+            &self.config.message_ttl,
+            self.instance_state.clock().now(),
+        )
+        .stamp(serialized_message);
 
         spawn(async move {
             let memberships_da_committee_members = api
@@ -538,9 +714,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
                 api
                     .send_external_event(Event {
                         view_number,
-                        event: EventType::Transactions {
-                            transactions: vec![transaction],
-                        },
+                        event,
                     }),
             }
         });
@@ -553,6 +727,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         Arc::clone(&self.consensus.inner_consensus)
     }
 
+    /// Hand every alert raised from now on to `sink`: safety violations, repeated timeouts,
+    /// storage failures and peer bans
+    pub async fn add_alert_sink<S: AlertSink<TYPES>>(&self, sink: S) {
+        self.alerts.add(Arc::new(sink)).await;
+    }
+
     /// Returns a copy of the instance state
     pub fn instance_state(&self) -> Arc<TYPES::InstanceState> {
         Arc::clone(&self.instance_state)
@@ -598,6 +778,30 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         self.consensus.read().await.state(view).cloned()
     }
 
+    /// Stream the decided leaves from `height` on, in order of height and each once: first those
+    /// decided already, as far back as storage keeps them, then each as it is decided.
+    #[instrument(skip_all, target = "SystemContext", fields(id = self.id))]
+    pub async fn stream_decided_leaves_from(
+        &self,
+        height: u64,
+    ) -> impl Stream<Item = Leaf2<TYPES>> {
+        // Subscribe before reading storage, so that no leaf decided in between is missed
+        let events = self.external_event_stream.1.activate_cloned();
+        stream_decided_leaves(Arc::clone(&self.storage), events, height).await
+    }
+
+    /// Open a feed of the proposals this node validates, the certificates it sees and the leaves
+    /// it decides, signed by this node, for its read replicas to follow
+    #[instrument(skip_all, target = "SystemContext", fields(id = self.id))]
+    pub fn read_replica_feed(&self) -> impl Stream<Item = SignedReplicaFeedMessage<TYPES>> {
+        open_replica_feed(
+            self.external_event_stream.1.activate_cloned(),
+            self.public_key.clone(),
+            self.private_key.clone(),
+            self.upgrade_lock.clone(),
+        )
+    }
+
     /// Initializes a new [`SystemContext`] and does the work of setting up all the background tasks
     ///
     /// Assumes networking implementation is already primed.
@@ -610,7 +814,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     /// To construct a [`SystemContext`] without setting up tasks, use `fn new` instead.
     /// # Errors
     ///
-    /// Can throw an error if `Self::new` fails.
+    /// Can throw an error if `Self::new` fails, or if `config` is invalid.
     #[allow(clippy::too_many_arguments)]
     pub async fn init(
         public_key: TYPES::SignatureKey,
@@ -631,6 +835,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         ),
         HotShotError<TYPES>,
     > {
+        config
+            .validate()
+            .map_err(|e| HotShotError::InvalidConfig(e.to_string()))?;
+
         let hotshot = Self::new(
             public_key,
             private_key,
@@ -649,6 +857,53 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
 
         Ok((handle, tx, rx.activate()))
     }
+
+    /// Starts a local development chain run by `validator_config` alone, and returns its handle.
+    ///
+    /// The node leads every view and its own vote forms every certificate, so each block is
+    /// decided as soon as the chain rule allows, a few milliseconds after it is proposed, with no
+    /// other nodes to wait on. Timers are configured as in
+    /// [`HotShotConfigFile::dev_single_node`]. Consensus is already started on the returned
+    /// handle.
+    ///
+    /// This gives application developers a fast local chain. It tolerates no faults and must not
+    /// be used in production.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node could not be initialized.
+    pub async fn dev_single_node(
+        validator_config: &ValidatorConfig<TYPES::SignatureKey>,
+        builder_url: Url,
+        network: Arc<I::Network>,
+        initializer: HotShotInitializer<TYPES>,
+        storage: I::Storage,
+        marketplace_config: MarketplaceConfig<TYPES, I>,
+    ) -> Result<SystemContextHandle<TYPES, I, V>, HotShotError<TYPES>> {
+        let config: HotShotConfig<TYPES::SignatureKey> =
+            HotShotConfigFile::dev_single_node(validator_config, builder_url).into();
+        let memberships = Arc::new(RwLock::new(TYPES::Membership::new(
+            config.known_nodes_with_stake.clone(),
+            config.da_committee(),
+        )));
+
+        let (handle, _, _) = Self::init(
+            validator_config.public_key.clone(),
+            validator_config.private_key.clone(),
+            0,
+            config,
+            memberships,
+            network,
+            initializer,
+            ConsensusMetricsValue::default(),
+            storage,
+            marketplace_config,
+        )
+        .await?;
+        handle.hotshot.start_consensus().await;
+
+        Ok(handle)
+    }
     /// return the timeout for a view for `self`
     #[must_use]
     pub fn next_view_timeout(&self) -> u64 {
@@ -791,6 +1046,7 @@ where
         SystemContextHandle<TYPES, I, V>,
     ) {
         let epoch_height = config.epoch_height;
+        let channel_capacities = config.channel_capacities;
         let left_system_context = SystemContext::new(
             public_key.clone(),
             private_key.clone(),
@@ -826,23 +1082,26 @@ where
         let right_network_registry = NetworkTaskRegistry::new();
 
         // create external channels for both handles
-        let (left_external_sender, left_external_receiver) = broadcast(EXTERNAL_EVENT_CHANNEL_SIZE);
+        let (left_external_sender, left_external_receiver) =
+            broadcast(channel_capacities.output_events);
         let left_external_event_stream =
             (left_external_sender, left_external_receiver.deactivate());
 
         let (right_external_sender, right_external_receiver) =
-            broadcast(EXTERNAL_EVENT_CHANNEL_SIZE);
+            broadcast(channel_capacities.output_events);
         let right_external_event_stream =
             (right_external_sender, right_external_receiver.deactivate());
 
         // create internal channels for both handles
-        let (left_internal_sender, left_internal_receiver) = broadcast(EVENT_CHANNEL_SIZE);
+        let (left_internal_sender, left_internal_receiver) =
+            broadcast(channel_capacities.internal_events);
         let left_internal_event_stream = (
             left_internal_sender.clone(),
             left_internal_receiver.clone().deactivate(),
         );
 
-        let (right_internal_sender, right_internal_receiver) = broadcast(EVENT_CHANNEL_SIZE);
+        let (right_internal_sender, right_internal_receiver) =
+            broadcast(channel_capacities.internal_events);
         let right_internal_event_stream = (
             right_internal_sender.clone(),
             right_internal_receiver.clone().deactivate(),
@@ -1054,6 +1313,42 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
         })
     }
 
+    /// Initialize a new chain which carries on from `snapshot` of an existing one, in place of the
+    /// built-in genesis.
+    ///
+    /// The stake tables of `snapshot` are not applied here: the configuration and membership the
+    /// node is started with must be built from them, see [`ChainSnapshot::apply_stake_table`].
+    ///
+    /// # Errors
+    /// If the stake tables of `snapshot` are unusable
+    pub async fn from_snapshot<V: Versions>(
+        snapshot: ChainSnapshot<TYPES>,
+        instance_state: TYPES::InstanceState,
+    ) -> Result<Self, HotShotError<TYPES>> {
+        snapshot
+            .validate()
+            .map_err(|e| HotShotError::InvalidState(format!("Invalid chain snapshot: {e}")))?;
+
+        let genesis_leaf = snapshot.genesis_leaf();
+        let high_qc = QuorumCertificate2::for_genesis_leaf::<V>(&genesis_leaf).await;
+
+        Ok(Self {
+            inner: genesis_leaf,
+            validated_state: snapshot.validated_state.map(Arc::new),
+            state_delta: None,
+            start_view: TYPES::View::new(0),
+            start_epoch: TYPES::Epoch::new(0),
+            actioned_view: TYPES::View::new(0),
+            saved_proposals: BTreeMap::new(),
+            high_qc,
+            next_epoch_high_qc: None,
+            decided_upgrade_certificate: None,
+            undecided_leaves: Vec::new(),
+            undecided_state: BTreeMap::new(),
+            instance_state,
+        })
+    }
+
     /// Reload previous state based on most recent leaf and the instance-level state.
     ///
     /// # Arguments
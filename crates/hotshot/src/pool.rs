@@ -0,0 +1,142 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Running several chains in one process.
+//!
+//! Each chain is an independent [`SystemContext`](crate::SystemContext), possibly of a different
+//! [`NodeType`], and the [`SystemContextPool`] keeps their handles by chain ID so they can be
+//! started and shut down together. Chains can share a transport through
+//! [`SharedNetwork`](crate::traits::implementations::SharedNetwork).
+
+use std::{any::Any, collections::BTreeMap};
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use hotshot_types::traits::node_implementation::{NodeImplementation, NodeType, Versions};
+
+use crate::{types::SystemContextHandle, HotShotError};
+
+/// A chain running in a [`SystemContextPool`], with its types erased
+#[async_trait]
+pub trait PooledChain: Send + Sync {
+    /// Start consensus on the chain
+    async fn start_consensus(&self);
+
+    /// Shut the chain down
+    async fn shut_down(&mut self);
+
+    /// The chain, to downcast to its handle
+    fn as_any(&self) -> &dyn Any;
+
+    /// The chain, to downcast to its handle
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> PooledChain
+    for SystemContextHandle<TYPES, I, V>
+{
+    async fn start_consensus(&self) {
+        self.hotshot.start_consensus().await;
+    }
+
+    async fn shut_down(&mut self) {
+        SystemContextHandle::shut_down(self).await;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The chains running in one process, by chain ID
+#[derive(Default)]
+pub struct SystemContextPool {
+    /// The handles of the chains
+    chains: BTreeMap<u64, Box<dyn PooledChain>>,
+}
+
+impl SystemContextPool {
+    /// An empty pool
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the chain `chain_id`, run by `handle`
+    ///
+    /// # Errors
+    /// If the pool already has a chain with this ID
+    pub fn insert<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+        &mut self,
+        chain_id: u64,
+        handle: SystemContextHandle<TYPES, I, V>,
+    ) -> Result<(), HotShotError<TYPES>> {
+        if self.chains.contains_key(&chain_id) {
+            return Err(HotShotError::InvalidConfig(format!(
+                "The pool already has a chain with ID {chain_id}"
+            )));
+        }
+        self.chains.insert(chain_id, Box::new(handle));
+
+        Ok(())
+    }
+
+    /// The handle of the chain `chain_id`, if it is in the pool and of these types
+    #[must_use]
+    pub fn get<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+        &self,
+        chain_id: u64,
+    ) -> Option<&SystemContextHandle<TYPES, I, V>> {
+        self.chains.get(&chain_id)?.as_any().downcast_ref()
+    }
+
+    /// The mutable handle of the chain `chain_id`, if it is in the pool and of these types
+    #[must_use]
+    pub fn get_mut<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+        &mut self,
+        chain_id: u64,
+    ) -> Option<&mut SystemContextHandle<TYPES, I, V>> {
+        self.chains.get_mut(&chain_id)?.as_any_mut().downcast_mut()
+    }
+
+    /// Take the chain `chain_id` out of the pool, without shutting it down
+    pub fn remove(&mut self, chain_id: u64) -> Option<Box<dyn PooledChain>> {
+        self.chains.remove(&chain_id)
+    }
+
+    /// The IDs of the chains in the pool
+    pub fn chain_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.chains.keys().copied()
+    }
+
+    /// Number of chains in the pool
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.chains.len()
+    }
+
+    /// Whether the pool has no chains
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+
+    /// Start consensus on every chain
+    pub async fn start_all(&self) {
+        join_all(self.chains.values().map(|chain| chain.start_consensus())).await;
+    }
+
+    /// Shut down every chain and empty the pool
+    pub async fn shut_down_all(&mut self) {
+        join_all(self.chains.values_mut().map(|chain| chain.shut_down())).await;
+        self.chains.clear();
+    }
+}
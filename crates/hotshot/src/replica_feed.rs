@@ -0,0 +1,150 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! The feed a validator streams to its read replicas.
+//!
+//! The feed follows the node's events: each validated quorum proposal is sent, preceded by its
+//! justify QC if that is for a higher view than any certificate sent before, and each decide is
+//! sent with the decided leaves. Every message is numbered and signed, as
+//! [`hotshot_types::read_replica`] describes. A feed which falls behind the node's events ends
+//! rather than skip them, so that replicas never miss a message unnoticed; they attach to a new
+//! feed instead.
+
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_broadcast::{Receiver, RecvError};
+use futures::Stream;
+use hotshot_types::{
+    event::{Event, EventType},
+    message::UpgradeLock,
+    read_replica::{ReplicaFeedItem, ReplicaFeedMessage, SignedReplicaFeedMessage},
+    traits::{
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::HasViewNumber,
+};
+
+/// The state of a validator's feed to its read replicas
+struct ReplicaFeed<TYPES: NodeType, V: Versions> {
+    /// Events of the node
+    events: Receiver<Event<TYPES>>,
+    /// The node's public key
+    public_key: TYPES::SignatureKey,
+    /// The node's private key, to sign the messages with
+    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// The node's upgrade lock, for the chain id
+    upgrade_lock: UpgradeLock<TYPES, V>,
+    /// When the feed was opened, which identifies it
+    feed: u64,
+    /// Sequence number of the next message
+    sequence: u64,
+    /// View of the highest certificate sent
+    high_qc_view: Option<TYPES::View>,
+    /// Items ready to be sent, in order
+    ready: VecDeque<ReplicaFeedItem<TYPES>>,
+}
+
+impl<TYPES: NodeType, V: Versions> ReplicaFeed<TYPES, V> {
+    /// Queue the items `event` gives the replicas
+    fn push_event(&mut self, event: EventType<TYPES>) {
+        match event {
+            EventType::QuorumProposal { proposal, .. } => {
+                let justify_qc = &proposal.data.justify_qc;
+                if self
+                    .high_qc_view
+                    .map_or(true, |view| view < justify_qc.view_number())
+                {
+                    self.high_qc_view = Some(justify_qc.view_number());
+                    self.ready
+                        .push_back(ReplicaFeedItem::Certificate(justify_qc.clone()));
+                }
+                self.ready.push_back(ReplicaFeedItem::Proposal(proposal));
+            }
+            EventType::Decide { leaf_chain, qc, .. } => {
+                self.ready.push_back(ReplicaFeedItem::Decide {
+                    leaves: leaf_chain.iter().map(|info| info.leaf.clone()).collect(),
+                    qc: (*qc).clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// The next message of the feed, or `None` once the feed ends
+    async fn next(&mut self) -> Option<SignedReplicaFeedMessage<TYPES>> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                let message = ReplicaFeedMessage {
+                    feed: self.feed,
+                    sequence: self.sequence,
+                    item,
+                };
+                match SignedReplicaFeedMessage::sign(
+                    message,
+                    self.public_key.clone(),
+                    &self.private_key,
+                    &self.upgrade_lock,
+                )
+                .await
+                {
+                    Ok(signed) => {
+                        self.sequence += 1;
+                        return Some(signed);
+                    }
+                    Err(e) => {
+                        tracing::error!("Ending the read replica feed: {e}");
+                        return None;
+                    }
+                }
+            }
+
+            match self.events.recv_direct().await {
+                Ok(event) => self.push_event(event.event),
+                Err(RecvError::Closed) => return None,
+                Err(RecvError::Overflowed(missed)) => {
+                    tracing::warn!(
+                        "Ending the read replica feed, which fell {missed} events behind the node"
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Open a feed of the node's validated proposals, certificates and decides for its read replicas,
+/// following the node's `events`
+pub(crate) fn open_replica_feed<TYPES: NodeType, V: Versions>(
+    events: Receiver<Event<TYPES>>,
+    public_key: TYPES::SignatureKey,
+    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    upgrade_lock: UpgradeLock<TYPES, V>,
+) -> impl Stream<Item = SignedReplicaFeedMessage<TYPES>> {
+    let feed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| {
+            u64::try_from(time.as_millis()).unwrap_or(u64::MAX)
+        });
+    let state = ReplicaFeed {
+        events,
+        public_key,
+        private_key,
+        upgrade_lock,
+        feed,
+        sequence: 0,
+        high_qc_view: None,
+        ready: VecDeque::new(),
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        let message = state.next().await?;
+        Some((message, state))
+    })
+}
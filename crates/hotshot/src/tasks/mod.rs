@@ -8,7 +8,13 @@
 
 /// Provides trait to create task states from a `SystemContextHandle`
 pub mod task_state;
-use std::{collections::BTreeMap, fmt::Debug, num::NonZeroUsize, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    num::NonZeroUsize,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_broadcast::{broadcast, RecvError};
 use async_lock::RwLock;
@@ -17,35 +23,59 @@ use futures::{
     future::{BoxFuture, FutureExt},
     stream, StreamExt,
 };
-use hotshot_task::task::Task;
+use hotshot_task::{sharded::ViewSharded, task::Task};
 #[cfg(feature = "rewind")]
 use hotshot_task_impls::rewind::RewindTaskState;
 use hotshot_task_impls::{
+    anti_entropy::AntiEntropyTaskState,
+    availability::AvailabilitySamplingTaskState,
+    catchup::CatchupTaskState,
     da::DaTaskState,
+    decryption::DecryptionTaskState,
     events::HotShotEvent,
+    heartbeat::HeartbeatTaskState,
+    helpers::broadcast_event,
     network::{NetworkEventTaskState, NetworkMessageTaskState},
+    peer_score::PeerScoreTaskState,
+    post_mortem::PostMortemTaskState,
+    proposal_dispersal::ProposalDispersalTaskState,
     request::NetworkRequestState,
     response::{run_response_task, NetworkResponseState},
+    send_scheduler::SendScheduler,
+    shadow_consensus::ShadowConsensusTaskState,
     transactions::TransactionTaskState,
     upgrade::UpgradeTaskState,
     vid::VidTaskState,
     view_sync::ViewSyncTaskState,
+    work_scheduler::WorkClass,
 };
 use hotshot_types::{
+    alert::AlertDetector,
+    channel_depth::ChannelDepth,
+    compact_vote::CompactVoteDecoder,
     consensus::{Consensus, OuterConsensus},
-    constants::EVENT_CHANNEL_SIZE,
+    constants::{
+        EVENT_CHANNEL_SIZE, EVENT_CHANNEL_WARNING_DELAY, EXECUTION_RETRY_DELAY,
+        REPEATED_TIMEOUT_ALERT_THRESHOLD,
+    },
+    event::{Event, EventType},
     message::{Message, UpgradeLock},
     traits::{
+        metrics::MetricsFamily,
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        states::InstanceState,
+        storage::Storage,
     },
 };
-use tokio::{spawn, time::sleep};
+use tokio::{spawn, sync::mpsc, time::sleep};
 use vbs::version::StaticVersionType;
 
 use crate::{
-    tasks::task_state::CreateTaskState, types::SystemContextHandle, ConsensusApi,
-    ConsensusMetricsValue, ConsensusTaskRegistry, HotShotConfig, HotShotInitializer,
+    execution::{ExecutionHook, ExecutionQueue},
+    tasks::task_state::CreateTaskState,
+    types::SystemContextHandle,
+    ConsensusApi, ConsensusMetricsValue, ConsensusTaskRegistry, HotShotConfig, HotShotInitializer,
     MarketplaceConfig, NetworkTaskRegistry, SignatureKey, SystemContext, Versions,
 };
 
@@ -58,6 +88,50 @@ pub enum GlobalEvent {
     Dummy,
 }
 
+/// A consensus task which can be restarted on a running node, losing its state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RestartableTask {
+    /// the DA task
+    Da,
+    /// the VID task
+    Vid,
+    /// the view sync task
+    ViewSync,
+}
+
+impl RestartableTask {
+    /// name the task is registered under
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            RestartableTask::Da => "DA",
+            RestartableTask::Vid => "VID",
+            RestartableTask::ViewSync => "view sync",
+        }
+    }
+}
+
+/// Start `task` with a fresh state, replacing the instance of it which is already running
+pub async fn add_restartable_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+    task: RestartableTask,
+) {
+    match task {
+        RestartableTask::Da => {
+            let state = DaTaskState::<TYPES, I, V>::create_from(handle).await;
+            handle.add_named_task(task.name(), ViewSharded::new(state));
+        }
+        RestartableTask::Vid => {
+            let state = VidTaskState::<TYPES, I, V>::create_from(handle).await;
+            handle.add_named_task(task.name(), ViewSharded::new(state));
+        }
+        RestartableTask::ViewSync => {
+            let state = ViewSyncTaskState::<TYPES, I, V>::create_from(handle).await;
+            handle.add_named_task(task.name(), state);
+        }
+    }
+}
+
 /// Add tasks for network requests and responses
 pub async fn add_request_network_task<
     TYPES: NodeType,
@@ -66,7 +140,91 @@ pub async fn add_request_network_task<
 >(
     handle: &mut SystemContextHandle<TYPES, I, V>,
 ) {
-    let state = NetworkRequestState::<TYPES, I>::create_from(handle).await;
+    let state = NetworkRequestState::<TYPES, I, V>::create_from(handle).await;
+
+    let task = Task::new(
+        state,
+        handle.internal_event_stream.0.clone(),
+        handle.internal_event_stream.1.activate_cloned(),
+    );
+    handle.consensus_registry.run_task(task);
+}
+
+/// Add the task which samples the availability of decided blocks
+pub async fn add_availability_sampling_task<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    V: Versions,
+>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let state = AvailabilitySamplingTaskState::<TYPES, I, V>::create_from(handle).await;
+
+    let task = Task::new(
+        state,
+        handle.internal_event_stream.0.clone(),
+        handle.internal_event_stream.1.activate_cloned(),
+    );
+    handle.consensus_registry.run_task(task);
+}
+
+/// Add the task which exchanges certificates with random peers, along with the timer which
+/// periodically starts an exchange
+pub async fn add_anti_entropy_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let state = AntiEntropyTaskState::<TYPES, V>::create_from(handle).await;
+
+    let task = Task::new(
+        state,
+        handle.internal_event_stream.0.clone(),
+        handle.internal_event_stream.1.activate_cloned(),
+    );
+    handle.consensus_registry.run_task(task);
+
+    let interval = handle.hotshot.config.anti_entropy_interval;
+    let sender = handle.internal_event_stream.0.clone();
+    let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
+    let task_handle = spawn(async move {
+        futures::pin_mut!(shutdown_signal);
+        loop {
+            futures::select! {
+                () = shutdown_signal => {
+                    return;
+                },
+                () = sleep(interval).fuse() => {
+                    broadcast_event(Arc::new(HotShotEvent::AntiEntropyTick), &sender).await;
+                }
+            }
+        }
+    });
+    handle.network_registry.register(task_handle);
+}
+
+/// Add the task which catches up on the decided leaves we missed from peers, and serves ours to
+/// peers catching up
+pub async fn add_catchup_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let state = CatchupTaskState::<TYPES, I, V>::create_from(handle).await;
+
+    let task = Task::new(
+        state,
+        handle.internal_event_stream.0.clone(),
+        handle.internal_event_stream.1.activate_cloned(),
+    );
+    handle.consensus_registry.run_task(task);
+}
+
+/// Add the task which broadcasts our heartbeat and tracks the liveness of peers, if heartbeats are
+/// enabled
+pub async fn add_heartbeat_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    if handle.hotshot.config.heartbeat_interval.is_none() {
+        return;
+    }
+    let state = HeartbeatTaskState::<TYPES, V>::create_from(handle).await;
 
     let task = Task::new(
         state,
@@ -76,30 +234,167 @@ pub async fn add_request_network_task<
     handle.consensus_registry.run_task(task);
 }
 
+/// Add the task which scores misbehaving peers, so the network message task can ban them
+pub fn add_peer_score_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let state = PeerScoreTaskState {
+        peer_scores: Arc::clone(&handle.hotshot.peer_scores),
+        id: handle.hotshot.id,
+    };
+
+    let task = Task::new(
+        state,
+        handle.internal_event_stream.0.clone(),
+        handle.internal_event_stream.1.activate_cloned(),
+    );
+    handle.consensus_registry.run_task(task);
+}
+
+/// Add the tasks which hand each decided block to `hook`, in order of height, carrying on from
+/// the last block executed before a restart
+pub async fn add_execution_hook_task<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    V: Versions,
+    H: ExecutionHook<TYPES>,
+>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+    mut hook: H,
+) {
+    let storage = Arc::clone(&handle.storage);
+    let work_scheduler = Arc::clone(&handle.hotshot.work_scheduler);
+    let cursor = storage
+        .read()
+        .await
+        .load_execution_cursor()
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load the execution cursor from storage: {e}");
+            None
+        });
+    let mut queue = ExecutionQueue::<TYPES>::new(cursor);
+
+    // Decides are read off the output stream as they come, so that they are not dropped from it
+    // while a block executes, and queued for the executing task
+    let (decided_sender, mut decided_receiver) = mpsc::unbounded_channel();
+    let mut output_stream = handle.output_event_stream.1.activate_cloned();
+    let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
+    let receive_handle = spawn(async move {
+        futures::pin_mut!(shutdown_signal);
+        loop {
+            futures::select! {
+                () = shutdown_signal => {
+                    return;
+                },
+                event = output_stream.recv_direct().fuse() => match event {
+                    Ok(Event {
+                        event: EventType::Decide { leaf_chain, .. },
+                        ..
+                    }) => {
+                        if decided_sender.send(leaf_chain).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Closed) => return,
+                    Err(e) => {
+                        tracing::error!("Execution hook missed decide events: {e}");
+                    }
+                },
+            }
+        }
+    });
+
+    let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
+    let execute_handle = spawn(async move {
+        futures::pin_mut!(shutdown_signal);
+        loop {
+            let leaf_chain = futures::select! {
+                () = shutdown_signal => {
+                    return;
+                },
+                leaf_chain = decided_receiver.recv().fuse() => match leaf_chain {
+                    Some(leaf_chain) => leaf_chain,
+                    None => return,
+                },
+            };
+            queue.push_leaf_chain(&leaf_chain);
+
+            while let Some(block) = queue.pop_next() {
+                while let Err(e) = hook.execute(&block).await {
+                    tracing::warn!("Failed to execute block {}, retrying: {e:#}", block.height);
+                    futures::select! {
+                        () = shutdown_signal => {
+                            return;
+                        },
+                        () = sleep(EXECUTION_RETRY_DELAY).fuse() => {}
+                    }
+                }
+                queue.mark_executed(block.height);
+
+                let flushed = work_scheduler
+                    .run(WorkClass::StorageFlush, async {
+                        storage
+                            .read()
+                            .await
+                            .update_execution_cursor(block.height)
+                            .await
+                    })
+                    .await;
+                if let Err(e) = flushed {
+                    tracing::warn!("Failed to persist the execution cursor: {e}");
+                }
+            }
+        }
+    });
+
+    handle.network_registry.register(receive_handle);
+    handle.network_registry.register(execute_handle);
+}
+
 /// Add a task which responds to requests on the network.
 pub fn add_response_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
     handle: &mut SystemContextHandle<TYPES, I, V>,
 ) {
-    let state = NetworkResponseState::<TYPES>::new(
+    let state = NetworkResponseState::<TYPES, V>::new(
         handle.hotshot.consensus(),
         Arc::clone(&handle.memberships),
         handle.public_key().clone(),
         handle.private_key().clone(),
         handle.hotshot.id,
+        handle.hotshot.upgrade_lock.clone(),
     );
-    handle.network_registry.register(run_response_task::<TYPES>(
-        state,
-        handle.internal_event_stream.1.activate_cloned(),
-        handle.internal_event_stream.0.clone(),
-    ));
+    handle
+        .network_registry
+        .register(run_response_task::<TYPES, V>(
+            state,
+            handle.internal_event_stream.1.activate_cloned(),
+            handle.internal_event_stream.0.clone(),
+        ));
 }
 
-/// Add a task which updates our queue length metric at a set interval
+/// Add a task which updates our queue length and event channel depth metrics at a set interval,
+/// warning about event channels which stay close to full
 pub fn add_queue_len_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
     handle: &mut SystemContextHandle<TYPES, I, V>,
 ) {
     let consensus = handle.hotshot.consensus();
     let rx = handle.internal_event_stream.1.clone();
+    let output_rx = handle.output_event_stream.1.clone();
+    let warning_fraction = handle.hotshot.config.event_channel_warning_fraction;
+    let mut internal_depth = ChannelDepth::new(
+        "internal",
+        rx.capacity(),
+        warning_fraction,
+        EVENT_CHANNEL_WARNING_DELAY,
+    );
+    let mut output_depth = ChannelDepth::new(
+        "output",
+        output_rx.capacity(),
+        warning_fraction,
+        EVENT_CHANNEL_WARNING_DELAY,
+    );
     let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
     let task_handle = spawn(async move {
         futures::pin_mut!(shutdown_signal);
@@ -109,7 +404,22 @@ pub fn add_queue_len_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Vers
                     return;
                 },
                 () = sleep(Duration::from_millis(500)).fuse() => {
-                    consensus.read().await.metrics.internal_event_queue_len.set(rx.len());
+                    let now = Instant::now();
+                    let consensus_reader = consensus.read().await;
+                    let metrics = &consensus_reader.metrics;
+                    metrics.internal_event_queue_len.set(rx.len());
+                    for (depth, len) in [
+                        (&mut internal_depth, rx.len()),
+                        (&mut output_depth, output_rx.len()),
+                    ] {
+                        depth.sample(len, now);
+                        let label = vec![depth.name().to_string()];
+                        metrics.event_channel_depth.create(label.clone()).set(len);
+                        metrics
+                            .event_channel_high_water_mark
+                            .create(label)
+                            .set(depth.high_water_mark());
+                    }
                 }
             }
         }
@@ -117,6 +427,39 @@ pub fn add_queue_len_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Vers
     handle.network_registry.register(task_handle);
 }
 
+/// Add a task which raises alerts on the conditions it detects in the output events to the alert
+/// sinks registered on the node
+pub fn add_alert_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let alerts = handle.hotshot.alerts.clone();
+    let mut detector = AlertDetector::<TYPES>::new(REPEATED_TIMEOUT_ALERT_THRESHOLD);
+    let mut output_stream = handle.output_event_stream.1.activate_cloned();
+    let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
+    let task_handle = spawn(async move {
+        futures::pin_mut!(shutdown_signal);
+        loop {
+            futures::select! {
+                () = shutdown_signal => {
+                    return;
+                },
+                event = output_stream.recv_direct().fuse() => match event {
+                    Ok(event) => {
+                        if let Some(alert) = detector.observe(&event.event) {
+                            alerts.raise(alert).await;
+                        }
+                    }
+                    Err(RecvError::Closed) => return,
+                    Err(e) => {
+                        tracing::error!("Alert task missed events: {e}");
+                    }
+                },
+            }
+        }
+    });
+    handle.network_registry.register(task_handle);
+}
+
 /// Add the network task to handle messages and publish events.
 #[allow(clippy::missing_panics_doc)]
 pub fn add_network_message_task<
@@ -128,11 +471,23 @@ pub fn add_network_message_task<
     handle: &mut SystemContextHandle<TYPES, I, V>,
     channel: &Arc<NET>,
 ) {
-    let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
+    let network_state: NetworkMessageTaskState<_, _> = NetworkMessageTaskState {
         internal_event_stream: handle.internal_event_stream.0.clone(),
         external_event_stream: handle.output_event_stream.0.clone(),
         public_key: handle.public_key().clone(),
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
+        consensus: OuterConsensus::new(handle.hotshot.consensus()),
+        replay_protection: handle.hotshot.config.replay_protection,
+        rejected_messages: lru::LruCache::new(NonZeroUsize::new(10_000).unwrap()),
+        beyond_horizon_messages: 0,
+        horizon_catchup: None,
+        clock: handle.hotshot.instance_state().clock(),
+        expired_messages: HashMap::new(),
+        compact_votes: CompactVoteDecoder::new(Arc::new(handle.hotshot.config.known_stake_keys())),
+        peer_scores: Arc::clone(&handle.hotshot.peer_scores),
+        membership: Arc::clone(&handle.hotshot.memberships),
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        epoch_height: handle.epoch_height,
     };
 
     let upgrade_lock = handle.hotshot.upgrade_lock.clone();
@@ -165,8 +520,13 @@ pub fn add_network_message_task<
                         }
                     };
 
+                    // Drop the message unread if it has expired
+                    let Some(message) = state.strip_ttl_header(&message).await else {
+                        continue;
+                    };
+
                     // Deserialize the message
-                    let deserialized_message: Message<TYPES> = match upgrade_lock.deserialize(&message).await {
+                    let deserialized_message: Message<TYPES> = match upgrade_lock.deserialize_with(message, network.wire_format()).await {
                         Ok(message) => message,
                         Err(e) => {
                             tracing::error!("Failed to deserialize message: {:?}", e);
@@ -203,6 +563,16 @@ pub fn add_network_event_task<
         consensus: OuterConsensus::new(handle.consensus()),
         upgrade_lock: handle.hotshot.upgrade_lock.clone(),
         transmit_tasks: BTreeMap::new(),
+        resent_outbound_messages: false,
+        connection_warm_up_views: handle.hotshot.config.connection_warm_up_views,
+        message_ttl: handle.hotshot.config.message_ttl,
+        clock: handle.hotshot.instance_state().clock(),
+        known_stake_table: Arc::new(handle.hotshot.config.known_stake_keys()),
+        proposal_dispersal_min_bytes: handle.hotshot.config.proposal_dispersal_min_bytes,
+        send_scheduler: Arc::new(SendScheduler::new(
+            handle.hotshot.config.upload_budget,
+            Arc::clone(&handle.hotshot.metrics),
+        )),
     };
     let task = Task::new(
         network_state,
@@ -216,9 +586,9 @@ pub fn add_network_event_task<
 pub async fn add_consensus_tasks<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
     handle: &mut SystemContextHandle<TYPES, I, V>,
 ) {
-    handle.add_task(ViewSyncTaskState::<TYPES, V>::create_from(handle).await);
-    handle.add_task(VidTaskState::<TYPES, I>::create_from(handle).await);
-    handle.add_task(DaTaskState::<TYPES, I, V>::create_from(handle).await);
+    add_restartable_task(handle, RestartableTask::ViewSync).await;
+    add_restartable_task(handle, RestartableTask::Vid).await;
+    add_restartable_task(handle, RestartableTask::Da).await;
     handle.add_task(TransactionTaskState::<TYPES, I, V>::create_from(handle).await);
 
     {
@@ -241,6 +611,9 @@ pub async fn add_consensus_tasks<TYPES: NodeType, I: NodeImplementation<TYPES>,
     // only spawn the upgrade task if we are actually configured to perform an upgrade.
     if V::Base::VERSION < V::Upgrade::VERSION {
         handle.add_task(UpgradeTaskState::<TYPES, V>::create_from(handle).await);
+        if handle.hotshot.config.shadow_upgrade {
+            handle.add_task(ShadowConsensusTaskState::<TYPES, V>::create_from(handle).await);
+        }
     }
 
     {
@@ -254,11 +627,56 @@ pub async fn add_consensus_tasks<TYPES: NodeType, I: NodeImplementation<TYPES>,
         handle.add_task(QuorumProposalRecvTaskState::<TYPES, I, V>::create_from(handle).await);
         handle.add_task(ConsensusTaskState::<TYPES, I, V>::create_from(handle).await);
     }
+    if handle.hotshot.config.proposal_dispersal_min_bytes.is_some() {
+        handle.add_task(ProposalDispersalTaskState::<TYPES, V>::create_from(handle).await);
+    }
     add_queue_len_task(handle);
+    add_alert_task(handle);
+    add_post_mortem_task(handle).await;
+    add_decryption_task(handle);
     #[cfg(feature = "rewind")]
     handle.add_task(RewindTaskState::<TYPES>::create_from(&handle).await);
 }
 
+/// Adds the task which collects post-mortem bundles, if a directory for them is configured.
+pub async fn add_post_mortem_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let Some(post_mortem_dir) = handle.hotshot.config.post_mortem_dir.clone() else {
+        return;
+    };
+    let consensus = handle.hotshot.consensus();
+    let decided_leaf = consensus.read().await.decided_leaf();
+
+    handle.add_task(PostMortemTaskState::<TYPES, I>::new(
+        post_mortem_dir,
+        OuterConsensus::new(consensus),
+        Arc::clone(&handle.hotshot.network),
+        &decided_leaf,
+        handle.hotshot.id,
+    ));
+}
+
+/// Adds the task which decrypts the encrypted transactions of decided blocks, if the application
+/// enables the encrypted mempool.
+pub fn add_decryption_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let Some(decryption) = handle.hotshot.instance_state().threshold_decryption() else {
+        return;
+    };
+
+    handle.add_task(DecryptionTaskState::<TYPES, V>::new(
+        decryption,
+        Arc::clone(&handle.hotshot.memberships),
+        handle.public_key().clone(),
+        handle.private_key().clone(),
+        handle.hotshot.upgrade_lock.clone(),
+        handle.hotshot.external_event_stream.0.clone(),
+        handle.hotshot.id,
+    ));
+}
+
 /// Creates a monitor for shutdown events.
 ///
 /// # Returns
@@ -554,9 +972,14 @@ pub async fn add_network_message_and_request_receiver_tasks<
     let network = Arc::clone(&handle.network);
 
     add_network_message_task(handle, &network);
+    add_peer_score_task(handle);
 
     add_request_network_task(handle).await;
+    add_availability_sampling_task(handle).await;
     add_response_task(handle);
+    add_anti_entropy_task(handle).await;
+    add_catchup_task(handle).await;
+    add_heartbeat_task(handle).await;
 }
 
 /// Adds the `NetworkEventTaskState` tasks.
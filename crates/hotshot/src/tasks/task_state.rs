@@ -6,30 +6,48 @@
 
 use std::{
     collections::{BTreeMap, HashMap},
-    sync::{atomic::AtomicBool, Arc},
+    marker::PhantomData,
+    num::NonZeroU64,
+    sync::{
+        atomic::{AtomicBool, AtomicU64},
+        Arc,
+    },
 };
 
+use async_lock::Mutex;
 use async_trait::async_trait;
-use chrono::Utc;
 use hotshot_task_impls::{
+    anti_entropy::AntiEntropyTaskState,
+    availability::{AvailabilitySamplingTaskState, SamplingOutcomes},
     builder::BuilderClient,
+    catchup::CatchupTaskState,
     consensus::ConsensusTaskState,
     da::DaTaskState,
+    future_buffer::FutureEventBuffer,
+    heartbeat::HeartbeatTaskState,
+    proposal_dispersal::ProposalDispersalTaskState,
     quorum_proposal::QuorumProposalTaskState,
     quorum_proposal_recv::QuorumProposalRecvTaskState,
     quorum_vote::{drb_computations::DrbComputations, QuorumVoteTaskState},
+    recent_transactions::RecentTransactionFilter,
     request::NetworkRequestState,
     rewind::RewindTaskState,
+    shadow_consensus::ShadowConsensusTaskState,
     transactions::TransactionTaskState,
     upgrade::UpgradeTaskState,
     vid::VidTaskState,
     view_sync::ViewSyncTaskState,
 };
 use hotshot_types::{
+    catchup::CatchupRateLimiter,
     consensus::OuterConsensus,
+    heartbeat::PeerLiveness,
+    proposal_dispersal::ProposalChunkCollector,
     traits::{
         consensus_api::ConsensusApi,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        states::InstanceState,
+        storage::Storage,
     },
 };
 use tokio::spawn;
@@ -50,7 +68,7 @@ where
 
 #[async_trait]
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
-    for NetworkRequestState<TYPES, I>
+    for NetworkRequestState<TYPES, I, V>
 {
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
         Self {
@@ -64,6 +82,122 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             id: handle.hotshot.id,
             shutdown_flag: Arc::new(AtomicBool::new(false)),
             spawned_tasks: BTreeMap::new(),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for AvailabilitySamplingTaskState<TYPES, I, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        Self {
+            membership: Arc::clone(&handle.hotshot.memberships),
+            public_key: handle.public_key().clone(),
+            private_key: handle.private_key().clone(),
+            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            id: handle.hotshot.id,
+            outcomes: Arc::new(Mutex::new(SamplingOutcomes::new())),
+            shutdown_flag: Arc::new(AtomicBool::new(false)),
+            spawned_tasks: BTreeMap::new(),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            _pd: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for AntiEntropyTaskState<TYPES, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        Self {
+            consensus: OuterConsensus::new(handle.hotshot.consensus()),
+            membership: Arc::clone(&handle.hotshot.memberships),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            public_key: handle.public_key().clone(),
+            high_timeout_cert: None,
+            id: handle.hotshot.id,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for CatchupTaskState<TYPES, I, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        let config = handle.hotshot.config.catchup;
+
+        Self {
+            consensus: OuterConsensus::new(handle.hotshot.consensus()),
+            storage: Arc::clone(&handle.storage),
+            membership: Arc::clone(&handle.hotshot.memberships),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            public_key: handle.public_key().clone(),
+            config,
+            rate_limiter: CatchupRateLimiter::new(&config),
+            in_progress: None,
+            caught_up: None,
+            started: None,
+            id: handle.hotshot.id,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for ProposalDispersalTaskState<TYPES, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        let consensus = handle.hotshot.consensus();
+        let (cur_view, cur_epoch) = {
+            let consensus_reader = consensus.read().await;
+            (consensus_reader.cur_view(), consensus_reader.cur_epoch())
+        };
+
+        Self {
+            membership: Arc::clone(&handle.hotshot.memberships),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            public_key: handle.public_key().clone(),
+            private_key: handle.private_key().clone(),
+            min_bytes: handle.hotshot.config.proposal_dispersal_min_bytes,
+            collector: ProposalChunkCollector::default(),
+            cur_view,
+            cur_epoch,
+            id: handle.hotshot.id,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for HeartbeatTaskState<TYPES, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        let consensus = handle.hotshot.consensus();
+        let (cur_view, cur_epoch) = {
+            let consensus_reader = consensus.read().await;
+            (consensus_reader.cur_view(), consensus_reader.cur_epoch())
+        };
+
+        Self {
+            consensus: OuterConsensus::new(consensus),
+            membership: Arc::clone(&handle.hotshot.memberships),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            public_key: handle.public_key().clone(),
+            private_key: handle.private_key().clone(),
+            // The task is only added when heartbeats are enabled
+            interval: handle
+                .hotshot
+                .config
+                .heartbeat_interval
+                .unwrap_or(NonZeroU64::MIN),
+            liveness: PeerLiveness::default(),
+            cur_view,
+            cur_epoch,
+            id: handle.hotshot.id,
         }
     }
 }
@@ -92,6 +226,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             start_voting_time: handle.hotshot.config.start_voting_time,
             stop_voting_time: handle.hotshot.config.stop_voting_time,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            clock: handle.hotshot.instance_state().clock(),
         };
 
         #[cfg(feature = "example-upgrade")]
@@ -114,25 +249,30 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             start_voting_time: 0,
             stop_voting_time: u64::MAX,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            clock: handle.hotshot.instance_state().clock(),
         };
     }
 }
 
 #[async_trait]
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
-    for VidTaskState<TYPES, I>
+    for VidTaskState<TYPES, I, V>
 {
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
         Self {
             consensus: OuterConsensus::new(handle.hotshot.consensus()),
-            cur_view: handle.cur_view().await,
-            cur_epoch: handle.cur_epoch().await,
+            cur_view: AtomicU64::new(*handle.cur_view().await),
+            cur_epoch: AtomicU64::new(*handle.cur_epoch().await),
             network: Arc::clone(&handle.hotshot.network),
             membership: Arc::clone(&handle.hotshot.memberships),
             public_key: handle.public_key().clone(),
             private_key: handle.private_key().clone(),
             id: handle.hotshot.id,
             epoch_height: handle.epoch_height,
+            view_tracker: Arc::clone(&handle.hotshot.view_tracker),
+            full_replication: handle.hotshot.config.full_replication(),
+            work_scheduler: Arc::clone(&handle.hotshot.work_scheduler),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
         }
     }
 }
@@ -147,24 +287,38 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             output_event_stream: handle.hotshot.external_event_stream.0.clone(),
             membership: Arc::clone(&handle.hotshot.memberships),
             network: Arc::clone(&handle.hotshot.network),
-            cur_view: handle.cur_view().await,
-            cur_epoch: handle.cur_epoch().await,
-            vote_collectors: BTreeMap::default(),
+            cur_view: AtomicU64::new(*handle.cur_view().await),
+            cur_epoch: AtomicU64::new(*handle.cur_epoch().await),
+            vote_collectors: Mutex::new(BTreeMap::default()),
             public_key: handle.public_key().clone(),
             private_key: handle.private_key().clone(),
             id: handle.hotshot.id,
             storage: Arc::clone(&handle.storage),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            view_tracker: Arc::clone(&handle.hotshot.view_tracker),
+            full_replication: handle.hotshot.config.full_replication(),
+            work_scheduler: Arc::clone(&handle.hotshot.work_scheduler),
+            future_events: Mutex::new(FutureEventBuffer::default()),
         }
     }
 }
 
 #[async_trait]
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
-    for ViewSyncTaskState<TYPES, V>
+    for ViewSyncTaskState<TYPES, I, V>
 {
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
         let cur_view = handle.cur_view().await;
+        let restored_certificate = handle
+            .storage
+            .read()
+            .await
+            .load_view_sync_certificate()
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load the view sync certificate from storage: {e}");
+                None
+            });
 
         Self {
             cur_view,
@@ -178,10 +332,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             pre_commit_relay_map: HashMap::default().into(),
             commit_relay_map: HashMap::default().into(),
             finalize_relay_map: HashMap::default().into(),
-            view_sync_timeout: handle.hotshot.config.view_sync_timeout,
+            relay_timeout: handle.hotshot.config.view_sync_relay_timeout(),
             id: handle.hotshot.id,
             last_garbage_collected_view: TYPES::View::new(0),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            metrics: Arc::clone(&handle.hotshot.metrics),
+            storage: Arc::clone(&handle.storage),
+            restored_certificate,
+            view_tracker: Arc::clone(&handle.hotshot.view_tracker),
         }
     }
 }
@@ -220,6 +378,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
                 .fallback_builder_url
                 .clone(),
             epoch_height: handle.epoch_height,
+            recent_transactions: RecentTransactionFilter::default(),
+            high_priority_reserved_percent: handle.hotshot.config.high_priority_reserved_percent,
+            transaction_arrivals: HashMap::new(),
+            injected_block: Arc::clone(&handle.hotshot.injected_block),
+            preplanned_view: None,
+            preplanned_block: None,
+            governed_parameters: Arc::clone(&handle.hotshot.governed_parameters),
         }
     }
 }
@@ -250,6 +415,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
             consensus_metrics,
+            work_scheduler: Arc::clone(&handle.hotshot.work_scheduler),
+            governed_parameters: Arc::clone(&handle.hotshot.governed_parameters),
         }
     }
 }
@@ -277,6 +444,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
             highest_qc: handle.hotshot.consensus.read().await.high_qc().clone(),
+            governed_parameters: Arc::clone(&handle.hotshot.governed_parameters),
         }
     }
 }
@@ -302,6 +470,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             id: handle.hotshot.id,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
+            instance_state: handle.hotshot.instance_state(),
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for ShadowConsensusTaskState<TYPES, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        Self {
+            consensus: OuterConsensus::new(handle.hotshot.consensus()),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            public_key: handle.public_key().clone(),
+            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            divergences: 0,
+            id: handle.hotshot.id,
         }
     }
 }
@@ -323,7 +508,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             next_epoch_vote_collectors: BTreeMap::default(),
             timeout_vote_collectors: BTreeMap::default(),
             cur_view: handle.cur_view().await,
-            cur_view_time: Utc::now().timestamp(),
+            cur_view_time: handle.hotshot.instance_state().clock().unix_timestamp(),
             cur_epoch: handle.cur_epoch().await,
             output_event_stream: handle.hotshot.external_event_stream.0.clone(),
             timeout_task: spawn(async {}),
@@ -332,6 +517,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             id: handle.hotshot.id,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
+            future_events: FutureEventBuffer::default(),
+            view_tracker: Arc::clone(&handle.hotshot.view_tracker),
+            work_scheduler: Arc::clone(&handle.hotshot.work_scheduler),
+            governed_parameters: Arc::clone(&handle.hotshot.governed_parameters),
         }
     }
 }
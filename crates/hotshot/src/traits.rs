@@ -19,10 +19,12 @@ pub mod implementations {
     pub use super::networking::{
         combined_network::{CombinedNetworks, UnderlyingCombinedNetworks},
         libp2p_network::{
-            derive_libp2p_keypair, derive_libp2p_multiaddr, derive_libp2p_peer_id, GossipConfig,
-            Libp2pMetricsValue, Libp2pNetwork, PeerInfoVec, RequestResponseConfig,
+            derive_libp2p_keypair, derive_libp2p_multiaddr, derive_libp2p_peer_id,
+            CompressionConfig, GossipConfig, Libp2pMetricsValue, Libp2pNetwork, PeerInfoVec,
+            RelayConfig, RequestResponseConfig,
         },
         memory_network::{MasterMap, MemoryNetwork},
+        multiplexed_network::{ChainNetwork, SharedNetwork},
         push_cdn_network::{
             CdnMetricsValue, KeyPair, ProductionDef, PushCdnNetwork, TestingDef, Topic as CdnTopic,
             WrappedSignatureKey,
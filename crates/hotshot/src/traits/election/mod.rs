@@ -12,6 +12,9 @@ pub mod randomized_committee;
 /// quorum randomized every view, with configurable overlap
 pub mod randomized_committee_members;
 
+/// quorum voters verifiably sampled every view from a large committee
+pub mod sampled_committee;
+
 /// static (round robin) committee election
 pub mod static_committee;
 
@@ -5,13 +5,12 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    cmp::max,
     collections::{BTreeMap, BTreeSet},
     marker::PhantomData,
-    num::NonZeroU64,
 };
 
 use hotshot_types::{
+    stake_table::ThresholdConfig,
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeType},
@@ -47,6 +46,9 @@ pub struct RandomizedCommitteeMembers<T: NodeType, C: QuorumFilterConfig> {
     indexed_da_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
 
+    /// The threshold functions used to compute certificate thresholds by stake
+    thresholds: ThresholdConfig,
+
     /// Phantom
     _pd: PhantomData<C>,
 }
@@ -68,6 +70,11 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
 {
     type Error = utils::anytrace::Error;
 
+    /// Replace the threshold functions used by this committee
+    fn set_thresholds(&mut self, thresholds: ThresholdConfig) {
+        self.thresholds = thresholds;
+    }
+
     /// Create a new election
     fn new(
         committee_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
@@ -120,6 +127,7 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
             da_stake_table: da_members,
             indexed_stake_table,
             indexed_da_stake_table,
+            thresholds: ThresholdConfig::default(),
             _pd: PhantomData,
         }
     }
@@ -328,26 +336,30 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
     }
 
     /// Get the voting success threshold for the committee
-    fn success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
-        let len = self.total_nodes(epoch);
-        NonZeroU64::new(((len as u64 * 2) / 3) + 1).unwrap()
+    fn success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds
+            .success
+            .for_stake_table(&self.stake_table(epoch))
     }
 
     /// Get the voting success threshold for the committee
-    fn da_success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
-        let len = self.da_total_nodes(epoch);
-        NonZeroU64::new(((len as u64 * 2) / 3) + 1).unwrap()
+    fn da_success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds
+            .da_success
+            .for_stake_table(&self.da_stake_table(epoch))
     }
 
     /// Get the voting failure threshold for the committee
-    fn failure_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
-        let len = self.total_nodes(epoch);
-        NonZeroU64::new(((len as u64) / 3) + 1).unwrap()
+    fn failure_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds
+            .failure
+            .for_stake_table(&self.stake_table(epoch))
     }
 
     /// Get the voting upgrade threshold for the committee
-    fn upgrade_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
-        let len = self.total_nodes(epoch);
-        NonZeroU64::new(max((len as u64 * 9) / 10, ((len as u64 * 2) / 3) + 1)).unwrap()
+    fn upgrade_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds
+            .upgrade
+            .for_stake_table(&self.stake_table(epoch))
     }
 }
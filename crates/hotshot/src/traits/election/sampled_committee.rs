@@ -0,0 +1,478 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    num::NonZeroUsize,
+    sync::Arc,
+};
+
+use hotshot_types::{
+    stake_table::{total_stake, ThresholdConfig},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+    PeerConfig,
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+use primitive_types::U256;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use utils::anytrace::Result;
+
+/// Domain separator for the per-view sampling seed
+const SAMPLE_SEED_DOMAIN: &[u8] = b"HotShot sampled committee";
+
+/// Number of views whose samples are kept, so that votes and certificates for the views around
+/// the current one are checked without drawing their sample again
+const CACHED_SAMPLES: usize = 64;
+
+/// The quorum voters sampled for a view
+#[derive(Debug)]
+struct Sample<T: NodeType> {
+    /// Indices into the stake table of the sampled voters
+    indices: BTreeSet<usize>,
+    /// The sampled voters and their stake, in stake table order
+    stake_table: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+    /// Stake of the sampled voters needed for a quorum certificate
+    success_threshold: U256,
+}
+
+/// The samples of the most recent views they were needed for, by epoch and view
+struct SampleCache<T: NodeType>(Mutex<LruCache<(u64, u64), Arc<Sample<T>>>>);
+
+impl<T: NodeType> SampleCache<T> {
+    /// An empty cache
+    fn new() -> Self {
+        Self(Mutex::new(LruCache::new(
+            NonZeroUsize::new(CACHED_SAMPLES).unwrap_or(NonZeroUsize::MIN),
+        )))
+    }
+}
+
+impl<T: NodeType> Clone for SampleCache<T> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<T: NodeType> fmt::Debug for SampleCache<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SampleCache({} views)", self.0.lock().len())
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A committee where each view's quorum voters are a verifiable sample of `SAMPLE_SIZE` members
+/// of the full stake table.
+///
+/// The sample for a view is derived from `(beacon, epoch, view)` only, where the beacon is the
+/// latest one set for the view's epoch or an earlier one, so every node computes the same sample
+/// and can check a QC against it. Members are drawn one at a time with a chance proportional to
+/// their stake, so the stake of a sample reflects that of the committee: members with little stake
+/// are unlikely to be drawn in numbers, however many of them there are.
+///
+/// The beacon must be unpredictable, or the members sampled for a view could be known and
+/// targeted in advance. Consensus sets one per epoch from the decided DRB results, see
+/// [`hotshot_types::drb::sampling_beacon`]. Until one is set through
+/// [`Membership::set_sampling_beacon`], every view's quorum voters are the full committee, and a
+/// warning is logged for each view. Leadership, DA and non-quorum certificates (timeout, view
+/// sync, upgrade) always use the full committee.
+pub struct SampledCommittee<T: NodeType, const SAMPLE_SIZE: usize> {
+    /// The nodes eligible for leadership.
+    /// NOTE: This is currently a hack because the DA leader needs to be the quorum
+    /// leader but without voting rights.
+    eligible_leaders: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The nodes on the committee and their stake
+    stake_table: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The nodes on the da committee and their stake
+    da_stake_table: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The nodes on the committee and their stake, indexed by public key
+    indexed_stake_table:
+        BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The index of each node in the stake table, by public key
+    stake_table_indices: BTreeMap<T::SignatureKey, usize>,
+
+    /// The nodes on the da committee and their stake, indexed by public key
+    indexed_da_stake_table:
+        BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The threshold functions used to compute certificate thresholds by stake
+    thresholds: ThresholdConfig,
+
+    /// Randomness beacons mixed into the samples of the views of each epoch on, by the first
+    /// epoch they apply to
+    beacons: BTreeMap<T::Epoch, [u8; 32]>,
+
+    /// Samples of recent views
+    samples: SampleCache<T>,
+}
+
+impl<TYPES: NodeType, const SAMPLE_SIZE: usize> SampledCommittee<TYPES, SAMPLE_SIZE> {
+    /// The quorum voters sampled for `view` in `epoch`
+    fn sample(
+        &self,
+        view: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Arc<Sample<TYPES>> {
+        let key = (epoch.u64(), view.u64());
+        if let Some(sample) = self.samples.0.lock().get(&key) {
+            return Arc::clone(sample);
+        }
+
+        let sample = Arc::new(self.draw_sample(view, epoch));
+        self.samples.0.lock().put(key, Arc::clone(&sample));
+        sample
+    }
+
+    /// Draw the quorum voters for `view` in `epoch`
+    fn draw_sample(
+        &self,
+        view: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Sample<TYPES> {
+        let beacon = self.sampling_beacon(epoch);
+        if beacon.is_none() && self.stake_table.len() > SAMPLE_SIZE {
+            tracing::warn!(
+                "No sampling beacon for epoch {epoch:?}, using the full committee as the quorum of \
+                 view {view:?}"
+            );
+        }
+        let indices = match beacon {
+            Some(beacon) if self.stake_table.len() > SAMPLE_SIZE => {
+                let mut hasher = Sha256::new();
+                hasher.update(SAMPLE_SEED_DOMAIN);
+                hasher.update(beacon);
+                hasher.update(epoch.u64().to_le_bytes());
+                hasher.update(view.u64().to_le_bytes());
+                let mut rng = StdRng::from_seed(hasher.finalize().into());
+
+                // Draw members without replacement, each with a chance proportional to its stake
+                // among the members not drawn yet. Members without stake are never drawn.
+                let mut remaining: Vec<(usize, U256)> = self
+                    .stake_table
+                    .iter()
+                    .map(|entry| entry.stake())
+                    .enumerate()
+                    .collect();
+                let mut remaining_stake = total_stake::<TYPES::SignatureKey, _>(&self.stake_table);
+                let mut indices = BTreeSet::new();
+                while indices.len() < SAMPLE_SIZE && !remaining_stake.is_zero() {
+                    let mut point = U256(rng.gen::<[u64; 4]>()) % remaining_stake;
+                    let position = remaining
+                        .iter()
+                        .position(|(_, stake)| {
+                            if point < *stake {
+                                return true;
+                            }
+                            point -= *stake;
+                            false
+                        })
+                        .unwrap_or(remaining.len() - 1);
+                    let (index, stake) = remaining.swap_remove(position);
+                    remaining_stake -= stake;
+                    indices.insert(index);
+                }
+
+                indices
+            }
+            _ => (0..self.stake_table.len()).collect(),
+        };
+
+        let stake_table = indices
+            .iter()
+            .map(|index| self.stake_table[*index].clone())
+            .collect::<Vec<_>>();
+        let success_threshold = self.thresholds.success.for_stake_table(&stake_table);
+
+        Sample {
+            indices,
+            stake_table,
+            success_threshold,
+        }
+    }
+}
+
+impl<TYPES: NodeType, const SAMPLE_SIZE: usize> Membership<TYPES>
+    for SampledCommittee<TYPES, SAMPLE_SIZE>
+{
+    type Error = utils::anytrace::Error;
+
+    /// Replace the threshold functions used by this committee
+    fn set_thresholds(&mut self, thresholds: ThresholdConfig) {
+        self.thresholds = thresholds;
+        self.samples = SampleCache::new();
+    }
+
+    /// Set the randomness beacon the per-view samples of `from_epoch` on are drawn with
+    fn set_sampling_beacon(&mut self, from_epoch: TYPES::Epoch, beacon: [u8; 32]) {
+        self.beacons.insert(from_epoch, beacon);
+        self.samples = SampleCache::new();
+    }
+
+    /// The latest beacon set for `epoch` or an earlier one
+    fn sampling_beacon(&self, epoch: TYPES::Epoch) -> Option<[u8; 32]> {
+        self.beacons
+            .range(..=epoch)
+            .next_back()
+            .map(|(_, beacon)| *beacon)
+    }
+
+    /// Create a new election
+    fn new(
+        committee_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+        da_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+    ) -> Self {
+        // For each eligible leader, get the stake table entry
+        let eligible_leaders: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> =
+            committee_members
+                .iter()
+                .map(|member| member.stake_table_entry.clone())
+                .filter(|entry| entry.stake() > U256::zero())
+                .collect();
+
+        // For each member, get the stake table entry
+        let members: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> =
+            committee_members
+                .iter()
+                .map(|member| member.stake_table_entry.clone())
+                .filter(|entry| entry.stake() > U256::zero())
+                .collect();
+
+        // For each da member, get the stake table entry
+        let da_members: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> = da_members
+            .iter()
+            .map(|member| member.stake_table_entry.clone())
+            .filter(|entry| entry.stake() > U256::zero())
+            .collect();
+
+        // Index the stake table by public key
+        let indexed_stake_table: BTreeMap<
+            TYPES::SignatureKey,
+            <TYPES::SignatureKey as SignatureKey>::StakeTableEntry,
+        > = members
+            .iter()
+            .map(|entry| (TYPES::SignatureKey::public_key(entry), entry.clone()))
+            .collect();
+
+        let stake_table_indices = members
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (TYPES::SignatureKey::public_key(entry), index))
+            .collect();
+
+        // Index the stake table by public key
+        let indexed_da_stake_table: BTreeMap<
+            TYPES::SignatureKey,
+            <TYPES::SignatureKey as SignatureKey>::StakeTableEntry,
+        > = da_members
+            .iter()
+            .map(|entry| (TYPES::SignatureKey::public_key(entry), entry.clone()))
+            .collect();
+
+        Self {
+            eligible_leaders,
+            stake_table: members,
+            da_stake_table: da_members,
+            indexed_stake_table,
+            stake_table_indices,
+            indexed_da_stake_table,
+            thresholds: ThresholdConfig::default(),
+            beacons: BTreeMap::new(),
+            samples: SampleCache::new(),
+        }
+    }
+
+    /// Get the stake table for the current view
+    fn stake_table(
+        &self,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.stake_table.clone()
+    }
+
+    /// Get the stake table for the current view
+    fn da_stake_table(
+        &self,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.da_stake_table.clone()
+    }
+
+    /// Get all members of the committee for the current view
+    ///
+    /// NOTE: this is the full committee, not the view's sample, since every member still
+    /// receives a VID share.
+    fn committee_members(
+        &self,
+        _view_number: <TYPES as NodeType>::View,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.stake_table
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .collect()
+    }
+
+    /// Get all members of the committee for the current view
+    fn da_committee_members(
+        &self,
+        _view_number: <TYPES as NodeType>::View,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.da_stake_table
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .collect()
+    }
+
+    /// Get all eligible leaders of the committee for the current view
+    fn committee_leaders(
+        &self,
+        _view_number: <TYPES as NodeType>::View,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.eligible_leaders
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .collect()
+    }
+
+    /// Get the stake table entry for a public key
+    fn stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        // Only return the stake if it is above zero
+        self.indexed_stake_table.get(pub_key).cloned()
+    }
+
+    /// Get the DA stake table entry for a public key
+    fn da_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        // Only return the stake if it is above zero
+        self.indexed_da_stake_table.get(pub_key).cloned()
+    }
+
+    /// Check if a node has stake in the committee
+    fn has_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> bool {
+        self.indexed_stake_table
+            .get(pub_key)
+            .is_some_and(|x| x.stake() > U256::zero())
+    }
+
+    /// Check if a node has stake in the committee
+    fn has_da_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> bool {
+        self.indexed_da_stake_table
+            .get(pub_key)
+            .is_some_and(|x| x.stake() > U256::zero())
+    }
+
+    /// Index the vector of public keys with the current view number
+    fn lookup_leader(
+        &self,
+        view_number: TYPES::View,
+        _epoch: <TYPES as NodeType>::Epoch,
+    ) -> Result<TYPES::SignatureKey> {
+        #[allow(clippy::cast_possible_truncation)]
+        let index = *view_number as usize % self.eligible_leaders.len();
+        let res = self.eligible_leaders[index].clone();
+        Ok(TYPES::SignatureKey::public_key(&res))
+    }
+
+    /// Get the total number of nodes in the committee
+    fn total_nodes(&self, _epoch: <TYPES as NodeType>::Epoch) -> usize {
+        self.stake_table.len()
+    }
+
+    /// Get the total number of DA nodes in the committee
+    fn da_total_nodes(&self, _epoch: <TYPES as NodeType>::Epoch) -> usize {
+        self.da_stake_table.len()
+    }
+
+    /// Get the voting success threshold for the committee
+    fn success_threshold(&self, _epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds.success.for_stake_table(&self.stake_table)
+    }
+
+    /// Get the voting success threshold for the committee
+    fn da_success_threshold(&self, _epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds
+            .da_success
+            .for_stake_table(&self.da_stake_table)
+    }
+
+    /// Get the voting failure threshold for the committee
+    fn failure_threshold(&self, _epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds.failure.for_stake_table(&self.stake_table)
+    }
+
+    /// Get the voting upgrade threshold for the committee
+    fn upgrade_threshold(&self, _epoch: <TYPES as NodeType>::Epoch) -> U256 {
+        self.thresholds.upgrade.for_stake_table(&self.stake_table)
+    }
+
+    /// Get the quorum voters sampled for the given view
+    fn sampled_stake_table(
+        &self,
+        view: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.sample(view, epoch).stake_table.clone()
+    }
+
+    /// Get the stake table entry for a public key, if it was sampled for the given view
+    fn sampled_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        view: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        let index = *self.stake_table_indices.get(pub_key)?;
+        self.sample(view, epoch)
+            .indices
+            .contains(&index)
+            .then(|| self.stake_table[index].clone())
+    }
+
+    /// Get the number of quorum voters sampled for the given view
+    fn sampled_total_nodes(
+        &self,
+        view: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> usize {
+        self.sample(view, epoch).indices.len()
+    }
+
+    /// Get the voting success threshold over the quorum voters sampled for the given view
+    fn sampled_success_threshold(
+        &self,
+        view: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> U256 {
+        self.sample(view, epoch).success_threshold
+    }
+}
@@ -4,9 +4,10 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{cmp::max, collections::BTreeMap, num::NonZeroU64};
+use std::{collections::BTreeMap, num::NonZeroUsize};
 
 use hotshot_types::{
+    stake_table::ThresholdConfig,
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -31,6 +32,10 @@ pub struct StaticCommittee<T: NodeType> {
     /// The nodes on the committee and their stake
     da_stake_table: Vec<<T::SignatureKey as SignatureKey>::StakeTableEntry>,
 
+    /// The number of DA nodes, from the front of `da_stake_table`, on the DA committee from each
+    /// view on, or `None` for all of them. Views before the first entry have all of them.
+    da_committee_sizes: BTreeMap<T::View, Option<NonZeroUsize>>,
+
     /// The nodes on the committee and their stake, indexed by public key
     indexed_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
@@ -38,11 +43,24 @@ pub struct StaticCommittee<T: NodeType> {
     /// The nodes on the committee and their stake, indexed by public key
     indexed_da_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The threshold functions used to compute certificate thresholds by stake
+    thresholds: ThresholdConfig,
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
     type Error = utils::anytrace::Error;
 
+    /// Replace the threshold functions used by this committee
+    fn set_thresholds(&mut self, thresholds: ThresholdConfig) {
+        self.thresholds = thresholds;
+    }
+
+    /// Limit the DA committee to the first `size` DA nodes from `from_view` on
+    fn set_da_committee_size(&mut self, from_view: TYPES::View, size: Option<NonZeroUsize>) {
+        self.da_committee_sizes.insert(from_view, size);
+    }
+
     /// Create a new election
     fn new(
         committee_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
@@ -93,8 +111,10 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
             eligible_leaders,
             stake_table: members,
             da_stake_table: da_members,
+            da_committee_sizes: BTreeMap::new(),
             indexed_stake_table,
             indexed_da_stake_table,
+            thresholds: ThresholdConfig::default(),
         }
     }
 
@@ -126,13 +146,13 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
             .collect()
     }
 
-    /// Get all members of the committee for the current view
+    /// Get all members of the DA committee for the view
     fn da_committee_members(
         &self,
-        _view_number: <TYPES as NodeType>::View,
+        view_number: <TYPES as NodeType>::View,
         _epoch: <TYPES as NodeType>::Epoch,
     ) -> std::collections::BTreeSet<<TYPES as NodeType>::SignatureKey> {
-        self.da_stake_table
+        self.da_committee_at(view_number)
             .iter()
             .map(TYPES::SignatureKey::public_key)
             .collect()
@@ -215,23 +235,69 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
     }
 
     /// Get the voting success threshold for the committee
-    fn success_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        NonZeroU64::new(((self.stake_table.len() as u64 * 2) / 3) + 1).unwrap()
+    fn success_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds.success.for_stake_table(&self.stake_table)
     }
 
     /// Get the voting success threshold for the committee
-    fn da_success_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        NonZeroU64::new(((self.da_stake_table.len() as u64 * 2) / 3) + 1).unwrap()
+    fn da_success_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds
+            .da_success
+            .for_stake_table(&self.da_stake_table)
     }
 
     /// Get the voting failure threshold for the committee
-    fn failure_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        NonZeroU64::new(((self.stake_table.len() as u64) / 3) + 1).unwrap()
+    fn failure_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds.failure.for_stake_table(&self.stake_table)
     }
 
     /// Get the voting upgrade threshold for the committee
-    fn upgrade_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        let len = self.stake_table.len();
-        NonZeroU64::new(max((len as u64 * 9) / 10, ((len as u64 * 2) / 3) + 1)).unwrap()
+    fn upgrade_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds.upgrade.for_stake_table(&self.stake_table)
+    }
+
+    /// Get the DA committee for the view
+    fn da_stake_table_at(
+        &self,
+        view: TYPES::View,
+        _epoch: TYPES::Epoch,
+    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.da_committee_at(view).to_vec()
+    }
+
+    /// Get the DA stake table entry for a public key on the DA committee for the view
+    fn da_stake_at(
+        &self,
+        pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
+        _epoch: TYPES::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.da_committee_at(view)
+            .iter()
+            .find(|entry| TYPES::SignatureKey::public_key(entry) == *pub_key)
+            .cloned()
+    }
+
+    /// Get the DA voting success threshold for the DA committee for the view
+    fn da_success_threshold_at(&self, view: TYPES::View, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds
+            .da_success
+            .for_stake_table(self.da_committee_at(view))
+    }
+}
+
+impl<TYPES: NodeType> StaticCommittee<TYPES> {
+    /// The DA nodes on the DA committee for `view`
+    fn da_committee_at(
+        &self,
+        view: TYPES::View,
+    ) -> &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry] {
+        let size = self
+            .da_committee_sizes
+            .range(..=view)
+            .next_back()
+            .and_then(|(_, size)| *size)
+            .map_or(self.da_stake_table.len(), NonZeroUsize::get);
+        &self.da_stake_table[..size.min(self.da_stake_table.len())]
     }
 }
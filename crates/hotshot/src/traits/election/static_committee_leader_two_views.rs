@@ -4,9 +4,10 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{collections::BTreeMap, num::NonZeroU64};
+use std::collections::BTreeMap;
 
 use hotshot_types::{
+    stake_table::{ThresholdConfig, ThresholdFunction},
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -39,11 +40,19 @@ pub struct StaticCommitteeLeaderForTwoViews<T: NodeType> {
     /// The nodes on the committee and their stake, indexed by public key
     indexed_da_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The threshold functions used to compute certificate thresholds by stake
+    thresholds: ThresholdConfig,
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYPES> {
     type Error = utils::anytrace::Error;
 
+    /// Replace the threshold functions used by this committee
+    fn set_thresholds(&mut self, thresholds: ThresholdConfig) {
+        self.thresholds = thresholds;
+    }
+
     /// Create a new election
     fn new(
         committee_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
@@ -96,6 +105,10 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
             da_stake_table: da_members,
             indexed_stake_table,
             indexed_da_stake_table,
+            thresholds: ThresholdConfig {
+                upgrade: ThresholdFunction::MoreThanNineTenths,
+                ..ThresholdConfig::default()
+            },
         }
     }
 
@@ -217,22 +230,24 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
     }
 
     /// Get the voting success threshold for the committee
-    fn success_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        NonZeroU64::new(((self.stake_table.len() as u64 * 2) / 3) + 1).unwrap()
+    fn success_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds.success.for_stake_table(&self.stake_table)
     }
 
     /// Get the voting success threshold for the committee
-    fn da_success_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        NonZeroU64::new(((self.da_stake_table.len() as u64 * 2) / 3) + 1).unwrap()
+    fn da_success_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds
+            .da_success
+            .for_stake_table(&self.da_stake_table)
     }
 
     /// Get the voting failure threshold for the committee
-    fn failure_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        NonZeroU64::new(((self.stake_table.len() as u64) / 3) + 1).unwrap()
+    fn failure_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds.failure.for_stake_table(&self.stake_table)
     }
 
     /// Get the voting upgrade threshold for the committee
-    fn upgrade_threshold(&self, _epoch: TYPES::Epoch) -> NonZeroU64 {
-        NonZeroU64::new(((self.stake_table.len() as u64 * 9) / 10) + 1).unwrap()
+    fn upgrade_threshold(&self, _epoch: TYPES::Epoch) -> U256 {
+        self.thresholds.upgrade.for_stake_table(&self.stake_table)
     }
 }
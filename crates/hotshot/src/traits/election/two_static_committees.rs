@@ -4,9 +4,10 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{cmp::max, collections::BTreeMap, num::NonZeroU64};
+use std::collections::BTreeMap;
 
 use hotshot_types::{
+    stake_table::ThresholdConfig,
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -60,6 +61,16 @@ pub struct TwoStaticCommittees<T: NodeType> {
 
     /// The nodes on the committee and their stake, indexed by public key
     indexed_da_stake_table: IndexedStakeTables<T>,
+
+    /// The threshold functions used to compute certificate thresholds by stake
+    thresholds: ThresholdConfig,
+}
+
+impl<TYPES: NodeType> TwoStaticCommittees<TYPES> {
+    /// Replace the threshold functions used by this committee
+    pub fn set_thresholds(&mut self, thresholds: ThresholdConfig) {
+        self.thresholds = thresholds;
+    }
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
@@ -172,6 +183,7 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
             da_stake_table: (da_members1, da_members2),
             indexed_stake_table: (indexed_stake_table1, indexed_stake_table2),
             indexed_da_stake_table: (indexed_da_stake_table1, indexed_da_stake_table2),
+            thresholds: ThresholdConfig::default(),
         }
     }
 
@@ -366,46 +378,42 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
     }
 
     /// Get the voting success threshold for the committee
-    fn success_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64 {
+    fn success_threshold(&self, epoch: TYPES::Epoch) -> U256 {
         if *epoch != 0 && *epoch % 2 == 0 {
-            NonZeroU64::new(((self.stake_table.0.len() as u64 * 2) / 3) + 1).unwrap()
+            self.thresholds.success.for_stake_table(&self.stake_table.0)
         } else {
-            NonZeroU64::new(((self.stake_table.1.len() as u64 * 2) / 3) + 1).unwrap()
+            self.thresholds.success.for_stake_table(&self.stake_table.1)
         }
     }
 
     /// Get the voting success threshold for the committee
-    fn da_success_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64 {
+    fn da_success_threshold(&self, epoch: TYPES::Epoch) -> U256 {
         if *epoch != 0 && *epoch % 2 == 0 {
-            NonZeroU64::new(((self.da_stake_table.0.len() as u64 * 2) / 3) + 1).unwrap()
+            self.thresholds
+                .da_success
+                .for_stake_table(&self.da_stake_table.0)
         } else {
-            NonZeroU64::new(((self.da_stake_table.1.len() as u64 * 2) / 3) + 1).unwrap()
+            self.thresholds
+                .da_success
+                .for_stake_table(&self.da_stake_table.1)
         }
     }
 
     /// Get the voting failure threshold for the committee
-    fn failure_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64 {
+    fn failure_threshold(&self, epoch: TYPES::Epoch) -> U256 {
         if *epoch != 0 && *epoch % 2 == 0 {
-            NonZeroU64::new(((self.stake_table.0.len() as u64) / 3) + 1).unwrap()
+            self.thresholds.failure.for_stake_table(&self.stake_table.0)
         } else {
-            NonZeroU64::new(((self.stake_table.1.len() as u64) / 3) + 1).unwrap()
+            self.thresholds.failure.for_stake_table(&self.stake_table.1)
         }
     }
 
     /// Get the voting upgrade threshold for the committee
-    fn upgrade_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64 {
+    fn upgrade_threshold(&self, epoch: TYPES::Epoch) -> U256 {
         if *epoch != 0 && *epoch % 2 == 0 {
-            NonZeroU64::new(max(
-                (self.stake_table.0.len() as u64 * 9) / 10,
-                ((self.stake_table.0.len() as u64 * 2) / 3) + 1,
-            ))
-            .unwrap()
+            self.thresholds.upgrade.for_stake_table(&self.stake_table.0)
         } else {
-            NonZeroU64::new(max(
-                (self.stake_table.1.len() as u64 * 9) / 10,
-                ((self.stake_table.1.len() as u64 * 2) / 3) + 1,
-            ))
-            .unwrap()
+            self.thresholds.upgrade.for_stake_table(&self.stake_table.1)
         }
     }
 }
@@ -10,10 +10,12 @@
 //! trait. Currently this includes
 //! - [`MemoryNetwork`](memory_network::MemoryNetwork), an in memory testing-only implementation
 //! - [`Libp2pNetwork`](libp2p_network::Libp2pNetwork), a production-ready networking implementation built on top of libp2p-rs.
+//! - [`ChainNetwork`](multiplexed_network::ChainNetwork), one of several chains sharing another network
 
 pub mod combined_network;
 pub mod libp2p_network;
 pub mod memory_network;
+pub mod multiplexed_network;
 /// The Push CDN network
 pub mod push_cdn_network;
 
@@ -37,6 +37,7 @@ use hotshot_types::{
         network::{BroadcastDelay, ConnectedNetwork, Topic},
         node_implementation::NodeType,
     },
+    wire_codec::WireFormat,
     BoxSyncFuture,
 };
 use lru::LruCache;
@@ -475,6 +476,11 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         self.secondary().queue_node_lookup(view_number, pk)
     }
 
+    fn warm_up_connections(&self, peers: Vec<TYPES::SignatureKey>) {
+        self.primary().warm_up_connections(peers.clone());
+        self.secondary().warm_up_connections(peers);
+    }
+
     async fn update_view<'a, T>(
         &'a self,
         view: u64,
@@ -499,9 +505,11 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
                 }
             }
         });
-        // Run `update_view` logic for the libp2p network
-        self.networks
-            .1
+        // Run `update_view` logic for both networks
+        self.primary()
+            .update_view::<T>(view, epoch, Arc::clone(&membership))
+            .await;
+        self.secondary()
             .update_view::<T>(view, epoch, membership)
             .await;
     }
@@ -509,4 +517,9 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
     fn is_primary_down(&self) -> bool {
         self.primary_down.load(Ordering::Relaxed)
     }
+
+    /// Both networks carry the same serialized messages, so they must use the same format
+    fn wire_format(&self) -> WireFormat {
+        self.primary().wire_format()
+    }
 }
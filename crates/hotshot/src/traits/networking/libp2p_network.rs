@@ -8,7 +8,7 @@
 //! This module provides a libp2p based networking implementation where each node in the
 //! network forms a tcp or udp connection to a subset of other nodes in the network
 #[cfg(feature = "hotshot-testing")]
-use std::str::FromStr;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::{
     cmp::min,
     collections::{BTreeSet, HashSet},
@@ -33,7 +33,7 @@ use hotshot_types::traits::network::{
 };
 use hotshot_types::{
     boxed_sync,
-    constants::LOOK_AHEAD,
+    constants::{LOOK_AHEAD, NETWORK_INBOUND_CHANNEL_SIZE},
     data::ViewNumber,
     network::NetworkConfig,
     traits::{
@@ -43,16 +43,20 @@ use hotshot_types::{
         node_implementation::{ConsensusTime, NodeType},
         signature_key::{PrivateSignatureKey, SignatureKey},
     },
+    wire_codec::WireFormat,
     BoxSyncFuture,
 };
 use libp2p_identity::{
     ed25519::{self, SecretKey},
     Keypair, PeerId,
 };
-pub use libp2p_networking::network::{GossipConfig, RequestResponseConfig};
+pub use libp2p_networking::network::{
+    compression::CompressionConfig, GossipConfig, RelayConfig, RequestResponseConfig,
+};
 use libp2p_networking::{
     network::{
         behaviours::dht::record::{Namespace, RecordKey, RecordValue},
+        compression::{decompress, Compression},
         spawn_network_node,
         transport::construct_auth_message,
         NetworkEvent::{self, DirectRequest, DirectResponse, GossipMsg},
@@ -84,6 +88,12 @@ pub struct Libp2pMetricsValue {
     pub num_failed_messages: Box<dyn Counter>,
     /// Whether or not the network is considered ready
     pub is_ready: Box<dyn Gauge>,
+    /// The number of bytes of the messages we sent, before compression
+    pub raw_bytes_sent: Box<dyn Counter>,
+    /// The number of bytes of the messages we sent, after compression
+    pub compressed_bytes_sent: Box<dyn Counter>,
+    /// The number of peers we are connected to through a relay
+    pub num_relayed_peers: Box<dyn Gauge>,
 }
 
 impl Libp2pMetricsValue {
@@ -97,6 +107,9 @@ impl Libp2pMetricsValue {
             num_connected_peers: subgroup.create_gauge("num_connected_peers".into(), None),
             num_failed_messages: subgroup.create_counter("num_failed_messages".into(), None),
             is_ready: subgroup.create_gauge("is_ready".into(), None),
+            raw_bytes_sent: subgroup.create_counter("raw_bytes_sent".into(), None),
+            compressed_bytes_sent: subgroup.create_counter("compressed_bytes_sent".into(), None),
+            num_relayed_peers: subgroup.create_gauge("num_relayed_peers".into(), None),
         }
     }
 }
@@ -174,6 +187,8 @@ struct Libp2pNetworkInner<T: NodeType> {
     reliability_config: Option<Box<dyn NetworkReliability>>,
     /// Killswitch sender
     kill_switch: Sender<()>,
+    /// The encoding of messages sent over the network
+    wire_format: WireFormat,
 }
 
 /// Networking implementation that uses libp2p
@@ -195,7 +210,6 @@ impl<T: NodeType> TestableNetworkingImplementation<T> for Libp2pNetwork<T> {
     /// - An invalid configuration
     ///   (probably an issue with the defaults of this function)
     /// - An inability to spin up the replica's network
-    #[allow(clippy::panic, clippy::too_many_lines)]
     fn generator(
         expected_node_count: usize,
         num_bootstrap: usize,
@@ -203,11 +217,49 @@ impl<T: NodeType> TestableNetworkingImplementation<T> for Libp2pNetwork<T> {
         da_committee_size: usize,
         reliability_config: Option<Box<dyn NetworkReliability>>,
         _secondary_network_delay: Duration,
+    ) -> AsyncGenerator<Arc<Self>> {
+        Self::generator_with_config(
+            expected_node_count,
+            num_bootstrap,
+            da_committee_size,
+            reliability_config,
+            GossipConfig::default(),
+            vec![Ipv4Addr::LOCALHOST.into()],
+        )
+    }
+
+    fn in_flight_message_count(&self) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(feature = "hotshot-testing")]
+impl<T: NodeType> Libp2pNetwork<T> {
+    /// Like [`TestableNetworkingImplementation::generator`], but with the given gossipsub
+    /// configuration instead of the default one, and with every node listening on each of
+    /// `loopback_ips`, which may be of either address family
+    ///
+    /// # Panics
+    /// Returned function may panic either:
+    /// - An invalid configuration
+    /// - An inability to spin up the replica's network
+    #[allow(clippy::panic, clippy::too_many_lines)]
+    pub fn generator_with_config(
+        expected_node_count: usize,
+        num_bootstrap: usize,
+        da_committee_size: usize,
+        reliability_config: Option<Box<dyn NetworkReliability>>,
+        gossip_config: GossipConfig,
+        loopback_ips: Vec<IpAddr>,
     ) -> AsyncGenerator<Arc<Self>> {
         assert!(
             da_committee_size <= expected_node_count,
             "DA committee size must be less than or equal to total # nodes"
         );
+        assert!(
+            !loopback_ips.is_empty(),
+            "Nodes must listen on at least one address"
+        );
         let bootstrap_addrs: PeerInfoVec = Arc::default();
         let node_ids: Arc<RwLock<HashSet<u64>>> = Arc::default();
 
@@ -224,8 +276,9 @@ impl<T: NodeType> TestableNetworkingImplementation<T> for Libp2pNetwork<T> {
                 // pick a free, unused UDP port for testing
                 let port = portpicker::pick_unused_port().expect("Could not find an open port");
 
-                let addr =
-                    Multiaddr::from_str(&format!("/ip4/127.0.0.1/udp/{port}/quic-v1")).unwrap();
+                let mut addrs = loopback_ips.iter().map(|ip| {
+                    derive_libp2p_multiaddr(&SocketAddr::new(*ip, port).to_string()).unwrap()
+                });
 
                 // We assign node's public key and stake value rather than read from config file since it's a test
                 let privkey = T::SignatureKey::generated_from_seed_indexed([0u8; 32], node_id).1;
@@ -251,9 +304,11 @@ impl<T: NodeType> TestableNetworkingImplementation<T> for Libp2pNetwork<T> {
                 let config = NetworkNodeConfigBuilder::default()
                     .keypair(libp2p_keypair)
                     .replication_factor(replication_factor)
-                    .bind_address(Some(addr))
+                    .bind_address(addrs.next())
+                    .additional_bind_addresses(addrs.collect())
                     .to_connect_addrs(HashSet::default())
                     .republication_interval(None)
+                    .gossip_config(gossip_config.clone())
                     .build()
                     .expect("Failed to build network node config");
 
@@ -293,10 +348,6 @@ impl<T: NodeType> TestableNetworkingImplementation<T> for Libp2pNetwork<T> {
             }
         })
     }
-
-    fn in_flight_message_count(&self) -> Option<usize> {
-        None
-    }
 }
 
 /// Derive a Libp2p keypair from a given private key
@@ -344,8 +395,12 @@ pub fn derive_libp2p_multiaddr(addr: &String) -> anyhow::Result<Multiaddr> {
         None => return Err(anyhow!("Invalid address format, no port supplied")),
     };
 
-    // Try parsing the host as an IP address
-    let ip = host.parse::<IpAddr>();
+    // Try parsing the host as an IP address, which is in brackets if it is an IPv6 address
+    let ip = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+        .parse::<IpAddr>();
 
     // Conditionally build the multiaddr string
     let multiaddr_string = match ip {
@@ -383,7 +438,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
     /// and various other configuration-specific values.
     ///
     /// # Errors
-    /// If we are unable to parse a Multiaddress
+    /// If we are unable to parse a Multiaddress, or the configured channel capacities are invalid
     ///
     /// # Panics
     /// If we are unable to calculate the replication factor
@@ -393,11 +448,19 @@ impl<T: NodeType> Libp2pNetwork<T> {
         membership: Arc<RwLock<T::Membership>>,
         gossip_config: GossipConfig,
         request_response_config: RequestResponseConfig,
+        compression_config: CompressionConfig,
         bind_address: Multiaddr,
+        additional_bind_addresses: Vec<Multiaddr>,
         pub_key: &T::SignatureKey,
         priv_key: &<T::SignatureKey as SignatureKey>::PrivateKey,
         metrics: Libp2pMetricsValue,
     ) -> anyhow::Result<Self> {
+        config
+            .config
+            .channel_capacities
+            .validate()
+            .map_err(|e| anyhow!("Invalid channel capacities: {e}"))?;
+
         // Try to take our Libp2p config from our broader network config
         let libp2p_config = config
             .libp2p_config
@@ -413,6 +476,22 @@ impl<T: NodeType> Libp2pNetwork<T> {
         // Set the gossip configuration
         config_builder.gossip_config(gossip_config.clone());
         config_builder.request_response_config(request_response_config);
+        config_builder.compression(compression_config);
+        config_builder.wire_format(config.wire_format);
+        config_builder
+            .inbound_channel_capacity(Some(config.config.channel_capacities.network_inbound));
+
+        // Reserve circuits on the designated relays if we turn out to be behind a NAT, or serve
+        // as a relay if we are one of them
+        let peer_id = keypair.public().to_peer_id();
+        config_builder.relay(RelayConfig {
+            serve: libp2p_config
+                .relay_nodes
+                .iter()
+                .any(|(relay, _)| *relay == peer_id),
+            relay_nodes: libp2p_config.relay_nodes,
+            ..RelayConfig::default()
+        });
 
         // Construct the auth message
         let auth_message =
@@ -447,7 +526,8 @@ impl<T: NodeType> Libp2pNetwork<T> {
         config_builder
             .keypair(keypair)
             .replication_factor(replication_factor)
-            .bind_address(Some(bind_address.clone()));
+            .bind_address(Some(bind_address.clone()))
+            .additional_bind_addresses(additional_bind_addresses);
 
         // Choose `mesh_n` random nodes to connect to for bootstrap
         let bootstrap_nodes = libp2p_config
@@ -522,10 +602,14 @@ impl<T: NodeType> Libp2pNetwork<T> {
             .await
             .map_err(|e| NetworkError::ConfigError(format!("failed to spawn network node: {e}")))?;
 
-        // Add our own address to the bootstrap addresses
-        let addr = network_handle.listen_addr();
+        // Add our own addresses to the bootstrap addresses
         let pid = network_handle.peer_id();
-        bootstrap_addrs.write().await.push((pid, addr));
+        bootstrap_addrs.write().await.extend(
+            network_handle
+                .listen_addrs()
+                .iter()
+                .map(|addr| (pid, addr.clone())),
+        );
 
         let mut pubkey_pid_map = BiHashMap::new();
         pubkey_pid_map.insert(pk.clone(), network_handle.peer_id());
@@ -535,7 +619,11 @@ impl<T: NodeType> Libp2pNetwork<T> {
 
         // unbounded channels may not be the best choice (spammed?)
         // if bounded figure out a way to log dropped msgs
-        let (sender, receiver) = channel(1000);
+        let (sender, receiver) = channel(
+            config
+                .inbound_channel_capacity
+                .unwrap_or(NETWORK_INBOUND_CHANNEL_SIZE),
+        );
         let (node_lookup_send, node_lookup_recv) = channel(10);
         let (kill_tx, kill_rx) = channel(1);
         rx.set_kill_switch(kill_rx);
@@ -561,6 +649,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
                 #[cfg(feature = "hotshot-testing")]
                 reliability_config,
                 kill_switch: kill_tx,
+                wire_format: config.wire_format,
             }),
         };
 
@@ -660,6 +749,20 @@ impl<T: NodeType> Libp2pNetwork<T> {
         });
     }
 
+    /// Compress `message` with `algorithm` if it is large enough, and count the bytes sent
+    fn compress(&self, message: Vec<u8>, algorithm: Compression) -> Vec<u8> {
+        let raw_len = message.len();
+        let message = self
+            .inner
+            .handle
+            .peer_compression()
+            .compress(message, algorithm);
+        self.inner.metrics.raw_bytes_sent.add(raw_len);
+        self.inner.metrics.compressed_bytes_sent.add(message.len());
+
+        message
+    }
+
     /// Handle events
     fn handle_recvd_events(
         &self,
@@ -668,12 +771,12 @@ impl<T: NodeType> Libp2pNetwork<T> {
     ) -> Result<(), NetworkError> {
         match msg {
             GossipMsg(msg) => {
-                sender.try_send(msg).map_err(|err| {
+                sender.try_send(decompress(msg)?).map_err(|err| {
                     NetworkError::ChannelSendError(format!("failed to send gossip message: {err}"))
                 })?;
             }
             DirectRequest(msg, _pid, chan) => {
-                sender.try_send(msg).map_err(|err| {
+                sender.try_send(decompress(msg)?).map_err(|err| {
                     NetworkError::ChannelSendError(format!(
                         "failed to send direct request message: {err}"
                     ))
@@ -698,7 +801,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
             NetworkEvent::IsBootstrapped => {
                 error!("handle_recvd_events received `NetworkEvent::IsBootstrapped`, which should be impossible.");
             }
-            NetworkEvent::ConnectedPeersUpdate(_) => {}
+            NetworkEvent::ConnectedPeersUpdate(_) | NetworkEvent::RelayedPeersUpdate(_) => {}
         }
         Ok::<(), NetworkError>(())
     }
@@ -734,6 +837,9 @@ impl<T: NodeType> Libp2pNetwork<T> {
                             NetworkEvent::ConnectedPeersUpdate(num_peers) => {
                                 handle.inner.metrics.num_connected_peers.set(num_peers);
                             }
+                            NetworkEvent::RelayedPeersUpdate(num_peers) => {
+                                handle.inner.metrics.num_relayed_peers.set(num_peers);
+                            }
                         }
                     }
 
@@ -799,6 +905,9 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             })?;
         }
 
+        let algorithm = self.inner.handle.peer_compression().for_broadcast();
+        let message = self.compress(message, algorithm);
+
         // NOTE: metrics is threadsafe, so clone is fine (and lightweight)
         #[cfg(feature = "hotshot-testing")]
         {
@@ -903,6 +1012,9 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             }
         };
 
+        let algorithm = self.inner.handle.peer_compression().for_peer(&pid);
+        let message = self.compress(message, algorithm);
+
         #[cfg(feature = "hotshot-testing")]
         {
             let metrics = self.inner.metrics.clone();
@@ -954,6 +1066,10 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
         Ok(result)
     }
 
+    fn wire_format(&self) -> WireFormat {
+        self.inner.wire_format
+    }
+
     #[instrument(name = "Libp2pNetwork::queue_node_lookup", skip_all)]
     #[allow(clippy::type_complexity)]
     fn queue_node_lookup(
@@ -966,6 +1082,35 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             .try_send(Some((view_number, pk)))
     }
 
+    /// Looks up the libp2p peer ID of each of `peers` in the DHT, and dials the ones we aren't
+    /// already connected to
+    #[instrument(name = "Libp2pNetwork::warm_up_connections", skip_all)]
+    fn warm_up_connections(&self, peers: Vec<T::SignatureKey>) {
+        let handle = Arc::clone(&self.inner.handle);
+        let dht_timeout = self.inner.dht_timeout;
+        let peers = peers
+            .into_iter()
+            .filter(|pk| *pk != self.inner.pk)
+            .collect::<Vec<_>>();
+
+        spawn(async move {
+            join_all(peers.into_iter().map(|pk| {
+                let handle = Arc::clone(&handle);
+                async move {
+                    match handle.lookup_node(&pk.to_bytes(), dht_timeout).await {
+                        Ok(pid) => {
+                            if let Err(err) = handle.dial(pid) {
+                                warn!("Failed to dial {:?}: {}", pk, err);
+                            }
+                        }
+                        Err(err) => warn!("Failed to look up {:?} to dial it: {}", pk, err),
+                    }
+                }
+            }))
+            .await;
+        });
+    }
+
     /// The libp2p view update is a special operation intrinsic to its internal behavior.
     ///
     /// Libp2p needs to do a lookup because a libp2p address is not related to
@@ -1040,6 +1185,19 @@ mod test {
             );
         }
 
+        /// Test derivation of an IPv6 address in brackets, as socket addresses are written
+        #[test]
+        fn test_v6_bracketed() {
+            for (addr, expected) in [
+                ("[::1]:8080", "/ip6/::1/udp/8080/quic-v1"),
+                ("[::]:8080", "/ip6/::/udp/8080/quic-v1"),
+            ] {
+                let multiaddr = derive_libp2p_multiaddr(&addr.to_string())
+                    .expect("Failed to derive valid multiaddr, {}");
+                assert_eq!(multiaddr.to_string(), expected);
+            }
+        }
+
         /// Test that an invalid address fails to derive to a Multiaddr
         #[test]
         fn test_no_port() {
@@ -36,6 +36,7 @@ use hotshot_types::{
 use tokio::{
     spawn,
     sync::mpsc::{channel, error::SendError, Receiver, Sender},
+    time::sleep,
 };
 use tracing::{debug, error, info, info_span, instrument, trace, warn, Instrument};
 
@@ -73,6 +74,8 @@ struct MemoryNetworkInner<K: SignatureKey> {
     input: RwLock<Option<Sender<Vec<u8>>>>,
     /// Output for messages
     output: Mutex<Receiver<Vec<u8>>>,
+    /// Our public key
+    pub_key: K,
     /// The master map
     master_map: Arc<MasterMap<K>>,
 
@@ -142,6 +145,7 @@ impl<K: SignatureKey> MemoryNetwork<K> {
             inner: Arc::new(MemoryNetworkInner {
                 input: RwLock::new(Some(input)),
                 output: Mutex::new(output),
+                pub_key: pub_key.clone(),
                 master_map: Arc::clone(master_map),
                 in_flight_message_count,
                 reliability_config,
@@ -173,6 +177,37 @@ impl<K: SignatureKey> MemoryNetwork<K> {
             Err(SendError(message))
         }
     }
+
+    /// Send `message` to `node` over the unreliable network described by `config`, once the
+    /// message has been transmitted over the links between us and the `recipient`
+    fn chaos_send(
+        &self,
+        config: &dyn NetworkReliability,
+        recipient: &K,
+        node: MemoryNetwork<K>,
+        message: Vec<u8>,
+    ) {
+        let delay = config.transmission_delay(
+            &self.inner.pub_key.to_bytes(),
+            &recipient.to_bytes(),
+            message.len(),
+        );
+        let fut = config.chaos_send_msg(
+            message,
+            Arc::new(move |msg: Vec<u8>| {
+                let node = node.clone();
+                boxed_sync(async move {
+                    let _res = node.input(msg).await;
+                    // NOTE we're dropping metrics here but this is only for testing
+                    // purposes. I think that should be okay
+                })
+            }),
+        );
+        spawn(async move {
+            sleep(delay).await;
+            fut.await;
+        });
+    }
 }
 
 impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
@@ -262,21 +297,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
             let (key, node) = node;
             trace!(?key, "Sending message to node");
             if let Some(ref config) = &self.inner.reliability_config {
-                {
-                    let node2 = node.clone();
-                    let fut = config.chaos_send_msg(
-                        message.clone(),
-                        Arc::new(move |msg: Vec<u8>| {
-                            let node3 = (node2).clone();
-                            boxed_sync(async move {
-                                let _res = node3.input(msg).await;
-                                // NOTE we're dropping metrics here but this is only for testing
-                                // purposes. I think that should be okay
-                            })
-                        }),
-                    );
-                    spawn(fut);
-                }
+                self.chaos_send(config.as_ref(), key, node.clone(), message.clone());
             } else {
                 let res = node.input(message.clone()).await;
                 match res {
@@ -316,21 +337,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
             let (key, node) = node;
             trace!(?key, "Sending message to node");
             if let Some(ref config) = &self.inner.reliability_config {
-                {
-                    let node2 = node.clone();
-                    let fut = config.chaos_send_msg(
-                        message.clone(),
-                        Arc::new(move |msg: Vec<u8>| {
-                            let node3 = (node2).clone();
-                            boxed_sync(async move {
-                                let _res = node3.input(msg).await;
-                                // NOTE we're dropping metrics here but this is only for testing
-                                // purposes. I think that should be okay
-                            })
-                        }),
-                    );
-                    spawn(fut);
-                }
+                self.chaos_send(config.as_ref(), key, node.clone(), message.clone());
             } else {
                 let res = node.input(message.clone()).await;
                 match res {
@@ -354,20 +361,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         if let Some(node) = self.inner.master_map.map.get(&recipient) {
             let node = node.value().clone();
             if let Some(ref config) = &self.inner.reliability_config {
-                {
-                    let fut = config.chaos_send_msg(
-                        message.clone(),
-                        Arc::new(move |msg: Vec<u8>| {
-                            let node2 = node.clone();
-                            boxed_sync(async move {
-                                let _res = node2.input(msg).await;
-                                // NOTE we're dropping metrics here but this is only for testing
-                                // purposes. I think that should be okay
-                            })
-                        }),
-                    );
-                    spawn(fut);
-                }
+                self.chaos_send(config.as_ref(), &recipient, node, message);
                 Ok(())
             } else {
                 let res = node.input(message).await;
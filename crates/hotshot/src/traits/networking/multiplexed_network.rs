@@ -0,0 +1,331 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Networking for several chains over one transport.
+//!
+//! A process running consensus for more than one chain can connect them all through a single
+//! network: a [`SharedNetwork`] wraps the transport, and each chain gets a [`ChainNetwork`] from it.
+//! Every message a chain sends is prefixed with its chain ID, and the shared network hands each
+//! received message to the chain it is addressed to, so the chains never see each other's
+//! messages.
+
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use async_lock::{Mutex, RwLock};
+use async_trait::async_trait;
+use hotshot_types::{
+    boxed_sync,
+    constants::NETWORK_INBOUND_CHANNEL_SIZE,
+    data::ViewNumber,
+    traits::{
+        network::{BroadcastDelay, ConnectedNetwork, Topic},
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+    },
+    wire_codec::WireFormat,
+    BoxSyncFuture,
+};
+use parking_lot::RwLock as PlRwLock;
+use tokio::{
+    spawn,
+    sync::mpsc::{self, error::TrySendError, Receiver, Sender},
+    task::JoinHandle,
+};
+use tracing::{debug, warn};
+
+use super::NetworkError;
+
+/// Number of bytes of the chain ID prefixed to every message
+const CHAIN_ID_LEN: usize = std::mem::size_of::<u64>();
+
+/// The senders to the chains of a shared network, by chain ID
+type ChainSenders = Arc<PlRwLock<HashMap<u64, Sender<Vec<u8>>>>>;
+
+/// A network shared by several chains
+pub struct SharedNetwork<K: SignatureKey + 'static, N: ConnectedNetwork<K>> {
+    /// The underlying transport
+    network: N,
+
+    /// Where to send the messages received for each chain
+    chains: ChainSenders,
+
+    /// The task handing out received messages to the chains
+    receive_task: Mutex<Option<JoinHandle<()>>>,
+
+    /// The key type of the network
+    _pd: PhantomData<K>,
+}
+
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> SharedNetwork<K, N> {
+    /// Share `network` between chains, and start handing out the messages it receives
+    #[must_use]
+    pub fn new(network: N) -> Arc<Self> {
+        let chains = ChainSenders::default();
+        let receive_task = spawn(Self::run_receive_task(network.clone(), Arc::clone(&chains)));
+
+        Arc::new(Self {
+            network,
+            chains,
+            receive_task: Mutex::new(Some(receive_task)),
+            _pd: PhantomData,
+        })
+    }
+
+    /// Connect the chain `chain_id` to the shared network
+    ///
+    /// # Errors
+    /// If the chain is already connected
+    pub fn chain(self: &Arc<Self>, chain_id: u64) -> Result<ChainNetwork<K, N>, NetworkError> {
+        let mut chains = self.chains.write();
+        if chains.contains_key(&chain_id) {
+            return Err(NetworkError::ConfigError(format!(
+                "Chain {chain_id} is already connected to the shared network"
+            )));
+        }
+
+        let (sender, receiver) = mpsc::channel(NETWORK_INBOUND_CHANNEL_SIZE);
+        chains.insert(chain_id, sender);
+
+        Ok(ChainNetwork {
+            chain_id,
+            shared: Arc::clone(self),
+            receiver: Arc::new(RwLock::new(receiver)),
+            paused: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Number of chains connected to the shared network
+    #[must_use]
+    pub fn num_chains(&self) -> usize {
+        self.chains.read().len()
+    }
+
+    /// Shut down the underlying transport, disconnecting every chain
+    pub async fn shut_down(&self) {
+        if let Some(task) = self.receive_task.lock().await.take() {
+            task.abort();
+        }
+        self.chains.write().clear();
+        self.network.shut_down().await;
+    }
+
+    /// Receive messages from `network` and send each to the chain it is addressed to, until the
+    /// network shuts down
+    async fn run_receive_task(network: N, chains: ChainSenders) {
+        loop {
+            let message = match network.recv_message().await {
+                Ok(message) => message,
+                Err(NetworkError::ShutDown) => break,
+                Err(e) => {
+                    warn!("Failed to receive a message on the shared network: {e}");
+                    continue;
+                }
+            };
+
+            let Some((chain_id, message)) = split_chain_id(message) else {
+                warn!("Received a message without a chain ID on the shared network");
+                continue;
+            };
+
+            let Some(sender) = chains.read().get(&chain_id).cloned() else {
+                debug!("Dropping a message for chain {chain_id}, which is not connected");
+                continue;
+            };
+
+            match sender.try_send(message) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    warn!("The inbound channel of chain {chain_id} is full, dropping a message");
+                }
+                Err(TrySendError::Closed(_)) => {
+                    chains.write().remove(&chain_id);
+                }
+            }
+        }
+    }
+}
+
+/// Prefix `message` with `chain_id`
+fn tag_chain_id(chain_id: u64, message: &[u8]) -> Vec<u8> {
+    let mut tagged = Vec::with_capacity(CHAIN_ID_LEN + message.len());
+    tagged.extend_from_slice(&chain_id.to_le_bytes());
+    tagged.extend_from_slice(message);
+    tagged
+}
+
+/// Split the chain ID from the front of `message`
+fn split_chain_id(mut message: Vec<u8>) -> Option<(u64, Vec<u8>)> {
+    let chain_id = u64::from_le_bytes(message.get(..CHAIN_ID_LEN)?.try_into().ok()?);
+    message.drain(..CHAIN_ID_LEN);
+    Some((chain_id, message))
+}
+
+/// The connection of one chain to a [`SharedNetwork`]
+pub struct ChainNetwork<K: SignatureKey + 'static, N: ConnectedNetwork<K>> {
+    /// The ID the chain's messages are tagged with
+    chain_id: u64,
+
+    /// The network shared with the other chains
+    shared: Arc<SharedNetwork<K, N>>,
+
+    /// The messages received for this chain
+    receiver: Arc<RwLock<Receiver<Vec<u8>>>>,
+
+    /// Whether the chain is paused, in which case it neither sends nor receives
+    paused: Arc<AtomicBool>,
+}
+
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> Clone for ChainNetwork<K, N> {
+    fn clone(&self) -> Self {
+        Self {
+            chain_id: self.chain_id,
+            shared: Arc::clone(&self.shared),
+            receiver: Arc::clone(&self.receiver),
+            paused: Arc::clone(&self.paused),
+        }
+    }
+}
+
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> ChainNetwork<K, N> {
+    /// The ID of the chain
+    #[must_use]
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Whether the chain is paused
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> ConnectedNetwork<K> for ChainNetwork<K, N> {
+    fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    async fn wait_for_ready(&self) {
+        self.shared.network.wait_for_ready().await;
+    }
+
+    /// Disconnects only this chain. The underlying transport keeps running for the other chains,
+    /// and is shut down with [`SharedNetwork::shut_down`].
+    fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        let closure = async move {
+            // Dropping the sender ends `recv_message` once the received messages are drained
+            self.shared.chains.write().remove(&self.chain_id);
+        };
+        boxed_sync(closure)
+    }
+
+    async fn broadcast_message(
+        &self,
+        message: Vec<u8>,
+        topic: Topic,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        if self.is_paused() {
+            return Ok(());
+        }
+        self.shared
+            .network
+            .broadcast_message(
+                tag_chain_id(self.chain_id, &message),
+                topic,
+                broadcast_delay,
+            )
+            .await
+    }
+
+    async fn da_broadcast_message(
+        &self,
+        message: Vec<u8>,
+        recipients: Vec<K>,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        if self.is_paused() {
+            return Ok(());
+        }
+        self.shared
+            .network
+            .da_broadcast_message(
+                tag_chain_id(self.chain_id, &message),
+                recipients,
+                broadcast_delay,
+            )
+            .await
+    }
+
+    async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError> {
+        if self.is_paused() {
+            return Ok(());
+        }
+        self.shared
+            .network
+            .direct_message(tag_chain_id(self.chain_id, &message), recipient)
+            .await
+    }
+
+    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+        let mut receiver = self.receiver.write().await;
+        loop {
+            let message = receiver.recv().await.ok_or(NetworkError::ShutDown)?;
+            if !self.is_paused() {
+                return Ok(message);
+            }
+        }
+    }
+
+    fn queue_node_lookup(
+        &self,
+        view_number: ViewNumber,
+        pk: K,
+    ) -> Result<(), TrySendError<Option<(ViewNumber, K)>>> {
+        self.shared.network.queue_node_lookup(view_number, pk)
+    }
+
+    async fn update_view<'a, TYPES>(
+        &'a self,
+        view: u64,
+        epoch: u64,
+        membership: Arc<RwLock<TYPES::Membership>>,
+    ) where
+        TYPES: NodeType<SignatureKey = K> + 'a,
+    {
+        self.shared
+            .network
+            .update_view::<TYPES>(view, epoch, membership)
+            .await;
+    }
+
+    fn warm_up_connections(&self, peers: Vec<K>) {
+        self.shared.network.warm_up_connections(peers);
+    }
+
+    fn is_primary_down(&self) -> bool {
+        self.shared.network.is_primary_down()
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.shared.network.wire_format()
+    }
+}
@@ -6,10 +6,19 @@
 
 #[cfg(feature = "hotshot-testing")]
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::{collections::VecDeque, marker::PhantomData, sync::Arc};
+use std::{
+    collections::{HashSet, VecDeque},
+    marker::PhantomData,
+    sync::Arc,
+};
 #[cfg(feature = "hotshot-testing")]
-use std::{path::Path, time::Duration};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
 
+use async_lock::RwLock;
 use async_trait::async_trait;
 use bincode::config::Options;
 use cdn_broker::reexports::{
@@ -37,12 +46,14 @@ use hotshot_types::{
     boxed_sync,
     data::ViewNumber,
     traits::{
+        election::Membership,
         metrics::{Counter, Metrics, NoMetrics},
         network::{BroadcastDelay, ConnectedNetwork, Topic as HotShotTopic},
-        node_implementation::NodeType,
+        node_implementation::{ConsensusTime, NodeType},
         signature_key::SignatureKey,
     },
     utils::bincode_opts,
+    wire_codec::WireFormat,
     BoxSyncFuture,
 };
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -50,7 +61,7 @@ use parking_lot::Mutex;
 #[cfg(feature = "hotshot-testing")]
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use tokio::{spawn, sync::mpsc::error::TrySendError, time::sleep};
-use tracing::error;
+use tracing::{error, warn};
 
 use super::NetworkError;
 
@@ -196,6 +207,10 @@ pub struct PushCdnNetwork<K: SignatureKey + 'static> {
     internal_queue: Arc<Mutex<VecDeque<Vec<u8>>>>,
     /// The public key of this node
     public_key: K,
+    /// The encoding of messages on this network
+    wire_format: WireFormat,
+    /// The topics we are subscribed to, which follow our roles as the view changes
+    subscribed_topics: Arc<Mutex<HashSet<Topic>>>,
     /// Whether or not the underlying network is supposed to be paused
     #[cfg(feature = "hotshot-testing")]
     is_paused: Arc<AtomicBool>,
@@ -205,7 +220,7 @@ pub struct PushCdnNetwork<K: SignatureKey + 'static> {
 
 /// The enum for the topics we can subscribe to in the Push CDN
 #[repr(u8)]
-#[derive(IntoPrimitive, TryFromPrimitive, Clone, PartialEq, Eq)]
+#[derive(IntoPrimitive, TryFromPrimitive, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Topic {
     /// The global topic
     Global = 0,
@@ -217,6 +232,16 @@ pub enum Topic {
 /// topics that are not implemented at the application level.
 impl TopicTrait for Topic {}
 
+/// The topics a node should be subscribed to, given whether it is on the DA committee
+fn topics_for_roles(is_da: bool) -> HashSet<Topic> {
+    let mut topics = HashSet::from([Topic::Global]);
+    if is_da {
+        topics.insert(Topic::Da);
+    }
+
+    topics
+}
+
 impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
     /// Create a new `PushCdnNetwork` (really a client) from a marshal endpoint, a list of initial
     /// topics we are interested in, and our wrapped keypair that we use to authenticate with the
@@ -233,7 +258,7 @@ impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
         // Build config
         let config = ClientConfig {
             endpoint: marshal_endpoint,
-            subscribed_topics: topics.into_iter().map(|t| t as u8).collect(),
+            subscribed_topics: topics.iter().map(|t| *t as u8).collect(),
             keypair: keypair.clone(),
             use_local_authority: true,
         };
@@ -246,12 +271,55 @@ impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
             metrics: Arc::from(metrics),
             internal_queue: Arc::new(Mutex::new(VecDeque::new())),
             public_key: keypair.public_key.0,
+            wire_format: WireFormat::default(),
+            subscribed_topics: Arc::new(Mutex::new(topics.into_iter().collect())),
             // Start unpaused
             #[cfg(feature = "hotshot-testing")]
             is_paused: Arc::from(AtomicBool::new(false)),
         })
     }
 
+    /// Set the encoding of messages on this network, which all nodes must agree on
+    #[must_use]
+    pub fn with_wire_format(mut self, wire_format: WireFormat) -> Self {
+        self.wire_format = wire_format;
+        self
+    }
+
+    /// Subscribe to exactly `topics`, unsubscribing from any other topics.
+    ///
+    /// # Errors
+    /// If the client fails to subscribe or unsubscribe
+    async fn update_subscriptions(&self, topics: HashSet<Topic>) -> Result<(), NetworkError> {
+        let (subscribe, unsubscribe): (Vec<u8>, Vec<u8>) = {
+            let subscribed_topics = self.subscribed_topics.lock();
+            (
+                topics
+                    .difference(&subscribed_topics)
+                    .map(|t| *t as u8)
+                    .collect(),
+                subscribed_topics
+                    .difference(&topics)
+                    .map(|t| *t as u8)
+                    .collect(),
+            )
+        };
+
+        if !subscribe.is_empty() {
+            self.client.subscribe(subscribe).await.map_err(|err| {
+                NetworkError::MessageSendError(format!("failed to subscribe: {err}"))
+            })?;
+        }
+        if !unsubscribe.is_empty() {
+            self.client.unsubscribe(unsubscribe).await.map_err(|err| {
+                NetworkError::MessageSendError(format!("failed to unsubscribe: {err}"))
+            })?;
+        }
+        *self.subscribed_topics.lock() = topics;
+
+        Ok(())
+    }
+
     /// Broadcast a message to members of the particular topic. Does not retry.
     ///
     /// # Errors
@@ -285,7 +353,6 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
 {
     /// Generate n Push CDN clients, a marshal, and two brokers (that run locally).
     /// Uses a `SQLite` database instead of Redis.
-    #[allow(clippy::too_many_lines)]
     fn generator(
         _expected_node_count: usize,
         _num_bootstrap: usize,
@@ -293,12 +360,33 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
         da_committee_size: usize,
         _reliability_config: Option<Box<dyn NetworkReliability>>,
         _secondary_network_delay: Duration,
+    ) -> AsyncGenerator<Arc<Self>> {
+        Self::generator_with_loopback(da_committee_size, Ipv4Addr::LOCALHOST.into())
+    }
+
+    /// The PushCDN does not support in-flight message counts
+    fn in_flight_message_count(&self) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(feature = "hotshot-testing")]
+impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
+    /// Like [`TestableNetworkingImplementation::generator`], but with the brokers and the marshal
+    /// listening on `loopback`, which may be of either address family
+    ///
+    /// # Panics
+    /// If there are no free ports, or the brokers or the marshal fail to start
+    #[allow(clippy::too_many_lines)]
+    pub fn generator_with_loopback(
+        da_committee_size: usize,
+        loopback: IpAddr,
     ) -> AsyncGenerator<Arc<Self>> {
         // The configuration we are using for testing is 2 brokers & 1 marshal
 
         // A keypair shared between brokers
         let (broker_public_key, broker_private_key) =
-            TYPES::SignatureKey::generated_from_seed_indexed([0u8; 32], 1337);
+            K::generated_from_seed_indexed([0u8; 32], 1337);
 
         // Get the OS temporary directory
         let temp_dir = std::env::temp_dir();
@@ -312,15 +400,17 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
             .to_string_lossy()
             .into_owned();
 
-        // Pick some unused public ports
-        let public_address_1 = format!(
-            "127.0.0.1:{}",
-            portpicker::pick_unused_port().expect("could not find an open port")
-        );
-        let public_address_2 = format!(
-            "127.0.0.1:{}",
-            portpicker::pick_unused_port().expect("could not find an open port")
-        );
+        // Pick some unused public ports. Formatting a `SocketAddr` puts IPv6 addresses in brackets.
+        let public_address_1 = SocketAddr::new(
+            loopback,
+            portpicker::pick_unused_port().expect("could not find an open port"),
+        )
+        .to_string();
+        let public_address_2 = SocketAddr::new(
+            loopback,
+            portpicker::pick_unused_port().expect("could not find an open port"),
+        )
+        .to_string();
 
         // 2 brokers
         for i in 0..2 {
@@ -328,7 +418,7 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
             let private_port = portpicker::pick_unused_port().expect("could not find an open port");
 
             // Extrapolate addresses
-            let private_address = format!("127.0.0.1:{private_port}");
+            let private_address = SocketAddr::new(loopback, private_port).to_string();
             let (public_address, other_public_address) = if i == 0 {
                 (public_address_1.clone(), public_address_2.clone())
             } else {
@@ -340,7 +430,7 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
             let other_broker_identifier = format!("{other_public_address}/{other_public_address}");
 
             // Configure the broker
-            let config: BrokerConfig<TestingDef<TYPES::SignatureKey>> = BrokerConfig {
+            let config: BrokerConfig<TestingDef<K>> = BrokerConfig {
                 public_advertise_endpoint: public_address.clone(),
                 public_bind_endpoint: public_address,
                 private_advertise_endpoint: private_address.clone(),
@@ -363,7 +453,7 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
 
             // Create and spawn the broker
             spawn(async move {
-                let broker: Broker<TestingDef<TYPES::SignatureKey>> =
+                let broker: Broker<TestingDef<K>> =
                     Broker::new(config).await.expect("broker failed to start");
 
                 // If we are the first broker by identifier, we need to sleep a bit
@@ -383,7 +473,7 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
         let marshal_port = portpicker::pick_unused_port().expect("could not find an open port");
 
         // Configure the marshal
-        let marshal_endpoint = format!("127.0.0.1:{marshal_port}");
+        let marshal_endpoint = SocketAddr::new(loopback, marshal_port).to_string();
         let marshal_config = MarshalConfig {
             bind_endpoint: marshal_endpoint.clone(),
             discovery_endpoint,
@@ -396,7 +486,7 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
 
         // Spawn the marshal
         spawn(async move {
-            let marshal: Marshal<TestingDef<TYPES::SignatureKey>> = Marshal::new(marshal_config)
+            let marshal: Marshal<TestingDef<K>> = Marshal::new(marshal_config)
                 .await
                 .expect("failed to spawn marshal");
 
@@ -414,28 +504,22 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
 
                 Box::pin(async move {
                     // Derive our public and priate keys from our index
-                    let private_key =
-                        TYPES::SignatureKey::generated_from_seed_indexed([0u8; 32], node_id).1;
-                    let public_key = TYPES::SignatureKey::from_private(&private_key);
+                    let private_key = K::generated_from_seed_indexed([0u8; 32], node_id).1;
+                    let public_key = K::from_private(&private_key);
 
                     // Calculate if we're DA or not
-                    let topics = if node_id < da_committee_size as u64 {
-                        vec![Topic::Da as u8, Topic::Global as u8]
-                    } else {
-                        vec![Topic::Global as u8]
-                    };
+                    let topics = topics_for_roles(node_id < da_committee_size as u64);
 
                     // Configure our client
-                    let client_config: ClientConfig<ClientDef<TYPES::SignatureKey>> =
-                        ClientConfig {
-                            keypair: KeyPair {
-                                public_key: WrappedSignatureKey(public_key.clone()),
-                                private_key,
-                            },
-                            subscribed_topics: topics,
-                            endpoint: marshal_endpoint,
-                            use_local_authority: true,
-                        };
+                    let client_config: ClientConfig<ClientDef<K>> = ClientConfig {
+                        keypair: KeyPair {
+                            public_key: WrappedSignatureKey(public_key.clone()),
+                            private_key,
+                        },
+                        subscribed_topics: topics.iter().map(|t| *t as u8).collect(),
+                        endpoint: marshal_endpoint,
+                        use_local_authority: true,
+                    };
 
                     // Create our client
                     Arc::new(PushCdnNetwork {
@@ -443,6 +527,8 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
                         metrics: Arc::new(CdnMetricsValue::default()),
                         internal_queue: Arc::new(Mutex::new(VecDeque::new())),
                         public_key,
+                        wire_format: WireFormat::default(),
+                        subscribed_topics: Arc::new(Mutex::new(topics)),
                         #[cfg(feature = "hotshot-testing")]
                         is_paused: Arc::from(AtomicBool::new(false)),
                     })
@@ -450,11 +536,6 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
             }
         })
     }
-
-    /// The PushCDN does not support in-flight message counts
-    fn in_flight_message_count(&self) -> Option<usize> {
-        None
-    }
 }
 
 #[async_trait]
@@ -607,6 +688,40 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         Ok(message)
     }
 
+    fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+
+    /// Follow our roles in the new view with our topic subscriptions, so that broadcasts only
+    /// fan out to the nodes which need them. We stay on the DA topic while we are on the DA
+    /// committee of this view or the next, whose proposals may arrive early.
+    ///
+    /// Votes are sent directly to the leader who collects them, so there are no vote topics to
+    /// manage.
+    async fn update_view<'a, TYPES>(
+        &'a self,
+        view: u64,
+        epoch: u64,
+        membership: Arc<RwLock<TYPES::Membership>>,
+    ) where
+        TYPES: NodeType<SignatureKey = K> + 'a,
+    {
+        let view = TYPES::View::new(view);
+        let epoch = TYPES::Epoch::new(epoch);
+        let is_da = {
+            let membership = membership.read().await;
+            [view, view + 1].into_iter().any(|view| {
+                membership
+                    .da_committee_members(view, epoch)
+                    .contains(&self.public_key)
+            })
+        };
+
+        if let Err(err) = self.update_subscriptions(topics_for_roles(is_da)).await {
+            warn!("Failed to update topic subscriptions for view {view:?}: {err}");
+        }
+    }
+
     /// Do nothing here, as we don't need to look up nodes.
     fn queue_node_lookup(
         &self,
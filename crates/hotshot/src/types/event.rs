@@ -6,4 +6,7 @@
 
 //! Events that a [`SystemContext`](crate::SystemContext) instance can emit
 
-pub use hotshot_types::event::{Event, EventType};
+pub use hotshot_types::{
+    event::{Event, EventType},
+    external_event::{ExternalEvent, ExternalEventType},
+};
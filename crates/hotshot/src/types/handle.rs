@@ -6,36 +6,64 @@
 
 //! Provides an event-streaming handle for a [`SystemContext`] running in the background
 
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Context, Ok, Result};
 use async_broadcast::{InactiveReceiver, Receiver, Sender};
 use async_lock::RwLock;
 use committable::{Commitment, Committable};
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use hotshot_task::{
     dependency::{Dependency, EventDependency},
     task::{ConsensusTaskRegistry, NetworkTaskRegistry, Task, TaskState},
 };
-use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    helpers::{broadcast_event, validate_block_against_parent},
+};
 use hotshot_types::{
     consensus::Consensus,
-    data::{Leaf2, QuorumProposal2},
-    error::HotShotError,
+    data::{Leaf2, QuorumProposal2, VidDisperse, ViewChangeEvidence},
+    error::{BlockValidationError, ConsensusError, HotShotError},
     message::{Message, MessageKind, Proposal, RecipientList},
+    message_ttl::TtlHeader,
+    participation::ValidatorParticipation,
+    peer_score::PeerRecord,
+    read_replica::SignedReplicaFeedMessage,
     request_response::ProposalRequestPayload,
     traits::{
+        block_contents::{BlockHeader, EncodeBytes, TransactionPriority},
         consensus_api::ConsensusApi,
         election::Membership,
         network::{BroadcastDelay, ConnectedNetwork, Topic},
-        node_implementation::NodeType,
+        node_implementation::{ConsensusTime, NodeType},
         signature_key::SignatureKey,
+        states::InstanceState,
+        storage::Storage,
+        BlockPayload,
     },
+    utils::epoch_from_block_number,
+    validator_set::{ValidatorSetAttestation, ValidatorSets},
+    view_history::{ViewStats, ViewSummary},
     vote::HasViewNumber,
 };
+use tokio::{spawn, time::sleep};
 use tracing::instrument;
 
-use crate::{traits::NodeImplementation, types::Event, SystemContext, Versions};
+use crate::{
+    execution::ExecutionHook,
+    helpers::{
+        add_log_filter_override, remove_log_filter_override, LogFilterOverride, LogFilterRevert,
+    },
+    tasks::{add_execution_hook_task, add_restartable_task, RestartableTask},
+    traits::NodeImplementation,
+    types::{Event, ExternalEvent},
+    SystemContext, Versions,
+};
 
 /// Event streaming handle for a [`SystemContext`] instance running in the background
 ///
@@ -89,11 +117,111 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.consensus_registry.run_task(task);
     }
 
+    /// Adds a hotshot consensus-related task to the `SystemContextHandle` under `name`, replacing
+    /// the task previously added under that name.
+    pub fn add_named_task<S: TaskState<Event = HotShotEvent<TYPES>> + 'static>(
+        &mut self,
+        name: &'static str,
+        task_state: S,
+    ) {
+        let task = Task::new(
+            task_state,
+            self.internal_event_stream.0.clone(),
+            self.internal_event_stream.1.activate_cloned(),
+        );
+
+        self.consensus_registry.run_named_task(name, task);
+    }
+
+    /// Kill `task` and start it again with a fresh state, as if it had crashed and been restarted
+    pub async fn restart_task(&mut self, task: RestartableTask) {
+        self.consensus_registry.abort_named_task(task.name());
+        add_restartable_task(self, task).await;
+    }
+
+    /// Hand each decided block to `hook`, in order of height, carrying on from the last block it
+    /// executed before a restart. Add the hook before starting consensus, so that it sees every
+    /// decide.
+    pub async fn add_execution_hook<H: ExecutionHook<TYPES>>(&mut self, hook: H) {
+        add_execution_hook_task(self, hook).await;
+    }
+
+    /// Filter logs by `directives` on top of the current filter until `revert`, e.g.
+    /// `hotshot_task_impls::consensus=debug` for the next 100 views to debug the consensus task.
+    /// The override can be reverted early with [`remove_log_filter_override`].
+    ///
+    /// # Errors
+    /// If `directives` are invalid, see [`add_log_filter_override`]
+    pub async fn override_log_filter(
+        &self,
+        directives: &str,
+        revert: LogFilterRevert,
+    ) -> Result<LogFilterOverride> {
+        let id = add_log_filter_override(directives)?;
+        match revert {
+            LogFilterRevert::Never => {}
+            LogFilterRevert::After(duration) => {
+                spawn(async move {
+                    sleep(duration).await;
+                    remove_log_filter_override(id);
+                });
+            }
+            LogFilterRevert::AfterViews(views) => {
+                let until = self.cur_view().await + views;
+                let mut events = self.event_stream_known_impl();
+                spawn(async move {
+                    // Also reverted if the node shuts down first
+                    while let Some(event) = events.next().await {
+                        if event.view_number >= until {
+                            break;
+                        }
+                    }
+                    remove_log_filter_override(id);
+                });
+            }
+        }
+
+        Ok(id)
+    }
+
     /// obtains a stream to expose to the user
     pub fn event_stream(&self) -> impl Stream<Item = Event<TYPES>> {
         self.output_event_stream.1.activate_cloned()
     }
 
+    /// Obtains a stream of the events, in the stable external schema, for consumers outside the
+    /// node
+    pub fn external_event_stream(&self) -> impl Stream<Item = ExternalEvent<TYPES>> {
+        self.event_stream().map(|event| ExternalEvent::from(&event))
+    }
+
+    /// Stream the decided leaves from `height` on, in order of height and each once: first those
+    /// decided already, as far back as storage keeps them, then each as it is decided.
+    pub async fn stream_decided_leaves_from(
+        &self,
+        height: u64,
+    ) -> impl Stream<Item = Leaf2<TYPES>> {
+        self.hotshot.stream_decided_leaves_from(height).await
+    }
+
+    /// Open a feed of the proposals this node validates, the certificates it sees and the leaves
+    /// it decides, signed by this node, for its read replicas to follow. Each message is to be
+    /// applied to a [`ReadReplica`](hotshot_types::read_replica::ReadReplica) of this node.
+    pub fn read_replica_feed(&self) -> impl Stream<Item = SignedReplicaFeedMessage<TYPES>> {
+        self.hotshot.read_replica_feed()
+    }
+
+    /// Catch up on the leaves decided up to `view` from a peer, as for syncing the application's
+    /// state, even if this node has not fallen far enough behind to catch up on its own. The
+    /// leaves are persisted as decided, so streams of decided leaves pick them up.
+    pub async fn catch_up_to(&self, view: TYPES::View) {
+        broadcast_event(
+            Arc::new(HotShotEvent::CatchupStart(view)),
+            &self.internal_event_stream.0,
+        )
+        .await;
+    }
+
     /// Message other participants with a serialized message from the application
     /// Receivers of this message will get an `Event::ExternalMessageReceived` via
     /// the event stream.
@@ -109,7 +237,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
             sender: self.public_key().clone(),
             kind: MessageKind::External(msg),
         };
-        let serialized_message = self.hotshot.upgrade_lock.serialize(&message).await?;
+        let serialized_message = self
+            .hotshot
+            .upgrade_lock
+            .serialize_with(&message, self.network.wire_format())
+            .await?;
+        let serialized_message = TtlHeader::new(
+            &message.kind,
+            &self.hotshot.config.message_ttl,
+            self.hotshot.instance_state().clock().now(),
+        )
+        .stamp(serialized_message);
 
         match recipients {
             RecipientList::Broadcast => {
@@ -137,7 +275,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
     ///
     /// # Errors
     /// Errors if signing the request for proposal fails
-    pub fn request_proposal(
+    pub async fn request_proposal(
         &self,
         view: TYPES::View,
         leaf_commitment: Commitment<Leaf2<TYPES>>,
@@ -153,13 +291,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         // Finally, compute the signature for the payload.
         let signature = TYPES::SignatureKey::sign(
             self.private_key(),
-            signed_proposal_request.commit().as_ref(),
+            &self
+                .hotshot
+                .upgrade_lock
+                .signing_message(signed_proposal_request.commit().as_ref(), view)
+                .await,
         )?;
 
         let mem = Arc::clone(&self.memberships);
         let receiver = self.internal_event_stream.1.activate_cloned();
         let sender = self.internal_event_stream.0.clone();
         let epoch_height = self.epoch_height;
+        let upgrade_lock = self.hotshot.upgrade_lock.clone();
         Ok(async move {
             // First, broadcast that we need a proposal
             broadcast_event(
@@ -188,7 +331,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
                 {
                     // Make sure that the quorum_proposal is valid
                     let mem_reader = mem.read().await;
-                    if let Err(err) = quorum_proposal.validate_signature(&mem_reader, epoch_height)
+                    if let Err(err) = quorum_proposal
+                        .validate_signature(&mem_reader, epoch_height, &upgrade_lock)
+                        .await
                     {
                         tracing::warn!("Invalid Proposal Received after Request.  Err {:?}", err);
                         continue;
@@ -268,6 +413,100 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.hotshot.try_decided_leaf()
     }
 
+    /// Check a candidate block with `header` and `payload`, built on the block proposed in
+    /// `parent_view`, as consensus would check it if it were proposed next, so that builders and
+    /// RPC services can turn away a bad block before it wastes a view. The block must follow its
+    /// parent, its header must commit to the payload, and the header must extend the parent's
+    /// state. Nothing is stored.
+    ///
+    /// # Errors
+    /// The first check the block fails.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn validate_block_for_proposal(
+        &self,
+        header: &TYPES::BlockHeader,
+        payload: &TYPES::BlockPayload,
+        parent_view: TYPES::View,
+    ) -> Result<(), BlockValidationError> {
+        let (parent, parent_state) = {
+            let consensus = self.hotshot.consensus.read().await;
+            consensus
+                .validated_state_map()
+                .get(&parent_view)
+                .and_then(|view| view.leaf_and_state())
+                .and_then(|(commitment, state)| {
+                    consensus
+                        .saved_leaves()
+                        .get(&commitment)
+                        .map(|leaf| (leaf.clone(), Arc::clone(state)))
+                })
+                .ok_or(BlockValidationError::UnknownParent(*parent_view))?
+        };
+
+        let expected = parent.height() + 1;
+        if header.block_number() != expected {
+            return Err(BlockValidationError::BlockNumber {
+                expected,
+                actual: header.block_number(),
+            });
+        }
+
+        let builder_commitment = payload.builder_commitment(header.metadata());
+        if header.builder_commitment() != builder_commitment {
+            return Err(BlockValidationError::BuilderCommitment {
+                header: format!("{:?}", header.builder_commitment()),
+                payload: format!("{builder_commitment:?}"),
+            });
+        }
+
+        // The block would be proposed in the view after its parent at the earliest
+        let view_number = parent_view + 1;
+        let epoch = TYPES::Epoch::new(epoch_from_block_number(
+            header.block_number(),
+            self.hotshot.config.epoch_height,
+        ));
+        let vid_disperse = VidDisperse::calculate_vid_disperse(
+            payload.encode(),
+            &self.hotshot.memberships,
+            view_number,
+            epoch,
+            epoch,
+            None,
+        )
+        .await;
+        if header.payload_commitment() != vid_disperse.payload_commitment {
+            return Err(BlockValidationError::PayloadCommitment {
+                header: format!("{:?}", header.payload_commitment()),
+                payload: format!("{:?}", vid_disperse.payload_commitment),
+            });
+        }
+
+        let version = self
+            .hotshot
+            .upgrade_lock
+            .version(view_number)
+            .await
+            .map_err(|e| BlockValidationError::Rejected(e.to_string()))?;
+
+        validate_block_against_parent(
+            &self.hotshot.instance_state(),
+            &parent,
+            &parent_state,
+            header,
+            vid_disperse.common,
+            version,
+            view_number,
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| match e {
+            ConsensusError::InvalidStateTransition(e) => {
+                BlockValidationError::InvalidStateTransition(e)
+            }
+            e => BlockValidationError::Rejected(e.to_string()),
+        })
+    }
+
     /// Submits a transaction to the backing [`SystemContext`] instance.
     ///
     /// The current node broadcasts the transaction to all nodes on the network.
@@ -283,6 +522,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.hotshot.publish_transaction_async(tx).await
     }
 
+    /// Submits a transaction tagged with a priority class to the backing [`SystemContext`]
+    /// instance.
+    ///
+    /// Leaders reserve part of each block for high-priority transactions.
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`HotShotError`] if some error occurs in the underlying
+    /// [`SystemContext`] instance.
+    pub async fn submit_transaction_with_priority(
+        &self,
+        tx: TYPES::Transaction,
+        priority: TransactionPriority,
+    ) -> Result<(), HotShotError<TYPES>> {
+        self.hotshot
+            .publish_prioritized_transaction_async(tx, priority)
+            .await
+    }
+
     /// Get the underlying consensus state for this [`SystemContext`]
     #[must_use]
     pub fn consensus(&self) -> Arc<RwLock<Consensus<TYPES>>> {
@@ -357,6 +615,20 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.internal_event_stream.0.clone()
     }
 
+    /// Propose `payload` with `metadata` the next time this node leads a view, instead of a block
+    /// from its builders, so that tests can decide exactly the block they need. The block header
+    /// is built from `metadata` as usual. A block injected before the previous one was proposed
+    /// replaces it.
+    #[cfg(feature = "hotshot-testing")]
+    pub async fn inject_block(
+        &self,
+        payload: TYPES::BlockPayload,
+        metadata: <TYPES::BlockPayload as hotshot_types::traits::BlockPayload<TYPES>>::Metadata,
+    ) {
+        *self.hotshot.injected_block.write().await =
+            Some(hotshot_task_impls::transactions::InjectedBlock { payload, metadata });
+    }
+
     /// Wrapper to get the view number this node is on.
     #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
     pub async fn cur_view(&self) -> TYPES::View {
@@ -369,6 +641,120 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.hotshot.consensus.read().await.cur_epoch()
     }
 
+    /// Participation counters for every validator seen in a decided certificate or leader view.
+    ///
+    /// Use [`ValidatorParticipation::score`] to turn the counters into a liveness score.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn participation_scores(
+        &self,
+    ) -> HashMap<TYPES::SignatureKey, ValidatorParticipation> {
+        self.hotshot
+            .consensus
+            .read()
+            .await
+            .participation()
+            .all()
+            .clone()
+    }
+
+    /// Statistics on the `count` most recent views, oldest first.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn recent_views(&self, count: usize) -> Vec<ViewStats<TYPES>> {
+        self.hotshot
+            .consensus
+            .read()
+            .await
+            .view_history()
+            .recent(count)
+    }
+
+    /// Statistics over the views entered in the last `period`, such as the number of views per
+    /// decide. Only the views the history still holds are counted.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn view_summary(&self, period: Duration) -> ViewSummary {
+        self.hotshot
+            .consensus
+            .read()
+            .await
+            .view_history()
+            .summary(period, Instant::now())
+    }
+
+    /// What this node holds against every peer which misbehaved.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn peer_records(&self) -> HashMap<TYPES::SignatureKey, PeerRecord> {
+        self.hotshot.peer_scores.records().await
+    }
+
+    /// Every peer this node currently bans, with how long its ban still lasts.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn banned_peers(&self) -> Vec<(TYPES::SignatureKey, Duration)> {
+        self.hotshot.peer_scores.banned_peers().await
+    }
+
+    /// Lift the ban on `peer` and reset its score. Returns whether it was banned.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn clear_ban(&self, peer: &TYPES::SignatureKey) -> bool {
+        self.hotshot.peer_scores.clear_ban(peer).await
+    }
+
+    /// Lift every ban and reset every peer's score.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn clear_all_bans(&self) {
+        self.hotshot.peer_scores.clear_all_bans().await;
+    }
+
+    /// The DA committee's threshold key for the current epoch, which clients encrypt transactions
+    /// to. Returns `None` if the encrypted mempool is disabled.
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn mempool_encryption_key(&self) -> Option<Vec<u8>> {
+        let epoch = self.cur_epoch().await;
+        self.hotshot
+            .instance_state()
+            .threshold_decryption()?
+            .encryption_key(*epoch)
+    }
+
+    /// The validators and stakes of the current and next epochs, signed by this node, for external
+    /// systems which follow the validator set of consensus.
+    ///
+    /// # Errors
+    /// Returns an error if signing the validator sets fails
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn validator_set_attestation(&self) -> Result<ValidatorSetAttestation<TYPES>> {
+        let (view, epoch) = {
+            let consensus = self.hotshot.consensus.read().await;
+            (consensus.cur_view(), consensus.cur_epoch())
+        };
+        let sets = ValidatorSets::new(&*self.memberships.read().await, view, epoch);
+
+        Ok(ValidatorSetAttestation::sign(
+            sets,
+            self.public_key(),
+            self.private_key(),
+            &self.hotshot.upgrade_lock,
+        )
+        .await?)
+    }
+
+    /// The recorded timeout and view sync certificates which advanced consensus from the parent of
+    /// a decided `leaf` to the leaf's view, by the view of the proposal they justified.
+    ///
+    /// # Errors
+    /// Returns an error if the evidence cannot be loaded from storage
+    #[instrument(skip_all, target = "SystemContextHandle", fields(id = self.hotshot.id))]
+    pub async fn view_change_evidence_chain(
+        &self,
+        leaf: &Leaf2<TYPES>,
+    ) -> Result<BTreeMap<TYPES::View, ViewChangeEvidence<TYPES>>> {
+        self.storage
+            .read()
+            .await
+            .load_view_change_evidence(leaf.justify_qc().view_number() + 1, leaf.view_number())
+            .await
+            .context("Failed to load view change evidence")
+    }
+
     /// Provides a reference to the underlying storage for this [`SystemContext`], allowing access to
     /// historical data
     #[must_use]
@@ -0,0 +1,253 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Key generation and stake table assembly for bootstrapping a network.
+//!
+//! A network starts from a genesis stake table, the [`PublicKeysFile`] the orchestrator reads
+//! from `ORCHESTRATOR_PUBLIC_KEYS`. [`generate`] creates the keys of validators, with the stake
+//! and DA membership of each, [`assemble`] turns the public half of such validators into a
+//! genesis stake table, and [`validate`] checks that a genesis stake table can run a network.
+
+use std::num::NonZeroUsize;
+
+use hotshot_types::{
+    config_validation::{ConfigViolation, ConfigViolations},
+    light_client::StateVerKey,
+    network::{NetworkConfig, PeerConfigKeys, PublicKeysFile},
+    stake_table::{total_stake, ThresholdConfig},
+    traits::signature_key::{PrivateSignatureKey, SignatureKey},
+    ValidatorConfig,
+};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+/// The keys of validators, with the stake and DA membership of each
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct ValidatorsFile<KEY: SignatureKey> {
+    /// The validators
+    pub validators: Vec<GeneratedValidator<KEY>>,
+}
+
+/// The keys of a validator. The public fields are those of a [`PeerConfigKeys`], so a
+/// [`ValidatorsFile`] can be assembled into a stake table as it is, or with the stakes edited.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct GeneratedValidator<KEY: SignatureKey> {
+    /// Index the keys were generated from the seed with
+    pub index: u64,
+    /// The validator's public key
+    pub stake_table_key: KEY,
+    /// The validator's key for signing light client states
+    pub state_ver_key: StateVerKey,
+    /// The validator's stake
+    pub stake: u64,
+    /// Whether the validator is in the DA committee
+    pub da: bool,
+    /// The validator's private key, in tagged base64
+    pub private_key: String,
+    /// The validator's private key for signing light client states, in tagged base64
+    pub state_sign_key: String,
+}
+
+impl<KEY: SignatureKey> GeneratedValidator<KEY> {
+    /// The entry of the validator in a stake table
+    #[must_use]
+    pub fn public_keys(&self) -> PeerConfigKeys<KEY> {
+        PeerConfigKeys {
+            stake_table_key: self.stake_table_key.clone(),
+            state_ver_key: self.state_ver_key.clone(),
+            stake: self.stake,
+            da: self.da,
+        }
+    }
+}
+
+/// Generate the keys of `count` validators from `seed`, at consecutive indices from
+/// `first_index`, each with `stake`. The first `da_count` of them are in the DA committee.
+///
+/// # Errors
+/// If a private key cannot be encoded
+pub fn generate<KEY: SignatureKey>(
+    seed: [u8; 32],
+    first_index: u64,
+    count: u64,
+    stake: u64,
+    da_count: u64,
+) -> anyhow::Result<ValidatorsFile<KEY>> {
+    let validators = (0..count)
+        .map(|n| {
+            let index = first_index + n;
+            let config = ValidatorConfig::<KEY>::generated_from_seed_indexed(
+                seed,
+                index,
+                stake,
+                n < da_count,
+            );
+
+            Ok(GeneratedValidator {
+                index,
+                stake_table_key: config.public_key.clone(),
+                state_ver_key: config.state_key_pair.ver_key(),
+                stake,
+                da: config.is_da,
+                private_key: config.private_key.to_tagged_base64()?.to_string(),
+                state_sign_key: config.state_key_pair.sign_key_ref().to_string(),
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(ValidatorsFile { validators })
+}
+
+/// The genesis stake table of `validators`, checked with [`validate`]
+///
+/// # Errors
+/// If the stake table cannot run a network
+pub fn assemble<KEY: SignatureKey>(
+    validators: Vec<PeerConfigKeys<KEY>>,
+) -> Result<PublicKeysFile<KEY>, ConfigViolations> {
+    let genesis = PublicKeysFile {
+        public_keys: validators,
+    };
+    validate(&genesis)?;
+
+    Ok(genesis)
+}
+
+/// Totals of a genesis stake table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenesisSummary {
+    /// Number of validators
+    pub nodes: usize,
+    /// Number of validators in the DA committee
+    pub da_nodes: usize,
+    /// Total stake of the validators
+    pub total_stake: U256,
+    /// Stake needed for a quorum certificate
+    pub quorum_stake: U256,
+    /// Stake needed for a DA certificate
+    pub da_quorum_stake: U256,
+}
+
+/// Check that the genesis stake table `genesis` can run a network: it is not empty, no validator
+/// appears twice or without stake, and the DA committee is not empty and has stake.
+///
+/// # Errors
+/// Every violation found, if there are any
+pub fn validate<KEY: SignatureKey>(
+    genesis: &PublicKeysFile<KEY>,
+) -> Result<GenesisSummary, ConfigViolations> {
+    let Some(nodes) = NonZeroUsize::new(genesis.public_keys.len()) else {
+        return Err(ConfigViolations(vec![ConfigViolation::NoStake]));
+    };
+    let da_nodes = genesis.public_keys.iter().filter(|keys| keys.da).count();
+
+    // Check the stake table as the orchestrator would run it
+    let mut network_config = NetworkConfig::<KEY>::default();
+    network_config.public_keys.clone_from(&genesis.public_keys);
+    network_config.config.num_nodes_with_stake = nodes;
+    network_config.config.da_staked_committee_size = da_nodes;
+    network_config.apply_public_keys();
+    network_config.validate()?;
+
+    let config = &network_config.config;
+    let stake_table: Vec<_> = config
+        .known_nodes_with_stake
+        .iter()
+        .map(|peer| peer.stake_table_entry.clone())
+        .collect();
+    let da_stake_table: Vec<_> = config
+        .known_da_nodes
+        .iter()
+        .map(|peer| peer.stake_table_entry.clone())
+        .collect();
+    let thresholds = ThresholdConfig::default();
+
+    Ok(GenesisSummary {
+        nodes: nodes.get(),
+        da_nodes,
+        total_stake: total_stake::<KEY, _>(&stake_table),
+        quorum_stake: thresholds.success.for_stake_table::<KEY, _>(&stake_table),
+        da_quorum_stake: thresholds
+            .da_success
+            .for_stake_table::<KEY, _>(&da_stake_table),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use hotshot_types::{
+        config_validation::ConfigViolation, network::PublicKeysFile, signature_key::BLSPubKey,
+    };
+    use primitive_types::U256;
+
+    use super::{assemble, generate, validate};
+
+    #[test]
+    fn generated_validators_assemble_into_a_valid_stake_table() {
+        let validators = generate::<BLSPubKey>([7u8; 32], 0, 4, 10, 2).unwrap();
+        assert_eq!(validators.validators.len(), 4);
+
+        // The keys round trip through the file, and the private fields are dropped on assembly
+        let file = toml::to_string(&validators).unwrap();
+        let mut validators: super::ValidatorsFile<BLSPubKey> = toml::from_str(&file).unwrap();
+        validators.validators[3].stake = 40;
+        let genesis = assemble(
+            validators
+                .validators
+                .iter()
+                .map(super::GeneratedValidator::public_keys)
+                .collect(),
+        )
+        .unwrap();
+        let genesis: PublicKeysFile<BLSPubKey> =
+            toml::from_str(&toml::to_string(&genesis).unwrap()).unwrap();
+
+        let summary = validate(&genesis).unwrap();
+        assert_eq!(summary.nodes, 4);
+        assert_eq!(summary.da_nodes, 2);
+        assert_eq!(summary.total_stake, U256::from(70u64));
+        assert_eq!(summary.quorum_stake, U256::from(47));
+        assert_eq!(summary.da_quorum_stake, U256::from(14));
+    }
+
+    #[test]
+    fn invalid_stake_tables_are_rejected() {
+        let validators = generate::<BLSPubKey>([7u8; 32], 0, 3, 1, 1).unwrap();
+        let mut public_keys: Vec<_> = validators
+            .validators
+            .iter()
+            .map(super::GeneratedValidator::public_keys)
+            .collect();
+        public_keys[1].stake = 0;
+        public_keys.push(public_keys[0].clone());
+
+        let violations = assemble(public_keys).unwrap_err();
+        assert!(violations
+            .0
+            .iter()
+            .any(|violation| matches!(violation, ConfigViolation::ZeroStakeNode(_))));
+        assert!(violations
+            .0
+            .iter()
+            .any(|violation| matches!(violation, ConfigViolation::DuplicateNode(_))));
+
+        // Without a DA committee
+        let public_keys = generate::<BLSPubKey>([7u8; 32], 0, 3, 1, 0)
+            .unwrap()
+            .validators
+            .iter()
+            .map(super::GeneratedValidator::public_keys)
+            .collect();
+        assert!(assemble(public_keys).is_err());
+
+        assert!(validate(&PublicKeysFile::<BLSPubKey> {
+            public_keys: Vec::new()
+        })
+        .is_err());
+    }
+}
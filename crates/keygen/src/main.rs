@@ -0,0 +1,145 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Generates validator keys, and assembles and validates genesis stake tables.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use hotshot_keygen::{assemble, generate, validate};
+use hotshot_types::{
+    network::{PeerConfigKeys, PublicKeysFile},
+    signature_key::BLSPubKey,
+};
+use rand::RngCore;
+use serde::{de::DeserializeOwned, Deserialize};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+/// Bootstrapping of HotShot networks
+struct Args {
+    /// What to do
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate the keys of validators
+    Generate {
+        /// Number of validators
+        #[arg(short, long, default_value_t = 1)]
+        count: u64,
+        /// Seed to derive the keys from, as 64 hex digits. A random seed is used if not provided
+        #[arg(long)]
+        seed: Option<String>,
+        /// Index of the first validator's keys
+        #[arg(long, default_value_t = 0)]
+        first_index: u64,
+        /// Stake of each validator
+        #[arg(long, default_value_t = 1)]
+        stake: u64,
+        /// Number of validators, from the first, in the DA committee
+        #[arg(long, default_value_t = 0)]
+        da_count: u64,
+        /// Where to write the keys. If not provided, they are written to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Assemble a genesis stake table from the public keys, stakes and DA membership of
+    /// validators, such as those written by `generate`
+    Assemble {
+        /// TOML file with a `validators` list of `stake_table_key`, `state_ver_key`, `stake` and
+        /// `da`. Any other fields, such as private keys, are left out of the stake table
+        validators: PathBuf,
+        /// Where to write the stake table. If not provided, it is written to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check that a genesis stake table can run a network
+    Validate {
+        /// The stake table, as read by the orchestrator from `ORCHESTRATOR_PUBLIC_KEYS`
+        genesis: PathBuf,
+    },
+}
+
+/// The public half of a list of validators
+#[derive(Deserialize)]
+struct Validators {
+    /// The validators
+    validators: Vec<PeerConfigKeys<BLSPubKey>>,
+}
+
+/// Parse a seed of 64 hex digits
+fn parse_seed(seed: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(seed.trim_start_matches("0x")).context("the seed is not hex")?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow::anyhow!("the seed is {} bytes, not 32", bytes.len()))
+}
+
+/// Read `path` as TOML
+fn read<T: DeserializeOwned>(path: &PathBuf) -> Result<T> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Write `text` to `output`, or to stdout if `None`
+fn write(output: Option<PathBuf>, text: &str) -> Result<()> {
+    match output {
+        Some(output) => fs::write(&output, text)
+            .with_context(|| format!("failed to write {}", output.display())),
+        None => {
+            print!("{text}");
+            Ok(())
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    match Args::parse().command {
+        Command::Generate {
+            count,
+            seed,
+            first_index,
+            stake,
+            da_count,
+            output,
+        } => {
+            let seed = match seed {
+                Some(seed) => parse_seed(&seed)?,
+                None => {
+                    let mut seed = [0u8; 32];
+                    rand::thread_rng().fill_bytes(&mut seed);
+                    seed
+                }
+            };
+            let validators = generate::<BLSPubKey>(seed, first_index, count, stake, da_count)?;
+            write(output, &toml::to_string(&validators)?)?;
+        }
+        Command::Assemble { validators, output } => {
+            let validators: Validators = read(&validators)?;
+            let genesis = assemble(validators.validators)?;
+            write(output, &toml::to_string(&genesis)?)?;
+        }
+        Command::Validate { genesis } => {
+            let genesis: PublicKeysFile<BLSPubKey> = read(&genesis)?;
+            let summary = validate(&genesis)?;
+            println!(
+                "{} validators, {} in the DA committee. Total stake {}, of which {} forms a quorum and {} of the DA committee's forms a DA certificate",
+                summary.nodes,
+                summary.da_nodes,
+                summary.total_stake,
+                summary.quorum_stake,
+                summary.da_quorum_stake
+            );
+        }
+    }
+
+    Ok(())
+}
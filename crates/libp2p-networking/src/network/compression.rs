@@ -0,0 +1,390 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Compression of large messages, negotiated per connection.
+//!
+//! Every node advertises the algorithms it is willing to send in the agent version of the identify
+//! protocol. A direct message is compressed with the first of our algorithms the recipient also
+//! advertises. Gossip is relayed beyond our own connections, so a broadcast is only compressed
+//! with an algorithm every connected peer advertises. Messages smaller than the threshold are
+//! always sent as they are.
+//!
+//! Compressed messages are framed with a header which no serialized message starts with, so
+//! uncompressed messages, including those from peers which don't compress at all, pass through
+//! [`decompress`] unchanged.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use hotshot_types::traits::network::NetworkError;
+use libp2p_identity::PeerId;
+
+use super::node::MAX_GOSSIP_MSG_SIZE;
+
+/// Prefix of the agent version advertising the algorithms a node supports
+pub const AGENT_VERSION_PREFIX: &str = "HotShot/compression:";
+
+/// Messages smaller than this many bytes are not compressed by default
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 16 * 1024;
+
+/// Start of every compressed message. Serialized messages start with their version, and no
+/// version has a major number of `0xffff`.
+const MAGIC: [u8; 4] = [0xff, 0xff, b'H', b'C'];
+
+/// Length of the header of a compressed message: the magic bytes, the algorithm and the length
+/// of the uncompressed message
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4;
+
+/// Compression level used for zstd, which favours speed
+const ZSTD_LEVEL: i32 = 3;
+
+/// A compression algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Send messages as they are
+    None,
+    /// Zstandard
+    Zstd,
+    /// LZ4
+    Lz4,
+}
+
+impl Compression {
+    /// The identifier of the algorithm in the header of a compressed message
+    fn id(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Zstd => 1,
+            Self::Lz4 => 2,
+        }
+    }
+
+    /// The algorithm with identifier `id`
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::None),
+            1 => Some(Self::Zstd),
+            2 => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Zstd => "zstd",
+            Self::Lz4 => "lz4",
+        })
+    }
+}
+
+impl FromStr for Compression {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "zstd" => Ok(Self::Zstd),
+            "lz4" => Ok(Self::Lz4),
+            _ => Err(NetworkError::ConfigError(format!(
+                "unknown compression algorithm: {s}"
+            ))),
+        }
+    }
+}
+
+/// Configuration of message compression
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// the algorithms this node is willing to send, in order of preference
+    pub algorithms: Vec<Compression>,
+    /// messages smaller than this many bytes are not compressed
+    pub threshold: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithms: vec![Compression::Zstd, Compression::Lz4],
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// A configuration which never compresses
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            algorithms: Vec::new(),
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+        }
+    }
+
+    /// The agent version advertising our algorithms to peers
+    #[must_use]
+    pub fn agent_version(&self) -> String {
+        let algorithms = self
+            .algorithms
+            .iter()
+            .filter(|algorithm| **algorithm != Compression::None)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!("{AGENT_VERSION_PREFIX}{algorithms}")
+    }
+}
+
+/// The algorithms advertised in a peer's agent version, which is empty for peers which don't
+/// compress
+#[must_use]
+pub fn parse_agent_version(agent_version: &str) -> Vec<Compression> {
+    agent_version
+        .strip_prefix(AGENT_VERSION_PREFIX)
+        .map(|algorithms| {
+            algorithms
+                .split(',')
+                .filter_map(|algorithm| algorithm.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The algorithms advertised by each connected peer, and the choice of algorithm for a message
+#[derive(Clone, Debug, Default)]
+pub struct PeerCompression {
+    /// our configuration
+    config: CompressionConfig,
+    /// peer -> the algorithms it advertised
+    peers: Arc<RwLock<HashMap<PeerId, Vec<Compression>>>>,
+}
+
+impl PeerCompression {
+    /// No peers yet, with our configuration `config`
+    #[must_use]
+    pub fn new(config: CompressionConfig) -> Self {
+        Self {
+            config,
+            peers: Arc::default(),
+        }
+    }
+
+    /// Our configuration
+    #[must_use]
+    pub fn config(&self) -> &CompressionConfig {
+        &self.config
+    }
+
+    /// Record the agent version `peer` identified itself with
+    pub fn identified(&self, peer: PeerId, agent_version: &str) {
+        self.peers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(peer, parse_agent_version(agent_version));
+    }
+
+    /// Forget `peer` after its last connection closed
+    pub fn disconnected(&self, peer: &PeerId) {
+        self.peers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(peer);
+    }
+
+    /// The algorithm to send direct messages to `peer` with
+    #[must_use]
+    pub fn for_peer(&self, peer: &PeerId) -> Compression {
+        let peers = self.peers.read().unwrap_or_else(PoisonError::into_inner);
+        let Some(theirs) = peers.get(peer) else {
+            return Compression::None;
+        };
+
+        self.config
+            .algorithms
+            .iter()
+            .copied()
+            .find(|algorithm| theirs.contains(algorithm))
+            .unwrap_or(Compression::None)
+    }
+
+    /// The algorithm to broadcast with, which every connected peer supports
+    #[must_use]
+    pub fn for_broadcast(&self) -> Compression {
+        let peers = self.peers.read().unwrap_or_else(PoisonError::into_inner);
+        if peers.is_empty() {
+            return Compression::None;
+        }
+
+        self.config
+            .algorithms
+            .iter()
+            .copied()
+            .find(|algorithm| peers.values().all(|theirs| theirs.contains(algorithm)))
+            .unwrap_or(Compression::None)
+    }
+
+    /// Compress `message` with `algorithm`, unless it is below the threshold or doesn't get any
+    /// smaller
+    #[must_use]
+    pub fn compress(&self, message: Vec<u8>, algorithm: Compression) -> Vec<u8> {
+        if message.len() < self.config.threshold {
+            return message;
+        }
+
+        compress(&message, algorithm).unwrap_or(message)
+    }
+}
+
+/// Compress `message` with `algorithm`, returning `None` if it doesn't get any smaller
+#[must_use]
+pub fn compress(message: &[u8], algorithm: Compression) -> Option<Vec<u8>> {
+    let raw_len = u32::try_from(message.len()).ok()?;
+    let compressed = match algorithm {
+        Compression::None => return None,
+        Compression::Zstd => zstd::bulk::compress(message, ZSTD_LEVEL).ok()?,
+        Compression::Lz4 => lz4_flex::block::compress(message),
+    };
+    if HEADER_LEN + compressed.len() >= message.len() {
+        return None;
+    }
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + compressed.len());
+    frame.extend_from_slice(&MAGIC);
+    frame.push(algorithm.id());
+    frame.extend_from_slice(&raw_len.to_le_bytes());
+    frame.extend_from_slice(&compressed);
+
+    Some(frame)
+}
+
+/// Decompress `message` if it is compressed, and return it unchanged otherwise
+///
+/// # Errors
+/// If the message is compressed with an unknown algorithm, claims to be larger than any message
+/// may be, or is corrupt
+pub fn decompress(message: Vec<u8>) -> Result<Vec<u8>, NetworkError> {
+    if message.len() < HEADER_LEN || message[..MAGIC.len()] != MAGIC {
+        return Ok(message);
+    }
+
+    let algorithm = Compression::from_id(message[MAGIC.len()]).ok_or_else(|| {
+        NetworkError::FailedToDeserialize(format!(
+            "unknown compression algorithm {}",
+            message[MAGIC.len()]
+        ))
+    })?;
+    let mut raw_len = [0u8; 4];
+    raw_len.copy_from_slice(&message[MAGIC.len() + 1..HEADER_LEN]);
+    let raw_len = u32::from_le_bytes(raw_len) as usize;
+    if raw_len > MAX_GOSSIP_MSG_SIZE {
+        return Err(NetworkError::FailedToDeserialize(format!(
+            "compressed message claims to be {raw_len} bytes"
+        )));
+    }
+
+    let compressed = &message[HEADER_LEN..];
+    let raw = match algorithm {
+        Compression::None => Ok(compressed.to_vec()),
+        Compression::Zstd => {
+            zstd::bulk::decompress(compressed, raw_len).map_err(|err| err.to_string())
+        }
+        Compression::Lz4 => {
+            lz4_flex::block::decompress(compressed, raw_len).map_err(|err| err.to_string())
+        }
+    }
+    .map_err(|err| {
+        NetworkError::FailedToDeserialize(format!(
+            "failed to decompress {algorithm} message: {err}"
+        ))
+    })?;
+
+    if raw.len() != raw_len {
+        return Err(NetworkError::FailedToDeserialize(format!(
+            "{algorithm} message decompressed to {} bytes, expected {raw_len}",
+            raw.len()
+        )));
+    }
+
+    Ok(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A large, compressible message starting with a version number
+    fn message() -> Vec<u8> {
+        let mut message = vec![0, 0, 1, 0];
+        message.extend((0..64 * 1024).map(|i| (i % 7) as u8));
+        message
+    }
+
+    #[test]
+    fn test_compression_round_trip() {
+        for algorithm in [Compression::Zstd, Compression::Lz4] {
+            let compressed = compress(&message(), algorithm).unwrap();
+            assert!(compressed.len() < message().len());
+            assert_eq!(decompress(compressed).unwrap(), message());
+        }
+
+        // Uncompressed messages pass through
+        assert_eq!(decompress(message()).unwrap(), message());
+        assert!(compress(&message(), Compression::None).is_none());
+    }
+
+    #[test]
+    fn test_compression_rejects_corrupt_messages() {
+        let mut compressed = compress(&message(), Compression::Zstd).unwrap();
+        compressed[MAGIC.len()] = 9;
+        assert!(decompress(compressed).is_err());
+
+        let mut compressed = compress(&message(), Compression::Lz4).unwrap();
+        compressed.truncate(compressed.len() / 2);
+        assert!(decompress(compressed).is_err());
+    }
+
+    #[test]
+    fn test_compression_negotiation() {
+        let ours = PeerCompression::new(CompressionConfig::default());
+        let zstd_and_lz4 = PeerId::random();
+        let lz4 = PeerId::random();
+        let old = PeerId::random();
+
+        // Nothing is compressed before peers identify themselves
+        assert_eq!(ours.for_broadcast(), Compression::None);
+        assert_eq!(ours.for_peer(&zstd_and_lz4), Compression::None);
+
+        ours.identified(zstd_and_lz4, &CompressionConfig::default().agent_version());
+        ours.identified(
+            lz4,
+            &CompressionConfig {
+                algorithms: vec![Compression::Lz4],
+                ..CompressionConfig::default()
+            }
+            .agent_version(),
+        );
+        assert_eq!(ours.for_peer(&zstd_and_lz4), Compression::Zstd);
+        assert_eq!(ours.for_peer(&lz4), Compression::Lz4);
+        assert_eq!(ours.for_broadcast(), Compression::Lz4);
+
+        // A peer which doesn't compress disables compression of broadcasts until it disconnects
+        ours.identified(old, "rust-libp2p/0.54.1");
+        assert_eq!(ours.for_peer(&old), Compression::None);
+        assert_eq!(ours.for_broadcast(), Compression::None);
+        ours.disconnected(&old);
+        assert_eq!(ours.for_broadcast(), Compression::Lz4);
+
+        // Small messages are sent as they are
+        assert_eq!(ours.compress(vec![0; 16], Compression::Zstd), vec![0; 16]);
+    }
+}
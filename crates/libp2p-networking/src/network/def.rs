@@ -6,11 +6,13 @@
 
 use hotshot_types::traits::signature_key::SignatureKey;
 use libp2p::{
-    autonat,
+    autonat, dcutr,
     gossipsub::{Behaviour as GossipBehaviour, Event as GossipEvent, IdentTopic},
     identify::{Behaviour as IdentifyBehaviour, Event as IdentifyEvent},
     kad::store::MemoryStore,
+    relay,
     request_response::{OutboundRequestId, ResponseChannel},
+    swarm::behaviour::toggle::Toggle,
     Multiaddr,
 };
 use libp2p_identity::PeerId;
@@ -54,17 +56,33 @@ pub struct NetworkDef<K: SignatureKey + 'static> {
     /// by which address
     #[debug(skip)]
     pub autonat: libp2p::autonat::Behaviour,
+
+    /// purpose: reaching peers and being reached through relays when behind a NAT
+    #[debug(skip)]
+    pub relay_client: relay::client::Behaviour,
+
+    /// purpose: relaying connections for peers behind a NAT, if we serve as a relay
+    #[debug(skip)]
+    pub relay_server: Toggle<relay::Behaviour>,
+
+    /// purpose: replacing relayed connections with direct ones by hole punching
+    #[debug(skip)]
+    pub dcutr: Toggle<dcutr::Behaviour>,
 }
 
 impl<K: SignatureKey + 'static> NetworkDef<K> {
     /// Create a new instance of a `NetworkDef`
     #[must_use]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         gossipsub: GossipBehaviour,
         dht: libp2p::kad::Behaviour<FileBackedStore<ValidatedStore<MemoryStore, K>>>,
         identify: IdentifyBehaviour,
         direct_message: super::cbor::Behaviour<Vec<u8>, Vec<u8>>,
         autonat: autonat::Behaviour,
+        relay_client: relay::client::Behaviour,
+        relay_server: Option<relay::Behaviour>,
+        dcutr: Option<dcutr::Behaviour>,
     ) -> NetworkDef<K> {
         Self {
             gossipsub,
@@ -72,6 +90,9 @@ impl<K: SignatureKey + 'static> NetworkDef<K> {
             identify,
             direct_message,
             autonat,
+            relay_client,
+            relay_server: relay_server.into(),
+            dcutr: dcutr.into(),
         }
     }
 }
@@ -154,3 +175,21 @@ impl From<libp2p::autonat::Event> for NetworkEventInternal {
         Self::AutonatEvent(event)
     }
 }
+
+impl From<relay::client::Event> for NetworkEventInternal {
+    fn from(event: relay::client::Event) -> Self {
+        Self::RelayClientEvent(Box::new(event))
+    }
+}
+
+impl From<relay::Event> for NetworkEventInternal {
+    fn from(event: relay::Event) -> Self {
+        Self::RelayServerEvent(Box::new(event))
+    }
+}
+
+impl From<dcutr::Event> for NetworkEventInternal {
+    fn from(event: dcutr::Event) -> Self {
+        Self::DcutrEvent(Box::new(event))
+    }
+}
@@ -16,21 +16,24 @@ pub mod transport;
 /// Forked `cbor` codec with altered request/response sizes
 pub mod cbor;
 
-use std::{collections::HashSet, fmt::Debug, sync::Arc};
+/// Compression of large messages, negotiated per connection
+pub mod compression;
+
+use std::{collections::HashSet, fmt::Debug, io, sync::Arc};
 
 use async_lock::RwLock;
 use futures::channel::oneshot::Sender;
 use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
 use libp2p::{
     build_multiaddr,
-    core::{muxing::StreamMuxerBox, transport::Boxed},
+    core::{muxing::StreamMuxerBox, transport::Boxed, upgrade},
     dns::tokio::Transport as DnsTransport,
     gossipsub::Event as GossipEvent,
     identify::Event as IdentifyEvent,
     identity::Keypair,
-    quic,
+    noise, quic, relay,
     request_response::ResponseChannel,
-    Multiaddr, Transport,
+    yamux, Multiaddr, Transport,
 };
 use libp2p_identity::PeerId;
 use quic::tokio::Transport as QuicTransport;
@@ -41,7 +44,7 @@ pub use self::{
     def::NetworkDef,
     node::{
         spawn_network_node, GossipConfig, NetworkNode, NetworkNodeConfig, NetworkNodeConfigBuilder,
-        NetworkNodeConfigBuilderError, NetworkNodeHandle, NetworkNodeReceiver,
+        NetworkNodeConfigBuilderError, NetworkNodeHandle, NetworkNodeReceiver, RelayConfig,
         RequestResponseConfig, DEFAULT_REPLICATION_FACTOR,
     },
 };
@@ -104,6 +107,8 @@ pub enum ClientRequest {
     GetRoutingTable(Sender<()>),
     /// Get address of peer
     LookupPeer(PeerId, Sender<()>),
+    /// Connect to a peer we are not connected to, using the addresses kademlia knows for it
+    Dial(PeerId),
 }
 
 /// events generated by the swarm that we wish
@@ -120,6 +125,8 @@ pub enum NetworkEvent {
     IsBootstrapped,
     /// The number of connected peers has possibly changed
     ConnectedPeersUpdate(usize),
+    /// The number of peers we are connected to through a relay has possibly changed
+    RelayedPeersUpdate(usize),
 }
 
 #[derive(Debug)]
@@ -137,6 +144,12 @@ pub enum NetworkEventInternal {
     DMEvent(libp2p::request_response::Event<Vec<u8>, Vec<u8>>),
     /// a autonat event
     AutonatEvent(libp2p::autonat::Event),
+    /// an event of our relay client
+    RelayClientEvent(Box<relay::client::Event>),
+    /// an event of the relay we serve
+    RelayServerEvent(Box<relay::Event>),
+    /// a hole punching event
+    DcutrEvent(Box<libp2p::dcutr::Event>),
 }
 
 /// Bind all interfaces on port `port`
@@ -155,13 +168,17 @@ type BoxedTransport = Boxed<(PeerId, StreamMuxerBox)>;
 /// If the stake table or authentication message is not provided, the transport will
 /// not participate in stake table authentication.
 ///
+/// Connections through a relay go over `relay_transport`, and are secured and authenticated
+/// against the stake table end to end, as the relay is not trusted.
+///
 /// # Errors
 /// If we could not create a DNS transport
-#[instrument(skip(identity))]
+#[instrument(skip(identity, relay_transport))]
 pub async fn gen_transport<T: NodeType>(
     identity: Keypair,
     stake_table: Option<Arc<RwLock<T::Membership>>>,
     auth_message: Option<Vec<u8>>,
+    relay_transport: relay::client::Transport,
 ) -> Result<BoxedTransport, NetworkError> {
     // Create the initial `Quic` transport
     let transport = {
@@ -170,9 +187,26 @@ pub async fn gen_transport<T: NodeType>(
         QuicTransport::new(config)
     };
 
-    // Require authentication against the stake table
+    // Relayed streams are not secured or multiplexed by the relay, so upgrade them ourselves
+    let relay_transport =
+        relay_transport
+            .upgrade(upgrade::Version::V1Lazy)
+            .authenticate(noise::Config::new(&identity).map_err(|e| {
+                NetworkError::ConfigError(format!("failed to build noise config: {e}"))
+            })?)
+            .multiplex(yamux::Config::default())
+            .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+            .map_err(io::Error::other);
+
+    // Require authentication against the stake table, directly or through a relay
     let transport: StakeTableAuthentication<_, T, _> =
-        StakeTableAuthentication::new(transport, stake_table, auth_message);
+        StakeTableAuthentication::new(transport, stake_table.clone(), auth_message.clone());
+    let relay_transport: StakeTableAuthentication<_, T, _> =
+        StakeTableAuthentication::new(relay_transport, stake_table, auth_message);
+    let transport = transport
+        .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+        .or_transport(relay_transport)
+        .map(|output, _| output.into_inner());
 
     // Support DNS resolution
     let transport = {
@@ -182,7 +216,5 @@ pub async fn gen_transport<T: NodeType>(
     }
     .map_err(|e| NetworkError::ConfigError(format!("failed to build DNS transport: {e}")))?;
 
-    Ok(transport
-        .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
-        .boxed())
+    Ok(transport.boxed())
 }
@@ -25,6 +25,7 @@ use hotshot_types::{
 use libp2p::{
     autonat,
     core::transport::ListenerId,
+    dcutr,
     gossipsub::{
         Behaviour as Gossipsub, ConfigBuilder as GossipsubConfigBuilder, Event as GossipEvent,
         Message as GossipsubMessage, MessageAuthenticity, MessageId, Topic, ValidationMode,
@@ -35,10 +36,14 @@ use libp2p::{
     },
     identity::Keypair,
     kad::{store::MemoryStore, Behaviour, Config, Mode, Record},
+    relay,
     request_response::{
         Behaviour as RequestResponse, Config as Libp2pRequestResponseConfig, ProtocolSupport,
     },
-    swarm::SwarmEvent,
+    swarm::{
+        dial_opts::{DialOpts, PeerCondition},
+        SwarmEvent,
+    },
     Multiaddr, StreamProtocol, Swarm, SwarmBuilder,
 };
 use libp2p_identity::PeerId;
@@ -52,7 +57,7 @@ use tracing::{debug, error, info, info_span, instrument, warn, Instrument};
 pub use self::{
     config::{
         GossipConfig, NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeConfigBuilderError,
-        RequestResponseConfig, DEFAULT_REPLICATION_FACTOR,
+        RelayConfig, RequestResponseConfig, DEFAULT_REPLICATION_FACTOR,
     },
     handle::{spawn_network_node, NetworkNodeHandle, NetworkNodeReceiver},
 };
@@ -62,6 +67,7 @@ use super::{
         store::{file_backed::FileBackedStore, validated::ValidatedStore},
     },
     cbor::Cbor,
+    compression::PeerCompression,
     gen_transport, BoxedTransport, ClientRequest, NetworkDef, NetworkError, NetworkEvent,
     NetworkEventInternal,
 };
@@ -88,14 +94,22 @@ pub struct NetworkNode<T: NodeType> {
     /// the swarm of networkbehaviours
     #[debug(skip)]
     swarm: Swarm<NetworkDef<T::SignatureKey>>,
-    /// the listener id we are listening on, if it exists
-    listener_id: Option<ListenerId>,
+    /// the ids of the listeners on our bind addresses
+    listener_ids: Vec<ListenerId>,
     /// Handler for direct messages
     direct_message_state: DMBehaviour,
     /// Handler for DHT Events
     dht_handler: DHTBehaviour<T::SignatureKey>,
     /// Channel to resend requests, set to Some when we call `spawn_listeners`
     resend_tx: Option<UnboundedSender<ClientRequest>>,
+    /// The compression algorithms of connected peers
+    peer_compression: PeerCompression,
+    /// Configuration of NAT traversal through relays
+    relay_config: RelayConfig,
+    /// The listeners through our relays, which exist while we are not publicly reachable
+    relay_listeners: Vec<ListenerId>,
+    /// peer -> the number of our connections to it which go through a relay
+    relayed_connections: HashMap<PeerId, usize>,
 }
 
 impl<T: NodeType> NetworkNode<T> {
@@ -109,20 +123,39 @@ impl<T: NodeType> NetworkNode<T> {
         self.swarm.connected_peers().copied().collect()
     }
 
-    /// starts the swarm listening on `listen_addr`
-    /// and optionally dials into peer `known_peer`
+    /// starts the swarm listening on `listen_addr`, in addition to any addresses it is
+    /// already listening on
     /// returns the address the swarm is listening upon
     #[instrument(skip(self))]
     pub async fn start_listen(
         &mut self,
         listen_addr: Multiaddr,
     ) -> Result<Multiaddr, NetworkError> {
-        self.listener_id = Some(self.swarm.listen_on(listen_addr).map_err(|err| {
+        let listener_id = self.swarm.listen_on(listen_addr).map_err(|err| {
             NetworkError::ListenError(format!("failed to listen for Libp2p: {err}"))
-        })?);
+        })?;
+        self.listener_ids.push(listener_id);
         let addr = loop {
-            if let Some(SwarmEvent::NewListenAddr { address, .. }) = self.swarm.next().await {
-                break address;
+            match self.swarm.next().await {
+                Some(SwarmEvent::NewListenAddr {
+                    listener_id: id,
+                    address,
+                }) if id == listener_id => break address,
+                Some(SwarmEvent::ListenerClosed {
+                    listener_id: id,
+                    reason: Err(err),
+                    ..
+                }) if id == listener_id => {
+                    return Err(NetworkError::ListenError(format!(
+                        "failed to listen for Libp2p: {err}"
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    return Err(NetworkError::ListenError(
+                        "swarm stopped before listening".to_string(),
+                    ));
+                }
             }
         };
         info!("Libp2p listening on {:?}", addr);
@@ -149,12 +182,36 @@ impl<T: NodeType> NetworkNode<T> {
         }
     }
 
+    /// Listen through our relays while `AutoNAT` finds that we are not publicly reachable, and
+    /// stop once we are
+    fn update_relay_listeners(&mut self, status: &autonat::NatStatus) {
+        match status {
+            autonat::NatStatus::Private if self.relay_listeners.is_empty() => {
+                for addr in self.relay_config.circuit_addrs(&self.peer_id) {
+                    match self.swarm.listen_on(addr.clone()) {
+                        Ok(listener_id) => {
+                            info!("Not publicly reachable, listening through relay {addr}");
+                            self.relay_listeners.push(listener_id);
+                        }
+                        Err(err) => warn!("Failed to listen through relay {addr}: {err}"),
+                    }
+                }
+            }
+            autonat::NatStatus::Public(_) => {
+                for listener_id in self.relay_listeners.drain(..) {
+                    self.swarm.remove_listener(listener_id);
+                }
+            }
+            autonat::NatStatus::Private | autonat::NatStatus::Unknown => {}
+        }
+    }
+
     /// Creates a new `Network` with the given settings.
     ///
     /// Currently:
     ///   * Generates a random key pair and associated [`PeerId`]
     ///   * Launches a hopefully production ready transport:
-    ///       QUIC v1 (RFC 9000) + DNS
+    ///       QUIC v1 (RFC 9000) + DNS, and circuits through relays
     ///   * Generates a connection to the "broadcast" topic
     ///   * Creates a swarm to manage peers and events
     #[instrument]
@@ -168,11 +225,15 @@ impl<T: NodeType> NetworkNode<T> {
         // Get the `PeerId` from the `KeyPair`
         let peer_id = PeerId::from(keypair.public());
 
+        // The relay client dials and listens through relays with a transport of its own
+        let (relay_transport, relay_client) = relay::client::new(peer_id);
+
         // Generate the transport from the keypair, stake table, and auth message
         let transport: BoxedTransport = gen_transport::<T>(
             keypair.clone(),
             config.stake_table.clone(),
             config.auth_message.clone(),
+            relay_transport,
         )
         .await?;
 
@@ -228,8 +289,10 @@ impl<T: NodeType> NetworkNode<T> {
             //   node connection information
             //   E.g. this will answer the question: how are other nodes
             //   seeing the peer from behind a NAT
+            //   It also advertises the compression algorithms we support
             let identify_cfg =
-                IdentifyConfig::new("HotShot/identify/1.0".to_string(), keypair.public());
+                IdentifyConfig::new("HotShot/identify/1.0".to_string(), keypair.public())
+                    .with_agent_version(config.compression.agent_version());
             let identify = IdentifyBehaviour::new(identify_cfg);
 
             // - Build DHT needed for peer discovery
@@ -295,12 +358,33 @@ impl<T: NodeType> NetworkNode<T> {
                 ..Default::default()
             };
 
+            // Relay connections for peers behind a NAT, if configured to
+            let relay_server = config.relay.serve.then(|| {
+                relay::Behaviour::new(
+                    peer_id,
+                    relay::Config {
+                        max_reservations: config.relay.max_reservations,
+                        max_circuits: config.relay.max_circuits,
+                        max_circuit_duration: config.relay.max_circuit_duration,
+                        max_circuit_bytes: config.relay.max_circuit_bytes,
+                        ..Default::default()
+                    },
+                )
+            });
+            let dcutr = config
+                .relay
+                .hole_punching
+                .then(|| dcutr::Behaviour::new(peer_id));
+
             let network = NetworkDef::new(
                 gossipsub,
                 kadem,
                 identify,
                 direct_message,
                 autonat::Behaviour::new(peer_id, autonat_config),
+                relay_client,
+                relay_server,
+                dcutr,
             );
 
             // build swarm
@@ -319,11 +403,21 @@ impl<T: NodeType> NetworkNode<T> {
                 swarm.behaviour_mut().add_address(peer, addr.clone());
             }
         }
+        // Relays are publicly reachable, so they can also tell us whether we are
+        for (peer, addr) in &config.relay.relay_nodes {
+            if peer != swarm.local_peer_id() {
+                swarm.behaviour_mut().add_address(peer, addr.clone());
+                swarm
+                    .behaviour_mut()
+                    .autonat
+                    .add_server(*peer, Some(addr.clone()));
+            }
+        }
 
         Ok(Self {
             peer_id,
             swarm,
-            listener_id: None,
+            listener_ids: Vec::new(),
             direct_message_state: DMBehaviour::default(),
             dht_handler: DHTBehaviour::new(
                 peer_id,
@@ -332,6 +426,10 @@ impl<T: NodeType> NetworkNode<T> {
                     .unwrap_or(NonZeroUsize::new(4).unwrap()),
             ),
             resend_tx: None,
+            peer_compression: PeerCompression::new(config.compression.clone()),
+            relay_config: config.relay.clone(),
+            relay_listeners: Vec::new(),
+            relayed_connections: HashMap::new(),
         })
     }
 
@@ -392,6 +490,14 @@ impl<T: NodeType> NetworkNode<T> {
                             .in_progress_get_closest_peers
                             .insert(id, chan);
                     }
+                    ClientRequest::Dial(pid) => {
+                        let opts = DialOpts::peer_id(pid)
+                            .condition(PeerCondition::DisconnectedAndNotDialing)
+                            .build();
+                        if let Err(err) = self.swarm.dial(opts) {
+                            debug!("Failed to dial {:?}: {}", pid, err);
+                        }
+                    }
                     ClientRequest::GetRoutingTable(chan) => {
                         self.dht_handler
                             .print_routing_table(&mut self.swarm.behaviour_mut().dht);
@@ -437,7 +543,7 @@ impl<T: NodeType> NetworkNode<T> {
                         // NOTE used by test with conductor only
                     }
                     ClientRequest::Shutdown => {
-                        if let Some(listener_id) = self.listener_id {
+                        for listener_id in self.listener_ids.drain(..) {
                             self.swarm.remove_listener(listener_id);
                         }
 
@@ -530,6 +636,15 @@ impl<T: NodeType> NetworkNode<T> {
                     );
                 }
 
+                if endpoint.is_relayed() {
+                    *self.relayed_connections.entry(peer_id).or_default() += 1;
+                    send_to_client
+                        .send(NetworkEvent::RelayedPeersUpdate(
+                            self.relayed_connections.len(),
+                        ))
+                        .map_err(|err| NetworkError::ChannelSendError(err.to_string()))?;
+                }
+
                 // Send the number of connected peers to the client
                 send_to_client
                     .send(NetworkEvent::ConnectedPeersUpdate(self.num_connected()))
@@ -553,6 +668,22 @@ impl<T: NodeType> NetworkNode<T> {
                         peer_id, endpoint, cause
                     );
                 }
+                if num_established == 0 {
+                    self.peer_compression.disconnected(&peer_id);
+                }
+                if endpoint.is_relayed() {
+                    if let Some(relayed) = self.relayed_connections.get_mut(&peer_id) {
+                        *relayed = relayed.saturating_sub(1);
+                        if *relayed == 0 {
+                            self.relayed_connections.remove(&peer_id);
+                        }
+                    }
+                    send_to_client
+                        .send(NetworkEvent::RelayedPeersUpdate(
+                            self.relayed_connections.len(),
+                        ))
+                        .map_err(|err| NetworkError::ChannelSendError(err.to_string()))?;
+                }
 
                 // Send the number of connected peers to the client
                 send_to_client
@@ -600,12 +731,13 @@ impl<T: NodeType> NetworkNode<T> {
                                     protocols: _,
                                     public_key: _,
                                     protocol_version: _,
-                                    agent_version: _,
+                                    agent_version,
                                     observed_addr: _,
                                 },
                             connection_id: _,
                         } = *e
                         {
+                            self.peer_compression.identified(peer_id, &agent_version);
                             let behaviour = self.swarm.behaviour_mut();
 
                             // into hashset to delete duplicates (I checked: there are duplicates)
@@ -656,10 +788,42 @@ impl<T: NodeType> NetworkNode<T> {
                             },
                             autonat::Event::StatusChanged { old, new } => {
                                 debug!("AutoNAT Status changed. Old: {:?}, New: {:?}", old, new);
+                                self.update_relay_listeners(&new);
                             }
                         };
                         None
                     }
+                    NetworkEventInternal::RelayClientEvent(e) => {
+                        if let relay::client::Event::ReservationReqAccepted {
+                            relay_peer_id, ..
+                        } = *e
+                        {
+                            info!("Reserved a circuit on relay {:?}", relay_peer_id);
+                        } else {
+                            debug!("Relay client event: {:?}", e);
+                        }
+                        None
+                    }
+                    NetworkEventInternal::RelayServerEvent(e) => {
+                        debug!("Relay server event: {:?}", e);
+                        None
+                    }
+                    NetworkEventInternal::DcutrEvent(e) => {
+                        let dcutr::Event {
+                            remote_peer_id,
+                            result,
+                        } = *e;
+                        match result {
+                            Ok(_) => info!(
+                                "Hole punched to {:?}, the relayed connection can be dropped",
+                                remote_peer_id
+                            ),
+                            Err(err) => {
+                                warn!("Failed to hole punch to {:?}: {}", remote_peer_id, err);
+                            }
+                        }
+                        None
+                    }
                 };
 
                 if let Some(event) = maybe_event {
@@ -761,4 +925,9 @@ impl<T: NodeType> NetworkNode<T> {
     pub fn peer_id(&self) -> PeerId {
         self.peer_id
     }
+
+    /// The compression algorithms of connected peers
+    pub fn peer_compression(&self) -> PeerCompression {
+        self.peer_compression.clone()
+    }
 }
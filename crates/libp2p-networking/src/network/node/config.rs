@@ -7,11 +7,14 @@
 use std::{collections::HashSet, num::NonZeroUsize, sync::Arc, time::Duration};
 
 use async_lock::RwLock;
-use hotshot_types::traits::node_implementation::NodeType;
-use libp2p::{identity::Keypair, Multiaddr};
+use hotshot_types::{
+    network::GossipTuning, traits::node_implementation::NodeType, wire_codec::WireFormat,
+};
+use libp2p::{identity::Keypair, multiaddr::Protocol, Multiaddr};
 use libp2p_identity::PeerId;
 
 use super::MAX_GOSSIP_MSG_SIZE;
+use crate::network::compression::CompressionConfig;
 
 /// The default Kademlia replication factor
 pub const DEFAULT_REPLICATION_FACTOR: Option<NonZeroUsize> = NonZeroUsize::new(10);
@@ -28,6 +31,11 @@ pub struct NetworkNodeConfig<T: NodeType> {
     #[builder(default)]
     pub bind_address: Option<Multiaddr>,
 
+    /// Further addresses to bind to, such as one of the other address family for a dual-stack
+    /// node. Peers learn about all of them through the identify protocol.
+    #[builder(default)]
+    pub additional_bind_addresses: Vec<Multiaddr>,
+
     /// Replication factor for entries in the DHT
     #[builder(setter(into, strip_option), default = "DEFAULT_REPLICATION_FACTOR")]
     pub replication_factor: Option<NonZeroUsize>,
@@ -68,6 +76,23 @@ pub struct NetworkNodeConfig<T: NodeType> {
     #[builder(default)]
     /// The timeout for DHT lookups.
     pub dht_timeout: Option<Duration>,
+
+    #[builder(default)]
+    /// Compression of large messages
+    pub compression: CompressionConfig,
+
+    #[builder(default)]
+    /// The encoding of messages sent over the network
+    pub wire_format: WireFormat,
+
+    #[builder(default)]
+    /// NAT traversal through relays
+    pub relay: RelayConfig,
+
+    #[builder(default)]
+    /// Number of received messages queued for the application before the network stops
+    /// delivering more
+    pub inbound_channel_capacity: Option<usize>,
 }
 
 impl<T: NodeType> Clone for NetworkNodeConfig<T> {
@@ -75,6 +100,7 @@ impl<T: NodeType> Clone for NetworkNodeConfig<T> {
         Self {
             keypair: self.keypair.clone(),
             bind_address: self.bind_address.clone(),
+            additional_bind_addresses: self.additional_bind_addresses.clone(),
             replication_factor: self.replication_factor,
             gossip_config: self.gossip_config.clone(),
             request_response_config: self.request_response_config.clone(),
@@ -85,6 +111,10 @@ impl<T: NodeType> Clone for NetworkNodeConfig<T> {
             dht_file_path: self.dht_file_path.clone(),
             auth_message: self.auth_message.clone(),
             dht_timeout: self.dht_timeout,
+            compression: self.compression.clone(),
+            wire_format: self.wire_format,
+            relay: self.relay.clone(),
+            inbound_channel_capacity: self.inbound_channel_capacity,
         }
     }
 }
@@ -184,6 +214,20 @@ impl Default for GossipConfig {
     }
 }
 
+impl From<GossipTuning> for GossipConfig {
+    /// The default configuration, with the mesh parameters of `tuning`
+    fn from(tuning: GossipTuning) -> Self {
+        Self {
+            heartbeat_interval: tuning.heartbeat_interval,
+            mesh_n: tuning.mesh_n,
+            mesh_n_high: tuning.mesh_n_high,
+            mesh_n_low: tuning.mesh_n_low,
+            flood_publish: tuning.flood_publish,
+            ..Self::default()
+        }
+    }
+}
+
 /// Configuration for Libp2p's request-response
 #[derive(Clone, Debug)]
 pub struct RequestResponseConfig {
@@ -201,3 +245,118 @@ impl Default for RequestResponseConfig {
         }
     }
 }
+
+/// Configuration for reaching and being reached through relays when behind a NAT
+#[derive(Clone, Debug)]
+pub struct RelayConfig {
+    /// The designated relays, which we reserve circuits on if `AutoNAT` finds that we are not
+    /// publicly reachable
+    pub relay_nodes: Vec<(PeerId, Multiaddr)>,
+    /// Whether to relay connections for nodes behind a NAT
+    pub serve: bool,
+    /// Whether to try to replace relayed connections with direct ones by hole punching
+    pub hole_punching: bool,
+    /// The maximum number of reservations we hold for other nodes, if we serve as a relay
+    pub max_reservations: usize,
+    /// The maximum number of circuits we relay at once, if we serve as a relay
+    pub max_circuits: usize,
+    /// The longest we relay a single circuit for, if we serve as a relay
+    pub max_circuit_duration: Duration,
+    /// The most bytes we relay over a single circuit, if we serve as a relay
+    pub max_circuit_bytes: u64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            relay_nodes: Vec::new(),
+            serve: false,
+            hole_punching: true,
+            max_reservations: 128,
+            max_circuits: 64,
+            // Relayed connections are meant to be upgraded by hole punching, but validators
+            // whose NAT can't be punched through keep using them
+            max_circuit_duration: Duration::from_secs(60 * 60),
+            max_circuit_bytes: 1 << 30,
+        }
+    }
+}
+
+impl RelayConfig {
+    /// The addresses to listen on to be reachable through each of our relays, other than
+    /// ourselves
+    #[must_use]
+    pub fn circuit_addrs(&self, local_peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.relay_nodes
+            .iter()
+            .filter(|(peer_id, _)| peer_id != local_peer_id)
+            .map(|(peer_id, addr)| {
+                let mut addr = addr.clone();
+                if !matches!(addr.iter().last(), Some(Protocol::P2p(_))) {
+                    addr.push(Protocol::P2p(*peer_id));
+                }
+                addr.with(Protocol::P2pCircuit)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hotshot_types::network::{GossipPreset, GossipTuning};
+    use libp2p::{multiaddr::Protocol, Multiaddr};
+    use libp2p_identity::PeerId;
+
+    use super::{GossipConfig, RelayConfig};
+
+    #[test]
+    fn default_is_the_medium_preset() {
+        let default = GossipConfig::default();
+        let medium = GossipConfig::from(GossipPreset::Medium.tuning());
+
+        assert_eq!(default.mesh_n, medium.mesh_n);
+        assert_eq!(default.mesh_n_low, medium.mesh_n_low);
+        assert_eq!(default.mesh_n_high, medium.mesh_n_high);
+        assert_eq!(default.heartbeat_interval, medium.heartbeat_interval);
+        assert_eq!(default.flood_publish, medium.flood_publish);
+        assert_eq!(GossipTuning::default(), GossipPreset::Medium.tuning());
+    }
+
+    #[test]
+    fn presets_are_valid_meshes() {
+        for preset in [
+            GossipPreset::Small,
+            GossipPreset::Medium,
+            GossipPreset::Large,
+        ] {
+            let config = GossipConfig::from(preset.tuning());
+
+            // The constraints gossipsub checks when it is configured
+            assert!(config.mesh_outbound_min <= config.mesh_n_low, "{preset:?}");
+            assert!(config.mesh_n_low <= config.mesh_n, "{preset:?}");
+            assert!(config.mesh_n <= config.mesh_n_high, "{preset:?}");
+            assert!(config.mesh_outbound_min * 2 <= config.mesh_n, "{preset:?}");
+        }
+    }
+
+    #[test]
+    fn circuit_addrs_go_through_every_other_relay() {
+        let local = PeerId::random();
+        let relay = PeerId::random();
+        let addr: Multiaddr = "/ip4/10.0.0.1/udp/9000/quic-v1".parse().unwrap();
+        let config = RelayConfig {
+            relay_nodes: vec![
+                (local, addr.clone()),
+                (relay, addr.clone()),
+                (relay, addr.clone().with(Protocol::P2p(relay))),
+            ],
+            ..RelayConfig::default()
+        };
+
+        let expected = addr.with(Protocol::P2p(relay)).with(Protocol::P2pCircuit);
+        assert_eq!(
+            config.circuit_addrs(&local),
+            vec![expected.clone(), expected]
+        );
+    }
+}
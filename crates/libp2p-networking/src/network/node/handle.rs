@@ -4,7 +4,7 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{collections::HashSet, fmt::Debug, time::Duration};
+use std::{collections::HashSet, fmt::Debug, iter, time::Duration};
 
 use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
 use libp2p::{request_response::ResponseChannel, Multiaddr};
@@ -17,6 +17,7 @@ use tracing::{debug, info, instrument};
 
 use crate::network::{
     behaviours::dht::record::{Namespace, RecordKey, RecordValue},
+    compression::PeerCompression,
     gen_multiaddr, ClientRequest, NetworkEvent, NetworkNode, NetworkNodeConfig,
 };
 
@@ -31,14 +32,17 @@ pub struct NetworkNodeHandle<T: NodeType> {
     /// send an action to the networkbehaviour
     send_network: UnboundedSender<ClientRequest>,
 
-    /// the local address we're listening on
-    listen_addr: Multiaddr,
+    /// the local addresses we're listening on, one for each bind address
+    listen_addrs: Vec<Multiaddr>,
 
     /// the peer id of the networkbehaviour
     peer_id: PeerId,
 
     /// human readable id
     id: usize,
+
+    /// the compression algorithms of connected peers
+    peer_compression: PeerCompression,
 }
 
 /// internal network node receiver
@@ -84,15 +88,22 @@ pub async fn spawn_network_node<T: NodeType>(
     let mut network = NetworkNode::new(config.clone())
         .await
         .map_err(|e| NetworkError::ConfigError(format!("failed to create network node: {e}")))?;
-    // randomly assigned port
-    let listen_addr = config
-        .bind_address
-        .clone()
-        .unwrap_or_else(|| gen_multiaddr(0));
+    // the bind address, on a randomly assigned port if there is none, then any others
+    let bind_addrs = iter::once(
+        config
+            .bind_address
+            .clone()
+            .unwrap_or_else(|| gen_multiaddr(0)),
+    )
+    .chain(config.additional_bind_addresses.iter().cloned());
     let peer_id = network.peer_id();
-    let listen_addr = network.start_listen(listen_addr).await.map_err(|e| {
-        NetworkError::ListenError(format!("failed to start listening on Libp2p: {e}"))
-    })?;
+    let peer_compression = network.peer_compression();
+    let mut listen_addrs = Vec::new();
+    for bind_addr in bind_addrs {
+        listen_addrs.push(network.start_listen(bind_addr).await.map_err(|e| {
+            NetworkError::ListenError(format!("failed to start listening on Libp2p: {e}"))
+        })?);
+    }
     // pin here to force the future onto the heap since it can be large
     // in the case of flume
     let (send_chan, recv_chan) = Box::pin(network.spawn_listeners()).await.map_err(|err| {
@@ -106,9 +117,10 @@ pub async fn spawn_network_node<T: NodeType>(
     let handle = NetworkNodeHandle::<T> {
         network_config: config,
         send_network: send_chan,
-        listen_addr,
+        listen_addrs,
         peer_id,
         id,
+        peer_compression,
     };
     Ok((receiver, handle))
 }
@@ -131,10 +143,22 @@ impl<T: NodeType> NetworkNodeHandle<T> {
         self.send_request(req)
     }
 
+    /// The compression algorithms of connected peers
+    #[must_use]
+    pub fn peer_compression(&self) -> &PeerCompression {
+        &self.peer_compression
+    }
+
     /// Get a reference to the network node handle's listen addr.
     #[must_use]
     pub fn listen_addr(&self) -> Multiaddr {
-        self.listen_addr.clone()
+        self.listen_addrs[0].clone()
+    }
+
+    /// The addresses the network node is listening on, one for each of its bind addresses
+    #[must_use]
+    pub fn listen_addrs(&self) -> &[Multiaddr] {
+        &self.listen_addrs
     }
 
     /// Print out the routing table used by kademlia
@@ -190,6 +214,16 @@ impl<T: NodeType> NetworkNodeHandle<T> {
             .map_err(|err| NetworkError::ChannelReceiveError(err.to_string()))
     }
 
+    /// Connect to `peer_id` ahead of sending it anything, if we aren't already connected or
+    /// dialing it
+    ///
+    /// # Errors
+    /// If the channel closes
+    pub fn dial(&self, peer_id: PeerId) -> Result<(), NetworkError> {
+        let req = ClientRequest::Dial(peer_id);
+        self.send_request(req)
+    }
+
     /// Looks up a node's `PeerId` by its staking key. Is authenticated through
     /// `get_record` assuming each record should be signed.
     ///
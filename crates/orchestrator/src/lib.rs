@@ -814,7 +814,8 @@ where
 
 /// Runs the orchestrator
 /// # Errors
-/// This errors if tide disco runs into an issue during serving
+/// This errors if the network config is invalid, or if tide disco runs into an issue during
+/// serving
 /// # Panics
 /// This panics if unable to register the api with tide disco
 pub async fn run_orchestrator<KEY>(
@@ -849,24 +850,10 @@ where
         }
     }
 
-    network_config.config.known_nodes_with_stake = network_config
-        .public_keys
-        .iter()
-        .map(|keys| PeerConfig {
-            stake_table_entry: keys.stake_table_key.stake_table_entry(keys.stake),
-            state_ver_key: keys.state_ver_key.clone(),
-        })
-        .collect();
-
-    network_config.config.known_da_nodes = network_config
-        .public_keys
-        .iter()
-        .filter(|keys| keys.da)
-        .map(|keys| PeerConfig {
-            stake_table_entry: keys.stake_table_key.stake_table_entry(keys.stake),
-            state_ver_key: keys.state_ver_key.clone(),
-        })
-        .collect();
+    network_config.apply_public_keys();
+    network_config
+        .validate()
+        .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e.to_string()))?;
 
     let web_api =
         define_api().map_err(|_e| io::Error::new(ErrorKind::Other, "Failed to define api"));
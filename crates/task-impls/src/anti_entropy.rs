@@ -0,0 +1,268 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use either::Either;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    anti_entropy::{CertificateDigest, CertificateSync},
+    consensus::OuterConsensus,
+    data::ViewChangeEvidence,
+    message::UpgradeLock,
+    simple_certificate::{QuorumCertificate2, TimeoutCertificate2},
+    traits::{
+        election::Membership,
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::{Certificate, HasViewNumber},
+};
+use rand::seq::SliceRandom;
+use tracing::instrument;
+use utils::anytrace::Result;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Task which periodically sends the views of our highest certificates to a random peer, sends
+/// peers the certificates we hold that are newer than theirs, and accepts newer certificates
+/// from them in turn.
+pub struct AntiEntropyTaskState<TYPES: NodeType, V: Versions> {
+    /// Reference to consensus, which holds our high QC and DA certificates
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// Membership, used to pick peers and validate the certificates they send
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// The highest timeout certificate we have formed or seen attached to a proposal
+    pub high_timeout_cert: Option<TimeoutCertificate2<TYPES>>,
+
+    /// The node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> AntiEntropyTaskState<TYPES, V> {
+    /// The views of our highest certificates
+    async fn digest(&self) -> CertificateDigest<TYPES> {
+        let consensus_reader = self.consensus.read().await;
+
+        CertificateDigest {
+            view: consensus_reader.cur_view(),
+            high_qc: consensus_reader.high_qc().view_number(),
+            timeout_cert: self
+                .high_timeout_cert
+                .as_ref()
+                .map(HasViewNumber::view_number),
+            da_cert: consensus_reader.saved_da_certs().max_view(),
+        }
+    }
+
+    /// Send our digest to a random member of the current stake table other than ourselves
+    async fn send_digest(&self, sender: &Sender<Arc<HotShotEvent<TYPES>>>) {
+        let cur_epoch = self.consensus.read().await.cur_epoch();
+        let peers: Vec<TYPES::SignatureKey> = self
+            .membership
+            .read()
+            .await
+            .stake_table(cur_epoch)
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .filter(|key| *key != self.public_key)
+            .collect();
+        let Some(peer) = peers.choose(&mut rand::thread_rng()) else {
+            return;
+        };
+
+        broadcast_event(
+            Arc::new(HotShotEvent::CertificateDigestSend(
+                self.digest().await,
+                self.public_key.clone(),
+                peer.clone(),
+            )),
+            sender,
+        )
+        .await;
+    }
+
+    /// Send `peer` the certificates we hold which are newer than those of its `digest`
+    async fn answer_digest(
+        &self,
+        digest: &CertificateDigest<TYPES>,
+        peer: &TYPES::SignatureKey,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let sync = {
+            let consensus_reader = self.consensus.read().await;
+            let da_cert = consensus_reader
+                .saved_da_certs()
+                .last()
+                .map(|(_, cert)| cert);
+
+            CertificateSync::newer_than(
+                digest,
+                consensus_reader.cur_view(),
+                consensus_reader.high_qc(),
+                self.high_timeout_cert.as_ref(),
+                da_cert.as_ref(),
+            )
+        };
+        if sync.is_empty() {
+            return;
+        }
+
+        broadcast_event(
+            Arc::new(HotShotEvent::CertificateSyncSend(
+                sync,
+                self.public_key.clone(),
+                peer.clone(),
+            )),
+            sender,
+        )
+        .await;
+    }
+
+    /// Accept the certificates a peer sent us which are newer than ours and valid, passing them on
+    /// to the other tasks as if we had formed or received them ourselves
+    async fn handle_sync(
+        &mut self,
+        sync: &CertificateSync<TYPES>,
+        peer: &TYPES::SignatureKey,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        if let Some(qc) = &sync.high_qc {
+            let high_qc_view = self.consensus.read().await.high_qc().view_number();
+            if qc.view_number() > high_qc_view {
+                if self.is_valid_qc(qc).await {
+                    tracing::debug!("Caught up to the QC for view {:?}", qc.view_number());
+                    broadcast_event(
+                        Arc::new(HotShotEvent::Qc2Formed(Either::Left(qc.clone()))),
+                        sender,
+                    )
+                    .await;
+                } else {
+                    tracing::warn!("Peer {peer} sent an invalid QC");
+                }
+            }
+        }
+
+        if let Some(timeout_cert) = &sync.timeout_cert {
+            if self.is_newer_timeout_cert(timeout_cert) {
+                if self.is_valid_timeout_cert(timeout_cert).await {
+                    tracing::debug!(
+                        "Caught up to the timeout certificate for view {:?}",
+                        timeout_cert.view_number()
+                    );
+                    self.high_timeout_cert = Some(timeout_cert.clone());
+                    broadcast_event(
+                        Arc::new(HotShotEvent::Qc2Formed(Either::Right(timeout_cert.clone()))),
+                        sender,
+                    )
+                    .await;
+                } else {
+                    tracing::warn!("Peer {peer} sent an invalid timeout certificate");
+                }
+            }
+        }
+
+        // DA certificates are validated by the DA task like broadcast ones
+        if let Some(da_cert) = &sync.da_cert {
+            let known = self
+                .consensus
+                .read()
+                .await
+                .saved_da_certs()
+                .contains_key(&da_cert.view_number());
+            if !known {
+                broadcast_event(
+                    Arc::new(HotShotEvent::DaCertificateRecv(da_cert.clone())),
+                    sender,
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Whether `timeout_cert` is newer than the highest timeout certificate we have seen
+    fn is_newer_timeout_cert(&self, timeout_cert: &TimeoutCertificate2<TYPES>) -> bool {
+        self.high_timeout_cert
+            .as_ref()
+            .map_or(true, |high| timeout_cert.view_number() > high.view_number())
+    }
+
+    /// Whether `qc` carries enough valid signatures of its epoch's stake table
+    async fn is_valid_qc(&self, qc: &QuorumCertificate2<TYPES>) -> bool {
+        let membership_reader = self.membership.read().await;
+        let stake_table = membership_reader.sampled_stake_table(qc.view_number(), qc.data.epoch);
+        let threshold =
+            membership_reader.sampled_success_threshold(qc.view_number(), qc.data.epoch);
+        drop(membership_reader);
+
+        qc.is_valid_cert(stake_table, threshold, &self.upgrade_lock)
+            .await
+    }
+
+    /// Whether `timeout_cert` carries enough valid signatures of its epoch's stake table
+    async fn is_valid_timeout_cert(&self, timeout_cert: &TimeoutCertificate2<TYPES>) -> bool {
+        let membership_reader = self.membership.read().await;
+        let stake_table = membership_reader.stake_table(timeout_cert.data.epoch);
+        let threshold = membership_reader.success_threshold(timeout_cert.data.epoch);
+        drop(membership_reader);
+
+        timeout_cert
+            .is_valid_cert(stake_table, threshold, &self.upgrade_lock)
+            .await
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for AntiEntropyTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "AntiEntropyTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::AntiEntropyTick => self.send_digest(sender).await,
+            HotShotEvent::CertificateDigestRecv(digest, peer) => {
+                self.answer_digest(digest, peer, sender).await;
+            }
+            HotShotEvent::CertificateSyncRecv(sync, peer) => {
+                self.handle_sync(sync, peer, sender).await;
+            }
+            HotShotEvent::Qc2Formed(Either::Right(timeout_cert)) => {
+                if self.is_newer_timeout_cert(timeout_cert) {
+                    self.high_timeout_cert = Some(timeout_cert.clone());
+                }
+            }
+            HotShotEvent::QuorumProposalValidated(proposal, _) => {
+                if let Some(ViewChangeEvidence::Timeout(timeout_cert)) =
+                    &proposal.data.view_change_evidence
+                {
+                    if self.is_newer_timeout_cert(timeout_cert) {
+                        self.high_timeout_cert = Some(timeout_cert.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
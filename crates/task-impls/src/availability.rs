@@ -0,0 +1,414 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::{Mutex, RwLock};
+use async_trait::async_trait;
+use hotshot_task::{
+    dependency::{Dependency, EventDependency},
+    task::TaskState,
+};
+use hotshot_types::{
+    data::Leaf2,
+    event::{Event, EventType},
+    message::UpgradeLock,
+    traits::{
+        block_contents::BlockHeader,
+        election::Membership,
+        network::{DataRequest, RequestKind},
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vid::{vid_scheme, VidCommitment},
+};
+use jf_vid::VidScheme;
+use rand::{
+    seq::{IteratorRandom, SliceRandom},
+    thread_rng,
+};
+use sha2::{Digest, Sha256};
+use tokio::{spawn, task::JoinHandle, time::timeout};
+use tracing::instrument;
+use utils::anytrace::Result;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event, request::REQUEST_TIMEOUT};
+
+/// Number of random VID shares we sample for every decided block.
+pub const AVAILABILITY_SAMPLES_PER_BLOCK: usize = 2;
+
+/// Number of consecutive decided blocks which must fail sampling before we raise an alert.
+pub const AVAILABILITY_FAILURE_ALERT_THRESHOLD: u64 = 3;
+
+/// Number of DA members asked for a sampled share before it is counted as unavailable.
+pub const AVAILABILITY_SAMPLE_ATTEMPTS: usize = 3;
+
+/// Outcomes of the availability sampling of decided blocks, counted in the order the blocks were
+/// decided even though their sampling runs concurrently.
+#[derive(Debug)]
+pub struct SamplingOutcomes<TYPES: NodeType> {
+    /// Views whose blocks are being sampled, with the recipients of the shares found unavailable
+    /// once their sampling has finished
+    pending: BTreeMap<TYPES::View, Option<Vec<TYPES::SignatureKey>>>,
+
+    /// Number of decided blocks in a row for which sampling has failed
+    consecutive_failures: u64,
+}
+
+impl<TYPES: NodeType> SamplingOutcomes<TYPES> {
+    /// No blocks sampled yet
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Number of decided blocks in a row, among those whose sampling outcome has been counted,
+    /// for which sampling has failed
+    #[must_use]
+    pub fn consecutive_failures(&self) -> u64 {
+        self.consecutive_failures
+    }
+
+    /// Start sampling the block decided in `view`
+    pub fn start(&mut self, view: TYPES::View) {
+        self.pending.entry(view).or_insert(None);
+    }
+
+    /// Record that sampling the block decided in `view` found the shares of
+    /// `unavailable_shares` unavailable, and count every outcome no longer waiting on the
+    /// sampling of an earlier block.
+    ///
+    /// Returns the views whose sampling failed among those counted, each with the number of
+    /// consecutive failures as of that view and its unavailable shares.
+    pub fn finish(
+        &mut self,
+        view: TYPES::View,
+        unavailable_shares: Vec<TYPES::SignatureKey>,
+    ) -> Vec<(TYPES::View, u64, Vec<TYPES::SignatureKey>)> {
+        let Some(outcome) = self.pending.get_mut(&view) else {
+            return Vec::new();
+        };
+        *outcome = Some(unavailable_shares);
+
+        let mut failures = Vec::new();
+        while let Some(entry) = self.pending.first_entry() {
+            if entry.get().is_none() {
+                break;
+            }
+            let (view, unavailable_shares) = entry.remove_entry();
+            let unavailable_shares = unavailable_shares.unwrap_or_default();
+            if unavailable_shares.is_empty() {
+                self.consecutive_failures = 0;
+            } else {
+                self.consecutive_failures += 1;
+                failures.push((view, self.consecutive_failures, unavailable_shares));
+            }
+        }
+
+        failures
+    }
+}
+
+impl<TYPES: NodeType> Default for SamplingOutcomes<TYPES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Task which gives nodes outside the DA committee a probabilistic availability guarantee.
+///
+/// For every decided block, the task requests the VID shares of a few random quorum members
+/// from the DA committee and verifies them against the block's payload commitment, asking other
+/// DA members when one does not answer. If sampling fails for several decided blocks in a row,
+/// an `AvailabilitySamplingFailed` event is emitted.
+pub struct AvailabilitySamplingTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
+{
+    /// Membership, used to pick the shares to sample and the DA members to ask
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// This nodes public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// This nodes private/signing key, used to sign requests.
+    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+
+    /// Output events to application
+    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+
+    /// The node's id
+    pub id: u64,
+
+    /// Outcomes of the sampling of decided blocks
+    pub outcomes: Arc<Mutex<SamplingOutcomes<TYPES>>>,
+
+    /// A flag indicating that `HotShotEvent::Shutdown` has been received
+    pub shutdown_flag: Arc<AtomicBool>,
+
+    /// Sampling tasks we have spawned, by the view of the block they sample
+    pub spawned_tasks: BTreeMap<TYPES::View, JoinHandle<()>>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Phantom data for the node implementation
+    pub _pd: std::marker::PhantomData<I>,
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Drop
+    for AvailabilitySamplingTaskState<TYPES, I, V>
+{
+    fn drop(&mut self) {
+        self.cancel_subtasks();
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
+    for AvailabilitySamplingTaskState<TYPES, I, V>
+{
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "AvailabilitySamplingTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        if let HotShotEvent::LeavesDecided(leaves) = event.as_ref() {
+            self.spawned_tasks.retain(|_, handle| !handle.is_finished());
+            for leaf in leaves.iter().rev() {
+                self.spawn_sampling_task(leaf, sender, receiver).await;
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {
+        self.shutdown_flag.store(true, Ordering::Relaxed);
+
+        while let Some((_, handle)) = self.spawned_tasks.pop_first() {
+            handle.abort();
+        }
+    }
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
+    AvailabilitySamplingTaskState<TYPES, I, V>
+{
+    /// Spawn a task sampling the VID shares of a decided leaf, unless we are on the DA committee
+    /// for it and therefore hold the full payload anyway.
+    async fn spawn_sampling_task(
+        &mut self,
+        leaf: &Leaf2<TYPES>,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+        receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let view = leaf.view_number();
+        if view == TYPES::View::genesis() || self.spawned_tasks.contains_key(&view) {
+            return;
+        }
+        let epoch = leaf.epoch();
+
+        let membership_reader = self.membership.read().await;
+        if membership_reader.has_da_stake_at(&self.public_key, view, epoch) {
+            return;
+        }
+        let da_members = membership_reader.da_committee_members(view, epoch);
+        let sampled_keys: Vec<TYPES::SignatureKey> = membership_reader
+            .committee_members(view, epoch)
+            .into_iter()
+            .filter(|key| *key != self.public_key)
+            .choose_multiple(&mut thread_rng(), AVAILABILITY_SAMPLES_PER_BLOCK);
+        drop(membership_reader);
+
+        if da_members.is_empty() || sampled_keys.is_empty() {
+            return;
+        }
+
+        let mut requests = Vec::with_capacity(sampled_keys.len());
+        for key in sampled_keys {
+            let request = RequestKind::VidSample(view, key.clone());
+            let Some(signature) = self.serialize_and_sign(&request) else {
+                return;
+            };
+            requests.push((
+                key,
+                DataRequest::<TYPES> {
+                    request,
+                    view,
+                    signature,
+                },
+            ));
+        }
+
+        let payload_commitment = leaf.block_header().payload_commitment();
+        let signing_prefix = self.upgrade_lock.signing_prefix(view).await;
+        let membership = Arc::clone(&self.membership);
+        let public_key = self.public_key.clone();
+        let output_event_stream = self.output_event_stream.clone();
+        let outcomes = Arc::clone(&self.outcomes);
+        let shutdown_flag = Arc::clone(&self.shutdown_flag);
+        let sender = sender.clone();
+        let receiver = receiver.clone();
+        outcomes.lock().await.start(view);
+
+        let handle = spawn(async move {
+            let mut unavailable_shares = Vec::new();
+            for (key, request) in requests {
+                let mut recipients = da_members.iter().cloned().collect::<Vec<_>>();
+                recipients.shuffle(&mut thread_rng());
+
+                let mut available = false;
+                for recipient in recipients.into_iter().take(AVAILABILITY_SAMPLE_ATTEMPTS) {
+                    if shutdown_flag.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    broadcast_event(
+                        HotShotEvent::VidRequestSend(
+                            request.clone(),
+                            public_key.clone(),
+                            recipient.clone(),
+                        )
+                        .into(),
+                        &sender,
+                    )
+                    .await;
+
+                    if Self::await_valid_sample(
+                        &receiver,
+                        &membership,
+                        view,
+                        &key,
+                        &recipient,
+                        payload_commitment,
+                        &signing_prefix,
+                    )
+                    .await
+                    {
+                        available = true;
+                        break;
+                    }
+                }
+                if !available {
+                    unavailable_shares.push(key);
+                }
+            }
+
+            let failures = outcomes.lock().await.finish(view, unavailable_shares);
+            for (view, consecutive_failures, unavailable_shares) in failures {
+                tracing::warn!(
+                    "Availability sampling failed for view {:?}; {} consecutive failures",
+                    view,
+                    consecutive_failures
+                );
+                if consecutive_failures >= AVAILABILITY_FAILURE_ALERT_THRESHOLD {
+                    broadcast_event(
+                        Event {
+                            view_number: view,
+                            event: EventType::AvailabilitySamplingFailed {
+                                consecutive_failures,
+                                unavailable_shares,
+                            },
+                        },
+                        &output_event_stream,
+                    )
+                    .await;
+                }
+            }
+        });
+        self.spawned_tasks.insert(view, handle);
+    }
+
+    /// Wait for `responder` to send the VID share of `key` for `view` and check it against the
+    /// block's payload commitment. Returns `false` on timeout or if the share fails verification.
+    async fn await_valid_sample(
+        receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
+        membership: &RwLock<TYPES::Membership>,
+        view: TYPES::View,
+        key: &TYPES::SignatureKey,
+        responder: &TYPES::SignatureKey,
+        payload_commitment: VidCommitment,
+        signing_prefix: &[u8],
+    ) -> bool {
+        let expected_key = key.clone();
+        let responder = responder.clone();
+        let signing_prefix = signing_prefix.to_vec();
+        let dependency = EventDependency::new(
+            receiver.clone(),
+            Box::new(move |event: &Arc<HotShotEvent<TYPES>>| {
+                if let HotShotEvent::VidResponseRecv(sender_key, proposal) = event.as_ref() {
+                    proposal.data.view_number == view
+                        && proposal.data.recipient_key == expected_key
+                        && *sender_key == responder
+                        && sender_key.validate(
+                            &proposal.signature,
+                            &[
+                                signing_prefix.as_slice(),
+                                proposal.data.payload_commitment.as_ref(),
+                            ]
+                            .concat(),
+                        )
+                } else {
+                    false
+                }
+            }),
+        );
+
+        let Ok(Some(event)) = timeout(REQUEST_TIMEOUT, dependency.completed()).await else {
+            return false;
+        };
+        let HotShotEvent::VidResponseRecv(_, proposal) = event.as_ref() else {
+            return false;
+        };
+        let share = &proposal.data;
+
+        if share.payload_commitment != payload_commitment
+            && share.data_epoch_payload_commitment != Some(payload_commitment)
+        {
+            return false;
+        }
+        let total_nodes = membership.read().await.total_nodes(share.target_epoch);
+
+        // NOTE: `verify_share` returns a nested `Result`, so we must check both the inner
+        // and outer results
+        matches!(
+            vid_scheme(total_nodes).verify_share(
+                &share.share,
+                &share.common,
+                &share.payload_commitment
+            ),
+            Ok(Ok(()))
+        )
+    }
+
+    /// Sign the serialized version of the request
+    fn serialize_and_sign(
+        &self,
+        request: &RequestKind<TYPES>,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType> {
+        let Ok(data) = bincode::serialize(&request) else {
+            tracing::error!("Failed to serialize request!");
+            return None;
+        };
+        let Ok(signature) = TYPES::SignatureKey::sign(&self.private_key, &Sha256::digest(data))
+        else {
+            tracing::error!("Failed to sign Data Request");
+            return None;
+        };
+        Some(signature)
+    }
+}
@@ -6,6 +6,7 @@
 
 use std::time::{Duration, Instant};
 
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
 use hotshot_builder_api::v0_1::{
     block_info::AvailableBlockInfo,
     builder::{BuildError, Error as BuilderApiError},
@@ -124,6 +125,34 @@ impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
             .await
             .map_err(Into::into)
     }
+
+    /// Subscribe to the builder's available blocks, receiving a new candidate every time the
+    /// builder has assembled a better one
+    ///
+    /// # Errors
+    /// - [`BuilderClientError::Api`] if the builder doesn't support streaming block assembly or
+    ///   the connection can't be established
+    pub async fn available_blocks_stream(
+        &self,
+        parent: VidCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<
+        BoxStream<'static, Result<AvailableBlockInfo<TYPES>, BuilderClientError>>,
+        BuilderClientError,
+    > {
+        let encoded_signature: TaggedBase64 = signature.clone().into();
+        let connection = self
+            .client
+            .socket(&format!(
+                "{LEGACY_BUILDER_MODULE}/availableblocksstream/{parent}/{view_number}/{sender}/{encoded_signature}"
+            ))
+            .subscribe::<AvailableBlockInfo<TYPES>>()
+            .await
+            .map_err(BuilderClientError::from)?;
+        Ok(connection.map_err(Into::into).boxed())
+    }
 }
 
 /// Version 0.1
@@ -0,0 +1,475 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Instant};
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use committable::Committable;
+use futures::future::join_all;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    catchup::{CatchupConfig, CatchupPage, CatchupRateLimiter, CatchupRequest, DecideProof},
+    consensus::OuterConsensus,
+    data::Leaf2,
+    message::UpgradeLock,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        election::Membership,
+        node_implementation::{NodeImplementation, NodeType, Versions},
+        signature_key::SignatureKey,
+        storage::Storage,
+    },
+    vote::{Certificate, HasViewNumber},
+};
+use rand::seq::SliceRandom;
+use tokio::spawn;
+use tracing::instrument;
+use utils::anytrace::*;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// A catchup we are waiting on a page for
+#[derive(Clone, Debug)]
+pub struct CatchupProgress<TYPES: NodeType> {
+    /// The peer we asked for the page
+    pub peer: TYPES::SignatureKey,
+    /// Our request for the page
+    pub request: CatchupRequest<TYPES>,
+    /// The last leaf we have, which the page must extend
+    pub parent: Leaf2<TYPES>,
+    /// When we asked for the page
+    pub requested_at: Instant,
+}
+
+/// Task which catches up on the decided leaves we missed when we fall too far behind the view we
+/// are in, as after a restart or when joining late, and serves the decided leaves we hold to
+/// peers catching up, within a rate limit.
+///
+/// Leaves we catch up on are persisted as decided leaves, from which decided leaf streams and the
+/// application's state sync pick them up.
+pub struct CatchupTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
+    /// Reference to consensus, which holds our decided leaf
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// Storage of the decided leaves we serve and catch up on
+    pub storage: Arc<RwLock<I::Storage>>,
+
+    /// Membership, used to pick peers and validate the QCs of the leaves they send
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// When to catch up, and how large the pages we serve are
+    pub config: CatchupConfig,
+
+    /// Limits on the pages we serve
+    pub rate_limiter: CatchupRateLimiter<TYPES::SignatureKey>,
+
+    /// The catchup we are waiting on a page for, if any
+    pub in_progress: Option<CatchupProgress<TYPES>>,
+
+    /// The last leaf we caught up on, which may be ahead of our decided leaf
+    pub caught_up: Option<Leaf2<TYPES>>,
+
+    /// When the current catchup started, and the view of the last leaf we had then
+    pub started: Option<(Instant, TYPES::View)>,
+
+    /// The node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CatchupTaskState<TYPES, I, V> {
+    /// A random member of the current stake table other than ourselves, preferring one other
+    /// than `avoid`
+    async fn random_peer(
+        &self,
+        avoid: Option<&TYPES::SignatureKey>,
+    ) -> Option<TYPES::SignatureKey> {
+        let cur_epoch = self.consensus.read().await.cur_epoch();
+        let peers: Vec<TYPES::SignatureKey> = self
+            .membership
+            .read()
+            .await
+            .stake_table(cur_epoch)
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .filter(|key| *key != self.public_key)
+            .collect();
+        let others: Vec<_> = peers.iter().filter(|key| Some(*key) != avoid).collect();
+
+        others
+            .choose(&mut rand::thread_rng())
+            .copied()
+            .or(peers.first())
+            .cloned()
+    }
+
+    /// The last leaf we have: our decided leaf, or the last leaf we caught up on if it is newer
+    async fn last_leaf(&self) -> Leaf2<TYPES> {
+        let decided_leaf = self.consensus.read().await.decided_leaf();
+        match &self.caught_up {
+            Some(leaf) if leaf.height() > decided_leaf.height() => leaf.clone(),
+            _ => decided_leaf,
+        }
+    }
+
+    /// Ask `peer`, or a random peer if `None`, for the page of leaves after `parent` up to `end`
+    async fn request_page(
+        &mut self,
+        parent: Leaf2<TYPES>,
+        end: TYPES::View,
+        peer: Option<TYPES::SignatureKey>,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let peer = match peer {
+            Some(peer) => peer,
+            None => match self.random_peer(None).await {
+                Some(peer) => peer,
+                None => return,
+            },
+        };
+        let request = CatchupRequest {
+            view: self.consensus.read().await.cur_view(),
+            start: parent.view_number() + 1,
+            end,
+            cursor: parent.height() + 1,
+        };
+        tracing::debug!(
+            "Catching up on the leaves of views {:?} to {:?} from {peer}",
+            request.start,
+            request.end
+        );
+
+        self.in_progress = Some(CatchupProgress {
+            peer: peer.clone(),
+            request: request.clone(),
+            parent,
+            requested_at: Instant::now(),
+        });
+        broadcast_event(
+            Arc::new(HotShotEvent::CatchupRequestSend(
+                request,
+                self.public_key.clone(),
+                peer,
+            )),
+            sender,
+        )
+        .await;
+    }
+
+    /// Start catching up to `end`, unless we are already catching up
+    async fn start(&mut self, end: TYPES::View, sender: &Sender<Arc<HotShotEvent<TYPES>>>) {
+        if self.in_progress.is_some() {
+            return;
+        }
+        let parent = self.last_leaf().await;
+        if parent.view_number() >= end {
+            return;
+        }
+
+        self.started = Some((Instant::now(), parent.view_number()));
+        self.request_page(parent, end, None, sender).await;
+    }
+
+    /// Catch up if we have fallen too far behind `view`, and ask another peer for a page we have
+    /// waited on for too long
+    async fn handle_view_change(
+        &mut self,
+        view: TYPES::View,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        if let Some(progress) = &self.in_progress {
+            if progress.requested_at.elapsed() < self.config.request_timeout {
+                return;
+            }
+            tracing::info!(
+                "Peer {} did not answer our catchup request in time",
+                progress.peer
+            );
+            let CatchupProgress {
+                peer,
+                request,
+                parent,
+                ..
+            } = progress.clone();
+            let other = self.random_peer(Some(&peer)).await;
+            self.request_page(parent, request.end.max(view), other, sender)
+                .await;
+            return;
+        }
+
+        let last_view = self.last_leaf().await.view_number();
+        if *view > *last_view + self.config.max_lag {
+            self.start(view, sender).await;
+        }
+    }
+
+    /// Answer `request` from `peer` with a page of our decided leaves, if our rate limit allows
+    async fn answer_request(
+        &mut self,
+        request: &CatchupRequest<TYPES>,
+        peer: &TYPES::SignatureKey,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        if !self.rate_limiter.admit(peer, Instant::now()) {
+            tracing::debug!("Not serving catchup to {peer}: over the rate limit");
+            return;
+        }
+
+        let decided = match self
+            .storage
+            .read()
+            .await
+            .load_decided_leaves(request.cursor)
+            .await
+        {
+            Ok(decided) => decided,
+            Err(e) => {
+                tracing::warn!("Failed to load decided leaves to serve catchup: {e:#}");
+                return;
+            }
+        };
+
+        let page = {
+            let consensus_reader = self.consensus.read().await;
+            // Our last decided leaves are proven by the leaves after them we hold, which are
+            // certified by a leaf after them, or by our high QC
+            let decide_proof = |leaf: &Leaf2<TYPES>| {
+                let children = |leaf: &Leaf2<TYPES>| {
+                    let commit = leaf.commit();
+                    consensus_reader
+                        .saved_leaves()
+                        .values()
+                        .filter(move |child| child.parent_commitment() == commit)
+                };
+                let child = children(leaf)
+                    .find(|child| child.view_number() == leaf.view_number() + 1)?
+                    .clone();
+                let high_qc = consensus_reader.high_qc();
+                let qc = if high_qc.data.leaf_commit == child.commit() {
+                    high_qc.clone()
+                } else {
+                    children(&child).next()?.justify_qc()
+                };
+                Some(DecideProof { child, qc })
+            };
+            CatchupPage::answer(
+                request.clone(),
+                consensus_reader.cur_view(),
+                decided,
+                self.config.page_size,
+                decide_proof,
+            )
+        };
+
+        broadcast_event(
+            Arc::new(HotShotEvent::CatchupPageSend(
+                page,
+                self.public_key.clone(),
+                peer.clone(),
+            )),
+            sender,
+        )
+        .await;
+    }
+
+    /// Persist the leaves of a page answering our request once they are shown to continue our
+    /// chain and to be decided.
+    ///
+    /// The page's links and decide proof are checked first, which is cheap, then the signatures
+    /// of its QCs, in parallel batches. Only a page which passes both is applied, and the next
+    /// page requested from its last leaf. If a page fails either, it is requested again from
+    /// another peer.
+    async fn handle_page(
+        &mut self,
+        page: &CatchupPage<TYPES>,
+        peer: &TYPES::SignatureKey,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let Some(progress) = self
+            .in_progress
+            .as_ref()
+            .filter(|progress| progress.peer == *peer && progress.request == page.request)
+            .cloned()
+        else {
+            return;
+        };
+
+        if let Err(e) = page.check(&progress.request, &progress.parent) {
+            tracing::warn!(
+                "Peer {peer} sent a catchup page which does not continue our chain: {e}"
+            );
+            let other = self.random_peer(Some(peer)).await;
+            self.request_page(progress.parent, progress.request.end, other, sender)
+                .await;
+            return;
+        }
+        let (Some(last), Some(decide_proof)) = (page.leaves.last().cloned(), &page.decide_proof)
+        else {
+            tracing::debug!("Peer {peer} has no decided leaves for us to catch up on");
+            self.finish();
+            return;
+        };
+
+        let qcs = page
+            .leaves
+            .iter()
+            .map(Leaf2::justify_qc)
+            .chain(decide_proof.qcs())
+            .collect();
+        if let Err(e) = verify_qcs_in_batches(
+            qcs,
+            &self.membership,
+            &self.upgrade_lock,
+            self.config.verify_batch_size,
+        )
+        .await
+        {
+            tracing::warn!("Peer {peer} sent a catchup page with an invalid QC: {e}");
+            let other = self.random_peer(Some(peer)).await;
+            self.request_page(progress.parent, progress.request.end, other, sender)
+                .await;
+            return;
+        }
+
+        if let Err(e) = self
+            .storage
+            .read()
+            .await
+            .append_decided_leaves(&page.leaves)
+            .await
+        {
+            tracing::warn!("Failed to persist the leaves we caught up on: {e:#}");
+            self.finish();
+            return;
+        }
+        self.caught_up = Some(last.clone());
+        self.record_throughput(last.view_number()).await;
+
+        if page.next_cursor.is_some() {
+            self.request_page(last, progress.request.end, Some(peer.clone()), sender)
+                .await;
+        } else {
+            tracing::info!("Caught up to view {:?}", last.view_number());
+            self.finish();
+        }
+    }
+
+    /// Update the catchup throughput metric, now that we have caught up to `view`
+    async fn record_throughput(&self, view: TYPES::View) {
+        let Some((started_at, start_view)) = self.started else {
+            return;
+        };
+        let elapsed = started_at.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
+        }
+
+        let views = view.saturating_sub(*start_view);
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let views_per_second = (views as f64 / elapsed) as usize;
+        let consensus_reader = self.consensus.read().await;
+        consensus_reader
+            .metrics
+            .catchup_views_per_second
+            .set(views_per_second);
+    }
+
+    /// Stop catching up
+    fn finish(&mut self) {
+        self.in_progress = None;
+        self.started = None;
+    }
+}
+
+/// Verify the signatures of `qcs` against the stake tables of their epochs, spreading them over
+/// parallel tasks of `batch_size` QCs each.
+///
+/// # Errors
+/// If any QC does not carry enough valid signatures
+pub async fn verify_qcs_in_batches<TYPES: NodeType, V: Versions>(
+    qcs: Vec<QuorumCertificate2<TYPES>>,
+    membership: &RwLock<TYPES::Membership>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+    batch_size: u64,
+) -> Result<()> {
+    let membership_reader = membership.read().await;
+    let checks: Vec<_> = qcs
+        .into_iter()
+        .map(|qc| {
+            let stake_table =
+                membership_reader.sampled_stake_table(qc.view_number(), qc.data.epoch);
+            let threshold =
+                membership_reader.sampled_success_threshold(qc.view_number(), qc.data.epoch);
+            (qc, stake_table, threshold)
+        })
+        .collect();
+    drop(membership_reader);
+
+    let batch_size = usize::try_from(batch_size).unwrap_or(usize::MAX).max(1);
+    let batches = checks.chunks(batch_size).map(|batch| {
+        let batch = batch.to_vec();
+        let upgrade_lock = upgrade_lock.clone();
+        spawn(async move {
+            for (qc, stake_table, threshold) in batch {
+                ensure!(
+                    qc.is_valid_cert(stake_table, threshold, &upgrade_lock)
+                        .await,
+                    warn!("The QC for view {} is invalid", qc.view_number())
+                );
+            }
+            Ok(())
+        })
+    });
+
+    for batch in join_all(batches).await {
+        batch
+            .wrap()
+            .context(error!("A QC verification task failed"))??;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
+    for CatchupTaskState<TYPES, I, V>
+{
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "CatchupTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::ViewChange(view, _) => self.handle_view_change(*view, sender).await,
+            HotShotEvent::CatchupStart(view) => self.start(*view, sender).await,
+            HotShotEvent::CatchupRequestRecv(request, peer) => {
+                self.answer_request(request, peer, sender).await;
+            }
+            HotShotEvent::CatchupPageRecv(page, peer) => {
+                self.handle_page(page, peer, sender).await;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -7,15 +7,16 @@
 use std::{sync::Arc, time::Duration};
 
 use async_broadcast::Sender;
-use chrono::Utc;
 use hotshot_types::{
     event::{Event, EventType},
     simple_vote::{HasEpoch, QuorumVote2, TimeoutData2, TimeoutVote2},
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        states::InstanceState,
     },
     utils::EpochTransitionIndicator,
+    view_tracker::TrackedTask,
     vote::{HasViewNumber, Vote},
 };
 use tokio::{spawn, time::sleep};
@@ -79,11 +80,11 @@ pub(crate) async fn handle_quorum_vote_recv<
     .await?;
 
     // If the vote sender belongs to the next epoch, collect it separately to form the second QC
-    let has_stake = task_state
-        .membership
-        .read()
-        .await
-        .has_stake(&vote.signing_key(), vote.epoch() + 1);
+    let has_stake = task_state.membership.read().await.has_sampled_stake(
+        &vote.signing_key(),
+        vote.view_number(),
+        vote.epoch() + 1,
+    );
     if has_stake {
         handle_vote(
             &mut task_state.next_epoch_vote_collectors,
@@ -224,10 +225,29 @@ pub(crate) async fn handle_view_change<
     // Move this node to the next view
     task_state.cur_view = new_view_number;
     task_state
-        .consensus
-        .write()
+        .view_tracker
+        .update(TrackedTask::Consensus, new_view_number)
+        .await?;
+
+    // Apply the view timeout of any decided parameter change which takes effect in the new view.
+    // The DA committee size is kept per view by the membership.
+    task_state.timeout = task_state
+        .governed_parameters
+        .at(new_view_number)
+        .view_timeout;
+    task_state
+        .work_scheduler
+        .start_view(*new_view_number, Duration::from_millis(task_state.timeout));
+    let leader = task_state
+        .membership
+        .read()
         .await
-        .update_view(new_view_number)?;
+        .leader(new_view_number, task_state.cur_epoch)
+        .ok();
+    let mut consensus_writer = task_state.consensus.write().await;
+    consensus_writer.update_view(new_view_number)?;
+    consensus_writer.record_view_entered(new_view_number, leader);
+    drop(consensus_writer);
 
     // If we have a decided upgrade certificate, the protocol version may also have been upgraded.
     let decided_upgrade_certificate_read = task_state
@@ -277,7 +297,7 @@ pub(crate) async fn handle_view_change<
         .metrics
         .current_view
         .set(usize::try_from(task_state.cur_view.u64()).unwrap());
-    let cur_view_time = Utc::now().timestamp();
+    let cur_view_time = task_state.instance_state.clock().unix_timestamp();
     if old_view_leader_key == task_state.public_key {
         #[allow(clippy::cast_precision_loss)]
         consensus_reader
@@ -287,19 +307,18 @@ pub(crate) async fn handle_view_change<
     }
     task_state.cur_view_time = cur_view_time;
 
-    // Do the comparison before the subtraction to avoid potential overflow, since
-    // `last_decided_view` may be greater than `cur_view` if the node is catching up.
-    if usize::try_from(task_state.cur_view.u64()).unwrap()
-        > usize::try_from(consensus_reader.last_decided_view().u64()).unwrap()
-    {
-        consensus_reader
-            .metrics
-            .number_of_views_since_last_decide
-            .set(
-                usize::try_from(task_state.cur_view.u64()).unwrap()
-                    - usize::try_from(consensus_reader.last_decided_view().u64()).unwrap(),
-            );
-    }
+    // `last_decided_view` may be greater than `cur_view` if the node is catching up
+    consensus_reader
+        .metrics
+        .number_of_views_since_last_decide
+        .set(
+            usize::try_from(
+                task_state
+                    .cur_view
+                    .saturating_distance(consensus_reader.last_decided_view()),
+            )
+            .unwrap_or(usize::MAX),
+        );
 
     broadcast_event(
         Event {
@@ -383,10 +402,11 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
         .await
         .leader(view_number, task_state.cur_epoch);
 
-    let consensus_reader = task_state.consensus.read().await;
-    consensus_reader.metrics.number_of_timeouts.add(1);
+    let mut consensus_writer = task_state.consensus.write().await;
+    consensus_writer.record_view_timeout(view_number);
+    consensus_writer.metrics.number_of_timeouts.add(1);
     if leader? == task_state.public_key {
-        consensus_reader.metrics.number_of_timeouts_as_leader.add(1);
+        consensus_writer.metrics.number_of_timeouts_as_leader.add(1);
     }
 
     Ok(())
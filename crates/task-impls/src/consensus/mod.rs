@@ -14,14 +14,17 @@ use hotshot_task::task::TaskState;
 use hotshot_types::{
     consensus::OuterConsensus,
     event::Event,
+    governance::GovernedParameters,
     message::UpgradeLock,
     simple_certificate::{NextEpochQuorumCertificate2, QuorumCertificate2, TimeoutCertificate2},
     simple_vote::{NextEpochQuorumVote2, QuorumVote2, TimeoutVote2},
     traits::{
+        election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
         signature_key::SignatureKey,
     },
     utils::epoch_from_block_number,
+    view_tracker::ViewTracker,
     vote::HasViewNumber,
 };
 use tokio::task::JoinHandle;
@@ -31,7 +34,10 @@ use utils::anytrace::*;
 use self::handlers::{
     handle_quorum_vote_recv, handle_timeout, handle_timeout_vote_recv, handle_view_change,
 };
-use crate::{events::HotShotEvent, helpers::broadcast_event, vote_collection::VoteCollectorsMap};
+use crate::{
+    events::HotShotEvent, future_buffer::FutureEventBuffer, helpers::broadcast_event,
+    vote_collection::VoteCollectorsMap, work_scheduler::WorkScheduler,
+};
 
 /// Event handlers for use in the `handle` method.
 mod handlers;
@@ -83,7 +89,7 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// Timeout task handle
     pub timeout_task: JoinHandle<()>,
 
-    /// View timeout from config.
+    /// View timeout of the current view, from config until a governance change replaces it
     pub timeout: u64,
 
     /// A reference to the metrics trait.
@@ -97,6 +103,18 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Votes received for views we have not entered yet
+    pub future_events: FutureEventBuffer<TYPES>,
+
+    /// The view each task is in, shared between the tasks
+    pub view_tracker: Arc<ViewTracker<TYPES>>,
+
+    /// Budget of background work in each view, restarted as we enter a view
+    pub work_scheduler: Arc<WorkScheduler>,
+
+    /// Runtime parameters of each view, which decide the timeout and the DA committee
+    pub governed_parameters: Arc<GovernedParameters<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskState<TYPES, I, V> {
@@ -109,6 +127,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskSt
     ) -> Result<()> {
         match event.as_ref() {
             HotShotEvent::QuorumVoteRecv(ref vote) => {
+                // Votes for the next views which we don't collect now may be ours to collect once
+                // we have caught up to them, so hold them until then.
+                let view = vote.view_number();
+                if view > self.cur_view
+                    && !self.is_leader(view + 1, vote.data.epoch).await
+                    && !self.consensus.read().await.is_high_qc_for_last_block()
+                    && self.future_events.hold(self.cur_view, view, &event)
+                {
+                    return Ok(());
+                }
                 if let Err(e) =
                     handle_quorum_vote_recv(vote, Arc::clone(&event), &sender, self).await
                 {
@@ -116,6 +144,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskSt
                 }
             }
             HotShotEvent::TimeoutVoteRecv(ref vote) => {
+                // We check the leader against our current epoch, so votes for the next views which
+                // we don't collect now may be ours to collect once we have caught up to them. The
+                // votes we do collect are handled at once, as we need their certificate to get
+                // there.
+                let view = vote.view_number();
+                if view > self.cur_view
+                    && !self.is_leader(view + 1, self.cur_epoch).await
+                    && self.future_events.hold(self.cur_view, view, &event)
+                {
+                    return Ok(());
+                }
                 if let Err(e) =
                     handle_timeout_vote_recv(vote, Arc::clone(&event), &sender, self).await
                 {
@@ -172,6 +211,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskSt
 
         Ok(())
     }
+
+    /// Whether we lead `view` in `epoch`
+    async fn is_leader(&self, view: TYPES::View, epoch: TYPES::Epoch) -> bool {
+        self.membership
+            .read()
+            .await
+            .leader(view, epoch)
+            .is_ok_and(|leader| leader == self.public_key)
+    }
 }
 
 #[async_trait]
@@ -186,7 +234,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
         sender: &Sender<Arc<Self::Event>>,
         _receiver: &Receiver<Arc<Self::Event>>,
     ) -> Result<()> {
-        self.handle(event, sender.clone()).await
+        let result = self.handle(event, sender.clone()).await;
+
+        // Replay the events held for the view we are in now
+        for event in self.future_events.release(self.cur_view) {
+            if let Err(e) = self.handle(event, sender.clone()).await {
+                tracing::debug!("Failed to handle a held event; error = {e}");
+            }
+        }
+
+        result
     }
 
     /// Joins all subtasks.
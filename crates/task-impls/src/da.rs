@@ -4,12 +4,18 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use async_broadcast::{Receiver, Sender};
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use hotshot_task::sharded::ViewShardedTaskState;
 use hotshot_types::{
     consensus::{Consensus, OuterConsensus},
     data::{DaProposal2, PackedBundle},
@@ -21,11 +27,12 @@ use hotshot_types::{
         block_contents::vid_commitment,
         election::Membership,
         network::ConnectedNetwork,
-        node_implementation::{NodeImplementation, NodeType, Versions},
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
         signature_key::SignatureKey,
         storage::Storage,
     },
     utils::EpochTransitionIndicator,
+    view_tracker::{TrackedTask, ViewTracker},
     vote::HasViewNumber,
 };
 use sha2::{Digest, Sha256};
@@ -35,8 +42,10 @@ use utils::anytrace::*;
 
 use crate::{
     events::HotShotEvent,
+    future_buffer::FutureEventBuffer,
     helpers::broadcast_event,
     vote_collection::{handle_vote, VoteCollectorsMap},
+    work_scheduler::{WorkClass, WorkScheduler},
 };
 
 /// Tracks state of a DA task
@@ -44,11 +53,12 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
     /// Output events to application
     pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
 
-    /// View number this view is executing in.
-    pub cur_view: TYPES::View,
+    /// View number this view is executing in, updated on view changes without waiting for the
+    /// proposals of earlier views.
+    pub cur_view: AtomicU64,
 
     /// Epoch number this node is executing in.
-    pub cur_epoch: TYPES::Epoch,
+    pub cur_epoch: AtomicU64,
 
     /// Reference to consensus. Leader will require a read lock on this.
     pub consensus: OuterConsensus<TYPES>,
@@ -62,7 +72,7 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
     pub network: Arc<I::Network>,
 
     /// A map of `DaVote` collector tasks.
-    pub vote_collectors: VoteCollectorsMap<TYPES, DaVote2<TYPES>, DaCertificate2<TYPES>, V>,
+    pub vote_collectors: Mutex<VoteCollectorsMap<TYPES, DaVote2<TYPES>, DaCertificate2<TYPES>, V>>,
 
     /// This Nodes public key
     pub public_key: TYPES::SignatureKey,
@@ -78,13 +88,77 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// The view each task is in, shared between the tasks
+    pub view_tracker: Arc<ViewTracker<TYPES>>,
+
+    /// Whether every node stores every payload, in which case nodes derive their own VID share
+    /// from the payload instead of waiting for one from the leader
+    pub full_replication: bool,
+
+    /// Budget of background work in each view, within which we compute VID shares ourselves
+    pub work_scheduler: Arc<WorkScheduler>,
+
+    /// DA proposals received for views too far ahead of us to handle yet
+    pub future_events: Mutex<FutureEventBuffer<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYPES, I, V> {
+    /// View number this view is executing in
+    #[must_use]
+    pub fn cur_view(&self) -> TYPES::View {
+        TYPES::View::new(self.cur_view.load(Ordering::Acquire))
+    }
+
+    /// Epoch number this node is executing in
+    #[must_use]
+    pub fn cur_epoch(&self) -> TYPES::Epoch {
+        TYPES::Epoch::new(self.cur_epoch.load(Ordering::Acquire))
+    }
+
+    /// Handler for view changes, run as soon as they are received rather than after the
+    /// proposals of earlier views
+    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view(), epoch = *self.cur_epoch()), name = "DA View Change", level = "error", target = "DaTaskState")]
+    pub async fn handle_view_change(
+        &self,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        self.cur_epoch.fetch_max(*epoch, Ordering::AcqRel);
+
+        // The view is updated under the lock on the held proposals, so a proposal is either held
+        // before the update and released by it, or checked against the new view
+        let released = {
+            let mut future_events = self.future_events.lock().await;
+            let cur_view = self.cur_view();
+            ensure!(
+                *cur_view < *view,
+                info!("Received a view change to an older view.")
+            );
+
+            if view.saturating_distance(cur_view) > 1 {
+                tracing::info!("View changed by more than 1 going to view {:?}", view);
+            }
+            self.cur_view.store(*view, Ordering::Release);
+            future_events.release(view + 1)
+        };
+        self.view_tracker.update(TrackedTask::Da, view).await?;
+
+        // Replay the proposals held for the views we can handle now
+        for event in released {
+            if let Err(e) = self.handle(event, event_stream.clone()).await {
+                tracing::debug!("Failed to handle a held event; error = {e}");
+            }
+        }
+
+        Ok(())
+    }
+
     /// main task event handler
-    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = *self.cur_epoch), name = "DA Main Task", level = "error", target = "DaTaskState")]
+    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view(), epoch = *self.cur_epoch()), name = "DA Main Task", level = "error", target = "DaTaskState")]
     pub async fn handle(
-        &mut self,
+        &self,
         event: Arc<HotShotEvent<TYPES>>,
         event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     ) -> Result<()> {
@@ -101,9 +175,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 // Allow a DA proposal that is one view older, in case we have voted on a quorum
                 // proposal and updated the view.
                 //
-                // Anything older is discarded because it is no longer relevant.
+                // Anything older is discarded because it is no longer relevant, while proposals
+                // for later views are held until we are within a view of them.
+                let mut future_events = self.future_events.lock().await;
+                let cur_view = self.cur_view();
+                if future_events.hold(cur_view + 1, view, &event) {
+                    return Ok(());
+                }
+                drop(future_events);
                 ensure!(
-                    self.cur_view <= view + 1,
+                    view.is_within(cur_view, 1),
                     "Throwing away DA proposal that is more than one view older"
                 );
 
@@ -129,8 +210,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     )
                 );
 
+                let signing_message = self
+                    .upgrade_lock
+                    .signing_message(&encoded_transactions_hash, view)
+                    .await;
                 ensure!(
-                    view_leader_key.validate(&proposal.signature, &encoded_transactions_hash),
+                    view_leader_key.validate(&proposal.signature, &signing_message),
                     warn!("Could not verify proposal.")
                 );
 
@@ -146,7 +231,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 let epoch_number = proposal.data.epoch;
 
                 ensure!(
-                  cur_view <= view_number + 1,
+                  view_number.is_within(cur_view, 1),
                   debug!(
                     "Validated DA proposal for prior view but it's too old now Current view {:?}, DA Proposal view {:?}", 
                     cur_view,
@@ -169,7 +254,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
 
                 let membership_reader = self.membership.read().await;
                 ensure!(
-                    membership_reader.has_da_stake(&self.public_key, epoch_number),
+                    membership_reader.has_da_stake_at(&self.public_key, view_number, epoch_number),
                     debug!(
                         "We were not chosen for consensus committee for view {:?} in epoch {:?}",
                         view_number, epoch_number
@@ -223,34 +308,40 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 ) {
                     tracing::trace!("{e:?}");
                 }
-                // Optimistically calculate and update VID if we know that the primary network is down.
-                if self.network.is_primary_down() {
+                // Calculate and update VID ourselves if the leader does not send us a share, or
+                // optimistically if we know that the primary network is down. Under full
+                // replication our vote waits on the share, so it is not deferred.
+                if self.full_replication || self.network.is_primary_down() {
+                    let work_class = if self.full_replication {
+                        WorkClass::ReplicatedVidEncoding
+                    } else {
+                        WorkClass::OptimisticVidEncoding
+                    };
                     let consensus =
                         OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
                     let membership = Arc::clone(&self.membership);
                     let pk = self.private_key.clone();
                     let public_key = self.public_key.clone();
                     let chan = event_stream.clone();
+                    let work_scheduler = Arc::clone(&self.work_scheduler);
+                    let upgrade_lock = self.upgrade_lock.clone();
                     spawn(async move {
-                        Consensus::calculate_and_update_vid(
-                            OuterConsensus::new(Arc::clone(&consensus.inner_consensus)),
-                            view_number,
-                            membership,
-                            &pk,
-                        )
-                        .await;
-                        if let Some(Some(vid_share)) = consensus
-                            .read()
-                            .await
-                            .vid_shares()
-                            .get(&view_number)
-                            .map(|shares| shares.get(&public_key).cloned())
-                        {
+                        work_scheduler
+                            .run(
+                                work_class,
+                                Consensus::calculate_and_update_vid(
+                                    OuterConsensus::new(Arc::clone(&consensus.inner_consensus)),
+                                    view_number,
+                                    membership,
+                                    &pk,
+                                    &upgrade_lock,
+                                ),
+                            )
+                            .await;
+                        let vid_share = consensus.read().await.vid_share(view_number, &public_key);
+                        if let Some(vid_share) = vid_share {
                             broadcast_event(
-                                Arc::new(HotShotEvent::VidShareRecv(
-                                    public_key.clone(),
-                                    vid_share.clone(),
-                                )),
+                                Arc::new(HotShotEvent::VidShareRecv(public_key.clone(), vid_share)),
                                 &chan,
                             )
                             .await;
@@ -276,7 +367,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 drop(membership_reader);
 
                 handle_vote(
-                    &mut self.vote_collectors,
+                    &mut *self.vote_collectors.lock().await,
                     vote,
                     self.public_key.clone(),
                     &self.membership,
@@ -289,22 +380,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 )
                 .await?;
             }
-            HotShotEvent::ViewChange(view, epoch) => {
-                if *epoch > self.cur_epoch {
-                    self.cur_epoch = *epoch;
-                }
-
-                let view = *view;
-                ensure!(
-                    *self.cur_view < *view,
-                    info!("Received a view change to an older view.")
-                );
-
-                if *view - *self.cur_view > 1 {
-                    tracing::info!("View changed by more than 1 going to view {:?}", view);
-                }
-                self.cur_view = view;
-            }
             HotShotEvent::BlockRecv(packed_bundle) => {
                 let PackedBundle::<TYPES> {
                     encoded_transactions,
@@ -318,11 +393,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 let encoded_transactions_hash = Sha256::digest(encoded_transactions);
 
                 // sign the encoded transactions as opposed to the VID commitment
-                let signature =
-                    TYPES::SignatureKey::sign(&self.private_key, &encoded_transactions_hash)
-                        .wrap()?;
+                let signature = TYPES::SignatureKey::sign(
+                    &self.private_key,
+                    &self
+                        .upgrade_lock
+                        .signing_message(&encoded_transactions_hash, view_number)
+                        .await,
+                )
+                .wrap()?;
 
-                let epoch = self.cur_epoch;
+                let epoch = self.cur_epoch();
                 let leader = self.membership.read().await.leader(view_number, epoch)?;
                 if leader != self.public_key {
                     tracing::debug!(
@@ -369,11 +449,47 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
 }
 
 #[async_trait]
-/// task state implementation for DA Task
-impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
+/// task state implementation for DA Task, which handles the proposals and votes of distinct views
+/// concurrently
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ViewShardedTaskState
     for DaTaskState<TYPES, I, V>
 {
     type Event = HotShotEvent<TYPES>;
+    type View = TYPES::View;
+
+    fn event_view(&self, event: &Self::Event) -> Option<Self::View> {
+        match event {
+            HotShotEvent::DaProposalRecv(proposal, _)
+            | HotShotEvent::DaProposalValidated(proposal, _) => Some(proposal.data.view_number()),
+            HotShotEvent::DaVoteRecv(vote) => Some(vote.view_number()),
+            HotShotEvent::BlockRecv(packed_bundle) => Some(packed_bundle.view_number),
+            _ => None,
+        }
+    }
+
+    fn is_shared_event(&self, event: &Self::Event) -> bool {
+        matches!(event, HotShotEvent::ViewChange(..))
+    }
+
+    async fn handle_view_event(
+        &self,
+        _view: Self::View,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle(event, sender.clone()).await
+    }
+
+    async fn handle_shared_event(
+        &self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+    ) -> Result<()> {
+        if let HotShotEvent::ViewChange(view, epoch) = event.as_ref() {
+            return self.handle_view_change(*view, *epoch, sender).await;
+        }
+        Ok(())
+    }
 
     async fn handle_event(
         &mut self,
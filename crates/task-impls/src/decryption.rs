@@ -0,0 +1,419 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    constants::MAX_OUTSTANDING_VIEWS,
+    data::Leaf2,
+    encrypted_mempool::{DecryptionShares, ThresholdDecryption},
+    event::{Event, EventType},
+    message::UpgradeLock,
+    traits::{
+        block_contents::{BlockHeader, Transaction},
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::SignatureKey,
+        BlockPayload,
+    },
+};
+use tracing::instrument;
+use utils::anytrace::Result;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Number of views after which we stop collecting shares for a decided block.
+pub const DECRYPTION_RETENTION_VIEWS: u64 = 100;
+
+/// Maximum number of share messages we hold in total for blocks we haven't seen decided yet.
+pub const MAX_EARLY_DECRYPTION_SHARES: usize = 10_000;
+
+/// Encrypted transactions of a decided block, along with the shares collected for them.
+struct PendingBlock<TYPES: NodeType> {
+    /// Epoch of the block
+    epoch: TYPES::Epoch,
+
+    /// Ciphertexts not decrypted yet, by position in the block
+    ciphertexts: BTreeMap<u64, Vec<u8>>,
+
+    /// Valid shares for each ciphertext, by serialized public key of the sender
+    shares: BTreeMap<u64, HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+/// Task which releases this node's decryption shares for the encrypted transactions of decided
+/// blocks and decrypts them once enough shares have been collected.
+pub struct DecryptionTaskState<TYPES: NodeType, V: Versions> {
+    /// Threshold decryption scheme and this node's key share
+    pub decryption: Arc<dyn ThresholdDecryption>,
+
+    /// Membership, used to check who may release shares
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// This node's private key, which we sign our shares with
+    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Output events to application
+    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+
+    /// Maximum number of share messages we hold for blocks we haven't seen decided yet
+    pub max_early_shares: usize,
+
+    /// Decided blocks with encrypted transactions we are still decrypting
+    pending: BTreeMap<TYPES::View, PendingBlock<TYPES>>,
+
+    /// Signed shares received for blocks we haven't seen decided yet, by view and sender
+    early_shares: BTreeMap<TYPES::View, HashMap<TYPES::SignatureKey, DecryptionShares<TYPES>>>,
+
+    /// The newest view we have seen decided
+    last_decided_view: TYPES::View,
+
+    /// The view we are in
+    cur_view: TYPES::View,
+
+    /// The node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> DecryptionTaskState<TYPES, V> {
+    /// Create a new task state
+    #[must_use]
+    pub fn new(
+        decryption: Arc<dyn ThresholdDecryption>,
+        membership: Arc<RwLock<TYPES::Membership>>,
+        public_key: TYPES::SignatureKey,
+        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: UpgradeLock<TYPES, V>,
+        output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+        id: u64,
+    ) -> Self {
+        Self {
+            decryption,
+            membership,
+            public_key,
+            private_key,
+            upgrade_lock,
+            output_event_stream,
+            max_early_shares: MAX_EARLY_DECRYPTION_SHARES,
+            pending: BTreeMap::new(),
+            early_shares: BTreeMap::new(),
+            last_decided_view: TYPES::View::genesis(),
+            cur_view: TYPES::View::genesis(),
+            id,
+        }
+    }
+
+    /// Number of share messages held for blocks we haven't seen decided yet
+    #[must_use]
+    pub fn early_shares(&self) -> usize {
+        self.early_shares.values().map(HashMap::len).sum()
+    }
+
+    /// Start decrypting the encrypted transactions of a decided leaf, releasing our own shares if
+    /// we are on the DA committee.
+    async fn handle_decided_leaf(
+        &mut self,
+        leaf: &Leaf2<TYPES>,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let view_number = leaf.view_number();
+        if self.pending.contains_key(&view_number) {
+            return;
+        }
+        let Some(payload) = leaf.block_payload() else {
+            tracing::debug!("Missing payload for decided view {view_number:?}; can't decrypt");
+            return;
+        };
+
+        let ciphertexts: BTreeMap<u64, Vec<u8>> = payload
+            .transactions(leaf.block_header().metadata())
+            .enumerate()
+            .filter_map(|(index, transaction)| {
+                transaction
+                    .ciphertext()
+                    .map(|ciphertext| (index as u64, ciphertext.to_vec()))
+            })
+            .collect();
+        if ciphertexts.is_empty() {
+            return;
+        }
+
+        let epoch = leaf.epoch();
+        self.pending.insert(
+            view_number,
+            PendingBlock {
+                epoch,
+                ciphertexts,
+                shares: BTreeMap::new(),
+            },
+        );
+
+        if self
+            .membership
+            .read()
+            .await
+            .has_da_stake_at(&self.public_key, view_number, epoch)
+        {
+            let pending = &self.pending[&view_number];
+            let shares: Vec<(u64, Vec<u8>)> = pending
+                .ciphertexts
+                .iter()
+                .filter_map(|(index, ciphertext)| {
+                    self.decryption
+                        .decryption_share(*epoch, ciphertext)
+                        .map(|share| (*index, share))
+                })
+                .collect();
+
+            let own_shares = match DecryptionShares::sign(
+                view_number,
+                epoch,
+                self.public_key.clone(),
+                shares,
+                &self.private_key,
+                &self.upgrade_lock,
+            )
+            .await
+            {
+                Ok(own_shares) => own_shares,
+                Err(e) => {
+                    tracing::error!("Failed to sign our decryption shares; error = {e}");
+                    return;
+                }
+            };
+            self.add_shares(&own_shares).await;
+            broadcast_event(
+                Arc::new(HotShotEvent::DecryptionSharesSend(own_shares)),
+                sender,
+            )
+            .await;
+        }
+
+        for shares in self
+            .early_shares
+            .remove(&view_number)
+            .unwrap_or_default()
+            .into_values()
+        {
+            self.add_shares(&shares).await;
+        }
+        self.try_decrypt(view_number).await;
+    }
+
+    /// Record the valid shares of a committee member for a pending block
+    async fn add_shares(&mut self, shares: &DecryptionShares<TYPES>) {
+        let Some(pending) = self.pending.get_mut(&shares.view_number) else {
+            return;
+        };
+        if shares.epoch != pending.epoch
+            || !self.membership.read().await.has_da_stake_at(
+                &shares.sender,
+                shares.view_number,
+                pending.epoch,
+            )
+        {
+            tracing::debug!(
+                "Ignoring decryption shares from {} for view {:?}; not on the DA committee",
+                shares.sender,
+                shares.view_number
+            );
+            return;
+        }
+
+        let sender = shares.sender.to_bytes();
+        for (index, share) in &shares.shares {
+            let Some(ciphertext) = pending.ciphertexts.get(index) else {
+                continue;
+            };
+            if !self
+                .decryption
+                .verify_share(*pending.epoch, &sender, ciphertext, share)
+            {
+                tracing::warn!(
+                    "Invalid decryption share from {} for view {:?}",
+                    shares.sender,
+                    shares.view_number
+                );
+                continue;
+            }
+            pending
+                .shares
+                .entry(*index)
+                .or_default()
+                .insert(sender.clone(), share.clone());
+        }
+    }
+
+    /// Decrypt every ciphertext of a pending block we have enough shares for, and emit the
+    /// resulting transactions.
+    async fn try_decrypt(&mut self, view_number: TYPES::View) {
+        let Some(pending) = self.pending.get_mut(&view_number) else {
+            return;
+        };
+        let threshold = self.decryption.threshold(*pending.epoch);
+
+        let mut transactions = Vec::new();
+        let ready: Vec<u64> = pending
+            .shares
+            .iter()
+            .filter(|(_, shares)| shares.len() >= threshold)
+            .map(|(index, _)| *index)
+            .collect();
+        for index in ready {
+            let (Some(ciphertext), Some(shares)) = (
+                pending.ciphertexts.remove(&index),
+                pending.shares.remove(&index),
+            ) else {
+                continue;
+            };
+            let shares: Vec<(Vec<u8>, Vec<u8>)> = shares.into_iter().collect();
+
+            let Some(plaintext) =
+                self.decryption
+                    .combine_shares(*pending.epoch, &ciphertext, &shares)
+            else {
+                tracing::warn!("Failed to combine decryption shares for view {view_number:?}");
+                continue;
+            };
+            match bincode::deserialize::<TYPES::Transaction>(&plaintext) {
+                Ok(transaction) => transactions.push(transaction),
+                Err(e) => {
+                    tracing::warn!(
+                        "Decrypted transaction in view {view_number:?} is malformed: {e}"
+                    );
+                }
+            }
+        }
+
+        if pending.ciphertexts.is_empty() {
+            self.pending.remove(&view_number);
+        }
+
+        if !transactions.is_empty() {
+            broadcast_event(
+                Event {
+                    view_number,
+                    event: EventType::TransactionsDecrypted { transactions },
+                },
+                &self.output_event_stream,
+            )
+            .await;
+        }
+    }
+
+    /// Hold signed shares from a committee member for a block we haven't seen decided yet, as long
+    /// as the block is not too far ahead of us and we are not holding too many shares already.
+    async fn hold_early_shares(&mut self, shares: &DecryptionShares<TYPES>) {
+        let view_number = shares.view_number;
+        if view_number <= self.last_decided_view
+            || *view_number > self.cur_view.saturating_add(MAX_OUTSTANDING_VIEWS)
+        {
+            tracing::debug!(
+                "Dropping decryption shares for view {:?} outside of the views we hold shares for",
+                view_number
+            );
+            return;
+        }
+        if !self.membership.read().await.has_da_stake_at(
+            &shares.sender,
+            shares.view_number,
+            shares.epoch,
+        ) {
+            tracing::debug!(
+                "Dropping early decryption shares from {} for view {:?}; not on the DA committee",
+                shares.sender,
+                view_number
+            );
+            return;
+        }
+
+        // A newer message from the same sender replaces its last one
+        let replaces = self
+            .early_shares
+            .get(&view_number)
+            .is_some_and(|early| early.contains_key(&shares.sender));
+        if !replaces && self.early_shares() >= self.max_early_shares {
+            tracing::debug!(
+                "Holding too many early decryption shares; dropping shares for view {:?}",
+                view_number
+            );
+            return;
+        }
+        self.early_shares
+            .entry(view_number)
+            .or_default()
+            .insert(shares.sender.clone(), shares.clone());
+    }
+
+    /// Stop collecting shares for blocks decided too long ago
+    fn prune(&mut self) {
+        let cutoff = TYPES::View::new(
+            self.last_decided_view
+                .saturating_sub(DECRYPTION_RETENTION_VIEWS),
+        );
+        self.pending = self.pending.split_off(&cutoff);
+        self.early_shares = self.early_shares.split_off(&cutoff);
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for DecryptionTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "DecryptionTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::LeavesDecided(leaves) => {
+                for leaf in leaves.iter().rev() {
+                    self.handle_decided_leaf(leaf, sender).await;
+                    self.last_decided_view = self.last_decided_view.max(leaf.view_number());
+                }
+                self.prune();
+            }
+            HotShotEvent::DecryptionSharesRecv(shares) => {
+                if !shares.is_signed(&self.upgrade_lock).await {
+                    tracing::debug!(
+                        "Dropping decryption shares for view {:?} not signed by {}",
+                        shares.view_number,
+                        shares.sender
+                    );
+                    return Ok(());
+                }
+                let view_number = shares.view_number;
+                if self.pending.contains_key(&view_number) {
+                    self.add_shares(shares).await;
+                    self.try_decrypt(view_number).await;
+                } else {
+                    self.hold_early_shares(shares).await;
+                }
+            }
+            HotShotEvent::ViewChange(view, _) => {
+                self.cur_view = self.cur_view.max(*view);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -10,11 +10,17 @@ use async_broadcast::Sender;
 use either::Either;
 use hotshot_task::task::TaskEvent;
 use hotshot_types::{
+    anti_entropy::{CertificateDigest, CertificateSync},
+    catchup::{CatchupPage, CatchupRequest},
     data::{
         DaProposal2, Leaf2, PackedBundle, QuorumProposal2, UpgradeProposal, VidDisperse,
         VidDisperseShare2,
     },
+    encrypted_mempool::DecryptionShares,
+    heartbeat::SignedHeartbeat,
     message::Proposal,
+    peer_score::Misbehavior,
+    proposal_dispersal::ProposalChunk,
     request_response::ProposalRequestPayload,
     simple_certificate::{
         DaCertificate2, NextEpochQuorumCertificate2, QuorumCertificate, QuorumCertificate2,
@@ -26,8 +32,11 @@ use hotshot_types::{
         ViewSyncFinalizeVote2, ViewSyncPreCommitVote2,
     },
     traits::{
-        block_contents::BuilderFee, network::DataRequest, node_implementation::NodeType,
-        signature_key::SignatureKey, BlockPayload,
+        block_contents::{BuilderFee, TransactionPriority},
+        network::DataRequest,
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+        BlockPayload,
     },
     utils::BuilderCommitment,
     vid::VidCommitment,
@@ -79,6 +88,10 @@ pub enum HotShotEvent<TYPES: NodeType> {
     TimeoutVoteRecv(TimeoutVote2<TYPES>),
     /// Send a timeout vote to the network; emitted by consensus task replicas
     TimeoutVoteSend(TimeoutVote2<TYPES>),
+    /// The timeout votes for a view accumulated so far, as a percentage of the threshold for a
+    /// timeout certificate, with the epoch they were cast in; emitted by the next leader's timeout
+    /// vote collector and handled by the transaction task
+    TimeoutVoteProgress(TYPES::View, TYPES::Epoch, u64),
     /// A DA proposal has been received from the network; handled by the DA task
     DaProposalRecv(Proposal<TYPES, DaProposal2<TYPES>>, TYPES::SignatureKey),
     /// A DA proposal has been validated; handled by the DA task and VID task
@@ -91,6 +104,17 @@ pub enum HotShotEvent<TYPES: NodeType> {
     DaCertificateValidated(DaCertificate2<TYPES>),
     /// Send a quorum proposal to the network; emitted by the leader in the consensus task
     QuorumProposalSend(Proposal<TYPES, QuorumProposal2<TYPES>>, TYPES::SignatureKey),
+    /// Send each quorum member its chunk of our erasure-coded proposal; emitted by the leader in
+    /// the proposal dispersal task
+    ProposalChunksSend(
+        Vec<(TYPES::SignatureKey, ProposalChunk<TYPES>)>,
+        TYPES::SignatureKey,
+    ),
+    /// Forward the chunk of a proposal we were sent to the rest of the quorum; emitted by the
+    /// proposal dispersal task
+    ProposalChunkForward(ProposalChunk<TYPES>, TYPES::SignatureKey),
+    /// A chunk of a proposal was received from the network; handled by the proposal dispersal task
+    ProposalChunkRecv(ProposalChunk<TYPES>, TYPES::SignatureKey),
     /// Send a quorum vote to the next leader; emitted by a replica in the consensus task after seeing a valid quorum proposal
     QuorumVoteSend(QuorumVote2<TYPES>),
     /// Broadcast a quorum vote to form an eQC; emitted by a replica in the consensus task after seeing a valid quorum proposal
@@ -167,6 +191,8 @@ pub enum HotShotEvent<TYPES: NodeType> {
     Timeout(TYPES::View, TYPES::Epoch),
     /// Receive transactions from the network
     TransactionsRecv(Vec<TYPES::Transaction>),
+    /// Receive transactions tagged with a priority class from the network
+    PrioritizedTransactionsRecv(Vec<(TYPES::Transaction, TransactionPriority)>),
     /// Send transactions to the network
     TransactionSend(TYPES::Transaction, TYPES::SignatureKey),
     /// Event to send block payload commitment and metadata from DA leader to the quorum; internal event only
@@ -240,6 +266,95 @@ pub enum HotShotEvent<TYPES: NodeType> {
         Proposal<TYPES, VidDisperseShare2<TYPES>>,
     ),
 
+    /// Send a DA certificate request to the network; emitted to a member of the DA committee or
+    /// the leader. Includes the data request, node's public key and the public key of the node we
+    /// want to send to.
+    DaCertificateRequestSend(
+        DataRequest<TYPES>,
+        // Sender
+        TYPES::SignatureKey,
+        // Recipient
+        TYPES::SignatureKey,
+    ),
+
+    /// Receive a DA certificate request from the network. Includes the data request and the
+    /// requesting node's public key.
+    DaCertificateRequestRecv(DataRequest<TYPES>, TYPES::SignatureKey),
+
+    /// Send a DA certificate in response to a request; emitted to the requesting node.
+    DaCertificateResponseSend(
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+        DaCertificate2<TYPES>,
+    ),
+
+    /// Time to send the views of our highest certificates to a random peer; emitted periodically
+    /// by the anti-entropy timer
+    AntiEntropyTick,
+
+    /// Send the views of our highest certificates to a peer. Includes the digest, our public key
+    /// and the public key of the peer.
+    CertificateDigestSend(
+        CertificateDigest<TYPES>,
+        // Sender
+        TYPES::SignatureKey,
+        // Recipient
+        TYPES::SignatureKey,
+    ),
+
+    /// Receive the views of a peer's highest certificates. Includes the digest and the peer's
+    /// public key.
+    CertificateDigestRecv(CertificateDigest<TYPES>, TYPES::SignatureKey),
+
+    /// Send a peer the certificates we hold which are newer than those of its digest
+    CertificateSyncSend(
+        CertificateSync<TYPES>,
+        // Sender
+        TYPES::SignatureKey,
+        // Recipient
+        TYPES::SignatureKey,
+    ),
+
+    /// Receive certificates newer than those of our digest from a peer
+    CertificateSyncRecv(CertificateSync<TYPES>, TYPES::SignatureKey),
+
+    /// Catch up on the decided leaves up to the given view from a peer, even if we are not far
+    /// behind it; emitted by the application to sync its state
+    CatchupStart(TYPES::View),
+
+    /// Request the decided leaves in a range of views from a peer
+    CatchupRequestSend(
+        CatchupRequest<TYPES>,
+        // Sender
+        TYPES::SignatureKey,
+        // Recipient
+        TYPES::SignatureKey,
+    ),
+
+    /// Receive a request for the decided leaves in a range of views. Includes the request and the
+    /// peer's public key.
+    CatchupRequestRecv(CatchupRequest<TYPES>, TYPES::SignatureKey),
+
+    /// Send a peer a page of decided leaves answering its catchup request
+    CatchupPageSend(
+        CatchupPage<TYPES>,
+        // Sender
+        TYPES::SignatureKey,
+        // Recipient
+        TYPES::SignatureKey,
+    ),
+
+    /// Receive a page of decided leaves answering our catchup request from a peer
+    CatchupPageRecv(CatchupPage<TYPES>, TYPES::SignatureKey),
+
+    /// Broadcast our signed heartbeat. Includes the heartbeat and our public key.
+    HeartbeatSend(SignedHeartbeat<TYPES>, TYPES::SignatureKey),
+
+    /// Receive a peer's signed heartbeat. Includes the heartbeat and the peer's public key.
+    HeartbeatRecv(SignedHeartbeat<TYPES>, TYPES::SignatureKey),
+
     /// A replica send us a High QC
     HighQcRecv(QuorumCertificate2<TYPES>, TYPES::SignatureKey),
 
@@ -249,6 +364,18 @@ pub enum HotShotEvent<TYPES: NodeType> {
         TYPES::SignatureKey,
         TYPES::SignatureKey,
     ),
+
+    /// A new chain of leaves was decided, newest first; emitted by the quorum vote task
+    LeavesDecided(Vec<Leaf2<TYPES>>),
+
+    /// Send our decryption shares for a decided block to all nodes; emitted by the decryption task
+    DecryptionSharesSend(DecryptionShares<TYPES>),
+
+    /// Decryption shares for a decided block were received from the network
+    DecryptionSharesRecv(DecryptionShares<TYPES>),
+
+    /// A peer signed a message proving it misbehaved
+    PeerMisbehaved(TYPES::SignatureKey, Misbehavior),
 }
 
 impl<TYPES: NodeType> HotShotEvent<TYPES> {
@@ -260,6 +387,12 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::TimeoutVoteRecv(v) | HotShotEvent::TimeoutVoteSend(v) => {
                 Some(v.view_number())
             }
+            HotShotEvent::TimeoutVoteProgress(view_number, ..) => Some(*view_number),
+            HotShotEvent::ProposalChunksSend(chunks, _) => {
+                chunks.first().map(|(_, chunk)| chunk.view_number)
+            }
+            HotShotEvent::ProposalChunkForward(chunk, _)
+            | HotShotEvent::ProposalChunkRecv(chunk, _) => Some(chunk.view_number),
             HotShotEvent::QuorumProposalRecv(proposal, _)
             | HotShotEvent::QuorumProposalSend(proposal, _)
             | HotShotEvent::QuorumProposalValidated(proposal, _)
@@ -306,8 +439,11 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             }
             HotShotEvent::BlockRecv(packed_bundle) => Some(packed_bundle.view_number),
             HotShotEvent::Shutdown
+            | HotShotEvent::AntiEntropyTick
             | HotShotEvent::TransactionSend(_, _)
-            | HotShotEvent::TransactionsRecv(_) => None,
+            | HotShotEvent::TransactionsRecv(_)
+            | HotShotEvent::PrioritizedTransactionsRecv(_)
+            | HotShotEvent::PeerMisbehaved(..) => None,
             HotShotEvent::VidDisperseSend(proposal, _) => Some(proposal.data.view_number()),
             HotShotEvent::VidShareRecv(_, proposal) | HotShotEvent::VidShareValidated(proposal) => {
                 Some(proposal.data.view_number())
@@ -329,12 +465,30 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::DaCertificateValidated(cert) => Some(cert.view_number),
             HotShotEvent::UpgradeCertificateFormed(cert) => Some(cert.view_number()),
             HotShotEvent::VidRequestSend(request, _, _)
-            | HotShotEvent::VidRequestRecv(request, _) => Some(request.view),
+            | HotShotEvent::VidRequestRecv(request, _)
+            | HotShotEvent::DaCertificateRequestSend(request, _, _)
+            | HotShotEvent::DaCertificateRequestRecv(request, _) => Some(request.view),
+            HotShotEvent::DaCertificateResponseSend(_, _, cert) => Some(cert.view_number),
+            HotShotEvent::CertificateDigestSend(digest, _, _)
+            | HotShotEvent::CertificateDigestRecv(digest, _) => Some(digest.view),
+            HotShotEvent::CertificateSyncSend(sync, _, _)
+            | HotShotEvent::CertificateSyncRecv(sync, _) => Some(sync.view),
+            HotShotEvent::CatchupStart(view) => Some(*view),
+            HotShotEvent::CatchupRequestSend(request, _, _)
+            | HotShotEvent::CatchupRequestRecv(request, _) => Some(request.view),
+            HotShotEvent::CatchupPageSend(page, _, _) | HotShotEvent::CatchupPageRecv(page, _) => {
+                Some(page.view)
+            }
+            HotShotEvent::HeartbeatSend(heartbeat, _)
+            | HotShotEvent::HeartbeatRecv(heartbeat, _) => Some(heartbeat.heartbeat.view),
             HotShotEvent::VidResponseSend(_, _, proposal)
             | HotShotEvent::VidResponseRecv(_, proposal) => Some(proposal.data.view_number),
             HotShotEvent::HighQcRecv(qc, _) | HotShotEvent::HighQcSend(qc, ..) => {
                 Some(qc.view_number())
             }
+            HotShotEvent::LeavesDecided(leaves) => leaves.first().map(Leaf2::view_number),
+            HotShotEvent::DecryptionSharesSend(shares)
+            | HotShotEvent::DecryptionSharesRecv(shares) => Some(shares.view_number),
         }
     }
 }
@@ -365,6 +519,12 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
             HotShotEvent::TimeoutVoteSend(v) => {
                 write!(f, "TimeoutVoteSend(view_number={:?})", v.view_number())
             }
+            HotShotEvent::TimeoutVoteProgress(view_number, _, percent) => {
+                write!(
+                    f,
+                    "TimeoutVoteProgress(view_number={view_number:?}, percent={percent})"
+                )
+            }
             HotShotEvent::DaProposalRecv(proposal, _) => write!(
                 f,
                 "DaProposalRecv(view_number={:?})",
@@ -391,6 +551,22 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "QuorumProposalSend(view_number={:?})",
                 proposal.data.view_number()
             ),
+            HotShotEvent::ProposalChunksSend(chunks, _) => write!(
+                f,
+                "ProposalChunksSend(view_number={:?}, chunks={})",
+                chunks.first().map(|(_, chunk)| chunk.view_number),
+                chunks.len()
+            ),
+            HotShotEvent::ProposalChunkForward(chunk, _) => write!(
+                f,
+                "ProposalChunkForward(view_number={:?}, index={})",
+                chunk.view_number, chunk.index
+            ),
+            HotShotEvent::ProposalChunkRecv(chunk, _) => write!(
+                f,
+                "ProposalChunkRecv(view_number={:?}, index={})",
+                chunk.view_number, chunk.index
+            ),
             HotShotEvent::QuorumVoteSend(vote) => {
                 write!(f, "QuorumVoteSend(view_number={:?})", vote.view_number())
             }
@@ -514,6 +690,9 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 write!(f, "Timeout(view_number={view_number:?}, epoch={epoch:?})")
             }
             HotShotEvent::TransactionsRecv(_) => write!(f, "TransactionsRecv"),
+            HotShotEvent::PrioritizedTransactionsRecv(_) => {
+                write!(f, "PrioritizedTransactionsRecv")
+            }
             HotShotEvent::TransactionSend(_, _) => write!(f, "TransactionSend"),
             HotShotEvent::SendPayloadCommitmentAndMetadata(_, _, _, view_number, _, _) => {
                 write!(
@@ -607,12 +786,91 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                     proposal.data.view_number
                 )
             }
+            HotShotEvent::DaCertificateRequestSend(request, _, _) => {
+                write!(f, "DaCertificateRequestSend(view_number={:?}", request.view)
+            }
+            HotShotEvent::DaCertificateRequestRecv(request, _) => {
+                write!(f, "DaCertificateRequestRecv(view_number={:?}", request.view)
+            }
+            HotShotEvent::DaCertificateResponseSend(_, _, cert) => {
+                write!(
+                    f,
+                    "DaCertificateResponseSend(view_number={:?}",
+                    cert.view_number
+                )
+            }
+            HotShotEvent::AntiEntropyTick => write!(f, "AntiEntropyTick"),
+            HotShotEvent::CertificateDigestSend(digest, _, _) => {
+                write!(f, "CertificateDigestSend(view_number={:?})", digest.view)
+            }
+            HotShotEvent::CertificateDigestRecv(digest, _) => {
+                write!(f, "CertificateDigestRecv(view_number={:?})", digest.view)
+            }
+            HotShotEvent::CertificateSyncSend(sync, _, _) => {
+                write!(f, "CertificateSyncSend(view_number={:?})", sync.view)
+            }
+            HotShotEvent::CertificateSyncRecv(sync, _) => {
+                write!(f, "CertificateSyncRecv(view_number={:?})", sync.view)
+            }
+            HotShotEvent::CatchupStart(view) => {
+                write!(f, "CatchupStart(view_number={view:?})")
+            }
+            HotShotEvent::CatchupRequestSend(request, _, _) => {
+                write!(f, "CatchupRequestSend(view_number={:?})", request.view)
+            }
+            HotShotEvent::CatchupRequestRecv(request, _) => {
+                write!(f, "CatchupRequestRecv(view_number={:?})", request.view)
+            }
+            HotShotEvent::CatchupPageSend(page, _, _) => {
+                write!(f, "CatchupPageSend(view_number={:?})", page.view)
+            }
+            HotShotEvent::CatchupPageRecv(page, _) => {
+                write!(f, "CatchupPageRecv(view_number={:?})", page.view)
+            }
+            HotShotEvent::HeartbeatSend(heartbeat, _) => {
+                write!(
+                    f,
+                    "HeartbeatSend(view_number={:?})",
+                    heartbeat.heartbeat.view
+                )
+            }
+            HotShotEvent::HeartbeatRecv(heartbeat, _) => {
+                write!(
+                    f,
+                    "HeartbeatRecv(view_number={:?})",
+                    heartbeat.heartbeat.view
+                )
+            }
             HotShotEvent::HighQcRecv(qc, _) => {
                 write!(f, "HighQcRecv(view_number={:?}", qc.view_number())
             }
             HotShotEvent::HighQcSend(qc, ..) => {
                 write!(f, "HighQcSend(view_number={:?}", qc.view_number())
             }
+            HotShotEvent::LeavesDecided(leaves) => {
+                write!(
+                    f,
+                    "LeavesDecided(view_number={:?}",
+                    leaves.first().map(Leaf2::view_number)
+                )
+            }
+            HotShotEvent::DecryptionSharesSend(shares) => {
+                write!(
+                    f,
+                    "DecryptionSharesSend(view_number={:?})",
+                    shares.view_number
+                )
+            }
+            HotShotEvent::DecryptionSharesRecv(shares) => {
+                write!(
+                    f,
+                    "DecryptionSharesRecv(view_number={:?})",
+                    shares.view_number
+                )
+            }
+            HotShotEvent::PeerMisbehaved(peer, misbehavior) => {
+                write!(f, "PeerMisbehaved(peer={peer}, misbehavior={misbehavior})")
+            }
         }
     }
 }
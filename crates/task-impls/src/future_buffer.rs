@@ -0,0 +1,108 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use hotshot_types::traits::node_implementation::{ConsensusTime, NodeType};
+
+use crate::events::HotShotEvent;
+
+/// Number of views ahead of the current view for which we hold early events.
+pub const FUTURE_EVENT_HORIZON: u64 = 2;
+
+/// Maximum number of early events we hold for a single view.
+pub const FUTURE_EVENTS_PER_VIEW: usize = 1000;
+
+/// A bounded buffer which holds events for views we have not entered yet, so that a task can
+/// replay them once it does instead of handling them with stale view and epoch information.
+///
+/// Tasks only hold events they would otherwise drop, and hand them back to their own handler on
+/// the view change which releases them:
+/// * votes we collect as the leader are handled at once, so the next leader forms its certificate
+///   whatever view it is in; only votes we would reject now are held, in case we turn out to
+///   collect them once we are in their view, and
+/// * proposals are held until their view is close enough for the task to handle them.
+///
+/// Events which move a node into a new view, such as quorum proposals and view sync certificates,
+/// are never held, as holding them until the node is in their view would keep it from getting
+/// there.
+pub struct FutureEventBuffer<TYPES: NodeType> {
+    /// Number of views ahead of the current view for which we hold events
+    horizon: u64,
+
+    /// Maximum number of events held for a single view
+    capacity_per_view: usize,
+
+    /// Held events by view, in the order they were received
+    events: BTreeMap<TYPES::View, Vec<Arc<HotShotEvent<TYPES>>>>,
+}
+
+impl<TYPES: NodeType> Default for FutureEventBuffer<TYPES> {
+    fn default() -> Self {
+        Self::new(FUTURE_EVENT_HORIZON, FUTURE_EVENTS_PER_VIEW)
+    }
+}
+
+impl<TYPES: NodeType> FutureEventBuffer<TYPES> {
+    /// Create a new buffer holding up to `capacity_per_view` events for each of the `horizon`
+    /// views after the current view
+    #[must_use]
+    pub fn new(horizon: u64, capacity_per_view: usize) -> Self {
+        Self {
+            horizon,
+            capacity_per_view,
+            events: BTreeMap::new(),
+        }
+    }
+
+    /// Hold `event` until `cur_view` reaches `view`, if the view is within the horizon after it.
+    ///
+    /// Returns `false` if the event should be handled now instead, either because its view is not
+    /// in the future or because it is too far in the future to wait for. Events beyond the
+    /// capacity for a view are dropped.
+    pub fn hold(
+        &mut self,
+        cur_view: TYPES::View,
+        view: TYPES::View,
+        event: &Arc<HotShotEvent<TYPES>>,
+    ) -> bool {
+        if view <= cur_view || *view > cur_view.saturating_add(self.horizon) {
+            return false;
+        }
+
+        let held = self.events.entry(view).or_default();
+        if held.len() < self.capacity_per_view {
+            held.push(Arc::clone(event));
+        } else {
+            tracing::debug!(
+                "Future event buffer for view {:?} is full; dropping event",
+                view
+            );
+        }
+        true
+    }
+
+    /// Take all held events for views up to and including `cur_view`, oldest view first.
+    pub fn release(&mut self, cur_view: TYPES::View) -> Vec<Arc<HotShotEvent<TYPES>>> {
+        let later = self.events.split_off(&(cur_view + 1));
+        std::mem::replace(&mut self.events, later)
+            .into_values()
+            .flatten()
+            .collect()
+    }
+
+    /// Number of events currently held
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.events.values().map(Vec::len).sum()
+    }
+
+    /// Whether no events are currently held
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
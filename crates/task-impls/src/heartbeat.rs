@@ -0,0 +1,179 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{num::NonZeroU64, sync::Arc, time::Instant};
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    consensus::OuterConsensus,
+    constants::HEARTBEAT_MISSED_BEATS,
+    heartbeat::{Heartbeat, PeerLiveness, SignedHeartbeat},
+    message::UpgradeLock,
+    traits::{
+        election::Membership,
+        metrics::MetricsFamily,
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::HasViewNumber,
+};
+use tracing::instrument;
+use utils::anytrace::*;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Task which broadcasts our signed heartbeat every few views, and tracks which peers are live
+/// from theirs
+pub struct HeartbeatTaskState<TYPES: NodeType, V: Versions> {
+    /// Reference to consensus, for our high QC and the metrics
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// Membership, to check that heartbeats come from staked peers
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// This node's private key
+    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+
+    /// Number of views between our heartbeats
+    pub interval: NonZeroU64,
+
+    /// The latest heartbeat of each peer
+    pub liveness: PeerLiveness<TYPES>,
+
+    /// The current view
+    pub cur_view: TYPES::View,
+
+    /// The current epoch
+    pub cur_epoch: TYPES::Epoch,
+
+    /// The node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> HeartbeatTaskState<TYPES, V> {
+    /// Number of views a peer's latest heartbeat may be behind ours for it to be live
+    fn liveness_window(&self) -> u64 {
+        self.interval.get().saturating_mul(HEARTBEAT_MISSED_BEATS)
+    }
+
+    /// Broadcast our heartbeat for `view`
+    async fn send_heartbeat(
+        &self,
+        view: TYPES::View,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let heartbeat = Heartbeat {
+            view,
+            high_qc_view: self.consensus.read().await.high_qc().view_number(),
+        };
+        let heartbeat =
+            SignedHeartbeat::sign(heartbeat, &self.private_key, &self.upgrade_lock).await?;
+
+        broadcast_event(
+            Arc::new(HotShotEvent::HeartbeatSend(
+                heartbeat,
+                self.public_key.clone(),
+            )),
+            sender,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Record the heartbeat of `peer`, if it is staked and signed it
+    async fn handle_heartbeat(
+        &mut self,
+        heartbeat: &SignedHeartbeat<TYPES>,
+        peer: &TYPES::SignatureKey,
+    ) -> Result<()> {
+        ensure!(
+            self.membership.read().await.has_stake(peer, self.cur_epoch),
+            "Dropping a heartbeat from {}, which is not staked",
+            peer
+        );
+        ensure!(
+            heartbeat.is_signed_by(peer, &self.upgrade_lock).await,
+            warn!(
+                "Dropping a heartbeat from {} with an invalid signature",
+                peer
+            )
+        );
+
+        if self
+            .liveness
+            .record(peer.clone(), heartbeat.heartbeat.clone(), Instant::now())
+        {
+            self.consensus
+                .read()
+                .await
+                .metrics
+                .peer_heartbeat_view
+                .create(vec![peer.to_string()])
+                .set(usize::try_from(*heartbeat.heartbeat.view).unwrap_or(usize::MAX));
+        }
+
+        Ok(())
+    }
+
+    /// Publish the number of live peers
+    async fn update_live_peers(&self) {
+        let live_peers = self
+            .liveness
+            .live_peers(self.cur_view, self.liveness_window())
+            .count();
+        self.consensus
+            .read()
+            .await
+            .metrics
+            .live_peers
+            .set(live_peers);
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for HeartbeatTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "HeartbeatTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::ViewChange(view, epoch) => {
+                if *view <= self.cur_view {
+                    return Ok(());
+                }
+                self.cur_view = *view;
+                self.cur_epoch = *epoch;
+
+                if **view % self.interval.get() == 0 {
+                    self.send_heartbeat(*view, sender).await?;
+                }
+                self.update_live_peers().await;
+            }
+            HotShotEvent::HeartbeatRecv(heartbeat, peer) => {
+                self.handle_heartbeat(heartbeat, peer).await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
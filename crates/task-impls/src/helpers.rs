@@ -4,19 +4,19 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{collections::HashSet, sync::Arc};
 
 use async_broadcast::{Receiver, SendError, Sender};
 use async_lock::RwLock;
 use committable::{Commitment, Committable};
 use hotshot_task::dependency::{Dependency, EventDependency};
 use hotshot_types::{
-    consensus::OuterConsensus,
-    data::{Leaf2, QuorumProposal2, ViewChangeEvidence},
+    consensus::{Consensus, OuterConsensus},
+    data::{null_block, Leaf2, QuorumProposal2, ViewChangeEvidence},
+    error::ConsensusError,
     event::{Event, EventType, LeafInfo},
+    fee_market::expected_base_fee,
+    governance::{GovernedParameters, ParameterChange},
     message::{Proposal, UpgradeLock},
     request_response::ProposalRequestPayload,
     simple_certificate::{QuorumCertificate2, UpgradeCertificate},
@@ -26,14 +26,18 @@ use hotshot_types::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
         signature_key::SignatureKey,
+        states::InstanceState,
+        storage::Storage,
         BlockPayload, ValidatedState,
     },
     utils::{epoch_from_block_number, is_last_block_in_epoch, Terminator, View, ViewInner},
+    vid::{VidCommitment, VidCommon},
     vote::{Certificate, HasViewNumber},
 };
 use tokio::time::timeout;
 use tracing::instrument;
 use utils::anytrace::*;
+use vbs::version::Version;
 
 use crate::{events::HotShotEvent, quorum_proposal_recv::ValidationInfo, request::REQUEST_TIMEOUT};
 
@@ -61,7 +65,9 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     // Finally, compute the signature for the payload.
     let signature = TYPES::SignatureKey::sign(
         &sender_private_key,
-        signed_proposal_request.commit().as_ref(),
+        &upgrade_lock
+            .signing_message(signed_proposal_request.commit().as_ref(), view_number)
+            .await,
     )
     .wrap()
     .context(error!("Failed to sign proposal. This should never happen."))?;
@@ -74,6 +80,7 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     .await;
 
     let mem = Arc::clone(&membership);
+    let proposal_upgrade_lock = upgrade_lock.clone();
     // Make a background task to await the arrival of the event data.
     let Ok(Some(proposal)) =
         // We want to explicitly timeout here so we aren't waiting around for the data.
@@ -106,7 +113,11 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
                     {
                         // Make sure that the quorum_proposal is valid
                         let mem_reader = mem.read().await;
-                        if quorum_proposal.validate_signature(&mem_reader, epoch_height).is_ok() {
+                        if quorum_proposal
+                            .validate_signature(&mem_reader, epoch_height, &proposal_upgrade_lock)
+                            .await
+                            .is_ok()
+                        {
                             proposal = Some(quorum_proposal.clone());
                         }
 
@@ -129,8 +140,10 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     let justify_qc_epoch = justify_qc.data.epoch();
 
     let membership_reader = membership.read().await;
-    let membership_stake_table = membership_reader.stake_table(justify_qc_epoch);
-    let membership_success_threshold = membership_reader.success_threshold(justify_qc_epoch);
+    let membership_stake_table =
+        membership_reader.sampled_stake_table(justify_qc.view_number(), justify_qc_epoch);
+    let membership_success_threshold =
+        membership_reader.sampled_success_threshold(justify_qc.view_number(), justify_qc_epoch);
     drop(membership_reader);
 
     if !justify_qc
@@ -202,6 +215,24 @@ impl<TYPES: NodeType + Default> Default for LeafChainTraversalOutcome<TYPES> {
     }
 }
 
+/// Pairs each leaf of a decided chain with the QC which certifies it. `leaf_views` is sorted newest
+/// first, as in [`LeafChainTraversalOutcome`], so the QC certifying a leaf is the justify QC of the
+/// leaf before it in the list, or `decide_qc` for the newest leaf.
+#[must_use]
+pub fn certifying_qcs<'a, TYPES: NodeType>(
+    leaf_views: &'a [LeafInfo<TYPES>],
+    decide_qc: Option<&QuorumCertificate2<TYPES>>,
+) -> Vec<(&'a LeafInfo<TYPES>, Option<QuorumCertificate2<TYPES>>)> {
+    let mut certifying_qc = decide_qc.cloned();
+    leaf_views
+        .iter()
+        .map(|info| {
+            let qc = std::mem::replace(&mut certifying_qc, Some(info.leaf.justify_qc()));
+            (info, qc)
+        })
+        .collect()
+}
+
 /// calculate the new decided leaf chain based on the rules of hostuff 2
 ///
 /// # Panics
@@ -212,8 +243,29 @@ pub async fn decide_from_proposal_2<TYPES: NodeType>(
     existing_upgrade_cert: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
     public_key: &TYPES::SignatureKey,
 ) -> LeafChainTraversalOutcome<TYPES> {
-    let mut res = LeafChainTraversalOutcome::default();
     let consensus_reader = consensus.read().await;
+    let existing_upgrade_cert_reader = existing_upgrade_cert.read().await;
+
+    decide_from_proposal_2_locked(
+        proposal,
+        &consensus_reader,
+        existing_upgrade_cert_reader.as_ref(),
+        public_key,
+    )
+}
+
+/// [`decide_from_proposal_2`], with the consensus state and the decided upgrade certificate
+/// already read, so that several decide rules can be applied to the same state
+///
+/// # Panics
+/// Can't actually panic
+pub fn decide_from_proposal_2_locked<TYPES: NodeType>(
+    proposal: &QuorumProposal2<TYPES>,
+    consensus_reader: &Consensus<TYPES>,
+    existing_upgrade_cert: Option<&UpgradeCertificate<TYPES>>,
+    public_key: &TYPES::SignatureKey,
+) -> LeafChainTraversalOutcome<TYPES> {
+    let mut res = LeafChainTraversalOutcome::default();
     let proposed_leaf = Leaf2::from_quorum_proposal(proposal);
     res.new_locked_view_number = Some(proposed_leaf.justify_qc().view_number());
 
@@ -236,7 +288,6 @@ pub async fn decide_from_proposal_2<TYPES: NodeType>(
     // We've reached decide, now get the leaf chain all the way back to the last decided view, not including it.
     let old_anchor_view = consensus_reader.last_decided_view();
     let mut current_leaf_info = Some(grand_parent_info);
-    let existing_upgrade_cert_reader = existing_upgrade_cert.read().await;
     let mut txns = HashSet::new();
     while current_leaf_info
         .as_ref()
@@ -246,7 +297,7 @@ pub async fn decide_from_proposal_2<TYPES: NodeType>(
         let info = &mut current_leaf_info.unwrap();
         // Check if there's a new upgrade certificate available.
         if let Some(cert) = info.leaf.upgrade_certificate() {
-            if info.leaf.upgrade_certificate() != *existing_upgrade_cert_reader {
+            if Some(&cert) != existing_upgrade_cert {
                 if cert.data.decide_by < decided_view_number {
                     tracing::warn!("Failed to decide an upgrade certificate in time. Ignoring.");
                 } else {
@@ -320,6 +371,23 @@ pub async fn decide_from_proposal<TYPES: NodeType>(
 ) -> LeafChainTraversalOutcome<TYPES> {
     let consensus_reader = consensus.read().await;
     let existing_upgrade_cert_reader = existing_upgrade_cert.read().await;
+
+    decide_from_proposal_locked(
+        proposal,
+        &consensus_reader,
+        existing_upgrade_cert_reader.as_ref(),
+        public_key,
+    )
+}
+
+/// [`decide_from_proposal`], with the consensus state and the decided upgrade certificate already
+/// read, so that several decide rules can be applied to the same state
+pub fn decide_from_proposal_locked<TYPES: NodeType>(
+    proposal: &QuorumProposal2<TYPES>,
+    consensus_reader: &Consensus<TYPES>,
+    existing_upgrade_cert: Option<&UpgradeCertificate<TYPES>>,
+    public_key: &TYPES::SignatureKey,
+) -> LeafChainTraversalOutcome<TYPES> {
     let view_number = proposal.view_number();
     let parent_view_number = proposal.justify_qc.view_number();
     let old_anchor_view = consensus_reader.last_decided_view();
@@ -374,7 +442,7 @@ pub async fn decide_from_proposal<TYPES: NodeType>(
 
                 // Check if there's a new upgrade certificate available.
                 if let Some(cert) = leaf.upgrade_certificate() {
-                    if leaf.upgrade_certificate() != *existing_upgrade_cert_reader {
+                    if Some(&cert) != existing_upgrade_cert {
                         if cert.data.decide_by < view_number {
                             tracing::warn!(
                                 "Failed to decide an upgrade certificate in time. Ignoring."
@@ -399,11 +467,7 @@ pub async fn decide_from_proposal<TYPES: NodeType>(
                 // Get the VID share at the leaf's view number, corresponding to our key
                 // (if one exists)
                 let vid_share = consensus_reader
-                    .vid_shares()
-                    .get(&leaf.view_number())
-                    .unwrap_or(&HashMap::new())
-                    .get(public_key)
-                    .cloned()
+                    .vid_share(leaf.view_number(), public_key)
                     .map(|prop| prop.data);
 
                 // Add our data into a new `LeafInfo`
@@ -431,7 +495,8 @@ pub async fn decide_from_proposal<TYPES: NodeType>(
     res
 }
 
-/// Gets the parent leaf and state from the parent of a proposal, returning an [`utils::anytrace::Error`] if not.
+/// Gets the parent leaf and state from the parent of a proposal, returning a
+/// [`ConsensusError::ParentMissing`] if we can't get hold of them.
 #[instrument(skip_all)]
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
@@ -444,7 +509,7 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
     upgrade_lock: &UpgradeLock<TYPES, V>,
     parent_view_number: TYPES::View,
     epoch_height: u64,
-) -> Result<(Leaf2<TYPES>, Arc<<TYPES as NodeType>::ValidatedState>)> {
+) -> std::result::Result<(Leaf2<TYPES>, Arc<<TYPES as NodeType>::ValidatedState>), ConsensusError> {
     let consensus_reader = consensus.read().await;
     let vsm_contains_parent_view = consensus_reader
         .validated_state_map()
@@ -464,18 +529,33 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
             epoch_height,
         )
         .await
-        .context(info!("Failed to fetch proposal"))?;
+        .map_err(|e| {
+            ConsensusError::ParentMissing(format!(
+                "Failed to fetch proposal for parent view {}: {e}",
+                *parent_view_number
+            ))
+        })?;
     }
 
     let consensus_reader = consensus.read().await;
     //let parent_view_number = consensus_reader.high_qc().view_number();
-    let parent_view = consensus_reader.validated_state_map().get(&parent_view_number).context(
-        debug!("Couldn't find parent view in state map, waiting for replica to see proposal; parent_view_number: {}", *parent_view_number)
-    )?;
-
-    let (leaf_commitment, state) = parent_view.leaf_and_state().context(
-        info!("Parent of high QC points to a view without a proposal; parent_view_number: {parent_view_number:?}, parent_view {parent_view:?}")
-    )?;
+    let parent_view = consensus_reader
+        .validated_state_map()
+        .get(&parent_view_number)
+        .ok_or_else(|| {
+            ConsensusError::ParentMissing(format!(
+                "Couldn't find parent view in state map, waiting for replica to see proposal; \
+                 parent_view_number: {}",
+                *parent_view_number
+            ))
+        })?;
+
+    let (leaf_commitment, state) = parent_view.leaf_and_state().ok_or_else(|| {
+        ConsensusError::ParentMissing(format!(
+            "Parent of high QC points to a view without a proposal; parent_view_number: \
+             {parent_view_number:?}, parent_view {parent_view:?}"
+        ))
+    })?;
 
     if leaf_commitment != consensus_reader.high_qc().data().leaf_commit {
         // NOTE: This happens on the genesis block
@@ -489,11 +569,166 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
     let leaf = consensus_reader
         .saved_leaves()
         .get(&leaf_commitment)
-        .context(info!("Failed to find high QC of parent"))?;
+        .ok_or_else(|| {
+            ConsensusError::ParentMissing(String::from("Failed to find high QC of parent"))
+        })?;
 
     Ok((leaf.clone(), Arc::clone(state)))
 }
 
+/// Looks up the leaf `justify_qc` certifies, and the validated state of its view, among those we
+/// have already seen.
+///
+/// # Errors
+/// [`ConsensusError::ParentMissing`] if we haven't seen the leaf yet, and
+/// [`ConsensusError::InconsistentState`] if we have the leaf but not its state.
+pub fn saved_parent_leaf_and_state<TYPES: NodeType>(
+    consensus: &Consensus<TYPES>,
+    justify_qc: &QuorumCertificate2<TYPES>,
+) -> std::result::Result<(Leaf2<TYPES>, Arc<<TYPES as NodeType>::ValidatedState>), ConsensusError> {
+    let leaf = consensus
+        .saved_leaves()
+        .get(&justify_qc.data.leaf_commit)
+        .ok_or_else(|| {
+            ConsensusError::ParentMissing(format!(
+                "Proposal's parent missing from storage with commitment: {:?}",
+                justify_qc.data.leaf_commit
+            ))
+        })?;
+
+    let (Some(state), _) = consensus.state_and_delta(leaf.view_number()) else {
+        return Err(ConsensusError::InconsistentState(String::from(
+            "Parent state not found! Consensus internally inconsistent",
+        )));
+    };
+
+    Ok((leaf.clone(), state))
+}
+
+/// Check that the block with `header` extends `parent`, whose state is `parent_state`, as a
+/// replica does before voting for it in `view_number`, and compute the state after the block.
+/// `vid_common` is the VID common data of the block's payload.
+///
+/// # Errors
+/// [`ConsensusError::ProposalInvalid`] if the block has the wrong base fee or header extension,
+/// and [`ConsensusError::InvalidStateTransition`] if it fails to apply to `parent_state`.
+pub async fn validate_block_against_parent<TYPES: NodeType>(
+    instance_state: &TYPES::InstanceState,
+    parent: &Leaf2<TYPES>,
+    parent_state: &TYPES::ValidatedState,
+    header: &TYPES::BlockHeader,
+    vid_common: VidCommon,
+    version: Version,
+    view_number: TYPES::View,
+) -> std::result::Result<
+    (
+        TYPES::ValidatedState,
+        <TYPES::ValidatedState as ValidatedState<TYPES>>::Delta,
+    ),
+    ConsensusError,
+> {
+    if let Some(base_fee) = expected_base_fee::<TYPES>(instance_state, parent.block_header()) {
+        if header.base_fee() != Some(base_fee) {
+            return Err(ConsensusError::ProposalInvalid(format!(
+                "Proposed block has base fee {:?}, but its parent requires {}",
+                header.base_fee(),
+                base_fee
+            )));
+        }
+    }
+
+    // A block re-proposed to form an eQC was already checked against its parent
+    if header.block_number() != parent.height() {
+        instance_state
+            .validate_header_extension(
+                parent.block_header().header_extension(),
+                header.header_extension(),
+                header.block_number(),
+                version,
+            )
+            .map_err(|e| {
+                ConsensusError::ProposalInvalid(format!(
+                    "Proposed block has an invalid header extension: {e}"
+                ))
+            })?;
+    }
+
+    parent_state
+        .validate_and_apply_header(
+            instance_state,
+            parent,
+            header,
+            vid_common,
+            version,
+            *view_number,
+        )
+        .await
+        .map_err(|e| ConsensusError::InvalidStateTransition(e.to_string()))
+}
+
+/// Ensure that the block with `payload_commitment` may be proposed in `view_number`: between the
+/// last view of the version we upgrade from and the first view of the one we upgrade to, only the
+/// null block may be.
+///
+/// # Errors
+/// If `view_number` is between versions and the block is not the null block.
+pub async fn ensure_null_block_while_upgrading<TYPES: NodeType, V: Versions>(
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+    view_number: TYPES::View,
+    payload_commitment: VidCommitment,
+    num_storage_nodes: usize,
+) -> Result<()> {
+    if upgrade_lock.upgrading_in(view_number).await {
+        ensure!(
+            Some(payload_commitment) == null_block::commitment(num_storage_nodes),
+            warn!(
+                "Only the null block may be proposed in view {:?}, which is between versions",
+                view_number
+            )
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove the view change evidence archived for the proposals of the views in `start..=end` which
+/// could not have justified them, so that the archive only keeps the certificates which advanced
+/// consensus. Returns the number of records removed.
+///
+/// # Errors
+/// If the evidence cannot be loaded from or removed from `storage`.
+pub async fn prune_view_change_evidence<TYPES: NodeType, S: Storage<TYPES>>(
+    storage: &S,
+    start: TYPES::View,
+    end: TYPES::View,
+) -> Result<usize> {
+    let archived = storage
+        .load_view_change_evidence(start, end)
+        .await
+        .wrap()
+        .context(warn!("Failed to load view change evidence"))?;
+
+    let mut pruned = 0;
+    for (view, evidence) in archived {
+        if evidence.is_valid_for_view(&view) {
+            continue;
+        }
+        tracing::debug!(
+            "Pruning view change evidence for view {:?} archived with the proposal for view {:?}",
+            evidence.justified_view(),
+            view
+        );
+        storage
+            .remove_view_change_evidence(view)
+            .await
+            .wrap()
+            .context(warn!("Failed to remove view change evidence"))?;
+        pruned += 1;
+    }
+
+    Ok(pruned)
+}
+
 /// Validate the state and safety and liveness of a proposal then emit
 /// a `QuorumProposalValidated` event.
 ///
@@ -556,6 +791,23 @@ pub async fn validate_proposal_safety_and_liveness<
         )
         .await?;
 
+    // Between versions we only vote for the null block. A block re-proposed to form an eQC was
+    // already checked when it was first proposed.
+    if proposed_leaf.height() != parent_leaf.height() {
+        let num_storage_nodes = validation_info
+            .membership
+            .read()
+            .await
+            .total_nodes(TYPES::Epoch::new(proposal_epoch));
+        ensure_null_block_while_upgrading(
+            &validation_info.upgrade_lock,
+            view_number,
+            proposal.data.block_header.payload_commitment(),
+            num_storage_nodes,
+        )
+        .await?;
+    }
+
     let justify_qc = proposal.data.justify_qc.clone();
     // Create a positive vote if either liveness or safety check
     // passes.
@@ -658,34 +910,52 @@ pub(crate) async fn validate_proposal_view_and_certs<
 >(
     proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
     validation_info: &ValidationInfo<TYPES, I, V>,
-) -> Result<()> {
+) -> std::result::Result<(), ConsensusError> {
     let view_number = proposal.data.view_number();
-    ensure!(
-        view_number >= validation_info.consensus.read().await.cur_view(),
-        "Proposal is from an older view {:?}",
-        proposal.data.clone()
-    );
+    if view_number < validation_info.consensus.read().await.cur_view() {
+        return Err(ConsensusError::StaleMessage(format!(
+            "Proposal is from an older view {:?}",
+            proposal.data.clone()
+        )));
+    }
 
     // Validate the proposal's signature. This should also catch if the leaf_commitment does not equal our calculated parent commitment
     let membership_reader = validation_info.membership.read().await;
-    proposal.validate_signature(&membership_reader, validation_info.epoch_height)?;
+    proposal
+        .validate_signature(
+            &membership_reader,
+            validation_info.epoch_height,
+            &validation_info.upgrade_lock,
+        )
+        .await
+        .map_err(|e| ConsensusError::SignatureInvalid(e.to_string()))?;
     drop(membership_reader);
 
-    // Verify a timeout certificate OR a view sync certificate exists and is valid.
-    if proposal.data.justify_qc.view_number() != view_number - 1 {
-        let received_proposal_cert =
-            proposal.data.view_change_evidence.clone().context(debug!(
-                "Quorum proposal for view {} needed a timeout or view sync certificate, but did not have one",
+    // Verify a timeout certificate OR a view sync certificate exists if the proposal needs one.
+    let preceding_view = view_number.checked_sub(1);
+    if Some(proposal.data.justify_qc.view_number()) != preceding_view
+        && proposal.data.view_change_evidence.is_none()
+    {
+        return Err(ConsensusError::ProposalInvalid(format!(
+            "Quorum proposal for view {} needed a timeout or view sync certificate, but did not have one",
+            *view_number
+        )));
+    }
+
+    // Evidence is validated whenever it is attached, even if the proposal does not need it, since
+    // it is archived along with the proposal. Evidence for any other view is stale, and would let
+    // an ancient certificate pass for the reason the view advanced.
+    if let Some(ref evidence) = proposal.data.view_change_evidence {
+        if !evidence.is_valid_for_view(&view_number) {
+            return Err(ConsensusError::CertificateInvalid(format!(
+                "View change evidence for view {} cannot justify the proposal for view {}",
+                *evidence.justified_view(),
                 *view_number
-        ))?;
+            )));
+        }
 
-        match received_proposal_cert {
+        match evidence {
             ViewChangeEvidence::Timeout(timeout_cert) => {
-                ensure!(
-                    timeout_cert.data().view == view_number - 1,
-                    "Timeout certificate for view {} was not for the immediately preceding view",
-                    *view_number
-                );
                 let timeout_cert_epoch = timeout_cert.data().epoch();
 
                 let membership_reader = validation_info.membership.read().await;
@@ -694,26 +964,21 @@ pub(crate) async fn validate_proposal_view_and_certs<
                     membership_reader.success_threshold(timeout_cert_epoch);
                 drop(membership_reader);
 
-                ensure!(
-                    timeout_cert
-                        .is_valid_cert(
-                            membership_stake_table,
-                            membership_success_threshold,
-                            &validation_info.upgrade_lock
-                        )
-                        .await,
-                    "Timeout certificate for view {} was invalid",
-                    *view_number
-                );
+                if !timeout_cert
+                    .is_valid_cert(
+                        membership_stake_table,
+                        membership_success_threshold,
+                        &validation_info.upgrade_lock,
+                    )
+                    .await
+                {
+                    return Err(ConsensusError::CertificateInvalid(format!(
+                        "Timeout certificate for view {} was invalid",
+                        *view_number
+                    )));
+                }
             }
             ViewChangeEvidence::ViewSync(view_sync_cert) => {
-                ensure!(
-                    view_sync_cert.view_number == view_number,
-                    "View sync cert view number {:?} does not match proposal view number {:?}",
-                    view_sync_cert.view_number,
-                    view_number
-                );
-
                 let view_sync_cert_epoch = view_sync_cert.data().epoch();
 
                 let membership_reader = validation_info.membership.read().await;
@@ -723,16 +988,18 @@ pub(crate) async fn validate_proposal_view_and_certs<
                 drop(membership_reader);
 
                 // View sync certs must also be valid.
-                ensure!(
-                    view_sync_cert
-                        .is_valid_cert(
-                            membership_stake_table,
-                            membership_success_threshold,
-                            &validation_info.upgrade_lock
-                        )
-                        .await,
-                    "Invalid view sync finalize cert provided"
-                );
+                if !view_sync_cert
+                    .is_valid_cert(
+                        membership_stake_table,
+                        membership_success_threshold,
+                        &validation_info.upgrade_lock,
+                    )
+                    .await
+                {
+                    return Err(ConsensusError::CertificateInvalid(String::from(
+                        "Invalid view sync finalize cert provided",
+                    )));
+                }
             }
         }
     }
@@ -750,12 +1017,45 @@ pub(crate) async fn validate_proposal_view_and_certs<
             epoch,
             &validation_info.upgrade_lock,
         )
-        .await?;
+        .await
+        .map_err(|e| ConsensusError::CertificateInvalid(e.to_string()))?;
     }
 
     Ok(())
 }
 
+/// Logs a [`ConsensusError`] at its level and counts it in the metrics. Errors the operator can
+/// act on are also surfaced to the application as an event.
+pub async fn report_consensus_error<TYPES: NodeType>(
+    error: &ConsensusError,
+    view_number: TYPES::View,
+    consensus: &OuterConsensus<TYPES>,
+    output_event_stream: &Sender<Event<TYPES>>,
+) {
+    Error::from(error.clone()).log();
+
+    consensus
+        .read()
+        .await
+        .metrics
+        .consensus_errors
+        .create(vec![error.kind().to_string()])
+        .add(1);
+
+    if error.is_user_actionable() {
+        broadcast_event(
+            Event {
+                view_number,
+                event: EventType::ConsensusError {
+                    error: error.clone(),
+                },
+            },
+            output_event_stream,
+        )
+        .await;
+    }
+}
+
 /// Helper function to send events and log errors
 pub async fn broadcast_event<E: Clone + std::fmt::Debug>(event: E, sender: &Sender<E>) {
     match sender.broadcast_direct(event).await {
@@ -774,3 +1074,56 @@ pub async fn broadcast_event<E: Clone + std::fmt::Debug>(event: E, sender: &Send
         }
     }
 }
+
+/// How many views' leaders, per node, we look through for the distinct relays of a view sync round
+const VIEW_SYNC_RELAY_SEARCH_FACTOR: u64 = 4;
+
+/// The node which collects the view sync votes of `round` for relay number `relay`.
+///
+/// The relays of a round are the distinct leaders of the views from `round` on, in order, so
+/// that failing over to the next relay always moves to another node even if the leader schedule
+/// repeats a node. Once every node has been a relay, the relays start over.
+///
+/// # Errors
+/// If a leader cannot be calculated
+pub fn view_sync_relay<TYPES: NodeType>(
+    membership: &TYPES::Membership,
+    round: TYPES::View,
+    relay: u64,
+    epoch: TYPES::Epoch,
+) -> Result<TYPES::SignatureKey> {
+    let num_nodes = u64::try_from(membership.total_nodes(epoch)).unwrap_or(u64::MAX);
+    let target = usize::try_from(relay % num_nodes.max(1)).unwrap_or(usize::MAX);
+
+    // A leader schedule which skips some nodes for a long stretch of views falls back to the
+    // plain leader schedule
+    let max_views = num_nodes.saturating_mul(VIEW_SYNC_RELAY_SEARCH_FACTOR);
+    let mut relays = HashSet::new();
+    for offset in 0..max_views {
+        let leader = membership.leader(round + offset, epoch)?;
+        if relays.insert(leader.clone()) && relays.len() > target {
+            return Ok(leader);
+        }
+    }
+
+    membership.leader(round + relay, epoch)
+}
+
+/// Schedules a decided parameter change from `activation_view` on, and limits the DA committee of
+/// `membership` to the size it sets from the same view on, if it sets one. The DA committee is
+/// kept per view, so a change decided after its activation view still applies to exactly the
+/// views from its activation view on.
+pub async fn schedule_parameter_change<TYPES: NodeType>(
+    governed_parameters: &GovernedParameters<TYPES>,
+    membership: &RwLock<TYPES::Membership>,
+    activation_view: TYPES::View,
+    change: ParameterChange,
+) {
+    governed_parameters.schedule(activation_view, change);
+    if let Some(da_committee_size) = change.da_committee_size {
+        membership
+            .write()
+            .await
+            .set_da_committee_size(activation_view, Some(da_committee_size));
+    }
+}
@@ -19,6 +19,9 @@ pub mod da;
 /// The task which implements all transaction handling
 pub mod transactions;
 
+/// Filter of recently decided transactions, used to keep them out of proposals
+pub mod recent_transactions;
+
 /// Defines the events passed between tasks
 pub mod events;
 
@@ -47,12 +50,48 @@ pub mod builder;
 /// Helper functions used by any task
 pub mod helpers;
 
+/// Buffer for events that arrive before the node enters their view
+pub mod future_buffer;
+
 /// Task which responses to requests from the network
 pub mod response;
 
 /// Task for requesting the network for things
 pub mod request;
 
+/// Task for sampling the availability of decided blocks from outside the DA committee
+pub mod availability;
+
+/// Task for decrypting the encrypted transactions of decided blocks
+pub mod decryption;
+
+/// Task for exchanging certificates with peers that may have missed them
+pub mod anti_entropy;
+
+/// Task for catching up on missed decided leaves from peers, and serving them to peers
+pub mod catchup;
+
+/// Task for broadcasting our heartbeat and tracking the liveness of peers
+pub mod heartbeat;
+
+/// Task for scoring misbehaving peers and banning them
+pub mod peer_score;
+
+/// Task for erasure coding large proposals among the quorum and recovering them from chunks
+pub mod proposal_dispersal;
+
+/// Pacing of our sends within an upload bandwidth budget, most urgent first
+pub mod send_scheduler;
+
+/// Budgeting of background work within each view, deferring low priority work near the deadline
+pub mod work_scheduler;
+
+/// Task which collects post-mortem bundles when the node stalls or a safety check fails
+pub mod post_mortem;
+
+/// Task which rehearses the decide rule of the version we are set to upgrade to on live traffic
+pub mod shadow_consensus;
+
 /// Task for handling logic for quorum proposals
 pub mod quorum_proposal;
 
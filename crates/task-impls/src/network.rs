@@ -5,7 +5,7 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     hash::{DefaultHasher, Hash, Hasher},
     sync::Arc,
 };
@@ -13,16 +13,24 @@ use std::{
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::Committable;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
+    clock::Clock,
+    compact_vote::{CompactQuorumVote, CompactVoteDecoder},
     consensus::OuterConsensus,
-    data::{VidDisperse, VidDisperseShare, VidDisperseShare2},
+    data::{QuorumProposal2, VidDisperse, VidDisperseShare, VidDisperseShare2},
     event::{Event, EventType, HotShotAction},
     message::{
         convert_proposal, DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message,
-        MessageKind, Proposal, SequencingMessage, UpgradeLock,
+        MessageClass, MessageKind, OutboundMessage, Proposal, ReplayProtectionConfig,
+        SequencingMessage, UpgradeLock,
     },
-    simple_vote::HasEpoch,
+    message_ttl::{MessageTtlConfig, TtlHeader},
+    peer_score::PeerScoreboard,
+    proposal_dispersal::ProposalChunk,
+    simple_certificate::QuorumCertificate2,
+    simple_vote::{HasEpoch, QuorumVote2, VersionedVoteData},
     traits::{
         election::Membership,
         network::{
@@ -30,9 +38,10 @@ use hotshot_types::{
             ViewMessage,
         },
         node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::SignatureKey,
         storage::Storage,
     },
-    vote::{HasViewNumber, Vote},
+    vote::{Certificate, HasViewNumber, Vote},
 };
 use tokio::{spawn, task::JoinHandle};
 use tracing::instrument;
@@ -41,12 +50,13 @@ use vbs::version::StaticVersionType;
 
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
-    helpers::broadcast_event,
+    helpers::{broadcast_event, view_sync_relay},
+    send_scheduler::{SendClass, SendScheduler},
 };
 
 /// the network message task state
 #[derive(Clone)]
-pub struct NetworkMessageTaskState<TYPES: NodeType> {
+pub struct NetworkMessageTaskState<TYPES: NodeType, V: Versions> {
     /// Sender to send internal events this task generates to other tasks
     pub internal_event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
 
@@ -58,9 +68,46 @@ pub struct NetworkMessageTaskState<TYPES: NodeType> {
 
     /// Transaction Cache to ignore previously seen transactions
     pub transactions_cache: lru::LruCache<u64, ()>,
+
+    /// Reference to consensus, used to look up the current view for replay protection
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// How far in the past or future of the current view we accept each type of message
+    pub replay_protection: ReplayProtectionConfig,
+
+    /// Number of messages rejected by replay protection, per peer
+    pub rejected_messages: lru::LruCache<TYPES::SignatureKey, u64>,
+
+    /// Number of messages dropped for views beyond the outstanding view horizon
+    pub beyond_horizon_messages: u64,
+
+    /// Our view when we last started catching up beyond the horizon, and the furthest view we
+    /// started catching up to since
+    pub horizon_catchup: Option<(TYPES::View, TYPES::View)>,
+
+    /// Our reading of the wall clock, which messages' expiry times are checked against
+    pub clock: Clock,
+
+    /// Number of messages dropped because they had expired, per class of message
+    pub expired_messages: HashMap<MessageClass, u64>,
+
+    /// Rebuilds the quorum votes we receive in their compact encoding
+    pub compact_votes: CompactVoteDecoder<TYPES>,
+
+    /// Scores of misbehaving peers; messages from banned peers are dropped unread
+    pub peer_scores: Arc<PeerScoreboard<TYPES>>,
+
+    /// Membership, used to authenticate messages beyond the horizon before catching up to them
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Number of blocks in an epoch, zero means there are no epochs
+    pub epoch_height: u64,
 }
 
-impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
+impl<TYPES: NodeType, V: Versions> NetworkMessageTaskState<TYPES, V> {
     #[instrument(skip_all, name = "Network message task", level = "trace")]
     /// Handles a (deserialized) message from the network
     pub async fn handle_message(&mut self, message: Message<TYPES>) {
@@ -68,30 +115,53 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
 
         // Match the message kind and send the appropriate event to the internal event stream
         let sender = message.sender;
+        if !self.peer_scores.admit(&sender).await {
+            tracing::trace!("Dropping message from banned or rate limited peer {sender}");
+            return;
+        }
+        // Messages beyond the horizon are from peers ahead of us rather than replays, so they are
+        // checked first, to catch up instead of rejecting them as replays
+        if !self.within_horizon(&message.kind).await {
+            return;
+        }
+        if !self.within_replay_window(&sender, &message.kind).await {
+            return;
+        }
         match message.kind {
             // Handle consensus messages
             MessageKind::Consensus(consensus_message) => {
                 let event = match consensus_message {
                     SequencingMessage::General(general_message) => match general_message {
                         GeneralConsensusMessage::Proposal(proposal) => {
-                            HotShotEvent::QuorumProposalRecv(convert_proposal(proposal), sender)
+                            let proposal = convert_proposal(proposal);
+                            self.release_compact_votes(&proposal.data).await;
+                            HotShotEvent::QuorumProposalRecv(proposal, sender)
                         }
                         GeneralConsensusMessage::Proposal2(proposal) => {
+                            self.release_compact_votes(&proposal.data).await;
                             HotShotEvent::QuorumProposalRecv(proposal, sender)
                         }
                         GeneralConsensusMessage::ProposalRequested(req, sig) => {
                             HotShotEvent::QuorumProposalRequestRecv(req, sig)
                         }
                         GeneralConsensusMessage::ProposalResponse(proposal) => {
-                            HotShotEvent::QuorumProposalResponseRecv(convert_proposal(proposal))
+                            let proposal = convert_proposal(proposal);
+                            self.release_compact_votes(&proposal.data).await;
+                            HotShotEvent::QuorumProposalResponseRecv(proposal)
                         }
                         GeneralConsensusMessage::ProposalResponse2(proposal) => {
+                            self.release_compact_votes(&proposal.data).await;
                             HotShotEvent::QuorumProposalResponseRecv(proposal)
                         }
                         GeneralConsensusMessage::Vote(vote) => {
                             HotShotEvent::QuorumVoteRecv(vote.to_vote2())
                         }
                         GeneralConsensusMessage::Vote2(vote) => HotShotEvent::QuorumVoteRecv(vote),
+                        GeneralConsensusMessage::CompactVote(vote) => {
+                            let votes = self.expand_compact_vote(vote).await;
+                            self.broadcast_quorum_votes(votes).await;
+                            return;
+                        }
                         GeneralConsensusMessage::ViewSyncPreCommitVote(view_sync_message) => {
                             HotShotEvent::ViewSyncPreCommitVoteRecv(view_sync_message.to_vote2())
                         }
@@ -146,6 +216,9 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                             HotShotEvent::UpgradeVoteRecv(message)
                         }
                         GeneralConsensusMessage::HighQc(qc) => HotShotEvent::HighQcRecv(qc, sender),
+                        GeneralConsensusMessage::ProposalChunk(chunk) => {
+                            HotShotEvent::ProposalChunkRecv(chunk, sender)
+                        }
                     },
                     SequencingMessage::Da(da_message) => match da_message {
                         DaConsensusMessage::DaProposal(proposal) => {
@@ -170,6 +243,9 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                         DaConsensusMessage::DaCertificate2(cert) => {
                             HotShotEvent::DaCertificateRecv(cert)
                         }
+                        DaConsensusMessage::DecryptionShares(shares) => {
+                            HotShotEvent::DecryptionSharesRecv(shares)
+                        }
                     },
                 };
                 broadcast_event(Arc::new(event), &self.internal_event_stream).await;
@@ -189,6 +265,21 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                     )
                     .await;
                 }
+                DataMessage::SubmitPrioritizedTransaction(transaction, priority, _) => {
+                    let mut hasher = DefaultHasher::new();
+                    transaction.hash(&mut hasher);
+                    if self.transactions_cache.put(hasher.finish(), ()).is_some() {
+                        return;
+                    }
+                    broadcast_event(
+                        Arc::new(HotShotEvent::PrioritizedTransactionsRecv(vec![(
+                            transaction,
+                            priority,
+                        )])),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
                 DataMessage::DataResponse(response) => {
                     if let ResponseMessage::Found(message) = response {
                         match message {
@@ -211,18 +302,78 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                                 )
                                 .await;
                             }
+                            // Certificates are validated like broadcast ones
+                            SequencingMessage::Da(DaConsensusMessage::DaCertificate(cert)) => {
+                                broadcast_event(
+                                    Arc::new(HotShotEvent::DaCertificateRecv(cert.to_dac2())),
+                                    &self.internal_event_stream,
+                                )
+                                .await;
+                            }
+                            SequencingMessage::Da(DaConsensusMessage::DaCertificate2(cert)) => {
+                                broadcast_event(
+                                    Arc::new(HotShotEvent::DaCertificateRecv(cert)),
+                                    &self.internal_event_stream,
+                                )
+                                .await;
+                            }
                             _ => {}
                         }
                     }
                 }
+                DataMessage::CertificateDigest(digest) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::CertificateDigestRecv(digest, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::CertificateSync(sync) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::CertificateSyncRecv(sync, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::CatchupRequest(request) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::CatchupRequestRecv(request, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::CatchupPage(page) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::CatchupPageRecv(page, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::Heartbeat(heartbeat) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::HeartbeatRecv(heartbeat, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
                 DataMessage::RequestData(data) => {
                     let req_data = data.clone();
-                    if let RequestKind::Vid(_view_number, _key) = req_data.request {
-                        broadcast_event(
-                            Arc::new(HotShotEvent::VidRequestRecv(data, sender)),
-                            &self.internal_event_stream,
-                        )
-                        .await;
+                    match req_data.request {
+                        RequestKind::Vid(..) | RequestKind::VidSample(..) => {
+                            broadcast_event(
+                                Arc::new(HotShotEvent::VidRequestRecv(data, sender)),
+                                &self.internal_event_stream,
+                            )
+                            .await;
+                        }
+                        RequestKind::DaCertificate(..) => {
+                            broadcast_event(
+                                Arc::new(HotShotEvent::DaCertificateRequestRecv(data, sender)),
+                                &self.internal_event_stream,
+                            )
+                            .await;
+                        }
+                        _ => {}
                     }
                 }
             },
@@ -244,6 +395,244 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
             }
         }
     }
+
+    /// Rebuilds a compact quorum vote into the full vote for each leaf proposed in its view
+    async fn expand_compact_vote(
+        &mut self,
+        vote: CompactQuorumVote<TYPES>,
+    ) -> Vec<QuorumVote2<TYPES>> {
+        let mut votes = Vec::new();
+
+        // Our own proposals don't come back to us over the network
+        if !self.compact_votes.knows_view(vote.view_number) {
+            let own_proposal = self
+                .consensus
+                .read()
+                .await
+                .last_proposals()
+                .get(&vote.view_number)
+                .map(|proposal| proposal.data.clone());
+            if let Some(proposal) = own_proposal {
+                votes.extend(self.compact_votes.add_proposal(&proposal));
+            }
+        }
+
+        votes.extend(self.compact_votes.add_vote(vote));
+        votes
+    }
+
+    /// Records a received proposal with the compact vote decoder, forwarding the votes that were
+    /// waiting on it
+    async fn release_compact_votes(&mut self, proposal: &QuorumProposal2<TYPES>) {
+        let votes = self.compact_votes.add_proposal(proposal);
+        self.broadcast_quorum_votes(votes).await;
+    }
+
+    /// Forwards received quorum votes to the other tasks
+    async fn broadcast_quorum_votes(&self, votes: Vec<QuorumVote2<TYPES>>) {
+        for vote in votes {
+            broadcast_event(
+                Arc::new(HotShotEvent::QuorumVoteRecv(vote)),
+                &self.internal_event_stream,
+            )
+            .await;
+        }
+    }
+
+    /// Checks that a message is within the replay protection window around our current view.
+    ///
+    /// Anyone can replay a peer's old messages, so rejected messages are counted but not held
+    /// against the sender they claim.
+    async fn within_replay_window(
+        &mut self,
+        sender: &TYPES::SignatureKey,
+        kind: &MessageKind<TYPES>,
+    ) -> bool {
+        let Some(tolerance) = self.replay_protection.tolerance(kind) else {
+            return true;
+        };
+        let view = kind.view_number();
+        let consensus = self.consensus.read().await;
+        let cur_view = consensus.cur_view();
+        if tolerance.contains(*view, *cur_view) {
+            return true;
+        }
+        consensus
+            .metrics
+            .replay_rejected_messages
+            .create(vec![MessageClass::of(kind).to_string()])
+            .add(1);
+        drop(consensus);
+
+        let rejected = self
+            .rejected_messages
+            .get_or_insert_mut(sender.clone(), || 0);
+        *rejected += 1;
+        tracing::debug!(
+            "Rejecting message for view {:?} outside of the replay window around view {:?}; {} messages rejected from this peer",
+            view,
+            cur_view,
+            rejected
+        );
+
+        false
+    }
+
+    /// Checks that a message is within the outstanding view horizon after our current view.
+    ///
+    /// Honest peers send messages beyond it when we have fallen behind, as after an outage, so the
+    /// sender isn't penalized; we drop the message, and catch up to its view instead. Anyone can
+    /// claim a far-future view, so we only catch up to messages which prove it: those signed by
+    /// their view's leader or by a staked member, and valid certificates.
+    async fn within_horizon(&mut self, kind: &MessageKind<TYPES>) -> bool {
+        let view = kind.view_number();
+        let cur_view = self.consensus.read().await.cur_view();
+        if !self
+            .replay_protection
+            .beyond_horizon(kind, *view, *cur_view)
+        {
+            return true;
+        }
+
+        self.beyond_horizon_messages += 1;
+        tracing::debug!(
+            "Dropping message for view {:?} beyond the outstanding view horizon of view {:?}; {} messages dropped",
+            view,
+            cur_view,
+            self.beyond_horizon_messages
+        );
+        // Checked before the message can take the catch-up slot, so forged views can't hold it
+        if !self.proves_view(kind).await {
+            tracing::debug!("Not catching up to view {view:?}: the message doesn't prove it");
+            return false;
+        }
+        // Start catching up again once our view changes, in case catching up to the furthest view
+        // so far did not get us there
+        if self.horizon_catchup.map_or(true, |(started_in, furthest)| {
+            started_in != cur_view || view > furthest
+        }) {
+            self.horizon_catchup = Some((cur_view, view));
+            broadcast_event(
+                Arc::new(HotShotEvent::CatchupStart(view)),
+                &self.internal_event_stream,
+            )
+            .await;
+        }
+
+        false
+    }
+
+    /// Whether a message proves the network has reached its view: a proposal signed by the view's
+    /// leader, a vote signed by a staked member, or a valid certificate.
+    async fn proves_view(&self, kind: &MessageKind<TYPES>) -> bool {
+        let MessageKind::Consensus(SequencingMessage::General(message)) = kind else {
+            return false;
+        };
+        match message {
+            GeneralConsensusMessage::Proposal(proposal)
+            | GeneralConsensusMessage::ProposalResponse(proposal) => proposal
+                .validate_signature(
+                    &*self.membership.read().await,
+                    self.epoch_height,
+                    &self.upgrade_lock,
+                )
+                .await
+                .is_ok(),
+            GeneralConsensusMessage::Proposal2(proposal)
+            | GeneralConsensusMessage::ProposalResponse2(proposal) => proposal
+                .validate_signature(
+                    &*self.membership.read().await,
+                    self.epoch_height,
+                    &self.upgrade_lock,
+                )
+                .await
+                .is_ok(),
+            GeneralConsensusMessage::Vote2(vote) => self.signed_by_staked_member(vote).await,
+            GeneralConsensusMessage::TimeoutVote2(vote) => self.signed_by_staked_member(vote).await,
+            GeneralConsensusMessage::HighQc(qc) => self.valid_qc(qc).await,
+            _ => false,
+        }
+    }
+
+    /// Whether `vote` is validly signed by a member with stake in the vote's epoch
+    async fn signed_by_staked_member<VOTE: Vote<TYPES> + HasEpoch<TYPES>>(
+        &self,
+        vote: &VOTE,
+    ) -> bool {
+        let key = vote.signing_key();
+        if !self.membership.read().await.has_stake(&key, vote.epoch()) {
+            return false;
+        }
+        let Ok(data) =
+            VersionedVoteData::new(vote.date().clone(), vote.view_number(), &self.upgrade_lock)
+                .await
+        else {
+            return false;
+        };
+
+        key.validate(&vote.signature(), data.commit().as_ref())
+    }
+
+    /// Whether `qc` is signed by enough of the quorum sampled for its view
+    async fn valid_qc(&self, qc: &QuorumCertificate2<TYPES>) -> bool {
+        let membership_reader = self.membership.read().await;
+        let stake_table = membership_reader.sampled_stake_table(qc.view_number(), qc.data.epoch);
+        let threshold =
+            membership_reader.sampled_success_threshold(qc.view_number(), qc.data.epoch);
+        drop(membership_reader);
+
+        qc.is_valid_cert(stake_table, threshold, &self.upgrade_lock)
+            .await
+    }
+
+    /// Number of messages dropped for views beyond the outstanding view horizon
+    #[must_use]
+    pub fn beyond_horizon_message_count(&self) -> u64 {
+        self.beyond_horizon_messages
+    }
+
+    /// Number of messages from `peer` rejected by replay protection
+    #[must_use]
+    pub fn rejected_message_count(&self, peer: &TYPES::SignatureKey) -> u64 {
+        self.rejected_messages.peek(peer).copied().unwrap_or(0)
+    }
+
+    /// Reads the TTL header off a message from the network, returning the message behind it, or
+    /// `None` if the message has expired, in which case it is dropped without being decoded.
+    pub async fn strip_ttl_header<'a>(&mut self, message: &'a [u8]) -> Option<&'a [u8]> {
+        let (header, message) = match TtlHeader::split(message) {
+            Ok(split) => split,
+            Err(e) => {
+                tracing::warn!("Dropping message without a TTL header: {e}");
+                return None;
+            }
+        };
+
+        let consensus = self.consensus.read().await;
+        if !header.is_expired(*consensus.cur_view(), self.clock.now()) {
+            return Some(message);
+        }
+        consensus
+            .metrics
+            .expired_messages
+            .create(vec![header.class.to_string()])
+            .add(1);
+        drop(consensus);
+
+        *self.expired_messages.entry(header.class).or_default() += 1;
+        tracing::debug!(
+            "Dropping expired {} message, which expired after view {}",
+            header.class,
+            header.expires_after_view
+        );
+        None
+    }
+
+    /// Number of messages of `class` dropped because they had expired
+    #[must_use]
+    pub fn expired_message_count(&self, class: MessageClass) -> u64 {
+        self.expired_messages.get(&class).copied().unwrap_or(0)
+    }
 }
 
 /// network event task state
@@ -276,6 +665,29 @@ pub struct NetworkEventTaskState<
 
     /// map view number to transmit tasks
     pub transmit_tasks: BTreeMap<TYPES::View, Vec<JoinHandle<()>>>,
+
+    /// Whether the messages left unsent by a previous run have been re-sent, which happens on the
+    /// first view change
+    pub resent_outbound_messages: bool,
+
+    /// Number of upcoming views whose leaders we connect to on each view change
+    pub connection_warm_up_views: u64,
+
+    /// How long each class of message stays relevant, which is stamped on messages we send
+    pub message_ttl: MessageTtlConfig,
+
+    /// Our reading of the wall clock, from which messages' expiry times are stamped
+    pub clock: Clock,
+
+    /// The known stake table, which our quorum votes index into when sent compactly
+    pub known_stake_table: Arc<Vec<TYPES::SignatureKey>>,
+
+    /// Minimum size of the proposals which are erasure coded among the quorum instead of
+    /// broadcast, in which case the proposal task sends their chunks
+    pub proposal_dispersal_min_bytes: Option<u64>,
+
+    /// Paces our sends within the upload budget, most urgent first
+    pub send_scheduler: Arc<SendScheduler>,
 }
 
 #[async_trait]
@@ -330,8 +742,10 @@ impl<
         sender: &<TYPES as NodeType>::SignatureKey,
     ) -> Option<HotShotTaskCompleted> {
         let view = vid_proposal.data.view_number;
+        let critical_recipients = self.critical_share_recipients(&vid_proposal.data).await;
         let vid_share_proposals = VidDisperseShare2::to_vid_share_proposals(vid_proposal);
-        let mut messages = HashMap::new();
+        let mut critical_messages = HashMap::new();
+        let mut straggler_messages = HashMap::new();
 
         for proposal in vid_share_proposals {
             let recipient = proposal.data.recipient_key.clone();
@@ -360,20 +774,31 @@ impl<
                     )),
                 }
             };
-            let serialized_message = match self.upgrade_lock.serialize(&message).await {
+            let serialized_message = match self
+                .upgrade_lock
+                .serialize_with(&message, self.network.wire_format())
+                .await
+            {
                 Ok(serialized) => serialized,
                 Err(e) => {
                     tracing::error!("Failed to serialize message: {}", e);
                     continue;
                 }
             };
+            let ttl_header = TtlHeader::new(&message.kind, &self.message_ttl, self.clock.now());
 
-            messages.insert(recipient, serialized_message);
+            let messages = if critical_recipients.contains(&recipient) {
+                &mut critical_messages
+            } else {
+                &mut straggler_messages
+            };
+            messages.insert(recipient, ttl_header.stamp(serialized_message));
         }
 
         let net = Arc::clone(&self.network);
         let storage = Arc::clone(&self.storage);
         let consensus = OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
+        let send_scheduler = Arc::clone(&self.send_scheduler);
         spawn(async move {
             if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
                 Some(HotShotAction::VidDisperse),
@@ -386,15 +811,117 @@ impl<
             {
                 return;
             }
-            match net.vid_broadcast_message(messages).await {
-                Ok(()) => {}
-                Err(e) => tracing::warn!("Failed to send message from network task: {:?}", e),
+            for (class, messages) in [
+                (SendClass::CriticalShares, critical_messages),
+                (SendClass::StragglerShares, straggler_messages),
+            ] {
+                if messages.is_empty() {
+                    continue;
+                }
+                let bytes = messages.values().map(Vec::len).sum();
+                if let Err(e) = send_scheduler
+                    .send(class, bytes, net.vid_broadcast_message(messages))
+                    .await
+                {
+                    tracing::warn!("Failed to send message from network task: {:?}", e);
+                }
             }
         });
 
         None
     }
 
+    /// The recipients of the VID shares of `vid_proposal` whose shares we send first: the next
+    /// leader, and enough other members, in stake table order, to form a quorum with it. Once
+    /// they have their shares they can vote, so the rest can wait.
+    async fn critical_share_recipients(
+        &self,
+        vid_proposal: &VidDisperse<TYPES>,
+    ) -> BTreeSet<TYPES::SignatureKey> {
+        let epoch = vid_proposal.target_epoch;
+        let (quorum, next_leader) = {
+            let membership = self.membership.read().await;
+            (
+                membership.total_nodes(epoch) * 2 / 3 + 1,
+                membership.leader(vid_proposal.view_number + 1, epoch).ok(),
+            )
+        };
+
+        let mut critical: BTreeSet<_> = next_leader.into_iter().collect();
+        for recipient in vid_proposal.shares.keys() {
+            if critical.len() >= quorum {
+                break;
+            }
+            critical.insert(recipient.clone());
+        }
+
+        critical
+    }
+
+    /// handle `ProposalChunksSend`, sending each quorum member its chunk of our proposal
+    async fn handle_proposal_chunks(
+        &self,
+        chunks: Vec<(TYPES::SignatureKey, ProposalChunk<TYPES>)>,
+        sender: &<TYPES as NodeType>::SignatureKey,
+    ) {
+        let Some(view) = chunks.first().map(|(_, chunk)| chunk.view_number) else {
+            return;
+        };
+        let mut messages = HashMap::new();
+
+        for (recipient, chunk) in chunks {
+            let message = Message {
+                sender: sender.clone(),
+                kind: MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                    GeneralConsensusMessage::ProposalChunk(chunk),
+                )),
+            };
+            let serialized_message = match self
+                .upgrade_lock
+                .serialize_with(&message, self.network.wire_format())
+                .await
+            {
+                Ok(serialized) => serialized,
+                Err(e) => {
+                    tracing::error!("Failed to serialize message: {}", e);
+                    continue;
+                }
+            };
+            let ttl_header = TtlHeader::new(&message.kind, &self.message_ttl, self.clock.now());
+
+            messages.insert(recipient, ttl_header.stamp(serialized_message));
+        }
+
+        let net = Arc::clone(&self.network);
+        let storage = Arc::clone(&self.storage);
+        let consensus = OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
+        let send_scheduler = Arc::clone(&self.send_scheduler);
+        spawn(async move {
+            if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
+                Some(HotShotAction::Propose),
+                storage,
+                consensus,
+                view,
+            )
+            .await
+            .is_err()
+            {
+                return;
+            }
+            let bytes = messages.values().map(Vec::len).sum();
+            if let Err(e) = send_scheduler
+                .send(
+                    SendClass::Proposal,
+                    bytes,
+                    net.vid_broadcast_message(messages),
+                )
+                .await
+            {
+                tracing::warn!("Failed to send proposal chunks: {:?}", e);
+            }
+        });
+    }
+
     /// Record `HotShotAction` if available
     async fn maybe_record_action(
         maybe_action: Option<HotShotAction>,
@@ -452,14 +979,18 @@ impl<
     )> {
         match event.as_ref().clone() {
             HotShotEvent::QuorumProposalSend(proposal, sender) => {
-                *maybe_action = Some(HotShotAction::Propose);
-
-                let message = if self
+                let version = self
                     .upgrade_lock
                     .version_infallible(proposal.data.view_number())
-                    .await
-                    >= V::Epochs::VERSION
+                    .await;
+                if version >= V::Epochs::VERSION
+                    && ProposalChunk::should_disperse(&proposal, self.proposal_dispersal_min_bytes)
                 {
+                    return None;
+                }
+                *maybe_action = Some(HotShotAction::Propose);
+
+                let message = if version >= V::Epochs::VERSION {
                     MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
                         GeneralConsensusMessage::Proposal2(proposal),
                     ))
@@ -493,12 +1024,19 @@ impl<
                     }
                 };
 
-                let message = if self
+                let version = self
                     .upgrade_lock
                     .version_infallible(vote.view_number())
-                    .await
-                    >= V::Epochs::VERSION
-                {
+                    .await;
+                let compact_vote = (version >= V::Epochs::VERSION
+                    && version >= V::CompactVotes::VERSION)
+                    .then(|| CompactQuorumVote::compact(&vote, &self.known_stake_table))
+                    .flatten();
+                let message = if let Some(compact_vote) = compact_vote {
+                    MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                        GeneralConsensusMessage::CompactVote(compact_vote),
+                    ))
+                } else if version >= V::Epochs::VERSION {
                     MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
                         GeneralConsensusMessage::Vote2(vote.clone()),
                     ))
@@ -562,6 +1100,17 @@ impl<
                 self.handle_vid_disperse_proposal(proposal, &sender).await;
                 None
             }
+            HotShotEvent::ProposalChunksSend(chunks, sender) => {
+                self.handle_proposal_chunks(chunks, &sender).await;
+                None
+            }
+            HotShotEvent::ProposalChunkForward(chunk, sender) => Some((
+                sender,
+                MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                    GeneralConsensusMessage::ProposalChunk(chunk),
+                )),
+                TransmitType::Broadcast,
+            )),
             HotShotEvent::DaProposalSend(proposal, sender) => {
                 *maybe_action = Some(HotShotAction::DaPropose);
 
@@ -631,14 +1180,27 @@ impl<
 
                 Some((sender, message, TransmitType::Broadcast))
             }
+            HotShotEvent::DecryptionSharesSend(shares) => {
+                let sender = shares.sender.clone();
+                let message = MessageKind::<TYPES>::from_consensus_message(SequencingMessage::Da(
+                    DaConsensusMessage::DecryptionShares(shares),
+                ));
+
+                Some((sender, message, TransmitType::Broadcast))
+            }
             HotShotEvent::ViewSyncPreCommitVoteSend(vote) => {
-                let view_number = vote.view_number() + vote.date().relay;
-                let leader = match self.membership.read().await.leader(view_number, self.epoch) {
+                let leader = match view_sync_relay::<TYPES>(
+                    &*self.membership.read().await,
+                    vote.view_number(),
+                    vote.date().relay,
+                    self.epoch,
+                ) {
                     Ok(l) => l,
                     Err(e) => {
                         tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
+                            "Failed to calculate relay {} for view sync round {:?}. Error: {:?}",
+                            vote.date().relay,
+                            vote.view_number(),
                             e
                         );
                         return None;
@@ -663,13 +1225,18 @@ impl<
             }
             HotShotEvent::ViewSyncCommitVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::ViewSyncVote);
-                let view_number = vote.view_number() + vote.date().relay;
-                let leader = match self.membership.read().await.leader(view_number, self.epoch) {
+                let leader = match view_sync_relay::<TYPES>(
+                    &*self.membership.read().await,
+                    vote.view_number(),
+                    vote.date().relay,
+                    self.epoch,
+                ) {
                     Ok(l) => l,
                     Err(e) => {
                         tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
+                            "Failed to calculate relay {} for view sync round {:?}. Error: {:?}",
+                            vote.date().relay,
+                            vote.view_number(),
                             e
                         );
                         return None;
@@ -694,13 +1261,18 @@ impl<
             }
             HotShotEvent::ViewSyncFinalizeVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::ViewSyncVote);
-                let view_number = vote.view_number() + vote.date().relay;
-                let leader = match self.membership.read().await.leader(view_number, self.epoch) {
+                let leader = match view_sync_relay::<TYPES>(
+                    &*self.membership.read().await,
+                    vote.view_number(),
+                    vote.date().relay,
+                    self.epoch,
+                ) {
                     Ok(l) => l,
                     Err(e) => {
                         tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
+                            "Failed to calculate relay {} for view sync round {:?}. Error: {:?}",
+                            vote.date().relay,
+                            vote.view_number(),
                             e
                         );
                         return None;
@@ -836,13 +1408,22 @@ impl<
                 if epoch > self.epoch {
                     self.epoch = epoch;
                 }
-                let keep_view = TYPES::View::new(view.saturating_sub(1));
+                let keep_view = view.checked_sub(1).unwrap_or_else(TYPES::View::genesis);
                 self.cancel_tasks(keep_view);
+                if !self.resent_outbound_messages {
+                    self.resent_outbound_messages = true;
+                    self.resend_outbound_messages(keep_view).await;
+                }
                 let net = Arc::clone(&self.network);
-                let epoch = self.epoch.u64();
+                let epoch = self.epoch;
+                let warm_up_views = self.connection_warm_up_views;
                 let mem = Arc::clone(&self.membership);
                 spawn(async move {
-                    net.update_view::<TYPES>(*keep_view, epoch, mem).await;
+                    net.update_view::<TYPES>(*keep_view, epoch.u64(), Arc::clone(&mem))
+                        .await;
+                    // Connect to the leaders we will send votes to before we need to
+                    let leaders = upcoming_leaders::<TYPES>(&mem, view, epoch, warm_up_views).await;
+                    net.warm_up_connections(leaders);
                 });
                 None
             }
@@ -870,6 +1451,55 @@ impl<
                 };
                 Some((sender, message, TransmitType::Direct(to)))
             }
+            HotShotEvent::DaCertificateRequestSend(req, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::RequestData(req)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::DaCertificateResponseSend(sender, to, certificate) => {
+                let message = if self
+                    .upgrade_lock
+                    .version_infallible(certificate.view_number())
+                    .await
+                    >= V::Epochs::VERSION
+                {
+                    MessageKind::Data(DataMessage::DataResponse(ResponseMessage::Found(
+                        SequencingMessage::Da(DaConsensusMessage::DaCertificate2(certificate)),
+                    )))
+                } else {
+                    MessageKind::Data(DataMessage::DataResponse(ResponseMessage::Found(
+                        SequencingMessage::Da(DaConsensusMessage::DaCertificate(
+                            certificate.to_dac(),
+                        )),
+                    )))
+                };
+                Some((sender, message, TransmitType::Direct(to)))
+            }
+            HotShotEvent::CertificateDigestSend(digest, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::CertificateDigest(digest)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::CertificateSyncSend(sync, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::CertificateSync(sync)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::CatchupRequestSend(request, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::CatchupRequest(request)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::CatchupPageSend(page, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::CatchupPage(page)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::HeartbeatSend(heartbeat, sender) => Some((
+                sender,
+                MessageKind::Data(DataMessage::Heartbeat(heartbeat)),
+                TransmitType::Broadcast,
+            )),
             HotShotEvent::HighQcSend(quorum_cert, leader, sender) => Some((
                 sender,
                 MessageKind::Consensus(SequencingMessage::General(
@@ -881,6 +1511,94 @@ impl<
         }
     }
 
+    /// When a message of kind `message_kind` should be broadcast
+    fn broadcast_delay(message_kind: &MessageKind<TYPES>) -> BroadcastDelay {
+        match message_kind {
+            MessageKind::Consensus(
+                SequencingMessage::General(GeneralConsensusMessage::Vote(_))
+                | SequencingMessage::Da(_),
+            ) => BroadcastDelay::View(*message_kind.view_number()),
+            _ => BroadcastDelay::None,
+        }
+    }
+
+    /// Serializes `message` and transmits it on the wire behind `ttl_header`, returning whether
+    /// it was sent.
+    #[allow(clippy::too_many_arguments)]
+    async fn transmit_message(
+        network: &NET,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+        send_scheduler: &SendScheduler,
+        message: &Message<TYPES>,
+        ttl_header: TtlHeader,
+        transmit: TransmitType<TYPES>,
+        da_committee: BTreeSet<TYPES::SignatureKey>,
+        num_nodes: usize,
+    ) -> bool {
+        let serialized_message = match upgrade_lock
+            .serialize_with(message, network.wire_format())
+            .await
+        {
+            Ok(serialized) => ttl_header.stamp(serialized),
+            Err(e) => {
+                tracing::error!("Failed to serialize message: {}", e);
+                return false;
+            }
+        };
+
+        // Every recipient costs us a copy of the message on the upload link
+        let copies = match &transmit {
+            TransmitType::Direct(_) => 1,
+            TransmitType::Broadcast => num_nodes.saturating_sub(1).max(1),
+            TransmitType::DaCommitteeBroadcast => da_committee.len(),
+        };
+        let bytes = serialized_message.len() * copies;
+        let broadcast_delay = Self::broadcast_delay(&message.kind);
+        let transmit_result = send_scheduler
+            .send(SendClass::of(&message.kind), bytes, async {
+                match transmit {
+                    TransmitType::Direct(recipient) => {
+                        network.direct_message(serialized_message, recipient).await
+                    }
+                    TransmitType::Broadcast => {
+                        network
+                            .broadcast_message(serialized_message, Topic::Global, broadcast_delay)
+                            .await
+                    }
+                    TransmitType::DaCommitteeBroadcast => {
+                        network
+                            .da_broadcast_message(
+                                serialized_message,
+                                da_committee.into_iter().collect(),
+                                broadcast_delay,
+                            )
+                            .await
+                    }
+                }
+            })
+            .await;
+
+        match transmit_result {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to send message task: {:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Forgets a persisted outbound message once it has been sent.
+    async fn remove_outbound_message(storage: &RwLock<S>, outbound: &OutboundMessage<TYPES>) {
+        if let Err(e) = storage
+            .write()
+            .await
+            .remove_outbound_message(outbound)
+            .await
+        {
+            tracing::warn!("Failed to remove sent message from storage: {:?}", e);
+        }
+    }
+
     /// Creates a network message and spawns a task that transmits it on the wire.
     async fn spawn_transmit_task(
         &mut self,
@@ -889,28 +1607,24 @@ impl<
         transmit: TransmitType<TYPES>,
         sender: TYPES::SignatureKey,
     ) {
-        let broadcast_delay = match &message_kind {
-            MessageKind::Consensus(
-                SequencingMessage::General(GeneralConsensusMessage::Vote(_))
-                | SequencingMessage::Da(_),
-            ) => BroadcastDelay::View(*message_kind.view_number()),
-            _ => BroadcastDelay::None,
-        };
         let message = Message {
             sender,
             kind: message_kind,
         };
         let view_number = message.kind.view_number();
-        let committee_topic = Topic::Global;
-        let da_committee = self
-            .membership
-            .read()
-            .await
-            .da_committee_members(view_number, self.epoch);
+        let (da_committee, num_nodes) = {
+            let membership = self.membership.read().await;
+            (
+                membership.da_committee_members(view_number, self.epoch),
+                membership.total_nodes(self.epoch),
+            )
+        };
         let network = Arc::clone(&self.network);
         let storage = Arc::clone(&self.storage);
         let consensus = OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
         let upgrade_lock = self.upgrade_lock.clone();
+        let send_scheduler = Arc::clone(&self.send_scheduler);
+        let ttl_header = TtlHeader::new(&message.kind, &self.message_ttl, self.clock.now());
         let handle = spawn(async move {
             if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
                 maybe_action,
@@ -938,37 +1652,41 @@ impl<
                 }
             }
 
-            let serialized_message = match upgrade_lock.serialize(&message).await {
-                Ok(serialized) => serialized,
-                Err(e) => {
-                    tracing::error!("Failed to serialize message: {}", e);
-                    return;
+            // The messages which record an action are the safety-critical ones, which would cost
+            // the view if they were lost in a crash, so they are persisted until they are sent
+            let outbound = maybe_action.map(|_| OutboundMessage {
+                message: message.clone(),
+                transmit: transmit.clone(),
+            });
+            if let Some(outbound) = &outbound {
+                if let Err(e) = storage
+                    .write()
+                    .await
+                    .append_outbound_message(outbound)
+                    .await
+                {
+                    tracing::warn!("Failed to persist outbound message: {:?}", e);
                 }
-            };
+            }
 
-            let transmit_result = match transmit {
-                TransmitType::Direct(recipient) => {
-                    network.direct_message(serialized_message, recipient).await
-                }
-                TransmitType::Broadcast => {
-                    network
-                        .broadcast_message(serialized_message, committee_topic, broadcast_delay)
-                        .await
-                }
-                TransmitType::DaCommitteeBroadcast => {
-                    network
-                        .da_broadcast_message(
-                            serialized_message,
-                            da_committee.iter().cloned().collect(),
-                            broadcast_delay,
-                        )
-                        .await
+            if NetworkEventTaskState::<TYPES, V, NET, S>::transmit_message(
+                &network,
+                &upgrade_lock,
+                &send_scheduler,
+                &message,
+                ttl_header,
+                transmit,
+                da_committee,
+                num_nodes,
+            )
+            .await
+            {
+                if let Some(outbound) = outbound {
+                    NetworkEventTaskState::<TYPES, V, NET, S>::remove_outbound_message(
+                        &storage, &outbound,
+                    )
+                    .await;
                 }
-            };
-
-            match transmit_result {
-                Ok(()) => {}
-                Err(e) => tracing::warn!("Failed to send message task: {:?}", e),
             }
         });
         self.transmit_tasks
@@ -976,6 +1694,83 @@ impl<
             .or_default()
             .push(handle);
     }
+
+    /// Re-sends the outbound messages for views from `view` on which were persisted, but not
+    /// sent, before the node restarted.
+    async fn resend_outbound_messages(&mut self, view: TYPES::View) {
+        let outbound_messages = match self.storage.read().await.load_outbound_messages(view).await {
+            Ok(outbound_messages) => outbound_messages,
+            Err(e) => {
+                tracing::warn!("Failed to load outbound messages from storage: {:?}", e);
+                return;
+            }
+        };
+
+        for outbound in outbound_messages {
+            let view_number = outbound.view_number();
+            tracing::info!("Re-sending message for view {view_number:?} left unsent at restart");
+            let (da_committee, num_nodes) = {
+                let membership = self.membership.read().await;
+                (
+                    membership.da_committee_members(view_number, self.epoch),
+                    membership.total_nodes(self.epoch),
+                )
+            };
+            let network = Arc::clone(&self.network);
+            let storage = Arc::clone(&self.storage);
+            let upgrade_lock = self.upgrade_lock.clone();
+            let send_scheduler = Arc::clone(&self.send_scheduler);
+            let ttl_header =
+                TtlHeader::new(&outbound.message.kind, &self.message_ttl, self.clock.now());
+            let handle = spawn(async move {
+                if NetworkEventTaskState::<TYPES, V, NET, S>::transmit_message(
+                    &network,
+                    &upgrade_lock,
+                    &send_scheduler,
+                    &outbound.message,
+                    ttl_header,
+                    outbound.transmit.clone(),
+                    da_committee,
+                    num_nodes,
+                )
+                .await
+                {
+                    NetworkEventTaskState::<TYPES, V, NET, S>::remove_outbound_message(
+                        &storage, &outbound,
+                    )
+                    .await;
+                }
+            });
+            self.transmit_tasks
+                .entry(view_number)
+                .or_default()
+                .push(handle);
+        }
+    }
+}
+
+/// The leaders of the `num_views` views after `view` in `epoch`, in order and without repeats
+pub async fn upcoming_leaders<TYPES: NodeType>(
+    membership: &RwLock<TYPES::Membership>,
+    view: TYPES::View,
+    epoch: TYPES::Epoch,
+    num_views: u64,
+) -> Vec<TYPES::SignatureKey> {
+    let membership = membership.read().await;
+    let mut leaders = Vec::new();
+    for upcoming_view in (1..=num_views).map(|offset| view + offset) {
+        match membership.leader(upcoming_view, epoch) {
+            Ok(leader) => {
+                if !leaders.contains(&leader) {
+                    leaders.push(leader);
+                }
+            }
+            Err(e) => {
+                tracing::debug!("Not warming up a connection for view {upcoming_view:?}: {e}")
+            }
+        }
+    }
+    leaders
 }
 
 /// A module with test helpers
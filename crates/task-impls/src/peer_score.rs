@@ -0,0 +1,47 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{peer_score::PeerScoreboard, traits::node_implementation::NodeType};
+use tracing::instrument;
+use utils::anytrace::Result;
+
+use crate::events::HotShotEvent;
+
+/// Task which penalizes peers for the misbehavior other tasks detect, banning them at the network
+/// layer once their score reaches the ban threshold
+pub struct PeerScoreTaskState<TYPES: NodeType> {
+    /// Scores of misbehaving peers, shared with the network message task
+    pub peer_scores: Arc<PeerScoreboard<TYPES>>,
+
+    /// The node's id
+    pub id: u64,
+}
+
+#[async_trait]
+impl<TYPES: NodeType> TaskState for PeerScoreTaskState<TYPES> {
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "PeerScoreTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        _sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        if let HotShotEvent::PeerMisbehaved(peer, misbehavior) = event.as_ref() {
+            self.peer_scores.report(peer, *misbehavior).await;
+        }
+
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
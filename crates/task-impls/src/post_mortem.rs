@@ -0,0 +1,419 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Display, Write as _},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use committable::{Commitment, Committable};
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    consensus::OuterConsensus,
+    data::Leaf2,
+    traits::{
+        network::ConnectedNetwork,
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+    },
+    vote::HasViewNumber,
+};
+use tokio::task::spawn_blocking;
+use tracing::instrument;
+use utils::anytrace::Result;
+
+use crate::events::HotShotEvent;
+
+/// Number of most recent events kept for the event log of a post-mortem bundle.
+pub const POST_MORTEM_EVENT_LOG_LEN: usize = 1000;
+
+/// Number of views without a decide after which the watchdog declares the node stuck.
+pub const WATCHDOG_STUCK_VIEW_THRESHOLD: u64 = 50;
+
+/// The reason a post-mortem bundle was collected.
+#[derive(Debug, Clone)]
+pub enum PostMortemReason<TYPES: NodeType> {
+    /// The watchdog saw no decide for too many views
+    Stuck {
+        /// The last view we decided
+        last_decided_view: TYPES::View,
+        /// The view we are currently in
+        current_view: TYPES::View,
+    },
+    /// A safety assertion on the decided chain failed
+    SafetyViolation(String),
+}
+
+impl<TYPES: NodeType> PostMortemReason<TYPES> {
+    /// Short name of the reason, used in the name of the bundle directory.
+    fn slug(&self) -> &'static str {
+        match self {
+            Self::Stuck { .. } => "stuck",
+            Self::SafetyViolation(_) => "safety",
+        }
+    }
+}
+
+impl<TYPES: NodeType> Display for PostMortemReason<TYPES> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stuck {
+                last_decided_view,
+                current_view,
+            } => write!(
+                f,
+                "Watchdog: no decide since view {last_decided_view:?}, current view is {current_view:?}"
+            ),
+            Self::SafetyViolation(reason) => write!(f, "Safety violation: {reason}"),
+        }
+    }
+}
+
+/// Activity seen for one kind of event.
+#[derive(Debug, Clone, Copy)]
+struct EventActivity {
+    /// Number of events of this kind received
+    count: u64,
+    /// When we last received an event of this kind
+    last_seen: DateTime<Utc>,
+}
+
+/// The task state for the post-mortem task, which watches the node for stalls and safety
+/// violations and writes a bundle of diagnostic data when one is detected.
+///
+/// A bundle is a timestamped directory under `post_mortem_dir` containing the reason, the tail of
+/// the internal event log, a snapshot of the activity of the event-driven tasks, network
+/// statistics, and the in-memory consensus state.
+pub struct PostMortemTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
+    /// Directory under which bundles are written
+    pub post_mortem_dir: PathBuf,
+
+    /// Reference to consensus, read when collecting a bundle
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// The underlying network
+    pub network: Arc<I::Network>,
+
+    /// The most recent events received, oldest first
+    pub recent_events: VecDeque<(DateTime<Utc>, String)>,
+
+    /// Activity by event kind
+    activity: BTreeMap<String, EventActivity>,
+
+    /// The view we are currently in
+    pub current_view: TYPES::View,
+
+    /// The last view we decided
+    pub last_decided_view: TYPES::View,
+
+    /// Height and commitment of the last leaf we decided
+    pub last_decided_leaf: (u64, Commitment<Leaf2<TYPES>>),
+
+    /// Whether the watchdog has already reported the current stall
+    pub stuck_reported: bool,
+
+    /// The node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> PostMortemTaskState<TYPES, I> {
+    /// Create a new post-mortem task state
+    pub fn new(
+        post_mortem_dir: PathBuf,
+        consensus: OuterConsensus<TYPES>,
+        network: Arc<I::Network>,
+        last_decided_leaf: &Leaf2<TYPES>,
+        id: u64,
+    ) -> Self {
+        Self {
+            post_mortem_dir,
+            consensus,
+            network,
+            recent_events: VecDeque::with_capacity(POST_MORTEM_EVENT_LOG_LEN),
+            activity: BTreeMap::new(),
+            current_view: last_decided_leaf.view_number(),
+            last_decided_view: last_decided_leaf.view_number(),
+            last_decided_leaf: (last_decided_leaf.height(), last_decided_leaf.commit()),
+            stuck_reported: false,
+            id,
+        }
+    }
+
+    /// Record an event in the event log and the activity snapshot
+    fn record(&mut self, event: &HotShotEvent<TYPES>) {
+        let now = Utc::now();
+        let rendered = event.to_string();
+        let kind = rendered
+            .split(['(', ' ', '{'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        self.activity
+            .entry(kind)
+            .and_modify(|activity| {
+                activity.count += 1;
+                activity.last_seen = now;
+            })
+            .or_insert(EventActivity {
+                count: 1,
+                last_seen: now,
+            });
+
+        if self.recent_events.len() == POST_MORTEM_EVENT_LOG_LEN {
+            self.recent_events.pop_front();
+        }
+        self.recent_events.push_back((now, rendered));
+    }
+
+    /// Check that a newly decided chain, sorted newest first, extends the chain we decided before
+    fn check_decided_chain(&self, leaves: &[Leaf2<TYPES>]) -> Option<String> {
+        for pair in leaves.windows(2) {
+            if pair[0].parent_commitment() != pair[1].commit() {
+                return Some(format!(
+                    "decided leaf for view {:?} does not extend decided leaf for view {:?}",
+                    pair[0].view_number(),
+                    pair[1].view_number()
+                ));
+            }
+        }
+
+        let (height, commitment) = self.last_decided_leaf;
+        let (newest, oldest) = (leaves.first()?, leaves.last()?);
+        if newest.height() < height {
+            return Some(format!(
+                "decided leaf for view {:?} at height {} is below the decided height {}",
+                newest.view_number(),
+                newest.height(),
+                height
+            ));
+        }
+        // We may not have the full chain back to the last decide, in which case there is
+        // nothing to compare against.
+        if oldest.height() == height + 1 && oldest.parent_commitment() != commitment {
+            return Some(format!(
+                "decided leaf for view {:?} at height {} conflicts with the previously decided leaf",
+                oldest.view_number(),
+                oldest.height()
+            ));
+        }
+
+        None
+    }
+
+    /// Collect a post-mortem bundle and write it to a new timestamped directory
+    async fn collect_bundle(&self, reason: &PostMortemReason<TYPES>) {
+        let now = Utc::now();
+        let dir = self.post_mortem_dir.join(format!(
+            "node_{}_{}_{}",
+            self.id,
+            now.format("%Y%m%dT%H%M%S%.3fZ"),
+            reason.slug()
+        ));
+
+        let files = [
+            ("reason.txt", format!("{reason}\ncollected at {now}\n")),
+            ("events.log", self.event_log()),
+            ("tasks.txt", self.task_snapshot()),
+            ("network.txt", self.network_snapshot()),
+            ("consensus.txt", self.consensus_snapshot().await),
+        ];
+
+        // The bundle is written on the blocking pool, so a slow disk doesn't hold up the runtime
+        let written = spawn_blocking({
+            let dir = dir.clone();
+            move || write_bundle(&dir, &files)
+        })
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        if let Err(e) = written {
+            tracing::error!(
+                "Failed to write post-mortem bundle to {}; error = {}",
+                dir.display(),
+                e
+            );
+            return;
+        }
+        tracing::error!(
+            "Node {} wrote post-mortem bundle to {}: {}",
+            self.id,
+            dir.display(),
+            reason
+        );
+    }
+
+    /// The tail of the event log, oldest first
+    fn event_log(&self) -> String {
+        let mut log = String::new();
+        for (time, event) in &self.recent_events {
+            let _ = writeln!(log, "{time}: {event}");
+        }
+        log
+    }
+
+    /// When every kind of event was last seen, which shows which tasks are still making progress
+    fn task_snapshot(&self) -> String {
+        let mut snapshot = String::new();
+        for (kind, activity) in &self.activity {
+            let _ = writeln!(
+                snapshot,
+                "{kind}: count = {}, last seen = {}",
+                activity.count, activity.last_seen
+            );
+        }
+        snapshot
+    }
+
+    /// Network statistics
+    fn network_snapshot(&self) -> String {
+        let mut snapshot = String::new();
+        let _ = writeln!(
+            snapshot,
+            "primary network down: {}",
+            self.network.is_primary_down()
+        );
+        for (kind, activity) in &self.activity {
+            if kind.ends_with("Recv") || kind.ends_with("Send") {
+                let _ = writeln!(
+                    snapshot,
+                    "{kind}: count = {}, last seen = {}",
+                    activity.count, activity.last_seen
+                );
+            }
+        }
+        snapshot
+    }
+
+    /// The consensus state held in memory, which may be ahead of what has been persisted
+    async fn consensus_snapshot(&self) -> String {
+        let consensus_reader = self.consensus.read().await;
+        let decided_leaf = consensus_reader.decided_leaf();
+
+        let mut snapshot = String::new();
+        let _ = writeln!(snapshot, "current view: {:?}", consensus_reader.cur_view());
+        let _ = writeln!(
+            snapshot,
+            "current epoch: {:?}",
+            consensus_reader.cur_epoch()
+        );
+        let _ = writeln!(
+            snapshot,
+            "locked view: {:?}",
+            consensus_reader.locked_view()
+        );
+        let _ = writeln!(
+            snapshot,
+            "last decided view: {:?}",
+            consensus_reader.last_decided_view()
+        );
+        let _ = writeln!(
+            snapshot,
+            "decided leaf: view = {:?}, height = {}, commitment = {}",
+            decided_leaf.view_number(),
+            decided_leaf.height(),
+            decided_leaf.commit()
+        );
+        let _ = writeln!(
+            snapshot,
+            "high qc: view = {:?}, epoch = {:?}",
+            consensus_reader.high_qc().view_number(),
+            consensus_reader.high_qc().data.epoch
+        );
+        let _ = writeln!(
+            snapshot,
+            "next epoch high qc view: {:?}",
+            consensus_reader
+                .next_epoch_high_qc()
+                .map(HasViewNumber::view_number)
+        );
+        let _ = writeln!(
+            snapshot,
+            "undecided views: {:?}",
+            consensus_reader
+                .validated_state_map()
+                .keys()
+                .collect::<Vec<_>>()
+        );
+        let _ = writeln!(
+            snapshot,
+            "saved leaves: {}",
+            consensus_reader.saved_leaves().len()
+        );
+        let _ = writeln!(
+            snapshot,
+            "saved payload views: {:?}",
+            consensus_reader.saved_payloads().keys().collect::<Vec<_>>()
+        );
+        let _ = writeln!(
+            snapshot,
+            "vid share views: {:?}",
+            consensus_reader.vid_shares().keys()
+        );
+        snapshot
+    }
+}
+
+/// Write every file of a bundle into `dir`, creating it first
+fn write_bundle(dir: &Path, files: &[(&str, String)]) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents)?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for PostMortemTaskState<TYPES, I> {
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "PostMortemTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        _sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.record(&event);
+
+        match event.as_ref() {
+            HotShotEvent::ViewChange(view, _) => {
+                self.current_view = std::cmp::max(self.current_view, *view);
+                if !self.stuck_reported
+                    && self.current_view.u64()
+                        >= self.last_decided_view.u64() + WATCHDOG_STUCK_VIEW_THRESHOLD
+                {
+                    self.stuck_reported = true;
+                    self.collect_bundle(&PostMortemReason::Stuck {
+                        last_decided_view: self.last_decided_view,
+                        current_view: self.current_view,
+                    })
+                    .await;
+                }
+            }
+            HotShotEvent::LeavesDecided(leaves) => {
+                if let Some(violation) = self.check_decided_chain(leaves) {
+                    self.collect_bundle(&PostMortemReason::SafetyViolation(violation))
+                        .await;
+                }
+                if let Some(newest) = leaves.first() {
+                    self.last_decided_view = newest.view_number();
+                    self.last_decided_leaf = (newest.height(), newest.commit());
+                    self.stuck_reported = false;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
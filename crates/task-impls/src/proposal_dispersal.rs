@@ -0,0 +1,246 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    data::QuorumProposal2,
+    message::{Proposal, UpgradeLock},
+    proposal_dispersal::{ProposalChunk, ProposalChunkCollector},
+    traits::{
+        election::Membership,
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::HasViewNumber,
+};
+use tokio::task::spawn_blocking;
+use tracing::instrument;
+use utils::anytrace::*;
+use vbs::version::StaticVersionType;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Task which erasure codes our large proposals among the quorum when we lead, and forwards and
+/// recovers the chunks of other leaders' proposals
+pub struct ProposalDispersalTaskState<TYPES: NodeType, V: Versions> {
+    /// Membership, for the quorum a proposal is dispersed among and the leader of each view
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// This node's private key, which signs the chunks of our proposals
+    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+
+    /// Minimum size of the proposals which are dispersed instead of broadcast
+    pub min_bytes: Option<u64>,
+
+    /// Chunks of the proposals we have not recovered yet
+    pub collector: ProposalChunkCollector<TYPES>,
+
+    /// The current view
+    pub cur_view: TYPES::View,
+
+    /// The current epoch
+    pub cur_epoch: TYPES::Epoch,
+
+    /// The node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> ProposalDispersalTaskState<TYPES, V> {
+    /// Erasure code our `proposal` among the quorum if it is large enough, sending each member its
+    /// chunk. The network task broadcasts the proposal whole otherwise.
+    async fn disperse_proposal(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let view = proposal.data.view_number();
+        if self.upgrade_lock.version_infallible(view).await < V::Epochs::VERSION
+            || !ProposalChunk::should_disperse(proposal, self.min_bytes)
+        {
+            return Ok(());
+        }
+
+        let epoch = self.cur_epoch;
+        let members: Vec<_> = self
+            .membership
+            .read()
+            .await
+            .committee_members(view, epoch)
+            .into_iter()
+            .collect();
+        let num_nodes = members.len();
+        let to_disperse = proposal.clone();
+        let private_key = self.private_key.clone();
+        let signing_prefix = self.upgrade_lock.signing_prefix(view).await;
+        let chunks = spawn_blocking(move || {
+            ProposalChunk::disperse(
+                &to_disperse,
+                epoch,
+                num_nodes,
+                &private_key,
+                &signing_prefix,
+            )
+        })
+        .await
+        .wrap()
+        .context(error!("Proposal dispersal panicked"))??;
+
+        let mut to_send = Vec::with_capacity(num_nodes);
+        for (member, chunk) in members.into_iter().zip(chunks) {
+            if member == self.public_key {
+                // Nobody would forward our own chunk for us
+                broadcast_event(
+                    Arc::new(HotShotEvent::ProposalChunkForward(
+                        chunk,
+                        self.public_key.clone(),
+                    )),
+                    sender,
+                )
+                .await;
+            } else {
+                to_send.push((member, chunk));
+            }
+        }
+        tracing::debug!(
+            "Dispersing our proposal for view {:?} in {} chunks",
+            view,
+            to_send.len()
+        );
+
+        broadcast_event(
+            Arc::new(HotShotEvent::ProposalChunksSend(
+                to_send,
+                self.public_key.clone(),
+            )),
+            sender,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Check a chunk from `peer`, forward it if it is ours to forward, and recover the proposal
+    /// once we have enough chunks
+    async fn handle_chunk(
+        &mut self,
+        chunk: &ProposalChunk<TYPES>,
+        peer: &TYPES::SignatureKey,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let view = chunk.view_number();
+        ensure!(
+            view >= self.cur_view,
+            debug!("Dropping a proposal chunk for past view {:?}", view)
+        );
+
+        let (leader, forwarder) = {
+            let membership = self.membership.read().await;
+            let leader = membership.leader(view, chunk.epoch)?;
+            let forwarder = membership
+                .committee_members(view, chunk.epoch)
+                .into_iter()
+                .nth(usize::try_from(chunk.index).wrap()?)
+                .context(warn!(
+                    "Proposal chunk index {} is outside the quorum",
+                    chunk.index
+                ))?;
+            (leader, forwarder)
+        };
+        // We have our own proposal already
+        if leader == self.public_key {
+            return Ok(());
+        }
+        ensure!(
+            *peer == leader || *peer == forwarder,
+            warn!(
+                "Dropping proposal chunk {} for view {:?} from {}, which neither leads the view nor forwards the chunk",
+                chunk.index,
+                view,
+                peer
+            )
+        );
+        ensure!(
+            chunk.is_valid(&leader, &self.upgrade_lock).await,
+            warn!(
+                "Dropping invalid proposal chunk {} for view {:?} from {}",
+                chunk.index, view, peer
+            )
+        );
+
+        if *peer == leader && forwarder == self.public_key {
+            broadcast_event(
+                Arc::new(HotShotEvent::ProposalChunkForward(
+                    chunk.clone(),
+                    self.public_key.clone(),
+                )),
+                sender,
+            )
+            .await;
+        }
+
+        let Some(recoverable) = self.collector.insert(chunk) else {
+            return Ok(());
+        };
+        let proposal = spawn_blocking(move || recoverable.recover())
+            .await
+            .wrap()
+            .context(error!("Proposal recovery panicked"))??;
+        tracing::debug!("Recovered the proposal for view {:?} from its chunks", view);
+
+        broadcast_event(
+            Arc::new(HotShotEvent::QuorumProposalRecv(proposal, leader)),
+            sender,
+        )
+        .await;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for ProposalDispersalTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    #[instrument(skip_all, target = "ProposalDispersalTaskState", fields(id = self.id))]
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::ViewChange(view, epoch) => {
+                if *view <= self.cur_view {
+                    return Ok(());
+                }
+                self.cur_view = *view;
+                self.cur_epoch = *epoch;
+                self.collector.gc(*view);
+            }
+            HotShotEvent::QuorumProposalSend(proposal, _) => {
+                self.disperse_proposal(proposal, sender).await?;
+            }
+            HotShotEvent::ProposalChunkRecv(chunk, peer) => {
+                self.handle_chunk(chunk, peer, sender).await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -28,11 +28,13 @@ use hotshot_types::{
     drb::{INITIAL_DRB_RESULT, INITIAL_DRB_SEED_INPUT},
     message::Proposal,
     simple_certificate::{NextEpochQuorumCertificate2, QuorumCertificate2, UpgradeCertificate},
+    stake_table::StakeTableCommitment,
     traits::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeType},
         signature_key::SignatureKey,
+        states::InstanceState,
     },
     utils::epoch_from_block_number,
     vote::{Certificate, HasViewNumber},
@@ -43,7 +45,7 @@ use vbs::version::StaticVersionType;
 
 use crate::{
     events::HotShotEvent,
-    helpers::{broadcast_event, parent_leaf_and_state},
+    helpers::{broadcast_event, ensure_null_block_while_upgrading, parent_leaf_and_state},
     quorum_proposal::{UpgradeLock, Versions},
 };
 
@@ -98,7 +100,7 @@ pub struct ProposalDependencyHandle<TYPES: NodeType, V: Versions> {
     /// Shared consensus task state
     pub consensus: OuterConsensus<TYPES>,
 
-    /// View timeout from config.
+    /// View timeout of the view being proposed for
     pub timeout: u64,
 
     /// The most recent upgrade certificate this node formed.
@@ -134,9 +136,10 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
         while let Ok(event) = rx.recv_direct().await {
             if let HotShotEvent::HighQcRecv(qc, _sender) = event.as_ref() {
                 let membership_reader = self.membership.read().await;
-                let membership_stake_table = membership_reader.stake_table(qc.data.epoch);
+                let membership_stake_table =
+                    membership_reader.sampled_stake_table(qc.view_number(), qc.data.epoch);
                 let membership_success_threshold =
-                    membership_reader.success_threshold(qc.data.epoch);
+                    membership_reader.sampled_success_threshold(qc.view_number(), qc.data.epoch);
                 drop(membership_reader);
 
                 if qc
@@ -312,10 +315,18 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             }
         }
 
-        let proposal_certificate = view_change_evidence
-            .as_ref()
-            .filter(|cert| cert.is_valid_for_view(&self.view_number))
-            .cloned();
+        // Evidence which cannot justify this view would only get the proposal rejected
+        let proposal_certificate = view_change_evidence.filter(|evidence| {
+            let valid = evidence.is_valid_for_view(&self.view_number);
+            if !valid {
+                tracing::warn!(
+                    "Discarding view change evidence for view {:?}, which cannot justify the proposal for view {:?}",
+                    evidence.justified_view(),
+                    self.view_number
+                );
+            }
+            valid
+        });
 
         ensure!(
             commitment_and_metadata.block_view == self.view_number,
@@ -327,9 +338,27 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
         let builder_commitment = commitment_and_metadata.builder_commitment.clone();
         let metadata = commitment_and_metadata.metadata.clone();
 
-        let block_header = if version >= V::Epochs::VERSION
-            && self.consensus.read().await.is_qc_forming_eqc(&parent_qc)
-        {
+        let reproposing = version >= V::Epochs::VERSION
+            && self.consensus.read().await.is_qc_forming_eqc(&parent_qc);
+
+        // Between versions replicas only vote for the null block, so anything else would waste
+        // the view
+        if !reproposing {
+            let num_storage_nodes = self
+                .membership
+                .read()
+                .await
+                .total_nodes(vid_share.data.epoch);
+            ensure_null_block_while_upgrading(
+                &self.upgrade_lock,
+                self.view_number,
+                commitment_and_metadata.commitment,
+                num_storage_nodes,
+            )
+            .await?;
+        }
+
+        let block_header = if reproposing {
             tracing::info!("Reached end of epoch. Proposing the same block again to form an eQC.");
             let block_header = parent_leaf.block_header().clone();
             tracing::debug!(
@@ -371,10 +400,25 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             .context(warn!("Failed to construct marketplace block header"))?
         };
 
+        // A re-proposed block keeps the extension it was first proposed with
+        let block_header = match self.instance_state.build_header_extension(
+            parent_leaf.block_header().header_extension(),
+            block_header.block_number(),
+            version,
+        ) {
+            Some(extension) if !reproposing => block_header.with_header_extension(extension),
+            _ => block_header,
+        };
+
         let epoch = TYPES::Epoch::new(epoch_from_block_number(
             block_header.block_number(),
             self.epoch_height,
         ));
+        let block_header =
+            block_header.with_stake_table_commitment(StakeTableCommitment::new::<TYPES>(
+                &*self.membership.read().await,
+                epoch,
+            ));
         // Make sure we are the leader for the view and epoch.
         // We might have ended up here because we were in the epoch transition.
         if self
@@ -416,10 +460,15 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             "Proposed leaf parent does not equal high qc"
         );
 
-        let signature =
-            TYPES::SignatureKey::sign(&self.private_key, proposed_leaf.commit().as_ref())
-                .wrap()
-                .context(error!("Failed to compute proposed_leaf.commit()"))?;
+        let signature = TYPES::SignatureKey::sign(
+            &self.private_key,
+            &self
+                .upgrade_lock
+                .signing_message(proposed_leaf.commit().as_ref(), self.view_number)
+                .await,
+        )
+        .wrap()
+        .context(error!("Failed to compute proposed_leaf.commit()"))?;
 
         let message = Proposal {
             data: proposal,
@@ -499,7 +548,14 @@ impl<TYPES: NodeType, V: Versions> HandleDepOutput for ProposalDependencyHandle<
             return;
         };
         let parent_qc = if let Some(qc) = parent_qc {
-            qc
+            // Build on our high QC instead if it competes with the formed QC and is preferred
+            let consensus_reader = self.consensus.read().await;
+            let high_qc = consensus_reader.high_qc();
+            if consensus_reader.fork_choice().prefers(high_qc, &qc) {
+                high_qc.clone()
+            } else {
+                qc
+            }
         } else if version < V::Epochs::VERSION {
             self.consensus.read().await.high_qc().clone()
         } else {
@@ -17,6 +17,7 @@ use hotshot_task::{
 };
 use hotshot_types::{
     consensus::OuterConsensus,
+    governance::GovernedParameters,
     message::UpgradeLock,
     simple_certificate::{QuorumCertificate2, UpgradeCertificate},
     traits::{
@@ -88,6 +89,9 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
 
     /// The highest_qc we've seen at the start of this task
     pub highest_qc: QuorumCertificate2<TYPES>,
+
+    /// Runtime parameters of each view, which decide how long to wait for a proposal
+    pub governed_parameters: Arc<GovernedParameters<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
@@ -328,7 +332,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 private_key: self.private_key.clone(),
                 instance_state: Arc::clone(&self.instance_state),
                 consensus: OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
-                timeout: self.timeout,
+                timeout: self.governed_parameters.at(view_number).view_timeout,
                 formed_upgrade_certificate: self.formed_upgrade_certificate.clone(),
                 upgrade_lock: self.upgrade_lock.clone(),
                 id: self.id,
@@ -541,11 +545,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 if epoch > &self.cur_epoch {
                     self.cur_epoch = *epoch;
                 }
-                let keep_view = TYPES::View::new(view.saturating_sub(1));
+                let keep_view = view.checked_sub(1).unwrap_or_else(TYPES::View::genesis);
                 self.cancel_tasks(keep_view);
             }
             HotShotEvent::Timeout(view, ..) => {
-                let keep_view = TYPES::View::new(view.saturating_sub(1));
+                let keep_view = view.checked_sub(1).unwrap_or_else(TYPES::View::genesis);
                 self.cancel_tasks(keep_view);
             }
             HotShotEvent::HighQcSend(qc, ..) => {
@@ -553,9 +557,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 let cert_epoch_number = qc.data.epoch;
 
                 let membership_reader = self.membership.read().await;
-                let membership_stake_table = membership_reader.stake_table(cert_epoch_number);
-                let membership_success_threshold =
-                    membership_reader.success_threshold(cert_epoch_number);
+                let membership_stake_table =
+                    membership_reader.sampled_stake_table(qc.view_number(), cert_epoch_number);
+                let membership_success_threshold = membership_reader
+                    .sampled_success_threshold(qc.view_number(), cert_epoch_number);
                 drop(membership_reader);
 
                 ensure!(
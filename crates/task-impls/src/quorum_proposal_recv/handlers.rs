@@ -12,10 +12,14 @@ use async_broadcast::{broadcast, Receiver, Sender};
 use async_lock::{RwLock, RwLockUpgradableReadGuard};
 use committable::Committable;
 use hotshot_types::{
+    chain_config::ChainConfig,
     consensus::OuterConsensus,
     data::{Leaf2, QuorumProposal, QuorumProposal2},
+    error::ConsensusError,
+    event::{Event, EventType},
     message::Proposal,
     simple_certificate::QuorumCertificate,
+    stake_table::StakeTableCommitment,
     traits::{
         block_contents::BlockHeader,
         election::Membership,
@@ -36,8 +40,8 @@ use super::{QuorumProposalRecvTaskState, ValidationInfo};
 use crate::{
     events::HotShotEvent,
     helpers::{
-        broadcast_event, fetch_proposal, validate_proposal_safety_and_liveness,
-        validate_proposal_view_and_certs,
+        broadcast_event, fetch_proposal, saved_parent_leaf_and_state,
+        validate_proposal_safety_and_liveness, validate_proposal_view_and_certs,
     },
     quorum_proposal_recv::{UpgradeLock, Versions},
 };
@@ -132,6 +136,10 @@ fn spawn_fetch_proposal<TYPES: NodeType, V: Versions>(
 /// - The justify qc is invalid.
 /// - The task is internally inconsistent.
 /// - The sequencer storage update fails.
+/// - The parent of the proposal is not known yet, in which case it is fetched and the view
+///   still changes.
+///
+/// Failures are classified as a [`ConsensusError`] so the caller can report them.
 #[allow(clippy::too_many_lines)]
 #[instrument(skip_all)]
 pub(crate) async fn handle_quorum_proposal_recv<
@@ -144,15 +152,37 @@ pub(crate) async fn handle_quorum_proposal_recv<
     event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
     event_receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
     validation_info: ValidationInfo<TYPES, I, V>,
-) -> Result<()> {
+) -> std::result::Result<(), ConsensusError> {
     let quorum_proposal_sender_key = quorum_proposal_sender_key.clone();
 
-    validate_proposal_view_and_certs(proposal, &validation_info)
-        .await
-        .context(warn!("Failed to validate proposal view or attached certs"))?;
+    validate_proposal_view_and_certs(proposal, &validation_info).await?;
 
     let view_number = proposal.data.view_number();
 
+    // A leader with a different chain configuration builds blocks we would not agree with, so
+    // report the mismatch instead of failing to reach quorum without an obvious cause
+    if let Some(proposed) = proposal.data.block_header.chain_config_commitment() {
+        let expected = ChainConfig::new::<TYPES>(&validation_info.instance_state).commit();
+        if proposed != expected {
+            broadcast_event(
+                Event {
+                    view_number,
+                    event: EventType::ConfigMismatch {
+                        leader: quorum_proposal_sender_key.clone(),
+                        expected,
+                        proposed,
+                    },
+                },
+                &validation_info.output_event_stream,
+            )
+            .await;
+            return Err(ConsensusError::ProposalInvalid(format!(
+                "Proposal for view {} was built with chain config {}, but ours is {}",
+                *view_number, proposed, expected
+            )));
+        }
+    }
+
     let justify_qc = proposal.data.justify_qc.clone();
     let maybe_next_epoch_justify_qc = proposal.data.next_epoch_justify_qc.clone();
 
@@ -163,8 +193,23 @@ pub(crate) async fn handle_quorum_proposal_recv<
     ));
 
     let membership_reader = validation_info.membership.read().await;
-    let membership_stake_table = membership_reader.stake_table(justify_qc.data.epoch);
-    let membership_success_threshold = membership_reader.success_threshold(justify_qc.data.epoch);
+
+    // Light clients follow validator set changes through these commitments, so they must match
+    // the stake tables we know of
+    if let Some(proposed) = proposal.data.block_header.stake_table_commitment() {
+        let expected = StakeTableCommitment::new::<TYPES>(&membership_reader, proposal_epoch);
+        if proposed != expected {
+            return Err(ConsensusError::ProposalInvalid(format!(
+                "Proposal for view {} commits to stake tables {:?}, but ours are {:?}",
+                *view_number, proposed, expected
+            )));
+        }
+    }
+
+    let membership_stake_table =
+        membership_reader.sampled_stake_table(justify_qc.view_number(), justify_qc.data.epoch);
+    let membership_success_threshold = membership_reader
+        .sampled_success_threshold(justify_qc.view_number(), justify_qc.data.epoch);
     drop(membership_reader);
 
     if !justify_qc
@@ -177,7 +222,10 @@ pub(crate) async fn handle_quorum_proposal_recv<
     {
         let consensus_reader = validation_info.consensus.read().await;
         consensus_reader.metrics.invalid_qc.update(1);
-        bail!("Invalid justify_qc in proposal for view {}", *view_number);
+        return Err(ConsensusError::CertificateInvalid(format!(
+            "Invalid justify_qc in proposal for view {}",
+            *view_number
+        )));
     }
 
     if let Some(ref next_epoch_justify_qc) = maybe_next_epoch_justify_qc {
@@ -186,13 +234,20 @@ pub(crate) async fn handle_quorum_proposal_recv<
             || justify_qc.data.epoch != next_epoch_justify_qc.data.epoch
             || justify_qc.data.leaf_commit != next_epoch_justify_qc.data.leaf_commit
         {
-            bail!("Next epoch justify qc exists but it's not equal with justify qc.");
+            return Err(ConsensusError::CertificateInvalid(String::from(
+                "Next epoch justify qc exists but it's not equal with justify qc.",
+            )));
         }
 
         let membership_reader = validation_info.membership.read().await;
-        let membership_next_stake_table = membership_reader.stake_table(justify_qc.data.epoch + 1);
-        let membership_next_success_threshold =
-            membership_reader.success_threshold(justify_qc.data.epoch + 1);
+        let membership_next_stake_table = membership_reader.sampled_stake_table(
+            next_epoch_justify_qc.view_number(),
+            justify_qc.data.epoch + 1,
+        );
+        let membership_next_success_threshold = membership_reader.sampled_success_threshold(
+            next_epoch_justify_qc.view_number(),
+            justify_qc.data.epoch + 1,
+        );
         drop(membership_reader);
 
         // Validate the next epoch justify qc as well
@@ -204,10 +259,26 @@ pub(crate) async fn handle_quorum_proposal_recv<
             )
             .await
         {
-            bail!(
+            return Err(ConsensusError::CertificateInvalid(format!(
                 "Invalid next_epoch_justify_qc in proposal for view {}",
                 *view_number
-            );
+            )));
+        }
+    }
+
+    // If we know a competing QC for the same view, the leader must have built on the one the fork
+    // choice rule prefers
+    {
+        let consensus_reader = validation_info.consensus.read().await;
+        let high_qc = consensus_reader.high_qc();
+        if high_qc.view_number() == justify_qc.view_number()
+            && high_qc.data.leaf_commit != justify_qc.data.leaf_commit
+            && consensus_reader.fork_choice().prefers(high_qc, &justify_qc)
+        {
+            return Err(ConsensusError::ProposalInvalid(format!(
+                "Proposal for view {} builds on a QC the fork choice rule does not prefer",
+                *view_number
+            )));
         }
     }
 
@@ -220,43 +291,30 @@ pub(crate) async fn handle_quorum_proposal_recv<
     .await;
 
     // Get the parent leaf and state.
-    let parent_leaf = validation_info
-        .consensus
-        .read()
-        .await
-        .saved_leaves()
-        .get(&justify_qc.data.leaf_commit)
-        .cloned();
-
-    if parent_leaf.is_none() {
-        spawn_fetch_proposal(
-            justify_qc.view_number(),
-            event_sender.clone(),
-            event_receiver.clone(),
-            Arc::clone(&validation_info.membership),
-            OuterConsensus::new(Arc::clone(&validation_info.consensus.inner_consensus)),
-            // Note that we explicitly use the node key here instead of the provided key in the signature.
-            // This is because the key that we receive is for the prior leader, so the payload would be routed
-            // incorrectly.
-            validation_info.public_key.clone(),
-            validation_info.private_key.clone(),
-            validation_info.upgrade_lock.clone(),
-            validation_info.epoch_height,
-        );
+    let parent = saved_parent_leaf_and_state(&*validation_info.consensus.read().await, &justify_qc);
+
+    match parent {
+        Ok(_) => {}
+        Err(ConsensusError::ParentMissing(_)) => {
+            spawn_fetch_proposal(
+                justify_qc.view_number(),
+                event_sender.clone(),
+                event_receiver.clone(),
+                Arc::clone(&validation_info.membership),
+                OuterConsensus::new(Arc::clone(&validation_info.consensus.inner_consensus)),
+                // Note that we explicitly use the node key here instead of the provided key in the signature.
+                // This is because the key that we receive is for the prior leader, so the payload would be routed
+                // incorrectly.
+                validation_info.public_key.clone(),
+                validation_info.private_key.clone(),
+                validation_info.upgrade_lock.clone(),
+                validation_info.epoch_height,
+            );
+        }
+        Err(e) => return Err(e),
     }
     let consensus_reader = validation_info.consensus.read().await;
 
-    let parent = match parent_leaf {
-        Some(leaf) => {
-            if let (Some(state), _) = consensus_reader.state_and_delta(leaf.view_number()) {
-                Some((leaf, Arc::clone(&state)))
-            } else {
-                bail!("Parent state not found! Consensus internally inconsistent");
-            }
-        }
-        None => None,
-    };
-
     if justify_qc.view_number() > consensus_reader.high_qc().view_number {
         if let Err(e) = validation_info
             .storage
@@ -265,7 +323,9 @@ pub(crate) async fn handle_quorum_proposal_recv<
             .update_high_qc2(justify_qc.clone())
             .await
         {
-            bail!("Failed to store High QC, not voting; error = {:?}", e);
+            return Err(ConsensusError::StorageFailure(format!(
+                "Failed to store High QC, not voting; error = {e:?}"
+            )));
         }
         if let Some(ref next_epoch_justify_qc) = maybe_next_epoch_justify_qc {
             if let Err(e) = validation_info
@@ -275,15 +335,29 @@ pub(crate) async fn handle_quorum_proposal_recv<
                 .update_next_epoch_high_qc2(next_epoch_justify_qc.clone())
                 .await
             {
-                bail!(
-                    "Failed to store next epoch High QC, not voting; error = {:?}",
-                    e
-                );
+                return Err(ConsensusError::StorageFailure(format!(
+                    "Failed to store next epoch High QC, not voting; error = {e:?}"
+                )));
             }
         }
     }
     drop(consensus_reader);
 
+    // Archive the evidence for why the view advanced, it was validated along with the proposal
+    if let Some(ref evidence) = proposal.data.view_change_evidence {
+        if let Err(e) = validation_info
+            .storage
+            .write()
+            .await
+            .append_view_change_evidence(view_number, evidence)
+            .await
+        {
+            return Err(ConsensusError::StorageFailure(format!(
+                "Failed to store view change evidence, not voting; error = {e:?}"
+            )));
+        }
+    }
+
     let mut consensus_writer = validation_info.consensus.write().await;
     if let Err(e) = consensus_writer.update_high_qc(justify_qc.clone()) {
         tracing::trace!("{e:?}");
@@ -295,23 +369,25 @@ pub(crate) async fn handle_quorum_proposal_recv<
     }
     drop(consensus_writer);
 
-    let Some((parent_leaf, _parent_state)) = parent else {
-        tracing::warn!(
-            "Proposal's parent missing from storage with commitment: {:?}",
-            justify_qc.data.leaf_commit
-        );
-        validate_proposal_liveness(proposal, &validation_info).await?;
-        tracing::trace!(
-            "Sending ViewChange for view {} and epoch {}",
-            view_number,
-            *proposal_epoch
-        );
-        broadcast_event(
-            Arc::new(HotShotEvent::ViewChange(view_number, proposal_epoch)),
-            event_sender,
-        )
-        .await;
-        return Ok(());
+    let (parent_leaf, _parent_state) = match parent {
+        Ok(parent) => parent,
+        Err(parent_missing) => {
+            validate_proposal_liveness(proposal, &validation_info)
+                .await
+                .map_err(|e| ConsensusError::ProposalInvalid(e.to_string()))?;
+            tracing::trace!(
+                "Sending ViewChange for view {} and epoch {}",
+                view_number,
+                *proposal_epoch
+            );
+            broadcast_event(
+                Arc::new(HotShotEvent::ViewChange(view_number, proposal_epoch)),
+                event_sender,
+            )
+            .await;
+            // The view still changes, but we can't vote until the parent is fetched
+            return Err(parent_missing);
+        }
     };
 
     // Validate the proposal
@@ -322,7 +398,8 @@ pub(crate) async fn handle_quorum_proposal_recv<
         event_sender.clone(),
         quorum_proposal_sender_key,
     )
-    .await?;
+    .await
+    .map_err(|e| ConsensusError::ProposalInvalid(e.to_string()))?;
 
     tracing::trace!(
         "Sending ViewChange for view {} and epoch {}",
@@ -34,7 +34,7 @@ use vbs::version::Version;
 use self::handlers::handle_quorum_proposal_recv;
 use crate::{
     events::{HotShotEvent, ProposalMissing},
-    helpers::{broadcast_event, fetch_proposal, parent_leaf_and_state},
+    helpers::{broadcast_event, fetch_proposal, parent_leaf_and_state, report_consensus_error},
 };
 /// Event handlers for this task.
 mod handlers;
@@ -81,6 +81,9 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Instance state, which determines our chain configuration
+    pub instance_state: Arc<TYPES::InstanceState>,
 }
 
 /// all the info we need to validate a proposal.  This makes it easy to spawn an effemeral task to
@@ -112,6 +115,9 @@ pub(crate) struct ValidationInfo<TYPES: NodeType, I: NodeImplementation<TYPES>,
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Instance state, which determines our chain configuration
+    pub instance_state: Arc<TYPES::InstanceState>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
@@ -155,6 +161,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                     storage: Arc::clone(&self.storage),
                     upgrade_lock: self.upgrade_lock.clone(),
                     epoch_height: self.epoch_height,
+                    instance_state: Arc::clone(&self.instance_state),
                 };
                 match handle_quorum_proposal_recv(
                     proposal,
@@ -166,7 +173,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 .await
                 {
                     Ok(()) => {}
-                    Err(e) => debug!(?e, "Failed to validate the proposal"),
+                    Err(e) => {
+                        report_consensus_error(
+                            &e,
+                            proposal.data.view_number(),
+                            &self.consensus,
+                            &self.output_event_stream,
+                        )
+                        .await;
+                    }
                 }
             }
             HotShotEvent::ViewChange(view, epoch) => {
@@ -181,7 +196,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 // view we want to KEEP tasks for.  We keep the view prior to this because
                 // we might still be processing the proposal from view V which caused us
                 // to enter view V + 1.
-                let oldest_view_to_keep = TYPES::View::new(view.saturating_sub(1));
+                let oldest_view_to_keep = view.checked_sub(1).unwrap_or_else(TYPES::View::genesis);
                 self.cancel_tasks(oldest_view_to_keep);
             }
             _ => {}
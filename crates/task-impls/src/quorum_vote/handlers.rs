@@ -4,25 +4,31 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use async_broadcast::{InactiveReceiver, Sender};
 use async_lock::RwLock;
+use bitvec::vec::BitVec;
 use chrono::Utc;
 use committable::Committable;
 use hotshot_types::{
     consensus::OuterConsensus,
     data::{Leaf2, QuorumProposal2, VidDisperseShare2},
+    drb::{sampling_beacon, SAMPLING_BEACON_EPOCH_LAG},
+    error::ConsensusError,
     event::{Event, EventType, LeafInfo},
+    governance::MIN_ACTIVATION_DELAY,
+    header_relay::RelayHeader,
     message::{Proposal, UpgradeLock},
-    simple_vote::{QuorumData2, QuorumVote2},
+    simple_certificate::{GovernanceCertificate, QuorumCertificate2},
+    simple_vote::{GovernanceData, QuorumData2, QuorumVote2},
+    stake_table::StakeTableCommitment,
     traits::{
-        block_contents::BlockHeader,
+        block_contents::{BlockHeader, BlockPayload},
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::{SignatureKey, StakeTableEntryType},
         storage::Storage,
-        ValidatedState,
     },
     utils::{epoch_from_block_number, is_last_block_in_epoch},
     vote::HasViewNumber,
@@ -35,10 +41,12 @@ use super::QuorumVoteTaskState;
 use crate::{
     events::HotShotEvent,
     helpers::{
-        broadcast_event, decide_from_proposal, decide_from_proposal_2, fetch_proposal,
-        LeafChainTraversalOutcome,
+        broadcast_event, certifying_qcs, decide_from_proposal, decide_from_proposal_2,
+        fetch_proposal, prune_view_change_evidence, schedule_parameter_change,
+        validate_block_against_parent, LeafChainTraversalOutcome,
     },
     quorum_vote::Versions,
+    work_scheduler::{WorkClass, WorkScheduler},
 };
 
 /// Handles starting the DRB calculation. Uses the seed previously stored in
@@ -135,6 +143,181 @@ async fn handle_quorum_proposal_validated_drb_calculation_seed<
     Ok(())
 }
 
+/// Builds a `BlockRewardInfo` event for every leaf in a newly decided chain.
+///
+/// This reads the DA certificates for the decided views out of the consensus state, so it must be
+/// called before garbage collection for the new decide.
+async fn block_reward_infos<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    leaf_views: &[LeafInfo<TYPES>],
+    decide_qc: Option<&QuorumCertificate2<TYPES>>,
+    task_state: &QuorumVoteTaskState<TYPES, I, V>,
+) -> Vec<Event<TYPES>> {
+    let consensus_reader = task_state.consensus.read().await;
+    let membership_reader = task_state.membership.read().await;
+
+    let mut events = Vec::with_capacity(leaf_views.len());
+    for (info, certifying_qc) in certifying_qcs(leaf_views, decide_qc) {
+        let leaf = &info.leaf;
+        let view_number = leaf.view_number();
+
+        let vote_participation = certifying_qc
+            .as_ref()
+            .and_then(|qc| qc.signatures.as_ref())
+            .map(|signatures| TYPES::SignatureKey::sig_proof(signatures).1)
+            .unwrap_or_default();
+
+        let leader = match membership_reader.leader(view_number, leaf.epoch()) {
+            Ok(leader) => leader,
+            Err(e) => {
+                tracing::warn!(
+                    "Not emitting reward info for decided block {}: could not determine the \
+                     leader of view {:?}: {e}",
+                    leaf.height(),
+                    view_number
+                );
+                continue;
+            }
+        };
+
+        let da_signers = consensus_reader
+            .saved_da_certs()
+            .with(&view_number, |cert| {
+                let (_, signers) = TYPES::SignatureKey::sig_proof(cert.signatures.as_ref()?);
+                let da_stake_table =
+                    membership_reader.da_stake_table_at(cert.view_number, cert.data.epoch);
+                Some(
+                    signers
+                        .iter_ones()
+                        .filter_map(|index| da_stake_table.get(index))
+                        .map(StakeTableEntryType::public_key)
+                        .collect(),
+                )
+            })
+            .flatten()
+            .unwrap_or_default();
+
+        events.push(Event {
+            view_number,
+            event: EventType::BlockRewardInfo {
+                block_height: leaf.height(),
+                leader,
+                builder_fee: leaf.block_header().builder_fee_amount(),
+                da_signers,
+                vote_participation,
+            },
+        });
+    }
+
+    events
+}
+
+/// Builds a `HeaderRelay` event for every leaf in a newly decided chain, oldest first.
+async fn header_relays<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    leaf_views: &[LeafInfo<TYPES>],
+    decide_qc: Option<&QuorumCertificate2<TYPES>>,
+    task_state: &QuorumVoteTaskState<TYPES, I, V>,
+) -> Vec<Event<TYPES>> {
+    let membership_reader = task_state.membership.read().await;
+
+    let mut events = Vec::with_capacity(leaf_views.len());
+    for (info, qc) in certifying_qcs(leaf_views, decide_qc) {
+        let leaf = &info.leaf;
+        let Some(qc) = qc else {
+            continue;
+        };
+
+        // Headers which don't carry the commitment get the one of the stake tables we have
+        let epoch = TYPES::Epoch::new(epoch_from_block_number(
+            leaf.height(),
+            task_state.epoch_height,
+        ));
+        let stake_table_commitment = match leaf.block_header().stake_table_commitment() {
+            Some(commitment) => commitment,
+            None => StakeTableCommitment::new::<TYPES>(&membership_reader, epoch),
+        };
+        let header = RelayHeader {
+            header: leaf.block_header().clone(),
+            qc,
+            stake_table_commitment,
+        };
+        let encoded = match header.encode() {
+            Ok(encoded) => encoded,
+            Err(e) => {
+                tracing::warn!("Not relaying the header at height {}: {e}", leaf.height());
+                continue;
+            }
+        };
+
+        events.push(Event {
+            view_number: leaf.view_number(),
+            event: EventType::HeaderRelay { header, encoded },
+        });
+    }
+    events.reverse();
+
+    events
+}
+
+/// Participation observations derived from a newly decided leaf chain.
+struct ParticipationUpdate<TYPES: NodeType> {
+    /// Quorum stake table and signer bitmap of each certificate over a decided leaf
+    certificates: Vec<(Vec<TYPES::SignatureKey>, BitVec)>,
+    /// Leader of each view since the previous decide, and whether its proposal was decided
+    leader_views: Vec<(TYPES::SignatureKey, bool)>,
+}
+
+/// Collects the participation observations for a newly decided leaf chain.
+async fn participation_update<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    leaf_views: &[LeafInfo<TYPES>],
+    decide_qc: Option<&QuorumCertificate2<TYPES>>,
+    task_state: &QuorumVoteTaskState<TYPES, I, V>,
+) -> ParticipationUpdate<TYPES> {
+    let old_decided_view = task_state.consensus.read().await.last_decided_view();
+    let membership_reader = task_state.membership.read().await;
+
+    let mut update = ParticipationUpdate {
+        certificates: Vec::new(),
+        leader_views: Vec::new(),
+    };
+
+    for (info, certifying_qc) in certifying_qcs(leaf_views, decide_qc) {
+        if let Some((qc, signatures)) = certifying_qc
+            .as_ref()
+            .and_then(|qc| qc.signatures.as_ref().map(|signatures| (qc, signatures)))
+        {
+            // The signer bitmap is over the committee sampled for the QC's view
+            let stake_table = membership_reader
+                .sampled_stake_table(qc.view_number(), info.leaf.epoch())
+                .iter()
+                .map(StakeTableEntryType::public_key)
+                .collect();
+            update
+                .certificates
+                .push((stake_table, TYPES::SignatureKey::sig_proof(signatures).1));
+        }
+    }
+
+    let Some(newest) = leaf_views.first() else {
+        return update;
+    };
+    let decided_views: HashSet<_> = leaf_views
+        .iter()
+        .map(|info| info.leaf.view_number())
+        .collect();
+    let epoch = newest.leaf.epoch();
+    let mut view = old_decided_view + 1;
+    while view <= newest.leaf.view_number() {
+        if let Ok(leader) = membership_reader.leader(view, epoch) {
+            update
+                .leader_views
+                .push((leader, decided_views.contains(&view)));
+        }
+        view = view + 1;
+    }
+
+    update
+}
+
 /// Handles the `QuorumProposalValidated` event.
 #[instrument(skip_all, fields(id = task_state.id, view = *proposal.view_number))]
 pub(crate) async fn handle_quorum_proposal_validated<
@@ -144,6 +327,7 @@ pub(crate) async fn handle_quorum_proposal_validated<
 >(
     proposal: &QuorumProposal2<TYPES>,
     task_state: &mut QuorumVoteTaskState<TYPES, I, V>,
+    event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
 ) -> Result<()> {
     let version = task_state
         .upgrade_lock
@@ -196,6 +380,16 @@ pub(crate) async fn handle_quorum_proposal_validated<
             .await;
     }
 
+    let (reward_infos, header_relays, participation) = if new_decided_view_number.is_some() {
+        (
+            block_reward_infos(&leaf_views, new_decide_qc.as_ref(), task_state).await,
+            header_relays(&leaf_views, new_decide_qc.as_ref(), task_state).await,
+            Some(participation_update(&leaf_views, new_decide_qc.as_ref(), task_state).await),
+        )
+    } else {
+        (Vec::new(), Vec::new(), None)
+    };
+
     let mut consensus_writer = task_state.consensus.write().await;
     if let Some(locked_view_number) = new_locked_view_number {
         consensus_writer.update_locked_view(locked_view_number)?;
@@ -211,6 +405,21 @@ pub(crate) async fn handle_quorum_proposal_validated<
         // Set the new decided view.
         consensus_writer.update_last_decided_view(decided_view_number)?;
 
+        if let Some(participation) = participation {
+            for (stake_table, signers) in &participation.certificates {
+                consensus_writer.record_certificate_participation(stake_table, signers);
+            }
+            for (leader, decided) in &participation.leader_views {
+                consensus_writer.record_leader_participation(leader, *decided);
+            }
+        }
+        for info in &leaf_views {
+            let block_size = info.leaf.block_payload().and_then(|payload| {
+                u64::try_from(payload.num_transactions(info.leaf.block_header().metadata())).ok()
+            });
+            consensus_writer.record_decided_view(info.leaf.view_number(), block_size);
+        }
+
         consensus_writer
             .metrics
             .last_decided_time
@@ -235,6 +444,31 @@ pub(crate) async fn handle_quorum_proposal_validated<
         // We don't need to hold this while we broadcast
         drop(consensus_writer);
 
+        // Persist the decided leaves before reporting them, so that a stream of decided leaves
+        // finds every leaf decided before it started in storage
+        let decided_leaves: Vec<_> = leaf_views.iter().map(|info| info.leaf.clone()).collect();
+        if let Err(e) = task_state
+            .storage
+            .write()
+            .await
+            .append_decided_leaves(&decided_leaves)
+            .await
+        {
+            tracing::warn!("Failed to store decided leaves: {e:#}");
+        }
+
+        // Clean out any evidence archived for the decided views which could not have justified
+        // their proposals
+        if let Err(e) = prune_view_change_evidence(
+            &*task_state.storage.read().await,
+            old_decided_view + 1,
+            decided_view_number,
+        )
+        .await
+        {
+            tracing::warn!("Failed to prune view change evidence: {e:#}");
+        }
+
         // Send an update to everyone saying that we've reached a decide
         broadcast_event(
             Event {
@@ -251,6 +485,38 @@ pub(crate) async fn handle_quorum_proposal_validated<
         .await;
         tracing::debug!("Successfully sent decide event");
 
+        // Schedule the parameter changes of the decided leaves, oldest first, so that a later
+        // change to the same parameter wins
+        for info in leaf_views.iter().rev() {
+            if let Some(cert) = info.leaf.block_header().governance_certificate() {
+                if let Err(e) =
+                    schedule_decided_parameter_change(&info.leaf, &cert, task_state).await
+                {
+                    tracing::warn!("Ignoring decided governance certificate: {e}");
+                }
+            }
+        }
+
+        // Draw the samples of later epochs with the randomness decided in this one
+        if task_state.epoch_height != 0 {
+            for info in leaf_views.iter().rev() {
+                set_decided_sampling_beacon(&info.leaf, task_state).await;
+            }
+        }
+
+        for reward_info in reward_infos {
+            broadcast_event(reward_info, &task_state.output_event_stream).await;
+        }
+        for header_relay in header_relays {
+            broadcast_event(header_relay, &task_state.output_event_stream).await;
+        }
+
+        broadcast_event(
+            Arc::new(HotShotEvent::LeavesDecided(decided_leaves)),
+            event_sender,
+        )
+        .await;
+
         if version >= V::Epochs::VERSION {
             handle_quorum_proposal_validated_drb_calculation_seed(
                 proposal,
@@ -264,6 +530,167 @@ pub(crate) async fn handle_quorum_proposal_validated<
     Ok(())
 }
 
+/// Schedules the parameter change of a governance certificate carried by a decided leaf, after
+/// checking it against the stake table of the leaf's epoch, persists it so that it is scheduled
+/// again after a restart, and reports it to the application.
+async fn schedule_decided_parameter_change<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    V: Versions,
+>(
+    leaf: &Leaf2<TYPES>,
+    cert: &GovernanceCertificate<TYPES>,
+    task_state: &QuorumVoteTaskState<TYPES, I, V>,
+) -> Result<()> {
+    let GovernanceData {
+        change,
+        activation_view,
+    } = cert.data.clone();
+    ensure!(
+        !change.is_empty(),
+        "Governance certificate in view {} changes no parameters",
+        *leaf.view_number()
+    );
+    ensure!(
+        *activation_view >= leaf.view_number().u64() + MIN_ACTIVATION_DELAY,
+        "Governance certificate in view {} activates in view {}, fewer than {MIN_ACTIVATION_DELAY} views later",
+        *leaf.view_number(),
+        *activation_view
+    );
+
+    let epoch = TYPES::Epoch::new(epoch_from_block_number(
+        leaf.height(),
+        task_state.epoch_height,
+    ));
+    cert.validate(&task_state.membership, epoch, &task_state.upgrade_lock)
+        .await?;
+
+    if let Err(e) = task_state
+        .storage
+        .read()
+        .await
+        .append_parameter_change(activation_view, change)
+        .await
+    {
+        tracing::warn!(
+            "Failed to persist the parameter change for view {}: {e:#}",
+            *activation_view
+        );
+    }
+    schedule_parameter_change(
+        &task_state.governed_parameters,
+        &task_state.membership,
+        activation_view,
+        change,
+    )
+    .await;
+    tracing::info!(
+        "Scheduled parameter change {change:?} for view {}",
+        *activation_view
+    );
+    broadcast_event(
+        Event {
+            view_number: leaf.view_number(),
+            event: EventType::ParameterChangeScheduled {
+                change,
+                activation_view,
+            },
+        },
+        &task_state.output_event_stream,
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Sets the beacon which the quorum samples of the epoch `SAMPLING_BEACON_EPOCH_LAG` after that of
+/// a decided leaf are drawn with, from the leaf's DRB result, and persists it so that it is set
+/// again after a restart.
+async fn set_decided_sampling_beacon<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    leaf: &Leaf2<TYPES>,
+    task_state: &QuorumVoteTaskState<TYPES, I, V>,
+) {
+    let Some(beacon) = sampling_beacon(&leaf.drb_result) else {
+        return;
+    };
+    let epoch = TYPES::Epoch::new(
+        epoch_from_block_number(leaf.height(), task_state.epoch_height) + SAMPLING_BEACON_EPOCH_LAG,
+    );
+    // Every leaf of an epoch carries the same result
+    if task_state.membership.read().await.sampling_beacon(epoch) == Some(beacon) {
+        return;
+    }
+
+    if let Err(e) = task_state
+        .storage
+        .read()
+        .await
+        .append_sampling_beacon(epoch, beacon)
+        .await
+    {
+        tracing::warn!("Failed to persist the sampling beacon for epoch {epoch:?}: {e:#}");
+    }
+    task_state
+        .membership
+        .write()
+        .await
+        .set_sampling_beacon(epoch, beacon);
+    tracing::info!("Set the sampling beacon for epoch {epoch:?}");
+}
+
+/// Records a proposed leaf whose block failed to apply to the validated state of its parent,
+/// counts the failure against its leader, and reports it to the application.
+async fn handle_invalid_state_transition<TYPES: NodeType, I: NodeImplementation<TYPES>>(
+    consensus: &OuterConsensus<TYPES>,
+    membership: &Arc<RwLock<TYPES::Membership>>,
+    storage: &Arc<RwLock<I::Storage>>,
+    output_event_stream: &Sender<Event<TYPES>>,
+    proposed_leaf: &Leaf2<TYPES>,
+    error: &str,
+    epoch_height: u64,
+) {
+    let view_number = proposed_leaf.view_number();
+
+    if let Err(e) = storage
+        .write()
+        .await
+        .record_invalid_state_transition(proposed_leaf, error)
+        .await
+    {
+        tracing::warn!("Failed to record invalid state transition in storage: {e:#}");
+    }
+
+    let epoch = TYPES::Epoch::new(epoch_from_block_number(
+        proposed_leaf.height(),
+        epoch_height,
+    ));
+    let leader = match membership.read().await.leader(view_number, epoch) {
+        Ok(leader) => leader,
+        Err(e) => {
+            tracing::warn!("Failed to find the leader of an invalid state transition: {e}");
+            return;
+        }
+    };
+
+    consensus
+        .write()
+        .await
+        .record_invalid_state_transition(&leader);
+
+    broadcast_event(
+        Event {
+            view_number,
+            event: EventType::InvalidStateTransition {
+                leader,
+                leaf: proposed_leaf.clone(),
+                error: error.to_string(),
+            },
+        },
+        output_event_stream,
+    )
+    .await;
+}
+
 /// Updates the shared consensus state with the new voting data.
 #[instrument(skip_all, target = "VoteDependencyHandle", fields(view = *view_number))]
 #[allow(clippy::too_many_arguments)]
@@ -282,6 +709,8 @@ pub(crate) async fn update_shared_state<
     view_number: TYPES::View,
     instance_state: Arc<TYPES::InstanceState>,
     storage: Arc<RwLock<I::Storage>>,
+    output_event_stream: Sender<Event<TYPES>>,
+    work_scheduler: &WorkScheduler,
     proposed_leaf: &Leaf2<TYPES>,
     vid_share: &Proposal<TYPES, VidDisperseShare2<TYPES>>,
     parent_view_number: Option<TYPES::View>,
@@ -333,37 +762,65 @@ pub(crate) async fn update_shared_state<
         }
     };
 
-    let parent = maybe_parent.context(info!(
-        "Proposal's parent missing from storage with commitment: {:?}, proposal view {:?}",
-        justify_qc.data.leaf_commit,
-        proposed_leaf.view_number(),
-    ))?;
+    let parent = maybe_parent.ok_or_else(|| {
+        ConsensusError::ParentMissing(format!(
+            "Proposal's parent missing from storage with commitment: {:?}, proposal view {:?}",
+            justify_qc.data.leaf_commit,
+            proposed_leaf.view_number(),
+        ))
+    })?;
 
     let Some(validated_view) = maybe_validated_view else {
-        bail!(
-            "Failed to fetch view for parent, parent view {:?}",
-            parent_view_number
-        );
+        return Err(ConsensusError::ParentMissing(format!(
+            "Failed to fetch view for parent, parent view {parent_view_number:?}"
+        ))
+        .into());
     };
 
     let (Some(parent_state), _) = validated_view.state_and_delta() else {
-        bail!("Parent state not found! Consensus internally inconsistent");
+        return Err(ConsensusError::InconsistentState(String::from(
+            "Parent state not found! Consensus internally inconsistent",
+        ))
+        .into());
     };
 
     let version = upgrade_lock.version(view_number).await?;
 
-    let (validated_state, state_delta) = parent_state
-        .validate_and_apply_header(
-            &instance_state,
-            &parent,
-            &proposed_leaf.block_header().clone(),
-            vid_share.data.common.clone(),
-            version,
-            *view_number,
+    let (validated_state, state_delta) = match work_scheduler
+        .run(
+            WorkClass::StateApplication,
+            validate_block_against_parent(
+                &instance_state,
+                &parent,
+                &parent_state,
+                proposed_leaf.block_header(),
+                vid_share.data.common.clone(),
+                version,
+                view_number,
+            ),
         )
         .await
-        .wrap()
-        .context(warn!("Block header doesn't extend the proposal!"))?;
+    {
+        Ok(applied) => applied,
+        Err(ConsensusError::InvalidStateTransition(error)) => {
+            handle_invalid_state_transition::<TYPES, I>(
+                &consensus,
+                &membership,
+                &storage,
+                &output_event_stream,
+                proposed_leaf,
+                &error,
+                epoch_height,
+            )
+            .await;
+            bail!(
+                "Proposal for view {} is an invalid state transition: {}",
+                *view_number,
+                error
+            );
+        }
+        Err(e) => return Err(e.into()),
+    };
 
     let state = Arc::new(validated_state);
     let delta = Arc::new(state_delta);
@@ -378,6 +835,11 @@ pub(crate) async fn update_shared_state<
     ) {
         tracing::trace!("{e:?}");
     }
+    consensus_writer.cache_speculative_state(
+        proposed_leaf,
+        Arc::clone(&state),
+        Some(Arc::clone(&delta)),
+    );
 
     // Kick back our updated structures for downstream usage.
     let new_leaves = consensus_writer.saved_leaves().clone();
@@ -417,11 +879,12 @@ pub(crate) async fn submit_vote<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     ));
 
     let membership_reader = membership.read().await;
-    let committee_member_in_current_epoch = membership_reader.has_stake(&public_key, epoch_number);
+    let committee_member_in_current_epoch =
+        membership_reader.has_sampled_stake(&public_key, view_number, epoch_number);
     // If the proposed leaf is for the last block in the epoch and the node is part of the quorum committee
     // in the next epoch, the node should vote to achieve the double quorum.
     let committee_member_in_next_epoch = is_last_block_in_epoch(leaf.height(), TYPES::EPOCH_HEIGHT)
-        && membership_reader.has_stake(&public_key, epoch_number + 1);
+        && membership_reader.has_sampled_stake(&public_key, view_number, epoch_number + 1);
     drop(membership_reader);
 
     ensure!(
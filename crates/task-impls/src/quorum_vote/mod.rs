@@ -19,7 +19,8 @@ use hotshot_task::{
 use hotshot_types::{
     consensus::{ConsensusMetricsValue, OuterConsensus},
     data::{Leaf2, QuorumProposal2},
-    event::Event,
+    event::{Event, EventType},
+    governance::GovernedParameters,
     message::{Proposal, UpgradeLock},
     traits::{
         block_contents::BlockHeader,
@@ -42,6 +43,7 @@ use crate::{
     events::HotShotEvent,
     helpers::broadcast_event,
     quorum_vote::handlers::{handle_quorum_proposal_validated, submit_vote, update_shared_state},
+    work_scheduler::WorkScheduler,
 };
 
 /// Helper for DRB Computations
@@ -90,6 +92,9 @@ pub struct VoteDependencyHandle<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     /// Event receiver.
     pub receiver: InactiveReceiver<Arc<HotShotEvent<TYPES>>>,
 
+    /// Output events to application
+    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
 
@@ -101,6 +106,9 @@ pub struct VoteDependencyHandle<TYPES: NodeType, I: NodeImplementation<TYPES>, V
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Budget of background work in each view, which applying the proposed block is charged to
+    pub work_scheduler: Arc<WorkScheduler>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> HandleDepOutput
@@ -222,6 +230,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
             self.view_number,
             Arc::clone(&self.instance_state),
             Arc::clone(&self.storage),
+            self.output_event_stream.clone(),
+            &self.work_scheduler,
             &leaf,
             &vid_share,
             parent_view_number,
@@ -317,6 +327,12 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Budget of background work in each view, which applying the proposed block is charged to
+    pub work_scheduler: Arc<WorkScheduler>,
+
+    /// Runtime parameters, which decided governance certificates schedule changes to
+    pub governed_parameters: Arc<GovernedParameters<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskState<TYPES, I, V> {
@@ -418,10 +434,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 view_number,
                 sender: event_sender.clone(),
                 receiver: event_receiver.clone().deactivate(),
+                output_event_stream: self.output_event_stream.clone(),
                 upgrade_lock: self.upgrade_lock.clone(),
                 id: self.id,
                 epoch_height: self.epoch_height,
                 consensus_metrics: Arc::clone(&self.consensus_metrics),
+                work_scheduler: Arc::clone(&self.work_scheduler),
             },
         );
         self.vote_dependencies
@@ -478,7 +496,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 );
 
                 // Handle the event before creating the dependency task.
-                if let Err(e) = handle_quorum_proposal_validated(&proposal.data, self).await {
+                if let Err(e) =
+                    handle_quorum_proposal_validated(&proposal.data, self, &event_sender).await
+                {
                     tracing::debug!(
                         "Failed to handle QuorumProposalValidated event; error = {e:#}"
                     );
@@ -525,9 +545,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 let cert_epoch = cert.data.epoch;
 
                 let membership_reader = self.membership.read().await;
-                let membership_da_stake_table = membership_reader.da_stake_table(cert_epoch);
+                let membership_da_stake_table =
+                    membership_reader.da_stake_table_at(view, cert_epoch);
                 let membership_da_success_threshold =
-                    membership_reader.da_success_threshold(cert_epoch);
+                    membership_reader.da_success_threshold_at(view, cert_epoch);
                 drop(membership_reader);
 
                 // Validate the DAC.
@@ -543,7 +564,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
 
                 // Add to the storage.
                 self.consensus
-                    .write()
+                    .read()
                     .await
                     .update_saved_da_certs(view, cert.clone());
 
@@ -571,8 +592,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 // Validate the VID share.
                 let payload_commitment = &disperse.data.payload_commitment;
                 // Check that the signature is valid
+                let signing_message = self
+                    .upgrade_lock
+                    .signing_message(payload_commitment.as_ref(), view)
+                    .await;
                 ensure!(
-                    sender.validate(&disperse.signature, payload_commitment.as_ref()),
+                    sender.validate(&disperse.signature, &signing_message),
                     "VID share signature is invalid"
                 );
 
@@ -605,7 +630,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 }
 
                 self.consensus
-                    .write()
+                    .read()
                     .await
                     .update_vid_shares(view, disperse.clone());
 
@@ -614,6 +639,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                     "Got a Valid VID share but it's not for our key"
                 );
 
+                broadcast_event(
+                    Event {
+                        view_number: view,
+                        event: EventType::VidShareReceived { view_number: view },
+                    },
+                    &self.output_event_stream,
+                )
+                .await;
+
                 broadcast_event(
                     Arc::new(HotShotEvent::VidShareValidated(disperse.clone())),
                     &event_sender.clone(),
@@ -627,7 +661,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 );
             }
             HotShotEvent::Timeout(view, ..) => {
-                let view = TYPES::View::new(view.saturating_sub(1));
+                let view = view.checked_sub(1).unwrap_or_else(TYPES::View::genesis);
                 // cancel old tasks
                 let current_tasks = self.vote_dependencies.split_off(&view);
                 while let Some((_, task)) = self.vote_dependencies.pop_last() {
@@ -636,7 +670,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 self.vote_dependencies = current_tasks;
             }
             HotShotEvent::ViewChange(mut view, _) => {
-                view = TYPES::View::new(view.saturating_sub(1));
+                view = view.checked_sub(1).unwrap_or_else(TYPES::View::genesis);
                 if !self.update_latest_voted_view(view).await {
                     tracing::debug!("view not updated");
                 }
@@ -675,26 +709,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
             "Reached end of epoch. Proposed leaf has the same height and payload as its parent."
         );
 
-        let mut consensus_writer = self.consensus.write().await;
-        let Some(vid_shares) = consensus_writer
-            .vid_shares()
-            .get(&parent_leaf.view_number())
+        let consensus_reader = self.consensus.read().await;
+        let Some(mut updated_vid) =
+            consensus_reader.vid_share(parent_leaf.view_number(), &self.public_key)
         else {
             tracing::warn!(
                 "Proposed leaf is the same as its parent but we don't have our VID for it"
             );
             return;
         };
-        let Some(vid) = vid_shares.get(&self.public_key) else {
-            tracing::warn!(
-                "Proposed leaf is the same as its parent but we don't have our VID for it"
-            );
-            return;
-        };
-        let mut updated_vid = vid.clone();
         updated_vid.data.view_number = proposal.data.view_number;
-        consensus_writer.update_vid_shares(updated_vid.data.view_number, updated_vid.clone());
-        drop(consensus_writer);
+        consensus_reader.update_vid_shares(updated_vid.data.view_number, updated_vid.clone());
+        drop(consensus_reader);
 
         if proposed_leaf.parent_commitment() != parent_commitment {
             tracing::warn!("Proposed leaf parent commitment does not match parent leaf payload commitment. Aborting vote.");
@@ -719,6 +745,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
             proposal.data.view_number(),
             Arc::clone(&self.instance_state),
             Arc::clone(&self.storage),
+            self.output_event_stream.clone(),
+            &self.work_scheduler,
             &proposed_leaf,
             &updated_vid,
             Some(parent_leaf.view_number()),
@@ -0,0 +1,135 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::HashSet;
+
+use bitvec::vec::BitVec;
+use committable::Committable;
+
+/// Number of bits in each generation of the filter.
+pub const RECENT_TRANSACTIONS_FILTER_BITS: usize = 1 << 23;
+
+/// Number of transactions recorded in a generation of the filter before it is rotated out.
+pub const RECENT_TRANSACTIONS_PER_GENERATION: usize = 200_000;
+
+/// Number of bits set for each transaction.
+const FILTER_HASHES: u64 = 4;
+
+/// A bloom filter over the commitments of recently decided transactions, used to keep
+/// transactions which were already decided out of new proposals.
+///
+/// The filter keeps two generations. Transactions are recorded in the current one, and once it
+/// is full the previous generation is dropped, so a transaction is remembered for at least
+/// `capacity` further insertions. The bloom filter answers most lookups, which are misses, without
+/// touching the commitments themselves; its hits are confirmed against the exact commitments of
+/// the same generations, so that a false positive never drops a transaction or a block.
+pub struct RecentTransactionFilter {
+    /// Generation new transactions are recorded in
+    current: BitVec,
+
+    /// Previous generation, still consulted on lookups
+    previous: BitVec,
+
+    /// Commitments of the transactions recorded in the current generation
+    current_commitments: HashSet<[u8; 32]>,
+
+    /// Commitments of the transactions recorded in the previous generation
+    previous_commitments: HashSet<[u8; 32]>,
+
+    /// Number of transactions recorded in the current generation
+    current_len: usize,
+
+    /// Number of transactions recorded in a generation before it is rotated out
+    capacity: usize,
+}
+
+impl Default for RecentTransactionFilter {
+    fn default() -> Self {
+        Self::new(
+            RECENT_TRANSACTIONS_FILTER_BITS,
+            RECENT_TRANSACTIONS_PER_GENERATION,
+        )
+    }
+}
+
+impl RecentTransactionFilter {
+    /// Create a new filter with `bits` bits per generation, rotating generations every
+    /// `capacity` transactions
+    #[must_use]
+    pub fn new(bits: usize, capacity: usize) -> Self {
+        Self {
+            current: BitVec::repeat(false, bits.max(1)),
+            previous: BitVec::repeat(false, bits.max(1)),
+            current_commitments: HashSet::new(),
+            previous_commitments: HashSet::new(),
+            current_len: 0,
+            capacity,
+        }
+    }
+
+    /// Record a decided transaction
+    pub fn insert<T: Committable>(&mut self, transaction: &T) {
+        if self.current_len >= self.capacity {
+            self.previous = std::mem::replace(
+                &mut self.current,
+                BitVec::repeat(false, self.previous.len()),
+            );
+            self.previous_commitments = std::mem::take(&mut self.current_commitments);
+            self.current_len = 0;
+        }
+
+        for index in Self::indices(transaction, self.current.len()) {
+            self.current.set(index, true);
+        }
+        self.current_commitments
+            .insert(<[u8; 32]>::from(transaction.commit()));
+        self.current_len += 1;
+    }
+
+    /// Whether the transaction was decided recently
+    #[must_use]
+    pub fn contains<T: Committable>(&self, transaction: &T) -> bool {
+        if !self.might_contain(transaction) {
+            return false;
+        }
+
+        let commitment = <[u8; 32]>::from(transaction.commit());
+        self.current_commitments.contains(&commitment)
+            || self.previous_commitments.contains(&commitment)
+    }
+
+    /// Whether the bloom filter holds the transaction: true for every transaction decided
+    /// recently, and possibly for others
+    #[must_use]
+    pub fn might_contain<T: Committable>(&self, transaction: &T) -> bool {
+        let len = self.current.len();
+        let indices = Self::indices(transaction, len).collect::<Vec<_>>();
+
+        indices.iter().all(|index| self.current[*index])
+            || indices.iter().all(|index| self.previous[*index])
+    }
+
+    /// Bits of a filter of length `len` which represent `transaction`.
+    ///
+    /// The commitment is already a cryptographic hash, so we derive the indices from its bytes by
+    /// double hashing instead of hashing it again.
+    fn indices<T: Committable>(transaction: &T, len: usize) -> impl Iterator<Item = usize> {
+        let bytes = <[u8; 32]>::from(transaction.commit());
+        let word = |i: usize| {
+            let mut chunk = [0u8; 8];
+            chunk.copy_from_slice(&bytes[i * 8..(i + 1) * 8]);
+            u64::from_le_bytes(chunk)
+        };
+        let (h1, h2) = (word(0), word(1) | 1);
+        let len = len as u64;
+
+        (0..FILTER_HASHES).map(move |i| {
+            #[allow(clippy::cast_possible_truncation)]
+            let index = (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize;
+            index
+        })
+    }
+}
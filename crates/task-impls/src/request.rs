@@ -22,11 +22,12 @@ use hotshot_task::{
 };
 use hotshot_types::{
     consensus::OuterConsensus,
+    message::UpgradeLock,
     traits::{
         block_contents::BlockHeader,
         election::Membership,
         network::{ConnectedNetwork, DataRequest, RequestKind},
-        node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
         signature_key::SignatureKey,
     },
     utils::epoch_from_block_number,
@@ -47,11 +48,14 @@ use crate::{events::HotShotEvent, helpers::broadcast_event};
 /// Amount of time to try for a request before timing out.
 pub const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
 
+/// Amount of time we wait for the DA certificate of a view after having the proposal and our VID
+/// share before requesting it from peers.
+pub const DA_CERTIFICATE_GRACE_PERIOD: Duration = Duration::from_millis(1000);
+
 /// Long running task which will request information after a proposal is received.
 /// The task will wait a it's `delay` and then send a request iteratively to peers
-/// for any data they don't have related to the proposal.  For now it's just requesting VID
-/// shares.
-pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
+/// for any data they don't have related to the proposal: our VID share and the DA certificate.
+pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// Network to send requests over
     /// The underlying network
     pub network: Arc<I::Network>,
@@ -83,9 +87,14 @@ pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
 
     /// A flag indicating that `HotShotEvent::Shutdown` has been received
     pub spawned_tasks: BTreeMap<TYPES::View, Vec<JoinHandle<()>>>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
 }
 
-impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Drop for NetworkRequestState<TYPES, I> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Drop
+    for NetworkRequestState<TYPES, I, V>
+{
     fn drop(&mut self) {
         self.cancel_subtasks();
     }
@@ -96,7 +105,9 @@ type Signature<TYPES> =
     <<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType;
 
 #[async_trait]
-impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequestState<TYPES, I> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
+    for NetworkRequestState<TYPES, I, V>
+{
     type Event = HotShotEvent<TYPES>;
 
     #[instrument(skip_all, target = "NetworkRequestState", fields(id = self.id))]
@@ -126,6 +137,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
                     self.spawn_requests(prop_view, prop_epoch, sender, receiver)
                         .await;
                 }
+
+                // If the DA certificate doesn't follow within the grace period, ask for it.
+                if prop_view >= self.view
+                    && !self
+                        .consensus
+                        .read()
+                        .await
+                        .saved_da_certs()
+                        .contains_key(&prop_view)
+                {
+                    self.spawn_da_certificate_request(prop_view, prop_epoch, sender, receiver)
+                        .await;
+                }
                 Ok(())
             }
             HotShotEvent::ViewChange(view, _) => {
@@ -154,7 +178,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
     }
 }
 
-impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> NetworkRequestState<TYPES, I, V> {
     /// Creates and signs the payload, then will create a request task
     async fn spawn_requests(
         &mut self,
@@ -179,6 +203,115 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         }
     }
 
+    /// Signs a request for the DA certificate of `view` and creates a task which sends it once
+    /// the grace period has passed
+    async fn spawn_da_certificate_request(
+        &mut self,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+        sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+        receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let request = RequestKind::DaCertificate(view);
+        let Some(signature) = self.serialize_and_sign(&request) else {
+            return;
+        };
+
+        let consensus = OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
+        let shutdown_flag = Arc::clone(&self.shutdown_flag);
+        let public_key = self.public_key.clone();
+        let sender = sender.clone();
+        let receiver = receiver.clone();
+
+        // Both the DA committee and the leader hold the certificate once it is formed
+        let membership_reader = self.membership.read().await;
+        let mut recipients: Vec<TYPES::SignatureKey> = membership_reader
+            .da_committee_members(view, epoch)
+            .into_iter()
+            .collect();
+        let leader = membership_reader.leader(view, epoch).ok();
+        drop(membership_reader);
+        recipients.shuffle(&mut thread_rng());
+        if let Some(leader) = leader {
+            if !recipients.contains(&leader) {
+                recipients.insert(0, leader);
+            }
+        }
+        recipients.retain(|recipient| *recipient != public_key);
+
+        let data_request = DataRequest::<TYPES> {
+            request,
+            view,
+            signature,
+        };
+        let my_id = self.id;
+        let handle: JoinHandle<()> = spawn(async move {
+            sleep(DA_CERTIFICATE_GRACE_PERIOD).await;
+
+            let mut recipients_it = recipients.iter();
+            loop {
+                let (cancel, has_vid_share) = {
+                    let consensus_reader = consensus.read().await;
+                    (
+                        shutdown_flag.load(Ordering::Relaxed)
+                            || consensus_reader.saved_da_certs().contains_key(&view)
+                            || consensus_reader.cur_view() > view,
+                        consensus_reader
+                            .vid_shares()
+                            .with(&view, |shares| shares.contains_key(&public_key))
+                            .unwrap_or(false),
+                    )
+                };
+                if cancel {
+                    return;
+                }
+                // We can't vote without our VID share either, so there is no point in asking for
+                // the certificate until the share has arrived.
+                if !has_vid_share {
+                    sleep(REQUEST_TIMEOUT).await;
+                    continue;
+                }
+
+                let Some(recipient) = recipients_it.next() else {
+                    tracing::warn!(
+                        "Requested the DA certificate from all DA members and the leader and got no response for view: {:?}, my id: {:?}",
+                        view,
+                        my_id,
+                    );
+                    return;
+                };
+                broadcast_event(
+                    HotShotEvent::DaCertificateRequestSend(
+                        data_request.clone(),
+                        public_key.clone(),
+                        recipient.clone(),
+                    )
+                    .into(),
+                    &sender,
+                )
+                .await;
+
+                // The certificate is validated and saved by the vote task, so we only wait for it to
+                // arrive here and check whether it was valid at the start of the loop.
+                let _ = timeout(
+                    REQUEST_TIMEOUT,
+                    EventDependency::new(
+                        receiver.clone(),
+                        Box::new(move |event: &Arc<HotShotEvent<TYPES>>| {
+                            matches!(
+                                event.as_ref(),
+                                HotShotEvent::DaCertificateValidated(cert) if cert.view_number == view
+                            )
+                        }),
+                    )
+                    .completed(),
+                )
+                .await;
+            }
+        });
+        self.spawned_tasks.entry(view).or_default().push(handle);
+    }
+
     /// Creates a task that will request a VID share from a DA member and wait for the `HotShotEvent::VidResponseRecv`event
     /// If we get the VID disperse share, broadcast `HotShotEvent::VidShareRecv` and terminate task
     async fn create_vid_request_task(
@@ -195,6 +328,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         let shutdown_flag = Arc::clone(&self.shutdown_flag);
         let delay = self.delay;
         let public_key = self.public_key.clone();
+        let signing_prefix = self.upgrade_lock.signing_prefix(view).await;
 
         // Get the committee members for the view and the leader, if applicable
         let membership_reader = self.membership.read().await;
@@ -254,6 +388,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
                         &da_committee_for_view,
                         &public_key,
                         view,
+                        &signing_prefix,
                     )
                     .await
                     {
@@ -284,6 +419,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         da_committee_for_view: &BTreeSet<<TYPES as NodeType>::SignatureKey>,
         public_key: &<TYPES as NodeType>::SignatureKey,
         view: TYPES::View,
+        signing_prefix: &[u8],
     ) -> bool {
         // First send request to a random DA member for the view
         broadcast_event(
@@ -300,7 +436,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         // Wait for a response
         let result = timeout(
             REQUEST_TIMEOUT,
-            Self::handle_event_dependency(receiver, da_committee_for_view.clone(), view),
+            Self::handle_event_dependency(
+                receiver,
+                da_committee_for_view.clone(),
+                public_key.clone(),
+                view,
+                signing_prefix.to_vec(),
+            ),
         )
         .await;
 
@@ -326,18 +468,26 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
     async fn handle_event_dependency(
         receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
         da_members_for_view: BTreeSet<<TYPES as NodeType>::SignatureKey>,
+        public_key: <TYPES as NodeType>::SignatureKey,
         view: TYPES::View,
+        signing_prefix: Vec<u8>,
     ) -> Option<Arc<HotShotEvent<TYPES>>> {
         EventDependency::new(
             receiver.clone(),
             Box::new(move |event: &Arc<HotShotEvent<TYPES>>| {
                 let event = event.as_ref();
                 if let HotShotEvent::VidResponseRecv(sender_key, proposal) = event {
+                    // Responses to availability samples carry other nodes' shares
                     proposal.data.view_number() == view
+                        && proposal.data.recipient_key == public_key
                         && da_members_for_view.contains(sender_key)
                         && sender_key.validate(
                             &proposal.signature,
-                            proposal.data.payload_commitment.as_ref(),
+                            &[
+                                signing_prefix.as_slice(),
+                                proposal.data.payload_commitment.as_ref(),
+                            ]
+                            .concat(),
                         )
                 } else {
                     false
@@ -362,11 +512,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
             || consensus_reader.vid_shares().contains_key(view)
             || consensus_reader.cur_view() > *view;
         if cancel {
-            if let Some(Some(vid_share)) = consensus_reader
-                .vid_shares()
-                .get(view)
-                .map(|shares| shares.get(public_key).cloned())
-            {
+            if let Some(vid_share) = consensus_reader.vid_share(*view, public_key) {
                 broadcast_event(
                     Arc::new(HotShotEvent::VidShareRecv(
                         public_key.clone(),
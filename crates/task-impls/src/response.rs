@@ -12,9 +12,11 @@ use committable::Committable;
 use hotshot_types::{
     consensus::{Consensus, LockedConsensusState, OuterConsensus},
     data::VidDisperseShare2,
-    message::Proposal,
+    message::{Proposal, UpgradeLock},
     traits::{
-        election::Membership, network::DataRequest, node_implementation::NodeType,
+        election::Membership,
+        network::{DataRequest, RequestKind},
+        node_implementation::{NodeType, Versions},
         signature_key::SignatureKey,
     },
 };
@@ -29,7 +31,7 @@ const TXNS_TIMEOUT: Duration = Duration::from_millis(100);
 /// Task state for the Network Request Task. The task is responsible for handling
 /// requests sent to this node by the network.  It will validate the sender,
 /// parse the request, and try to find the data request in the consensus stores.
-pub struct NetworkResponseState<TYPES: NodeType> {
+pub struct NetworkResponseState<TYPES: NodeType, V: Versions> {
     /// Locked consensus state
     consensus: LockedConsensusState<TYPES>,
 
@@ -44,9 +46,12 @@ pub struct NetworkResponseState<TYPES: NodeType> {
 
     /// The node's id
     id: u64,
+
+    /// Lock for a decided upgrade, which also binds the signatures we check to our chain
+    upgrade_lock: UpgradeLock<TYPES, V>,
 }
 
-impl<TYPES: NodeType> NetworkResponseState<TYPES> {
+impl<TYPES: NodeType, V: Versions> NetworkResponseState<TYPES, V> {
     /// Create the network request state with the info it needs
     pub fn new(
         consensus: LockedConsensusState<TYPES>,
@@ -54,6 +59,7 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
         pub_key: TYPES::SignatureKey,
         private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
         id: u64,
+        upgrade_lock: UpgradeLock<TYPES, V>,
     ) -> Self {
         Self {
             consensus,
@@ -61,6 +67,7 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
             pub_key,
             private_key,
             id,
+            upgrade_lock,
         }
     }
 
@@ -83,8 +90,13 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
                             {
                                 continue;
                             }
+                            // Availability samples ask for another node's share
+                            let share_key = match &request.request {
+                                RequestKind::VidSample(_, key) => key,
+                                _ => sender,
+                            };
                             if let Some(proposal) =
-                                self.get_or_calc_vid_share(request.view, sender).await
+                                self.get_or_calc_vid_share(request.view, share_key).await
                             {
                                 broadcast_event(
                                     HotShotEvent::VidResponseSend(
@@ -98,9 +110,40 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
                                 .await;
                             }
                         }
+                        HotShotEvent::DaCertificateRequestRecv(request, sender) => {
+                            let cur_epoch = self.consensus.read().await.cur_epoch();
+                            // Verify request is valid
+                            if !self.valid_sender(sender, cur_epoch).await
+                                || !valid_signature::<TYPES>(request, sender)
+                            {
+                                continue;
+                            }
+                            let certificate = self
+                                .consensus
+                                .read()
+                                .await
+                                .saved_da_certs()
+                                .get(&request.view);
+                            if let Some(certificate) = certificate {
+                                broadcast_event(
+                                    HotShotEvent::DaCertificateResponseSend(
+                                        self.pub_key.clone(),
+                                        sender.clone(),
+                                        certificate,
+                                    )
+                                    .into(),
+                                    &event_sender,
+                                )
+                                .await;
+                            }
+                        }
                         HotShotEvent::QuorumProposalRequestRecv(req, signature) => {
                             // Make sure that this request came from who we think it did
-                            if !req.key.validate(signature, req.commit().as_ref()) {
+                            let signing_message = self
+                                .upgrade_lock
+                                .signing_message(req.commit().as_ref(), req.view_number)
+                                .await;
+                            if !req.key.validate(signature, &signing_message) {
                                 tracing::warn!("Invalid signature key on proposal request.");
                                 return;
                             }
@@ -147,10 +190,8 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
         key: &TYPES::SignatureKey,
     ) -> Option<Proposal<TYPES, VidDisperseShare2<TYPES>>> {
         let consensus_reader = self.consensus.read().await;
-        if let Some(view) = consensus_reader.vid_shares().get(&view) {
-            if let Some(share) = view.get(key) {
-                return Some(share.clone());
-            }
+        if let Some(share) = consensus_reader.vid_share(view, key) {
+            return Some(share);
         }
 
         drop(consensus_reader);
@@ -160,6 +201,7 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
             view,
             Arc::clone(&self.membership),
             &self.private_key,
+            &self.upgrade_lock,
         )
         .await
         .is_none()
@@ -171,17 +213,11 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
                 view,
                 Arc::clone(&self.membership),
                 &self.private_key,
+                &self.upgrade_lock,
             )
             .await?;
         }
-        return self
-            .consensus
-            .read()
-            .await
-            .vid_shares()
-            .get(&view)?
-            .get(key)
-            .cloned();
+        return self.consensus.read().await.vid_share(view, key);
     }
 
     /// Makes sure the sender is allowed to send a request in the given epoch.
@@ -204,8 +240,8 @@ fn valid_signature<TYPES: NodeType>(
 /// Spawn the network response task to handle incoming request for data
 /// from other nodes.  It will shutdown when it gets `HotshotEvent::Shutdown`
 /// on the `event_stream` arg.
-pub fn run_response_task<TYPES: NodeType>(
-    task_state: NetworkResponseState<TYPES>,
+pub fn run_response_task<TYPES: NodeType, V: Versions>(
+    task_state: NetworkResponseState<TYPES, V>,
     event_stream: Receiver<Arc<HotShotEvent<TYPES>>>,
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
 ) -> JoinHandle<()> {
@@ -0,0 +1,283 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Pacing of our sends within an upload bandwidth budget.
+//!
+//! A leader pushes its quorum proposal, its DA proposal and a VID share for every quorum member at
+//! almost the same moment. Left to themselves these sends share the upload link evenly, so the
+//! proposal every replica is waiting for arrives no sooner than the last VID share. The
+//! [`SendScheduler`] instead hands the link to one send at a time, highest [`SendClass`] first,
+//! and paces the sends so that they stay within the configured budget. It also records how long
+//! each class of send takes from being queued to being handed to the network.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{self, Display},
+    future::Future,
+    num::NonZeroU64,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    message::{DaConsensusMessage, DataMessage, MessageClass, MessageKind, SequencingMessage},
+    traits::node_implementation::NodeType,
+};
+use tokio::{sync::Notify, time::sleep_until};
+
+/// The class of a send, which decides when it gets the upload link. Classes are served in the
+/// order they are declared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SendClass {
+    /// Quorum and upgrade proposals, which every replica is waiting for
+    Proposal,
+    /// Votes, certificates and view sync messages, which are small and time critical
+    Control,
+    /// DA proposals, which the DA committee votes on before the block is available
+    DaProposal,
+    /// VID shares of the quorum members whose votes complete a certificate soonest
+    CriticalShares,
+    /// The remaining VID shares
+    StragglerShares,
+    /// Responses, transactions and everything else off the critical path
+    Background,
+    /// Pages of decided leaves for peers catching up, which must not hold up live consensus
+    Catchup,
+}
+
+impl SendClass {
+    /// Every class of send, highest priority first
+    pub const ALL: [Self; 7] = [
+        Self::Proposal,
+        Self::Control,
+        Self::DaProposal,
+        Self::CriticalShares,
+        Self::StragglerShares,
+        Self::Background,
+        Self::Catchup,
+    ];
+
+    /// The class of a message of `kind` sent on its own
+    #[must_use]
+    pub fn of<TYPES: NodeType>(kind: &MessageKind<TYPES>) -> Self {
+        match kind {
+            MessageKind::Consensus(SequencingMessage::Da(
+                DaConsensusMessage::DaProposal(_) | DaConsensusMessage::DaProposal2(_),
+            )) => Self::DaProposal,
+            MessageKind::Consensus(SequencingMessage::Da(
+                DaConsensusMessage::VidDisperseMsg(_) | DaConsensusMessage::VidDisperseMsg2(_),
+            )) => Self::CriticalShares,
+            MessageKind::Data(DataMessage::CatchupPage(_)) => Self::Catchup,
+            _ => match MessageClass::of(kind) {
+                MessageClass::Proposal => Self::Proposal,
+                MessageClass::Vote | MessageClass::Certificate | MessageClass::ViewSync => {
+                    Self::Control
+                }
+                MessageClass::Vid | MessageClass::Unbound => Self::Background,
+            },
+        }
+    }
+}
+
+impl Display for SendClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Proposal => "proposal",
+            Self::Control => "control",
+            Self::DaProposal => "da_proposal",
+            Self::CriticalShares => "critical_shares",
+            Self::StragglerShares => "straggler_shares",
+            Self::Background => "background",
+            Self::Catchup => "catchup",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Sends completed for one class
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SendStats {
+    /// Number of sends
+    pub sends: u64,
+    /// Bytes uploaded by the sends
+    pub bytes: u64,
+    /// Time from being queued to being handed to the network, summed over the sends
+    pub total_time: Duration,
+    /// Longest time from being queued to being handed to the network
+    pub max_time: Duration,
+}
+
+/// Sends waiting for the upload link, and when it is next free
+#[derive(Debug)]
+struct SendQueue {
+    /// Waiting sends, by class and then in the order they were queued
+    waiting: BTreeSet<(SendClass, u64)>,
+    /// Ticket of the next send to be queued
+    next_ticket: u64,
+    /// When the bytes of the sends already granted have been uploaded
+    free_at: Instant,
+}
+
+/// Hands the upload link to one send at a time, highest class first, within a budget
+#[derive(Debug)]
+pub struct SendScheduler {
+    /// Upload budget in bytes per second, or `None` to send everything immediately
+    upload_budget: Option<NonZeroU64>,
+    /// Sends waiting for the link
+    queue: Mutex<SendQueue>,
+    /// Woken whenever the head of the queue changes
+    queue_changed: Notify,
+    /// Sends completed so far, per class
+    stats: Mutex<HashMap<SendClass, SendStats>>,
+    /// Metrics the send completion times are published to
+    metrics: Arc<ConsensusMetricsValue>,
+}
+
+/// Removes a send from the queue if it is dropped before it gets the link, e.g. because the
+/// transmit task was cancelled
+struct QueuedSend<'a> {
+    /// The scheduler the send is queued with
+    scheduler: &'a SendScheduler,
+    /// The send's place in the queue
+    ticket: (SendClass, u64),
+    /// Whether the send has left the queue
+    granted: bool,
+}
+
+impl Drop for QueuedSend<'_> {
+    fn drop(&mut self) {
+        if !self.granted {
+            self.scheduler.lock_queue().waiting.remove(&self.ticket);
+            self.scheduler.queue_changed.notify_waiters();
+        }
+    }
+}
+
+impl Default for SendScheduler {
+    fn default() -> Self {
+        Self::new(None, Arc::default())
+    }
+}
+
+impl SendScheduler {
+    /// Create a scheduler which paces sends to `upload_budget` bytes per second, publishing
+    /// completion times to `metrics`
+    #[must_use]
+    pub fn new(upload_budget: Option<NonZeroU64>, metrics: Arc<ConsensusMetricsValue>) -> Self {
+        Self {
+            upload_budget,
+            queue: Mutex::new(SendQueue {
+                waiting: BTreeSet::new(),
+                next_ticket: 0,
+                free_at: Instant::now(),
+            }),
+            queue_changed: Notify::new(),
+            stats: Mutex::new(HashMap::new()),
+            metrics,
+        }
+    }
+
+    /// The upload budget in bytes per second, if sends are paced
+    #[must_use]
+    pub fn upload_budget(&self) -> Option<NonZeroU64> {
+        self.upload_budget
+    }
+
+    /// Lock the queue, which is never left inconsistent by a panic
+    fn lock_queue(&self) -> std::sync::MutexGuard<'_, SendQueue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait for the link, then run `send`, which uploads `bytes` bytes of class `class`
+    pub async fn send<F: Future>(&self, class: SendClass, bytes: usize, send: F) -> F::Output {
+        let queued_at = Instant::now();
+        if let Some(upload_budget) = self.upload_budget {
+            self.wait_for_link(class, bytes, upload_budget).await;
+        }
+        let output = send.await;
+        self.record(class, bytes, queued_at.elapsed());
+
+        output
+    }
+
+    /// Wait until every send of a higher class, or of the same class queued earlier, has had the
+    /// link, and the bytes they uploaded have cleared the budget
+    async fn wait_for_link(&self, class: SendClass, bytes: usize, upload_budget: NonZeroU64) {
+        let mut queued = {
+            let mut queue = self.lock_queue();
+            let ticket = (class, queue.next_ticket);
+            queue.next_ticket += 1;
+            queue.waiting.insert(ticket);
+            QueuedSend {
+                scheduler: self,
+                ticket,
+                granted: false,
+            }
+        };
+        self.queue_changed.notify_waiters();
+
+        loop {
+            // Registered before the queue is checked, so no change in between is missed
+            let queue_changed = self.queue_changed.notified();
+            let free_at = {
+                let mut queue = self.lock_queue();
+                if queue.waiting.first() == Some(&queued.ticket) {
+                    let now = Instant::now();
+                    if queue.free_at <= now {
+                        queue.waiting.remove(&queued.ticket);
+                        #[allow(clippy::cast_precision_loss)]
+                        let upload_time =
+                            Duration::from_secs_f64(bytes as f64 / upload_budget.get() as f64);
+                        queue.free_at = now + upload_time;
+                        queued.granted = true;
+                        drop(queue);
+                        self.queue_changed.notify_waiters();
+                        return;
+                    }
+                    Some(queue.free_at)
+                } else {
+                    None
+                }
+            };
+
+            match free_at {
+                Some(free_at) => {
+                    tokio::select! {
+                        () = sleep_until(free_at.into()) => {}
+                        () = queue_changed => {}
+                    }
+                }
+                None => queue_changed.await,
+            }
+        }
+    }
+
+    /// Record a completed send
+    fn record(&self, class: SendClass, bytes: usize, elapsed: Duration) {
+        {
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+            let stats = stats.entry(class).or_default();
+            stats.sends += 1;
+            stats.bytes += bytes as u64;
+            stats.total_time += elapsed;
+            stats.max_time = stats.max_time.max(elapsed);
+        }
+        self.metrics
+            .send_completion_time
+            .create(vec![class.to_string()])
+            .add_point(elapsed.as_secs_f64());
+    }
+
+    /// Sends completed so far, per class
+    #[must_use]
+    pub fn stats(&self) -> HashMap<SendClass, SendStats> {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
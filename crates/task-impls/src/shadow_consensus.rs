@@ -0,0 +1,163 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    consensus::{Consensus, OuterConsensus},
+    data::QuorumProposal2,
+    event::{DecideOutcome, Event, EventType},
+    message::UpgradeLock,
+    simple_certificate::UpgradeCertificate,
+    traits::node_implementation::{NodeType, Versions},
+    vote::HasViewNumber,
+};
+use tracing::instrument;
+use utils::anytrace::*;
+use vbs::version::{StaticVersionType, Version};
+
+use crate::{
+    events::HotShotEvent,
+    helpers::{
+        broadcast_event, decide_from_proposal_2_locked, decide_from_proposal_locked,
+        LeafChainTraversalOutcome,
+    },
+};
+
+/// Task which rehearses an upgrade on live traffic: for every proposal we validate, it applies the
+/// decide rule of the version we are set to upgrade to alongside the rule of the version we run,
+/// and reports the proposals on which they reach different decisions.
+///
+/// The task only reads the consensus state the other tasks maintain. It sends no messages and
+/// changes no state, so the rehearsal cannot affect the live protocol.
+pub struct ShadowConsensusTaskState<TYPES: NodeType, V: Versions> {
+    /// Consensus state, which both rules are applied to
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// Lock for a decided upgrade, which tells which version we run in each view
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// Output events to application
+    pub output_event_stream: Sender<Event<TYPES>>,
+
+    /// Number of proposals on which the rules have diverged so far
+    pub divergences: u64,
+
+    /// The node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> ShadowConsensusTaskState<TYPES, V> {
+    /// Apply both decide rules to `proposal`, reporting it if they disagree
+    #[instrument(skip_all, fields(id = self.id, view = *proposal.view_number()), name = "Shadow consensus", level = "debug")]
+    pub async fn rehearse(&mut self, proposal: &QuorumProposal2<TYPES>) -> Result<()> {
+        let view = proposal.view_number();
+        let live_version = self.upgrade_lock.version(view).await?;
+        let shadow_version = V::Upgrade::VERSION;
+        if live_version >= shadow_version {
+            // We already run the version we were set to upgrade to
+            return Ok(());
+        }
+
+        // Apply both rules to the same state, so that the only difference between them is the rule
+        let consensus_reader = self.consensus.read().await;
+        let upgrade_cert_reader = self.upgrade_lock.decided_upgrade_certificate.read().await;
+        let live = decide::<TYPES, V>(
+            live_version,
+            proposal,
+            &consensus_reader,
+            upgrade_cert_reader.as_ref(),
+            &self.public_key,
+        );
+        let shadow = decide::<TYPES, V>(
+            shadow_version,
+            proposal,
+            &consensus_reader,
+            upgrade_cert_reader.as_ref(),
+            &self.public_key,
+        );
+        drop(upgrade_cert_reader);
+        drop(consensus_reader);
+
+        if live == shadow {
+            return Ok(());
+        }
+
+        self.divergences += 1;
+        tracing::warn!(
+            "Decide rule of version {shadow_version} diverged from that of version {live_version} in view {}: {live:?} against {shadow:?}",
+            *view
+        );
+        broadcast_event(
+            Event {
+                view_number: view,
+                event: EventType::ShadowDivergence {
+                    live_version,
+                    shadow_version,
+                    live,
+                    shadow,
+                },
+            },
+            &self.output_event_stream,
+        )
+        .await;
+
+        Ok(())
+    }
+}
+
+/// What the decide rule of `version` concludes from `proposal`
+fn decide<TYPES: NodeType, V: Versions>(
+    version: Version,
+    proposal: &QuorumProposal2<TYPES>,
+    consensus_reader: &Consensus<TYPES>,
+    upgrade_cert: Option<&UpgradeCertificate<TYPES>>,
+    public_key: &TYPES::SignatureKey,
+) -> DecideOutcome<TYPES> {
+    let LeafChainTraversalOutcome {
+        new_locked_view_number,
+        leaf_views,
+        ..
+    } = if version >= V::Epochs::VERSION {
+        decide_from_proposal_2_locked(proposal, consensus_reader, upgrade_cert, public_key)
+    } else {
+        decide_from_proposal_locked(proposal, consensus_reader, upgrade_cert, public_key)
+    };
+
+    DecideOutcome {
+        locked_view: new_locked_view_number,
+        decided_views: leaf_views
+            .iter()
+            .map(|info| info.leaf.view_number())
+            .collect(),
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for ShadowConsensusTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        _sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        if let HotShotEvent::QuorumProposalValidated(proposal, _) = event.as_ref() {
+            self.rehearse(&proposal.data).await?;
+        }
+
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
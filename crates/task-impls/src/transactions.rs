@@ -5,6 +5,7 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
+    collections::HashMap,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -12,17 +13,23 @@ use std::{
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::{Commitment, Committable};
 use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
 use hotshot_builder_api::v0_1::block_info::AvailableBlockInfo;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
     consensus::OuterConsensus,
+    constants::TIMEOUT_PREPLAN_PERCENT,
     data::{null_block, PackedBundle},
     event::{Event, EventType},
+    governance::GovernedParameters,
     message::UpgradeLock,
     traits::{
         auction_results_provider::AuctionResultsProvider,
-        block_contents::{precompute_vid_commitment, BuilderFee, EncodeBytes},
+        block_contents::{
+            precompute_vid_commitment, BlockHeader, BuilderFee, EncodeBytes, Transaction,
+            TransactionPriority,
+        },
         election::Membership,
         node_implementation::{ConsensusTime, HasUrls, NodeImplementation, NodeType, Versions},
         signature_key::{BuilderSignatureKey, SignatureKey},
@@ -30,6 +37,7 @@ use hotshot_types::{
     },
     utils::ViewInner,
     vid::{VidCommitment, VidPrecomputeData},
+    vote::HasViewNumber,
 };
 use tokio::time::{sleep, timeout};
 use tracing::instrument;
@@ -44,6 +52,7 @@ use crate::{
     },
     events::{HotShotEvent, HotShotTaskCompleted},
     helpers::broadcast_event,
+    recent_transactions::RecentTransactionFilter,
 };
 
 // Parameters for builder querying algorithm
@@ -61,6 +70,13 @@ const BUILDER_ADDITIONAL_TIME_MULTIPLIER: f32 = 0.2;
 const BUILDER_MINIMUM_QUERY_TIME: Duration = Duration::from_millis(300);
 /// Delay between re-tries on unsuccessful calls
 const RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Time reserved at the end of the builder timeout for claiming a block received over a stream
+const BUILDER_STREAM_CLAIM_RESERVE: Duration = Duration::from_millis(300);
+
+/// Maximum number of undecided transactions we track the arrival of for latency metrics
+const MAX_TRACKED_TRANSACTIONS: usize = 100_000;
+/// Time after which we stop waiting for a tracked transaction to be decided
+const TRANSACTION_TRACKING_RETENTION: Duration = Duration::from_secs(600);
 
 /// Builder Provided Responses
 pub struct BuilderResponse<TYPES: NodeType> {
@@ -77,6 +93,17 @@ pub struct BuilderResponse<TYPES: NodeType> {
     pub precompute_data: Option<VidPrecomputeData>,
 }
 
+/// A block for the leader to propose in place of one from its builders
+pub struct InjectedBlock<TYPES: NodeType> {
+    /// The block's payload
+    pub payload: TYPES::BlockPayload,
+    /// The payload's metadata, from which the leader builds the block header
+    pub metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+}
+
+/// Slot holding the block to propose in this node's next view as leader, if any
+pub type InjectedBlockSlot<TYPES> = Arc<RwLock<Option<InjectedBlock<TYPES>>>>;
+
 /// Tracks state of a Transaction task
 pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// The state's api
@@ -123,6 +150,28 @@ pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Transactions decided recently, which must not be proposed again
+    pub recent_transactions: RecentTransactionFilter,
+
+    /// Percentage of each block reserved for high-priority transactions
+    pub high_priority_reserved_percent: u64,
+
+    /// Priority class and arrival time of transactions we have not seen decided yet
+    pub transaction_arrivals:
+        HashMap<Commitment<TYPES::Transaction>, (TransactionPriority, Instant)>,
+
+    /// Block to propose in our next view as leader instead of asking the builders, set by tests
+    pub injected_block: InjectedBlockSlot<TYPES>,
+
+    /// View we lead whose block we started fetching before its timeout certificate formed
+    pub preplanned_view: Option<TYPES::View>,
+
+    /// Block fetched for `preplanned_view`, held back until the view starts
+    pub preplanned_block: Option<PackedBundle<TYPES>>,
+
+    /// Runtime parameters of each view, which decide the maximum size of the blocks we assemble
+    pub governed_parameters: Arc<GovernedParameters<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTaskState<TYPES, I, V> {
@@ -141,6 +190,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             }
         };
 
+        // Between versions only the null block may be proposed, so we don't ask the builders
+        if self.upgrade_lock.upgrading_in(block_view).await {
+            tracing::info!(
+                "Proposing the null block in view {:?}, which is between versions",
+                block_view
+            );
+            let null_block = self.null_block(block_view, block_epoch, version).await?;
+            self.send_block(null_block, event_stream).await;
+            return None;
+        }
+
         if version < V::Marketplace::VERSION {
             self.handle_view_change_legacy(event_stream, block_view, block_epoch)
                 .await
@@ -166,21 +226,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             }
         };
 
-        // Request a block from the builder unless we are between versions.
-        let block = {
-            if self
-                .upgrade_lock
-                .decided_upgrade_certificate
-                .read()
-                .await
-                .as_ref()
-                .is_some_and(|cert| cert.upgrading_in(block_view))
-            {
-                None
-            } else {
-                self.wait_for_block(block_view).await
-            }
-        };
+        let block = self.wait_for_block(block_view).await;
 
         if let Some(BuilderResponse {
             block_payload,
@@ -189,8 +235,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             precompute_data,
         }) = block
         {
-            broadcast_event(
-                Arc::new(HotShotEvent::BlockRecv(PackedBundle::new(
+            self.send_block(
+                PackedBundle::new(
                     block_payload.encode(),
                     metadata,
                     block_view,
@@ -198,7 +244,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     vec1::vec1![fee],
                     precompute_data,
                     None,
-                ))),
+                ),
                 event_stream,
             )
             .await;
@@ -231,8 +277,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             let (_, precompute_data) = precompute_vid_commitment(&[], membership_total_nodes);
 
             // Broadcast the empty block
-            broadcast_event(
-                Arc::new(HotShotEvent::BlockRecv(PackedBundle::new(
+            self.send_block(
+                PackedBundle::new(
                     vec![].into(),
                     metadata,
                     block_view,
@@ -240,7 +286,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     vec1::vec1![null_fee],
                     Some(precompute_data),
                     None,
-                ))),
+                ),
                 event_stream,
             )
             .await;
@@ -260,17 +306,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         block_epoch: TYPES::Epoch,
         task_start_time: Instant,
     ) -> Result<PackedBundle<TYPES>> {
-        ensure!(
-            !self
-                .upgrade_lock
-                .decided_upgrade_certificate
-                .read()
-                .await
-                .as_ref()
-                .is_some_and(|cert| cert.upgrading_in(block_view)),
-            info!("Not requesting block because we are upgrading")
-        );
-
         let (parent_view, parent_hash) = self
             .last_vid_commitment_retry(block_view, task_start_time)
             .await
@@ -332,6 +367,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             transactions.extend(bundle.transactions);
         }
 
+        let num_transactions = transactions.len();
+        transactions.retain(|transaction| !self.recent_transactions.contains(transaction));
+        if transactions.len() < num_transactions {
+            tracing::info!(
+                "Dropped {} recently decided transactions from the bundles for view {:?}",
+                num_transactions - transactions.len(),
+                block_view
+            );
+        }
+        let transactions = self.apply_priority_lane(transactions, block_view);
+
         let validated_state = self.consensus.read().await.decided_state();
 
         let sequencing_fees = Vec1::try_from_vec(sequencing_fees)
@@ -357,6 +403,190 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         ))
     }
 
+    /// Order `transactions` for a block we assemble ourselves, putting high-priority transactions
+    /// first.
+    ///
+    /// If the chain has a maximum block size in `block_view`, normal transactions may not take up
+    /// the part of the block reserved for high-priority transactions, and transactions which don't
+    /// fit are left out.
+    pub fn apply_priority_lane(
+        &self,
+        transactions: Vec<TYPES::Transaction>,
+        block_view: TYPES::View,
+    ) -> Vec<TYPES::Transaction> {
+        let (high, normal): (Vec<_>, Vec<_>) = transactions.into_iter().partition(|transaction| {
+            self.transaction_arrivals
+                .get(&transaction.commit())
+                .is_some_and(|(priority, _)| *priority == TransactionPriority::High)
+        });
+
+        let Some(max_block_size) = self.governed_parameters.at(block_view).max_block_size else {
+            return high.into_iter().chain(normal).collect();
+        };
+        let reserved = u128::from(max_block_size)
+            * u128::from(self.high_priority_reserved_percent.min(100))
+            / 100;
+        // `reserved` is at most `max_block_size`
+        let normal_limit = max_block_size - u64::try_from(reserved).unwrap_or(max_block_size);
+
+        let mut block = Vec::new();
+        let mut size = 0u64;
+        for transaction in high {
+            let next = size.saturating_add(transaction.minimum_block_size());
+            if next <= max_block_size {
+                size = next;
+                block.push(transaction);
+            }
+        }
+        let mut normal_size = 0u64;
+        for transaction in normal {
+            let transaction_size = transaction.minimum_block_size();
+            if size.saturating_add(transaction_size) <= max_block_size
+                && normal_size.saturating_add(transaction_size) <= normal_limit
+            {
+                size += transaction_size;
+                normal_size += transaction_size;
+                block.push(transaction);
+            }
+        }
+        block
+    }
+
+    /// Remember when we first saw each of `transactions`, to report its inclusion latency once it
+    /// is decided
+    fn record_arrivals<'a>(
+        &mut self,
+        transactions: impl Iterator<Item = (&'a TYPES::Transaction, TransactionPriority)>,
+    ) {
+        let now = Instant::now();
+        for (transaction, priority) in transactions {
+            if self.transaction_arrivals.len() >= MAX_TRACKED_TRANSACTIONS {
+                break;
+            }
+            self.transaction_arrivals
+                .entry(transaction.commit())
+                .or_insert((priority, now));
+        }
+    }
+
+    /// Send the block we produced on to be proposed, unless we fetched it ahead of its view, in
+    /// which case we hold it back until the view starts
+    async fn send_block(
+        &mut self,
+        bundle: PackedBundle<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        if self.preplanned_view == Some(bundle.view_number) {
+            self.preplanned_block = Some(bundle);
+            return;
+        }
+
+        broadcast_event(Arc::new(HotShotEvent::BlockRecv(bundle)), event_stream).await;
+    }
+
+    /// Take the block we fetched ahead of `block_view`, if it is still usable
+    fn take_preplanned_block(
+        &mut self,
+        block_view: TYPES::View,
+        block_epoch: TYPES::Epoch,
+    ) -> Option<PackedBundle<TYPES>> {
+        if self.preplanned_view != Some(block_view) {
+            return None;
+        }
+        self.preplanned_view = None;
+
+        self.preplanned_block
+            .take()
+            .filter(|bundle| bundle.view_number == block_view && bundle.epoch_number == block_epoch)
+    }
+
+    /// Start fetching the block for the view after `view` if we lead it, and the timeout votes for
+    /// `view` are far enough along that a timeout certificate is about to form
+    async fn preplan_after_timeout(
+        &mut self,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+        percent_of_threshold: u64,
+    ) -> Result<()> {
+        let block_view = view + 1;
+        ensure!(
+            percent_of_threshold >= TIMEOUT_PREPLAN_PERCENT,
+            debug!(
+                "Not enough timeout votes for view {:?} to prepare the next view yet",
+                view
+            )
+        );
+        ensure!(
+            block_view > self.cur_view
+                && !matches!(self.preplanned_view, Some(v) if v >= block_view),
+            debug!("Already prepared or entered view {:?}", block_view)
+        );
+        ensure!(
+            self.membership.read().await.leader(block_view, epoch)? == self.public_key,
+            debug!("We are not the leader of view {:?}", block_view)
+        );
+
+        tracing::info!(
+            "Timeout votes for view {:?} at {}% of the threshold, fetching the block for view {:?}",
+            view,
+            percent_of_threshold,
+            block_view
+        );
+        self.preplanned_view = Some(block_view);
+        self.preplanned_block = None;
+        self.handle_view_change(event_stream, block_view, epoch)
+            .await;
+
+        Ok(())
+    }
+
+    /// Take the injected block, if any, and pack it for proposing in `block_view`
+    async fn take_injected_block(
+        &self,
+        block_view: TYPES::View,
+        block_epoch: TYPES::Epoch,
+    ) -> Option<PackedBundle<TYPES>> {
+        let InjectedBlock { payload, metadata } = self.injected_block.write().await.take()?;
+
+        let version = match self.upgrade_lock.version(block_view).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!(
+                    "Failed to calculate version, dropping injected block: {}",
+                    err
+                );
+                return None;
+            }
+        };
+        let membership_total_nodes = self.membership.read().await.total_nodes(self.cur_epoch);
+        // Nobody built the block, so it carries the same zero fee as a null block
+        let Some(fee) =
+            null_block::builder_fee::<TYPES, V>(membership_total_nodes, version, *block_view)
+        else {
+            tracing::error!("Failed to calculate the fee of the injected block");
+            return None;
+        };
+
+        let encoded_transactions = payload.encode();
+        let (_, precompute_data) =
+            precompute_vid_commitment(&encoded_transactions, membership_total_nodes);
+        let auction_result =
+            (version >= V::Marketplace::VERSION).then(TYPES::AuctionResult::default);
+
+        tracing::info!("Proposing the injected block in view {:?}", block_view);
+
+        Some(PackedBundle::new(
+            encoded_transactions,
+            metadata,
+            block_view,
+            block_epoch,
+            vec1::vec1![fee],
+            Some(precompute_data),
+            auction_result,
+        ))
+    }
+
     /// Produce a null block
     pub async fn null_block(
         &self,
@@ -384,7 +614,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             block_epoch,
             vec1::vec1![null_fee],
             Some(precompute_data),
-            Some(TYPES::AuctionResult::default()),
+            (version >= V::Marketplace::VERSION).then(TYPES::AuctionResult::default),
         ))
     }
 
@@ -432,11 +662,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             }
         };
 
-        broadcast_event(
-            Arc::new(HotShotEvent::BlockRecv(packed_bundle)),
-            event_stream,
-        )
-        .await;
+        self.send_block(packed_bundle, event_stream).await;
 
         None
     }
@@ -467,6 +693,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
     ) -> Result<()> {
         match event.as_ref() {
             HotShotEvent::TransactionsRecv(transactions) => {
+                self.record_arrivals(
+                    transactions
+                        .iter()
+                        .map(|transaction| (transaction, TransactionPriority::Normal)),
+                );
                 broadcast_event(
                     Event {
                         view_number: self.cur_view,
@@ -478,6 +709,49 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 )
                 .await;
             }
+            HotShotEvent::PrioritizedTransactionsRecv(transactions) => {
+                self.record_arrivals(
+                    transactions
+                        .iter()
+                        .map(|(transaction, priority)| (transaction, *priority)),
+                );
+                broadcast_event(
+                    Event {
+                        view_number: self.cur_view,
+                        event: EventType::PrioritizedTransactions {
+                            transactions: transactions.clone(),
+                        },
+                    },
+                    &self.output_event_stream,
+                )
+                .await;
+            }
+            HotShotEvent::LeavesDecided(leaves) => {
+                let consensus_reader = self.consensus.read().await;
+                for leaf in leaves {
+                    if let Some(payload) = leaf.block_payload() {
+                        for transaction in payload.transactions(leaf.block_header().metadata()) {
+                            self.recent_transactions.insert(&transaction);
+                            if let Some((priority, arrival)) =
+                                self.transaction_arrivals.remove(&transaction.commit())
+                            {
+                                consensus_reader
+                                    .metrics
+                                    .transaction_inclusion_latency
+                                    .create(vec![priority.to_string()])
+                                    .add_point(arrival.elapsed().as_secs_f64());
+                            }
+                        }
+                    }
+                }
+                drop(consensus_reader);
+                self.transaction_arrivals
+                    .retain(|_, (_, arrival)| arrival.elapsed() < TRANSACTION_TRACKING_RETENTION);
+            }
+            HotShotEvent::TimeoutVoteProgress(view, epoch, percent_of_threshold) => {
+                self.preplan_after_timeout(&event_stream, *view, *epoch, *percent_of_threshold)
+                    .await?;
+            }
             HotShotEvent::ViewChange(view, epoch) => {
                 let view = TYPES::View::new(std::cmp::max(1, **view));
                 let epoch = if self.epoch_height != 0 {
@@ -498,6 +772,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
 
                 let leader = self.membership.read().await.leader(view, epoch)?;
                 if leader == self.public_key {
+                    // Between versions we propose the null block, whatever we fetched ahead of
+                    // the view, and keep any injected block for the next view we lead after it
+                    if self.upgrade_lock.upgrading_in(view).await {
+                        self.preplanned_view = None;
+                        self.preplanned_block = None;
+                        self.handle_view_change(&event_stream, view, epoch).await;
+                        return Ok(());
+                    }
+                    if let Some(bundle) = self.take_injected_block(view, epoch).await {
+                        broadcast_event(Arc::new(HotShotEvent::BlockRecv(bundle)), &event_stream)
+                            .await;
+                        return Ok(());
+                    }
+                    if let Some(bundle) = self.take_preplanned_block(view, epoch) {
+                        broadcast_event(Arc::new(HotShotEvent::BlockRecv(bundle)), &event_stream)
+                            .await;
+                        return Ok(());
+                    }
+
                     self.handle_view_change(&event_stream, view, epoch).await;
                     return Ok(());
                 }
@@ -536,7 +829,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         block_view: TYPES::View,
     ) -> Result<(TYPES::View, VidCommitment)> {
         let consensus_reader = self.consensus.read().await;
-        let mut target_view = TYPES::View::new(block_view.saturating_sub(1));
+        let mut target_view = block_view
+            .checked_sub(1)
+            .unwrap_or_else(TYPES::View::genesis);
 
         loop {
             let view_data = consensus_reader
@@ -560,8 +855,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 }
                 ViewInner::Failed => {
                     // For failed views, backtrack
-                    target_view =
-                        TYPES::View::new(target_view.checked_sub(1).context(warn!("Reached genesis. Something is wrong -- have we not decided any blocks since genesis?"))?);
+                    target_view = target_view.checked_sub(1).context(warn!("Reached genesis. Something is wrong -- have we not decided any blocks since genesis?"))?;
                     continue;
                 }
             }
@@ -595,6 +889,22 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             }
         };
 
+        // Prefer streaming block assembly, falling back to polling the builders if none of them
+        // support it or we fail to claim any of the streamed blocks
+        match self
+            .block_from_builder_streams(
+                parent_comm,
+                parent_view,
+                block_view,
+                &parent_comm_sig,
+                task_start_time,
+            )
+            .await
+        {
+            Ok(block) => return Some(block),
+            Err(err) => tracing::debug!("Couldn't get a block over a stream: {err:#}"),
+        }
+
         while task_start_time.elapsed() < self.builder_timeout {
             match timeout(
                 self.builder_timeout
@@ -705,10 +1015,109 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         view_number: TYPES::View,
         parent_comm_sig: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     ) -> Result<BuilderResponse<TYPES>> {
-        let mut available_blocks = self
+        let available_blocks = self
             .get_available_blocks(parent_comm, view_number, parent_comm_sig)
             .await;
 
+        self.claim_best_block(available_blocks, view_number).await
+    }
+
+    /// Get a block from the builders supporting streaming block assembly.
+    /// Subscribes to the available blocks of every builder and collects the candidates they
+    /// assemble until all streams close, we are ready to propose for `block_view`, or only the
+    /// time reserved for claiming is left. Then claims the best of the collected candidates.
+    ///
+    /// # Errors
+    /// If none of the builders support streaming, none of them offered a block in time, or
+    /// claiming a block fails for all of the candidates.
+    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view), name = "block_from_builder_streams", level = "error")]
+    async fn block_from_builder_streams(
+        &self,
+        parent_comm: VidCommitment,
+        view_number: TYPES::View,
+        block_view: TYPES::View,
+        parent_comm_sig: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        task_start_time: Instant,
+    ) -> Result<BuilderResponse<TYPES>> {
+        let streams = join_all(self.builder_clients.iter().enumerate().map(
+            |(builder_idx, client)| async move {
+                client
+                    .available_blocks_stream(
+                        parent_comm,
+                        view_number.u64(),
+                        self.public_key.clone(),
+                        parent_comm_sig,
+                    )
+                    .await
+                    .map(move |stream| stream.map(move |result| (result, builder_idx)))
+            },
+        ))
+        .await
+        .into_iter()
+        .filter_map(|result| match result {
+            Ok(stream) => Some(stream),
+            Err(err) => {
+                tracing::debug!(%err, "Builder does not stream available blocks");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+        ensure!(
+            !streams.is_empty(),
+            debug!("None of the builders support streaming block assembly")
+        );
+
+        let deadline = self
+            .builder_timeout
+            .saturating_sub(BUILDER_STREAM_CLAIM_RESERVE);
+        let mut candidates = futures::stream::select_all(streams);
+        let mut available_blocks = Vec::new();
+
+        while task_start_time.elapsed() < deadline {
+            // Commit to the best candidate so far as soon as we have what we need to propose
+            if !available_blocks.is_empty()
+                && self.consensus.read().await.high_qc().view_number() + 1 >= block_view
+            {
+                break;
+            }
+
+            match timeout(
+                std::cmp::min(
+                    RETRY_DELAY,
+                    deadline.saturating_sub(task_start_time.elapsed()),
+                ),
+                candidates.next(),
+            )
+            .await
+            {
+                Ok(Some((Ok(block_info), builder_idx))) => {
+                    available_blocks.push((block_info, builder_idx));
+                }
+                Ok(Some((Err(err), _))) => {
+                    tracing::warn!(%err, "Error in available blocks stream");
+                }
+                // All builders are done assembling
+                Ok(None) => break,
+                // Nothing new yet, check again whether we are ready to propose
+                Err(_) => {}
+            }
+        }
+        drop(candidates);
+
+        self.claim_best_block(available_blocks, view_number).await
+    }
+
+    /// Claim the block with the best fee/byte ratio out of `available_blocks`, re-trying with
+    /// the next best one in case of failure.
+    ///
+    /// # Errors
+    /// If there are no available blocks or claiming a block fails for all of the builders.
+    async fn claim_best_block(
+        &self,
+        mut available_blocks: Vec<(AvailableBlockInfo<TYPES>, usize)>,
+        view_number: TYPES::View,
+    ) -> Result<BuilderResponse<TYPES>> {
         available_blocks.sort_by(|(l, _), (r, _)| {
             // We want the block with the highest fee per byte of data we're going to have to
             // process, thus our comparison function is:
@@ -797,6 +1206,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     continue;
                 }
 
+                // The builder's block is signed as a whole, so we can't filter out transactions
+                // which were already decided and have to skip the block instead
+                if block_data
+                    .block_payload
+                    .transactions(&block_data.metadata)
+                    .any(|transaction| self.recent_transactions.contains(&transaction))
+                {
+                    tracing::warn!("Builder's block contains recently decided transactions");
+                    continue;
+                }
+
                 // verify the message signature and the fee_signature
                 if !header_input.validate_signature(block_info.offered_fee, &block_data.metadata) {
                     tracing::warn!(
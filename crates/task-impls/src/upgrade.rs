@@ -12,6 +12,7 @@ use async_trait::async_trait;
 use committable::Committable;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
+    clock::Clock,
     constants::{
         UPGRADE_BEGIN_OFFSET, UPGRADE_DECIDE_BY_OFFSET, UPGRADE_FINISH_OFFSET,
         UPGRADE_PROPOSE_OFFSET,
@@ -91,6 +92,9 @@ pub struct UpgradeTaskState<TYPES: NodeType, V: Versions> {
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Our wall clock, which the time windows are compared against
+    pub clock: Clock,
 }
 
 impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
@@ -122,7 +126,9 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                     info!("Already upgraded to {:?}; not voting.", V::Upgrade::VERSION)
                 );
 
-                let time = SystemTime::now()
+                let time = self
+                    .clock
+                    .now()
                     .duration_since(SystemTime::UNIX_EPOCH)
                     .wrap()
                     .context(error!(
@@ -168,11 +174,12 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                 // Allow an upgrade proposal that is one view older, in case we have voted on a quorum
                 // proposal and updated the view.
                 // `self.cur_view` should be at least 1 since there is a view change before getting
-                // the `UpgradeProposalRecv` event. Otherwise, the view number subtraction below will
-                // cause an overflow error.
+                // the `UpgradeProposalRecv` event, so we reject proposals while still in genesis.
                 // TODO Come back to this - we probably don't need this, but we should also never receive a UpgradeCertificate where this fails, investigate block ready so it doesn't make one for the genesis block
                 ensure!(
-                    self.cur_view != TYPES::View::genesis() && *view >= self.cur_view.saturating_sub(1),
+                    self.cur_view
+                        .checked_sub(1)
+                        .is_some_and(|previous_view| view >= previous_view),
                     warn!(
                       "Discarding old upgrade proposal; the proposal is for view {:?}, but the current view is {:?}.",
                       view,
@@ -188,6 +195,14 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                         "Upgrade proposal doesn't have expected leader key for view {} \n Upgrade proposal is: {:?}", *view, proposal.data.clone()
                     )
                 );
+                let signing_message = self
+                    .upgrade_lock
+                    .signing_message(proposal.data.upgrade_proposal.commit().as_ref(), view)
+                    .await;
+                ensure!(
+                    view_leader_key.validate(&proposal.signature, &signing_message),
+                    warn!("Could not verify the upgrade proposal for view {:?}", view)
+                );
 
                 // At this point, we've checked that:
                 //   * the proposal was expected,
@@ -258,7 +273,9 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                 self.cur_view = *new_view;
 
                 let view: u64 = *self.cur_view;
-                let time = SystemTime::now()
+                let time = self
+                    .clock
+                    .now()
                     .duration_since(SystemTime::UNIX_EPOCH)
                     .wrap()
                     .context(error!(
@@ -296,7 +313,13 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
 
                     let signature = TYPES::SignatureKey::sign(
                         &self.private_key,
-                        upgrade_proposal_data.commit().as_ref(),
+                        &self
+                            .upgrade_lock
+                            .signing_message(
+                                upgrade_proposal_data.commit().as_ref(),
+                                upgrade_proposal.view_number,
+                            )
+                            .await,
                     )
                     .expect("Failed to sign upgrade proposal commitment!");
 
@@ -4,24 +4,31 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use hotshot_task::sharded::ViewShardedTaskState;
 use hotshot_types::{
     consensus::OuterConsensus,
     data::{PackedBundle, VidDisperse, VidDisperseShare2},
-    message::Proposal,
+    message::{Proposal, UpgradeLock},
     traits::{
         block_contents::BlockHeader,
         election::Membership,
-        node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
         signature_key::SignatureKey,
         BlockPayload,
     },
     utils::epoch_from_block_number,
+    view_tracker::{TrackedTask, ViewTracker},
 };
 use tracing::{debug, error, info, instrument};
 use utils::anytrace::Result;
@@ -29,15 +36,17 @@ use utils::anytrace::Result;
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
     helpers::broadcast_event,
+    work_scheduler::{WorkClass, WorkScheduler},
 };
 
 /// Tracks state of a VID task
-pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
-    /// View number this view is executing in.
-    pub cur_view: TYPES::View,
+pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
+    /// View number this view is executing in, updated on view changes without waiting for the
+    /// dispersals of earlier views.
+    pub cur_view: AtomicU64,
 
     /// Epoch number this node is executing in.
-    pub cur_epoch: TYPES::Epoch,
+    pub cur_epoch: AtomicU64,
 
     /// Reference to consensus. Leader will require a read lock on this.
     pub consensus: OuterConsensus<TYPES>,
@@ -59,13 +68,39 @@ pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// The view each task is in, shared between the tasks
+    pub view_tracker: Arc<ViewTracker<TYPES>>,
+
+    /// Whether every node stores every payload, in which case we send no VID shares; nodes derive
+    /// their own from the payload in the DA proposal
+    pub full_replication: bool,
+
+    /// Budget of background work in each view, which VID encoding is charged to
+    pub work_scheduler: Arc<WorkScheduler>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
 }
 
-impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
-    /// main task event handler
-    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = *self.cur_epoch), name = "VID Main Task", level = "error", target = "VidTaskState")]
-    pub async fn handle(
-        &mut self,
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> VidTaskState<TYPES, I, V> {
+    /// View number this view is executing in
+    #[must_use]
+    pub fn cur_view(&self) -> TYPES::View {
+        TYPES::View::new(self.cur_view.load(Ordering::Acquire))
+    }
+
+    /// Epoch number this node is executing in
+    #[must_use]
+    pub fn cur_epoch(&self) -> TYPES::Epoch {
+        TYPES::Epoch::new(self.cur_epoch.load(Ordering::Acquire))
+    }
+
+    /// Handler for the events which belong to a single view, run concurrently with the events of
+    /// other views
+    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view(), epoch = *self.cur_epoch()), name = "VID View Task", level = "error", target = "VidTaskState")]
+    pub async fn handle_view_local(
+        &self,
         event: Arc<HotShotEvent<TYPES>>,
         event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     ) -> Option<HotShotTaskCompleted> {
@@ -83,7 +118,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 let payload =
                     <TYPES as NodeType>::BlockPayload::from_bytes(encoded_transactions, metadata);
                 let builder_commitment = payload.builder_commitment(metadata);
-                let epoch = self.cur_epoch;
+                let epoch = self.cur_epoch();
                 if self
                     .membership
                     .read()
@@ -97,24 +132,33 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                     );
                     return None;
                 }
-                let vid_disperse = VidDisperse::calculate_vid_disperse(
-                    Arc::clone(encoded_transactions),
-                    &Arc::clone(&self.membership),
-                    *view_number,
-                    epoch,
-                    epoch,
-                    vid_precompute.clone(),
-                )
-                .await;
+                let vid_disperse = self
+                    .work_scheduler
+                    .run(
+                        WorkClass::VidEncoding,
+                        VidDisperse::calculate_vid_disperse(
+                            Arc::clone(encoded_transactions),
+                            &Arc::clone(&self.membership),
+                            *view_number,
+                            epoch,
+                            epoch,
+                            vid_precompute.clone(),
+                        ),
+                    )
+                    .await;
                 let payload_commitment = vid_disperse.payload_commitment;
                 let shares = VidDisperseShare2::from_vid_disperse(vid_disperse.clone());
-                let mut consensus_writer = self.consensus.write().await;
                 for share in shares {
-                    if let Some(disperse) = share.to_proposal(&self.private_key) {
-                        consensus_writer.update_vid_shares(*view_number, disperse);
+                    if let Some(disperse) = share
+                        .to_proposal(&self.private_key, &self.upgrade_lock)
+                        .await
+                    {
+                        self.consensus
+                            .read()
+                            .await
+                            .update_vid_shares(*view_number, disperse);
                     }
                 }
-                drop(consensus_writer);
 
                 // send the commitment and metadata to consensus for block building
                 broadcast_event(
@@ -130,10 +174,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 )
                 .await;
 
+                if self.full_replication {
+                    debug!("Full replication, not dispersing VID shares for view {view_number}");
+                    return None;
+                }
+
                 let view_number = *view_number;
                 let Ok(signature) = TYPES::SignatureKey::sign(
                     &self.private_key,
-                    vid_disperse.payload_commitment.as_ref(),
+                    &self
+                        .upgrade_lock
+                        .signing_message(vid_disperse.payload_commitment.as_ref(), view_number)
+                        .await,
                 ) else {
                     error!("VID: failed to sign dispersal payload");
                     return None;
@@ -156,24 +208,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 .await;
             }
 
-            HotShotEvent::ViewChange(view, epoch) => {
-                if *epoch > self.cur_epoch {
-                    self.cur_epoch = *epoch;
-                }
-
-                let view = *view;
-                if (*view != 0 || *self.cur_view > 0) && *self.cur_view >= *view {
-                    return None;
-                }
-
-                if *view - *self.cur_view > 1 {
-                    info!("View changed by more than 1 going to view {:?}", view);
-                }
-                self.cur_view = view;
-
-                return None;
-            }
-
             HotShotEvent::QuorumProposalSend(proposal, _) => {
                 let proposed_block_number = proposal.data.block_header.block_number();
                 if self.epoch_height == 0 || proposed_block_number % self.epoch_height != 0 {
@@ -203,18 +237,29 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 let txns = Arc::clone(txns);
                 drop(consensus_reader);
 
-                let next_epoch_vid_disperse = VidDisperse::calculate_vid_disperse(
-                    txns,
-                    &Arc::clone(&self.membership),
-                    proposal_view_number,
-                    target_epoch,
-                    sender_epoch,
-                    None,
-                )
-                .await;
+                let next_epoch_vid_disperse = self
+                    .work_scheduler
+                    .run(
+                        WorkClass::VidEncoding,
+                        VidDisperse::calculate_vid_disperse(
+                            txns,
+                            &Arc::clone(&self.membership),
+                            proposal_view_number,
+                            target_epoch,
+                            sender_epoch,
+                            None,
+                        ),
+                    )
+                    .await;
                 let Ok(next_epoch_signature) = TYPES::SignatureKey::sign(
                     &self.private_key,
-                    next_epoch_vid_disperse.payload_commitment.as_ref(),
+                    &self
+                        .upgrade_lock
+                        .signing_message(
+                            next_epoch_vid_disperse.payload_commitment.as_ref(),
+                            proposal_view_number,
+                        )
+                        .await,
                 ) else {
                     error!("VID: failed to sign dispersal payload for the next epoch");
                     return None;
@@ -236,27 +281,88 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 )
                 .await;
             }
-            HotShotEvent::Shutdown => {
-                return Some(HotShotTaskCompleted);
-            }
             _ => {}
         }
         None
     }
+
+    /// Handler for view changes, run as soon as they are received rather than after the
+    /// dispersals of earlier views
+    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view(), epoch = *self.cur_epoch()), name = "VID View Change", level = "error", target = "VidTaskState")]
+    pub async fn handle_view_change(&self, view: TYPES::View, epoch: TYPES::Epoch) {
+        self.cur_epoch.fetch_max(*epoch, Ordering::AcqRel);
+
+        // View changes are handled one at a time, so nothing else updates the view in between
+        let cur_view = self.cur_view();
+        if (*view != 0 || *cur_view > 0) && *cur_view >= *view {
+            return;
+        }
+
+        if view.saturating_distance(cur_view) > 1 {
+            info!("View changed by more than 1 going to view {:?}", view);
+        }
+        self.cur_view.store(*view, Ordering::Release);
+        if let Err(e) = self.view_tracker.update(TrackedTask::Vid, view).await {
+            error!("{e}");
+        }
+    }
+
+    /// main task event handler
+    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view(), epoch = *self.cur_epoch()), name = "VID Main Task", level = "error", target = "VidTaskState")]
+    pub fn handle(&self, event: &HotShotEvent<TYPES>) -> Option<HotShotTaskCompleted> {
+        matches!(event, HotShotEvent::Shutdown).then_some(HotShotTaskCompleted)
+    }
 }
 
 #[async_trait]
-/// task state implementation for VID Task
-impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for VidTaskState<TYPES, I> {
+/// task state implementation for VID Task, which computes the dispersals of distinct views
+/// concurrently
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ViewShardedTaskState
+    for VidTaskState<TYPES, I, V>
+{
     type Event = HotShotEvent<TYPES>;
+    type View = TYPES::View;
+
+    fn event_view(&self, event: &Self::Event) -> Option<Self::View> {
+        match event {
+            HotShotEvent::BlockRecv(packed_bundle) => Some(packed_bundle.view_number),
+            HotShotEvent::QuorumProposalSend(proposal, _) => Some(proposal.data.view_number),
+            _ => None,
+        }
+    }
+
+    async fn handle_view_event(
+        &self,
+        _view: Self::View,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle_view_local(event, sender.clone()).await;
+        Ok(())
+    }
+
+    fn is_shared_event(&self, event: &Self::Event) -> bool {
+        matches!(event, HotShotEvent::ViewChange(..))
+    }
+
+    async fn handle_shared_event(
+        &self,
+        event: Arc<Self::Event>,
+        _sender: &Sender<Arc<Self::Event>>,
+    ) -> Result<()> {
+        if let HotShotEvent::ViewChange(view, epoch) = event.as_ref() {
+            self.handle_view_change(*view, *epoch).await;
+        }
+        Ok(())
+    }
 
     async fn handle_event(
         &mut self,
         event: Arc<Self::Event>,
-        sender: &Sender<Arc<Self::Event>>,
+        _sender: &Sender<Arc<Self::Event>>,
         _receiver: &Receiver<Arc<Self::Event>>,
     ) -> Result<()> {
-        self.handle(event, sender.clone()).await;
+        self.handle(&event);
         Ok(())
     }
 
@@ -16,6 +16,8 @@ use async_lock::RwLock;
 use async_trait::async_trait;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    data::ViewSyncCertificate,
     message::UpgradeLock,
     simple_certificate::{
         ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate2,
@@ -26,10 +28,13 @@ use hotshot_types::{
     },
     traits::{
         election::Membership,
-        node_implementation::{ConsensusTime, NodeType, Versions},
+        metrics::MetricsFamily,
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
         signature_key::SignatureKey,
+        storage::Storage,
     },
     utils::EpochTransitionIndicator,
+    view_tracker::{TrackedTask, ViewTracker},
     vote::{Certificate, HasViewNumber, Vote},
 };
 use tokio::{spawn, task::JoinHandle, time::sleep};
@@ -38,7 +43,7 @@ use utils::anytrace::*;
 
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
-    helpers::broadcast_event,
+    helpers::{broadcast_event, view_sync_relay},
     vote_collection::{
         create_vote_accumulator, AccumulatorInfo, HandleVoteEvent, VoteCollectionTaskState,
     },
@@ -63,7 +68,7 @@ type RelayMap<TYPES, VOTE, CERT, V> = HashMap<
 >;
 
 /// Main view sync task state
-pub struct ViewSyncTaskState<TYPES: NodeType, V: Versions> {
+pub struct ViewSyncTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// View HotShot is currently in
     pub cur_view: TYPES::View,
 
@@ -88,8 +93,11 @@ pub struct ViewSyncTaskState<TYPES: NodeType, V: Versions> {
     /// How many timeouts we've seen in a row; is reset upon a successful view change
     pub num_timeouts_tracked: u64,
 
+    /// The view each task is in, shared between the tasks
+    pub view_tracker: Arc<ViewTracker<TYPES>>,
+
     /// Map of running replica tasks
-    pub replica_task_map: RwLock<HashMap<TYPES::View, ViewSyncReplicaTaskState<TYPES, V>>>,
+    pub replica_task_map: RwLock<HashMap<TYPES::View, ViewSyncReplicaTaskState<TYPES, I, V>>>,
 
     /// Map of pre-commit vote accumulates for the relay
     pub pre_commit_relay_map: RwLock<
@@ -105,18 +113,30 @@ pub struct ViewSyncTaskState<TYPES: NodeType, V: Versions> {
         RelayMap<TYPES, ViewSyncFinalizeVote2<TYPES>, ViewSyncFinalizeCertificate2<TYPES>, V>,
     >,
 
-    /// Timeout duration for view sync rounds
-    pub view_sync_timeout: Duration,
+    /// How long a replica waits on one relay before failing over to the next
+    pub relay_timeout: Duration,
 
     /// Last view we garbage collected old tasks
     pub last_garbage_collected_view: TYPES::View,
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Metrics of the relays' outcomes
+    pub metrics: Arc<ConsensusMetricsValue>,
+
+    /// Storage for the latest view sync certificate
+    pub storage: Arc<RwLock<I::Storage>>,
+
+    /// The view sync certificate loaded from storage at startup, which we resume view sync from
+    /// once the task is running
+    pub restored_certificate: Option<ViewSyncCertificate<TYPES>>,
 }
 
 #[async_trait]
-impl<TYPES: NodeType, V: Versions> TaskState for ViewSyncTaskState<TYPES, V> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
+    for ViewSyncTaskState<TYPES, I, V>
+{
     type Event = HotShotEvent<TYPES>;
 
     async fn handle_event(
@@ -132,9 +152,9 @@ impl<TYPES: NodeType, V: Versions> TaskState for ViewSyncTaskState<TYPES, V> {
 }
 
 /// State of a view sync replica task
-pub struct ViewSyncReplicaTaskState<TYPES: NodeType, V: Versions> {
-    /// Timeout for view sync rounds
-    pub view_sync_timeout: Duration,
+pub struct ViewSyncReplicaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
+    /// How long we wait on one relay before failing over to the next
+    pub relay_timeout: Duration,
 
     /// Current round HotShot is in
     pub cur_view: TYPES::View,
@@ -171,10 +191,18 @@ pub struct ViewSyncReplicaTaskState<TYPES: NodeType, V: Versions> {
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Metrics of the relays' outcomes
+    pub metrics: Arc<ConsensusMetricsValue>,
+
+    /// Storage for the latest view sync certificate
+    pub storage: Arc<RwLock<I::Storage>>,
 }
 
 #[async_trait]
-impl<TYPES: NodeType, V: Versions> TaskState for ViewSyncReplicaTaskState<TYPES, V> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
+    for ViewSyncReplicaTaskState<TYPES, I, V>
+{
     type Event = HotShotEvent<TYPES>;
 
     async fn handle_event(
@@ -191,7 +219,7 @@ impl<TYPES: NodeType, V: Versions> TaskState for ViewSyncReplicaTaskState<TYPES,
     fn cancel_subtasks(&mut self) {}
 }
 
-impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ViewSyncTaskState<TYPES, I, V> {
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view), name = "View Sync Main Task", level = "error")]
     #[allow(clippy::type_complexity)]
     /// Handles incoming events for the main view sync task
@@ -227,7 +255,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
         }
 
         // We do not have a replica task already running, so start one
-        let mut replica_state: ViewSyncReplicaTaskState<TYPES, V> = ViewSyncReplicaTaskState {
+        let mut replica_state: ViewSyncReplicaTaskState<TYPES, I, V> = ViewSyncReplicaTaskState {
             cur_view: view,
             next_view: view,
             cur_epoch: self.cur_epoch,
@@ -238,9 +266,11 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
             membership: Arc::clone(&self.membership),
             public_key: self.public_key.clone(),
             private_key: self.private_key.clone(),
-            view_sync_timeout: self.view_sync_timeout,
+            relay_timeout: self.relay_timeout,
             id: self.id,
             upgrade_lock: self.upgrade_lock.clone(),
+            metrics: Arc::clone(&self.metrics),
+            storage: Arc::clone(&self.storage),
         };
 
         let result = replica_state
@@ -255,6 +285,36 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
         task_map.insert(view, replica_state);
     }
 
+    /// Resume view sync from the certificate loaded from storage, if it is for a round we have
+    /// not passed yet
+    async fn resume_from_restored_certificate(
+        &mut self,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let Some(certificate) = self.restored_certificate.take() else {
+            return;
+        };
+
+        let round = certificate.round();
+        if round < self.cur_view {
+            tracing::debug!("Discarding the restored view sync certificate for past round {round}");
+            return;
+        }
+
+        tracing::info!("Resuming view sync for round {round} from the restored certificate");
+        let event = match certificate {
+            ViewSyncCertificate::PreCommit(cert) => {
+                HotShotEvent::ViewSyncPreCommitCertificateRecv(cert)
+            }
+            ViewSyncCertificate::Commit(cert) => HotShotEvent::ViewSyncCommitCertificateRecv(cert),
+            ViewSyncCertificate::Finalize(cert) => {
+                HotShotEvent::ViewSyncFinalizeCertificateRecv(cert)
+            }
+        };
+        self.send_to_or_create_replica(Arc::new(event), round, event_stream)
+            .await;
+    }
+
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = *self.cur_epoch), name = "View Sync Main Task", level = "error")]
     #[allow(clippy::type_complexity)]
     /// Handles incoming events for the main view sync task
@@ -263,6 +323,8 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
         event: Arc<HotShotEvent<TYPES>>,
         event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     ) -> Result<()> {
+        self.resume_from_restored_certificate(&event_stream).await;
+
         match event.as_ref() {
             HotShotEvent::ViewSyncPreCommitCertificateRecv(certificate) => {
                 tracing::debug!("Received view sync cert for phase {:?}", certificate);
@@ -311,11 +373,12 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
 
                 // We do not have a relay task already running, so start one
                 ensure!(
-                    self.membership
-                        .read()
-                        .await
-                        .leader(vote_view + relay, self.cur_epoch)?
-                        == self.public_key,
+                    view_sync_relay::<TYPES>(
+                        &*self.membership.read().await,
+                        vote_view,
+                        relay,
+                        self.cur_epoch
+                    )? == self.public_key,
                     "View sync vote sent to wrong leader"
                 );
 
@@ -360,11 +423,12 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
 
                 // We do not have a relay task already running, so start one
                 ensure!(
-                    self.membership
-                        .read()
-                        .await
-                        .leader(vote_view + relay, self.cur_epoch)?
-                        == self.public_key,
+                    view_sync_relay::<TYPES>(
+                        &*self.membership.read().await,
+                        vote_view,
+                        relay,
+                        self.cur_epoch
+                    )? == self.public_key,
                     debug!("View sync vote sent to wrong leader")
                 );
 
@@ -409,11 +473,12 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
 
                 // We do not have a relay task already running, so start one
                 ensure!(
-                    self.membership
-                        .read()
-                        .await
-                        .leader(vote_view + relay, self.cur_epoch)?
-                        == self.public_key,
+                    view_sync_relay::<TYPES>(
+                        &*self.membership.read().await,
+                        vote_view,
+                        relay,
+                        self.cur_epoch
+                    )? == self.public_key,
                     debug!("View sync vote sent to wrong leader")
                 );
 
@@ -452,6 +517,9 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     self.cur_view = new_view;
                     self.next_view = self.cur_view;
                     self.num_timeouts_tracked = 0;
+                    self.view_tracker
+                        .update(TrackedTask::ViewSync, new_view)
+                        .await?;
 
                     // Garbage collect old tasks
                     // We could put this into a separate async task, but that would require making several fields on ViewSyncTaskState thread-safe and harm readability.  In the common case this will have zero tasks to clean up.
@@ -475,7 +543,10 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                             .remove_entry(&TYPES::View::new(i));
                     }
 
-                    self.last_garbage_collected_view = self.cur_view - 1;
+                    self.last_garbage_collected_view = self
+                        .cur_view
+                        .checked_sub(1)
+                        .unwrap_or_else(TYPES::View::genesis);
                 }
             }
             &HotShotEvent::Timeout(view_number, ..) => {
@@ -515,6 +586,9 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                 } else {
                     // If this is the first timeout we've seen advance to the next view
                     self.cur_view = view_number + 1;
+                    self.view_tracker
+                        .update(TrackedTask::ViewSync, self.cur_view)
+                        .await?;
                     broadcast_event(
                         Arc::new(HotShotEvent::ViewChange(self.cur_view, self.cur_epoch)),
                         &event_stream,
@@ -529,7 +603,30 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
     }
 }
 
-impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
+    ViewSyncReplicaTaskState<TYPES, I, V>
+{
+    /// Count a relay which either formed a certificate or timed out
+    fn record_relay_outcome(&self, outcome: &str) {
+        self.metrics
+            .view_sync_relay_outcomes
+            .create(vec![outcome.to_string()])
+            .add(1);
+    }
+
+    /// Persist a valid certificate, so that we can resume view sync from it after a restart
+    async fn persist_certificate(&self, certificate: ViewSyncCertificate<TYPES>) {
+        if let Err(e) = self
+            .storage
+            .write()
+            .await
+            .update_view_sync_certificate(&certificate)
+            .await
+        {
+            tracing::warn!("Failed to persist the view sync certificate: {e}");
+        }
+    }
+
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = *self.cur_epoch), name = "View Sync Replica Task", level = "error")]
     /// Handle incoming events for the view sync replica task
     pub async fn handle(
@@ -577,6 +674,9 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 if certificate.data().relay > self.relay {
                     self.relay = certificate.data().relay;
                 }
+                self.record_relay_outcome("certified");
+                self.persist_certificate(ViewSyncCertificate::PreCommit(certificate.clone()))
+                    .await;
 
                 let Ok(vote) = ViewSyncCommitVote2::<TYPES>::create_signed_vote(
                     ViewSyncCommitData2 {
@@ -610,7 +710,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     let phase = last_seen_certificate;
                     let relay = self.relay;
                     let next_view = self.next_view;
-                    let timeout = self.view_sync_timeout;
+                    let timeout = self.relay_timeout;
                     async move {
                         sleep(timeout).await;
                         tracing::warn!("Vote sending timed out in ViewSyncPreCommitCertificateRecv, Relay = {}", relay);
@@ -667,6 +767,9 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 if certificate.data().relay > self.relay {
                     self.relay = certificate.data().relay;
                 }
+                self.record_relay_outcome("certified");
+                self.persist_certificate(ViewSyncCertificate::Commit(certificate.clone()))
+                    .await;
 
                 let Ok(vote) = ViewSyncFinalizeVote2::<TYPES>::create_signed_vote(
                     ViewSyncFinalizeData2 {
@@ -711,7 +814,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     let phase = last_seen_certificate;
                     let relay = self.relay;
                     let next_view = self.next_view;
-                    let timeout = self.view_sync_timeout;
+                    let timeout = self.relay_timeout;
                     async move {
                         sleep(timeout).await;
                         tracing::warn!(
@@ -768,6 +871,9 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 if certificate.data().relay > self.relay {
                     self.relay = certificate.data().relay;
                 }
+                self.record_relay_outcome("certified");
+                self.persist_certificate(ViewSyncCertificate::Finalize(certificate.clone()))
+                    .await;
 
                 if let Some(timeout_task) = self.timeout_task.take() {
                     timeout_task.abort();
@@ -817,7 +923,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     let stream = event_stream.clone();
                     let relay = self.relay;
                     let next_view = self.next_view;
-                    let timeout = self.view_sync_timeout;
+                    let timeout = self.relay_timeout;
                     async move {
                         sleep(timeout).await;
                         tracing::warn!("Vote sending timed out in ViewSyncTrigger");
@@ -843,6 +949,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     if let Some(timeout_task) = self.timeout_task.take() {
                         timeout_task.abort();
                     }
+                    self.record_relay_outcome("timed_out");
                     self.relay += 1;
                     match last_seen_certificate {
                         ViewSyncPhase::None | ViewSyncPhase::PreCommit | ViewSyncPhase::Commit => {
@@ -879,7 +986,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                         let stream = event_stream.clone();
                         let relay = self.relay;
                         let next_view = self.next_view;
-                        let timeout = self.view_sync_timeout;
+                        let timeout = self.relay_timeout;
                         let last_cert = last_seen_certificate.clone();
                         async move {
                             sleep(timeout).await;
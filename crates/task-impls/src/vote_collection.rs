@@ -17,6 +17,7 @@ use async_trait::async_trait;
 use either::Either::{self, Left, Right};
 use hotshot_types::{
     message::UpgradeLock,
+    peer_score::Misbehavior,
     simple_certificate::{
         DaCertificate2, NextEpochQuorumCertificate2, QuorumCertificate, QuorumCertificate2,
         TimeoutCertificate2, UpgradeCertificate, ViewSyncCommitCertificate2,
@@ -131,10 +132,21 @@ impl<
             "No accumulator to handle vote with. This shouldn't happen."
         ))?;
 
-        match accumulator
+        let outcome = accumulator
             .accumulate(vote, &self.membership, sender_epoch)
-            .await
-        {
+            .await;
+        for signer in accumulator.equivocators.drain(..) {
+            broadcast_event(
+                Arc::new(HotShotEvent::PeerMisbehaved(
+                    signer,
+                    Misbehavior::Equivocation,
+                )),
+                event_stream,
+            )
+            .await;
+        }
+
+        match outcome {
             Either::Left(()) => Ok(None),
             Either::Right(cert) => {
                 tracing::debug!("Certificate Formed! {:?}", cert);
@@ -226,6 +238,7 @@ where
         signers: HashMap::new(),
         phantom: PhantomData,
         upgrade_lock,
+        equivocators: Vec::new(),
     };
 
     let mut state = VoteCollectionTaskState::<TYPES, VOTE, CERT, V> {
@@ -620,7 +633,12 @@ impl<TYPES: NodeType, V: Versions>
     ) -> Result<Option<TimeoutCertificate2<TYPES>>> {
         match event.as_ref() {
             HotShotEvent::TimeoutVoteRecv(vote) => {
-                self.accumulate_vote(vote, self.epoch, sender).await
+                let certificate = self.accumulate_vote(vote, self.epoch, sender).await?;
+                if certificate.is_none() {
+                    self.broadcast_progress(sender).await;
+                }
+
+                Ok(certificate)
             }
             _ => Ok(None),
         }
@@ -630,6 +648,31 @@ impl<TYPES: NodeType, V: Versions>
     }
 }
 
+impl<TYPES: NodeType, V: Versions> TimeoutVoteState<TYPES, V> {
+    /// Let the next leader's tasks know how close the timeout votes are to a certificate, so that
+    /// they can prepare the view's proposal before the certificate forms
+    async fn broadcast_progress(&self, sender: &Sender<Arc<HotShotEvent<TYPES>>>) {
+        let Some(accumulator) = &self.accumulator else {
+            return;
+        };
+        let threshold = TimeoutCertificate2::<TYPES>::threshold(
+            &*self.membership.read().await,
+            self.view,
+            self.epoch,
+        );
+
+        broadcast_event(
+            Arc::new(HotShotEvent::TimeoutVoteProgress(
+                self.view,
+                self.epoch,
+                accumulator.percent_of_threshold(threshold),
+            )),
+            sender,
+        )
+        .await;
+    }
+}
+
 #[async_trait]
 impl<TYPES: NodeType, V: Versions>
     HandleVoteEvent<TYPES, ViewSyncPreCommitVote2<TYPES>, ViewSyncPreCommitCertificate2<TYPES>>
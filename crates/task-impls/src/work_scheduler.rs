@@ -0,0 +1,300 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Budgeting of background work within each view.
+//!
+//! VID encoding, applying blocks to the validated state and storage flushes all take CPU time
+//! away from receiving proposals and voting on them. The [`WorkScheduler`] gives background work a
+//! share of each view's timeout as its budget, and charges every piece of work it runs to the
+//! budget of the view it ran in. Work that consensus is waiting on runs straight away; low
+//! priority work is deferred to the next view once the budget is spent or the view deadline is
+//! near. It also records how long each [`WorkClass`] of work takes, and how much of it is waiting
+//! for a later view.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    future::Future,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use hotshot_types::{consensus::ConsensusMetricsValue, BackgroundWorkBudget};
+use tokio::sync::Notify;
+
+/// The class of a piece of background work, which decides whether it may be deferred
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WorkClass {
+    /// Applying a proposed block to the validated state, which our vote waits on
+    StateApplication,
+    /// VID encoding of a block we propose, which our proposal waits on
+    VidEncoding,
+    /// VID encoding of a block from its DA proposal, in case the leader's share does not reach us
+    OptimisticVidEncoding,
+    /// VID encoding of our own share of a block under full replication, where the leader sends
+    /// no shares, so our vote waits on it
+    ReplicatedVidEncoding,
+    /// Storage writes nothing is waiting on
+    StorageFlush,
+}
+
+impl WorkClass {
+    /// Every class of background work
+    pub const ALL: [Self; 5] = [
+        Self::StateApplication,
+        Self::VidEncoding,
+        Self::OptimisticVidEncoding,
+        Self::ReplicatedVidEncoding,
+        Self::StorageFlush,
+    ];
+
+    /// Whether work of this class is low priority, and may be deferred to the next view
+    #[must_use]
+    pub fn is_deferrable(self) -> bool {
+        matches!(self, Self::OptimisticVidEncoding | Self::StorageFlush)
+    }
+}
+
+impl Display for WorkClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::StateApplication => "state_application",
+            Self::VidEncoding => "vid_encoding",
+            Self::OptimisticVidEncoding => "optimistic_vid_encoding",
+            Self::ReplicatedVidEncoding => "replicated_vid_encoding",
+            Self::StorageFlush => "storage_flush",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Background work run for one class
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorkStats {
+    /// Number of pieces of work run
+    pub runs: u64,
+    /// Number of them which were deferred to a later view first
+    pub deferrals: u64,
+    /// Time the work took to run, summed over the runs
+    pub total_time: Duration,
+    /// Longest time a piece of work took to run
+    pub max_time: Duration,
+}
+
+/// The view background work is currently charged to
+#[derive(Debug)]
+struct ViewBudget {
+    /// The view
+    view: u64,
+    /// When the view times out, or `None` before the first view starts
+    deadline: Option<Instant>,
+    /// Time background work may still take up in the view
+    remaining: Duration,
+}
+
+impl ViewBudget {
+    /// Whether low priority work should wait for the next view
+    fn is_exhausted(&self, deadline_margin: Duration) -> bool {
+        self.remaining.is_zero()
+            || self.deadline.is_some_and(|deadline| {
+                deadline.saturating_duration_since(Instant::now()) <= deadline_margin
+            })
+    }
+}
+
+/// Runs background work within a budget of each view, deferring low priority work once the budget
+/// is spent or the view deadline is near
+#[derive(Debug)]
+pub struct WorkScheduler {
+    /// The budget of each view, or `None` to run everything immediately
+    budget: Option<BackgroundWorkBudget>,
+    /// The view work is charged to
+    view: Mutex<ViewBudget>,
+    /// Woken whenever a new view starts
+    view_started: Notify,
+    /// Number of pieces of work waiting for a later view, per class
+    deferred: Mutex<HashMap<WorkClass, usize>>,
+    /// Work run so far, per class
+    stats: Mutex<HashMap<WorkClass, WorkStats>>,
+    /// Metrics the run times and the deferred backlog are published to
+    metrics: Arc<ConsensusMetricsValue>,
+}
+
+/// Counts a piece of work as deferred until it runs, or is dropped because the task running it
+/// was cancelled
+struct DeferredWork<'a> {
+    /// The scheduler the work is deferred by
+    scheduler: &'a WorkScheduler,
+    /// The class of the work
+    class: WorkClass,
+}
+
+impl<'a> DeferredWork<'a> {
+    /// Count a piece of work of `class` as deferred
+    fn new(scheduler: &'a WorkScheduler, class: WorkClass) -> Self {
+        scheduler.update_backlog(class, true);
+        Self { scheduler, class }
+    }
+}
+
+impl Drop for DeferredWork<'_> {
+    fn drop(&mut self) {
+        self.scheduler.update_backlog(self.class, false);
+    }
+}
+
+impl Default for WorkScheduler {
+    fn default() -> Self {
+        Self::new(None, Arc::default())
+    }
+}
+
+impl WorkScheduler {
+    /// Create a scheduler which runs background work within `budget` of each view, publishing run
+    /// times and the deferred backlog to `metrics`
+    #[must_use]
+    pub fn new(budget: Option<BackgroundWorkBudget>, metrics: Arc<ConsensusMetricsValue>) -> Self {
+        Self {
+            budget,
+            view: Mutex::new(ViewBudget {
+                view: 0,
+                deadline: None,
+                remaining: Duration::MAX,
+            }),
+            view_started: Notify::new(),
+            deferred: Mutex::new(HashMap::new()),
+            stats: Mutex::new(HashMap::new()),
+            metrics,
+        }
+    }
+
+    /// The budget of each view, if background work is budgeted
+    #[must_use]
+    pub fn budget(&self) -> Option<BackgroundWorkBudget> {
+        self.budget
+    }
+
+    /// Lock the view, which is never left inconsistent by a panic
+    fn lock_view(&self) -> std::sync::MutexGuard<'_, ViewBudget> {
+        self.view.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Start charging work to `view`, which times out after `timeout`, and run the work deferred
+    /// from earlier views. Views older than the current one are ignored.
+    pub fn start_view(&self, view: u64, timeout: Duration) {
+        {
+            let mut current = self.lock_view();
+            if view <= current.view && current.deadline.is_some() {
+                return;
+            }
+            *current = ViewBudget {
+                view,
+                deadline: Some(Instant::now() + timeout),
+                remaining: self.budget.map_or(Duration::MAX, |budget| {
+                    timeout * u32::from(budget.percent_of_view) / 100
+                }),
+            };
+        }
+        self.view_started.notify_waiters();
+    }
+
+    /// Time background work may still take up in the current view
+    #[must_use]
+    pub fn remaining_budget(&self) -> Duration {
+        self.lock_view().remaining
+    }
+
+    /// Run `work` of class `class`, deferring it to the next view first if it is low priority and
+    /// the current view has no room for it
+    pub async fn run<F: Future>(&self, class: WorkClass, work: F) -> F::Output {
+        let deferred = match self.budget {
+            Some(budget) if class.is_deferrable() => {
+                self.wait_for_budget(class, budget.deadline_margin).await
+            }
+            _ => false,
+        };
+        let started = Instant::now();
+        let output = work.await;
+        self.record(class, deferred, started.elapsed());
+
+        output
+    }
+
+    /// Wait until the current view has room for low priority work, or the next view starts.
+    /// Returns whether the work had to wait.
+    async fn wait_for_budget(&self, class: WorkClass, deadline_margin: Duration) -> bool {
+        let queued_view = self.lock_view().view;
+        let mut deferred = None;
+        loop {
+            // Registered before the view is checked, so no view starting in between is missed
+            let view_started = self.view_started.notified();
+            {
+                let view = self.lock_view();
+                // Work waits for one view at most, so that it is not starved by short views
+                if view.view > queued_view || !view.is_exhausted(deadline_margin) {
+                    return deferred.is_some();
+                }
+            }
+            if deferred.is_none() {
+                deferred = Some(DeferredWork::new(self, class));
+            }
+            view_started.await;
+        }
+    }
+
+    /// Count a piece of work of `class` as deferred, or as no longer deferred
+    fn update_backlog(&self, class: WorkClass, deferred: bool) {
+        let mut backlog = self.deferred.lock().unwrap_or_else(PoisonError::into_inner);
+        let backlog = backlog.entry(class).or_default();
+        if deferred {
+            *backlog += 1;
+        } else {
+            *backlog = backlog.saturating_sub(1);
+        }
+        self.metrics
+            .deferred_background_work
+            .create(vec![class.to_string()])
+            .set(*backlog);
+    }
+
+    /// Record a piece of work that ran, charging it to the current view
+    fn record(&self, class: WorkClass, deferred: bool, elapsed: Duration) {
+        {
+            let mut view = self.lock_view();
+            view.remaining = view.remaining.saturating_sub(elapsed);
+        }
+        {
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+            let stats = stats.entry(class).or_default();
+            stats.runs += 1;
+            stats.deferrals += u64::from(deferred);
+            stats.total_time += elapsed;
+            stats.max_time = stats.max_time.max(elapsed);
+        }
+        self.metrics
+            .background_work_time
+            .create(vec![class.to_string()])
+            .add_point(elapsed.as_secs_f64());
+    }
+
+    /// Number of pieces of work waiting for a later view, per class
+    #[must_use]
+    pub fn deferred(&self) -> HashMap<WorkClass, usize> {
+        self.deferred
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Work run so far, per class
+    #[must_use]
+    pub fn stats(&self) -> HashMap<WorkClass, WorkStats> {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
@@ -10,5 +10,7 @@
 pub mod dependency;
 /// Task which can uses dependencies
 pub mod dependency_task;
+/// Tasks which handle the events of distinct views concurrently
+pub mod sharded;
 /// Basic task types
 pub mod task;
@@ -0,0 +1,172 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Tasks which handle the events of distinct views concurrently.
+//!
+//! A [`Task`](crate::task::Task) handles its events one at a time, so a slow handler for an old
+//! view holds up the events of the current one. A [`ViewSharded`] task instead hands each event
+//! which only concerns a single view to a subtask for that view, and keeps handling events while
+//! the subtask runs. The ordering guarantees are:
+//!
+//! - events for the same view are handled in the order they were received,
+//! - events for distinct views may be handled concurrently, and
+//! - an event which does not belong to a single view is handled after every event received before
+//!   it has been handled, and before any event received after it is, unless
+//! - it is a shared event, such as a view change, which is handled as soon as it is received,
+//!   while the events of earlier views are still being handled, and before any event received
+//!   after it is.
+//!
+//! View-local and shared handlers only get shared access to the task state, so anything they
+//! update must stay behind a lock or in an atomic, as it would be for any other task.
+
+use std::{collections::HashMap, fmt::Debug, hash::Hash, sync::Arc};
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use tokio::task::{spawn, JoinHandle};
+use utils::anytrace::Result;
+
+use crate::task::{TaskEvent, TaskState};
+
+#[async_trait]
+/// Type for task state whose events for distinct views can be handled concurrently
+pub trait ViewShardedTaskState: Send + Sync + 'static {
+    /// Type of event sent and received by the task
+    type Event: TaskEvent + Clone + Send + Sync + 'static;
+
+    /// Type of the views events are sharded by
+    type View: Copy + Eq + Hash + Debug + Send + Sync + 'static;
+
+    /// The view `event` belongs to, if handling it only needs shared access to the state
+    fn event_view(&self, event: &Self::Event) -> Option<Self::View>;
+
+    /// Handles an event which belongs to `view`, concurrently with the events of other views
+    async fn handle_view_event(
+        &self,
+        view: Self::View,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+    ) -> Result<()>;
+
+    /// Whether `event`, which does not belong to a single view, can be handled with shared access
+    /// to the state, without waiting for the events of earlier views
+    fn is_shared_event(&self, _event: &Self::Event) -> bool {
+        false
+    }
+
+    /// Handles an event for which [`is_shared_event`](Self::is_shared_event) holds, concurrently
+    /// with the events of earlier views
+    async fn handle_shared_event(
+        &self,
+        _event: Arc<Self::Event>,
+        _sender: &Sender<Arc<Self::Event>>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Handles any other event which does not belong to a single view, with exclusive access to
+    /// the state
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()>;
+
+    /// Joins all subtasks.
+    fn cancel_subtasks(&mut self);
+}
+
+/// Runs a [`ViewShardedTaskState`] as a [`TaskState`], handling the events of each view in a
+/// subtask of its own
+pub struct ViewSharded<S: ViewShardedTaskState> {
+    /// The wrapped state, read by view-local and shared handlers and written by the others
+    state: Arc<RwLock<S>>,
+    /// The subtask handling the latest event of each view, until it finishes
+    in_flight: HashMap<S::View, JoinHandle<()>>,
+}
+
+impl<S: ViewShardedTaskState> ViewSharded<S> {
+    /// Wrap `state`, so that its events are handled per view
+    pub fn new(state: S) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(state)),
+            in_flight: HashMap::new(),
+        }
+    }
+
+    /// The wrapped state
+    #[must_use]
+    pub fn state(&self) -> &Arc<RwLock<S>> {
+        &self.state
+    }
+
+    /// Number of views with events still being handled
+    #[must_use]
+    pub fn views_in_flight(&self) -> usize {
+        self.in_flight
+            .values()
+            .filter(|handle| !handle.is_finished())
+            .count()
+    }
+}
+
+#[async_trait]
+impl<S: ViewShardedTaskState> TaskState for ViewSharded<S> {
+    type Event = S::Event;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.in_flight.retain(|_, handle| !handle.is_finished());
+
+        // The read guard is taken before the subtask is spawned and held until it is done, so an
+        // exclusive handler for a later event waits for it
+        let state = self.state.read_arc().await;
+        let Some(view) = state.event_view(&event) else {
+            // Shared events run alongside the subtasks, but are done before the next event is taken
+            if state.is_shared_event(&event) {
+                return state.handle_shared_event(event, sender).await;
+            }
+            drop(state);
+            return self
+                .state
+                .write()
+                .await
+                .handle_event(event, sender, receiver)
+                .await;
+        };
+
+        let previous = self.in_flight.remove(&view);
+        let sender = sender.clone();
+        let handle = spawn(async move {
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            let _ = state
+                .handle_view_event(view, event, &sender)
+                .await
+                .inspect_err(|e| tracing::debug!("{e}"));
+        });
+        self.in_flight.insert(view, handle);
+
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {
+        for (_, handle) in self.in_flight.drain() {
+            handle.abort();
+        }
+        match self.state.try_write() {
+            Some(mut state) => state.cancel_subtasks(),
+            None => tracing::warn!("View-local handlers still running, not cancelling subtasks"),
+        }
+    }
+}
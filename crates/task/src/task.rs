@@ -4,7 +4,7 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use async_broadcast::{Receiver, RecvError, Sender};
 use async_trait::async_trait;
@@ -104,6 +104,8 @@ impl<S: TaskState + Send + 'static> Task<S> {
 pub struct ConsensusTaskRegistry<EVENT> {
     /// Tasks this registry controls
     task_handles: Vec<JoinHandle<Box<dyn TaskState<Event = EVENT>>>>,
+    /// Tasks this registry controls which can be stopped individually, by name
+    named_task_handles: HashMap<&'static str, JoinHandle<Box<dyn TaskState<Event = EVENT>>>>,
 }
 
 impl<EVENT: Send + Sync + Clone + TaskEvent> ConsensusTaskRegistry<EVENT> {
@@ -112,6 +114,7 @@ impl<EVENT: Send + Sync + Clone + TaskEvent> ConsensusTaskRegistry<EVENT> {
     pub fn new() -> Self {
         ConsensusTaskRegistry {
             task_handles: vec![],
+            named_task_handles: HashMap::new(),
         }
     }
     /// Add a task to the registry
@@ -125,6 +128,7 @@ impl<EVENT: Send + Sync + Clone + TaskEvent> ConsensusTaskRegistry<EVENT> {
     /// Should not panic, unless awaiting on the JoinHandle in tokio fails.
     pub async fn shutdown(&mut self) {
         let handles = &mut self.task_handles;
+        handles.extend(self.named_task_handles.drain().map(|(_, handle)| handle));
 
         while let Some(handle) = handles.pop() {
             let mut task_state = handle.await.unwrap();
@@ -140,10 +144,32 @@ impl<EVENT: Send + Sync + Clone + TaskEvent> ConsensusTaskRegistry<EVENT> {
         self.register(task.run());
     }
 
+    /// Take a task, run it, and register it under `name`, aborting any task previously
+    /// registered under the same name
+    pub fn run_named_task<S>(&mut self, name: &'static str, task: Task<S>)
+    where
+        S: TaskState<Event = EVENT> + Send + 'static,
+    {
+        if let Some(previous) = self.named_task_handles.insert(name, task.run()) {
+            previous.abort();
+        }
+    }
+
+    /// Abort the task registered under `name`, without cancelling its subtasks.
+    /// Returns whether such a task was running.
+    pub fn abort_named_task(&mut self, name: &str) -> bool {
+        self.named_task_handles
+            .remove(name)
+            .inspect(JoinHandle::abort)
+            .is_some()
+    }
+
     /// Wait for the results of all the tasks registered
     /// # Panics
     /// Panics if one of the tasks panicked
-    pub async fn join_all(self) -> Vec<Box<dyn TaskState<Event = EVENT>>> {
+    pub async fn join_all(mut self) -> Vec<Box<dyn TaskState<Event = EVENT>>> {
+        self.task_handles
+            .extend(self.named_task_handles.into_values());
         try_join_all(self.task_handles).await.unwrap()
     }
 }
@@ -0,0 +1,90 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Exports stored golden traces as transition logs, and checks them for conformance with the
+//! specification.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use hotshot_testing::conformance::{check, export, parse_log, render_log, TransitionLog};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+/// Conformance checks of recorded consensus traces
+struct Args {
+    /// What to do
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Convert a golden trace into a transition log
+    Export {
+        /// The golden trace
+        trace: PathBuf,
+        /// Where to write the transition log. If not provided, it is written to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check golden traces or transition logs against the specification
+    Check {
+        /// The traces to check. Files ending in `.jsonl` are read as transition logs, and all
+        /// other files as golden traces
+        #[arg(required = true)]
+        traces: Vec<PathBuf>,
+    },
+}
+
+/// Read a golden trace or a transition log as a transition log
+fn load(path: &PathBuf) -> Result<TransitionLog> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let log = if path
+        .extension()
+        .is_some_and(|extension| extension == "jsonl")
+    {
+        parse_log(&text)
+    } else {
+        export(&text)
+    };
+
+    log.with_context(|| format!("failed to load {}", path.display()))
+}
+
+fn main() -> Result<()> {
+    match Args::parse().command {
+        Command::Export { trace, output } => {
+            let log = render_log(&load(&trace)?);
+            match output {
+                Some(output) => fs::write(&output, log)
+                    .with_context(|| format!("failed to write {}", output.display()))?,
+                None => print!("{log}"),
+            }
+        }
+        Command::Check { traces } => {
+            let mut failures = 0;
+            for path in &traces {
+                match load(path).and_then(|log| Ok(check(&log)?)) {
+                    Ok(()) => println!("{}: ok", path.display()),
+                    Err(err) => {
+                        println!("{}: {err:#}", path.display());
+                        failures += 1;
+                    }
+                }
+            }
+            anyhow::ensure!(
+                failures == 0,
+                "{failures} of {} traces do not conform",
+                traces.len()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -39,6 +39,9 @@ pub use random::RandomBuilderImplementation;
 pub mod simple;
 pub use simple::SimpleBuilderImplementation;
 
+pub mod scripted;
+pub use scripted::{BuilderBehaviour, ScriptedBuilderImplementation};
+
 #[async_trait]
 pub trait TestBuilderImplementation<TYPES: NodeType>
 where
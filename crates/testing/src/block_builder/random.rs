@@ -205,7 +205,7 @@ where
                                             handle.abort();
                                         }
                                     }
-                                    BuilderChange::FailClaims(_) => {}
+                                    BuilderChange::FailClaims(_) | BuilderChange::Behave(_) => {}
                                 }
                                 let _ = self.change_sender.broadcast(change).await;
                             }
@@ -0,0 +1,319 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A builder which misbehaves on cue.
+//!
+//! The scripted builder serves the same blocks as the [simple builder](super::simple), except in
+//! the views where the test scripts a [`BuilderBehaviour`] for it with [`BuilderChange::Behave`].
+//! A behaviour applies to every request for a view from the view it is scripted at until the next
+//! scripted behaviour, so the builder acts the same way however fast the test runs. Intermittent
+//! unavailability is scripted by alternating [`BuilderBehaviour::Unavailable`] and
+//! [`BuilderBehaviour::Honest`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_broadcast::broadcast;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use hotshot::types::SignatureKey;
+use hotshot_builder_api::{
+    v0_1::{
+        self,
+        block_info::{AvailableBlockData, AvailableBlockHeaderInput, AvailableBlockInfo},
+        builder::BuildError,
+        data_source::BuilderDataSource as _,
+    },
+    v0_99::{self, data_source::BuilderDataSource as _},
+};
+use hotshot_types::{
+    bundle::Bundle,
+    traits::{
+        block_contents::BuilderFee, node_implementation::NodeType,
+        signature_key::BuilderSignatureKey,
+    },
+    utils::BuilderCommitment,
+    vid::VidCommitment,
+};
+use tide_disco::{method::ReadState, Url};
+use tokio::time::sleep;
+
+use super::{
+    build_block, run_builder_source, simple::SimpleBuilderSource, BuilderTask,
+    SimpleBuilderImplementation, TestBuilderImplementation,
+};
+use crate::test_builder::BuilderChange;
+
+/// How the scripted builder responds to requests for a view
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum BuilderBehaviour {
+    /// Respond like the simple builder
+    #[default]
+    Honest,
+    /// Respond like the simple builder, but only after a delay
+    Slow(Duration),
+    /// Offer blocks without any transactions, even if there are transactions to include
+    EmptyBlocks,
+    /// Offer the simple builder's blocks, but advertise them as this many bytes large
+    OversizedBlocks(u64),
+    /// Sign the fee with the wrong message, so that the fee signature doesn't verify
+    InvalidFeeSignature,
+    /// Fail every request
+    Unavailable,
+}
+
+/// Builder which follows the [`BuilderBehaviour`]s scripted in its changes, see the
+/// [module documentation](self)
+pub struct ScriptedBuilderImplementation;
+
+#[async_trait]
+impl<TYPES: NodeType> TestBuilderImplementation<TYPES> for ScriptedBuilderImplementation
+where
+    <TYPES as NodeType>::InstanceState: Default,
+{
+    type Config = ();
+
+    async fn start(
+        num_nodes: usize,
+        url: Url,
+        _config: Self::Config,
+        changes: HashMap<u64, BuilderChange>,
+    ) -> Box<dyn BuilderTask<TYPES>> {
+        let script = changes
+            .iter()
+            .filter_map(|(view, change)| match change {
+                BuilderChange::Behave(behaviour) => Some((*view, behaviour.clone())),
+                _ => None,
+            })
+            .collect();
+
+        let (change_sender, change_receiver) = broadcast(128);
+        let (source, task) =
+            SimpleBuilderImplementation::create(num_nodes, changes, change_sender).await;
+        let source = ScriptedBuilderSource {
+            inner: source,
+            script: Arc::new(script),
+        };
+        run_builder_source(url, change_receiver, source);
+
+        Box::new(task)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScriptedBuilderSource<TYPES: NodeType> {
+    /// the builder whose responses are altered
+    inner: SimpleBuilderSource<TYPES>,
+    /// view -> behaviour from that view on
+    script: Arc<BTreeMap<u64, BuilderBehaviour>>,
+}
+
+impl<TYPES: NodeType> ScriptedBuilderSource<TYPES> {
+    /// The behaviour scripted for `view_number`
+    fn behaviour(&self, view_number: u64) -> &BuilderBehaviour {
+        self.script
+            .range(..=view_number)
+            .next_back()
+            .map_or(&BuilderBehaviour::Honest, |(_, behaviour)| behaviour)
+    }
+
+    /// Stall or fail a request for `view_number` as scripted, returning the behaviour for the view
+    async fn respond(&self, view_number: u64) -> Result<&BuilderBehaviour, BuildError> {
+        let behaviour = self.behaviour(view_number);
+        match behaviour {
+            BuilderBehaviour::Slow(delay) => sleep(*delay).await,
+            BuilderBehaviour::Unavailable => {
+                return Err(BuildError::Error(format!(
+                    "Builder is scripted to be unavailable in view {view_number}"
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(behaviour)
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> ReadState for ScriptedBuilderSource<TYPES> {
+    type State = Self;
+
+    async fn read<T>(
+        &self,
+        op: impl Send + for<'a> FnOnce(&'a Self::State) -> BoxFuture<'a, T> + 'async_trait,
+    ) -> T {
+        op(self).await
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> v0_99::data_source::BuilderDataSource<TYPES> for ScriptedBuilderSource<TYPES>
+where
+    <TYPES as NodeType>::InstanceState: Default,
+{
+    async fn bundle(
+        &self,
+        parent_view: u64,
+        parent_hash: &VidCommitment,
+        view_number: u64,
+    ) -> Result<Bundle<TYPES>, BuildError> {
+        let behaviour = self.respond(view_number).await?;
+        let inner = &self.inner;
+
+        if *behaviour == BuilderBehaviour::EmptyBlocks {
+            let fee_amount = 1;
+            return Ok(Bundle {
+                transactions: Vec::new(),
+                signature: TYPES::BuilderSignatureKey::sign_bundle::<TYPES>(&inner.priv_key, &[])
+                    .expect("Failed to sign bundle"),
+                sequencing_fee: BuilderFee {
+                    fee_amount,
+                    fee_account: inner.pub_key.clone(),
+                    fee_signature: TYPES::BuilderSignatureKey::sign_sequencing_fee_marketplace(
+                        &inner.priv_key,
+                        fee_amount,
+                        view_number,
+                    )
+                    .expect("Failed to sign fee"),
+                },
+            });
+        }
+
+        let mut bundle = inner.bundle(parent_view, parent_hash, view_number).await?;
+        if *behaviour == BuilderBehaviour::InvalidFeeSignature {
+            // Sign the fee for the wrong view
+            bundle.sequencing_fee.fee_signature =
+                TYPES::BuilderSignatureKey::sign_sequencing_fee_marketplace(
+                    &inner.priv_key,
+                    bundle.sequencing_fee.fee_amount,
+                    view_number + 1,
+                )
+                .expect("Failed to sign fee");
+        }
+
+        Ok(bundle)
+    }
+
+    async fn builder_address(&self) -> Result<TYPES::BuilderSignatureKey, BuildError> {
+        Ok(self.inner.pub_key.clone())
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> v0_1::data_source::BuilderDataSource<TYPES> for ScriptedBuilderSource<TYPES>
+where
+    <TYPES as NodeType>::InstanceState: Default,
+{
+    async fn available_blocks(
+        &self,
+        for_parent: &VidCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuildError> {
+        let behaviour = self.respond(view_number).await?;
+        let inner = &self.inner;
+
+        match behaviour {
+            BuilderBehaviour::EmptyBlocks => {
+                let block_entry = build_block(
+                    Vec::new(),
+                    inner.num_nodes.clone(),
+                    inner.pub_key.clone(),
+                    inner.priv_key.clone(),
+                )
+                .await;
+                let metadata = block_entry.metadata.clone();
+                inner
+                    .blocks
+                    .write()
+                    .await
+                    .insert(metadata.block_hash.clone(), block_entry);
+
+                Ok(vec![metadata])
+            }
+            BuilderBehaviour::OversizedBlocks(block_size) => {
+                let blocks = inner
+                    .available_blocks(for_parent, view_number, sender, signature)
+                    .await?;
+
+                Ok(blocks
+                    .into_iter()
+                    .map(|mut block_info| {
+                        block_info.block_size = *block_size;
+                        block_info.signature = TYPES::BuilderSignatureKey::sign_block_info(
+                            &inner.priv_key,
+                            *block_size,
+                            block_info.offered_fee,
+                            &block_info.block_hash,
+                        )
+                        .expect("Failed to sign block info");
+                        block_info
+                    })
+                    .collect())
+            }
+            _ => {
+                inner
+                    .available_blocks(for_parent, view_number, sender, signature)
+                    .await
+            }
+        }
+    }
+
+    async fn claim_block(
+        &self,
+        block_hash: &BuilderCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<AvailableBlockData<TYPES>, BuildError> {
+        self.respond(view_number).await?;
+        self.inner
+            .claim_block(block_hash, view_number, sender, signature)
+            .await
+    }
+
+    async fn claim_block_with_num_nodes(
+        &self,
+        block_hash: &BuilderCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        num_nodes: usize,
+    ) -> Result<AvailableBlockData<TYPES>, BuildError> {
+        self.respond(view_number).await?;
+        self.inner
+            .claim_block_with_num_nodes(block_hash, view_number, sender, signature, num_nodes)
+            .await
+    }
+
+    async fn claim_block_header_input(
+        &self,
+        block_hash: &BuilderCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<AvailableBlockHeaderInput<TYPES>, BuildError> {
+        let behaviour = self.respond(view_number).await?;
+        let mut header_input = self
+            .inner
+            .claim_block_header_input(block_hash, view_number, sender, signature)
+            .await?;
+        if *behaviour == BuilderBehaviour::InvalidFeeSignature {
+            // A valid signature, but over the VID commitment rather than the fee
+            header_input.fee_signature = header_input.message_signature.clone();
+        }
+
+        Ok(header_input)
+    }
+
+    async fn builder_address(&self) -> Result<TYPES::BuilderSignatureKey, BuildError> {
+        Ok(self.inner.pub_key.clone())
+    }
+}
@@ -18,7 +18,11 @@ use async_broadcast::{broadcast, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use committable::{Commitment, Committable};
-use futures::{future::BoxFuture, Stream, StreamExt};
+use futures::{
+    future::BoxFuture,
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
 use hotshot::{
     traits::BlockPayload,
     types::{Event, EventType, SignatureKey},
@@ -110,12 +114,12 @@ where
 
 #[derive(Debug, Clone)]
 pub struct SimpleBuilderSource<TYPES: NodeType> {
-    pub_key: TYPES::BuilderSignatureKey,
-    priv_key: <TYPES::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
-    num_nodes: Arc<RwLock<usize>>,
+    pub(super) pub_key: TYPES::BuilderSignatureKey,
+    pub(super) priv_key: <TYPES::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
+    pub(super) num_nodes: Arc<RwLock<usize>>,
     #[allow(clippy::type_complexity)]
     transactions: Arc<RwLock<HashMap<Commitment<TYPES::Transaction>, SubmittedTransaction<TYPES>>>>,
-    blocks: Arc<RwLock<HashMap<BuilderCommitment, BlockEntry<TYPES>>>>,
+    pub(super) blocks: Arc<RwLock<HashMap<BuilderCommitment, BlockEntry<TYPES>>>>,
     should_fail_claims: Arc<AtomicBool>,
 }
 
@@ -331,12 +335,39 @@ where
     }
 }
 
+#[async_trait]
+impl<TYPES: NodeType> v0_1::data_source::StreamingBuilderDataSource<TYPES>
+    for SimpleBuilderSource<TYPES>
+where
+    <TYPES as NodeType>::InstanceState: Default,
+{
+    /// Offers the block `available_blocks` would offer, if any, and ends the stream
+    async fn available_blocks_stream(
+        &self,
+        for_parent: &VidCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<BoxStream<'static, AvailableBlockInfo<TYPES>>, BuildError> {
+        let blocks = v0_1::data_source::BuilderDataSource::available_blocks(
+            self,
+            for_parent,
+            view_number,
+            sender,
+            signature,
+        )
+        .await?;
+
+        Ok(stream::iter(blocks).boxed())
+    }
+}
+
 impl<TYPES: NodeType> SimpleBuilderSource<TYPES> {
     pub async fn run(self, url: Url)
     where
         <TYPES as NodeType>::InstanceState: Default,
     {
-        let builder_api_0_1 = hotshot_builder_api::v0_1::builder::define_api::<
+        let builder_api_0_1 = hotshot_builder_api::v0_1::builder::define_streaming_api::<
             SimpleBuilderSource<TYPES>,
             TYPES,
         >(&Options::default())
@@ -400,6 +431,7 @@ impl<TYPES: NodeType> BuilderTask<TYPES> for SimpleBuilderTask<TYPES> {
                                     BuilderChange::FailClaims(value) => {
                                         self.should_fail_claims.store(value, Ordering::Relaxed);
                                     }
+                                    BuilderChange::Behave(_) => {}
                                 }
                                 let _ = self.change_sender.broadcast(change).await;
                             }
@@ -434,6 +466,22 @@ impl<TYPES: NodeType> BuilderTask<TYPES> for SimpleBuilderTask<TYPES> {
                                 }
                             }
                         }
+                        EventType::PrioritizedTransactions { transactions }
+                            if should_build_blocks =>
+                        {
+                            let mut queue = self.transactions.write().await;
+                            for (transaction, _) in transactions {
+                                if !self.decided_transactions.contains(&transaction.commit()) {
+                                    queue.insert(
+                                        transaction.commit(),
+                                        SubmittedTransaction {
+                                            claimed: None,
+                                            transaction: transaction.clone(),
+                                        },
+                                    );
+                                }
+                            }
+                        }
                         EventType::Transactions { transactions } if should_build_blocks => {
                             let mut queue = self.transactions.write().await;
                             for transaction in transactions {
@@ -0,0 +1,84 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Stake distributions which give the adversarial nodes of a test a chosen fraction of the stake.
+//!
+//! Quorums are counted by stake, so what the adversary can do depends on its share of the stake
+//! rather than on how many nodes it runs. With the default thresholds, a quorum needs more than
+//! two thirds of the stake:
+//!
+//! - below one third, the honest nodes form quorums on their own, so consensus stays live even if
+//!   the adversary withholds every vote, and no two conflicting quorums can form, so it stays safe;
+//! - at one third or more, an adversary which withholds its votes leaves the honest nodes short of
+//!   a quorum, so no view decides, and neither timeout nor view sync certificates form to move on.
+//!   Honest nodes still never decide conflicting leaves while the adversary only withholds votes,
+//!   but an adversary which also votes for conflicting proposals can complete two conflicting
+//!   quorums once it holds a third of the stake, which breaks safety.
+
+use std::collections::{BTreeSet, HashMap};
+
+/// Whether the consensus tolerates an adversary with `numerator / denominator` of the stake, i.e.
+/// whether that is less than one third
+#[must_use]
+pub fn is_tolerated((numerator, denominator): (u64, u64)) -> bool {
+    u128::from(numerator) * 3 < u128::from(denominator)
+}
+
+/// Stakes of `num_nodes` nodes which give the `adversaries` exactly `numerator / denominator` of
+/// the total stake, split evenly among them, with the rest split evenly among the honest nodes.
+///
+/// # Panics
+/// If the adversaries are not all among the nodes, there are no adversaries or no honest nodes, or
+/// the fraction is not strictly between zero and one
+#[must_use]
+pub fn adversary_stakes(
+    num_nodes: usize,
+    adversaries: &BTreeSet<u64>,
+    (numerator, denominator): (u64, u64),
+) -> HashMap<u64, u64> {
+    let num_adversaries = adversaries.len() as u64;
+    let num_honest = (num_nodes as u64)
+        .checked_sub(num_adversaries)
+        .expect("More adversaries than nodes");
+    assert!(
+        adversaries.iter().all(|node| *node < num_nodes as u64),
+        "Adversaries outside of the {num_nodes} nodes in the network"
+    );
+    assert!(
+        num_adversaries > 0 && num_honest > 0,
+        "Need both adversarial and honest nodes"
+    );
+    assert!(
+        0 < numerator && numerator < denominator,
+        "The adversary's share of the stake must be strictly between zero and one"
+    );
+
+    // The adversaries hold `num_adversaries * adversary_stake`, and the honest nodes
+    // `num_honest * honest_stake`, which are in the ratio `numerator : denominator - numerator`
+    let adversary_stake = numerator * num_honest;
+    let honest_stake = (denominator - numerator) * num_adversaries;
+    let divisor = gcd(adversary_stake, honest_stake);
+
+    (0..num_nodes as u64)
+        .map(|node| {
+            let stake = if adversaries.contains(&node) {
+                adversary_stake
+            } else {
+                honest_stake
+            };
+            (node, stake / divisor)
+        })
+        .collect()
+}
+
+/// Greatest common divisor of `a` and `b`
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
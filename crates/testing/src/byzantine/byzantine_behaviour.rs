@@ -6,6 +6,7 @@ use std::{
 use anyhow::Context;
 use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::Committable;
 use hotshot::{
     tasks::EventTransformerState,
     types::{SignatureKey, SystemContextHandle},
@@ -19,10 +20,13 @@ use hotshot_task_impls::{
 };
 use hotshot_types::{
     consensus::{Consensus, OuterConsensus},
-    data::QuorumProposal2,
+    data::{Leaf2, QuorumProposal2},
     message::{Proposal, UpgradeLock},
     simple_vote::QuorumVote2,
-    traits::node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
+    traits::{
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
+        states::InstanceState,
+    },
 };
 
 #[derive(Debug)]
@@ -350,6 +354,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
             consensus: OuterConsensus::new(handle.consensus()),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             transmit_tasks: BTreeMap::new(),
+            resent_outbound_messages: false,
+            connection_warm_up_views: handle.hotshot.config.connection_warm_up_views,
+            message_ttl: handle.hotshot.config.message_ttl,
+            clock: handle.hotshot.instance_state().clock(),
+            known_stake_table: Arc::new(handle.hotshot.config.known_stake_keys()),
+            proposal_dispersal_min_bytes: handle.hotshot.config.proposal_dispersal_min_bytes,
+            send_scheduler: Arc::default(),
         };
         let modified_network_state = NetworkEventTaskStateModifier {
             network_event_task_state: network_state,
@@ -430,3 +441,163 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         vec![event.clone()]
     }
 }
+
+#[derive(Debug)]
+/// An `EventTransformerState` that equivocates as leader: alongside each honest `QuorumProposalSend`
+/// from `equivocate_from_view` on, it sends a second, validly signed proposal for the same view which
+/// builds on an older QC
+pub struct EquivocatingLeader<TYPES: NodeType> {
+    /// First view at which to equivocate
+    pub equivocate_from_view: u64,
+    /// Store proposals from previous views, whose QCs the conflicting proposals reuse
+    pub validated_proposals: Vec<QuorumProposal2<TYPES>>,
+}
+
+impl<TYPES: NodeType> EquivocatingLeader<TYPES> {
+    /// Create a leader which equivocates on every proposal from `equivocate_from_view` on
+    #[must_use]
+    pub fn new(equivocate_from_view: u64) -> Self {
+        Self {
+            equivocate_from_view,
+            validated_proposals: Vec::new(),
+        }
+    }
+
+    /// A proposal conflicting with `proposal`, signed with `private_key`, if we know an older QC
+    /// to build it on
+    async fn conflicting_proposal<V: Versions>(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Option<Proposal<TYPES, QuorumProposal2<TYPES>>> {
+        let older_qc = self
+            .validated_proposals
+            .iter()
+            .rev()
+            .map(|validated| &validated.justify_qc)
+            .find(|qc| qc.data.leaf_commit != proposal.data.justify_qc.data.leaf_commit)?;
+
+        let mut conflicting = proposal.clone();
+        conflicting.data.justify_qc = older_qc.clone();
+        conflicting.signature = TYPES::SignatureKey::sign(
+            private_key,
+            &upgrade_lock
+                .signing_message(
+                    Leaf2::from_quorum_proposal(&conflicting.data)
+                        .commit()
+                        .as_ref(),
+                    conflicting.data.view_number,
+                )
+                .await,
+        )
+        .context("Failed to sign conflicting proposal")
+        .unwrap();
+
+        Some(conflicting)
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Versions>
+    EventTransformerState<TYPES, I, V> for EquivocatingLeader<TYPES>
+{
+    async fn recv_handler(&mut self, event: &HotShotEvent<TYPES>) -> Vec<HotShotEvent<TYPES>> {
+        vec![event.clone()]
+    }
+
+    async fn send_handler(
+        &mut self,
+        event: &HotShotEvent<TYPES>,
+        _public_key: &TYPES::SignatureKey,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+        _consensus: Arc<RwLock<Consensus<TYPES>>>,
+    ) -> Vec<HotShotEvent<TYPES>> {
+        match event {
+            HotShotEvent::QuorumProposalSend(proposal, sender)
+                if *proposal.data.view_number >= self.equivocate_from_view =>
+            {
+                if let Some(conflicting) = self
+                    .conflicting_proposal(proposal, private_key, upgrade_lock)
+                    .await
+                {
+                    tracing::debug!(
+                        "Equivocating on the proposal for view {:?}",
+                        proposal.data.view_number
+                    );
+                    return vec![
+                        event.clone(),
+                        HotShotEvent::QuorumProposalSend(conflicting, sender.clone()),
+                    ];
+                }
+            }
+            HotShotEvent::QuorumProposalValidated(proposal, _) => {
+                self.validated_proposals.push(proposal.data.clone());
+            }
+            _ => {}
+        }
+        vec![event.clone()]
+    }
+}
+
+#[derive(Debug)]
+/// An `EventTransformerState` that withholds every quorum, timeout, DA and view sync vote the node
+/// sends, and optionally equivocates as leader too
+pub struct VoteWithholder<TYPES: NodeType> {
+    /// Equivocation on the node's own proposals, if it equivocates as well
+    pub equivocator: Option<EquivocatingLeader<TYPES>>,
+}
+
+impl<TYPES: NodeType> VoteWithholder<TYPES> {
+    /// Create a node which withholds its votes, and equivocates with `equivocator` if given
+    #[must_use]
+    pub fn new(equivocator: Option<EquivocatingLeader<TYPES>>) -> Self {
+        Self { equivocator }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Versions>
+    EventTransformerState<TYPES, I, V> for VoteWithholder<TYPES>
+{
+    async fn recv_handler(&mut self, event: &HotShotEvent<TYPES>) -> Vec<HotShotEvent<TYPES>> {
+        vec![event.clone()]
+    }
+
+    async fn send_handler(
+        &mut self,
+        event: &HotShotEvent<TYPES>,
+        public_key: &TYPES::SignatureKey,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+        consensus: Arc<RwLock<Consensus<TYPES>>>,
+    ) -> Vec<HotShotEvent<TYPES>> {
+        match event {
+            HotShotEvent::QuorumVoteSend(_)
+            | HotShotEvent::ExtendedQuorumVoteSend(_)
+            | HotShotEvent::TimeoutVoteSend(_)
+            | HotShotEvent::DaVoteSend(_)
+            | HotShotEvent::ViewSyncPreCommitVoteSend(_)
+            | HotShotEvent::ViewSyncCommitVoteSend(_)
+            | HotShotEvent::ViewSyncFinalizeVoteSend(_) => {
+                tracing::debug!("Withholding vote {event}");
+                vec![]
+            }
+            _ => match &mut self.equivocator {
+                Some(equivocator) => {
+                    EventTransformerState::<TYPES, I, V>::send_handler(
+                        equivocator,
+                        event,
+                        public_key,
+                        private_key,
+                        upgrade_lock,
+                        consensus,
+                    )
+                    .await
+                }
+                None => vec![event.clone()],
+            },
+        }
+    }
+}
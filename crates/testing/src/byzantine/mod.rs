@@ -1,2 +1,4 @@
+/// Stake distributions giving adversarial nodes a chosen fraction of the stake
+pub mod adversary_stake;
 /// Byzantine definitions and implementations of different behaviours
 pub mod byzantine_behaviour;
@@ -0,0 +1,373 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Transition logs of consensus, for checking runs against a formal specification.
+//!
+//! [`export`] converts a [golden trace](crate::golden_trace) into a [`TransitionLog`]: for every
+//! node, the sequence of actions it took, each with the abstract state of the node after the
+//! action (its view, the newest QC it has seen, its locked view and its decided view). The log is
+//! written as JSON lines, one [`Transition`] per line, so that an external checker, such as a
+//! TLA+ model checker replaying the trace, can read it without knowing anything about HotShot.
+//!
+//! [`check`] runs the same conformance check in process: every state must follow from the
+//! previous one by the HotStuff-2 rules, decides must extend the chain of proposals the node saw,
+//! and all nodes must decide the same sequence of views. The `hotshot-conformance` binary runs
+//! both over stored traces.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// An action a node took, as traced
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// the node saw a proposal for `view`, justified by a QC for `parent`
+    Propose {
+        /// view of the proposal
+        view: u64,
+        /// view of the QC justifying the proposal, which is the view of its parent
+        parent: u64,
+    },
+    /// the node timed out in `view`
+    Timeout {
+        /// the view
+        view: u64,
+    },
+    /// the node decided `views`, oldest first, on seeing a QC for `qc`
+    Decide {
+        /// the decided views, oldest first
+        views: Vec<u64>,
+        /// view of the QC which completed the decide
+        qc: u64,
+    },
+}
+
+/// The abstract state of a node, as seen by the specification
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeState {
+    /// the newest view the node has taken part in
+    pub view: u64,
+    /// view of the newest QC the node has seen
+    pub high_qc: u64,
+    /// view of the block the node is locked on
+    pub locked_view: u64,
+    /// view of the newest block the node has decided
+    pub decided_view: u64,
+}
+
+impl NodeState {
+    /// The state after `action`, by the HotStuff-2 rules
+    #[must_use]
+    pub fn step(self, action: &Action) -> Self {
+        match action {
+            Action::Propose { view, parent } => Self {
+                view: self.view.max(*view),
+                high_qc: self.high_qc.max(*parent),
+                locked_view: self.locked_view.max(*parent),
+                ..self
+            },
+            Action::Timeout { view } => Self {
+                view: self.view.max(*view),
+                ..self
+            },
+            Action::Decide { views, qc } => Self {
+                high_qc: self.high_qc.max(*qc),
+                decided_view: views
+                    .last()
+                    .map_or(self.decided_view, |view| self.decided_view.max(*view)),
+                ..self
+            },
+        }
+    }
+}
+
+/// A single step of a node
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    /// the node
+    pub node: usize,
+    /// index of the step among the node's steps, starting at 0
+    pub step: usize,
+    /// what the node did
+    #[serde(flatten)]
+    pub action: Action,
+    /// the state of the node after the action
+    pub state: NodeState,
+}
+
+/// The transitions of all nodes, ordered by node and then by step
+pub type TransitionLog = Vec<Transition>;
+
+/// the ways in which a trace can fail to convert or to conform
+#[derive(Error, Debug)]
+pub enum ConformanceErr {
+    /// a line of a golden trace or a transition log could not be parsed
+    #[error("Failed to parse line {line}: {text:?}")]
+    Parse {
+        /// the line, starting at 1
+        line: usize,
+        /// the text of the line
+        text: String,
+    },
+    /// a state does not follow from the previous one by the rules
+    #[error("Node {node} step {step}: expected state {expected:?}, got {actual:?}")]
+    InvalidTransition {
+        /// the node
+        node: usize,
+        /// the step
+        step: usize,
+        /// the state the rules lead to
+        expected: NodeState,
+        /// the state in the log
+        actual: NodeState,
+    },
+    /// a decide goes back to or below a view which is already decided
+    #[error(
+        "Node {node} step {step}: decided view {view}, but had already decided view {decided_view}"
+    )]
+    Redecided {
+        /// the node
+        node: usize,
+        /// the step
+        step: usize,
+        /// the offending view
+        view: u64,
+        /// the view the node had already decided
+        decided_view: u64,
+    },
+    /// a decided block does not extend the previously decided block
+    #[error("Node {node} step {step}: decided view {view} extending view {parent}, expected view {expected}")]
+    BrokenChain {
+        /// the node
+        node: usize,
+        /// the step
+        step: usize,
+        /// the decided view
+        view: u64,
+        /// the parent of the decided block in the proposal the node saw
+        parent: u64,
+        /// the previously decided view
+        expected: u64,
+    },
+    /// two nodes decided different views at the same position
+    #[error("Nodes {node} and {other} disagree on decide {index}: view {view} against view {other_view}")]
+    Disagreement {
+        /// the first node
+        node: usize,
+        /// the second node
+        other: usize,
+        /// position in the sequence of decided views, starting at 0
+        index: usize,
+        /// the view the first node decided
+        view: u64,
+        /// the view the second node decided
+        other_view: u64,
+    },
+}
+
+/// Parse the entry of a golden trace line, without its node and view prefix
+fn parse_entry(view: u64, entry: &str) -> Option<Option<Action>> {
+    if entry == "timeout" {
+        return Some(Some(Action::Timeout { view }));
+    }
+    if entry.starts_with("upgrade proposal") {
+        return Some(None);
+    }
+    if let Some(rest) = entry.strip_prefix("proposal of block ") {
+        // Any certificates after the QC don't change the abstract state
+        let (_, rest) = rest.split_once(" justified by QC for view ")?;
+        let parent = rest.split(',').next()?.parse().ok()?;
+        return Some(Some(Action::Propose { view, parent }));
+    }
+    if let Some(rest) = entry.strip_prefix("decide views ") {
+        let (views, qc) = rest.split_once(" with QC for view ")?;
+        let views = views
+            .split(", ")
+            .map(str::parse)
+            .collect::<Result<Vec<u64>, _>>()
+            .ok()?;
+        return Some(Some(Action::Decide {
+            views,
+            qc: qc.parse().ok()?,
+        }));
+    }
+
+    None
+}
+
+/// Convert a golden trace into a transition log
+///
+/// # Errors
+/// If a line of the trace is not in the golden trace format
+pub fn export(trace: &str) -> Result<TransitionLog, ConformanceErr> {
+    let mut states = BTreeMap::<usize, (usize, NodeState)>::new();
+    let mut log = Vec::new();
+
+    for (index, text) in trace.lines().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let parse_err = || ConformanceErr::Parse {
+            line: index + 1,
+            text: text.to_string(),
+        };
+        let (node, rest) = text
+            .strip_prefix("node ")
+            .and_then(|rest| rest.split_once(": view "))
+            .ok_or_else(parse_err)?;
+        let (view, entry) = rest.split_once(": ").ok_or_else(parse_err)?;
+        let node = node.parse().map_err(|_| parse_err())?;
+        let view = view.parse().map_err(|_| parse_err())?;
+        let Some(action) = parse_entry(view, entry).ok_or_else(parse_err)? else {
+            continue;
+        };
+
+        let (step, state) = states.entry(node).or_default();
+        *state = state.step(&action);
+        log.push(Transition {
+            node,
+            step: *step,
+            action,
+            state: *state,
+        });
+        *step += 1;
+    }
+    log.sort_by_key(|transition| (transition.node, transition.step));
+
+    Ok(log)
+}
+
+/// Render a transition log as JSON lines
+///
+/// # Panics
+/// If a transition cannot be serialized, which never happens
+#[must_use]
+pub fn render_log(log: &[Transition]) -> String {
+    log.iter()
+        .map(|transition| {
+            serde_json::to_string(transition).expect("failed to serialize a transition") + "\n"
+        })
+        .collect()
+}
+
+/// Parse a transition log from JSON lines
+///
+/// # Errors
+/// If a line is not a transition
+pub fn parse_log(text: &str) -> Result<TransitionLog, ConformanceErr> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|_| ConformanceErr::Parse {
+                line: index + 1,
+                text: line.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Check that a transition log conforms to the specification, see the
+/// [module documentation](self)
+///
+/// # Errors
+/// On the first violation found
+pub fn check(log: &[Transition]) -> Result<(), ConformanceErr> {
+    let mut nodes = BTreeMap::<usize, Vec<&Transition>>::new();
+    for transition in log {
+        nodes.entry(transition.node).or_default().push(transition);
+    }
+
+    let mut decided = BTreeMap::new();
+    for (node, mut transitions) in nodes {
+        transitions.sort_by_key(|transition| transition.step);
+        decided.insert(node, check_node(node, &transitions)?);
+    }
+
+    // Agreement: of any two nodes, one has decided a prefix of what the other decided
+    let mut nodes = decided.iter();
+    if let Some((first, first_views)) = nodes.next() {
+        for (node, views) in nodes {
+            if let Some((index, (view, other_view))) = first_views
+                .iter()
+                .zip(views)
+                .enumerate()
+                .find(|(_, (view, other_view))| view != other_view)
+            {
+                return Err(ConformanceErr::Disagreement {
+                    node: *first,
+                    other: *node,
+                    index,
+                    view: *view,
+                    other_view: *other_view,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the transitions of a single node, returning the views it decided in order
+fn check_node(node: usize, transitions: &[&Transition]) -> Result<Vec<u64>, ConformanceErr> {
+    let mut state = NodeState::default();
+    // view -> view of its parent, for every proposal the node saw
+    let mut parents = BTreeMap::new();
+    let mut decided = Vec::new();
+
+    for transition in transitions {
+        let step = transition.step;
+        let expected = state.step(&transition.action);
+        if expected != transition.state {
+            return Err(ConformanceErr::InvalidTransition {
+                node,
+                step,
+                expected,
+                actual: transition.state,
+            });
+        }
+
+        match &transition.action {
+            Action::Propose { view, parent } => {
+                parents.insert(*view, *parent);
+            }
+            Action::Timeout { .. } => {}
+            Action::Decide { views, .. } => {
+                for view in views {
+                    let previous = decided.last().copied().unwrap_or(0);
+                    if !decided.is_empty() && *view <= previous {
+                        return Err(ConformanceErr::Redecided {
+                            node,
+                            step,
+                            view: *view,
+                            decided_view: previous,
+                        });
+                    }
+                    // The trace may start after the node's first decides, so only blocks whose
+                    // parent was decided in the trace are checked
+                    if let (Some(parent), false) = (parents.get(view), decided.is_empty()) {
+                        if *parent != previous {
+                            return Err(ConformanceErr::BrokenChain {
+                                node,
+                                step,
+                                view: *view,
+                                parent: *parent,
+                                expected: previous,
+                            });
+                        }
+                    }
+                    decided.push(*view);
+                }
+            }
+        }
+
+        state = expected;
+    }
+
+    Ok(decided)
+}
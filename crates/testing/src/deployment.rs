@@ -0,0 +1,272 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Containerized deployments of test networks.
+//!
+//! A [`Deployment`] turns a [`TestDescription`] into a Docker Compose project which runs the same
+//! network as separate containers: an orchestrator and one `validator-libp2p` container per staked
+//! node, each on its own address in a private network. The orchestrator config pins the stake
+//! table to the keys the test would use, and each validator can shape its outgoing traffic with
+//! `tc netem` to add latency and loss.
+//!
+//! Only the shape of the network carries over: the number of nodes, the DA committee, the
+//! bootstrap nodes and the timeouts. Faults injected by the test runner, such as spinning nodes
+//! down or byzantine behaviour, are not reproduced.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    net::{Ipv4Addr, SocketAddr},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use hotshot::traits::NodeImplementation;
+use hotshot_types::{
+    constants::{
+        ORCHESTRATOR_DEFAULT_TRANSACTIONS_PER_ROUND, ORCHESTRATOR_DEFAULT_TRANSACTION_SIZE,
+    },
+    hotshot_config_file::HotShotConfigFile,
+    network::{BuilderType, NetworkConfigFile, PeerConfigKeys},
+    traits::node_implementation::{NodeType, Versions},
+    wire_codec::WireFormat,
+    ValidatorConfig,
+};
+
+use crate::test_builder::TestDescription;
+
+/// Port the orchestrator listens on
+pub const ORCHESTRATOR_PORT: u16 = 4444;
+/// Port each validator advertises for libp2p
+pub const VALIDATOR_PORT: u16 = 8000;
+/// Port each validator runs its builder on
+pub const BUILDER_PORT: u16 = 9000;
+
+/// Artificial conditions on the links between validators, applied with `tc netem`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinkShaping {
+    /// latency added to every outgoing packet, in milliseconds
+    pub delay_ms: u64,
+    /// random variation of the latency, in milliseconds
+    pub jitter_ms: u64,
+    /// share of outgoing packets to drop, in percent
+    pub loss_percent: f64,
+}
+
+/// Options for a [`Deployment`] which are not part of the test description
+#[derive(Clone, Debug)]
+pub struct DeploymentOptions {
+    /// tag of the `ghcr.io/espressosystems/hotshot` images to run
+    pub image_tag: String,
+    /// number of views to run
+    pub rounds: usize,
+    /// conditions on the links between validators, if any
+    pub link_shaping: Option<LinkShaping>,
+}
+
+impl Default for DeploymentOptions {
+    fn default() -> Self {
+        Self {
+            image_tag: "main".to_string(),
+            rounds: 100,
+            link_shaping: None,
+        }
+    }
+}
+
+/// A Docker Compose project running a test network, as a set of files
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deployment {
+    /// contents of each file, by its path relative to the project directory
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+impl Deployment {
+    /// Address of the orchestrator in the deployment's network
+    #[must_use]
+    pub fn orchestrator_address() -> Ipv4Addr {
+        Ipv4Addr::new(172, 28, 0, 2)
+    }
+
+    /// Address of validator `node_id` in the deployment's network
+    ///
+    /// # Panics
+    /// If there are more nodes than fit in the network
+    #[must_use]
+    pub fn validator_address(node_id: usize) -> Ipv4Addr {
+        let host = u16::try_from(node_id).expect("too many nodes for the deployment network");
+        let [high, low] = (host + 256).to_be_bytes();
+        Ipv4Addr::new(172, 28, high, low)
+    }
+
+    /// Generate the deployment of the network described by `description`
+    ///
+    /// # Panics
+    /// If the orchestrator config cannot be serialized
+    #[must_use]
+    pub fn from_description<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+        description: &TestDescription<TYPES, I, V>,
+        options: &DeploymentOptions,
+    ) -> Self {
+        let config = Self::orchestrator_config(description, options);
+        let run_config = toml::to_string(&config).expect("failed to serialize the run config");
+
+        let mut files = BTreeMap::new();
+        files.insert(PathBuf::from("run-config.toml"), run_config);
+        files.insert(
+            PathBuf::from("docker-compose.yml"),
+            Self::compose_file(description.num_nodes_with_stake, options),
+        );
+
+        Self { files }
+    }
+
+    /// Write the files of the deployment into `dir`
+    ///
+    /// # Errors
+    /// If a file cannot be written
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        for (path, contents) in &self.files {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+
+        Ok(())
+    }
+
+    /// The orchestrator config, with the stake table of the test
+    fn orchestrator_config<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+        description: &TestDescription<TYPES, I, V>,
+        options: &DeploymentOptions,
+    ) -> NetworkConfigFile<TYPES::SignatureKey> {
+        let num_nodes = description.num_nodes_with_stake;
+        let da_committee_size = description.da_staked_committee_size;
+        let timing = &description.timing_data;
+
+        // The same keys the test runner generates for its nodes
+        let public_keys = (0..num_nodes)
+            .map(|node_id| {
+                let is_da = node_id < da_committee_size;
+                let validator_config =
+                    ValidatorConfig::<TYPES::SignatureKey>::generated_from_seed_indexed(
+                        [0u8; 32],
+                        node_id as u64,
+                        1,
+                        is_da,
+                    );
+                PeerConfigKeys {
+                    stake_table_key: validator_config.public_key,
+                    state_ver_key: validator_config.state_key_pair.ver_key(),
+                    stake: validator_config.stake_value,
+                    da: is_da,
+                }
+            })
+            .collect();
+
+        NetworkConfigFile {
+            rounds: options.rounds,
+            indexed_da: true,
+            transactions_per_round: ORCHESTRATOR_DEFAULT_TRANSACTIONS_PER_ROUND,
+            manual_start_password: None,
+            node_index: 0,
+            seed: [0u8; 32],
+            transaction_size: ORCHESTRATOR_DEFAULT_TRANSACTION_SIZE,
+            config: HotShotConfigFile {
+                num_nodes_with_stake: NonZeroUsize::new(num_nodes)
+                    .expect("a deployment needs at least one node"),
+                start_threshold: (num_nodes as u64, num_nodes as u64),
+                staked_da_nodes: da_committee_size,
+                next_view_timeout: timing.next_view_timeout,
+                view_sync_timeout: timing.view_sync_timeout,
+                num_bootstrap: description.num_bootstrap_nodes,
+                builder_timeout: timing.builder_timeout,
+                data_request_delay: Some(timing.data_request_delay),
+                ..HotShotConfigFile::hotshot_config_5_nodes_10_da()
+            },
+            cdn_marshal_address: None,
+            combined_network_config: None,
+            builder: BuilderType::Simple,
+            random_builder: None,
+            public_keys,
+            wire_format: WireFormat::default(),
+            libp2p_relay_nodes: Vec::new(),
+        }
+    }
+
+    /// The Compose file running the orchestrator and `num_nodes` validators
+    fn compose_file(num_nodes: usize, options: &DeploymentOptions) -> String {
+        let tag = &options.image_tag;
+        let orchestrator_address = Self::orchestrator_address();
+        let orchestrator_url = format!("http://{orchestrator_address}:{ORCHESTRATOR_PORT}");
+
+        let mut compose = format!(
+            "services:
+  orchestrator:
+    image: ghcr.io/espressosystems/hotshot/orchestrator:{tag}
+    command: [\"--config_file\", \"/config/run-config.toml\", \"--orchestrator_url\", \"http://0.0.0.0:{ORCHESTRATOR_PORT}\"]
+    volumes:
+      - ./run-config.toml:/config/run-config.toml:ro
+    ports:
+      - \"{ORCHESTRATOR_PORT}:{ORCHESTRATOR_PORT}\"
+    networks:
+      hotshot:
+        ipv4_address: {orchestrator_address}
+"
+        );
+
+        for node_id in 0..num_nodes {
+            let address = Self::validator_address(node_id);
+            let validator = format!(
+                "validator-libp2p {orchestrator_url} {} {}",
+                SocketAddr::new(address.into(), VALIDATOR_PORT),
+                SocketAddr::new(address.into(), BUILDER_PORT),
+            );
+            let (entrypoint, cap_add) = match options.link_shaping {
+                Some(LinkShaping {
+                    delay_ms,
+                    jitter_ms,
+                    loss_percent,
+                }) => (
+                    format!(
+                        "[\"sh\", \"-c\", \"tc qdisc add dev eth0 root netem delay {delay_ms}ms {jitter_ms}ms loss {loss_percent}% && exec {validator}\"]"
+                    ),
+                    "\n    cap_add:\n      - NET_ADMIN",
+                ),
+                None => (format!("[\"sh\", \"-c\", \"exec {validator}\"]"), ""),
+            };
+
+            let _ = write!(
+                compose,
+                "
+  validator-{node_id}:
+    image: ghcr.io/espressosystems/hotshot/validator-libp2p:{tag}
+    entrypoint: {entrypoint}{cap_add}
+    depends_on:
+      - orchestrator
+    networks:
+      hotshot:
+        ipv4_address: {address}
+"
+            );
+        }
+
+        compose.push_str(
+            "
+networks:
+  hotshot:
+    ipam:
+      config:
+        - subnet: 172.28.0.0/16
+",
+        );
+
+        compose
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Golden traces of the externally visible behaviour of consensus.
+//!
+//! A [`GoldenTraceTask`] records, for every node, the ordered proposals, certificates, timeouts
+//! and decides it reports up to some view. The first run of a test writes this trace to a file,
+//! and every later run compares against it, so a change to consensus which alters what the nodes
+//! do fails the test even when all other assertions still hold. Set `HOTSHOT_RECORD_GOLDEN_TRACES`
+//! to record the trace again after an intended change.
+//!
+//! The trace only holds data which is the same for every run of a test, such as view numbers and
+//! block heights, but not commitments, which depend on block timestamps. Tests comparing against a
+//! golden trace should be free of timing-dependent faults, like a randomly unreliable network.
+
+use std::{
+    collections::BTreeMap, fmt::Write as _, fs, marker::PhantomData, path::PathBuf, sync::Arc,
+};
+
+use anyhow::Result;
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::traits::TestableNodeImplementation;
+use hotshot_types::{
+    data::ViewChangeEvidence,
+    event::{Event, EventType},
+    traits::{
+        block_contents::BlockHeader,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+    },
+};
+use thiserror::Error;
+
+use crate::{
+    test_runner::Node,
+    test_task::{AnyTestTaskState, TestResult, TestTaskState, TestTaskStateSeed},
+};
+
+/// Environment variable which makes every golden trace task record its trace again
+pub const RECORD_GOLDEN_TRACES_VAR: &str = "HOTSHOT_RECORD_GOLDEN_TRACES";
+
+/// Whether a golden trace task writes the trace or compares against it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoldenTraceMode {
+    /// write the trace of this run to the golden trace file
+    Record,
+    /// compare the trace of this run with the golden trace file
+    Compare,
+}
+
+/// the ways in which a run can deviate from its golden trace
+#[derive(Error, Debug)]
+pub enum GoldenTraceErr {
+    /// a node never decided the last traced view, so the trace is incomplete
+    #[error("Node {node} only decided up to view {decided:?}, the trace ends at view {last_view}")]
+    Incomplete {
+        /// the node
+        node: usize,
+        /// the latest view the node decided
+        decided: Option<u64>,
+        /// the last view in the trace
+        last_view: u64,
+    },
+    /// the golden trace file could not be read or written
+    #[error("Failed to access golden trace {path}: {error}")]
+    Io {
+        /// path of the golden trace
+        path: PathBuf,
+        /// the underlying error
+        error: std::io::Error,
+    },
+    /// the trace differs from the golden trace
+    #[error("Trace differs from golden trace {path} at line {line}: expected {expected:?}, got {actual:?}")]
+    Mismatch {
+        /// path of the golden trace
+        path: PathBuf,
+        /// line of the first difference, starting at 1
+        line: usize,
+        /// the line in the golden trace, `None` if it ended early
+        expected: Option<String>,
+        /// the line in this run's trace, `None` if it ended early
+        actual: Option<String>,
+    },
+}
+
+/// Test task which records the trace of a run and checks it against the golden trace
+pub struct GoldenTraceTask<TYPES: NodeType> {
+    /// where the golden trace is stored
+    pub path: PathBuf,
+    /// last view included in the trace
+    pub last_view: u64,
+    /// whether to write or compare the trace
+    pub mode: GoldenTraceMode,
+    /// ordered trace of each node
+    pub traces: BTreeMap<usize, Vec<String>>,
+    /// latest view decided by each node
+    pub decided_views: BTreeMap<usize, u64>,
+    /// phantom
+    pub _pd: PhantomData<TYPES>,
+}
+
+impl<TYPES: NodeType> GoldenTraceTask<TYPES> {
+    /// The trace of a single event, if the event belongs in the trace
+    fn trace(&self, event: &EventType<TYPES>) -> Option<String> {
+        let entry = match event {
+            EventType::QuorumProposal { proposal, .. } => {
+                let proposal = &proposal.data;
+                let mut entry = format!(
+                    "view {}: proposal of block {} justified by QC for view {}",
+                    proposal.view_number.u64(),
+                    proposal.block_header.block_number(),
+                    proposal.justify_qc.view_number.u64()
+                );
+                match &proposal.view_change_evidence {
+                    Some(ViewChangeEvidence::Timeout(cert)) => {
+                        let _ = write!(
+                            entry,
+                            ", timeout certificate for view {}",
+                            cert.view_number.u64()
+                        );
+                    }
+                    Some(ViewChangeEvidence::ViewSync(cert)) => {
+                        let _ = write!(
+                            entry,
+                            ", view sync certificate for view {}",
+                            cert.view_number.u64()
+                        );
+                    }
+                    None => {}
+                }
+                if let Some(cert) = &proposal.upgrade_certificate {
+                    let _ = write!(
+                        entry,
+                        ", upgrade certificate to version {}",
+                        cert.data.new_version
+                    );
+                }
+                (proposal.view_number.u64(), entry)
+            }
+            EventType::UpgradeProposal { proposal, .. } => {
+                let view = proposal.data.view_number.u64();
+                (
+                    view,
+                    format!(
+                        "view {view}: upgrade proposal to version {}",
+                        proposal.data.upgrade_proposal.new_version
+                    ),
+                )
+            }
+            EventType::ViewTimeout { view_number } => {
+                let view = view_number.u64();
+                (view, format!("view {view}: timeout"))
+            }
+            EventType::Decide { leaf_chain, qc, .. } => {
+                // Leaves are decided a few views after they are proposed, so trace the decides
+                // of the leaves up to the last view, whatever view the QC is for
+                let views = leaf_chain
+                    .iter()
+                    .rev()
+                    .map(|leaf_info| leaf_info.leaf.view_number().u64())
+                    .filter(|view| *view <= self.last_view)
+                    .collect::<Vec<_>>();
+                let newest = *views.last()?;
+                (
+                    newest,
+                    format!(
+                        "view {}: decide views {} with QC for view {}",
+                        newest,
+                        views
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        qc.view_number.u64()
+                    ),
+                )
+            }
+            _ => return None,
+        };
+
+        (entry.0 <= self.last_view).then_some(entry.1)
+    }
+
+    /// The trace of all nodes, one line per entry
+    fn render(&self) -> String {
+        self.traces
+            .iter()
+            .flat_map(|(node, trace)| {
+                trace
+                    .iter()
+                    .map(move |entry| format!("node {node}: {entry}\n"))
+            })
+            .collect()
+    }
+
+    /// Compare the trace of this run with the golden trace
+    fn compare(&self, actual: &str) -> Result<(), GoldenTraceErr> {
+        let expected = fs::read_to_string(&self.path).map_err(|error| GoldenTraceErr::Io {
+            path: self.path.clone(),
+            error,
+        })?;
+
+        let mut expected_lines = expected.lines();
+        let mut actual_lines = actual.lines();
+        for line in 1.. {
+            match (expected_lines.next(), actual_lines.next()) {
+                (None, None) => break,
+                (expected, actual) if expected == actual => {}
+                (expected, actual) => {
+                    return Err(GoldenTraceErr::Mismatch {
+                        path: self.path.clone(),
+                        line,
+                        expected: expected.map(ToString::to_string),
+                        actual: actual.map(ToString::to_string),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the trace of this run as the golden trace
+    fn record(&self, actual: &str) -> Result<(), GoldenTraceErr> {
+        let io_err = |error| GoldenTraceErr::Io {
+            path: self.path.clone(),
+            error,
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        fs::write(&self.path, actual).map_err(io_err)
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> TestTaskState for GoldenTraceTask<TYPES> {
+    type Event = Event<TYPES>;
+
+    async fn handle_event(&mut self, (message, id): (Self::Event, usize)) -> Result<()> {
+        if let EventType::Decide { leaf_chain, .. } = &message.event {
+            if let Some(view) = leaf_chain
+                .iter()
+                .map(|leaf_info| leaf_info.leaf.view_number().u64())
+                .max()
+            {
+                let decided = self.decided_views.entry(id).or_insert(view);
+                *decided = (*decided).max(view);
+            }
+        }
+
+        if let Some(entry) = self.trace(&message.event) {
+            self.traces.entry(id).or_default().push(entry);
+        }
+
+        Ok(())
+    }
+
+    async fn check(&self) -> TestResult {
+        if let Some((node, decided)) = self
+            .traces
+            .keys()
+            .map(|node| (*node, self.decided_views.get(node).copied()))
+            .find(|(_, decided)| decided.map_or(true, |view| view < self.last_view))
+        {
+            return TestResult::Fail(Box::new(GoldenTraceErr::Incomplete {
+                node,
+                decided,
+                last_view: self.last_view,
+            }));
+        }
+
+        let actual = self.render();
+        let result = match self.mode {
+            GoldenTraceMode::Record => self.record(&actual),
+            GoldenTraceMode::Compare => self.compare(&actual),
+        };
+
+        match result {
+            Ok(()) => TestResult::Pass,
+            Err(err) => TestResult::Fail(Box::new(err)),
+        }
+    }
+}
+
+/// Seed for a [`GoldenTraceTask`], to pass to
+/// [`TestDescription::gen_launcher_with_tasks`](crate::test_builder::TestDescription::gen_launcher_with_tasks)
+pub struct GoldenTraceDescription {
+    /// where the golden trace is stored
+    pub path: PathBuf,
+    /// last view included in the trace
+    pub last_view: u64,
+    /// whether to write or compare the trace
+    pub mode: GoldenTraceMode,
+}
+
+impl GoldenTraceDescription {
+    /// Trace up to `last_view` against the golden trace at `path`. The trace is recorded if the
+    /// file doesn't exist yet or [`RECORD_GOLDEN_TRACES_VAR`] is set, and compared otherwise.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>, last_view: u64) -> Self {
+        let path = path.into();
+        let mode = if std::env::var_os(RECORD_GOLDEN_TRACES_VAR).is_some() || !path.exists() {
+            GoldenTraceMode::Record
+        } else {
+            GoldenTraceMode::Compare
+        };
+
+        Self {
+            path,
+            last_view,
+            mode,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions>
+    TestTaskStateSeed<TYPES, I, V> for GoldenTraceDescription
+{
+    async fn into_state(
+        self: Box<Self>,
+        _handles: Arc<RwLock<Vec<Node<TYPES, I, V>>>>,
+    ) -> AnyTestTaskState<TYPES> {
+        Box::new(GoldenTraceTask {
+            path: self.path,
+            last_view: self.last_view,
+            mode: self.mode,
+            traces: BTreeMap::new(),
+            decided_views: BTreeMap::new(),
+            _pd: PhantomData,
+        })
+    }
+}
@@ -112,7 +112,7 @@ pub async fn build_system_handle_from_launcher<
 
     let memberships = Arc::new(RwLock::new(TYPES::Membership::new(
         config.known_nodes_with_stake.clone(),
-        config.known_da_nodes.clone(),
+        config.da_committee(),
     )));
 
     SystemContext::init(
@@ -214,11 +214,11 @@ pub async fn build_assembled_sig<
     upgrade_lock: &UpgradeLock<TYPES, V>,
 ) -> <TYPES::SignatureKey as SignatureKey>::QcType {
     let membership_reader = membership.read().await;
-    let stake_table = CERT::stake_table(&*membership_reader, epoch);
+    let stake_table = CERT::stake_table(&*membership_reader, view, epoch);
     let real_qc_pp: <TYPES::SignatureKey as SignatureKey>::QcParams =
         <TYPES::SignatureKey as SignatureKey>::public_parameter(
             stake_table.clone(),
-            U256::from(CERT::threshold(&*membership_reader, epoch)),
+            CERT::threshold(&*membership_reader, view, epoch),
         );
     drop(membership_reader);
 
@@ -321,12 +321,13 @@ pub async fn build_payload_commitment<TYPES: NodeType>(
 }
 
 /// TODO: <https://github.com/EspressoSystems/HotShot/issues/2821>
-pub async fn build_vid_proposal<TYPES: NodeType>(
+pub async fn build_vid_proposal<TYPES: NodeType, V: Versions>(
     membership: &Arc<RwLock<<TYPES as NodeType>::Membership>>,
     view_number: TYPES::View,
     epoch_number: TYPES::Epoch,
     transactions: Vec<TestTransaction>,
     private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
 ) -> VidProposal<TYPES> {
     let mut vid = vid_scheme_from_view_number::<TYPES>(membership, view_number, epoch_number).await;
     let encoded_transactions = TestTransaction::encode(&transactions);
@@ -341,26 +342,30 @@ pub async fn build_vid_proposal<TYPES: NodeType>(
     )
     .await;
 
-    let signature =
-        TYPES::SignatureKey::sign(private_key, vid_disperse.payload_commitment.as_ref())
-            .expect("Failed to sign VID commitment");
+    let signature = TYPES::SignatureKey::sign(
+        private_key,
+        &upgrade_lock
+            .signing_message(vid_disperse.payload_commitment.as_ref(), view_number)
+            .await,
+    )
+    .expect("Failed to sign VID commitment");
     let vid_disperse_proposal = Proposal {
         data: vid_disperse.clone(),
         signature,
         _pd: PhantomData,
     };
 
-    (
-        vid_disperse_proposal,
-        VidDisperseShare2::from_vid_disperse(vid_disperse)
-            .into_iter()
-            .map(|vid_disperse| {
-                vid_disperse
-                    .to_proposal(private_key)
-                    .expect("Failed to sign payload commitment")
-            })
-            .collect(),
-    )
+    let mut share_proposals = Vec::new();
+    for share in VidDisperseShare2::from_vid_disperse(vid_disperse) {
+        share_proposals.push(
+            share
+                .to_proposal(private_key, upgrade_lock)
+                .await
+                .expect("Failed to sign payload commitment"),
+        );
+    }
+
+    (vid_disperse_proposal, share_proposals)
 }
 
 #[allow(clippy::too_many_arguments)]
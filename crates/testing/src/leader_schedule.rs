@@ -0,0 +1,381 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Harness for comparing leader selection strategies.
+//!
+//! A strategy is the `Membership` of a node type, so two strategies are compared by running the
+//! same [`TestDescription`](crate::test_builder::TestDescription) with two node types which differ
+//! only in their `Membership`, each with a [`LeaderScheduleTaskDescription`], and comparing the
+//! [`LeaderScheduleReport`]s of the runs: decide latency, timeout frequency and how evenly the
+//! views were spread over the leaders. [`schedule_load`] gives the load a strategy puts on each
+//! leader over many views without running consensus at all.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Display},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::traits::TestableNodeImplementation;
+use hotshot_types::{
+    event::{Event, EventType},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+    },
+};
+
+use crate::{
+    test_runner::Node,
+    test_task::{AnyTestTaskState, TestResult, TestTaskState, TestTaskStateSeed},
+};
+
+/// How many views each leader led
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LeaderLoad {
+    /// number of views led by each eligible leader, by node id
+    pub views_led: BTreeMap<u64, u64>,
+    /// the most consecutive views led by the same leader
+    pub longest_run: u64,
+}
+
+impl LeaderLoad {
+    /// The load of the `leaders` given the leader of each view in `schedule`. Views missing from
+    /// the schedule break up runs of consecutive views.
+    #[must_use]
+    pub fn from_schedule(
+        leaders: impl IntoIterator<Item = u64>,
+        schedule: &BTreeMap<u64, u64>,
+    ) -> Self {
+        let mut views_led = leaders
+            .into_iter()
+            .map(|leader| (leader, 0))
+            .collect::<BTreeMap<_, _>>();
+        let mut longest_run = 0;
+        let mut run: Option<(u64, u64, u64)> = None;
+        for (view, leader) in schedule {
+            *views_led.entry(*leader).or_default() += 1;
+            let length = match run {
+                Some((last_view, last_leader, length))
+                    if last_view + 1 == *view && last_leader == *leader =>
+                {
+                    length + 1
+                }
+                _ => 1,
+            };
+            longest_run = longest_run.max(length);
+            run = Some((*view, *leader, length));
+        }
+
+        Self {
+            views_led,
+            longest_run,
+        }
+    }
+
+    /// Total number of views led
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.views_led.values().sum()
+    }
+
+    /// The most views led by a single leader
+    #[must_use]
+    pub fn max(&self) -> u64 {
+        self.views_led.values().copied().max().unwrap_or(0)
+    }
+
+    /// The fewest views led by a single leader
+    #[must_use]
+    pub fn min(&self) -> u64 {
+        self.views_led.values().copied().min().unwrap_or(0)
+    }
+
+    /// Standard deviation of the views led over the mean, zero when every leader led as many
+    /// views as every other
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn coefficient_of_variation(&self) -> f64 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        let leaders = self.views_led.len() as f64;
+        let mean = self.total() as f64 / leaders;
+        let variance = self
+            .views_led
+            .values()
+            .map(|views| (*views as f64 - mean).powi(2))
+            .sum::<f64>()
+            / leaders;
+
+        variance.sqrt() / mean
+    }
+}
+
+impl Display for LeaderLoad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} views over {} leaders, {} to {} each (cv {:.3}), longest run {}",
+            self.total(),
+            self.views_led.len(),
+            self.min(),
+            self.max(),
+            self.coefficient_of_variation(),
+            self.longest_run,
+        )
+    }
+}
+
+/// The load `membership` puts on each of its leaders over views `0..views` of the genesis epoch.
+/// Leaders are identified by their index in `known_nodes`.
+///
+/// # Panics
+/// If the membership fails to look up a leader, or a leader is not in `known_nodes`
+#[must_use]
+pub fn schedule_load<TYPES: NodeType>(
+    membership: &TYPES::Membership,
+    known_nodes: &[TYPES::SignatureKey],
+    views: u64,
+) -> LeaderLoad {
+    let epoch = TYPES::Epoch::genesis();
+    let node_id = |key: &TYPES::SignatureKey| {
+        known_nodes
+            .iter()
+            .position(|known| known == key)
+            .expect("Leader is not a known node") as u64
+    };
+    let leaders = membership
+        .committee_leaders(TYPES::View::genesis(), epoch)
+        .iter()
+        .map(node_id)
+        .collect::<Vec<_>>();
+    let schedule = (0..views)
+        .map(|view| {
+            let leader = membership
+                .lookup_leader(TYPES::View::new(view), epoch)
+                .unwrap_or_else(|e| panic!("Failed to look up the leader of view {view}: {e}"));
+            (view, node_id(&leader))
+        })
+        .collect();
+
+    LeaderLoad::from_schedule(leaders, &schedule)
+}
+
+/// What a run under one leader selection strategy looked like
+#[derive(Clone, Debug)]
+pub struct LeaderScheduleReport {
+    /// name of the strategy
+    pub strategy: String,
+    /// highest view any node reached
+    pub views: u64,
+    /// number of views in which some node timed out
+    pub timed_out_views: u64,
+    /// time from each decided view's proposal to the first node deciding it, sorted
+    pub decide_latencies: Vec<Duration>,
+    /// the views proposed by each leader
+    pub leader_load: LeaderLoad,
+}
+
+impl LeaderScheduleReport {
+    /// Fraction of the views reached which timed out
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn timeout_frequency(&self) -> f64 {
+        if self.views == 0 {
+            return 0.0;
+        }
+        self.timed_out_views as f64 / self.views as f64
+    }
+
+    /// The `percentile`th percentile of the decide latencies, if any view was decided
+    #[must_use]
+    pub fn decide_latency(&self, percentile: usize) -> Option<Duration> {
+        if self.decide_latencies.is_empty() {
+            return None;
+        }
+        let index = (self.decide_latencies.len() - 1) * percentile.min(100) / 100;
+
+        Some(self.decide_latencies[index])
+    }
+}
+
+impl Display for LeaderScheduleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} views, {} decided (latency p50 {:?}, p90 {:?}, max {:?}), \
+             {} timed out ({:.1}%), leader load: {}",
+            self.strategy,
+            self.views,
+            self.decide_latencies.len(),
+            self.decide_latency(50),
+            self.decide_latency(90),
+            self.decide_latency(100),
+            self.timed_out_views,
+            self.timeout_frequency() * 100.0,
+            self.leader_load,
+        )
+    }
+}
+
+/// Test task which measures the decide latency, timeout frequency and leader load of a run, to
+/// compare leader selection strategies. It never fails the test.
+pub struct LeaderScheduleTask<TYPES: NodeType> {
+    /// name of the strategy under test
+    pub strategy: String,
+    /// where to leave the report when the test ends
+    pub report: Arc<RwLock<Option<LeaderScheduleReport>>>,
+    /// node id of each node's key
+    pub node_ids: HashMap<TYPES::SignatureKey, u64>,
+    /// node ids of the eligible leaders
+    pub leaders: BTreeSet<u64>,
+    /// leader of each proposed view, and when the proposal was first seen
+    pub proposed: BTreeMap<TYPES::View, (u64, Instant)>,
+    /// when each view was first decided
+    pub decided: BTreeMap<TYPES::View, Instant>,
+    /// views in which some node timed out
+    pub timed_out: BTreeSet<TYPES::View>,
+    /// highest view any node reached
+    pub last_view: TYPES::View,
+}
+
+impl<TYPES: NodeType> LeaderScheduleTask<TYPES> {
+    /// The report on the run so far
+    fn build_report(&self) -> LeaderScheduleReport {
+        let mut decide_latencies = self
+            .decided
+            .iter()
+            .filter_map(|(view, decided)| {
+                let (_, proposed) = self.proposed.get(view)?;
+                Some(decided.saturating_duration_since(*proposed))
+            })
+            .collect::<Vec<_>>();
+        decide_latencies.sort();
+        let schedule = self
+            .proposed
+            .iter()
+            .map(|(view, (leader, _))| (view.u64(), *leader))
+            .collect();
+
+        LeaderScheduleReport {
+            strategy: self.strategy.clone(),
+            views: self.last_view.u64(),
+            timed_out_views: self.timed_out.len() as u64,
+            decide_latencies,
+            leader_load: LeaderLoad::from_schedule(self.leaders.iter().copied(), &schedule),
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> TestTaskState for LeaderScheduleTask<TYPES> {
+    type Event = Event<TYPES>;
+
+    async fn handle_event(&mut self, (message, _id): (Self::Event, usize)) -> Result<()> {
+        let now = Instant::now();
+        self.last_view = self.last_view.max(message.view_number);
+        match message.event {
+            EventType::QuorumProposal { proposal, sender } => {
+                if let Some(leader) = self.node_ids.get(&sender) {
+                    self.proposed
+                        .entry(proposal.data.view_number)
+                        .or_insert((*leader, now));
+                }
+            }
+            EventType::Decide { leaf_chain, .. } => {
+                for leaf_info in leaf_chain.iter() {
+                    self.decided
+                        .entry(leaf_info.leaf.view_number())
+                        .or_insert(now);
+                }
+            }
+            EventType::ViewTimeout { view_number } => {
+                self.timed_out.insert(view_number);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn check(&self) -> TestResult {
+        let report = self.build_report();
+        tracing::info!("Leader schedule report for {report}");
+        *self.report.write().await = Some(report);
+
+        TestResult::Pass
+    }
+}
+
+/// Seed for a [`LeaderScheduleTask`], to pass to
+/// [`TestDescription::gen_launcher_with_tasks`](crate::test_builder::TestDescription::gen_launcher_with_tasks)
+pub struct LeaderScheduleTaskDescription {
+    /// name of the strategy under test
+    pub strategy: String,
+    /// where the task leaves its report when the test ends
+    pub report: Arc<RwLock<Option<LeaderScheduleReport>>>,
+}
+
+impl LeaderScheduleTaskDescription {
+    /// A task measuring the run under the leader selection strategy called `strategy`
+    #[must_use]
+    pub fn new(strategy: impl Into<String>) -> Self {
+        Self {
+            strategy: strategy.into(),
+            report: Arc::default(),
+        }
+    }
+
+    /// Where the task leaves its report when the test ends
+    #[must_use]
+    pub fn report(&self) -> Arc<RwLock<Option<LeaderScheduleReport>>> {
+        Arc::clone(&self.report)
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions>
+    TestTaskStateSeed<TYPES, I, V> for LeaderScheduleTaskDescription
+{
+    async fn into_state(
+        self: Box<Self>,
+        handles: Arc<RwLock<Vec<Node<TYPES, I, V>>>>,
+    ) -> AnyTestTaskState<TYPES> {
+        let nodes = handles.read().await;
+        let node_ids = nodes
+            .iter()
+            .map(|node| (node.handle.public_key(), node.node_id))
+            .collect::<HashMap<_, _>>();
+        let leaders = match nodes.first() {
+            Some(node) => node
+                .handle
+                .memberships
+                .read()
+                .await
+                .committee_leaders(TYPES::View::genesis(), TYPES::Epoch::genesis())
+                .iter()
+                .filter_map(|key| node_ids.get(key).copied())
+                .collect(),
+            None => BTreeSet::new(),
+        };
+
+        Box::new(LeaderScheduleTask::<TYPES> {
+            strategy: self.strategy,
+            report: self.report,
+            node_ids,
+            leaders,
+            proposed: BTreeMap::new(),
+            decided: BTreeMap::new(),
+            timed_out: BTreeSet::new(),
+            last_view: TYPES::View::genesis(),
+        })
+    }
+}
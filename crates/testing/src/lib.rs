@@ -9,6 +9,10 @@
 /// Helpers for initializing system context handle and building tasks.
 pub mod helpers;
 
+/// in-memory networks for application integration tests
+pub mod quick_network;
+pub use quick_network::quick_network;
+
 ///  builder
 pub mod test_builder;
 
@@ -39,6 +43,21 @@ pub mod test_task;
 /// task for checking if view sync got activated
 pub mod view_sync_task;
 
+/// view sync tasks wired together through lossy relays
+pub mod view_sync_network;
+
+/// task for checking that every node switches to the upgraded version
+pub mod upgrade_task;
+
+/// task for recording and comparing golden traces of consensus
+pub mod golden_trace;
+
+/// task for checking that every node receives its VID shares
+pub mod vid_distribution_task;
+
+/// task for comparing leader selection strategies
+pub mod leader_schedule;
+
 /// Test implementation of block builder
 pub mod block_builder;
 
@@ -53,3 +72,15 @@ pub mod view_generator;
 
 /// byzantine framework for tests
 pub mod byzantine;
+
+/// declarative test scenarios
+pub mod scenario;
+
+/// containerized deployments of test networks
+pub mod deployment;
+
+/// reference model of the consensus rules for differential testing
+pub mod reference_model;
+
+/// transition logs of consensus traces for conformance checks
+pub mod conformance;
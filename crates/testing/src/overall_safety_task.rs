@@ -19,12 +19,14 @@ use hotshot_types::{
     error::RoundTimedoutState,
     event::{Event, EventType, LeafChain},
     simple_certificate::QuorumCertificate2,
+    stake_table::total_stake,
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeType, Versions},
     },
     vid::VidCommitment,
 };
+use primitive_types::U256;
 use thiserror::Error;
 use tracing::error;
 
@@ -32,6 +34,18 @@ use crate::{
     test_runner::Node,
     test_task::{TestEvent, TestResult, TestTaskState},
 };
+/// Number of the `num_nodes` nodes which must decide a view for it to succeed: the same share of
+/// the nodes as the share of the `total_stake` the `stake_threshold` is, rounded up. With a stake
+/// of one per node, as in most tests, this is the threshold itself.
+fn node_threshold(stake_threshold: U256, total_stake: U256, num_nodes: usize) -> usize {
+    if total_stake.is_zero() {
+        return num_nodes;
+    }
+    let threshold = stake_threshold * U256::from(num_nodes);
+
+    ((threshold + total_stake - 1) / total_stake).as_usize()
+}
+
 /// convenience type alias for state and block
 pub type StateAndBlock<S, B> = (Vec<S>, Vec<B>);
 
@@ -206,7 +220,11 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> TestTas
         let len = memberships_reader.total_nodes(epoch);
 
         // update view count
-        let threshold = memberships_reader.success_threshold(epoch).get() as usize;
+        let threshold = node_threshold(
+            memberships_reader.success_threshold(epoch),
+            total_stake::<TYPES::SignatureKey, _>(&memberships_reader.stake_table(epoch)),
+            len,
+        );
         drop(memberships_reader);
         drop(memberships_arc);
 
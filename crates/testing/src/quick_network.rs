@@ -0,0 +1,117 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! In-memory networks for application integration tests
+//!
+//! [`quick_network`] starts `n` nodes of the default test types over an in-memory network, with
+//! a test builder, and starts consensus on all of them. Applications can then submit
+//! transactions and watch the nodes' events without any of the test harness' tasks in the way:
+//!
+//! ```ignore
+//! let network = hotshot_testing::quick_network(4).await;
+//! network.handle(0).submit_transaction(transaction).await?;
+//! let mut events = network.handle(1).event_stream();
+//! // ... wait for the transaction to be decided ...
+//! network.shut_down().await;
+//! ```
+
+use std::collections::HashSet;
+
+use futures::Stream;
+use hotshot::types::{Event, SystemContextHandle};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+
+use crate::{
+    block_builder::SimpleBuilderImplementation, test_builder::TestDescription, test_runner::Node,
+};
+
+/// A running in-memory network of nodes with the default test types
+pub struct QuickNetwork {
+    /// The nodes, in order of their ids
+    nodes: Vec<Node<TestTypes, MemoryImpl, TestVersions>>,
+}
+
+impl QuickNetwork {
+    /// Number of nodes in the network
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the network has no nodes
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The handle of the node with id `node_id`
+    ///
+    /// # Panics
+    /// if there is no such node
+    #[must_use]
+    pub fn handle(
+        &self,
+        node_id: usize,
+    ) -> &SystemContextHandle<TestTypes, MemoryImpl, TestVersions> {
+        &self.nodes[node_id].handle
+    }
+
+    /// The handles of all nodes, in order of their ids
+    pub fn handles(
+        &self,
+    ) -> impl Iterator<Item = &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>> {
+        self.nodes.iter().map(|node| &node.handle)
+    }
+
+    /// A new stream of external events for every node, in order of their ids
+    #[must_use]
+    pub fn event_streams(&self) -> Vec<impl Stream<Item = Event<TestTypes>>> {
+        self.nodes
+            .iter()
+            .map(|node| node.handle.event_stream())
+            .collect()
+    }
+
+    /// Shut every node down and wait for its tasks to finish
+    pub async fn shut_down(mut self) {
+        for node in &mut self.nodes {
+            node.handle.shut_down().await;
+        }
+    }
+}
+
+/// Start a network of `num_nodes` nodes with the default test types over an in-memory network,
+/// all of them staked and on the DA committee, and start consensus on them.
+///
+/// # Panics
+/// if `num_nodes` is zero, or if a node cannot be initialized
+pub async fn quick_network(num_nodes: usize) -> QuickNetwork {
+    assert!(num_nodes > 0, "A network needs at least one node");
+
+    let mut runner = TestDescription::<TestTypes, MemoryImpl, TestVersions> {
+        num_nodes_with_stake: num_nodes,
+        start_nodes: num_nodes,
+        num_bootstrap_nodes: num_nodes,
+        da_staked_committee_size: num_nodes,
+        start_solver: false,
+        ..TestDescription::default()
+    }
+    .gen_launcher(0)
+    .launch();
+
+    runner
+        .add_nodes::<SimpleBuilderImplementation>(num_nodes, &HashSet::new(), &HashSet::new())
+        .await;
+
+    // `add_nodes` has already waited for every network to be ready
+    for node in &runner.nodes {
+        node.handle.hotshot.start_consensus().await;
+    }
+
+    QuickNetwork {
+        nodes: runner.nodes,
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A reference model of the HotStuff-2 locking and decide rules, and a harness which checks
+//! HotShot against it.
+//!
+//! The model works on abstract proposals, which only name their view and the view of the block
+//! their justify QC is for, and applies the rules as pure functions:
+//!
+//! * a replica locks on the block certified by the justify QC of each proposal it accepts, if the
+//!   QC is newer than its lock, and
+//! * a proposal whose parent and grandparent are in consecutive views decides the grandparent,
+//!   along with all of its undecided ancestors.
+//!
+//! The [`DifferentialHarness`] generates real proposals for the same sequence of parents, feeds
+//! them to the decide logic the quorum vote task runs ([`decide_from_proposal_2`]), and compares
+//! the locked view and the decided views with those of the model after every proposal.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_lock::RwLock;
+use hotshot::types::SystemContextHandle;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_task_impls::helpers::decide_from_proposal_2;
+use hotshot_types::{
+    consensus::OuterConsensus, data::Leaf2, traits::node_implementation::ConsensusTime,
+    vote::HasViewNumber,
+};
+use thiserror::Error;
+
+use crate::{
+    helpers::{build_system_handle, vid_share},
+    view_generator::TestView,
+};
+
+/// A proposal, reduced to what the locking and decide rules look at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModelProposal {
+    /// view of the proposal
+    pub view: u64,
+    /// view of the block the proposal's justify QC is for, which the proposal extends
+    pub parent: u64,
+}
+
+/// What a replica has locked and decided
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelOutcome {
+    /// view of the locked block
+    pub locked_view: u64,
+    /// view of the latest decided block
+    pub decided_view: u64,
+    /// views decided by the last proposal, newest first
+    pub newly_decided: Vec<u64>,
+}
+
+/// State of a replica in the reference model
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelState {
+    /// view -> view of its parent, for every block the replica has seen
+    parents: BTreeMap<u64, u64>,
+    /// view of the locked block
+    locked_view: u64,
+    /// view of the latest decided block
+    decided_view: u64,
+}
+
+impl Default for ModelState {
+    /// A replica which has only seen the genesis block, in view 0
+    fn default() -> Self {
+        Self {
+            parents: BTreeMap::new(),
+            locked_view: 0,
+            decided_view: 0,
+        }
+    }
+}
+
+impl ModelState {
+    /// Whether the replica has seen the block in `view`
+    #[must_use]
+    pub fn knows(&self, view: u64) -> bool {
+        view == 0 || self.parents.contains_key(&view)
+    }
+
+    /// Apply the locking and decide rules to `proposal`, returning the new state and what it
+    /// locked and decided
+    #[must_use]
+    pub fn apply(&self, proposal: ModelProposal) -> (Self, ModelOutcome) {
+        let mut state = self.clone();
+        state.parents.insert(proposal.view, proposal.parent);
+        state.locked_view = state.locked_view.max(proposal.parent);
+
+        let mut newly_decided = Vec::new();
+        if let Some(&grandparent) = self.parents.get(&proposal.parent) {
+            if grandparent + 1 == proposal.parent && grandparent > state.decided_view {
+                let mut view = Some(grandparent);
+                while let Some(decided) = view.filter(|view| *view > state.decided_view) {
+                    newly_decided.push(decided);
+                    view = state.parents.get(&decided).copied();
+                }
+                state.decided_view = grandparent;
+            }
+        }
+
+        let outcome = ModelOutcome {
+            locked_view: state.locked_view,
+            decided_view: state.decided_view,
+            newly_decided,
+        };
+
+        (state, outcome)
+    }
+}
+
+/// the ways in which HotShot can disagree with the reference model
+#[derive(Error, Debug)]
+pub enum DifferentialErr {
+    /// the parent of a proposal was never proposed
+    #[error("View {0} was never proposed")]
+    UnknownParent(u64),
+    /// HotShot locked or decided something other than the model
+    #[error(
+        "Proposal {proposal:?} diverged from the model: expected {expected:?}, got {actual:?}"
+    )]
+    Diverged {
+        /// the proposal
+        proposal: ModelProposal,
+        /// the outcome in the model
+        expected: ModelOutcome,
+        /// the outcome in HotShot
+        actual: ModelOutcome,
+    },
+}
+
+/// Feeds the same proposals to the reference model and to HotShot's decide logic, see the
+/// [module documentation](self)
+pub struct DifferentialHarness {
+    /// the replica running HotShot's logic
+    handle: SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+    /// every generated view, whether or not it was delivered
+    views: BTreeMap<u64, TestView>,
+    /// the latest generated view, which later views are numbered after
+    latest: TestView,
+    /// the replica running the model
+    model: ModelState,
+}
+
+impl DifferentialHarness {
+    /// Start both replicas and deliver the proposal for view 1, which extends genesis
+    ///
+    /// # Errors
+    /// If HotShot disagrees with the model on the first proposal
+    pub async fn new() -> Result<Self, DifferentialErr> {
+        let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+            .await
+            .0;
+        let latest = TestView::genesis(&handle.hotshot.memberships).await;
+        let mut harness = Self {
+            handle,
+            views: BTreeMap::new(),
+            latest: latest.clone(),
+            model: ModelState::default(),
+        };
+        harness
+            .views
+            .insert(latest.view_number.u64(), latest.clone());
+        harness.deliver(&latest).await?;
+
+        Ok(harness)
+    }
+
+    /// Propose a block extending the block in `parent` in the next view, and deliver it to both
+    /// replicas, returning the view of the proposal
+    ///
+    /// # Errors
+    /// If `parent` was never proposed, or HotShot disagrees with the model on the proposal
+    pub async fn propose(&mut self, parent: u64) -> Result<u64, DifferentialErr> {
+        let view = self.generate(parent).await?;
+        self.deliver(&view).await?;
+
+        Ok(view.view_number.u64())
+    }
+
+    /// Propose a block extending the block in `parent` in the next view, but never deliver it, as
+    /// if the leader failed, returning the view of the proposal
+    ///
+    /// # Errors
+    /// If `parent` was never proposed
+    pub async fn lose(&mut self, parent: u64) -> Result<u64, DifferentialErr> {
+        Ok(self.generate(parent).await?.view_number.u64())
+    }
+
+    /// Generate the proposal for the next view, extending the block in `parent`
+    async fn generate(&mut self, parent: u64) -> Result<TestView, DifferentialErr> {
+        let ancestor = self
+            .views
+            .get(&parent)
+            .cloned()
+            .ok_or(DifferentialErr::UnknownParent(parent))?;
+        let view = self.latest.next_view_from_ancestor(ancestor).await;
+        self.views.insert(view.view_number.u64(), view.clone());
+        self.latest = view.clone();
+
+        Ok(view)
+    }
+
+    /// Deliver the proposal of `view` to both replicas and compare the outcomes
+    async fn deliver(&mut self, view: &TestView) -> Result<(), DifferentialErr> {
+        let proposal = &view.quorum_proposal.data;
+        let model_proposal = ModelProposal {
+            view: proposal.view_number().u64(),
+            parent: proposal.justify_qc.view_number().u64(),
+        };
+        if !self.model.knows(model_proposal.parent) {
+            return Err(DifferentialErr::UnknownParent(model_proposal.parent));
+        }
+        let (model, expected) = self.model.apply(model_proposal);
+        self.model = model;
+
+        // Store the proposal the way the quorum vote task does before it votes
+        let consensus = self.handle.consensus();
+        let public_key = self.handle.public_key();
+        let mut consensus_writer = consensus.write().await;
+        consensus_writer
+            .update_leaf(
+                Leaf2::from_quorum_proposal(proposal),
+                Arc::new(TestValidatedState::default()),
+                None,
+            )
+            .expect("Failed to store the proposed leaf");
+        consensus_writer.update_vid_shares(
+            proposal.view_number(),
+            vid_share(&view.vid_proposal.0, self.handle.public_key()),
+        );
+        drop(consensus_writer);
+
+        let outcome = decide_from_proposal_2(
+            proposal,
+            OuterConsensus::new(Arc::clone(&consensus)),
+            Arc::new(RwLock::new(None)),
+            &public_key,
+        )
+        .await;
+
+        // Apply the outcome the way the quorum vote task does, where neither view may go back
+        let mut consensus_writer = consensus.write().await;
+        if let Some(locked_view) = outcome.new_locked_view_number {
+            let _ = consensus_writer.update_locked_view(locked_view);
+        }
+        if let Some(decided_view) = outcome.new_decided_view_number {
+            let _ = consensus_writer.update_last_decided_view(decided_view);
+        }
+        let actual = ModelOutcome {
+            locked_view: consensus_writer.locked_view().u64(),
+            decided_view: consensus_writer.last_decided_view().u64(),
+            newly_decided: outcome
+                .leaf_views
+                .iter()
+                .map(|leaf_info| leaf_info.leaf.view_number().u64())
+                .collect(),
+        };
+
+        if actual != expected {
+            return Err(DifferentialErr::Diverged {
+                proposal: model_proposal,
+                expected,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
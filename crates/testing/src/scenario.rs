@@ -0,0 +1,267 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A declarative way to describe consensus tests.
+//!
+//! A [`Scenario`] states the network, the faults injected into it and the expected outcome, and
+//! compiles into a [`TestDescription`], e.g.
+//!
+//! ```ignore
+//! Scenario::nodes(10)
+//!     .equivocate(3, 5)
+//!     .partition(8..=12, [7, 8, 9])
+//!     .expect_decides(20)
+//!     .expect_no_safety_violation()
+//!     .build::<TestTypes, MemoryImpl, TestVersions>()
+//! ```
+//!
+//! An adversary can also be given a share of the stake rather than of the nodes, e.g.
+//! `.stake_weighted_adversary([7, 8, 9], (333, 1000))` has nodes 7 to 9 hold just under a third of
+//! the stake and withhold their votes, see [`adversary_stake`](crate::byzantine::adversary_stake).
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Debug,
+    ops::RangeInclusive,
+    rc::Rc,
+    time::Duration,
+};
+
+use hotshot::traits::NodeImplementation;
+use hotshot_types::traits::node_implementation::{ConsensusTime, NodeType, Versions};
+
+use crate::{
+    byzantine::{
+        adversary_stake::adversary_stakes,
+        byzantine_behaviour::{EquivocatingLeader, VoteWithholder},
+    },
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::{Behaviour, TestDescription},
+    view_sync_task::ViewSyncTaskDescription,
+};
+
+/// How long a scenario runs for, unless set with [`Scenario::within`]
+pub const DEFAULT_SCENARIO_DURATION: Duration = Duration::from_secs(60);
+
+/// A consensus test scenario, see the [module documentation](self)
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    /// Total number of staked nodes
+    num_nodes: usize,
+    /// Size of the DA committee, all nodes if `None`
+    da_committee_size: Option<usize>,
+    /// Equivocating nodes, along with the view they start equivocating at
+    equivocations: BTreeMap<u64, u64>,
+    /// Nodes which withhold their votes
+    withholding: BTreeSet<u64>,
+    /// Stake of each node, one if not listed
+    stakes: HashMap<u64, u64>,
+    /// Node changes, by the view they happen at
+    node_changes: BTreeMap<u64, Vec<ChangeNode>>,
+    /// Minimum number of views which must decide
+    min_decides: usize,
+    /// Maximum number of views which may fail, unbounded if `None`
+    max_failed_views: Option<usize>,
+    /// Views which must fail, and the only ones which may fail if there are any
+    expected_failures: Vec<u64>,
+    /// Whether every node must decide the same leaves
+    check_leaves: bool,
+    /// How long the scenario runs for
+    duration: Duration,
+}
+
+impl Scenario {
+    /// A scenario with `num_nodes` honest nodes, all of them on the DA committee
+    #[must_use]
+    pub fn nodes(num_nodes: usize) -> Self {
+        Self {
+            num_nodes,
+            da_committee_size: None,
+            equivocations: BTreeMap::new(),
+            withholding: BTreeSet::new(),
+            stakes: HashMap::new(),
+            node_changes: BTreeMap::new(),
+            min_decides: 0,
+            max_failed_views: None,
+            expected_failures: Vec::new(),
+            check_leaves: false,
+            duration: DEFAULT_SCENARIO_DURATION,
+        }
+    }
+
+    /// Only put the first `size` nodes on the DA committee
+    #[must_use]
+    pub fn da_committee(mut self, size: usize) -> Self {
+        self.da_committee_size = Some(size);
+        self
+    }
+
+    /// `node` sends a second, conflicting proposal for every view it leads from `view` on
+    #[must_use]
+    pub fn equivocate(mut self, node: usize, view: u64) -> Self {
+        self.equivocations.insert(node as u64, view);
+        self
+    }
+
+    /// `nodes` withhold every vote, and together hold `fraction` of the stake, as a
+    /// `(numerator, denominator)` pair, with the rest split evenly among the other nodes.
+    ///
+    /// # Panics
+    /// If `nodes` are not all in the network, or the fraction is not strictly between zero and one
+    #[must_use]
+    pub fn stake_weighted_adversary(
+        mut self,
+        nodes: impl IntoIterator<Item = usize>,
+        fraction: (u64, u64),
+    ) -> Self {
+        self.withholding = nodes.into_iter().map(|node| node as u64).collect();
+        self.stakes = adversary_stakes(self.num_nodes, &self.withholding, fraction);
+        self
+    }
+
+    /// Cut `nodes` off from the network for the views in `views`, reconnecting them afterwards.
+    ///
+    /// This pauses the nodes' networks, so the test must use a network which supports pausing,
+    /// such as the Push CDN.
+    #[must_use]
+    pub fn partition(
+        self,
+        views: RangeInclusive<u64>,
+        nodes: impl IntoIterator<Item = usize>,
+    ) -> Self {
+        let (start, end) = views.into_inner();
+        nodes.into_iter().fold(self, |scenario, node| {
+            scenario
+                .change(start, node, NodeAction::NetworkDown)
+                .change(end + 1, node, NodeAction::NetworkUp)
+        })
+    }
+
+    /// Shut `node` down for good at `view`
+    #[must_use]
+    pub fn crash(self, node: usize, view: u64) -> Self {
+        self.change(view, node, NodeAction::Down)
+    }
+
+    /// Shut `node` down at `view` and restart it `views_down` views later
+    #[must_use]
+    pub fn restart(self, node: usize, view: u64, views_down: u64) -> Self {
+        self.change(view, node, NodeAction::RestartDown(views_down))
+    }
+
+    /// Expect at least `num_decides` views to decide
+    #[must_use]
+    pub fn expect_decides(mut self, num_decides: usize) -> Self {
+        self.min_decides = num_decides;
+        self
+    }
+
+    /// Expect at most `num_failed_views` views to fail. Failures aren't bounded otherwise.
+    #[must_use]
+    pub fn expect_at_most_failed_views(mut self, num_failed_views: usize) -> Self {
+        self.max_failed_views = Some(num_failed_views);
+        self
+    }
+
+    /// Expect `view` to fail. Once any view is expected to fail, no other view may fail.
+    #[must_use]
+    pub fn expect_failed_view(mut self, view: u64) -> Self {
+        self.expected_failures.push(view);
+        self
+    }
+
+    /// Expect every node to decide the same leaves
+    #[must_use]
+    pub fn expect_no_safety_violation(mut self) -> Self {
+        self.check_leaves = true;
+        self
+    }
+
+    /// Give up on the expectations after `duration`
+    #[must_use]
+    pub fn within(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Record a change of `node` at `view`
+    fn change(mut self, view: u64, node: usize, updown: NodeAction) -> Self {
+        self.node_changes
+            .entry(view)
+            .or_default()
+            .push(ChangeNode { idx: node, updown });
+        self
+    }
+
+    /// Compile the scenario into a test description.
+    ///
+    /// # Panics
+    /// If the scenario refers to nodes which don't exist
+    #[must_use]
+    pub fn build<TYPES: NodeType, I: NodeImplementation<TYPES> + Debug, V: Versions>(
+        self,
+    ) -> TestDescription<TYPES, I, V> {
+        let num_nodes = self.num_nodes;
+        assert!(
+            self.equivocations
+                .keys()
+                .chain(&self.withholding)
+                .all(|node| *node < num_nodes as u64)
+                && self
+                    .node_changes
+                    .values()
+                    .flatten()
+                    .all(|change| change.idx < num_nodes),
+            "Scenario refers to nodes outside of the {num_nodes} nodes in the network"
+        );
+
+        let mut metadata = TestDescription {
+            num_nodes_with_stake: num_nodes,
+            start_nodes: num_nodes,
+            num_bootstrap_nodes: num_nodes,
+            da_staked_committee_size: self.da_committee_size.unwrap_or(num_nodes),
+            spinning_properties: SpinningTaskDescription {
+                node_changes: self.node_changes.into_iter().collect(),
+            },
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: self.duration,
+                },
+            ),
+            view_sync_properties: ViewSyncTaskDescription::Threshold(0, num_nodes),
+            node_stakes: self.stakes,
+            ..TestDescription::default()
+        };
+
+        let equivocations = self.equivocations;
+        let withholding = self.withholding;
+        metadata.behaviour = Rc::new(move |node_id| {
+            let equivocator = equivocations
+                .get(&node_id)
+                .map(|view| EquivocatingLeader::<TYPES>::new(*view));
+            if withholding.contains(&node_id) {
+                Behaviour::Byzantine(Box::new(VoteWithholder::new(equivocator)))
+            } else if let Some(equivocator) = equivocator {
+                Behaviour::Byzantine(Box::new(equivocator))
+            } else {
+                Behaviour::Standard
+            }
+        });
+
+        let properties = &mut metadata.overall_safety_properties;
+        properties.num_successful_views = self.min_decides;
+        properties.num_failed_views = self.max_failed_views.unwrap_or(usize::MAX);
+        properties.check_leaf = self.check_leaves;
+        properties.expected_views_to_fail = self
+            .expected_failures
+            .into_iter()
+            .map(|view| (TYPES::View::new(view), false))
+            .collect::<HashMap<_, _>>();
+
+        metadata
+    }
+}
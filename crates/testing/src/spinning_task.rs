@@ -6,6 +6,7 @@
 
 use std::{
     collections::{BTreeMap, HashMap},
+    ops::Range,
     sync::Arc,
 };
 
@@ -15,7 +16,8 @@ use async_lock::RwLock;
 use async_trait::async_trait;
 use futures::future::join_all;
 use hotshot::{
-    traits::TestableNodeImplementation, types::EventType, HotShotInitializer, SystemContext,
+    tasks::RestartableTask, traits::TestableNodeImplementation, types::EventType,
+    HotShotInitializer, SystemContext,
 };
 use hotshot_example_types::{
     auction_results_provider_types::TestAuctionResultsProvider,
@@ -36,8 +38,10 @@ use hotshot_types::{
     vote::HasViewNumber,
     ValidatorConfig,
 };
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
 use crate::{
+    test_builder::{node_stake, test_instance_state},
     test_launcher::Network,
     test_runner::{LateNodeContext, LateNodeContextParameters, LateStartNode, Node, TestRunner},
     test_task::{TestResult, TestTaskState},
@@ -69,6 +73,10 @@ pub struct SpinningTask<
     pub(crate) next_epoch_high_qc: Option<NextEpochQuorumCertificate2<TYPES>>,
     /// Add specified delay to async calls
     pub(crate) async_delay_config: DelayConfig,
+    /// Skew of each node's clock in milliseconds
+    pub(crate) clock_skews: HashMap<u64, i64>,
+    /// Stake of each node
+    pub(crate) node_stakes: HashMap<u64, u64>,
     /// Context stored for nodes to be restarted with
     pub(crate) restart_contexts: HashMap<usize, RestartContext<TYPES, N, I, V>>,
     /// Generate network channel for restart nodes
@@ -155,7 +163,11 @@ where
 
                                         let initializer = HotShotInitializer::<TYPES>::from_reload(
                                             self.last_decided_leaf.clone(),
-                                            TestInstanceState::new(self.async_delay_config.clone()),
+                                            test_instance_state(
+                                                self.async_delay_config.clone(),
+                                                &self.clock_skews,
+                                                node_id,
+                                            ),
                                             None,
                                             TYPES::View::genesis(),
                                             TYPES::Epoch::genesis(),
@@ -172,7 +184,7 @@ where
                                             ValidatorConfig::generated_from_seed_indexed(
                                                 [0u8; 32],
                                                 node_id,
-                                                1,
+                                                node_stake(&self.node_stakes, node_id),
                                                 // For tests, make the node DA based on its index
                                                 node_id < config.da_staked_committee_size as u64,
                                             );
@@ -239,7 +251,11 @@ where
                                 let read_storage = storage.read().await;
                                 let initializer = HotShotInitializer::<TYPES>::from_reload(
                                     self.last_decided_leaf.clone(),
-                                    TestInstanceState::new(self.async_delay_config.clone()),
+                                    test_instance_state(
+                                        self.async_delay_config.clone(),
+                                        &self.clock_skews,
+                                        node_id,
+                                    ),
                                     None,
                                     read_storage.last_actioned_view().await,
                                     read_storage.last_actioned_epoch().await,
@@ -319,6 +335,12 @@ where
                                 handle.network.pause();
                             }
                         }
+                        NodeAction::RestartTask(task) => {
+                            if let Some(node) = self.handles.write().await.get_mut(idx) {
+                                tracing::error!("Node {} restarting its {} task", idx, task.name());
+                                node.handle.restart_task(task).await;
+                            }
+                        }
                     }
                 }
             }
@@ -396,6 +418,8 @@ pub enum NodeAction {
     /// Start a node up again after it's been shutdown for restart.  This
     /// should only be created following a `RestartDown`
     RestartUp,
+    /// Kill one of the node's consensus tasks and start it again with a fresh state
+    RestartTask(RestartableTask),
 }
 
 /// denotes a change in node state
@@ -407,6 +431,48 @@ pub struct ChangeNode {
     pub updown: NodeAction,
 }
 
+/// Restarts of consensus tasks on randomly chosen nodes and views, to check that nodes recover
+/// from a task crashing
+#[derive(Clone, Debug)]
+pub struct TaskRestartChaos {
+    /// the nodes whose tasks may be restarted
+    pub nodes: Vec<usize>,
+    /// the tasks which may be restarted
+    pub tasks: Vec<RestartableTask>,
+    /// the views in which tasks may be restarted
+    pub views: Range<u64>,
+    /// the number of restarts
+    pub num_restarts: usize,
+    /// seed for choosing the restarts, so that a failing run can be reproduced
+    pub seed: u64,
+}
+
+impl TaskRestartChaos {
+    /// The restarts as changes for the spinning task
+    #[must_use]
+    pub fn node_changes(&self) -> Vec<(u64, Vec<ChangeNode>)> {
+        if self.views.is_empty() {
+            return vec![];
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        (0..self.num_restarts)
+            .filter_map(|_| {
+                let idx = *self.nodes.choose(&mut rng)?;
+                let task = *self.tasks.choose(&mut rng)?;
+                let view = rng.gen_range(self.views.clone());
+                Some((
+                    view,
+                    vec![ChangeNode {
+                        idx,
+                        updown: NodeAction::RestartTask(task),
+                    }],
+                ))
+            })
+            .collect()
+    }
+}
+
 /// description of the spinning task
 /// (used to build a spinning task)
 #[derive(Clone, Debug)]
@@ -5,7 +5,12 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    any::TypeId, collections::HashMap, num::NonZeroUsize, rc::Rc, sync::Arc, time::Duration,
+    any::TypeId,
+    collections::HashMap,
+    num::{NonZeroU64, NonZeroUsize},
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
 };
 
 use anyhow::{ensure, Result};
@@ -21,9 +26,21 @@ use hotshot_example_types::{
     state_types::TestInstanceState, storage_types::TestStorage, testable_delay::DelayConfig,
 };
 use hotshot_types::{
+    catchup::CatchupConfig,
+    channel_depth::ChannelCapacities,
+    clock::Clock,
     consensus::ConsensusMetricsValue,
+    constants::{
+        ANTI_ENTROPY_INTERVAL, CONNECTION_WARM_UP_VIEWS, EVENT_CHANNEL_WARNING_FRACTION,
+        VIEW_SYNC_RELAYS_PER_ROUND,
+    },
+    fork_choice::ForkChoiceRule,
+    message::ReplayProtectionConfig,
+    message_ttl::MessageTtlConfig,
+    network::GossipTuning,
+    peer_score::PeerScoreConfig,
     traits::node_implementation::{NodeType, Versions},
-    HotShotConfig, ValidatorConfig,
+    BackgroundWorkBudget, DataAvailabilityMode, HotShotConfig, ValidatorConfig,
 };
 use tide_disco::Url;
 use vec1::Vec1;
@@ -34,7 +51,8 @@ use super::{
     txn_task::TxnTaskDescription,
 };
 use crate::{
-    spinning_task::SpinningTaskDescription,
+    block_builder::BuilderBehaviour,
+    spinning_task::{SpinningTaskDescription, TaskRestartChaos},
     test_launcher::{Network, ResourceGenerators, TestLauncher},
     test_task::TestTaskStateSeed,
     view_sync_task::ViewSyncTaskDescription,
@@ -95,10 +113,32 @@ pub struct TestDescription<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Ver
     pub behaviour: Rc<dyn Fn(u64) -> Behaviour<TYPES, I, V>>,
     /// Delay config if any to add delays to asynchronous calls
     pub async_delay_config: DelayConfig,
+    /// Skew of each node's clock in milliseconds, nodes not listed run on real time
+    pub clock_skews: HashMap<u64, i64>,
+    /// Stake of each node, nodes not listed have a stake of one
+    pub node_stakes: HashMap<u64, u64>,
+    /// random restarts of consensus tasks, on top of the node changes of `spinning_properties`
+    pub task_restart_chaos: Option<TaskRestartChaos>,
     /// view in which to propose an upgrade
     pub upgrade_view: Option<u64>,
     /// whether to initialize the solver on startup
     pub start_solver: bool,
+    /// how block payloads are made available
+    pub data_availability: DataAvailabilityMode,
+    /// whether to use the thresholds for networks of at most three nodes
+    pub small_network: bool,
+    /// minimum size of the proposals erasure coded among the quorum instead of broadcast
+    pub proposal_dispersal_min_bytes: Option<u64>,
+    /// upload bandwidth in bytes per second which each node's sends are paced within
+    pub upload_budget: Option<NonZeroU64>,
+    /// whether nodes serve decided leaves the state computed when voting for them
+    pub speculative_execution: bool,
+    /// rule nodes choose among QCs for the same view with
+    pub fork_choice: ForkChoiceRule,
+    /// CPU time background work may take up in each view on each node
+    pub background_work_budget: Option<BackgroundWorkBudget>,
+    /// whether nodes rehearse the decide rule of the version they upgrade to
+    pub shadow_upgrade: bool,
     /// boxed closure used to validate the resulting transactions
     pub validate_transactions: TransactionValidator,
 }
@@ -161,6 +201,25 @@ pub fn nonempty_block_limit(limit: (u64, u64)) -> TransactionValidator {
     })
 }
 
+/// Stake of node `node_id`, as given in `node_stakes`
+#[must_use]
+pub fn node_stake(node_stakes: &HashMap<u64, u64>, node_id: u64) -> u64 {
+    node_stakes.get(&node_id).copied().unwrap_or(1)
+}
+
+/// Instance state of node `node_id`, with its clock skewed as given in `clock_skews`
+#[must_use]
+pub fn test_instance_state(
+    async_delay_config: DelayConfig,
+    clock_skews: &HashMap<u64, i64>,
+    node_id: u64,
+) -> TestInstanceState {
+    TestInstanceState {
+        clock: Clock::with_skew(clock_skews.get(&node_id).copied().unwrap_or_default()),
+        ..TestInstanceState::new(async_delay_config)
+    }
+}
+
 #[derive(Debug)]
 pub enum Behaviour<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     ByzantineTwins(Box<dyn TwinsHandlerState<TYPES, I, V>>),
@@ -181,8 +240,10 @@ pub async fn create_test_handle<
     storage: I::Storage,
     marketplace_config: MarketplaceConfig<TYPES, I>,
 ) -> SystemContextHandle<TYPES, I, V> {
-    let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(TestInstanceState::new(
+    let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(test_instance_state(
         metadata.async_delay_config,
+        &metadata.clock_skews,
+        node_id,
     ))
     .await
     .unwrap();
@@ -191,7 +252,12 @@ pub async fn create_test_handle<
     let is_da = node_id < config.da_staked_committee_size as u64;
 
     let validator_config: ValidatorConfig<TYPES::SignatureKey> =
-        ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, is_da);
+        ValidatorConfig::generated_from_seed_indexed(
+            [0u8; 32],
+            node_id,
+            node_stake(&metadata.node_stakes, node_id),
+            is_da,
+        );
 
     // Get key pair for certificate aggregation
     let private_key = validator_config.private_key.clone();
@@ -265,6 +331,9 @@ pub enum BuilderChange {
     // Toggles whether builder should always respond
     // to claim calls with errors
     FailClaims(bool),
+    // Scripts how the builder responds to requests from this view on,
+    // only followed by the scripted builder
+    Behave(BuilderBehaviour),
 }
 
 /// Metadata describing builder behaviour during a test
@@ -415,8 +484,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Default
             },
             behaviour: Rc::new(|_| Behaviour::Standard),
             async_delay_config: DelayConfig::default(),
+            clock_skews: HashMap::new(),
+            node_stakes: HashMap::new(),
+            task_restart_chaos: None,
             upgrade_view: None,
             start_solver: true,
+            data_availability: DataAvailabilityMode::default(),
+            small_network: false,
+            proposal_dispersal_min_bytes: None,
+            upload_budget: None,
+            speculative_execution: false,
+            fork_choice: ForkChoiceRule::default(),
+            background_work_budget: None,
+            shadow_upgrade: false,
             validate_transactions: Arc::new(|_| Ok(())),
         }
     }
@@ -455,6 +535,15 @@ where
             timing_data,
             da_staked_committee_size,
             unreliable_network,
+            data_availability,
+            small_network,
+            proposal_dispersal_min_bytes,
+            upload_budget,
+            speculative_execution,
+            fork_choice,
+            background_work_budget,
+            shadow_upgrade,
+            node_stakes,
             ..
         } = self.clone();
 
@@ -467,7 +556,7 @@ where
                     ValidatorConfig::generated_from_seed_indexed(
                         [0u8; 32],
                         node_id_ as u64,
-                        1,
+                        node_stake(&node_stakes, node_id_ as u64),
                         node_id_ < da_staked_committee_size,
                     );
 
@@ -483,7 +572,7 @@ where
         let validator_config = ValidatorConfig::<TYPES::SignatureKey>::generated_from_seed_indexed(
             [0u8; 32],
             node_id,
-            1,
+            node_stake(&node_stakes, node_id),
             // This is the config for node 0
             0 < da_staked_committee_size,
         );
@@ -517,6 +606,30 @@ where
             start_voting_time: u64::MAX,
             stop_voting_time: 0,
             epoch_height,
+            post_mortem_dir: None,
+            replay_protection: ReplayProtectionConfig::default(),
+            max_task_view_lag: None,
+            high_priority_reserved_percent: 0,
+            gossip: GossipTuning::default(),
+            connection_warm_up_views: CONNECTION_WARM_UP_VIEWS,
+            message_ttl: MessageTtlConfig::default(),
+            anti_entropy_interval: ANTI_ENTROPY_INTERVAL,
+            event_channel_warning_fraction: EVENT_CHANNEL_WARNING_FRACTION,
+            channel_capacities: ChannelCapacities::default(),
+            chain_id: 0,
+            heartbeat_interval: None,
+            view_sync_relays_per_round: VIEW_SYNC_RELAYS_PER_ROUND,
+            data_availability,
+            small_network,
+            committee_sampling_beacon: Some(rand::random()),
+            peer_scoring: PeerScoreConfig::default(),
+            proposal_dispersal_min_bytes,
+            upload_budget,
+            speculative_execution,
+            fork_choice,
+            background_work_budget,
+            shadow_upgrade,
+            catchup: CatchupConfig::default(),
         };
         let TimingData {
             next_view_timeout,
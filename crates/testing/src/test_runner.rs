@@ -54,7 +54,7 @@ use crate::{
     block_builder::{BuilderTask, TestBuilderImplementation},
     completion_task::CompletionTaskDescription,
     spinning_task::{ChangeNode, NodeAction, SpinningTask},
-    test_builder::create_test_handle,
+    test_builder::{create_test_handle, node_stake, test_instance_state},
     test_launcher::{Network, TestLauncher},
     test_task::{TestResult, TestTask},
     txn_task::TxnTaskDescription,
@@ -90,12 +90,15 @@ where
     #[allow(clippy::too_many_lines)]
     pub async fn run_test<B: TestBuilderImplementation<TYPES>>(mut self) {
         let (test_sender, test_receiver) = broadcast(EVENT_CHANNEL_SIZE);
-        let spinning_changes = self
+        let mut spinning_changes = self
             .launcher
             .metadata
             .spinning_properties
             .node_changes
             .clone();
+        if let Some(chaos) = &self.launcher.metadata.task_restart_chaos {
+            spinning_changes.extend(chaos.node_changes());
+        }
 
         let mut late_start_nodes: HashSet<u64> = HashSet::new();
         let mut restart_nodes: HashSet<u64> = HashSet::new();
@@ -191,6 +194,8 @@ where
             .await,
             next_epoch_high_qc: None,
             async_delay_config: launcher.metadata.async_delay_config,
+            clock_skews: launcher.metadata.clock_skews.clone(),
+            node_stakes: launcher.metadata.node_stakes.clone(),
             restart_contexts: HashMap::new(),
             channel_generator: launcher.resource_generator.channel_generator,
         };
@@ -402,7 +407,7 @@ where
         // TODO This is only a workaround. Number of nodes changes from epoch to epoch. Builder should be made epoch-aware.
         let temp_memberships = <TYPES as NodeType>::Membership::new(
             config.known_nodes_with_stake.clone(),
-            config.known_da_nodes.clone(),
+            config.da_committee(),
         );
         let num_nodes = temp_memberships.total_nodes(TYPES::Epoch::new(0));
         let (mut builder_tasks, builder_urls, fallback_builder_url) =
@@ -468,7 +473,7 @@ where
                                     storage,
                                     memberships: <TYPES as NodeType>::Membership::new(
                                         config.known_nodes_with_stake.clone(),
-                                        config.known_da_nodes.clone(),
+                                        config.da_committee(),
                                     ),
                                     config,
                                     marketplace_config,
@@ -477,25 +482,32 @@ where
                         },
                     );
                 } else {
-                    let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(
-                        TestInstanceState::new(self.launcher.metadata.async_delay_config.clone()),
-                    )
-                    .await
-                    .unwrap();
+                    let initializer =
+                        HotShotInitializer::<TYPES>::from_genesis::<V>(test_instance_state(
+                            self.launcher.metadata.async_delay_config.clone(),
+                            &self.launcher.metadata.clock_skews,
+                            node_id,
+                        ))
+                        .await
+                        .unwrap();
 
                     // See whether or not we should be DA
                     let is_da = node_id < config.da_staked_committee_size as u64;
 
                     // We assign node's public key and stake value rather than read from config file since it's a test
-                    let validator_config =
-                        ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, is_da);
+                    let validator_config = ValidatorConfig::generated_from_seed_indexed(
+                        [0u8; 32],
+                        node_id,
+                        node_stake(&self.launcher.metadata.node_stakes, node_id),
+                        is_da,
+                    );
 
                     let hotshot = Self::add_node_with_config(
                         node_id,
                         network.clone(),
                         <TYPES as NodeType>::Membership::new(
                             config.known_nodes_with_stake.clone(),
-                            config.known_da_nodes.clone(),
+                            config.da_committee(),
                         ),
                         initializer,
                         config,
@@ -518,7 +530,7 @@ where
                     network,
                     <TYPES as NodeType>::Membership::new(
                         config.known_nodes_with_stake.clone(),
-                        config.known_da_nodes.clone(),
+                        config.da_committee(),
                     ),
                     config,
                     storage,
@@ -642,6 +654,15 @@ where
         let private_key = validator_config.private_key.clone();
         let public_key = validator_config.public_key.clone();
 
+        let mut membership_writer = memberships.write().await;
+        if let Some(thresholds) = config.thresholds() {
+            membership_writer.set_thresholds(thresholds);
+        }
+        if let Some(beacon) = config.committee_sampling_beacon {
+            membership_writer.set_sampling_beacon(TYPES::Epoch::genesis(), beacon);
+        }
+        drop(membership_writer);
+
         SystemContext::new_from_channels(
             public_key,
             private_key,
@@ -4,7 +4,7 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{num::NonZeroUsize, sync::Arc, time::Duration};
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use async_broadcast::{Receiver, Sender};
@@ -17,7 +17,10 @@ use hotshot::{
 };
 use hotshot_task_impls::{events::HotShotEvent, network::NetworkMessageTaskState};
 use hotshot_types::{
-    message::UpgradeLock,
+    clock::Clock,
+    compact_vote::CompactVoteDecoder,
+    consensus::OuterConsensus,
+    message::{ReplayProtectionConfig, UpgradeLock},
     traits::{
         network::ConnectedNetwork,
         node_implementation::{NodeType, Versions},
@@ -162,13 +165,28 @@ pub async fn add_network_message_test_task<
     upgrade_lock: UpgradeLock<TYPES, V>,
     channel: Arc<NET>,
     public_key: TYPES::SignatureKey,
+    consensus: OuterConsensus<TYPES>,
+    membership: Arc<RwLock<TYPES::Membership>>,
+    epoch_height: u64,
 ) -> JoinHandle<()> {
     let net = Arc::clone(&channel);
-    let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
+    let network_state: NetworkMessageTaskState<_, _> = NetworkMessageTaskState {
         internal_event_stream: internal_event_stream.clone(),
         external_event_stream: external_event_stream.clone(),
         public_key,
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
+        consensus,
+        replay_protection: ReplayProtectionConfig::default(),
+        rejected_messages: lru::LruCache::new(NonZeroUsize::new(10_000).unwrap()),
+        beyond_horizon_messages: 0,
+        horizon_catchup: None,
+        clock: Clock::default(),
+        expired_messages: HashMap::new(),
+        compact_votes: CompactVoteDecoder::new(Arc::default()),
+        peer_scores: Arc::default(),
+        membership,
+        upgrade_lock: upgrade_lock.clone(),
+        epoch_height,
     };
 
     let network = Arc::clone(&net);
@@ -185,15 +203,22 @@ pub async fn add_network_message_test_task<
                 }
             };
 
+            // Drop the message unread if it has expired
+            let Some(message) = state.strip_ttl_header(&message).await else {
+                continue;
+            };
+
             // Deserialize the message
-            let deserialized_message: Message<TYPES> =
-                match upgrade_lock.deserialize(&message).await {
-                    Ok(message) => message,
-                    Err(e) => {
-                        tracing::error!("Failed to deserialize message: {:?}", e);
-                        continue;
-                    }
-                };
+            let deserialized_message: Message<TYPES> = match upgrade_lock
+                .deserialize_with(message, network.wire_format())
+                .await
+            {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::error!("Failed to deserialize message: {:?}", e);
+                    continue;
+                }
+            };
 
             // Handle the message
             state.handle_message(deserialized_message).await;
@@ -0,0 +1,204 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Result;
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::traits::TestableNodeImplementation;
+use hotshot_types::{
+    data::{null_block, Leaf2},
+    event::{Event, EventType},
+    traits::{
+        block_contents::BlockHeader,
+        election::Membership,
+        node_implementation::{NodeType, Versions},
+    },
+};
+use thiserror::Error;
+use vbs::version::{StaticVersionType, Version};
+
+use crate::{
+    test_runner::Node,
+    test_task::{AnyTestTaskState, TestResult, TestTaskState, TestTaskStateSeed},
+};
+
+/// the ways in which the network can fail to upgrade
+#[derive(Error, Debug)]
+pub enum UpgradeTaskErr<TYPES: NodeType> {
+    /// no upgrade certificate was ever decided
+    #[error("No upgrade certificate was decided")]
+    NoUpgradeDecided,
+    /// the decided upgrade certificate isn't for the version we upgrade to
+    #[error("Decided an upgrade to version {0}, expected version {1}")]
+    WrongVersion(Version, Version),
+    /// a node never decided a view of the new version
+    #[error("Node {node} decided up to view {decided:?}, but the new version starts in view {first_view:?}")]
+    NotUpgraded {
+        /// the node
+        node: usize,
+        /// the latest view the node decided
+        decided: Option<TYPES::View>,
+        /// the first view of the new version
+        first_view: TYPES::View,
+    },
+    /// a node runs a different version than the one we upgraded to
+    #[error("Node {node} runs version {actual} in view {view:?}, expected version {expected}")]
+    VersionMismatch {
+        /// the node
+        node: usize,
+        /// the view in which we checked the version
+        view: TYPES::View,
+        /// the version the node runs
+        actual: Version,
+        /// the version we upgraded to
+        expected: Version,
+    },
+    /// a node decided a block other than the null block between versions, wasting the view
+    #[error("Node {node} decided a non-null block in view {view:?}, which is between versions")]
+    NonNullBlock {
+        /// the node
+        node: usize,
+        /// the view of the block
+        view: TYPES::View,
+    },
+}
+
+/// Test task which checks that every node decides the upgrade to `V::Upgrade` and switches to
+/// the new version at its activation view, deciding only null blocks in between.
+pub struct UpgradeTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> {
+    /// handles to the nodes in the test
+    pub handles: Arc<RwLock<Vec<Node<TYPES, I, V>>>>,
+    /// latest view decided by each node
+    pub decided_views: BTreeMap<usize, TYPES::View>,
+    /// version and first view of the decided upgrade, once we have seen it
+    pub upgrade: Option<(Version, TYPES::View)>,
+    /// the first block other than the null block decided between versions, and the node which
+    /// decided it
+    pub non_null_block: Option<(usize, TYPES::View)>,
+}
+
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> UpgradeTask<TYPES, I, V> {
+    /// Whether `leaf` carries the null block
+    async fn is_null_block(&self, leaf: &Leaf2<TYPES>) -> bool {
+        // Every node has the same stake table, so any node's will do
+        let handles = self.handles.read().await;
+        let Some(node) = handles.first() else {
+            return false;
+        };
+        let num_storage_nodes = node
+            .handle
+            .hotshot
+            .memberships
+            .read()
+            .await
+            .total_nodes(leaf.epoch());
+
+        Some(leaf.block_header().payload_commitment()) == null_block::commitment(num_storage_nodes)
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> TestTaskState
+    for UpgradeTask<TYPES, I, V>
+{
+    type Event = Event<TYPES>;
+
+    async fn handle_event(&mut self, (message, id): (Self::Event, usize)) -> Result<()> {
+        if let EventType::Decide { leaf_chain, .. } = message.event {
+            for leaf_info in leaf_chain.iter() {
+                let leaf = &leaf_info.leaf;
+                if let Some(cert) = leaf.upgrade_certificate() {
+                    self.upgrade = Some((cert.data.new_version, cert.data.new_version_first_view));
+
+                    // Leaves keep the certificate until the new version starts, so it tells us
+                    // whether this one is between versions
+                    if cert.upgrading_in(leaf.view_number())
+                        && self.non_null_block.is_none()
+                        && !self.is_null_block(leaf).await
+                    {
+                        self.non_null_block = Some((id, leaf.view_number()));
+                    }
+                }
+
+                let decided = self.decided_views.entry(id).or_insert(leaf.view_number());
+                *decided = (*decided).max(leaf.view_number());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check(&self) -> TestResult {
+        if let Some((node, view)) = self.non_null_block {
+            return TestResult::Fail(Box::new(UpgradeTaskErr::<TYPES>::NonNullBlock {
+                node,
+                view,
+            }));
+        }
+
+        let Some((new_version, first_view)) = self.upgrade else {
+            return TestResult::Fail(Box::new(UpgradeTaskErr::<TYPES>::NoUpgradeDecided));
+        };
+        if new_version != V::Upgrade::VERSION {
+            return TestResult::Fail(Box::new(UpgradeTaskErr::<TYPES>::WrongVersion(
+                new_version,
+                V::Upgrade::VERSION,
+            )));
+        }
+
+        for node in self.handles.read().await.iter() {
+            let id = usize::try_from(node.node_id).unwrap();
+            let decided = self.decided_views.get(&id).copied();
+            let Some(view) = decided.filter(|view| *view >= first_view) else {
+                return TestResult::Fail(Box::new(UpgradeTaskErr::<TYPES>::NotUpgraded {
+                    node: id,
+                    decided,
+                    first_view,
+                }));
+            };
+
+            let actual = node
+                .handle
+                .hotshot
+                .upgrade_lock
+                .version_infallible(view)
+                .await;
+            if actual != V::Upgrade::VERSION {
+                return TestResult::Fail(Box::new(UpgradeTaskErr::<TYPES>::VersionMismatch {
+                    node: id,
+                    view,
+                    actual,
+                    expected: V::Upgrade::VERSION,
+                }));
+            }
+        }
+
+        TestResult::Pass
+    }
+}
+
+/// Seed for an [`UpgradeTask`], to pass to
+/// [`TestDescription::gen_launcher_with_tasks`](crate::test_builder::TestDescription::gen_launcher_with_tasks)
+pub struct UpgradeTaskDescription;
+
+#[async_trait]
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions>
+    TestTaskStateSeed<TYPES, I, V> for UpgradeTaskDescription
+{
+    async fn into_state(
+        self: Box<Self>,
+        handles: Arc<RwLock<Vec<Node<TYPES, I, V>>>>,
+    ) -> AnyTestTaskState<TYPES> {
+        Box::new(UpgradeTask {
+            handles,
+            decided_views: BTreeMap::new(),
+            upgrade: None,
+            non_null_block: None,
+        })
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::traits::TestableNodeImplementation;
+use hotshot_types::{
+    event::{Event, EventType},
+    traits::node_implementation::{ConsensusTime, NodeType, Versions},
+};
+use thiserror::Error;
+
+use crate::{
+    test_runner::Node,
+    test_task::{AnyTestTaskState, TestResult, TestTaskState, TestTaskStateSeed},
+};
+
+/// the ways in which VID share distribution can fail
+#[derive(Error, Debug)]
+pub enum VidDistributionTaskErr {
+    /// a node decided too many views without having received its VID share
+    #[error("Node {node} decided views {views:?} without its VID share")]
+    SharesMissing {
+        /// the node
+        node: usize,
+        /// the views decided without a share
+        views: Vec<u64>,
+    },
+    /// no node received a single VID share
+    #[error("No VID shares were received")]
+    NoShares,
+}
+
+/// Test task which checks that every staked node receives its VID share for a view before it
+/// decides the view, and reports how long the shares take to arrive.
+///
+/// The latency of a share is measured from the first time any node reports the view's proposal,
+/// which is when the leader sends the shares out.
+pub struct VidDistributionTask<TYPES: NodeType> {
+    /// number of staked nodes, which are the nodes with the lowest ids
+    pub num_staked_nodes: usize,
+    /// number of decided views a node may be missing its share for, e.g. after catching up
+    pub max_missing_shares_per_node: usize,
+    /// when each view was first proposed
+    pub view_starts: HashMap<TYPES::View, Instant>,
+    /// when each node received its share for each view
+    pub received: HashMap<TYPES::View, BTreeMap<usize, Instant>>,
+    /// views each node decided without having received its share
+    pub missing: BTreeMap<usize, BTreeSet<u64>>,
+}
+
+impl<TYPES: NodeType> VidDistributionTask<TYPES> {
+    /// Time from each view's proposal to each node receiving its share for the view
+    fn latencies(&self) -> Vec<Duration> {
+        let mut latencies = self
+            .received
+            .iter()
+            .filter_map(|(view, received)| {
+                let start = self.view_starts.get(view)?;
+                Some(
+                    received
+                        .values()
+                        .map(|time| time.saturating_duration_since(*start)),
+                )
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        latencies.sort();
+
+        latencies
+    }
+}
+
+/// The `percentile`th percentile of `sorted`, which must not be empty
+fn percentile(sorted: &[Duration], percentile: usize) -> Duration {
+    sorted[(sorted.len() - 1) * percentile / 100]
+}
+
+#[async_trait]
+impl<TYPES: NodeType> TestTaskState for VidDistributionTask<TYPES> {
+    type Event = Event<TYPES>;
+
+    async fn handle_event(&mut self, (message, id): (Self::Event, usize)) -> Result<()> {
+        let now = Instant::now();
+        match message.event {
+            EventType::QuorumProposal { proposal, .. } => {
+                self.view_starts
+                    .entry(proposal.data.view_number)
+                    .or_insert(now);
+            }
+            EventType::DaProposal { proposal, .. } => {
+                self.view_starts
+                    .entry(proposal.data.view_number)
+                    .or_insert(now);
+            }
+            EventType::VidShareReceived { view_number } => {
+                // The share may overtake the proposal
+                self.view_starts.entry(view_number).or_insert(now);
+                self.received
+                    .entry(view_number)
+                    .or_default()
+                    .entry(id)
+                    .or_insert(now);
+            }
+            EventType::Decide { leaf_chain, .. } if id < self.num_staked_nodes => {
+                for leaf_info in leaf_chain.iter() {
+                    let view = leaf_info.leaf.view_number();
+                    let received = self
+                        .received
+                        .get(&view)
+                        .is_some_and(|received| received.contains_key(&id));
+                    if !received && leaf_info.vid_share.is_none() {
+                        self.missing.entry(id).or_default().insert(view.u64());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn check(&self) -> TestResult {
+        let latencies = self.latencies();
+        if latencies.is_empty() {
+            return TestResult::Fail(Box::new(VidDistributionTaskErr::NoShares));
+        }
+        tracing::info!(
+            "Distributed {} VID shares, latency p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            latencies.len(),
+            percentile(&latencies, 50),
+            percentile(&latencies, 90),
+            percentile(&latencies, 99),
+            percentile(&latencies, 100),
+        );
+
+        if let Some((node, views)) = self
+            .missing
+            .iter()
+            .find(|(_, views)| views.len() > self.max_missing_shares_per_node)
+        {
+            return TestResult::Fail(Box::new(VidDistributionTaskErr::SharesMissing {
+                node: *node,
+                views: views.iter().copied().collect(),
+            }));
+        }
+
+        TestResult::Pass
+    }
+}
+
+/// Seed for a [`VidDistributionTask`], to pass to
+/// [`TestDescription::gen_launcher_with_tasks`](crate::test_builder::TestDescription::gen_launcher_with_tasks)
+pub struct VidDistributionTaskDescription {
+    /// number of staked nodes in the test
+    pub num_staked_nodes: usize,
+    /// number of decided views a node may be missing its share for
+    pub max_missing_shares_per_node: usize,
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions>
+    TestTaskStateSeed<TYPES, I, V> for VidDistributionTaskDescription
+{
+    async fn into_state(
+        self: Box<Self>,
+        _handles: Arc<RwLock<Vec<Node<TYPES, I, V>>>>,
+    ) -> AnyTestTaskState<TYPES> {
+        Box::new(VidDistributionTask::<TYPES> {
+            num_staked_nodes: self.num_staked_nodes,
+            max_missing_shares_per_node: self.max_missing_shares_per_node,
+            view_starts: HashMap::new(),
+            received: HashMap::new(),
+            missing: BTreeMap::new(),
+        })
+    }
+}
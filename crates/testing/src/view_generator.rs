@@ -22,6 +22,7 @@ use hotshot_example_types::{
     state_types::{TestInstanceState, TestValidatedState},
 };
 use hotshot_types::{
+    clock::Clock,
     data::{
         DaProposal2, EpochNumber, Leaf2, QuorumProposal2, VidDisperse, VidDisperseShare2,
         ViewChangeEvidence, ViewNumber,
@@ -76,7 +77,7 @@ impl TestView {
     pub async fn genesis(membership: &Arc<RwLock<<TestTypes as NodeType>::Membership>>) -> Self {
         let genesis_view = ViewNumber::new(1);
         let genesis_epoch = EpochNumber::new(0);
-        let upgrade_lock = UpgradeLock::new();
+        let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
 
         let transactions = Vec::new();
 
@@ -108,6 +109,7 @@ impl TestView {
             genesis_epoch,
             transactions.clone(),
             &private_key,
+            &upgrade_lock,
         )
         .await;
 
@@ -131,6 +133,7 @@ impl TestView {
             payload_commitment,
             builder_commitment,
             metadata,
+            &Clock::default(),
         );
 
         let quorum_proposal_inner = QuorumProposal2::<TestTypes> {
@@ -150,9 +153,13 @@ impl TestView {
 
         let encoded_transactions = Arc::from(TestTransaction::encode(&transactions));
         let encoded_transactions_hash = Sha256::digest(&encoded_transactions);
-        let block_payload_signature =
-            <TestTypes as NodeType>::SignatureKey::sign(&private_key, &encoded_transactions_hash)
-                .expect("Failed to sign block payload");
+        let block_payload_signature = <TestTypes as NodeType>::SignatureKey::sign(
+            &private_key,
+            &upgrade_lock
+                .signing_message(&encoded_transactions_hash, genesis_view)
+                .await,
+        )
+        .expect("Failed to sign block payload");
 
         let da_proposal_inner = DaProposal2::<TestTypes> {
             encoded_transactions: encoded_transactions.clone(),
@@ -172,8 +179,13 @@ impl TestView {
             transactions: transactions.clone(),
         });
 
-        let signature = <BLSPubKey as SignatureKey>::sign(&private_key, leaf.commit().as_ref())
-            .expect("Failed to sign leaf commitment!");
+        let signature = <BLSPubKey as SignatureKey>::sign(
+            &private_key,
+            &upgrade_lock
+                .signing_message(leaf.commit().as_ref(), genesis_view)
+                .await,
+        )
+        .expect("Failed to sign leaf commitment!");
 
         let quorum_proposal = Proposal {
             data: quorum_proposal_inner,
@@ -253,6 +265,7 @@ impl TestView {
             self.epoch_number,
             transactions.clone(),
             &private_key,
+            &self.upgrade_lock,
         )
         .await;
 
@@ -368,6 +381,11 @@ impl TestView {
             builder_commitment,
             metadata,
             random,
+            base_fee: None,
+            builder_fee: None,
+            chain_config: None,
+            stake_tables: None,
+            extension: None,
         };
 
         let proposal = QuorumProposal2::<TestTypes> {
@@ -386,8 +404,14 @@ impl TestView {
             transactions: transactions.clone(),
         });
 
-        let signature = <BLSPubKey as SignatureKey>::sign(&private_key, leaf.commit().as_ref())
-            .expect("Failed to sign leaf commitment.");
+        let signature = <BLSPubKey as SignatureKey>::sign(
+            &private_key,
+            &self
+                .upgrade_lock
+                .signing_message(leaf.commit().as_ref(), next_view)
+                .await,
+        )
+        .expect("Failed to sign leaf commitment.");
 
         let quorum_proposal = Proposal {
             data: proposal,
@@ -397,9 +421,14 @@ impl TestView {
 
         let encoded_transactions = Arc::from(TestTransaction::encode(transactions));
         let encoded_transactions_hash = Sha256::digest(&encoded_transactions);
-        let block_payload_signature =
-            <TestTypes as NodeType>::SignatureKey::sign(&private_key, &encoded_transactions_hash)
-                .expect("Failed to sign block payload");
+        let block_payload_signature = <TestTypes as NodeType>::SignatureKey::sign(
+            &private_key,
+            &self
+                .upgrade_lock
+                .signing_message(&encoded_transactions_hash, next_view)
+                .await,
+        )
+        .expect("Failed to sign block payload");
 
         let da_proposal_inner = DaProposal2::<TestTypes> {
             encoded_transactions: encoded_transactions.clone(),
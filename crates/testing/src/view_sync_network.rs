@@ -0,0 +1,328 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! In-process network of view sync tasks whose relays can lose votes.
+//!
+//! Every node runs only its view sync task. Votes go to the relay [`view_sync_relay`] picks for
+//! them and certificates to every node, the way the network task routes them, so a round of view
+//! sync runs end to end without consensus or a real network. A lossy relay drops a fraction of
+//! the votes of each phase it receives. The drops are spread evenly over the votes in the order
+//! they arrive rather than drawn at random, so how many votes reach a relay, and with that which
+//! certificates form, does not depend on the order the votes race in.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_broadcast::{broadcast, Sender};
+use futures::{
+    future::join_all,
+    stream::{select_all, StreamExt},
+};
+use hotshot::{
+    tasks::task_state::CreateTaskState, traits::NodeImplementation, types::SystemContextHandle,
+};
+use hotshot_task::task::Task;
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    helpers::view_sync_relay,
+    view_sync::{ViewSyncPhase, ViewSyncTaskState},
+};
+use hotshot_types::{
+    traits::node_implementation::{ConsensusTime, NodeType, Versions},
+    vote::{Certificate, HasViewNumber, Vote},
+};
+use tokio::time::timeout_at;
+
+/// Fraction of the votes of each phase a lossy relay drops
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RelayLoss {
+    /// fraction of pre-commit votes dropped
+    pub pre_commit: f64,
+    /// fraction of commit votes dropped
+    pub commit: f64,
+    /// fraction of finalize votes dropped
+    pub finalize: f64,
+}
+
+impl RelayLoss {
+    /// Drop `fraction` of the votes of every phase
+    #[must_use]
+    pub fn uniform(fraction: f64) -> Self {
+        Self {
+            pre_commit: fraction,
+            commit: fraction,
+            finalize: fraction,
+        }
+    }
+
+    /// Fraction of the votes of `phase` dropped
+    #[must_use]
+    pub fn fraction(&self, phase: &ViewSyncPhase) -> f64 {
+        match phase {
+            ViewSyncPhase::None => 0.0,
+            ViewSyncPhase::PreCommit => self.pre_commit,
+            ViewSyncPhase::Commit => self.commit,
+            ViewSyncPhase::Finalize => self.finalize,
+        }
+    }
+}
+
+/// A certificate a relay formed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormedCertificate {
+    /// the phase the certificate completes
+    pub phase: ViewSyncPhase,
+    /// the relay index the votes were sent to
+    pub relay: u64,
+    /// the node which formed the certificate
+    pub relay_node: u64,
+}
+
+impl FormedCertificate {
+    /// The certificate of `phase` formed by the node which is relay number `relay`
+    #[must_use]
+    pub fn new(phase: ViewSyncPhase, relay: u64, relay_node: u64) -> Self {
+        Self {
+            phase,
+            relay,
+            relay_node,
+        }
+    }
+}
+
+/// What happened in a round of view sync
+#[derive(Clone, Debug, Default)]
+pub struct ViewSyncRun {
+    /// each distinct certificate formed, in the order they were first formed
+    pub certificates: Vec<FormedCertificate>,
+    /// relay indices which some node timed out waiting on
+    pub timed_out_relays: BTreeSet<u64>,
+    /// nodes which moved on to the round being synced
+    pub synced: BTreeSet<u64>,
+    /// number of votes the lossy relays dropped
+    pub dropped_votes: usize,
+    /// time from the start of the round until it completed, or the budget ran out
+    pub elapsed: Duration,
+}
+
+impl ViewSyncRun {
+    /// Whether a finalize certificate formed and every one of `num_nodes` nodes moved on
+    #[must_use]
+    pub fn completed(&self, num_nodes: usize) -> bool {
+        self.synced.len() == num_nodes
+            && self
+                .certificates
+                .iter()
+                .any(|certificate| certificate.phase == ViewSyncPhase::Finalize)
+    }
+
+    /// Record that `relay_node` formed a certificate of `phase` as relay number `relay`
+    fn record(&mut self, phase: ViewSyncPhase, relay: u64, relay_node: u64) {
+        let certificate = FormedCertificate::new(phase, relay, relay_node);
+        if !self.certificates.contains(&certificate) {
+            self.certificates.push(certificate);
+        }
+    }
+}
+
+/// View sync tasks of a set of nodes, wired together through relays which may lose votes
+pub struct LossyViewSyncNetwork<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
+    /// the handles of the nodes, which are identified by their index
+    handles: Vec<SystemContextHandle<TYPES, I, V>>,
+    /// how lossy each lossy node is when it acts as a relay
+    losses: HashMap<u64, RelayLoss>,
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> LossyViewSyncNetwork<TYPES, I, V> {
+    /// A network of the nodes of `handles`, none of which loses votes
+    #[must_use]
+    pub fn new(handles: Vec<SystemContextHandle<TYPES, I, V>>) -> Self {
+        Self {
+            handles,
+            losses: HashMap::new(),
+        }
+    }
+
+    /// Make `node` drop votes according to `loss` whenever it acts as a relay
+    #[must_use]
+    pub fn with_lossy_relay(mut self, node: u64, loss: RelayLoss) -> Self {
+        self.losses.insert(node, loss);
+        self
+    }
+
+    /// The node which is relay number `relay` in `round`
+    ///
+    /// # Panics
+    /// If the relay cannot be calculated or is not one of the nodes
+    pub async fn relay_node(&self, round: TYPES::View, relay: u64) -> u64 {
+        self.relay_index(round, relay).await as u64
+    }
+
+    /// Index in `handles` of the node which is relay number `relay` in `round`
+    async fn relay_index(&self, round: TYPES::View, relay: u64) -> usize {
+        let membership = self.handles[0].hotshot.memberships.read().await;
+        let key = view_sync_relay::<TYPES>(&membership, round, relay, TYPES::Epoch::genesis())
+            .expect("Failed to calculate the view sync relay");
+
+        self.handles
+            .iter()
+            .position(|handle| handle.public_key() == key)
+            .expect("The view sync relay is not one of the nodes")
+    }
+
+    /// Time out every node in the two views before `round`, which starts view sync for `round`,
+    /// and run the protocol until every node has synced or `budget` runs out
+    pub async fn run(&self, round: TYPES::View, budget: Duration) -> ViewSyncRun {
+        let epoch = TYPES::Epoch::genesis();
+        let mut to_nodes = Vec::new();
+        let mut from_nodes = Vec::new();
+        let mut tasks = Vec::new();
+        for handle in &self.handles {
+            let (to_node, node_receiver) = broadcast(1024);
+            let (node_sender, from_node) = broadcast(1024);
+            let state = ViewSyncTaskState::<TYPES, I, V>::create_from(handle).await;
+            tasks.push(Task::new(state, node_sender, node_receiver).run());
+            to_nodes.push(to_node);
+            from_nodes.push(from_node);
+        }
+        let mut events = select_all(
+            from_nodes
+                .into_iter()
+                .enumerate()
+                .map(|(node, receiver)| receiver.map(move |event| (node, event)).boxed()),
+        );
+
+        let start = Instant::now();
+        let deadline = tokio::time::Instant::now() + budget;
+        for to_node in &to_nodes {
+            for view in [round - 2, round - 1] {
+                deliver(to_node, HotShotEvent::Timeout(view, epoch)).await;
+            }
+        }
+
+        let mut run = ViewSyncRun::default();
+        let mut votes_received = HashMap::new();
+        while !run.completed(self.handles.len()) {
+            let Ok(Some((node, event))) = timeout_at(deadline, events.next()).await else {
+                break;
+            };
+            let (phase, vote_round, relay, vote) = match event.as_ref() {
+                HotShotEvent::ViewSyncPreCommitVoteSend(vote) => (
+                    ViewSyncPhase::PreCommit,
+                    vote.view_number(),
+                    vote.date().relay,
+                    HotShotEvent::ViewSyncPreCommitVoteRecv(vote.clone()),
+                ),
+                HotShotEvent::ViewSyncCommitVoteSend(vote) => (
+                    ViewSyncPhase::Commit,
+                    vote.view_number(),
+                    vote.date().relay,
+                    HotShotEvent::ViewSyncCommitVoteRecv(vote.clone()),
+                ),
+                HotShotEvent::ViewSyncFinalizeVoteSend(vote) => (
+                    ViewSyncPhase::Finalize,
+                    vote.view_number(),
+                    vote.date().relay,
+                    HotShotEvent::ViewSyncFinalizeVoteRecv(vote.clone()),
+                ),
+                HotShotEvent::ViewSyncPreCommitCertificateSend(certificate, _) => {
+                    run.record(
+                        ViewSyncPhase::PreCommit,
+                        certificate.data().relay,
+                        node as u64,
+                    );
+                    let received =
+                        HotShotEvent::ViewSyncPreCommitCertificateRecv(certificate.clone());
+                    deliver_to_all(&to_nodes, received).await;
+                    continue;
+                }
+                HotShotEvent::ViewSyncCommitCertificateSend(certificate, _) => {
+                    run.record(ViewSyncPhase::Commit, certificate.data().relay, node as u64);
+                    let received = HotShotEvent::ViewSyncCommitCertificateRecv(certificate.clone());
+                    deliver_to_all(&to_nodes, received).await;
+                    continue;
+                }
+                HotShotEvent::ViewSyncFinalizeCertificateSend(certificate, _) => {
+                    run.record(
+                        ViewSyncPhase::Finalize,
+                        certificate.data().relay,
+                        node as u64,
+                    );
+                    let received =
+                        HotShotEvent::ViewSyncFinalizeCertificateRecv(certificate.clone());
+                    deliver_to_all(&to_nodes, received).await;
+                    continue;
+                }
+                // Events a node sends itself go back to it, as they would on its internal stream
+                HotShotEvent::ViewSyncTimeout(_, relay, _) => {
+                    run.timed_out_relays.insert(*relay);
+                    deliver(&to_nodes[node], event.as_ref().clone()).await;
+                    continue;
+                }
+                HotShotEvent::ViewChange(view, _) => {
+                    if *view >= round {
+                        run.synced.insert(node as u64);
+                    }
+                    deliver(&to_nodes[node], event.as_ref().clone()).await;
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let relay_node = self.relay_index(vote_round, relay).await;
+            let received = votes_received
+                .entry((relay_node, phase.clone(), relay))
+                .or_insert(0_u64);
+            let fraction = self
+                .losses
+                .get(&(relay_node as u64))
+                .map_or(0.0, |loss| loss.fraction(&phase));
+            let dropped = is_dropped(*received, fraction);
+            *received += 1;
+            if dropped {
+                run.dropped_votes += 1;
+            } else {
+                deliver(&to_nodes[relay_node], vote).await;
+            }
+        }
+        run.elapsed = start.elapsed();
+
+        deliver_to_all(&to_nodes, HotShotEvent::Shutdown).await;
+        join_all(tasks).await;
+
+        run
+    }
+}
+
+/// Whether a relay dropping `fraction` of its votes drops the vote after the first `received`.
+/// Spreads the drops evenly, so that of the first `n` votes, `floor(n * fraction)` are dropped.
+#[allow(clippy::cast_precision_loss)]
+fn is_dropped(received: u64, fraction: f64) -> bool {
+    ((received + 1) as f64 * fraction).floor() > (received as f64 * fraction).floor()
+}
+
+/// Send `event` to a node
+async fn deliver<TYPES: NodeType>(
+    to_node: &Sender<Arc<HotShotEvent<TYPES>>>,
+    event: HotShotEvent<TYPES>,
+) {
+    if let Err(e) = to_node.broadcast_direct(Arc::new(event)).await {
+        tracing::warn!("Failed to deliver an event to a view sync task: {e}");
+    }
+}
+
+/// Send `event` to every node
+async fn deliver_to_all<TYPES: NodeType>(
+    to_nodes: &[Sender<Arc<HotShotEvent<TYPES>>>],
+    event: HotShotEvent<TYPES>,
+) {
+    for to_node in to_nodes {
+        deliver(to_node, event.clone()).await;
+    }
+}
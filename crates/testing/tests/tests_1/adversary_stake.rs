@@ -0,0 +1,72 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::BTreeSet;
+
+use hotshot_testing::byzantine::adversary_stake::{adversary_stakes, is_tolerated};
+use hotshot_types::{
+    signature_key::BLSPubKey,
+    stake_table::{StakeTableEntry, ThresholdFunction},
+    traits::signature_key::SignatureKey,
+};
+use primitive_types::U256;
+
+/// Total stake of the adversaries and of the honest nodes given `fraction` of the stake
+fn split(num_nodes: usize, adversaries: &BTreeSet<u64>, fraction: (u64, u64)) -> (u64, u64) {
+    let stakes = adversary_stakes(num_nodes, adversaries, fraction);
+    assert_eq!(stakes.len(), num_nodes);
+    stakes
+        .iter()
+        .fold((0, 0), |(adversary, honest), (node, stake)| {
+            if adversaries.contains(node) {
+                (adversary + stake, honest)
+            } else {
+                (adversary, honest + stake)
+            }
+        })
+}
+
+#[cfg(test)]
+#[test]
+fn test_adversary_stakes_give_exact_fraction() {
+    let adversaries = BTreeSet::from([7, 8, 9]);
+    for (numerator, denominator) in [(1, 10), (333, 1000), (1, 3), (334, 1000), (9, 10)] {
+        let (adversary, honest) = split(10, &adversaries, (numerator, denominator));
+        assert_eq!(adversary * denominator, (adversary + honest) * numerator);
+    }
+
+    // Stakes are as small as the fraction allows
+    let stakes = adversary_stakes(4, &BTreeSet::from([0]), (1, 4));
+    assert!(stakes.values().all(|stake| *stake == 1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_honest_quorum_only_below_one_third() {
+    let adversaries = BTreeSet::from([7, 8, 9]);
+    for fraction in [(1, 10), (333, 1000), (1, 3), (334, 1000), (1, 2)] {
+        let stakes = adversary_stakes(10, &adversaries, fraction);
+        let stake_table = (0..10)
+            .map(|node| StakeTableEntry {
+                stake_key: BLSPubKey::generated_from_seed_indexed([0u8; 32], node).0,
+                stake_amount: U256::from(stakes[&node]),
+            })
+            .collect::<Vec<_>>();
+        let (_, honest) = split(10, &adversaries, fraction);
+
+        // The honest nodes form quorums without the adversary exactly when it is tolerated
+        let quorum = ThresholdFunction::TwoThirds.for_stake_table(&stake_table);
+        assert_eq!(
+            U256::from(honest) >= quorum,
+            is_tolerated(fraction),
+            "{fraction:?}"
+        );
+    }
+
+    assert!(is_tolerated((333, 1000)));
+    assert!(!is_tolerated((1, 3)));
+    assert!(!is_tolerated((334, 1000)));
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    alert::{Alert, AlertDetector, AlertSink},
+    data::ViewNumber,
+    error::ConsensusError,
+    event::{Event, EventType},
+    peer_score::Misbehavior,
+    signature_key::BLSPubKey,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+};
+use tokio::time::{sleep, timeout};
+
+/// Records every alert it is handed
+struct RecordingSink(Arc<Mutex<Vec<Alert<TestTypes>>>>);
+
+#[async_trait]
+impl AlertSink<TestTypes> for RecordingSink {
+    async fn alert(&self, alert: &Alert<TestTypes>) {
+        self.0.lock().await.push(alert.clone());
+    }
+}
+
+fn timeout_event(view: u64) -> EventType<TestTypes> {
+    EventType::ViewTimeout {
+        view_number: ViewNumber::new(view),
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_alert_detector() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let view = (&mut generator).take(1).collect::<Vec<_>>().await.remove(0);
+    let decide = EventType::Decide {
+        leaf_chain: Arc::new(Vec::new()),
+        qc: Arc::new(view.quorum_proposal.data.justify_qc.clone()),
+        block_size: None,
+    };
+
+    // Every third timeout in a row alerts, until a decide starts the count again
+    let mut detector = AlertDetector::<TestTypes>::new(3);
+    assert!(detector.observe(&timeout_event(1)).is_none());
+    assert!(detector.observe(&timeout_event(2)).is_none());
+    assert_eq!(
+        detector.observe(&timeout_event(3)),
+        Some(Alert::RepeatedTimeouts {
+            view: ViewNumber::new(3),
+            consecutive: 3
+        })
+    );
+    assert!(detector.observe(&timeout_event(4)).is_none());
+    assert!(detector.observe(&decide).is_none());
+    assert!(detector.observe(&timeout_event(6)).is_none());
+    assert!(detector.observe(&timeout_event(7)).is_none());
+    assert!(detector.observe(&timeout_event(8)).is_some());
+
+    // Only the errors the operator can act on alert
+    assert_eq!(
+        detector.observe(&EventType::ConsensusError {
+            error: ConsensusError::StorageFailure("disk full".to_string()),
+        }),
+        Some(Alert::StorageFailure {
+            reason: "disk full".to_string()
+        })
+    );
+    assert_eq!(
+        detector.observe(&EventType::ConsensusError {
+            error: ConsensusError::InconsistentState("two leaves for one view".to_string()),
+        }),
+        Some(Alert::SafetyViolation {
+            reason: "two leaves for one view".to_string()
+        })
+    );
+    assert!(detector
+        .observe(&EventType::ConsensusError {
+            error: ConsensusError::ProposalInvalid("bad proposal".to_string()),
+        })
+        .is_none());
+
+    // A threshold of zero never alerts on timeouts
+    let mut detector = AlertDetector::<TestTypes>::new(0);
+    assert!((1..10).all(|view| detector.observe(&timeout_event(view)).is_none()));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_alert_sinks_receive_alerts() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let alerts = Arc::new(Mutex::new(Vec::new()));
+    handle
+        .hotshot
+        .add_alert_sink(RecordingSink(Arc::clone(&alerts)))
+        .await;
+
+    // Bans are raised by the scoreboard as it makes them, and only once per ban
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 7).0;
+    let peer_scores = &handle.hotshot.peer_scores;
+    while !peer_scores
+        .report(&peer, Misbehavior::Equivocation)
+        .await
+    {}
+    peer_scores
+        .report(&peer, Misbehavior::Equivocation)
+        .await;
+    assert_eq!(
+        *alerts.lock().await,
+        vec![Alert::PeerBanned {
+            peer,
+            cooldown: peer_scores.config().ban_cooldown,
+            bans: 1,
+        }]
+    );
+
+    // Other alerts are derived from the node's events
+    handle
+        .external_channel_sender()
+        .broadcast_direct(Event {
+            view_number: ViewNumber::new(1),
+            event: EventType::ConsensusError {
+                error: ConsensusError::StorageFailure("disk full".to_string()),
+            },
+        })
+        .await
+        .unwrap();
+    timeout(Duration::from_secs(5), async {
+        while alerts.lock().await.len() < 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("The storage failure was not alerted");
+    assert_eq!(
+        alerts.lock().await[1],
+        Alert::StorageFailure {
+            reason: "disk full".to_string()
+        }
+    );
+}
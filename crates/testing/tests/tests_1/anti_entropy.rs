@@ -0,0 +1,160 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::{run_test, test_scripts};
+use hotshot_task_impls::{anti_entropy::AntiEntropyTaskState, events::HotShotEvent::*};
+use hotshot_testing::{
+    all_predicates,
+    helpers::build_system_handle,
+    predicates::event::{all_predicates, exact},
+    script::{Expectations, InputOrder, TaskScript},
+    serial,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    anti_entropy::{CertificateDigest, CertificateSync},
+    data::ViewNumber,
+    signature_key::BLSPubKey,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+    vote::HasViewNumber,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_certificate_sync_only_carries_newer_certificates() {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+    let high_qc = views[2].quorum_proposal.data.justify_qc.clone();
+    let da_cert = views[2].da_certificate.clone();
+
+    let behind = CertificateDigest::<TestTypes> {
+        view: ViewNumber::new(1),
+        high_qc: ViewNumber::genesis(),
+        timeout_cert: None,
+        da_cert: None,
+    };
+    let sync = CertificateSync::newer_than(
+        &behind,
+        ViewNumber::new(3),
+        &high_qc,
+        None,
+        Some(&da_cert),
+    );
+    assert_eq!(sync.high_qc, Some(high_qc.clone()));
+    assert_eq!(sync.da_cert, Some(da_cert.clone()));
+    assert!(sync.timeout_cert.is_none());
+
+    let caught_up = CertificateDigest::<TestTypes> {
+        view: ViewNumber::new(3),
+        high_qc: high_qc.view_number(),
+        timeout_cert: None,
+        da_cert: Some(da_cert.view_number()),
+    };
+    assert!(CertificateSync::newer_than(
+        &caught_up,
+        ViewNumber::new(3),
+        &high_qc,
+        None,
+        Some(&da_cert),
+    )
+    .is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_anti_entropy_task_answers_stale_digest() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 3).0;
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+    let high_qc = views[2].quorum_proposal.data.justify_qc.clone();
+    let da_cert = views[2].da_certificate.clone();
+
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+    consensus_writer.update_high_qc(high_qc.clone()).unwrap();
+    consensus_writer.update_saved_da_certs(da_cert.view_number(), da_cert.clone());
+    let cur_view = consensus_writer.cur_view();
+    drop(consensus_writer);
+
+    let digest = CertificateDigest {
+        view: ViewNumber::genesis(),
+        high_qc: ViewNumber::genesis(),
+        timeout_cert: None,
+        da_cert: None,
+    };
+    let inputs = vec![serial![CertificateDigestRecv(digest, peer)]];
+
+    let state = AntiEntropyTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations: vec![Expectations::from_outputs(vec![exact(
+            CertificateSyncSend(
+                CertificateSync {
+                    view: cur_view,
+                    high_qc: Some(high_qc),
+                    timeout_cert: None,
+                    da_cert: Some(da_cert),
+                },
+                handle.public_key(),
+                peer,
+            ),
+        )])],
+    };
+
+    run_test![inputs, script].await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_anti_entropy_task_accepts_newer_certificates() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 3).0;
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+    let high_qc = views[2].quorum_proposal.data.justify_qc.clone();
+    let da_cert = views[2].da_certificate.clone();
+
+    let sync = CertificateSync {
+        view: ViewNumber::new(3),
+        high_qc: Some(high_qc.clone()),
+        timeout_cert: None,
+        da_cert: Some(da_cert.clone()),
+    };
+    let inputs = vec![serial![CertificateSyncRecv(sync, peer)]];
+
+    let state = AntiEntropyTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations: vec![Expectations::from_outputs(all_predicates![
+            exact(Qc2Formed(either::Left(high_qc))),
+            exact(DaCertificateRecv(da_cert)),
+        ])],
+    };
+
+    run_test![inputs, script].await;
+}
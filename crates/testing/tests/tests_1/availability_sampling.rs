@@ -0,0 +1,82 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_task_impls::availability::SamplingOutcomes;
+use hotshot_testing::helpers::key_pair_for_id;
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+// Outcomes of concurrently sampled blocks are counted in decide order, so a block which finishes
+// sampling early neither resets nor extends the failures of the blocks decided before it
+#[cfg(test)]
+#[test]
+fn test_availability_sampling_outcomes_in_decide_order() {
+    hotshot::helpers::initialize_logging();
+
+    let key = key_pair_for_id::<TestTypes>(1).1;
+    let mut outcomes = SamplingOutcomes::<TestTypes>::new();
+    for view in 1..=4 {
+        outcomes.start(ViewNumber::new(view));
+    }
+
+    // Views 2 and 3 finish first and wait for view 1
+    assert!(outcomes
+        .finish(ViewNumber::new(3), vec![key.clone()])
+        .is_empty());
+    assert!(outcomes.finish(ViewNumber::new(2), Vec::new()).is_empty());
+    assert_eq!(outcomes.consecutive_failures(), 0);
+
+    // View 1 failing is counted before the success of view 2 resets the count
+    let failures = outcomes.finish(ViewNumber::new(1), vec![key.clone()]);
+    assert_eq!(
+        failures,
+        vec![
+            (ViewNumber::new(1), 1, vec![key.clone()]),
+            (ViewNumber::new(3), 1, vec![key.clone()])
+        ]
+    );
+    assert_eq!(outcomes.consecutive_failures(), 1);
+
+    let failures = outcomes.finish(ViewNumber::new(4), vec![key.clone()]);
+    assert_eq!(failures, vec![(ViewNumber::new(4), 2, vec![key.clone()])]);
+
+    // Outcomes of views which are not being sampled are ignored
+    assert!(outcomes.finish(ViewNumber::new(9), vec![key]).is_empty());
+    assert_eq!(outcomes.consecutive_failures(), 2);
+}
+
+// A success only resets the failures of the blocks decided before it
+#[cfg(test)]
+#[test]
+fn test_availability_sampling_outcomes_reset() {
+    hotshot::helpers::initialize_logging();
+
+    let key = key_pair_for_id::<TestTypes>(1).1;
+    let mut outcomes = SamplingOutcomes::<TestTypes>::new();
+    for view in 1..=5 {
+        outcomes.start(ViewNumber::new(view));
+    }
+
+    // Views 4 and 5 fail while view 3 is still being sampled
+    outcomes.finish(ViewNumber::new(1), vec![key.clone()]);
+    outcomes.finish(ViewNumber::new(2), vec![key.clone()]);
+    assert!(outcomes
+        .finish(ViewNumber::new(5), vec![key.clone()])
+        .is_empty());
+    assert!(outcomes
+        .finish(ViewNumber::new(4), vec![key.clone()])
+        .is_empty());
+    assert_eq!(outcomes.consecutive_failures(), 2);
+
+    let failures = outcomes.finish(ViewNumber::new(3), Vec::new());
+    assert_eq!(
+        failures
+            .iter()
+            .map(|(view, failures, _)| (*view, *failures))
+            .collect::<Vec<_>>(),
+        vec![(ViewNumber::new(4), 1), (ViewNumber::new(5), 2)]
+    );
+}
@@ -0,0 +1,116 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot::{HotShotInitializer, SystemContext};
+use hotshot_example_types::{
+    block_types::{TestBlockHeader, TestBlockPayload, TestMetadata, TestTransaction},
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestInstanceState,
+};
+use hotshot_testing::test_builder::TestDescription;
+use hotshot_types::{
+    clock::Clock,
+    data::ViewNumber,
+    error::BlockValidationError,
+    traits::{
+        block_contents::vid_commitment, node_implementation::ConsensusTime, BlockPayload,
+        EncodeBytes,
+    },
+    ValidatorConfig,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_validate_block_for_proposal() {
+    hotshot::helpers::initialize_logging();
+
+    let launcher =
+        TestDescription::<TestTypes, MemoryImpl, TestVersions>::default().gen_launcher(0);
+    let validator_config = ValidatorConfig::generated_from_seed_indexed([0u8; 32], 0, 1, true);
+    let initializer =
+        HotShotInitializer::<TestTypes>::from_genesis::<TestVersions>(TestInstanceState::default())
+            .await
+            .unwrap();
+    // Consensus is not started, so the genesis leaf stays the only one
+    let (mut handle, _, _) = SystemContext::<TestTypes, MemoryImpl, TestVersions>::builder()
+        .keys(validator_config.public_key, validator_config.private_key)
+        .config(launcher.resource_generator.config.clone())
+        .network((launcher.resource_generator.channel_generator)(0).await)
+        .initializer(initializer)
+        .storage((launcher.resource_generator.storage)(0))
+        .init()
+        .await
+        .unwrap();
+    let num_nodes = launcher
+        .resource_generator
+        .config
+        .num_nodes_with_stake
+        .get();
+    let genesis_view = ViewNumber::genesis();
+    let genesis_leaf = handle.decided_leaf().await;
+
+    let payload = TestBlockPayload {
+        transactions: vec![TestTransaction::new(vec![1, 2, 3])],
+    };
+    let metadata = TestMetadata {
+        num_transactions: 1,
+    };
+    let header = TestBlockHeader::new(
+        &genesis_leaf,
+        vid_commitment(&payload.encode(), num_nodes),
+        payload.builder_commitment(&metadata),
+        metadata,
+        &Clock::default(),
+    );
+    handle
+        .validate_block_for_proposal(&header, &payload, genesis_view)
+        .await
+        .unwrap();
+
+    // A parent this node has not seen
+    assert_eq!(
+        handle
+            .validate_block_for_proposal(&header, &payload, genesis_view + 5)
+            .await,
+        Err(BlockValidationError::UnknownParent(5))
+    );
+
+    // A block which skips a height
+    let mut skipping = header.clone();
+    skipping.block_number += 1;
+    assert_eq!(
+        handle
+            .validate_block_for_proposal(&skipping, &payload, genesis_view)
+            .await,
+        Err(BlockValidationError::BlockNumber {
+            expected: 1,
+            actual: 2,
+        })
+    );
+
+    // A header for another payload
+    let other_payload = TestBlockPayload {
+        transactions: vec![TestTransaction::new(vec![4, 5, 6])],
+    };
+    assert!(matches!(
+        handle
+            .validate_block_for_proposal(&header, &other_payload, genesis_view)
+            .await,
+        Err(BlockValidationError::BuilderCommitment { .. })
+    ));
+
+    // A header which commits to the payload for a different number of storage nodes
+    let mut wrong_commitment = header.clone();
+    wrong_commitment.payload_commitment = vid_commitment(&payload.encode(), num_nodes + 1);
+    assert!(matches!(
+        handle
+            .validate_block_for_proposal(&wrong_commitment, &payload, genesis_view)
+            .await,
+        Err(BlockValidationError::PayloadCommitment { .. })
+    ));
+
+    handle.shut_down().await;
+}
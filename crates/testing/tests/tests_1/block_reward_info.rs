@@ -0,0 +1,99 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use hotshot::{types::EventType, HotShotInitializer, SystemContext};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestInstanceState,
+};
+use hotshot_testing::test_builder::TestDescription;
+use hotshot_types::ValidatorConfig;
+
+// Every block of a decided chain gets a reward record, naming this node, which leads and signs
+// everything, and the fee its builder claimed
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_reward_info_for_every_decided_block() {
+    hotshot::helpers::initialize_logging();
+
+    let launcher = TestDescription::<TestTypes, MemoryImpl, TestVersions> {
+        num_nodes_with_stake: 1,
+        start_nodes: 1,
+        num_bootstrap_nodes: 1,
+        da_staked_committee_size: 1,
+        ..TestDescription::default()
+    }
+    .gen_launcher(0);
+
+    let validator_config = ValidatorConfig::generated_from_seed_indexed([0u8; 32], 0, 1, true);
+    let builder_url = launcher
+        .resource_generator
+        .config
+        .builder_urls
+        .first()
+        .clone();
+    let initializer =
+        HotShotInitializer::<TestTypes>::from_genesis::<TestVersions>(TestInstanceState::default())
+            .await
+            .unwrap();
+
+    let handle = SystemContext::<TestTypes, MemoryImpl, TestVersions>::dev_single_node(
+        &validator_config,
+        builder_url,
+        (launcher.resource_generator.channel_generator)(0).await,
+        initializer,
+        (launcher.resource_generator.storage)(0),
+        (launcher.resource_generator.marketplace_config)(0),
+    )
+    .await
+    .unwrap();
+    let public_key = validator_config.public_key;
+
+    // Heights of the decided blocks, and of the blocks with reward records, in the order we saw
+    // them
+    let mut decided = Vec::new();
+    let mut rewarded = Vec::new();
+    let mut signed_by_us = false;
+    let mut voted_by_us = false;
+    let mut events = handle.event_stream();
+    while rewarded.len() < 4 || rewarded.len() < decided.len() {
+        let event = tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await
+            .expect("the dev node stopped deciding")
+            .unwrap();
+        match event.event {
+            EventType::Decide { leaf_chain, .. } => {
+                decided.extend(leaf_chain.iter().map(|info| info.leaf.height()));
+            }
+            EventType::BlockRewardInfo {
+                block_height,
+                leader,
+                builder_fee,
+                da_signers,
+                vote_participation,
+            } => {
+                assert_eq!(leader, public_key);
+                if block_height > 0 {
+                    assert!(builder_fee.is_some());
+                }
+                assert!(da_signers.iter().all(|signer| *signer == public_key));
+                assert!(vote_participation.iter_ones().all(|index| index == 0));
+                signed_by_us |= !da_signers.is_empty();
+                voted_by_us |= vote_participation.any();
+                rewarded.push(block_height);
+            }
+            _ => {}
+        }
+    }
+
+    // One record per decided block, in the order of the decided chains
+    assert_eq!(rewarded, decided);
+    assert!(signed_by_us);
+    assert!(voted_by_us);
+}
@@ -0,0 +1,121 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use async_broadcast::broadcast;
+use futures::StreamExt;
+use hotshot::types::{Event, EventType};
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use hotshot_task_impls::builder::BuilderClient;
+use hotshot_testing::block_builder::{
+    run_builder_source_0_1, BuilderTask, SimpleBuilderImplementation,
+};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        block_contents::vid_commitment,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+    },
+};
+use tide_disco::Url;
+use tokio::time::sleep;
+use vbs::version::StaticVersion;
+
+/// A URL on a free local port
+fn local_url() -> Url {
+    let port = portpicker::pick_unused_port().expect("No free ports");
+    Url::parse(&format!("http://localhost:{port}")).expect("Valid URL")
+}
+
+// A streaming builder offers its blocks over a stream which ends once it has nothing more to
+// offer, and a streamed block is claimed like a polled one
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_builder_streaming_and_claiming() {
+    hotshot::helpers::initialize_logging();
+
+    let (change_sender, change_receiver) = broadcast(16);
+    let (source, task) =
+        SimpleBuilderImplementation::create::<TestTypes>(10, HashMap::new(), change_sender).await;
+    let streaming_url = local_url();
+    source.clone().run(streaming_url.clone()).await;
+    let polling_url = local_url();
+    run_builder_source_0_1(polling_url.clone(), change_receiver, source);
+
+    let transactions = vec![TestTransaction::new(vec![1, 2, 3])];
+    Box::new(task).start(Box::new(futures::stream::iter(vec![Event {
+        view_number: ViewNumber::new(1),
+        event: EventType::Transactions {
+            transactions: transactions.clone(),
+        },
+    }])));
+
+    let client: BuilderClient<TestTypes, StaticVersion<0, 1>> = BuilderClient::new(streaming_url);
+    assert!(client.connect(Duration::from_secs(2)).await);
+
+    let (pub_key, private_key) =
+        <TestTypes as NodeType>::SignatureKey::generated_from_seed_indexed([0_u8; 32], 0);
+    let signature = <TestTypes as NodeType>::SignatureKey::sign(&private_key, &[0_u8; 32])
+        .expect("Failed to create dummy signature");
+    let parent = vid_commitment(&[], 1);
+
+    let started = Instant::now();
+    let blocks = loop {
+        let blocks = client
+            .available_blocks_stream(parent, 1, pub_key, &signature)
+            .await
+            .expect("Failed to subscribe to available blocks")
+            .collect::<Vec<_>>()
+            .await;
+        if !blocks.is_empty() {
+            break blocks;
+        }
+
+        // Wait for the builder to receive the transactions
+        assert!(
+            started.elapsed() < Duration::from_secs(2),
+            "Builder failed to offer a block in two seconds"
+        );
+        sleep(Duration::from_millis(20)).await;
+    };
+    assert_eq!(blocks.len(), 1);
+    let block = blocks
+        .into_iter()
+        .next()
+        .unwrap()
+        .expect("Streamed an error");
+
+    let data = client
+        .claim_block(block.block_hash.clone(), 1, pub_key, &signature)
+        .await
+        .expect("Failed to claim streamed block");
+    assert_eq!(data.block_payload.transactions, transactions);
+
+    // A block is claimed at most once
+    assert!(client
+        .claim_block(block.block_hash, 1, pub_key, &signature)
+        .await
+        .is_err());
+
+    // Builders without streaming block assembly refuse the subscription, so the leader falls back
+    // to polling them, which offers nothing more now that the transactions are claimed
+    let client: BuilderClient<TestTypes, StaticVersion<0, 1>> = BuilderClient::new(polling_url);
+    assert!(client.connect(Duration::from_secs(2)).await);
+    assert!(client
+        .available_blocks_stream(parent, 1, pub_key, &signature)
+        .await
+        .is_err());
+    assert!(client
+        .available_blocks(parent, 1, pub_key, &signature)
+        .await
+        .expect("Failed to poll available blocks")
+        .is_empty());
+}
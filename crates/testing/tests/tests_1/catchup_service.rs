@@ -0,0 +1,392 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_broadcast::broadcast;
+use futures::StreamExt;
+use hotshot::{tasks::task_state::CreateTaskState, types::SystemContextHandle};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::{run_test, test_scripts};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::{
+    catchup::{verify_qcs_in_batches, CatchupProgress, CatchupTaskState},
+    events::HotShotEvent::{self, *},
+};
+use hotshot_testing::{
+    helpers::build_system_handle,
+    predicates::event::exact,
+    script::{Expectations, InputOrder, TaskScript},
+    serial,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    catchup::{CatchupConfig, CatchupPage, CatchupRateLimiter, CatchupRequest, DecideProof},
+    data::{Leaf2, ViewNumber},
+    signature_key::BLSPubKey,
+    traits::{
+        consensus_api::ConsensusApi, node_implementation::ConsensusTime,
+        signature_key::SignatureKey, storage::Storage,
+    },
+    vote::HasViewNumber,
+};
+
+/// The leaves of views 1 to `count`, each extending the one before it, at heights 1 to `count`
+async fn leaf_chain(
+    handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+    count: usize,
+) -> Vec<Leaf2<TestTypes>> {
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    (&mut generator)
+        .take(count)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await
+}
+
+/// The request for the leaves after `parent` up to view `end`
+fn request_after(parent: &Leaf2<TestTypes>, end: u64) -> CatchupRequest<TestTypes> {
+    CatchupRequest {
+        view: ViewNumber::new(end),
+        start: parent.view_number() + 1,
+        end: ViewNumber::new(end),
+        cursor: parent.height() + 1,
+    }
+}
+
+/// The proof that the leaf is decided out of `leaves`: the leaf after it, and the QC of the leaf
+/// after that
+fn known_proof(
+    leaves: &[Leaf2<TestTypes>],
+) -> impl Fn(&Leaf2<TestTypes>) -> Option<DecideProof<TestTypes>> + '_ {
+    |leaf| {
+        let i = leaves.iter().position(|known| known == leaf)?;
+        Some(DecideProof {
+            child: leaves.get(i + 1)?.clone(),
+            qc: leaves.get(i + 2)?.justify_qc(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_catchup_pages_continue_chain() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let leaves = leaf_chain(&handle, 10).await;
+    let decided = leaves[..8].to_vec();
+    let view = ViewNumber::new(10);
+
+    // The first page holds views 2 to 4, proven decided by the leaf for view 5 and its QC
+    let request = request_after(&leaves[0], 8);
+    let page = CatchupPage::answer(request.clone(), view, decided.clone(), 3, |_| None);
+    assert_eq!(page.leaves, leaves[1..4].to_vec());
+    assert_eq!(
+        page.decide_proof,
+        Some(DecideProof {
+            child: leaves[4].clone(),
+            qc: leaves[5].justify_qc(),
+        })
+    );
+    assert_eq!(page.next_cursor, Some(5));
+    page.check(&request, &leaves[0]).unwrap();
+
+    // Each page continues from the last leaf of the one before
+    let request = request_after(&leaves[3], 8);
+    let page = CatchupPage::answer(request.clone(), view, decided.clone(), 3, |_| None);
+    assert_eq!(page.leaves, leaves[4..6].to_vec());
+    assert_eq!(page.next_cursor, Some(7));
+    page.check(&request, &leaves[3]).unwrap();
+
+    // The last decided leaves are proven by undecided leaves after them
+    let request = request_after(&leaves[5], 8);
+    let page = CatchupPage::answer(
+        request.clone(),
+        view,
+        decided.clone(),
+        3,
+        known_proof(&leaves),
+    );
+    assert_eq!(page.leaves, leaves[6..8].to_vec());
+    assert_eq!(page.next_cursor, None);
+    page.check(&request, &leaves[5]).unwrap();
+
+    // Without a proof, the last decided leaf is left out
+    let request = request_after(&leaves[6], 8);
+    let page = CatchupPage::answer(request.clone(), view, decided.clone(), 3, |_| None);
+    assert!(page.leaves.is_empty());
+    assert!(page.decide_proof.is_none());
+    page.check(&request, &leaves[6]).unwrap();
+
+    // A page with a gap, or which does not extend our last leaf, does not continue our chain
+    let request = request_after(&leaves[0], 8);
+    let mut gapped = CatchupPage::answer(request.clone(), view, decided.clone(), 3, |_| None);
+    gapped.leaves.remove(1);
+    assert!(gapped.check(&request, &leaves[0]).is_err());
+    let page = CatchupPage::answer(request.clone(), view, decided.clone(), 3, |_| None);
+    assert!(page.check(&request, &leaves[1]).is_err());
+    assert!(page
+        .check(&request_after(&leaves[1], 8), &leaves[0])
+        .is_err());
+
+    // Leaves which are certified but not proven decided are not accepted
+    let mut unproven = page.clone();
+    unproven.decide_proof = None;
+    assert!(unproven.check(&request, &leaves[0]).is_err());
+    let mut misproven = page;
+    misproven.decide_proof = known_proof(&leaves)(&leaves[4]);
+    assert!(misproven.check(&request, &leaves[0]).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_catchup_rate_limit() {
+    let config = CatchupConfig {
+        max_pages_per_second: 3,
+        max_pages_per_peer_per_second: 2,
+        ..CatchupConfig::default()
+    };
+    let mut limiter = CatchupRateLimiter::new(&config);
+    let peer = |index| BLSPubKey::generated_from_seed_indexed([0u8; 32], index).0;
+    let now = Instant::now();
+
+    // Each peer gets its own share, up to the total of all peers
+    assert!(limiter.admit(&peer(1), now));
+    assert!(limiter.admit(&peer(1), now));
+    assert!(!limiter.admit(&peer(1), now));
+    assert!(limiter.admit(&peer(2), now));
+    assert!(!limiter.admit(&peer(3), now));
+
+    // Both limits start over the next second
+    let later = now + Duration::from_secs(1);
+    assert!(limiter.admit(&peer(3), later));
+    assert!(limiter.admit(&peer(1), later));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_catchup_request_served_within_rate_limit() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 3).0;
+    let leaves = leaf_chain(&handle, 6).await;
+    handle
+        .storage()
+        .read()
+        .await
+        .append_decided_leaves(&leaves)
+        .await
+        .unwrap();
+
+    let mut state =
+        CatchupTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    state.config.page_size = 2;
+    state.rate_limiter = CatchupRateLimiter::new(&CatchupConfig {
+        max_pages_per_peer_per_second: 1,
+        ..CatchupConfig::default()
+    });
+
+    let request = request_after(&leaves[0], 6);
+    let cur_view = handle.hotshot.consensus().read().await.cur_view();
+    let page = CatchupPage::answer(request.clone(), cur_view, leaves.clone(), 2, |_| None);
+    assert_eq!(page.leaves, leaves[1..3].to_vec());
+
+    // The second request of the peer within a second goes unanswered
+    let inputs = vec![
+        serial![CatchupRequestRecv(request.clone(), peer)],
+        serial![CatchupRequestRecv(request, peer)],
+    ];
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations: vec![
+            Expectations::from_outputs(vec![exact(CatchupPageSend(
+                page,
+                handle.public_key(),
+                peer,
+            ))]),
+            Expectations::from_outputs(vec![]),
+        ],
+    };
+
+    run_test![inputs, script].await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_caught_up_leaves_are_persisted() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 3).0;
+    let leaves = leaf_chain(&handle, 6).await;
+
+    let mut state =
+        CatchupTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let request = request_after(&leaves[0], 6);
+    state.in_progress = Some(CatchupProgress {
+        peer,
+        request: request.clone(),
+        parent: leaves[0].clone(),
+        requested_at: Instant::now(),
+    });
+    let page = CatchupPage::answer(request, ViewNumber::new(6), leaves.clone(), 3, |_| None);
+
+    let (sender, mut receiver) = broadcast::<Arc<HotShotEvent<TestTypes>>>(16);
+    let internal_receiver = receiver.clone();
+    state
+        .handle_event(
+            Arc::new(CatchupPageRecv(page, peer)),
+            &sender,
+            &internal_receiver,
+        )
+        .await
+        .unwrap();
+
+    // The leaves are persisted as decided, and the next page is requested from the same peer
+    let persisted = handle
+        .storage()
+        .read()
+        .await
+        .load_decided_leaves(leaves[1].height())
+        .await
+        .unwrap();
+    assert_eq!(persisted, leaves[1..4].to_vec());
+    assert_eq!(state.caught_up, Some(leaves[3].clone()));
+
+    let event = receiver.recv_direct().await.unwrap();
+    let CatchupRequestSend(next, _, to) = event.as_ref() else {
+        panic!("Expected a catchup request, got {event:?}");
+    };
+    assert_eq!(*to, peer);
+    assert_eq!(next.cursor, leaves[4].height());
+    assert_eq!(next.start, leaves[4].view_number());
+
+    // A page from a peer we did not ask is ignored
+    let stranger = BLSPubKey::generated_from_seed_indexed([0u8; 32], 4).0;
+    let page = CatchupPage::answer(next.clone(), ViewNumber::new(6), leaves.clone(), 3, |_| {
+        None
+    });
+    state
+        .handle_event(
+            Arc::new(CatchupPageRecv(page, stranger)),
+            &sender,
+            &internal_receiver,
+        )
+        .await
+        .unwrap();
+    assert!(receiver.is_empty());
+    assert_eq!(state.caught_up, Some(leaves[3].clone()));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_catchup_qcs_verified_in_parallel_batches() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let leaves = leaf_chain(&handle, 8).await;
+    let qcs: Vec<_> = leaves[1..].iter().map(Leaf2::justify_qc).collect();
+    let membership = &handle.hotshot.memberships;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+
+    for batch_size in [1, 3, 100] {
+        verify_qcs_in_batches(qcs.clone(), membership, upgrade_lock, batch_size)
+            .await
+            .unwrap();
+    }
+
+    // A QC whose signatures are over another leaf fails its batch
+    let mut forged = qcs.clone();
+    forged[4].signatures = qcs[1].signatures.clone();
+    assert!(verify_qcs_in_batches(forged, membership, upgrade_lock, 3)
+        .await
+        .is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_catchup_page_with_forged_qc_is_requested_again() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 3).0;
+    let leaves = leaf_chain(&handle, 6).await;
+
+    let mut state =
+        CatchupTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let request = request_after(&leaves[0], 6);
+    state.in_progress = Some(CatchupProgress {
+        peer,
+        request: request.clone(),
+        parent: leaves[0].clone(),
+        requested_at: Instant::now(),
+    });
+    let mut page = CatchupPage::answer(
+        request.clone(),
+        ViewNumber::new(6),
+        leaves.clone(),
+        3,
+        |_| None,
+    );
+    if let Some(proof) = page.decide_proof.as_mut() {
+        proof.qc.signatures = leaves[1].justify_qc().signatures;
+    }
+
+    let (sender, mut receiver) = broadcast::<Arc<HotShotEvent<TestTypes>>>(16);
+    let internal_receiver = receiver.clone();
+    state
+        .handle_event(
+            Arc::new(CatchupPageRecv(page, peer)),
+            &sender,
+            &internal_receiver,
+        )
+        .await
+        .unwrap();
+
+    // The next page is not requested before the page is verified: the page is requested again
+    // from another peer instead
+    let event = receiver.recv_direct().await.unwrap();
+    let CatchupRequestSend(again, _, to) = event.as_ref() else {
+        panic!("Expected a catchup request, got {event:?}");
+    };
+    assert_eq!(
+        *again,
+        CatchupRequest {
+            view: again.view,
+            ..request
+        }
+    );
+    assert_ne!(*to, peer);
+    assert!(receiver.is_empty());
+    assert_eq!(state.in_progress.unwrap().request, *again);
+
+    // Nothing was applied
+    assert!(state.caught_up.is_none());
+    assert!(handle
+        .storage()
+        .read()
+        .await
+        .load_decided_leaves(leaves[1].height())
+        .await
+        .unwrap()
+        .is_empty());
+}
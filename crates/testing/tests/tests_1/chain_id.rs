@@ -0,0 +1,175 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{
+    ChainIdUpgradeTestVersions, MemoryImpl, TestTypes, TestVersions,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber,
+    message::UpgradeLock,
+    signature_key::BLSPubKey,
+    simple_certificate::UpgradeCertificate,
+    simple_vote::{QuorumData2, QuorumVote2, UpgradeProposalData, VersionedVoteData},
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+    vote::Vote,
+};
+use vbs::version::Version;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_votes_are_bound_to_their_chain() {
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let mainnet = UpgradeLock::<TestTypes, TestVersions>::new().with_chain_id(1);
+    let testnet = UpgradeLock::<TestTypes, TestVersions>::new().with_chain_id(2);
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let leaf = handle.hotshot.consensus().read().await.decided_leaf();
+    let data = QuorumData2 {
+        leaf_commit: leaf.commit(),
+        epoch: leaf.epoch(),
+    };
+    let view = ViewNumber::new(1);
+
+    let vote = QuorumVote2::<TestTypes>::create_signed_vote(
+        data.clone(),
+        view,
+        &public_key,
+        &private_key,
+        &testnet,
+    )
+    .await
+    .unwrap();
+
+    let testnet_commit = VersionedVoteData::new(data.clone(), view, &testnet)
+        .await
+        .unwrap()
+        .commit();
+    let mainnet_commit = VersionedVoteData::new(data, view, &mainnet)
+        .await
+        .unwrap()
+        .commit();
+    assert!(public_key.validate(&vote.signature(), testnet_commit.as_ref()));
+    assert!(!public_key.validate(&vote.signature(), mainnet_commit.as_ref()));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_proposals_are_bound_to_their_chain() {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let epoch_height = handle.hotshot.config.epoch_height;
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+    let membership = handle.hotshot.memberships.read().await;
+
+    // The generator signs for the default chain
+    let default_chain = UpgradeLock::<TestTypes, TestVersions>::new();
+    let other_chain = UpgradeLock::<TestTypes, TestVersions>::new().with_chain_id(1);
+    for view in &views {
+        assert!(view
+            .quorum_proposal
+            .validate_signature(&membership, epoch_height, &default_chain)
+            .await
+            .is_ok());
+        assert!(view
+            .quorum_proposal
+            .validate_signature(&membership, epoch_height, &other_chain)
+            .await
+            .is_err());
+    }
+
+    let view = ViewNumber::new(1);
+    assert_ne!(
+        default_chain.signing_message(&[1, 2, 3], view).await,
+        other_chain.signing_message(&[1, 2, 3], view).await
+    );
+}
+
+// Signatures and vote commitments are only bound to the chain from the version which introduces
+// the binding, so those of nodes which have not upgraded yet stay valid
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_chain_binding_gated_on_version() {
+    hotshot::helpers::initialize_logging();
+
+    let mainnet = UpgradeLock::<TestTypes, ChainIdUpgradeTestVersions>::new().with_chain_id(1);
+    let testnet = UpgradeLock::<TestTypes, ChainIdUpgradeTestVersions>::new().with_chain_id(2);
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, ChainIdUpgradeTestVersions>(1)
+        .await
+        .0;
+    let leaf = handle.hotshot.consensus().read().await.decided_leaf();
+    let data = QuorumData2 {
+        leaf_commit: leaf.commit(),
+        epoch: leaf.epoch(),
+    };
+    let before = ViewNumber::new(6);
+    let after = ViewNumber::new(7);
+    let vote_commit = |lock: &UpgradeLock<TestTypes, ChainIdUpgradeTestVersions>, view| {
+        VersionedVoteData::new_infallible(data.clone(), view, lock)
+    };
+
+    assert_eq!(
+        mainnet.signing_message(&[1, 2, 3], after).await,
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        vote_commit(&mainnet, after).await.commit(),
+        vote_commit(&testnet, after).await.commit()
+    );
+
+    let upgrade_data = UpgradeProposalData {
+        old_version: Version { major: 0, minor: 1 },
+        new_version: Version { major: 0, minor: 2 },
+        decide_by: before,
+        new_version_hash: [0u8; 12].to_vec(),
+        old_version_last_view: before,
+        new_version_first_view: after,
+    };
+    let certificate = UpgradeCertificate::new(
+        upgrade_data.clone(),
+        upgrade_data.commit(),
+        before,
+        None,
+        PhantomData,
+    );
+    for lock in [&mainnet, &testnet] {
+        *lock.decided_upgrade_certificate.write().await = Some(certificate.clone());
+    }
+
+    // Views before the upgrade are still unbound
+    assert_eq!(
+        mainnet.signing_message(&[1, 2, 3], before).await,
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        vote_commit(&mainnet, before).await.commit(),
+        vote_commit(&testnet, before).await.commit()
+    );
+
+    // While those after it are bound to the chain
+    assert_eq!(
+        mainnet.signing_message(&[1, 2, 3], after).await,
+        [1u64.to_le_bytes().as_slice(), &[1, 2, 3][..]].concat()
+    );
+    assert_ne!(
+        mainnet.signing_message(&[1, 2, 3], after).await,
+        testnet.signing_message(&[1, 2, 3], after).await
+    );
+    assert_ne!(
+        vote_commit(&mainnet, after).await.commit(),
+        vote_commit(&testnet, after).await.commit()
+    );
+}
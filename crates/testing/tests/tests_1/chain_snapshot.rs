@@ -0,0 +1,126 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::HotShotInitializer;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::{Leaf2, ViewNumber},
+    simple_certificate::QuorumCertificate2,
+    snapshot::ChainSnapshot,
+    traits::{
+        block_contents::BlockHeader, node_implementation::ConsensusTime,
+        states::ValidatedState,
+    },
+    vote::HasViewNumber,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_genesis_from_chain_snapshot() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let config = handle.hotshot.config.clone();
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(4).collect::<Vec<_>>().await;
+    let last_leaf = views[3].leaf.clone();
+
+    let snapshot = ChainSnapshot::<TestTypes> {
+        leaf: last_leaf.clone(),
+        validated_state: None,
+        stake_table: config.known_nodes_with_stake.clone(),
+        da_stake_table: config.known_da_nodes.clone(),
+    };
+    snapshot.validate().unwrap();
+
+    // The new chain starts over in the genesis view, from the block of the snapshot leaf
+    let genesis_leaf = snapshot.genesis_leaf();
+    assert_eq!(genesis_leaf.view_number(), ViewNumber::genesis());
+    assert_eq!(
+        genesis_leaf.block_header().block_number(),
+        last_leaf.block_header().block_number()
+    );
+    assert_eq!(genesis_leaf.parent_commitment(), last_leaf.commit());
+    assert_ne!(genesis_leaf.commit(), last_leaf.commit());
+
+    let high_qc = QuorumCertificate2::for_genesis_leaf::<TestVersions>(&genesis_leaf).await;
+    assert_eq!(high_qc.view_number(), ViewNumber::genesis());
+    assert_eq!(high_qc.data.leaf_commit, genesis_leaf.commit());
+
+    HotShotInitializer::<TestTypes>::from_snapshot::<TestVersions>(
+        snapshot,
+        TestInstanceState::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_genesis_certificate_is_that_of_the_genesis_leaf() {
+    let instance_state = TestInstanceState::default();
+    let (validated_state, _) = TestValidatedState::genesis(&instance_state);
+
+    let genesis_leaf = Leaf2::<TestTypes>::genesis(&validated_state, &instance_state).await;
+    assert_eq!(
+        QuorumCertificate2::genesis::<TestVersions>(&validated_state, &instance_state).await,
+        QuorumCertificate2::for_genesis_leaf::<TestVersions>(&genesis_leaf).await
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_chain_snapshot_stake_tables() {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let mut config = handle.hotshot.config.clone();
+    let leaf = handle.hotshot.consensus().read().await.decided_leaf();
+
+    let snapshot = ChainSnapshot::<TestTypes> {
+        leaf,
+        validated_state: None,
+        stake_table: config.known_nodes_with_stake[..3].to_vec(),
+        da_stake_table: config.known_nodes_with_stake[..2].to_vec(),
+    };
+    snapshot.apply_stake_table(&mut config).unwrap();
+    assert_eq!(config.num_nodes_with_stake.get(), 3);
+    assert_eq!(config.known_nodes_with_stake, snapshot.stake_table);
+    assert_eq!(config.known_da_nodes, snapshot.da_stake_table);
+
+    let empty = ChainSnapshot {
+        stake_table: Vec::new(),
+        ..snapshot.clone()
+    };
+    assert!(empty.validate().is_err());
+
+    let mut duplicated = snapshot.clone();
+    duplicated
+        .stake_table
+        .push(duplicated.stake_table[0].clone());
+    assert!(duplicated.validate().is_err());
+
+    let unstaked_da = ChainSnapshot {
+        da_stake_table: config.known_nodes_with_stake[..1]
+            .iter()
+            .chain(handle.hotshot.config.known_nodes_with_stake[3..4].iter())
+            .cloned()
+            .collect(),
+        ..snapshot.clone()
+    };
+    assert!(unstaked_da.validate().is_err());
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::{Duration, Instant};
+
+use hotshot_types::{
+    channel_depth::{ChannelCapacities, ChannelDepth},
+    constants::MAX_CHANNEL_CAPACITY,
+};
+
+#[cfg(test)]
+#[test]
+fn test_channel_depth_warns_once_after_staying_full() {
+    let delay = Duration::from_secs(5);
+    let mut depth = ChannelDepth::new("internal", 100, 0.8, delay);
+    let start = Instant::now();
+
+    assert!(!depth.sample(50, start));
+    assert_eq!(depth.high_water_mark(), 50);
+
+    // Close to full, but not for long enough
+    assert!(!depth.sample(90, start));
+    assert!(!depth.sample(85, start + Duration::from_secs(4)));
+    assert_eq!(depth.high_water_mark(), 90);
+
+    // Still close to full past the delay, which is only warned about once
+    assert!(depth.sample(81, start + delay));
+    assert!(!depth.sample(95, start + Duration::from_secs(10)));
+    assert_eq!(depth.high_water_mark(), 95);
+
+    // Draining the channel resets the delay, but not the high-water mark
+    assert!(!depth.sample(10, start + Duration::from_secs(11)));
+    assert!(!depth.sample(90, start + Duration::from_secs(12)));
+    assert!(depth.sample(90, start + Duration::from_secs(17)));
+    assert_eq!(depth.high_water_mark(), 95);
+}
+
+#[cfg(test)]
+#[test]
+fn test_channel_capacities_validation() {
+    assert!(ChannelCapacities::default().validate().is_ok());
+
+    let empty_output = ChannelCapacities {
+        output_events: 0,
+        ..ChannelCapacities::default()
+    };
+    assert!(empty_output.validate().is_err());
+
+    let huge_inbound = ChannelCapacities {
+        network_inbound: MAX_CHANNEL_CAPACITY + 1,
+        ..ChannelCapacities::default()
+    };
+    assert!(huge_inbound.validate().is_err());
+
+    let small = ChannelCapacities {
+        internal_events: 1,
+        output_events: 1,
+        network_inbound: 1,
+    };
+    assert!(small.validate().is_ok());
+}
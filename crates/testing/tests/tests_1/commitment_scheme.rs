@@ -0,0 +1,46 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use hotshot_example_types::{
+    node_types::TestTypes,
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_types::{
+    data::{Leaf, ViewNumber},
+    traits::{
+        commitment_scheme::{Blake3Scheme, CommitmentScheme, Keccak256Scheme, Sha256Scheme},
+        node_implementation::ConsensusTime,
+    },
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_commitment_schemes() {
+    let leaf = Leaf::<TestTypes>::genesis(
+        &TestValidatedState::default(),
+        &TestInstanceState::default(),
+    )
+    .await;
+    let native = leaf.commit();
+
+    // The native scheme leaves commitments as they are
+    assert_eq!(Keccak256Scheme::commit(&leaf), native);
+
+    // Other schemes are deterministic, and differ from the native scheme and from each other
+    let sha256 = Sha256Scheme::commit(&leaf);
+    let blake3 = Blake3Scheme::commit(&leaf);
+    assert_eq!(sha256, Sha256Scheme::recommit(native));
+    assert_eq!(blake3, Blake3Scheme::recommit(native));
+    assert_ne!(sha256, native);
+    assert_ne!(blake3, native);
+    assert_ne!(sha256, blake3);
+
+    // Different values still have different commitments under every scheme
+    let (first, second) = (ViewNumber::new(1), ViewNumber::new(2));
+    assert_ne!(Sha256Scheme::commit(&first), Sha256Scheme::commit(&second));
+    assert_ne!(Blake3Scheme::commit(&first), Blake3Scheme::commit(&second));
+}
@@ -0,0 +1,168 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use committable::{Commitment, Committable};
+use futures::StreamExt;
+use hotshot_example_types::node_types::{
+    CompactVotesTestVersions, Libp2pImpl, MemoryImpl, PushCdnImpl, TestTypes,
+    TestTypesRandomizedLeader, TestVersions,
+};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    helpers::build_system_handle,
+    test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    compact_vote::{CompactQuorumVote, CompactVoteDecoder},
+    data::{EpochNumber, ViewNumber},
+    message::{GeneralConsensusMessage, Message, MessageKind, SequencingMessage, UpgradeLock},
+    signature_key::BLSPubKey,
+    simple_vote::{QuorumData2, QuorumVote2},
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+};
+
+/// Number of nodes in the stake table of the decoder tests
+const NUM_NODES: u64 = 5;
+
+/// The serialized size of a quorum vote message carrying `message`
+async fn vote_message_size(
+    upgrade_lock: &UpgradeLock<TestTypes, CompactVotesTestVersions>,
+    sender: BLSPubKey,
+    message: GeneralConsensusMessage<TestTypes>,
+) -> usize {
+    let message = Message::<TestTypes> {
+        sender,
+        kind: MessageKind::from_consensus_message(SequencingMessage::General(message)),
+    };
+    upgrade_lock.serialize(&message).await.unwrap().len()
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_votes_expand_against_proposals() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let upgrade_lock = UpgradeLock::<TestTypes, CompactVotesTestVersions>::new();
+    let keys: Vec<_> = (0..NUM_NODES)
+        .map(|node_id| BLSPubKey::generated_from_seed_indexed([0u8; 32], node_id))
+        .collect();
+    let stake_table = Arc::new(keys.iter().map(|(key, _)| *key).collect::<Vec<_>>());
+    let mut decoder = CompactVoteDecoder::<TestTypes>::new(Arc::clone(&stake_table));
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    for view in (&mut generator).take(3).collect::<Vec<_>>().await {
+        let data = QuorumData2 {
+            leaf_commit: view.leaf.commit(),
+            epoch: view.leaf.epoch(),
+        };
+        let mut votes = Vec::new();
+        for (public_key, private_key) in &keys {
+            votes.push(
+                QuorumVote2::<TestTypes>::create_signed_vote(
+                    data.clone(),
+                    view.view_number,
+                    public_key,
+                    private_key,
+                    &upgrade_lock,
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        let compact_votes: Vec<_> = votes
+            .iter()
+            .map(|vote| CompactQuorumVote::compact(vote, &stake_table).unwrap())
+            .collect();
+
+        // A vote arriving ahead of its proposal is held back until the proposal is seen
+        assert!(decoder.add_vote(compact_votes[0].clone()).is_empty());
+        assert_eq!(
+            decoder.add_proposal(&view.quorum_proposal.data),
+            vec![votes[0].clone()]
+        );
+        assert!(decoder.add_proposal(&view.quorum_proposal.data).is_empty());
+
+        for (vote, compact_vote) in votes.iter().zip(compact_votes).skip(1) {
+            assert_eq!(decoder.add_vote(compact_vote), vec![vote.clone()]);
+        }
+
+        let full_size = vote_message_size(
+            &upgrade_lock,
+            keys[0].0,
+            GeneralConsensusMessage::Vote2(votes[0].clone()),
+        )
+        .await;
+        let compact_size = vote_message_size(
+            &upgrade_lock,
+            keys[0].0,
+            GeneralConsensusMessage::CompactVote(
+                CompactQuorumVote::compact(&votes[0], &stake_table).unwrap(),
+            ),
+        )
+        .await;
+        assert!(compact_size < full_size);
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_vote_signer_outside_stake_table() {
+    let upgrade_lock = UpgradeLock::<TestTypes, CompactVotesTestVersions>::new();
+    let stake_table: Vec<_> = (0..NUM_NODES)
+        .map(|node_id| BLSPubKey::generated_from_seed_indexed([0u8; 32], node_id).0)
+        .collect();
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([1u8; 32], 0);
+
+    let vote = QuorumVote2::<TestTypes>::create_signed_vote(
+        QuorumData2 {
+            leaf_commit: Commitment::from_raw([0u8; 32]),
+            epoch: EpochNumber::new(0),
+        },
+        ViewNumber::new(1),
+        &public_key,
+        &private_key,
+        &upgrade_lock,
+    )
+    .await
+    .unwrap();
+    assert!(CompactQuorumVote::compact(&vote, &stake_table).is_none());
+
+    // Nor can an index past the end of the stake table be expanded
+    let compact_vote = CompactQuorumVote::<TestTypes> {
+        signer_index: u32::try_from(stake_table.len()).unwrap(),
+        ..CompactQuorumVote::compact(&vote, &[public_key]).unwrap()
+    };
+    assert!(compact_vote
+        .expand(vote.data.clone(), &stake_table)
+        .is_none());
+}
+
+cross_tests!(
+    TestName: test_success_with_compact_votes,
+    Impls: [Libp2pImpl, PushCdnImpl],
+    Types: [TestTypes, TestTypesRandomizedLeader],
+    Versions: [CompactVotesTestVersions],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            ..TestDescription::default()
+        }
+    },
+);
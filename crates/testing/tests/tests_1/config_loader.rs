@@ -0,0 +1,120 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use hotshot_types::{
+    config_loader::{load_network_config_file_with_env, resolve_network_config, ConfigLoadError},
+    network::{BuilderType, NetworkConfigFile},
+    signature_key::BLSPubKey,
+};
+
+/// A YAML config with every field which has no default
+const YAML_CONFIG: &str = "
+rounds: 20
+config:
+  start_threshold: [1, 1]
+  num_nodes_with_stake: 4
+  staked_da_nodes: 4
+  fixed_leader_for_gpuvid: 0
+  next_view_timeout: 10000
+  view_sync_timeout: { secs: 2, nanos: 0 }
+  num_bootstrap: 4
+  builder_timeout: { secs: 1, nanos: 0 }
+  upgrade:
+    start_proposing_view: 1
+    stop_proposing_view: 0
+    start_voting_view: 1
+    stop_voting_view: 0
+    start_proposing_time: 1
+    stop_proposing_time: 0
+    start_voting_time: 1
+    stop_voting_time: 0
+  epoch_height: 0
+";
+
+/// The orchestrator's example config
+fn run_config() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../orchestrator/run-config.toml")
+}
+
+/// Environment variables from `vars`
+fn env(vars: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+    Some(
+        vars.iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_environment_overrides_file() {
+    hotshot::helpers::initialize_logging();
+
+    let file: NetworkConfigFile<BLSPubKey> =
+        load_network_config_file_with_env(&run_config(), env(&[])).unwrap();
+    assert_eq!(file.config.next_view_timeout, 30000);
+
+    let config = resolve_network_config::<BLSPubKey>(
+        &run_config(),
+        env(&[
+            ("HOTSHOT_ROUNDS", "7"),
+            ("HOTSHOT_BUILDER", "External"),
+            ("hotshot_config__next_view_timeout", "5000"),
+            ("HOTSHOT_CONFIG__BUILDER_TIMEOUT__SECS", "2"),
+            (
+                "HOTSHOT_CONFIG__BUILDER_URLS",
+                "http://builder-a:3311/,http://builder-b:3311/",
+            ),
+            ("UNRELATED_ROUNDS", "8"),
+        ]),
+    )
+    .unwrap();
+
+    assert_eq!(config.rounds, 7);
+    assert!(matches!(config.builder, BuilderType::External));
+    assert_eq!(config.config.next_view_timeout, 5000);
+    assert_eq!(config.config.builder_timeout, Duration::from_secs(2));
+    assert_eq!(
+        config
+            .config
+            .builder_urls
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        ["http://builder-a:3311/", "http://builder-b:3311/"]
+    );
+
+    // The listed public keys become the stake table
+    assert_eq!(
+        config.config.known_nodes_with_stake.len(),
+        config.public_keys.len()
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_yaml_config() {
+    hotshot::helpers::initialize_logging();
+
+    let path = std::env::temp_dir().join(format!("hotshot-config-{}.yaml", std::process::id()));
+    std::fs::write(&path, YAML_CONFIG).unwrap();
+
+    let config = resolve_network_config::<BLSPubKey>(&path, env(&[])).unwrap();
+    assert_eq!(config.rounds, 20);
+    assert_eq!(config.config.num_nodes_with_stake.get(), 4);
+    assert_eq!(config.config.view_sync_timeout, Duration::from_secs(2));
+
+    // An override which makes the config invalid is reported along with the violation
+    let result = resolve_network_config::<BLSPubKey>(
+        &path,
+        env(&[("HOTSHOT_CONFIG__BUILDER_TIMEOUT__SECS", "20")]),
+    );
+    assert!(matches!(result, Err(ConfigLoadError::Invalid(_))));
+
+    std::fs::remove_file(path).unwrap();
+}
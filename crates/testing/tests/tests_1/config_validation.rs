@@ -0,0 +1,110 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::test_builder::TestDescription;
+use hotshot_types::{
+    config_validation::ConfigViolation, hotshot_config_file::HotShotConfigFile,
+    signature_key::BLSPubKey, HotShotConfig, ValidatorConfig,
+};
+
+/// The config of the default test network
+fn test_config() -> HotShotConfig<BLSPubKey> {
+    TestDescription::<TestTypes, MemoryImpl, TestVersions>::default()
+        .gen_launcher(0)
+        .resource_generator
+        .config
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shipped_configs_are_valid() {
+    hotshot::helpers::initialize_logging();
+
+    test_config().validate().unwrap();
+
+    let preset: HotShotConfig<BLSPubKey> = HotShotConfigFile::hotshot_config_5_nodes_10_da().into();
+    preset.validate().unwrap();
+
+    let validator_config =
+        ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], 0, 1, true);
+    let dev: HotShotConfig<BLSPubKey> = HotShotConfigFile::dev_single_node(
+        &validator_config,
+        "http://localhost:3311".parse().unwrap(),
+    )
+    .into();
+    dev.validate().unwrap();
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_every_violation_is_reported() {
+    hotshot::helpers::initialize_logging();
+
+    let mut config = test_config();
+    let nodes = config.num_nodes_with_stake.get();
+    config.num_nodes_with_stake = NonZeroUsize::new(nodes + 1).unwrap();
+    config.da_staked_committee_size = nodes + 2;
+    config.builder_timeout = Duration::from_millis(config.next_view_timeout + 1);
+    config.channel_capacities.internal_events = 0;
+
+    let violations = config.validate().unwrap_err();
+    assert_eq!(violations.0.len(), 4, "{violations}");
+    assert!(violations.0.contains(&ConfigViolation::NodeCount {
+        expected: nodes + 1,
+        listed: nodes,
+    }));
+    assert!(violations.0.contains(&ConfigViolation::DaCommitteeSize {
+        size: nodes + 2,
+        nodes: nodes + 1,
+    }));
+    assert!(violations.0.iter().any(|violation| matches!(
+        violation,
+        ConfigViolation::DelayExceedsViewTimeout {
+            field: "builder_timeout",
+            ..
+        }
+    )));
+    assert!(violations
+        .0
+        .iter()
+        .any(|violation| matches!(violation, ConfigViolation::ChannelCapacity(_))));
+
+    // Each violation is on its own line of the error
+    assert_eq!(violations.to_string().lines().count(), 5);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stake_table_violations() {
+    hotshot::helpers::initialize_logging();
+
+    // A repeated node, and a DA node which is not staked
+    let mut config = test_config();
+    config.known_nodes_with_stake[1] = config.known_nodes_with_stake[0].clone();
+    config.known_da_nodes.push(
+        ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([1u8; 32], 0, 1, true)
+            .public_config(),
+    );
+    let violations = config.validate().unwrap_err();
+    assert!(violations
+        .0
+        .iter()
+        .any(|violation| matches!(violation, ConfigViolation::DuplicateNode(_))));
+    assert!(violations
+        .0
+        .iter()
+        .any(|violation| matches!(violation, ConfigViolation::UnstakedDaNode(_))));
+
+    // Nodes which are yet to register are not checked for
+    let mut config = test_config();
+    config.known_nodes_with_stake.clear();
+    config.known_da_nodes.clear();
+    assert!(config.validate().is_err());
+    config.validate_parameters().unwrap();
+}
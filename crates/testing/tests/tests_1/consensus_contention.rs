@@ -0,0 +1,144 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::{future::join_all, StreamExt};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber, traits::node_implementation::ConsensusTime, view_map::ViewMap,
+};
+
+/// Number of tasks storing VID shares in the benchmark
+const WRITERS: u64 = 4;
+/// Number of VID shares each writer stores
+const WRITES: u64 = 50;
+/// Number of tasks reading consensus in the benchmark
+const READERS: usize = 8;
+/// How long each read holds the consensus lock
+const READ_TIME: Duration = Duration::from_millis(1);
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_map() {
+    hotshot::helpers::initialize_logging();
+
+    let map = ViewMap::<ViewNumber, u64>::new();
+    assert!(map.is_empty());
+    assert_eq!(map.max_view(), None);
+
+    for view in [5, 1, 17, 3, 33] {
+        assert_eq!(map.insert(ViewNumber::new(view), view), None);
+    }
+    map.update(ViewNumber::new(3), |value| *value += 100);
+    map.update(ViewNumber::new(40), |value| *value += 1);
+
+    assert_eq!(map.len(), 6);
+    assert_eq!(map.get(&ViewNumber::new(3)), Some(103));
+    assert_eq!(map.get(&ViewNumber::new(40)), Some(1));
+    assert_eq!(map.with(&ViewNumber::new(17), |value| value * 2), Some(34));
+    assert_eq!(map.last(), Some((ViewNumber::new(40), 1)));
+    assert_eq!(
+        map.keys(),
+        [1, 3, 5, 17, 33, 40].map(ViewNumber::new).to_vec()
+    );
+
+    map.remove_before(&ViewNumber::new(5));
+    assert!(!map.contains_key(&ViewNumber::new(3)));
+    assert_eq!(map.keys(), [5, 17, 33, 40].map(ViewNumber::new).to_vec());
+    assert_eq!(map.remove(&ViewNumber::new(33)), Some(33));
+    assert_eq!(map.max_view(), Some(ViewNumber::new(40)));
+
+    // Clones do not share entries
+    let copy = map.clone();
+    map.insert(ViewNumber::new(50), 50);
+    assert_eq!(copy.len(), 3);
+}
+
+/// Store VID shares from several tasks while others hold the consensus read lock, storing them
+/// through the write lock if `exclusive`, and return how long the writers took
+async fn store_vid_shares(exclusive: bool) -> Duration {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let share = generator.next().await.unwrap().vid_proposal.0[0].clone();
+    let consensus = handle.hotshot.consensus();
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let consensus = Arc::clone(&consensus);
+            tokio::spawn(async move {
+                let mut reads = 0u64;
+                while reads < WRITERS * WRITES && Instant::now() < deadline {
+                    let consensus_reader = consensus.read().await;
+                    tokio::time::sleep(READ_TIME).await;
+                    drop(consensus_reader);
+                    reads += 1;
+                }
+            })
+        })
+        .collect();
+
+    let start = Instant::now();
+    let writers = (0..WRITERS).map(|writer| {
+        let consensus = Arc::clone(&consensus);
+        let share = share.clone();
+        async move {
+            for n in 0..WRITES {
+                let mut share = share.clone();
+                share.data.view_number = ViewNumber::new(writer * WRITES + n);
+                if exclusive {
+                    consensus
+                        .write()
+                        .await
+                        .update_vid_shares(share.data.view_number, share);
+                } else {
+                    consensus
+                        .read()
+                        .await
+                        .update_vid_shares(share.data.view_number, share);
+                }
+            }
+        }
+    });
+    join_all(writers).await;
+    let elapsed = start.elapsed();
+
+    for reader in readers {
+        reader.abort();
+    }
+    let stored = consensus.read().await.vid_shares().len() as u64;
+    assert!(stored >= WRITERS * WRITES);
+
+    elapsed
+}
+
+/// Benchmark of storing VID shares while other tasks read consensus, through the write lock as
+/// before and through the read lock as now. Run with
+/// `cargo test --release test_consensus_contention -- --ignored --nocapture`.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore]
+async fn test_consensus_contention() {
+    hotshot::helpers::initialize_logging();
+
+    let exclusive = store_vid_shares(true).await;
+    let shared = store_vid_shares(false).await;
+
+    println!(
+        "Storing {} VID shares alongside {} readers took {:?} through the write lock, {:?} through the read lock",
+        WRITERS * WRITES,
+        READERS,
+        exclusive,
+        shared
+    );
+}
@@ -0,0 +1,42 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+#[cfg(test)]
+#[test]
+fn test_view_number_checked_sub() {
+    assert_eq!(ViewNumber::new(5).checked_sub(2), Some(ViewNumber::new(3)));
+    assert_eq!(ViewNumber::new(1).checked_sub(1), Some(ViewNumber::genesis()));
+    assert_eq!(ViewNumber::genesis().checked_sub(1), None);
+}
+
+#[cfg(test)]
+#[test]
+fn test_view_number_saturating_distance() {
+    assert_eq!(ViewNumber::new(7).saturating_distance(ViewNumber::new(3)), 4);
+    assert_eq!(ViewNumber::new(3).saturating_distance(ViewNumber::new(3)), 0);
+    // A later view is no distance behind
+    assert_eq!(ViewNumber::new(3).saturating_distance(ViewNumber::new(7)), 0);
+    assert_eq!(
+        ViewNumber::new(u64::MAX).saturating_distance(ViewNumber::genesis()),
+        u64::MAX
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_view_number_is_within() {
+    let cur_view = ViewNumber::new(10);
+
+    assert!(ViewNumber::new(9).is_within(cur_view, 1));
+    assert!(ViewNumber::new(10).is_within(cur_view, 1));
+    assert!(ViewNumber::new(11).is_within(cur_view, 1));
+    assert!(!ViewNumber::new(8).is_within(cur_view, 1));
+    assert!(ViewNumber::genesis().is_within(ViewNumber::genesis(), 0));
+    assert!(!ViewNumber::genesis().is_within(cur_view, 9));
+    assert!(ViewNumber::new(u64::MAX).is_within(cur_view, 0));
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use futures::StreamExt;
+use hotshot::{tasks::task_state::CreateTaskState, types::SystemContextHandle};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    request::{NetworkRequestState, DA_CERTIFICATE_GRACE_PERIOD},
+};
+use hotshot_testing::{
+    helpers::{build_system_handle, vid_share},
+    view_generator::{TestView, TestViewGenerator},
+};
+use hotshot_types::{
+    data::EpochNumber,
+    traits::{
+        consensus_api::ConsensusApi, election::Membership, network::RequestKind,
+        node_implementation::ConsensusTime,
+    },
+};
+use tokio::time::timeout;
+
+/// Margin around the grace period, for the scheduling of the request task
+const MARGIN: Duration = Duration::from_millis(200);
+
+/// The first two views, with our VID share for the second one already received
+async fn views_with_vid_share(
+    handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+) -> Vec<TestView> {
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+    handle.hotshot.consensus().read().await.update_vid_shares(
+        views[1].view_number,
+        vid_share(&views[1].vid_proposal.0, handle.public_key()),
+    );
+    views
+}
+
+// A node which has the proposal and its VID share but not the DA certificate asks a DA member or
+// the leader for it once the grace period has passed
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_da_certificate_requested_after_grace_period() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let views = views_with_vid_share(&handle).await;
+    let view = views[1].view_number;
+
+    let mut state =
+        NetworkRequestState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let (sender, mut receiver) = broadcast::<Arc<HotShotEvent<TestTypes>>>(32);
+    let internal_receiver = receiver.clone();
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::QuorumProposalValidated(
+                views[1].quorum_proposal.clone(),
+                views[0].leaf.clone(),
+            )),
+            &sender,
+            &internal_receiver,
+        )
+        .await
+        .unwrap();
+
+    // Nothing is requested while the certificate may still be on its way
+    assert!(
+        timeout(DA_CERTIFICATE_GRACE_PERIOD - MARGIN, receiver.recv_direct())
+            .await
+            .is_err()
+    );
+
+    let event = timeout(MARGIN * 2, receiver.recv_direct())
+        .await
+        .expect("The DA certificate was not requested after the grace period")
+        .unwrap();
+    let HotShotEvent::DaCertificateRequestSend(request, from, to) = event.as_ref() else {
+        panic!("Expected a DA certificate request, got {event:?}");
+    };
+    assert!(matches!(request.request, RequestKind::DaCertificate(v) if v == view));
+    assert_eq!(request.view, view);
+    assert_eq!(*from, handle.public_key());
+    assert_ne!(*to, handle.public_key());
+
+    let epoch = EpochNumber::new(0);
+    let membership = handle.hotshot.memberships.read().await;
+    assert!(
+        membership.da_committee_members(view, epoch).contains(to)
+            || membership.leader(view, epoch).ok().as_ref() == Some(to)
+    );
+}
+
+// A DA certificate arriving during the grace period cancels the request
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_da_certificate_within_grace_period_is_not_requested() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let views = views_with_vid_share(&handle).await;
+
+    let mut state =
+        NetworkRequestState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let (sender, mut receiver) = broadcast::<Arc<HotShotEvent<TestTypes>>>(32);
+    let internal_receiver = receiver.clone();
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::QuorumProposalValidated(
+                views[1].quorum_proposal.clone(),
+                views[0].leaf.clone(),
+            )),
+            &sender,
+            &internal_receiver,
+        )
+        .await
+        .unwrap();
+
+    handle
+        .hotshot
+        .consensus()
+        .read()
+        .await
+        .update_saved_da_certs(views[1].view_number, views[1].da_certificate.clone());
+
+    assert!(timeout(
+        DA_CERTIFICATE_GRACE_PERIOD + MARGIN * 2,
+        receiver.recv_direct()
+    )
+    .await
+    .is_err());
+
+    // Nor is a certificate requested which we already have
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::QuorumProposalValidated(
+                views[1].quorum_proposal.clone(),
+                views[0].leaf.clone(),
+            )),
+            &sender,
+            &internal_receiver,
+        )
+        .await
+        .unwrap();
+    assert!(timeout(
+        DA_CERTIFICATE_GRACE_PERIOD + MARGIN * 2,
+        receiver.recv_direct()
+    )
+    .await
+    .is_err());
+}
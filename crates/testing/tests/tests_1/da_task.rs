@@ -13,6 +13,7 @@ use hotshot_example_types::{
     node_types::{MemoryImpl, TestTypes, TestVersions},
 };
 use hotshot_macros::{run_test, test_scripts};
+use hotshot_task::sharded::ViewSharded;
 use hotshot_task_impls::{da::DaTaskState, events::HotShotEvent::*};
 use hotshot_testing::{
     helpers::build_system_handle,
@@ -126,8 +127,9 @@ async fn test_da_task() {
 
     let da_state = DaTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
     let mut da_script = TaskScript {
-        timeout: Duration::from_millis(35),
-        state: da_state,
+        // Proposals and votes are handled in subtasks, after the event has been handed over
+        timeout: Duration::from_millis(500),
+        state: ViewSharded::new(da_state),
         expectations: vec![
             Expectations::from_outputs(vec![exact(DaProposalSend(
                 proposals[1].clone(),
@@ -251,8 +253,9 @@ async fn test_da_task_storage_failure() {
 
     let da_state = DaTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
     let mut da_script = TaskScript {
-        timeout: Duration::from_millis(35),
-        state: da_state,
+        // Proposals and votes are handled in subtasks, after the event has been handed over
+        timeout: Duration::from_millis(500),
+        state: ViewSharded::new(da_state),
         expectations,
     };
 
@@ -0,0 +1,92 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::{types::EventType, HotShotInitializer, SystemContext};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestInstanceState,
+};
+use hotshot_testing::test_builder::TestDescription;
+use hotshot_types::{traits::block_contents::BlockHeader, ValidatorConfig};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_decided_leaf_stream_backfills_then_follows() {
+    hotshot::helpers::initialize_logging();
+
+    let launcher = TestDescription::<TestTypes, MemoryImpl, TestVersions> {
+        num_nodes_with_stake: 1,
+        start_nodes: 1,
+        num_bootstrap_nodes: 1,
+        da_staked_committee_size: 1,
+        ..TestDescription::default()
+    }
+    .gen_launcher(0);
+
+    let validator_config = ValidatorConfig::generated_from_seed_indexed([0u8; 32], 0, 1, true);
+    let builder_url = launcher
+        .resource_generator
+        .config
+        .builder_urls
+        .first()
+        .clone();
+    let initializer =
+        HotShotInitializer::<TestTypes>::from_genesis::<TestVersions>(TestInstanceState::default())
+            .await
+            .unwrap();
+
+    let handle = SystemContext::<TestTypes, MemoryImpl, TestVersions>::dev_single_node(
+        &validator_config,
+        builder_url,
+        (launcher.resource_generator.channel_generator)(0).await,
+        initializer,
+        (launcher.resource_generator.storage)(0),
+        (launcher.resource_generator.marketplace_config)(0),
+    )
+    .await
+    .unwrap();
+
+    // Let a few blocks be decided before the streams start
+    let mut events = handle.event_stream();
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await
+            .expect("the dev node stopped deciding")
+            .unwrap();
+        if let EventType::Decide { leaf_chain, .. } = event.event {
+            if leaf_chain[0].leaf.block_header().block_number() >= 3 {
+                break;
+            }
+        }
+    }
+
+    // Every leaf from the genesis one on, the first few from storage and the rest as they are
+    // decided, without gaps or repeats
+    let leaves: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(30),
+        handle.stream_decided_leaves_from(0).await.take(8).collect(),
+    )
+    .await
+    .expect("the stream stopped yielding leaves");
+    let heights: Vec<_> = leaves.iter().map(|leaf| leaf.height()).collect();
+    assert_eq!(heights, (0..8).collect::<Vec<_>>());
+    for pair in leaves.windows(2) {
+        assert_eq!(pair[1].parent_commitment(), pair[0].commit());
+    }
+
+    // A stream from a later height starts there
+    let leaves = handle.stream_decided_leaves_from(5).await;
+    futures::pin_mut!(leaves);
+    let leaf = tokio::time::timeout(Duration::from_secs(10), leaves.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(leaf.height(), 5);
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_broadcast::broadcast;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::{decryption::DecryptionTaskState, events::HotShotEvent};
+use hotshot_testing::helpers::{build_system_handle, key_pair_for_id};
+use hotshot_types::{
+    constants::MAX_OUTSTANDING_VIEWS,
+    data::{EpochNumber, ViewNumber},
+    encrypted_mempool::{DecryptionShares, ThresholdDecryption},
+    message::UpgradeLock,
+    traits::{election::Membership, node_implementation::ConsensusTime},
+};
+
+/// A scheme whose shares are the ciphertext itself, as only the buffering of shares is tested
+#[derive(Debug)]
+struct PlainDecryption;
+
+impl ThresholdDecryption for PlainDecryption {
+    fn encryption_key(&self, _epoch: u64) -> Option<Vec<u8>> {
+        Some(Vec::new())
+    }
+
+    fn threshold(&self, _epoch: u64) -> usize {
+        1
+    }
+
+    fn decryption_share(&self, _epoch: u64, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        Some(ciphertext.to_vec())
+    }
+
+    fn verify_share(&self, _epoch: u64, _sender: &[u8], ciphertext: &[u8], share: &[u8]) -> bool {
+        ciphertext == share
+    }
+
+    fn combine_shares(
+        &self,
+        _epoch: u64,
+        ciphertext: &[u8],
+        _shares: &[(Vec<u8>, Vec<u8>)],
+    ) -> Option<Vec<u8>> {
+        Some(ciphertext.to_vec())
+    }
+}
+
+/// Shares for the block of `view` signed by node `id`
+async fn shares(id: u64, view: u64) -> DecryptionShares<TestTypes> {
+    let (private_key, public_key) = key_pair_for_id::<TestTypes>(id);
+    DecryptionShares::sign(
+        ViewNumber::new(view),
+        EpochNumber::new(0),
+        public_key,
+        vec![(0, vec![1, 2, 3])],
+        &private_key,
+        &UpgradeLock::<TestTypes, TestVersions>::new(),
+    )
+    .await
+    .unwrap()
+}
+
+/// Tell `state` about `shares`, as if they were received from the network
+async fn receive(
+    state: &mut DecryptionTaskState<TestTypes, TestVersions>,
+    shares: DecryptionShares<TestTypes>,
+) {
+    let (sender, receiver) = broadcast(16);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::DecryptionSharesRecv(shares)),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+}
+
+// Shares for a block we haven't seen decided yet are held only if they are signed by a DA
+// committee member and close enough to our view, and only up to a limit
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_early_decryption_shares_bounded() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let membership = Arc::clone(&handle.hotshot.memberships);
+    let epoch = EpochNumber::new(0);
+    let mut da_members = Vec::new();
+    let mut non_da_members = Vec::new();
+    for id in 0..10 {
+        if membership
+            .read()
+            .await
+            .has_da_stake(&key_pair_for_id::<TestTypes>(id).1, epoch)
+        {
+            da_members.push(id);
+        } else {
+            non_da_members.push(id);
+        }
+    }
+    assert!(da_members.len() >= 2 && !non_da_members.is_empty());
+
+    let mut state = DecryptionTaskState::<TestTypes, TestVersions>::new(
+        Arc::new(PlainDecryption),
+        membership,
+        handle.public_key(),
+        handle.private_key().clone(),
+        UpgradeLock::new(),
+        handle.hotshot.external_event_stream.0.clone(),
+        2,
+    );
+
+    // Shares from a DA committee member within reach of our view are held
+    receive(&mut state, shares(da_members[0], 5).await).await;
+    assert_eq!(state.early_shares(), 1);
+
+    // Repeated shares from the same member replace its earlier ones
+    receive(&mut state, shares(da_members[0], 5).await).await;
+    assert_eq!(state.early_shares(), 1);
+
+    // Shares which aren't signed by their sender are dropped
+    let mut forged = shares(da_members[1], 5).await;
+    forged.sender = key_pair_for_id::<TestTypes>(da_members[0]).1;
+    forged.shares = vec![(0, vec![4, 5, 6])];
+    receive(&mut state, forged).await;
+    let mut unsigned = shares(da_members[1], 6).await;
+    unsigned.shares = vec![(0, vec![4, 5, 6])];
+    receive(&mut state, unsigned).await;
+    assert_eq!(state.early_shares(), 1);
+
+    // As are shares from outside the DA committee
+    receive(&mut state, shares(non_da_members[0], 5).await).await;
+    assert_eq!(state.early_shares(), 1);
+
+    // And shares for blocks too far ahead of us, until we get closer
+    let far_view = MAX_OUTSTANDING_VIEWS + 1;
+    receive(&mut state, shares(da_members[1], far_view).await).await;
+    assert_eq!(state.early_shares(), 1);
+    let (sender, receiver) = broadcast(16);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::ViewChange(ViewNumber::new(1), epoch)),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+    receive(&mut state, shares(da_members[1], far_view).await).await;
+    assert_eq!(state.early_shares(), 2);
+
+    // Beyond the limit, only members we hold shares from for the view may replace them
+    state.max_early_shares = 2;
+    receive(&mut state, shares(da_members[1], 7).await).await;
+    assert_eq!(state.early_shares(), 2);
+    receive(&mut state, shares(da_members[0], 5).await).await;
+    assert_eq!(state.early_shares(), 2);
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::path::Path;
+
+use hotshot::types::BLSPubKey;
+use hotshot_example_types::node_types::{Libp2pImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    deployment::{Deployment, DeploymentOptions, LinkShaping},
+    test_builder::TestDescription,
+};
+use hotshot_types::{network::NetworkConfigFile, ValidatorConfig};
+
+#[cfg(test)]
+#[test]
+fn test_deployment_from_description() {
+    let description = TestDescription::<TestTypes, Libp2pImpl, TestVersions> {
+        num_nodes_with_stake: 12,
+        start_nodes: 12,
+        num_bootstrap_nodes: 12,
+        da_staked_committee_size: 5,
+        ..TestDescription::default()
+    };
+    let options = DeploymentOptions {
+        link_shaping: Some(LinkShaping {
+            delay_ms: 50,
+            jitter_ms: 10,
+            loss_percent: 1.0,
+        }),
+        ..DeploymentOptions::default()
+    };
+    let deployment = Deployment::from_description(&description, &options);
+
+    let run_config: NetworkConfigFile<BLSPubKey> =
+        toml::from_str(&deployment.files[Path::new("run-config.toml")]).unwrap();
+    assert_eq!(run_config.config.num_nodes_with_stake.get(), 12);
+    assert_eq!(run_config.config.staked_da_nodes, 5);
+    assert_eq!(run_config.public_keys.len(), 12);
+    assert_eq!(run_config.public_keys.iter().filter(|key| key.da).count(), 5);
+    assert_eq!(
+        run_config.public_keys[3].stake_table_key,
+        ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], 3, 1, true).public_key
+    );
+
+    let compose = &deployment.files[Path::new("docker-compose.yml")];
+    assert_eq!(compose.matches("validator-libp2p:main").count(), 12);
+    assert_eq!(compose.matches("netem delay 50ms 10ms loss 1%").count(), 12);
+    assert!(compose.contains(&format!("ipv4_address: {}", Deployment::validator_address(11))));
+}
@@ -0,0 +1,70 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use hotshot::{types::EventType, HotShotInitializer, SystemContext};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestInstanceState,
+};
+use hotshot_testing::test_builder::TestDescription;
+use hotshot_types::{traits::block_contents::BlockHeader, ValidatorConfig};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dev_single_node_decides_every_block() {
+    hotshot::helpers::initialize_logging();
+
+    let launcher = TestDescription::<TestTypes, MemoryImpl, TestVersions> {
+        num_nodes_with_stake: 1,
+        start_nodes: 1,
+        num_bootstrap_nodes: 1,
+        da_staked_committee_size: 1,
+        ..TestDescription::default()
+    }
+    .gen_launcher(0);
+
+    let validator_config = ValidatorConfig::generated_from_seed_indexed([0u8; 32], 0, 1, true);
+    let builder_url = launcher.resource_generator.config.builder_urls.first().clone();
+    let initializer =
+        HotShotInitializer::<TestTypes>::from_genesis::<TestVersions>(TestInstanceState::default())
+            .await
+            .unwrap();
+
+    let handle = SystemContext::<TestTypes, MemoryImpl, TestVersions>::dev_single_node(
+        &validator_config,
+        builder_url,
+        (launcher.resource_generator.channel_generator)(0).await,
+        initializer,
+        (launcher.resource_generator.storage)(0),
+        (launcher.resource_generator.marketplace_config)(0),
+    )
+    .await
+    .unwrap();
+
+    // Every block is decided, in order, without any other node taking part
+    let mut events = handle.event_stream();
+    let mut next_height = 1;
+    while next_height <= 5 {
+        let event = tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await
+            .expect("the dev node stopped deciding")
+            .unwrap();
+
+        if let EventType::Decide { leaf_chain, .. } = event.event {
+            for leaf_info in leaf_chain.iter().rev() {
+                let height = leaf_info.leaf.block_header().block_number();
+                if height == 0 {
+                    continue;
+                }
+                assert_eq!(height, next_height);
+                next_height += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+use futures::StreamExt;
+use hotshot::execution::{DecidedBlock, ExecutionHook, ExecutionQueue};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{event::LeafInfo, traits::states::ValidatedState};
+use tokio::time::{sleep, timeout};
+
+/// Leaf chains of the decided leaves of `views`, newest first as in decide events
+async fn leaf_chain(views: &[u64]) -> Vec<LeafInfo<TestTypes>> {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let (state, _) = TestValidatedState::genesis(&TestInstanceState::default());
+    let state = Arc::new(state);
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let max_view = views.iter().copied().max().unwrap_or_default();
+    let generated = (&mut generator)
+        .take(usize::try_from(max_view).unwrap())
+        .collect::<Vec<_>>()
+        .await;
+
+    generated
+        .into_iter()
+        .filter(|view| views.contains(&view.leaf.height()))
+        .rev()
+        .map(|view| LeafInfo::new(view.leaf, Arc::clone(&state), None, None))
+        .collect()
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_queue_orders_blocks() {
+    let mut queue = ExecutionQueue::<TestTypes>::new(None);
+    queue.push_leaf_chain(&leaf_chain(&[1, 2, 3]).await);
+
+    // Blocks come out oldest first, although decide events list them newest first
+    let mut executed = Vec::new();
+    while let Some(block) = queue.pop_next() {
+        queue.mark_executed(block.height);
+        executed.push(block.height);
+    }
+    assert_eq!(executed, vec![1, 2, 3]);
+    assert_eq!(queue.cursor(), Some(3));
+
+    // Blocks decided again are not executed twice
+    queue.push_leaf_chain(&leaf_chain(&[2, 3, 4]).await);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue.pop_next().unwrap().height, 4);
+
+    // After a restart, the queue carries on from the persisted cursor
+    let mut queue = ExecutionQueue::<TestTypes>::new(Some(2));
+    queue.push_leaf_chain(&leaf_chain(&[1, 2, 3]).await);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue.pop_next().unwrap().height, 3);
+    assert!(queue.is_empty());
+}
+
+/// Records the height of every block it executes
+struct RecordingHook(Arc<Mutex<Vec<u64>>>);
+
+#[async_trait]
+impl ExecutionHook<TestTypes> for RecordingHook {
+    async fn execute(&mut self, block: &DecidedBlock<TestTypes>) -> anyhow::Result<()> {
+        self.0.lock().await.push(block.height);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_hook_persists_cursor() {
+    hotshot::helpers::initialize_logging();
+
+    let mut handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let executed = Arc::new(Mutex::new(Vec::new()));
+    handle
+        .add_execution_hook(RecordingHook(Arc::clone(&executed)))
+        .await;
+
+    // Starting consensus decides the genesis leaf
+    handle.hotshot.start_consensus().await;
+    timeout(Duration::from_secs(5), async {
+        while executed.lock().await.is_empty() {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("The genesis block was never executed");
+
+    assert_eq!(*executed.lock().await, vec![0]);
+    let storage = handle.storage();
+    timeout(Duration::from_secs(5), async {
+        while storage.read().await.execution_cursor().await.is_none() {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("The execution cursor was never persisted");
+    assert_eq!(storage.read().await.execution_cursor().await, Some(0));
+
+    handle.shut_down().await;
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::types::{Event, EventType, ExternalEvent, ExternalEventType};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber,
+    error::ConsensusError,
+    event::LeafInfo,
+    external_event::EXTERNAL_EVENT_VERSION,
+    traits::{node_implementation::ConsensusTime, states::ValidatedState},
+};
+use serde_json::json;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_external_event_schema() {
+    hotshot::helpers::initialize_logging();
+
+    // The JSON of version 1 events, which must not change within the version
+    let event = Event::<TestTypes> {
+        view_number: ViewNumber::new(3),
+        event: EventType::ViewFinished {
+            view_number: ViewNumber::new(3),
+        },
+    };
+    assert_eq!(
+        serde_json::to_value(ExternalEvent::from(&event)).unwrap(),
+        json!({
+            "version": 1,
+            "view_number": 3,
+            "event": { "type": "view_finished", "view_number": 3 },
+        })
+    );
+
+    let event = Event::<TestTypes> {
+        view_number: ViewNumber::new(4),
+        event: EventType::ConsensusError {
+            error: ConsensusError::StorageFailure("disk full".to_string()),
+        },
+    };
+    assert_eq!(
+        serde_json::to_value(ExternalEvent::from(&event)).unwrap(),
+        json!({
+            "version": 1,
+            "view_number": 4,
+            "event": {
+                "type": "consensus_error",
+                "kind": "storage_failure",
+                "message": "Storage failure: disk full",
+            },
+        })
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_external_event_compatibility() {
+    hotshot::helpers::initialize_logging();
+
+    // Events and fields added later in the same version are tolerated
+    let event: ExternalEvent<TestTypes> = serde_json::from_value(json!({
+        "version": 1,
+        "view_number": 5,
+        "event": { "type": "leader_rotated", "leader": "unknown" },
+    }))
+    .unwrap();
+    assert_eq!(event.event, ExternalEventType::Unknown);
+
+    let event: ExternalEvent<TestTypes> = serde_json::from_value(json!({
+        "version": 1,
+        "view_number": 5,
+        "event": { "type": "view_timeout", "view_number": 5, "reason": "slow leader" },
+    }))
+    .unwrap();
+    assert_eq!(
+        event.event,
+        ExternalEventType::ViewTimeout { view_number: 5 }
+    );
+
+    // Events of a newer version are not
+    let newer = json!({
+        "version": EXTERNAL_EVENT_VERSION + 1,
+        "view_number": 5,
+        "event": { "type": "view_timeout", "view_number": 5 },
+    });
+    assert!(serde_json::from_value::<ExternalEvent<TestTypes>>(newer).is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_external_decide_event() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+    let (state, _) = TestValidatedState::genesis(&TestInstanceState::default());
+    let state = Arc::new(state);
+
+    let qc = views[2].quorum_proposal.data.justify_qc.clone();
+    let event = Event::<TestTypes> {
+        view_number: views[2].view_number,
+        event: EventType::Decide {
+            // Newest first
+            leaf_chain: Arc::new(
+                views
+                    .iter()
+                    .rev()
+                    .map(|view| LeafInfo::new(view.leaf.clone(), Arc::clone(&state), None, None))
+                    .collect(),
+            ),
+            qc: Arc::new(qc.clone()),
+            block_size: Some(1),
+        },
+    };
+
+    let external = ExternalEvent::from(&event);
+    assert_eq!(external.version, EXTERNAL_EVENT_VERSION);
+    let ExternalEventType::Decide {
+        leaves,
+        qc: external_qc,
+        block_size,
+    } = &external.event
+    else {
+        panic!("expected a decide, got {:?}", external.event);
+    };
+
+    // Oldest first, with the commitments as strings
+    assert_eq!(leaves.len(), 3);
+    for (leaf, view) in leaves.iter().zip(&views) {
+        assert_eq!(leaf.height, view.leaf.height());
+        assert_eq!(leaf.view_number, *view.view_number);
+        assert_eq!(leaf.commitment, view.leaf.commit().to_string());
+        assert_eq!(leaf.block_header, *view.leaf.block_header());
+    }
+    assert_eq!(external_qc.leaf_commitment, qc.data.leaf_commit.to_string());
+    assert_eq!(*block_size, Some(1));
+
+    // The event survives a round trip through JSON
+    let json = serde_json::to_string(&external).unwrap();
+    assert_eq!(
+        serde_json::from_str::<ExternalEvent<TestTypes>>(&json).unwrap(),
+        external
+    );
+}
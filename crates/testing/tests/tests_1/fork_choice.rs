@@ -0,0 +1,104 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::types::SystemContextHandle;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    helpers::build_system_handle_from_launcher, test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{fork_choice::ForkChoiceRule, simple_certificate::QuorumCertificate2};
+
+/// A node started with `fork_choice` as its rule
+async fn build_handle(
+    fork_choice: ForkChoiceRule,
+) -> SystemContextHandle<TestTypes, MemoryImpl, TestVersions> {
+    let launcher = TestDescription::<TestTypes, MemoryImpl, TestVersions> {
+        fork_choice,
+        ..TestDescription::default_multiple_rounds()
+    }
+    .gen_launcher(1);
+    build_system_handle_from_launcher(1, &launcher).await.0
+}
+
+/// Two QCs for view 1 certifying different leaves, ordered by leaf commitment, and a QC for view 2
+async fn competing_qcs(
+    handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+) -> (
+    QuorumCertificate2<TestTypes>,
+    QuorumCertificate2<TestTypes>,
+    QuorumCertificate2<TestTypes>,
+) {
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+
+    let qc = views[1].quorum_proposal.data.justify_qc.clone();
+    let mut competing = qc.clone();
+    competing.data.leaf_commit = views[2].leaf.commit();
+    let next_view_qc = views[2].quorum_proposal.data.justify_qc.clone();
+    assert_eq!(qc.view_number, competing.view_number);
+    assert!(next_view_qc.view_number > qc.view_number);
+
+    if <[u8; 32]>::from(qc.data.leaf_commit) < <[u8; 32]>::from(competing.data.leaf_commit) {
+        (qc, competing, next_view_qc)
+    } else {
+        (competing, qc, next_view_qc)
+    }
+}
+
+// By default, a QC for the same view as the high QC replaces it if it certifies the leaf with the
+// higher commitment, so every node settles on the same one whichever order it saw them in
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_highest_qc_breaks_ties_by_commitment() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_handle(ForkChoiceRule::default()).await;
+    let (lower, higher, next_view_qc) = competing_qcs(&handle).await;
+    let consensus = handle.hotshot.consensus();
+    let mut consensus = consensus.write().await;
+
+    consensus.update_high_qc(lower.clone()).unwrap();
+    assert!(consensus.fork_choice().prefers(&higher, &lower));
+    consensus.update_high_qc(higher.clone()).unwrap();
+    assert_eq!(consensus.high_qc(), &higher);
+
+    // The same QC is accepted again, but not the one it was preferred over
+    consensus.update_high_qc(higher.clone()).unwrap();
+    assert!(!consensus.fork_choice().prefers(&lower, &higher));
+    assert!(consensus.update_high_qc(lower).is_err());
+    assert_eq!(consensus.high_qc(), &higher);
+
+    // A QC for a later view is preferred regardless of its commitment, and an earlier one never is
+    consensus.update_high_qc(next_view_qc.clone()).unwrap();
+    assert!(consensus.update_high_qc(higher).is_err());
+    assert_eq!(consensus.high_qc(), &next_view_qc);
+}
+
+// Nodes can be started with the rule which keeps the high QC they have for a view instead
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_highest_view_keeps_first_qc_for_view() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_handle(ForkChoiceRule::HighestView).await;
+    let (lower, higher, next_view_qc) = competing_qcs(&handle).await;
+    let consensus = handle.hotshot.consensus();
+    let mut consensus = consensus.write().await;
+
+    consensus.update_high_qc(lower.clone()).unwrap();
+    assert!(!consensus.fork_choice().prefers(&higher, &lower));
+    assert!(consensus.update_high_qc(higher.clone()).is_err());
+    assert_eq!(consensus.high_qc(), &lower);
+
+    consensus.update_high_qc(next_view_qc.clone()).unwrap();
+    assert!(consensus.update_high_qc(higher).is_err());
+    assert_eq!(consensus.high_qc(), &next_view_qc);
+}
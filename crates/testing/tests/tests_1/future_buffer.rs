@@ -0,0 +1,222 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::{broadcast, Receiver};
+use either::Either;
+use futures::StreamExt;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task::{sharded::ViewSharded, task::TaskState};
+use hotshot_task_impls::{
+    consensus::ConsensusTaskState, da::DaTaskState, events::HotShotEvent,
+    future_buffer::FutureEventBuffer,
+};
+use hotshot_testing::{
+    helpers::{build_system_handle, key_pair_for_id},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    message::UpgradeLock,
+    simple_vote::{TimeoutData2, TimeoutVote2},
+    traits::node_implementation::ConsensusTime,
+    vote::{HasViewNumber, Vote},
+};
+
+/// A timeout vote for `view` from every node
+async fn timeout_votes(
+    view: ViewNumber,
+    epoch: EpochNumber,
+    upgrade_lock: &UpgradeLock<TestTypes, TestVersions>,
+) -> Vec<Arc<HotShotEvent<TestTypes>>> {
+    let mut votes = Vec::new();
+    for id in 0..10 {
+        let (private_key, public_key) = key_pair_for_id::<TestTypes>(id);
+        let vote = TimeoutVote2::<TestTypes>::create_signed_vote(
+            TimeoutData2 { view, epoch },
+            view,
+            &public_key,
+            &private_key,
+            upgrade_lock,
+        )
+        .await
+        .unwrap();
+        votes.push(Arc::new(HotShotEvent::TimeoutVoteRecv(vote)));
+    }
+    votes
+}
+
+/// Every event waiting on `receiver`
+fn drain(
+    receiver: &mut Receiver<Arc<HotShotEvent<TestTypes>>>,
+) -> Vec<Arc<HotShotEvent<TestTypes>>> {
+    std::iter::from_fn(|| receiver.try_recv().ok()).collect()
+}
+
+// Events are held until their view, up to the horizon and the capacity of each view
+#[cfg(test)]
+#[test]
+fn test_future_event_buffer_bounds() {
+    hotshot::helpers::initialize_logging();
+
+    let epoch = EpochNumber::new(0);
+    let event = |view: u64| Arc::new(HotShotEvent::ViewChange(ViewNumber::new(view), epoch));
+    let mut buffer = FutureEventBuffer::<TestTypes>::new(2, 2);
+    let cur_view = ViewNumber::new(10);
+
+    // Current, past and distant views are handled at once
+    assert!(!buffer.hold(cur_view, ViewNumber::new(10), &event(10)));
+    assert!(!buffer.hold(cur_view, ViewNumber::new(9), &event(9)));
+    assert!(!buffer.hold(cur_view, ViewNumber::new(13), &event(13)));
+    assert!(buffer.is_empty());
+
+    assert!(buffer.hold(cur_view, ViewNumber::new(12), &event(12)));
+    assert!(buffer.hold(cur_view, ViewNumber::new(11), &event(11)));
+    assert!(buffer.hold(cur_view, ViewNumber::new(11), &event(111)));
+    // Beyond the capacity of a view, events are dropped rather than handled
+    assert!(buffer.hold(cur_view, ViewNumber::new(11), &event(1111)));
+    assert_eq!(buffer.len(), 3);
+
+    assert!(buffer.release(cur_view).is_empty());
+    let released = buffer.release(ViewNumber::new(11));
+    assert_eq!(released, vec![event(11), event(111)]);
+    assert_eq!(buffer.release(ViewNumber::new(20)), vec![event(12)]);
+    assert!(buffer.is_empty());
+}
+
+// The next leader forms a timeout certificate from votes for a view it has not entered yet,
+// since it needs the certificate to get there
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_next_leader_collects_early_timeout_votes() {
+    hotshot::helpers::initialize_logging();
+
+    // Node 2 leads view 2, and collects the timeout votes for view 1
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state =
+        ConsensusTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    assert_eq!(state.cur_view, ViewNumber::genesis());
+    let (sender, mut receiver) = broadcast(1024);
+
+    let view = ViewNumber::new(1);
+    for vote in timeout_votes(view, state.cur_epoch, &handle.hotshot.upgrade_lock).await {
+        state.handle_event(vote, &sender, &receiver).await.unwrap();
+    }
+    assert!(state.future_events.is_empty());
+    assert!(drain(&mut receiver).iter().any(|event| matches!(
+        event.as_ref(),
+        HotShotEvent::Qc2Formed(Either::Right(certificate)) if certificate.view_number() == view
+    )));
+}
+
+// Votes another node collects are held until their view, and replayed once we enter it
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_consensus_holds_early_timeout_votes() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(5)
+        .await
+        .0;
+    let mut state =
+        ConsensusTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let (sender, mut receiver) = broadcast(1024);
+    let epoch = state.cur_epoch;
+
+    for vote in timeout_votes(ViewNumber::new(1), epoch, &handle.hotshot.upgrade_lock).await {
+        state.handle_event(vote, &sender, &receiver).await.unwrap();
+    }
+    assert_eq!(state.future_events.len(), 10);
+
+    // Votes beyond the horizon are not held
+    for vote in timeout_votes(ViewNumber::new(4), epoch, &handle.hotshot.upgrade_lock).await {
+        state.handle_event(vote, &sender, &receiver).await.unwrap();
+    }
+    assert_eq!(state.future_events.len(), 10);
+
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::ViewChange(ViewNumber::new(1), epoch)),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+    assert!(state.future_events.is_empty());
+    // We don't lead view 2, so no certificate is formed from the replayed votes
+    assert!(!drain(&mut receiver)
+        .iter()
+        .any(|event| matches!(event.as_ref(), HotShotEvent::Qc2Formed(_))));
+}
+
+// DA proposals more than a view ahead of us are held until we are within a view of them, instead
+// of being thrown away
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_da_holds_early_proposals() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state = ViewSharded::new(
+        DaTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await,
+    );
+    let (sender, mut receiver) = broadcast(1024);
+    let views = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships))
+        .take(3)
+        .collect::<Vec<_>>()
+        .await;
+    let epoch = views[0].epoch_number;
+
+    let early = &views[2];
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::DaProposalRecv(
+                early.da_proposal.clone(),
+                early.leader_public_key,
+            )),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+    // The proposal is handled in a subtask of its view
+    while state.views_in_flight() > 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(
+        state.state().read().await.future_events.lock().await.len(),
+        1
+    );
+    assert!(drain(&mut receiver).is_empty());
+
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::ViewChange(ViewNumber::new(2), epoch)),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+    assert!(state
+        .state()
+        .read()
+        .await
+        .future_events
+        .lock()
+        .await
+        .is_empty());
+    assert!(drain(&mut receiver).iter().any(|event| matches!(
+        event.as_ref(),
+        HotShotEvent::DaProposalValidated(proposal, _)
+            if proposal.data.view_number() == early.view_number
+    )));
+}
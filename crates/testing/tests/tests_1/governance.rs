@@ -0,0 +1,229 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::num::NonZeroUsize;
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::helpers::{build_cert, build_system_handle};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    governance::{GovernedParameters, ParameterChange, RuntimeParameters},
+    simple_certificate::GovernanceCertificate,
+    simple_vote::{GovernanceData, GovernanceVote},
+    traits::{
+        consensus_api::ConsensusApi, election::Membership, node_implementation::ConsensusTime,
+        storage::Storage,
+    },
+};
+
+#[cfg(test)]
+#[test]
+fn test_parameter_changes_take_effect_from_activation_view() {
+    let base = RuntimeParameters {
+        view_timeout: 10_000,
+        max_block_size: None,
+        da_committee_size: None,
+    };
+    let parameters = GovernedParameters::<TestTypes>::new(base);
+
+    parameters.schedule(
+        ViewNumber::new(20),
+        ParameterChange {
+            max_block_size: Some(1_000),
+            ..ParameterChange::default()
+        },
+    );
+    parameters.schedule(
+        ViewNumber::new(30),
+        ParameterChange {
+            view_timeout: Some(5_000),
+            ..ParameterChange::default()
+        },
+    );
+
+    assert_eq!(parameters.at(ViewNumber::new(19)), base);
+    assert_eq!(
+        parameters.at(ViewNumber::new(20)),
+        RuntimeParameters {
+            max_block_size: Some(1_000),
+            ..base
+        }
+    );
+    // Later changes keep the parameters earlier ones set
+    assert_eq!(
+        parameters.at(ViewNumber::new(100)),
+        RuntimeParameters {
+            view_timeout: 5_000,
+            max_block_size: Some(1_000),
+            da_committee_size: None,
+        }
+    );
+
+    // A second change for the same view overrides only the parameters it sets
+    parameters.schedule(
+        ViewNumber::new(20),
+        ParameterChange {
+            view_timeout: Some(8_000),
+            max_block_size: Some(2_000),
+            ..ParameterChange::default()
+        },
+    );
+    assert_eq!(
+        parameters.at(ViewNumber::new(25)),
+        RuntimeParameters {
+            view_timeout: 8_000,
+            max_block_size: Some(2_000),
+            da_committee_size: None,
+        }
+    );
+    assert_eq!(parameters.at(ViewNumber::new(30)).view_timeout, 5_000);
+    assert_eq!(parameters.scheduled().len(), 2);
+    assert_eq!(parameters.base(), base);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_da_committee_size_change() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let epoch = EpochNumber::new(0);
+    let da_nodes = &handle.hotshot.config.known_da_nodes;
+    assert!(da_nodes.len() > 1);
+    let first = &da_nodes[0].stake_table_entry.stake_key;
+    let second = &da_nodes[1].stake_table_entry.stake_key;
+
+    // The committee shrinks to its first members from the view the change takes effect in, and
+    // the views before it keep all of them
+    let mut membership = handle.hotshot.memberships.write().await;
+    membership.set_da_committee_size(ViewNumber::new(20), NonZeroUsize::new(1));
+    let before = ViewNumber::new(19);
+    let after = ViewNumber::new(20);
+    assert_eq!(membership.da_total_nodes_at(before, epoch), da_nodes.len());
+    assert!(membership.has_da_stake_at(second, before, epoch));
+    assert_eq!(membership.da_total_nodes_at(after, epoch), 1);
+    assert!(membership.has_da_stake_at(first, after, epoch));
+    assert!(!membership.has_da_stake_at(second, after, epoch));
+    assert_eq!(membership.da_committee_members(after, epoch).len(), 1);
+    assert!(
+        membership.da_success_threshold_at(after, epoch)
+            < membership.da_success_threshold_at(before, epoch)
+    );
+
+    // And grows back to all of them from a later view
+    membership.set_da_committee_size(ViewNumber::new(30), None);
+    assert_eq!(membership.da_total_nodes_at(ViewNumber::new(25), epoch), 1);
+    let restored = ViewNumber::new(30);
+    assert_eq!(
+        membership.da_total_nodes_at(restored, epoch),
+        da_nodes.len()
+    );
+    assert!(membership.has_da_stake_at(second, restored, epoch));
+}
+
+// A change decided after the view it takes effect in, as when the decide is delayed, still applies
+// from that view on, and changes are scheduled again after a restart
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_parameter_changes_reloaded_from_storage() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let epoch = EpochNumber::new(0);
+    let da_nodes = handle.hotshot.config.known_da_nodes.len();
+    let activation_view = ViewNumber::new(20);
+    let change = ParameterChange {
+        view_timeout: Some(5_000),
+        da_committee_size: NonZeroUsize::new(1),
+        ..ParameterChange::default()
+    };
+    handle
+        .storage()
+        .read()
+        .await
+        .append_parameter_change(activation_view, change)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle
+            .hotshot
+            .memberships
+            .read()
+            .await
+            .da_total_nodes_at(ViewNumber::new(40), epoch),
+        da_nodes
+    );
+
+    handle.hotshot.reload_parameter_changes().await;
+    let parameters = &handle.hotshot.governed_parameters;
+    assert_eq!(parameters.at(ViewNumber::new(19)), parameters.base());
+    assert_eq!(parameters.at(ViewNumber::new(40)).view_timeout, 5_000);
+    let membership = handle.hotshot.memberships.read().await;
+    assert_eq!(
+        membership.da_total_nodes_at(ViewNumber::new(19), epoch),
+        da_nodes
+    );
+    assert_eq!(membership.da_total_nodes_at(ViewNumber::new(40), epoch), 1);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_governance_certificate_validation() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let view = ViewNumber::new(5);
+    let epoch = EpochNumber::new(0);
+    let data = GovernanceData {
+        change: ParameterChange {
+            view_timeout: Some(5_000),
+            ..ParameterChange::default()
+        },
+        activation_view: ViewNumber::new(50),
+    };
+
+    let cert = build_cert::<
+        TestTypes,
+        TestVersions,
+        GovernanceData<TestTypes>,
+        GovernanceVote<TestTypes>,
+        GovernanceCertificate<TestTypes>,
+    >(
+        data,
+        &handle.hotshot.memberships,
+        view,
+        epoch,
+        &handle.public_key(),
+        handle.private_key(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await;
+    let membership = &handle.hotshot.memberships;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let validate = move |cert: GovernanceCertificate<TestTypes>| async move {
+        cert.validate(membership, epoch, upgrade_lock).await
+    };
+    assert!(validate(cert.clone()).await.is_ok());
+
+    // The signatures are over the change, which cannot be swapped for another
+    let mut tampered = cert.clone();
+    tampered.data.change.max_block_size = Some(1);
+    assert!(validate(tampered).await.is_err());
+
+    // Nor can the signatures be left out
+    let mut unsigned = cert.clone();
+    unsigned.signatures = None;
+    assert!(validate(unsigned).await.is_err());
+    let mut genesis = cert;
+    genesis.view_number = ViewNumber::genesis();
+    assert!(validate(genesis).await.is_err());
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use hotshot_example_types::state_types::TestInstanceState;
+use hotshot_types::{
+    header_extension::{HeaderExtension, HeaderExtensionError},
+    traits::states::InstanceState,
+};
+use vbs::version::Version;
+
+/// An application which records the L1 block it has synced to in every header, one L1 block per
+/// HotShot block
+#[derive(Clone, Debug)]
+struct L1SyncInstance;
+
+impl InstanceState for L1SyncInstance {
+    fn build_header_extension(
+        &self,
+        parent: Option<&HeaderExtension>,
+        block_number: u64,
+        _version: Version,
+    ) -> Option<HeaderExtension> {
+        let l1_head = parent
+            .and_then(|parent| parent.field("l1 head"))
+            .map_or(0, |bytes| {
+                u64::from_le_bytes(bytes.try_into().unwrap_or_default())
+            });
+
+        Some(HeaderExtension::new(1).with_field(
+            "l1 head",
+            std::cmp::max(l1_head + 1, block_number).to_le_bytes(),
+        ))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_header_extension_validation() {
+    let version = Version { major: 0, minor: 1 };
+    let instance = L1SyncInstance;
+
+    let parent = instance
+        .build_header_extension(None, 1, version)
+        .unwrap();
+    let proposed = instance
+        .build_header_extension(Some(&parent), 2, version)
+        .unwrap();
+
+    // The extension we would have built ourselves is valid
+    assert_eq!(
+        instance.validate_header_extension(Some(&parent), Some(&proposed), 2, version),
+        Ok(())
+    );
+
+    // A missing, wrongly versioned or tampered extension is not
+    assert_eq!(
+        instance.validate_header_extension(Some(&parent), None, 2, version),
+        Err(HeaderExtensionError::Missing)
+    );
+    let mut other_version = proposed.clone();
+    other_version.version = 2;
+    assert_eq!(
+        instance.validate_header_extension(Some(&parent), Some(&other_version), 2, version),
+        Err(HeaderExtensionError::UnsupportedVersion(2))
+    );
+    let tampered = proposed.clone().with_field("l1 head", 100u64.to_le_bytes());
+    assert!(matches!(
+        instance.validate_header_extension(Some(&parent), Some(&tampered), 2, version),
+        Err(HeaderExtensionError::Invalid(_))
+    ));
+    assert_ne!(tampered.commit(), proposed.commit());
+
+    // Applications without extensions reject headers carrying one
+    assert_eq!(
+        TestInstanceState::default().validate_header_extension(None, Some(&proposed), 2, version),
+        Err(HeaderExtensionError::Unexpected)
+    );
+}
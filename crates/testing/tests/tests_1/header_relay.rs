@@ -0,0 +1,104 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{num::NonZeroUsize, sync::Arc};
+
+use bincode::Options;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::EpochNumber,
+    header_relay::{HeaderRelayBatch, HeaderRelayBatcher, RelayHeader},
+    stake_table::StakeTableCommitment,
+    traits::node_implementation::ConsensusTime,
+    utils::bincode_opts,
+};
+
+/// Relay headers for the first `count` generated leaves, each certified by the QC of the next
+async fn relay_headers(count: usize) -> Vec<RelayHeader<TestTypes>> {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(count + 1).collect::<Vec<_>>().await;
+    let stake_table_commitment = StakeTableCommitment::new::<TestTypes>(
+        &*handle.hotshot.memberships.read().await,
+        EpochNumber::new(0),
+    );
+
+    views
+        .windows(2)
+        .map(|pair| RelayHeader {
+            header: pair[0].leaf.block_header().clone(),
+            qc: pair[1].leaf.justify_qc(),
+            stake_table_commitment,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_relay_header_encoding() {
+    hotshot::helpers::initialize_logging();
+
+    let headers = relay_headers(2).await;
+    let encoded = headers[0].encode().unwrap();
+    assert_eq!(
+        bincode_opts()
+            .deserialize::<RelayHeader<TestTypes>>(&encoded)
+            .unwrap(),
+        headers[0]
+    );
+    // The serialization is canonical: encoding again gives the same bytes
+    assert_eq!(headers[0].clone().encode().unwrap(), encoded);
+
+    let batch = HeaderRelayBatch::new(headers.clone()).unwrap();
+    assert_eq!(
+        bincode_opts()
+            .deserialize::<HeaderRelayBatch<TestTypes>>(&batch.encode().unwrap())
+            .unwrap(),
+        batch
+    );
+
+    // Only consecutive headers make a batch
+    assert!(HeaderRelayBatch::<TestTypes>::new(Vec::new()).is_err());
+    assert!(HeaderRelayBatch::new(headers.into_iter().rev().collect()).is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_header_relay_batcher() {
+    hotshot::helpers::initialize_logging();
+
+    let headers = relay_headers(7).await;
+    let heights = |batch: HeaderRelayBatch<TestTypes>| {
+        batch
+            .headers
+            .iter()
+            .map(RelayHeader::height)
+            .collect::<Vec<_>>()
+    };
+    let first = headers[0].height();
+
+    let mut batcher = HeaderRelayBatcher::new(NonZeroUsize::new(3).unwrap());
+    assert!(batcher.push(headers[0].clone()).is_none());
+    assert!(batcher.push(headers[1].clone()).is_none());
+    assert_eq!(
+        heights(batcher.push(headers[2].clone()).unwrap()),
+        vec![first, first + 1, first + 2]
+    );
+
+    // A missed header drops the partial batch before it
+    assert!(batcher.push(headers[3].clone()).is_none());
+    assert!(batcher.push(headers[5].clone()).is_none());
+    assert!(batcher.push(headers[6].clone()).is_none());
+    assert_eq!(
+        heights(batcher.flush().unwrap()),
+        vec![first + 5, first + 6]
+    );
+    assert!(batcher.flush().is_none());
+}
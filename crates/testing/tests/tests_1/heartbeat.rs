@@ -0,0 +1,135 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    num::NonZeroU64,
+    time::{Duration, Instant},
+};
+
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::{run_test, test_scripts};
+use hotshot_task_impls::{events::HotShotEvent::*, heartbeat::HeartbeatTaskState};
+use hotshot_testing::{
+    helpers::build_system_handle,
+    predicates::event::exact,
+    script::{Expectations, InputOrder, TaskScript},
+    serial,
+};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    heartbeat::{Heartbeat, PeerLiveness, SignedHeartbeat},
+    message::UpgradeLock,
+    signature_key::BLSPubKey,
+    traits::{
+        consensus_api::ConsensusApi, node_implementation::ConsensusTime,
+        signature_key::SignatureKey,
+    },
+};
+
+/// A heartbeat for `view`, with the high QC one view behind
+fn heartbeat(view: u64) -> Heartbeat<TestTypes> {
+    Heartbeat {
+        view: ViewNumber::new(view),
+        high_qc_view: ViewNumber::new(view)
+            .checked_sub(1)
+            .unwrap_or_else(ViewNumber::genesis),
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_signed_heartbeat_validation() {
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let other_key = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+
+    let signed = SignedHeartbeat::sign(heartbeat(4), &private_key, &upgrade_lock)
+        .await
+        .unwrap();
+    assert!(signed.is_signed_by(&public_key, &upgrade_lock).await);
+    assert!(!signed.is_signed_by(&other_key, &upgrade_lock).await);
+    assert!(
+        !signed
+            .is_signed_by(&public_key, &upgrade_lock.clone().with_chain_id(1))
+            .await
+    );
+
+    let mut tampered = signed.clone();
+    tampered.heartbeat.view = ViewNumber::new(40);
+    assert!(!tampered.is_signed_by(&public_key, &upgrade_lock).await);
+}
+
+#[cfg(test)]
+#[test]
+fn test_peer_liveness() {
+    let peer_1 = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let peer_2 = BLSPubKey::generated_from_seed_indexed([0u8; 32], 2).0;
+    let now = Instant::now();
+    let mut liveness = PeerLiveness::<TestTypes>::default();
+
+    assert!(liveness.record(peer_1, heartbeat(10), now));
+    assert!(liveness.record(peer_2, heartbeat(4), now));
+    // Older or repeated heartbeats are ignored
+    assert!(!liveness.record(peer_1, heartbeat(8), now));
+    assert!(!liveness.record(peer_1, heartbeat(10), now));
+    assert_eq!(
+        liveness.status(&peer_1).unwrap().heartbeat.view,
+        ViewNumber::new(10)
+    );
+
+    let cur_view = ViewNumber::new(12);
+    assert!(liveness.is_live(&peer_1, cur_view, 6));
+    assert!(!liveness.is_live(&peer_2, cur_view, 6));
+    assert_eq!(
+        liveness.live_peers(cur_view, 6).collect::<Vec<_>>(),
+        vec![&peer_1]
+    );
+    assert_eq!(liveness.live_peers(cur_view, 8).count(), 2);
+
+    let unknown = BLSPubKey::generated_from_seed_indexed([0u8; 32], 3).0;
+    assert!(liveness.status(&unknown).is_none());
+    assert!(!liveness.is_live(&unknown, cur_view, u64::MAX));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_heartbeat_task_sends_every_interval() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+
+    let expected = SignedHeartbeat::sign(
+        Heartbeat {
+            view: ViewNumber::new(2),
+            high_qc_view: ViewNumber::genesis(),
+        },
+        handle.private_key(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .unwrap();
+
+    let inputs = vec![
+        serial![ViewChange(ViewNumber::new(1), EpochNumber::new(0))],
+        serial![ViewChange(ViewNumber::new(2), EpochNumber::new(0))],
+    ];
+
+    let mut state = HeartbeatTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    state.interval = NonZeroU64::new(2).unwrap();
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations: vec![
+            Expectations::from_outputs(vec![]),
+            Expectations::from_outputs(vec![exact(HeartbeatSend(expected, handle.public_key()))]),
+        ],
+    };
+
+    run_test![inputs, script].await;
+}
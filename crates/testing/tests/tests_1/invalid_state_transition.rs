@@ -0,0 +1,158 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_task::dependency_task::HandleDepOutput;
+use hotshot_task_impls::{events::HotShotEvent::*, quorum_vote::VoteDependencyHandle};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    consensus::OuterConsensus,
+    data::{EpochNumber, Leaf2, ViewNumber},
+    event::EventType,
+    traits::{
+        block_contents::BlockHeader, consensus_api::ConsensusApi, election::Membership,
+        node_implementation::ConsensusTime,
+    },
+};
+use tokio::time::timeout;
+
+const TIMEOUT: Duration = Duration::from_millis(35);
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_invalid_state_transition_is_surfaced() {
+    hotshot::helpers::initialize_logging();
+
+    let node_id = 2;
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id)
+        .await
+        .0;
+    let membership = Arc::clone(&handle.hotshot.memberships);
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&membership));
+
+    let mut proposals = Vec::new();
+    let mut leaves = Vec::new();
+    let mut dacs = Vec::new();
+    let mut vids = Vec::new();
+    let consensus = handle.hotshot.consensus().clone();
+    let mut consensus_writer = consensus.write().await;
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaves.push(view.leaf.clone());
+        dacs.push(view.da_certificate.clone());
+        vids.push(view.vid_proposal.clone());
+        consensus_writer
+            .update_leaf(
+                Leaf2::from_quorum_proposal(&view.quorum_proposal.data),
+                Arc::new(TestValidatedState::default()),
+                None,
+            )
+            .unwrap();
+    }
+    drop(consensus_writer);
+
+    // The application rejects the block proposed in view 2
+    let view_number = ViewNumber::new(2);
+    let invalid_height = proposals[1].data.block_header.block_number();
+    let instance_state = TestInstanceState {
+        invalid_block_heights: HashSet::from([invalid_height]),
+        ..TestInstanceState::default()
+    };
+
+    let (event_sender, mut event_receiver) = broadcast(1024);
+    let (output_sender, mut output_receiver) = broadcast(1024);
+    VoteDependencyHandle::<TestTypes, MemoryImpl, TestVersions> {
+        public_key: handle.public_key(),
+        private_key: handle.private_key().clone(),
+        consensus: OuterConsensus::new(consensus.clone()),
+        consensus_metrics: Arc::clone(&consensus.read().await.metrics),
+        instance_state: Arc::new(instance_state),
+        membership: Arc::clone(&membership),
+        storage: Arc::clone(&handle.storage()),
+        view_number,
+        sender: event_sender.clone(),
+        receiver: event_receiver.clone().deactivate(),
+        output_event_stream: output_sender,
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        id: handle.hotshot.id,
+        epoch_height: handle.hotshot.config.epoch_height,
+        work_scheduler: Arc::default(),
+    }
+    .handle_dep_result(vec![
+        Arc::new(QuorumProposalValidated(
+            proposals[1].clone(),
+            leaves[0].clone(),
+        )),
+        Arc::new(DaCertificateValidated(dacs[1].clone())),
+        Arc::new(VidShareValidated(vids[1].0[0].clone())),
+    ])
+    .await;
+
+    // We did not vote
+    while let Ok(Ok(event)) = timeout(TIMEOUT, event_receiver.recv_direct()).await {
+        assert!(
+            !matches!(event.as_ref(), QuorumVoteSend(_)),
+            "Voted for an invalid state transition"
+        );
+    }
+
+    // The failure is reported to the application, attributed to the leader of the view
+    let expected_leader = membership
+        .read()
+        .await
+        .leader(view_number, EpochNumber::new(0))
+        .unwrap();
+    let event = timeout(TIMEOUT, output_receiver.recv_direct())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.view_number, view_number);
+    let EventType::InvalidStateTransition {
+        leader,
+        leaf,
+        error,
+    } = &event.event
+    else {
+        panic!(
+            "Expected an invalid state transition, got {:?}",
+            event.event
+        );
+    };
+    assert_eq!(leader, &expected_leader);
+    assert_eq!(leaf.height(), invalid_height);
+    assert!(error.contains("configured to be invalid"));
+
+    // It is counted against the leader
+    assert_eq!(
+        consensus
+            .read()
+            .await
+            .participation()
+            .participation(leader)
+            .unwrap()
+            .invalid_state_transitions,
+        1
+    );
+
+    // And the proposed leaf is recorded along with the error
+    let recorded = handle
+        .storage()
+        .read()
+        .await
+        .invalid_state_transitions_cloned()
+        .await;
+    let (recorded_leaf, recorded_error) = &recorded[&view_number];
+    assert_eq!(recorded_leaf.height(), invalid_height);
+    assert_eq!(recorded_error, error);
+}
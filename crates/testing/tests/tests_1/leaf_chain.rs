@@ -0,0 +1,75 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_lock::RwLock;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    leaf_chain::verify_leaf_chain,
+    traits::{election::Membership, node_implementation::NodeType},
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_verify_leaf_chain() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let membership = &*handle.hotshot.memberships;
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let leaves = (&mut generator)
+        .take(6)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+
+    // The leaves for views 4 and 5, and the QC of the leaf for view 6, decide views 1 to 3
+    assert_eq!(
+        verify_leaf_chain(&leaves, membership, upgrade_lock)
+            .await
+            .unwrap(),
+        3
+    );
+
+    // Without a three-chain nothing is decided
+    assert!(verify_leaf_chain(&leaves[..3], membership, upgrade_lock)
+        .await
+        .is_err());
+    assert!(verify_leaf_chain(&[], membership, upgrade_lock)
+        .await
+        .is_err());
+
+    // Leaves out of order, or with a gap, do not form a chain
+    let mut reversed = leaves.clone();
+    reversed.reverse();
+    assert!(verify_leaf_chain(&reversed, membership, upgrade_lock)
+        .await
+        .is_err());
+
+    let mut gapped = leaves.clone();
+    gapped.remove(2);
+    assert!(verify_leaf_chain(&gapped, membership, upgrade_lock)
+        .await
+        .is_err());
+
+    // The QCs are checked against the quorum of their view, so they are not valid for a
+    // membership whose quorum is another one
+    let known_nodes = &handle.hotshot.config.known_nodes_with_stake;
+    let other = RwLock::new(<TestTypes as NodeType>::Membership::new(
+        known_nodes[..1].to_vec(),
+        known_nodes[..1].to_vec(),
+    ));
+    assert!(verify_leaf_chain(&leaves, &other, upgrade_lock)
+        .await
+        .is_err());
+}
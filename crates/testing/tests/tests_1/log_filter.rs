@@ -0,0 +1,85 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot::helpers::{
+    add_log_filter_override, log_filter, remove_log_filter_override, LogFilterRevert,
+};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::event::{Event, EventType};
+use tokio::time::{sleep, timeout};
+
+/// Wait for the log filter to be back to `base`
+async fn wait_for_revert(base: &str) {
+    timeout(Duration::from_secs(5), async {
+        while log_filter() != base {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("The log filter override was not reverted");
+}
+
+// The log filter is global, so every case runs in one test
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_log_filter_overrides() {
+    hotshot::helpers::initialize_logging();
+
+    let base = log_filter();
+    assert!(add_log_filter_override("hotshot_log_filter_test=loudest").is_err());
+    assert_eq!(log_filter(), base);
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+
+    // An override applies until it is removed
+    let id = handle
+        .override_log_filter("hotshot_log_filter_test=trace", LogFilterRevert::Never)
+        .await
+        .unwrap();
+    assert!(log_filter().ends_with("hotshot_log_filter_test=trace"));
+    assert!(tracing::enabled!(
+        target: "hotshot_log_filter_test",
+        tracing::Level::TRACE
+    ));
+    assert!(remove_log_filter_override(id));
+    assert!(!remove_log_filter_override(id));
+    assert_eq!(log_filter(), base);
+
+    // Overrides revert on their own after a while
+    handle
+        .override_log_filter(
+            "hotshot_log_filter_test=debug",
+            LogFilterRevert::After(Duration::from_millis(50)),
+        )
+        .await
+        .unwrap();
+    wait_for_revert(&base).await;
+
+    // Or once the node reaches a later view
+    let until = handle.cur_view().await + 2;
+    handle
+        .override_log_filter(
+            "hotshot_log_filter_test=debug",
+            LogFilterRevert::AfterViews(2),
+        )
+        .await
+        .unwrap();
+    assert_ne!(log_filter(), base);
+    handle
+        .external_channel_sender()
+        .broadcast_direct(Event {
+            view_number: until,
+            event: EventType::ViewFinished { view_number: until },
+        })
+        .await
+        .unwrap();
+    wait_for_revert(&base).await;
+}
@@ -0,0 +1,36 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot::helpers::LogRedaction;
+
+const LINE: &str = "view 12: vote from BLS_VER_KEY~bQszS-QKYvUij2g20VqS8asttGSb95NrTu2PUj0uMh1CBUxNy1FqyPDjZqB29M7ZbjWqj79QkEOWkpga84AmDYUeTuWmy-0P1AdKHD3ehc-dKvei78BDj5USwXPJiDUlCxvYs_9rWYhagaq-5_LXENr78xel17spftNd5MA1Mw5U with signature BLS_SIG~K3Ewdkv1ODkvEGdbAjBR7AWX8hTgoqCkOtSkNk9O9g4bbyaZnqo-89u6fHZFbrV_7Th0xxeNHV4GLfLr7EHfhUA over payload 0x5f3759df5f3759df5f3759df5f3759df5f3759df5f3759df";
+
+#[cfg(test)]
+#[test]
+fn test_log_redaction() {
+    let none = LogRedaction::parse("");
+    assert!(none.is_none());
+    assert_eq!(none.redact(LINE), LINE);
+
+    let keys = LogRedaction::parse("keys").redact(LINE).into_owned();
+    assert!(!keys.contains("bQszS"));
+    assert!(keys.contains("BLS_VER_KEY~#"));
+    assert!(keys.contains("BLS_SIG~K3Ewd"));
+    // The same key always hashes to the same value
+    assert_eq!(keys, LogRedaction::parse("keys").redact(LINE));
+
+    let all = LogRedaction::parse("all").redact(LINE).into_owned();
+    assert!(all.starts_with("view 12: vote from BLS_VER_KEY~#"));
+    assert!(all.contains("with signature BLS_SIG~<omitted> over"));
+    assert!(all.ends_with("payload 0x5f3759df5f3759df...<48 hex chars>"));
+
+    let payloads = LogRedaction::parse("payloads=4, signatures");
+    assert_eq!(payloads.payload_hex_limit, Some(4));
+    assert!(payloads.omit_signatures);
+    assert!(!payloads.hash_keys);
+    // Short hex strings and numbers are kept
+    assert_eq!(payloads.redact("view 123456789"), "view 123456789");
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::{
+    MemoryImpl, TestConsecutiveLeaderTypes, TestTypes, TestVersions,
+};
+use hotshot_testing::{helpers::build_system_handle, test_builder::TestDescription};
+use hotshot_types::{
+    data::EpochNumber,
+    stake_table::{ThresholdConfig, ThresholdFunction},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    ValidatorConfig,
+};
+
+/// The membership of `TYPES` over 10 equally staked nodes
+fn membership<TYPES: NodeType>() -> TYPES::Membership {
+    let peers = (0..10)
+        .map(|node_id| {
+            ValidatorConfig::<TYPES::SignatureKey>::generated_from_seed_indexed(
+                [0u8; 32], node_id, 1, true,
+            )
+            .public_config()
+        })
+        .collect::<Vec<_>>();
+
+    TYPES::Membership::new(peers.clone(), peers)
+}
+
+// Each membership keeps the upgrade threshold it had before thresholds were weighted by stake
+#[cfg(test)]
+#[test]
+fn test_membership_upgrade_thresholds() {
+    hotshot::helpers::initialize_logging();
+
+    let epoch = EpochNumber::new(0);
+    assert_eq!(
+        membership::<TestTypes>().upgrade_threshold(epoch).as_u64(),
+        9
+    );
+    assert_eq!(
+        membership::<TestConsecutiveLeaderTypes>()
+            .upgrade_threshold(epoch)
+            .as_u64(),
+        10
+    );
+    assert_eq!(
+        membership::<TestTypes>().success_threshold(epoch).as_u64(),
+        7
+    );
+    assert_eq!(
+        membership::<TestConsecutiveLeaderTypes>()
+            .success_threshold(epoch)
+            .as_u64(),
+        7
+    );
+}
+
+// Only small network mode overrides the thresholds of the membership
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_config_thresholds() {
+    hotshot::helpers::initialize_logging();
+
+    let mut config = TestDescription::<TestTypes, MemoryImpl, TestVersions>::default()
+        .gen_launcher(0)
+        .resource_generator
+        .config;
+    assert_eq!(config.thresholds(), None);
+
+    config.small_network = true;
+    config.known_nodes_with_stake.truncate(3);
+    assert_eq!(config.thresholds(), Some(ThresholdConfig::small_network()));
+
+    // Too large a network for small network mode keeps the membership's thresholds
+    config.known_nodes_with_stake = (0..20)
+        .map(|node_id| {
+            ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, true)
+                .public_config()
+        })
+        .collect();
+    assert_eq!(config.thresholds(), None);
+}
+
+// Starting a node leaves the thresholds of a membership with custom ones untouched
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_init_keeps_membership_thresholds() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestConsecutiveLeaderTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let epoch = EpochNumber::new(0);
+    let membership = handle.hotshot.memberships.read().await;
+    assert_eq!(
+        membership.upgrade_threshold(epoch),
+        ThresholdFunction::MoreThanNineTenths.for_stake_table(&membership.stake_table(epoch))
+    );
+}
@@ -0,0 +1,137 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::network::NetworkMessageTaskState;
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    clock::Clock,
+    compact_vote::CompactVoteDecoder,
+    consensus::OuterConsensus,
+    data::ViewNumber,
+    message::{DataMessage, MessageClass, MessageKind, ReplayProtectionConfig},
+    message_ttl::{MessageTtlConfig, TtlHeader},
+    signature_key::BLSPubKey,
+    traits::{
+        network::{DataRequest, RequestKind},
+        node_implementation::ConsensusTime,
+        signature_key::SignatureKey,
+    },
+};
+
+/// A request for the VID share of `view`, which expires with the VID TTL
+fn vid_request(view: u64) -> MessageKind<TestTypes> {
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let view = ViewNumber::new(view);
+
+    MessageKind::Data(DataMessage::RequestData(DataRequest {
+        request: RequestKind::Vid(view, public_key),
+        view,
+        signature: BLSPubKey::sign(&private_key, &[]).unwrap(),
+    }))
+}
+
+#[cfg(test)]
+#[test]
+fn test_ttl_header_round_trip() {
+    let config = MessageTtlConfig::default();
+    let now = SystemTime::now();
+    let header = TtlHeader::new(&vid_request(10), &config, now);
+    assert_eq!(header.class, MessageClass::Vid);
+    assert_eq!(header.expires_after_view, 10 + config.vid.views);
+
+    let message = b"the serialized message".to_vec();
+    let stamped = header.stamp(message.clone());
+    assert_eq!(stamped.len(), TtlHeader::LEN + message.len());
+    assert_eq!(TtlHeader::split(&stamped).unwrap(), (header, &message[..]));
+
+    // Neither a truncated header nor an unknown class can be read
+    assert!(TtlHeader::split(&stamped[..TtlHeader::LEN - 1]).is_err());
+    let mut unknown_class = stamped;
+    unknown_class[0] = u8::MAX;
+    assert!(TtlHeader::split(&unknown_class).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_ttl_header_expiry() {
+    let config = MessageTtlConfig::default();
+    let sent = SystemTime::now();
+    let header = TtlHeader::new(&vid_request(10), &config, sent);
+    let last_view = 10 + config.vid.views;
+
+    assert!(!header.is_expired(last_view, sent));
+    assert!(header.is_expired(last_view + 1, sent));
+    assert!(!header.is_expired(10, sent + config.vid.duration));
+    assert!(header.is_expired(10, sent + config.vid.duration + Duration::from_secs(1)));
+
+    // Messages not tied to a view never expire
+    let external = TtlHeader::new(
+        &MessageKind::<TestTypes>::External(Vec::new()),
+        &config,
+        sent,
+    );
+    assert_eq!(external.class, MessageClass::Unbound);
+    assert!(!external.is_expired(u64::MAX, sent + Duration::from_secs(365 * 24 * 60 * 60)));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_expired_messages_dropped_unread() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let (internal_event_stream, _internal_receiver) = async_broadcast::broadcast(10);
+    let (external_event_stream, _external_receiver) = async_broadcast::broadcast(10);
+    let mut state = NetworkMessageTaskState::<TestTypes, TestVersions> {
+        internal_event_stream,
+        external_event_stream,
+        public_key: handle.public_key(),
+        transactions_cache: lru::LruCache::new(NonZeroUsize::new(100).unwrap()),
+        consensus: OuterConsensus::new(handle.hotshot.consensus()),
+        replay_protection: ReplayProtectionConfig::default(),
+        rejected_messages: lru::LruCache::new(NonZeroUsize::new(100).unwrap()),
+        beyond_horizon_messages: 0,
+        horizon_catchup: None,
+        clock: Clock::default(),
+        expired_messages: HashMap::new(),
+        compact_votes: CompactVoteDecoder::new(Arc::default()),
+        peer_scores: Arc::default(),
+        membership: Arc::clone(&handle.hotshot.memberships),
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        epoch_height: handle.epoch_height,
+    };
+
+    let message = vec![0u8; 1024];
+    let live = TtlHeader::new(&vid_request(0), &MessageTtlConfig::default(), SystemTime::now());
+    assert_eq!(
+        state.strip_ttl_header(&live.stamp(message.clone())).await,
+        Some(&message[..])
+    );
+
+    // Garbage behind the header isn't decoded, so it doesn't matter that it isn't a message
+    let expired = TtlHeader {
+        expires_at_ms: 0,
+        ..live
+    };
+    assert_eq!(
+        state.strip_ttl_header(&expired.stamp(message.clone())).await,
+        None
+    );
+    assert_eq!(state.strip_ttl_header(&expired.stamp(message)).await, None);
+
+    assert_eq!(state.expired_message_count(MessageClass::Vid), 2);
+    assert_eq!(state.expired_message_count(MessageClass::Vote), 0);
+}
@@ -11,12 +11,16 @@ use async_lock::RwLock;
 use hotshot::traits::implementations::MemoryNetwork;
 use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
 use hotshot_task::task::{ConsensusTaskRegistry, Task};
-use hotshot_task_impls::{events::HotShotEvent, network::NetworkEventTaskState};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    network::{upcoming_leaders, NetworkEventTaskState},
+};
 use hotshot_testing::{
     helpers::build_system_handle, test_builder::TestDescription,
     test_task::add_network_message_test_task, view_generator::TestViewGenerator,
 };
 use hotshot_types::{
+    clock::Clock,
     consensus::OuterConsensus,
     data::{EpochNumber, ViewNumber},
     message::UpgradeLock,
@@ -70,8 +74,15 @@ async fn test_network_task() {
             membership: Arc::clone(&membership),
             upgrade_lock: upgrade_lock.clone(),
             storage,
-            consensus,
+            consensus: consensus.clone(),
             transmit_tasks: BTreeMap::new(),
+            resent_outbound_messages: false,
+            connection_warm_up_views: config.connection_warm_up_views,
+            message_ttl: config.message_ttl,
+            clock: Clock::default(),
+            known_stake_table: Arc::new(config.known_stake_keys()),
+            proposal_dispersal_min_bytes: config.proposal_dispersal_min_bytes,
+            send_scheduler: Arc::default(),
         };
     let (tx, rx) = async_broadcast::broadcast(10);
     let mut task_reg = ConsensusTaskRegistry::new();
@@ -79,7 +90,7 @@ async fn test_network_task() {
     let task = Task::new(network_state, tx.clone(), rx);
     task_reg.run_task(task);
 
-    let mut generator = TestViewGenerator::generate(membership);
+    let mut generator = TestViewGenerator::generate(Arc::clone(&membership));
     let view = generator.next().await.unwrap();
 
     let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(10);
@@ -90,6 +101,9 @@ async fn test_network_task() {
         upgrade_lock,
         network.clone(),
         public_key,
+        consensus,
+        Arc::clone(&membership),
+        config.epoch_height,
     )
     .await;
 
@@ -242,8 +256,15 @@ async fn test_network_storage_fail() {
             membership: Arc::clone(&membership),
             upgrade_lock: upgrade_lock.clone(),
             storage,
-            consensus,
+            consensus: consensus.clone(),
             transmit_tasks: BTreeMap::new(),
+            resent_outbound_messages: false,
+            connection_warm_up_views: config.connection_warm_up_views,
+            message_ttl: config.message_ttl,
+            clock: Clock::default(),
+            known_stake_table: Arc::new(config.known_stake_keys()),
+            proposal_dispersal_min_bytes: config.proposal_dispersal_min_bytes,
+            send_scheduler: Arc::default(),
         };
     let (tx, rx) = async_broadcast::broadcast(10);
     let mut task_reg = ConsensusTaskRegistry::new();
@@ -251,7 +272,7 @@ async fn test_network_storage_fail() {
     let task = Task::new(network_state, tx.clone(), rx);
     task_reg.run_task(task);
 
-    let mut generator = TestViewGenerator::generate(membership);
+    let mut generator = TestViewGenerator::generate(Arc::clone(&membership));
     let view = generator.next().await.unwrap();
 
     let (out_tx_internal, mut out_rx_internal): (Sender<Arc<HotShotEvent<TestTypes>>>, _) =
@@ -263,6 +284,9 @@ async fn test_network_storage_fail() {
         upgrade_lock,
         network.clone(),
         public_key,
+        consensus,
+        Arc::clone(&membership),
+        config.epoch_height,
     )
     .await;
 
@@ -275,3 +299,167 @@ async fn test_network_storage_fail() {
     let res = timeout(Duration::from_millis(100), out_rx_internal.recv_direct()).await;
     assert!(res.is_err());
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_network_resends_outbound_messages() {
+    use std::collections::BTreeMap;
+
+    use futures::StreamExt;
+    use hotshot_types::{
+        message::{
+            convert_proposal, GeneralConsensusMessage, Message, MessageKind, OutboundMessage,
+            SequencingMessage,
+        },
+        traits::{network::TransmitType, storage::Storage},
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    let builder: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_multiple_rounds();
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let node_id = 1;
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id)
+        .await
+        .0;
+    let launcher = builder.gen_launcher(node_id);
+
+    let network = (launcher.resource_generator.channel_generator)(node_id).await;
+
+    let test_storage = (launcher.resource_generator.storage)(node_id);
+    let consensus = OuterConsensus::new(handle.hotshot.consensus());
+    let config = launcher.resource_generator.config.clone();
+    let validator_config = launcher.resource_generator.validator_config.clone();
+    let public_key = validator_config.public_key;
+
+    let all_nodes = config.known_nodes_with_stake.clone();
+
+    let membership = Arc::new(RwLock::new(<TestTypes as NodeType>::Membership::new(
+        all_nodes.clone(),
+        all_nodes,
+    )));
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&membership));
+    let view = generator.next().await.unwrap();
+
+    // The proposal was queued, but the node went down before sending it
+    test_storage
+        .append_outbound_message(&OutboundMessage {
+            message: Message {
+                sender: public_key,
+                kind: MessageKind::from_consensus_message(SequencingMessage::General(
+                    GeneralConsensusMessage::Proposal(convert_proposal(view.quorum_proposal)),
+                )),
+            },
+            transmit: TransmitType::Broadcast,
+        })
+        .await
+        .unwrap();
+
+    let network_state: NetworkEventTaskState<TestTypes, TestVersions, MemoryNetwork<_>, _> =
+        NetworkEventTaskState {
+            network: network.clone(),
+            view: ViewNumber::new(0),
+            epoch: EpochNumber::new(0),
+            membership: Arc::clone(&membership),
+            upgrade_lock: upgrade_lock.clone(),
+            storage: Arc::new(RwLock::new(test_storage.clone())),
+            consensus: consensus.clone(),
+            transmit_tasks: BTreeMap::new(),
+            resent_outbound_messages: false,
+            connection_warm_up_views: config.connection_warm_up_views,
+            message_ttl: config.message_ttl,
+            clock: Clock::default(),
+            known_stake_table: Arc::new(config.known_stake_keys()),
+            proposal_dispersal_min_bytes: config.proposal_dispersal_min_bytes,
+            send_scheduler: Arc::default(),
+        };
+    let (tx, rx) = async_broadcast::broadcast(10);
+    let mut task_reg = ConsensusTaskRegistry::new();
+
+    let task = Task::new(network_state, tx.clone(), rx);
+    task_reg.run_task(task);
+
+    let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(10);
+    let (out_tx_external, _) = async_broadcast::broadcast(10);
+    add_network_message_test_task(
+        out_tx_internal.clone(),
+        out_tx_external.clone(),
+        upgrade_lock,
+        network.clone(),
+        public_key,
+        consensus,
+        Arc::clone(&membership),
+        config.epoch_height,
+    )
+    .await;
+
+    // Restarting in the view after the proposal is still within the window
+    tx.broadcast_direct(Arc::new(HotShotEvent::ViewChange(
+        ViewNumber::new(2),
+        EpochNumber::new(0),
+    )))
+    .await
+    .unwrap();
+    let res: Arc<HotShotEvent<TestTypes>> =
+        timeout(Duration::from_millis(100), out_rx_internal.recv_direct())
+            .await
+            .expect("timed out waiting for the re-sent proposal")
+            .expect("channel closed");
+    assert!(matches!(
+        res.as_ref(),
+        HotShotEvent::QuorumProposalRecv(_, _)
+    ));
+
+    // Once sent, the message is no longer persisted
+    timeout(Duration::from_millis(100), async {
+        while !test_storage.outbound_messages_cloned().await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .expect("the re-sent message was not removed from storage");
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upcoming_leaders() {
+    let builder: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_multiple_rounds();
+    let launcher = builder.gen_launcher(0);
+    let all_nodes = launcher
+        .resource_generator
+        .config
+        .known_nodes_with_stake
+        .clone();
+    let num_nodes = all_nodes.len() as u64;
+
+    let membership = RwLock::new(<TestTypes as NodeType>::Membership::new(
+        all_nodes.clone(),
+        all_nodes,
+    ));
+    let view = ViewNumber::new(3);
+    let epoch = EpochNumber::new(0);
+
+    // The leaders of the next views, starting with the one we vote to in this view
+    let expected = {
+        let membership = membership.read().await;
+        (1..=3)
+            .map(|offset| membership.leader(view + offset, epoch).unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        upcoming_leaders::<TestTypes>(&membership, view, epoch, 3).await,
+        expected
+    );
+
+    // Looking further ahead than the committee is large doesn't repeat leaders
+    let leaders = upcoming_leaders::<TestTypes>(&membership, view, epoch, 2 * num_nodes).await;
+    assert_eq!(leaders.len() as u64, num_nodes);
+    assert_eq!(leaders[..3], expected[..]);
+
+    assert!(upcoming_leaders::<TestTypes>(&membership, view, epoch, 0)
+        .await
+        .is_empty());
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc};
+
+use async_broadcast::Sender;
+use futures::StreamExt;
+use hotshot::types::{BLSPubKey, Event, SignatureKey, SystemContextHandle};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::{events::HotShotEvent, network::NetworkMessageTaskState};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    clock::Clock,
+    compact_vote::CompactVoteDecoder,
+    consensus::OuterConsensus,
+    data::{EpochNumber, ViewNumber},
+    message::{
+        GeneralConsensusMessage, Message, MessageKind, ReplayProtectionConfig, SequencingMessage,
+        ViewTolerance,
+    },
+    simple_vote::{TimeoutData2, TimeoutVote2, ViewSyncFinalizeData2, ViewSyncFinalizeVote2},
+    traits::{consensus_api::ConsensusApi, node_implementation::ConsensusTime},
+};
+
+/// The network message task of `handle`, which accepts proposals up to 3 views ahead of its
+/// current view, but messages at most 2 views ahead
+fn horizon_state(
+    handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+    internal_event_stream: Sender<Arc<HotShotEvent<TestTypes>>>,
+    external_event_stream: Sender<Event<TestTypes>>,
+) -> NetworkMessageTaskState<TestTypes, TestVersions> {
+    NetworkMessageTaskState {
+        internal_event_stream,
+        external_event_stream,
+        public_key: handle.public_key(),
+        transactions_cache: lru::LruCache::new(NonZeroUsize::new(100).unwrap()),
+        consensus: OuterConsensus::new(handle.hotshot.consensus()),
+        replay_protection: ReplayProtectionConfig {
+            proposal: ViewTolerance::new(100, 3),
+            max_outstanding_views: 2,
+            ..ReplayProtectionConfig::default()
+        },
+        rejected_messages: lru::LruCache::new(NonZeroUsize::new(100).unwrap()),
+        beyond_horizon_messages: 0,
+        horizon_catchup: None,
+        clock: Clock::default(),
+        expired_messages: HashMap::new(),
+        compact_votes: CompactVoteDecoder::new(Arc::default()),
+        peer_scores: Arc::default(),
+        membership: Arc::clone(&handle.hotshot.memberships),
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        epoch_height: handle.epoch_height,
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_messages_beyond_horizon_dropped_for_catchup() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let (internal_event_stream, mut internal_receiver) = async_broadcast::broadcast(100);
+    let (external_event_stream, _external_receiver) = async_broadcast::broadcast(10);
+    let mut state = horizon_state(&handle, internal_event_stream, external_event_stream);
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let mut proposals = Vec::new();
+    for view in (&mut generator).take(5).collect::<Vec<_>>().await {
+        proposals.push(Message {
+            sender: view.leader_public_key,
+            kind: MessageKind::<TestTypes>::Consensus(SequencingMessage::General(
+                GeneralConsensusMessage::Proposal2(view.quorum_proposal.clone()),
+            )),
+        });
+    }
+
+    // Proposals for views 1 and 2 are within the horizon of the genesis view; those for views 3
+    // to 5 are dropped, and each further view beyond the horizon moves catchup further ahead, even
+    // past the replay protection window
+    for proposal in &proposals {
+        state.handle_message(proposal.clone()).await;
+    }
+    state.handle_message(proposals[3].clone()).await;
+
+    let mut events = Vec::new();
+    while let Ok(event) = internal_receiver.try_recv() {
+        events.push(event);
+    }
+    let received: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event.as_ref() {
+            HotShotEvent::QuorumProposalRecv(proposal, _) => Some(*proposal.data.view_number),
+            _ => None,
+        })
+        .collect();
+    let catchups: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event.as_ref() {
+            HotShotEvent::CatchupStart(view) => Some(**view),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(received, vec![1, 2]);
+    assert_eq!(catchups, vec![3, 4, 5]);
+    assert_eq!(state.beyond_horizon_message_count(), 4);
+
+    // Dropping messages from honest peers ahead of us is not held against them
+    assert_eq!(state.rejected_message_count(&proposals[4].sender), 0);
+
+    // Once our view changes, we catch up again to views we already tried to catch up to
+    handle
+        .hotshot
+        .consensus()
+        .write()
+        .await
+        .update_view(ViewNumber::new(1))
+        .unwrap();
+    state.handle_message(proposals[3].clone()).await;
+    assert!(matches!(
+        internal_receiver.try_recv().unwrap().as_ref(),
+        HotShotEvent::CatchupStart(view) if **view == 4
+    ));
+
+    // View sync is how we move ahead, so its messages are accepted beyond the horizon
+    let round = ViewNumber::new(50);
+    let finalize_vote = ViewSyncFinalizeVote2::<TestTypes>::create_signed_vote(
+        ViewSyncFinalizeData2 {
+            relay: 0,
+            round,
+            epoch: EpochNumber::new(0),
+        },
+        round,
+        &handle.public_key(),
+        handle.private_key(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .unwrap();
+    state
+        .handle_message(Message {
+            sender: handle.public_key(),
+            kind: MessageKind::Consensus(SequencingMessage::General(
+                GeneralConsensusMessage::ViewSyncFinalizeVote2(finalize_vote.clone()),
+            )),
+        })
+        .await;
+    assert!(matches!(
+        internal_receiver.try_recv().unwrap().as_ref(),
+        HotShotEvent::ViewSyncFinalizeVoteRecv(vote) if *vote == finalize_vote
+    ));
+
+    // Once we have moved ahead, the messages for the views we moved to are accepted
+    handle
+        .hotshot
+        .consensus()
+        .write()
+        .await
+        .update_view(ViewNumber::new(3))
+        .unwrap();
+    state.handle_message(proposals[4].clone()).await;
+    assert!(matches!(
+        internal_receiver.try_recv().unwrap().as_ref(),
+        HotShotEvent::QuorumProposalRecv(proposal, _) if *proposal.data.view_number == 5
+    ));
+    assert_eq!(state.beyond_horizon_message_count(), 5);
+}
+
+// Anyone can claim a far-future view, so messages which don't prove it are dropped without
+// catching up to it
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unproven_messages_beyond_horizon_do_not_start_catchup() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let (internal_event_stream, mut internal_receiver) = async_broadcast::broadcast(100);
+    let (external_event_stream, _external_receiver) = async_broadcast::broadcast(10);
+    let mut state = horizon_state(&handle, internal_event_stream, external_event_stream);
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(5).collect::<Vec<_>>().await;
+    let message = |kind| Message {
+        sender: views[4].leader_public_key,
+        kind: MessageKind::<TestTypes>::Consensus(SequencingMessage::General(kind)),
+    };
+
+    // A proposal for view 5 carrying the signature of another view's proposal
+    let mut forged = views[4].quorum_proposal.clone();
+    forged.signature = views[3].quorum_proposal.signature.clone();
+    state
+        .handle_message(message(GeneralConsensusMessage::Proposal2(forged)))
+        .await;
+
+    // A timeout vote for view 5 signed by a key without stake
+    let (outsider, outsider_key) = BLSPubKey::generated_from_seed_indexed([1u8; 32], 100);
+    let timeout_vote = TimeoutVote2::<TestTypes>::create_signed_vote(
+        TimeoutData2 {
+            view: ViewNumber::new(5),
+            epoch: EpochNumber::new(0),
+        },
+        ViewNumber::new(5),
+        &outsider,
+        &outsider_key,
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .unwrap();
+    state
+        .handle_message(message(GeneralConsensusMessage::TimeoutVote2(timeout_vote)))
+        .await;
+
+    assert_eq!(state.beyond_horizon_message_count(), 2);
+    assert!(internal_receiver.try_recv().is_err());
+
+    // The forged messages don't stop us catching up to the leader's proposal for the same view
+    state
+        .handle_message(message(GeneralConsensusMessage::Proposal2(
+            views[4].quorum_proposal.clone(),
+        )))
+        .await;
+    assert!(matches!(
+        internal_receiver.try_recv().unwrap().as_ref(),
+        HotShotEvent::CatchupStart(view) if **view == 5
+    ));
+}
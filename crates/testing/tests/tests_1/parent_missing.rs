@@ -0,0 +1,54 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_task_impls::helpers::saved_parent_leaf_and_state;
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{data::Leaf2, error::ConsensusError};
+use utils::anytrace::Level;
+
+// A proposal whose parent we haven't seen yet is classified as such, rather than as invalid, so
+// it is neither logged as a warning nor surfaced to the operator
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_parent_missing_classification() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let consensus = handle.hotshot.consensus();
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+    let justify_qc = &views[1].quorum_proposal.data.justify_qc;
+
+    let error = saved_parent_leaf_and_state(&*consensus.read().await, justify_qc).unwrap_err();
+    assert!(matches!(error, ConsensusError::ParentMissing(_)));
+    assert_eq!(error.kind(), "parent_missing");
+    assert!(!error.is_user_actionable());
+    assert_eq!(error.level(), Level::Debug);
+
+    // Once we have seen the parent, it is found
+    let parent = Leaf2::from_quorum_proposal(&views[0].quorum_proposal.data);
+    consensus
+        .write()
+        .await
+        .update_leaf(
+            parent.clone(),
+            Arc::new(TestValidatedState::default()),
+            None,
+        )
+        .unwrap();
+    let (leaf, _) = saved_parent_leaf_and_state(&*consensus.read().await, justify_qc).unwrap();
+    assert_eq!(leaf, parent);
+}
@@ -0,0 +1,215 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, marker::PhantomData, sync::Arc, time::Duration};
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    helpers::{build_system_handle, key_pair_for_id},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    peer_score::{Misbehavior, PeerScoreConfig, PeerScoreboard},
+    signature_key::BLSPubKey,
+    simple_certificate::QuorumCertificate2,
+    simple_vote::{QuorumData2, QuorumVote2},
+    traits::signature_key::SignatureKey,
+    vote::{Vote, VoteAccumulator},
+};
+use tokio::time::sleep;
+
+/// A scoreboard which bans after two equivocations, and whose scores don't decay
+fn scoreboard(max_messages_per_second: Option<u64>) -> PeerScoreboard<TestTypes> {
+    PeerScoreboard::new(PeerScoreConfig {
+        equivocation_penalty: 5,
+        ban_threshold: 10,
+        ban_cooldown: Duration::from_secs(60),
+        score_decay_interval: Duration::ZERO,
+        max_messages_per_second,
+    })
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_banned_at_threshold() {
+    hotshot::helpers::initialize_logging();
+
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let other_peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 2).0;
+    let scores = scoreboard(None);
+
+    assert!(!scores.report(&peer, Misbehavior::Equivocation).await);
+    assert!(scores.admit(&peer).await);
+    assert!(scores.report(&peer, Misbehavior::Equivocation).await);
+    assert!(scores.is_banned(&peer).await);
+    assert!(!scores.admit(&peer).await);
+
+    // Other peers are unaffected
+    assert!(!scores.report(&other_peer, Misbehavior::Equivocation).await);
+    assert!(scores.admit(&other_peer).await);
+
+    let banned = scores.banned_peers().await;
+    assert_eq!(banned.len(), 1);
+    assert_eq!(banned[0].0, peer);
+    assert!(banned[0].1 <= Duration::from_secs(60));
+
+    let record = scores.records().await[&peer].clone();
+    assert_eq!(record.equivocations, 2);
+    assert_eq!(record.bans, 1);
+    assert_eq!(record.score, 0);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_ban_cleared() {
+    hotshot::helpers::initialize_logging();
+
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let scores = scoreboard(None);
+
+    for _ in 0..2 {
+        scores.report(&peer, Misbehavior::Equivocation).await;
+    }
+    assert!(scores.is_banned(&peer).await);
+
+    assert!(scores.clear_ban(&peer).await);
+    assert!(!scores.is_banned(&peer).await);
+    assert!(scores.admit(&peer).await);
+    assert!(!scores.clear_ban(&peer).await);
+
+    // The record of what the peer did is kept after its ban is lifted
+    assert_eq!(scores.records().await[&peer].equivocations, 2);
+}
+
+// Scores decay by one for every decay interval, so only misbehavior repeated faster than the decay
+// leads to a ban
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_score_decays() {
+    hotshot::helpers::initialize_logging();
+
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let scores = PeerScoreboard::<TestTypes>::new(PeerScoreConfig {
+        score_decay_interval: Duration::from_millis(100),
+        ..*scoreboard(None).config()
+    });
+
+    assert!(!scores.report(&peer, Misbehavior::Equivocation).await);
+    assert_eq!(scores.records().await[&peer].score, 5);
+
+    sleep(Duration::from_millis(250)).await;
+    let score = scores.records().await[&peer].score;
+    assert!(score < 5, "score {score} did not decay");
+
+    // The decayed score is what the next penalty adds to
+    assert!(!scores.report(&peer, Misbehavior::Equivocation).await);
+    assert!(scores.records().await[&peer].score < 10);
+
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(scores.records().await[&peer].score, 0);
+    assert_eq!(scores.records().await[&peer].equivocations, 2);
+}
+
+// Messages beyond the rate limit are dropped, but as the limit is kept per claimed sender, they
+// don't count against it
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_rate_limited() {
+    hotshot::helpers::initialize_logging();
+
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let scores = scoreboard(Some(5));
+
+    for _ in 0..5 {
+        assert!(scores.admit(&peer).await);
+    }
+    for _ in 0..100 {
+        assert!(!scores.admit(&peer).await);
+    }
+    assert!(!scores.is_banned(&peer).await);
+    assert!(!scores.records().await.contains_key(&peer));
+
+    // The limit applies to each second
+    sleep(Duration::from_secs(1)).await;
+    assert!(scores.admit(&peer).await);
+
+    // Penalties can be switched off entirely
+    let lenient = PeerScoreboard::<TestTypes>::new(PeerScoreConfig {
+        ban_threshold: 0,
+        ..PeerScoreConfig::default()
+    });
+    for _ in 0..1000 {
+        lenient.report(&peer, Misbehavior::Equivocation).await;
+    }
+    assert!(!lenient.is_banned(&peer).await);
+}
+
+// Only a validly signed vote conflicting with an earlier one from the same signer is attributed to
+// it; a vote with an invalid signature may carry anyone's key
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_vote_accumulator_attributes_equivocation() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let memberships = Arc::clone(&handle.hotshot.memberships);
+    let views = TestViewGenerator::generate(Arc::clone(&memberships))
+        .take(2)
+        .collect::<Vec<_>>()
+        .await;
+    let view = views[0].view_number;
+    let epoch = views[0].epoch_number;
+    let upgrade_lock = handle.hotshot.upgrade_lock.clone();
+
+    let mut accumulator =
+        VoteAccumulator::<TestTypes, QuorumVote2<TestTypes>, QuorumCertificate2<TestTypes>, _> {
+            vote_outcomes: HashMap::new(),
+            signers: HashMap::new(),
+            phantom: PhantomData,
+            upgrade_lock: upgrade_lock.clone(),
+            equivocators: Vec::new(),
+        };
+    let vote_for = |id: u64, leaf: usize| {
+        let (private_key, public_key) = key_pair_for_id::<TestTypes>(id);
+        let data = QuorumData2 {
+            leaf_commit: views[leaf].leaf.commit(),
+            epoch,
+        };
+        let upgrade_lock = upgrade_lock.clone();
+        async move {
+            QuorumVote2::<TestTypes>::create_signed_vote(
+                data,
+                view,
+                &public_key,
+                &private_key,
+                &upgrade_lock,
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    // Node 2's key on node 3's signature
+    let mut forged = vote_for(3, 1).await;
+    forged.signature.0 = key_pair_for_id::<TestTypes>(2).1;
+    let _ = accumulator.accumulate(&forged, &memberships, epoch).await;
+    assert!(accumulator.equivocators.is_empty());
+
+    let first = vote_for(2, 0).await;
+    let _ = accumulator.accumulate(&first, &memberships, epoch).await;
+    let _ = accumulator.accumulate(&first, &memberships, epoch).await;
+    assert!(accumulator.equivocators.is_empty());
+
+    let conflicting = vote_for(2, 1).await;
+    let _ = accumulator
+        .accumulate(&conflicting, &memberships, epoch)
+        .await;
+    assert_eq!(accumulator.equivocators, vec![conflicting.signing_key()]);
+}
@@ -0,0 +1,160 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    helpers::build_system_handle,
+    view_generator::{TestView, TestViewGenerator},
+};
+use hotshot_types::{
+    data::{Leaf, Leaf2, QuorumProposal, VidDisperseShare, VidDisperseShare2},
+    persistence::{self, Envelope, RecordKind, FORMAT_VERSION, MAGIC},
+    signature_key::BLSPubKey,
+    simple_certificate::QuorumCertificate2,
+    snapshot::ChainSnapshot,
+    HotShotConfig,
+};
+
+/// A few views of a chain to persist records of, and the config of the node they are for
+async fn views() -> (Vec<TestView>, HotShotConfig<BLSPubKey>) {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+
+    (
+        (&mut generator).take(3).collect::<Vec<_>>().await,
+        handle.hotshot.config.clone(),
+    )
+}
+
+/// `record` persisted with version 1 of the format
+fn encode_v1<T: serde::Serialize>(kind: RecordKind, record: &T) -> Vec<u8> {
+    Envelope {
+        version: 1,
+        kind,
+        payload: bincode::serialize(record).unwrap(),
+    }
+    .encode()
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_persisted_records_round_trip() {
+    hotshot::helpers::initialize_logging();
+
+    let (views, config) = views().await;
+    let view = &views[2];
+
+    let leaf = view.leaf.clone();
+    let bytes = persistence::encode(&leaf).unwrap();
+    assert_eq!(bytes[..4], MAGIC);
+    assert_eq!(
+        persistence::decode::<Leaf2<TestTypes>>(&bytes).unwrap(),
+        leaf
+    );
+
+    let qc = view.quorum_proposal.data.justify_qc.clone();
+    let bytes = persistence::encode(&qc).unwrap();
+    assert_eq!(
+        persistence::decode::<QuorumCertificate2<TestTypes>>(&bytes).unwrap(),
+        qc
+    );
+
+    let share = view.vid_proposal.0[0].data.clone();
+    let bytes = persistence::encode(&share).unwrap();
+    assert_eq!(
+        persistence::decode::<VidDisperseShare2<TestTypes>>(&bytes).unwrap(),
+        share
+    );
+
+    let snapshot = ChainSnapshot::<TestTypes> {
+        leaf: leaf.clone(),
+        validated_state: None,
+        stake_table: config.known_nodes_with_stake.clone(),
+        da_stake_table: config.known_da_nodes.clone(),
+    };
+    let bytes = persistence::encode(&snapshot).unwrap();
+    let decoded = persistence::decode::<ChainSnapshot<TestTypes>>(&bytes).unwrap();
+    assert_eq!(decoded.leaf, snapshot.leaf);
+    assert!(decoded.validated_state.is_none());
+    assert_eq!(decoded.stake_table, snapshot.stake_table);
+    assert_eq!(decoded.da_stake_table, snapshot.da_stake_table);
+
+    // A record of one kind is not read as another
+    assert!(persistence::decode::<Leaf2<TestTypes>>(&bytes).is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_version_1_records_are_upgraded() {
+    hotshot::helpers::initialize_logging();
+
+    let (views, _) = views().await;
+    let view = &views[2];
+
+    // Records written by a node from before epochs, which persisted the legacy types
+    let leaf = Leaf::<TestTypes>::from_quorum_proposal(&QuorumProposal::from(
+        view.quorum_proposal.data.clone(),
+    ));
+    let bytes = encode_v1(RecordKind::Leaf, &leaf);
+    assert_eq!(
+        persistence::decode::<Leaf2<TestTypes>>(&bytes).unwrap(),
+        Leaf2::from(leaf)
+    );
+
+    let qc = view.quorum_proposal.data.justify_qc.clone().to_qc();
+    let bytes = encode_v1(RecordKind::QuorumCertificate, &qc);
+    assert_eq!(
+        persistence::decode::<QuorumCertificate2<TestTypes>>(&bytes).unwrap(),
+        qc.to_qc2()
+    );
+
+    let share = VidDisperseShare::from(view.vid_proposal.0[0].data.clone());
+    let bytes = encode_v1(RecordKind::VidShare, &share);
+    assert_eq!(
+        persistence::decode::<VidDisperseShare2<TestTypes>>(&bytes).unwrap(),
+        VidDisperseShare2::from(share)
+    );
+
+    // Version 1 had no snapshots
+    let bytes = encode_v1(RecordKind::Snapshot, &view.leaf);
+    assert!(persistence::decode::<ChainSnapshot<TestTypes>>(&bytes).is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_invalid_records_are_rejected() {
+    hotshot::helpers::initialize_logging();
+
+    let (views, _) = views().await;
+    let bytes = persistence::encode(&views[1].leaf).unwrap();
+    let decode = |bytes: &[u8]| persistence::decode::<Leaf2<TestTypes>>(bytes);
+    assert!(decode(&bytes).is_ok());
+
+    // A flipped bit in the payload fails the checksum
+    let mut corrupt = bytes.clone();
+    corrupt[20] ^= 1;
+    assert!(decode(&corrupt).is_err());
+
+    // As does a truncated record
+    assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    assert!(decode(&bytes[..8]).is_err());
+
+    // Bytes which are not a persisted record at all
+    let mut not_a_record = bytes.clone();
+    not_a_record[..4].copy_from_slice(b"JUNK");
+    assert!(decode(&not_a_record).is_err());
+
+    // A record written by a newer node, with a valid checksum
+    let mut envelope = Envelope::decode(&bytes).unwrap();
+    assert_eq!(envelope.version, FORMAT_VERSION);
+    envelope.version = FORMAT_VERSION + 1;
+    assert!(decode(&envelope.encode()).is_err());
+}
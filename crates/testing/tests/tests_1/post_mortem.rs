@@ -0,0 +1,143 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{fs, path::Path, sync::Arc};
+
+use async_broadcast::broadcast;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::{
+    events::HotShotEvent::{self, *},
+    post_mortem::{PostMortemTaskState, WATCHDOG_STUCK_VIEW_THRESHOLD},
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    consensus::OuterConsensus,
+    data::{EpochNumber, ViewNumber},
+    traits::node_implementation::ConsensusTime,
+    vote::HasViewNumber,
+};
+
+/// The names of the bundles written under `dir`
+fn bundles(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+// The watchdog writes one bundle per stall, and a bundle for a decided chain that does not extend
+// the chain decided before it
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_post_mortem_bundles() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let dir = std::env::temp_dir().join(format!("hotshot-post-mortem-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let consensus = handle.hotshot.consensus();
+    let decided_leaf = consensus.read().await.decided_leaf();
+    let mut state = PostMortemTaskState::<TestTypes, MemoryImpl>::new(
+        dir.clone(),
+        OuterConsensus::new(consensus),
+        Arc::clone(&handle.hotshot.network),
+        &decided_leaf,
+        2,
+    );
+    let (sender, receiver) = broadcast::<Arc<HotShotEvent<TestTypes>>>(16);
+    let epoch = EpochNumber::new(0);
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let leaves = (&mut generator)
+        .take(3)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+
+    // A chain extending the decided leaf is not a safety violation
+    state
+        .handle_event(
+            Arc::new(LeavesDecided(vec![leaves[1].clone(), leaves[0].clone()])),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+    assert!(bundles(&dir).is_empty());
+
+    // Progress short of the watchdog threshold is not a stall
+    let last_decided = leaves[1].view_number().u64();
+    for view in last_decided + 1..last_decided + WATCHDOG_STUCK_VIEW_THRESHOLD {
+        state
+            .handle_event(
+                Arc::new(ViewChange(ViewNumber::new(view), epoch)),
+                &sender,
+                &receiver,
+            )
+            .await
+            .unwrap();
+    }
+    assert!(bundles(&dir).is_empty());
+
+    // A stall is reported once, however long it lasts
+    for view in 0..5 {
+        state
+            .handle_event(
+                Arc::new(ViewChange(
+                    ViewNumber::new(last_decided + WATCHDOG_STUCK_VIEW_THRESHOLD + view),
+                    epoch,
+                )),
+                &sender,
+                &receiver,
+            )
+            .await
+            .unwrap();
+    }
+    let stuck = bundles(&dir);
+    assert_eq!(stuck.len(), 1);
+    assert!(stuck[0].starts_with("node_2_") && stuck[0].ends_with("_stuck"));
+
+    let bundle = dir.join(&stuck[0]);
+    for file in [
+        "reason.txt",
+        "events.log",
+        "tasks.txt",
+        "network.txt",
+        "consensus.txt",
+    ] {
+        assert!(bundle.join(file).is_file(), "missing {file}");
+    }
+    let reason = fs::read_to_string(bundle.join("reason.txt")).unwrap();
+    assert!(reason.starts_with("Watchdog: no decide since view"));
+    let events = fs::read_to_string(bundle.join("events.log")).unwrap();
+    assert!(events.contains("ViewChange"));
+
+    // A decided leaf which skips its parent is a safety violation
+    state
+        .handle_event(
+            Arc::new(LeavesDecided(vec![leaves[2].clone(), leaves[0].clone()])),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+    let all = bundles(&dir);
+    assert_eq!(all.len(), 2);
+    let safety = all.iter().find(|name| name.ends_with("_safety")).unwrap();
+    let reason = fs::read_to_string(dir.join(safety).join("reason.txt")).unwrap();
+    assert!(reason.starts_with("Safety violation: decided leaf for view"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
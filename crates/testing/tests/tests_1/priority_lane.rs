@@ -0,0 +1,271 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::{tasks::task_state::CreateTaskState, types::EventType};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::{events::HotShotEvent, transactions::TransactionTaskState};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber,
+    governance::{GovernedParameters, RuntimeParameters},
+    traits::{block_contents::TransactionPriority, node_implementation::ConsensusTime},
+};
+use tokio::time::timeout;
+
+/// A distinct transaction for every `id`, of `size` bytes
+fn transaction(id: u8, size: usize) -> TestTransaction {
+    TestTransaction::new(vec![id; size])
+}
+
+/// A transaction task for node 2, which reserves `reserved_percent` of blocks of at most
+/// `max_block_size` for high-priority transactions
+async fn transaction_task(
+    max_block_size: Option<u64>,
+    reserved_percent: u64,
+) -> TransactionTaskState<TestTypes, MemoryImpl, TestVersions> {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    state.governed_parameters = Arc::new(GovernedParameters::new(RuntimeParameters {
+        max_block_size,
+        ..state.governed_parameters.base()
+    }));
+    state.high_priority_reserved_percent = reserved_percent;
+    state
+}
+
+/// Tell `state` about `transactions`, as if they were received from the network
+async fn receive(
+    state: &mut TransactionTaskState<TestTypes, MemoryImpl, TestVersions>,
+    transactions: Vec<(TestTransaction, TransactionPriority)>,
+) {
+    let (sender, receiver) = broadcast(16);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::PrioritizedTransactionsRecv(transactions)),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+}
+
+// High-priority transactions go first, and normal transactions may not use the reserved part of a
+// bounded block even when it is left empty
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_priority_lane_reserves_block_space() {
+    hotshot::helpers::initialize_logging();
+
+    let view = ViewNumber::new(1);
+    let high = [transaction(1, 30), transaction(2, 30)];
+    let normal = [
+        transaction(3, 20),
+        transaction(4, 20),
+        transaction(5, 20),
+        transaction(6, 20),
+    ];
+    let offered = vec![
+        normal[0].clone(),
+        high[0].clone(),
+        normal[1].clone(),
+        high[1].clone(),
+        normal[2].clone(),
+        normal[3].clone(),
+    ];
+
+    let mut state = transaction_task(Some(100), 40).await;
+    assert_eq!(
+        state.apply_priority_lane(offered.clone(), view),
+        vec![normal[0].clone(), high[0].clone()]
+    );
+
+    receive(
+        &mut state,
+        high.iter()
+            .map(|transaction| (transaction.clone(), TransactionPriority::High))
+            .chain([(normal[0].clone(), TransactionPriority::Normal)])
+            .collect(),
+    )
+    .await;
+    assert_eq!(
+        state.apply_priority_lane(offered.clone(), view),
+        vec![
+            high[0].clone(),
+            high[1].clone(),
+            normal[0].clone(),
+            normal[1].clone(),
+        ]
+    );
+
+    // High-priority transactions may take up the whole block
+    let mut state = transaction_task(Some(50), 40).await;
+    receive(
+        &mut state,
+        high.iter()
+            .map(|transaction| (transaction.clone(), TransactionPriority::High))
+            .collect(),
+    )
+    .await;
+    assert_eq!(
+        state.apply_priority_lane(offered.clone(), view),
+        vec![high[0].clone(), normal[0].clone()]
+    );
+
+    // Without a maximum block size nothing is left out
+    let mut state = transaction_task(None, 40).await;
+    receive(
+        &mut state,
+        vec![(high[1].clone(), TransactionPriority::High)],
+    )
+    .await;
+    assert_eq!(
+        state.apply_priority_lane(offered, view),
+        vec![
+            high[1].clone(),
+            normal[0].clone(),
+            high[0].clone(),
+            normal[1].clone(),
+            normal[2].clone(),
+            normal[3].clone(),
+        ]
+    );
+}
+
+// The priority of a transaction is tracked from its arrival until it is decided
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_priority_lane_tracks_transactions_until_decided() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let mut events = handle.event_stream();
+
+    let decided = transaction(1, 8);
+    let pending = transaction(2, 8);
+    let received = vec![
+        (decided.clone(), TransactionPriority::High),
+        (pending.clone(), TransactionPriority::Normal),
+    ];
+    receive(&mut state, received.clone()).await;
+
+    // Builders are told about the priorities
+    loop {
+        let event = timeout(Duration::from_secs(2), events.next())
+            .await
+            .expect("The transactions were not forwarded")
+            .unwrap();
+        if let EventType::PrioritizedTransactions { transactions } = event.event {
+            assert_eq!(transactions, received);
+            break;
+        }
+    }
+
+    assert_eq!(
+        state
+            .transaction_arrivals
+            .get(&decided.commit())
+            .map(|(priority, _)| *priority),
+        Some(TransactionPriority::High)
+    );
+
+    // A transaction keeps the priority it first arrived with
+    receive(
+        &mut state,
+        vec![(pending.clone(), TransactionPriority::High)],
+    )
+    .await;
+    assert_eq!(
+        state
+            .transaction_arrivals
+            .get(&pending.commit())
+            .map(|(priority, _)| *priority),
+        Some(TransactionPriority::Normal)
+    );
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    generator.add_transactions(vec![decided.clone()]);
+    let leaves = (&mut generator)
+        .take(2)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+    let (sender, receiver) = broadcast(16);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::LeavesDecided(
+                leaves.into_iter().rev().collect(),
+            )),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+
+    assert!(!state.transaction_arrivals.contains_key(&decided.commit()));
+    assert!(state.transaction_arrivals.contains_key(&pending.commit()));
+}
+
+// Transactions submitted with a high priority are published with it, and others as before
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_priority_lane_submission() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut events = handle.event_stream();
+
+    let high = transaction(1, 8);
+    handle
+        .submit_transaction_with_priority(high.clone(), TransactionPriority::High)
+        .await
+        .unwrap();
+    let normal = transaction(2, 8);
+    handle
+        .submit_transaction_with_priority(normal.clone(), TransactionPriority::Normal)
+        .await
+        .unwrap();
+
+    let mut published_high = false;
+    let mut published_normal = false;
+    while !(published_high && published_normal) {
+        let event = timeout(Duration::from_secs(2), events.next())
+            .await
+            .expect("The transactions were not published")
+            .unwrap();
+        match event.event {
+            EventType::PrioritizedTransactions { transactions } => {
+                assert_eq!(
+                    transactions,
+                    vec![(high.clone(), TransactionPriority::High)]
+                );
+                published_high = true;
+            }
+            EventType::Transactions { transactions } => {
+                assert_eq!(transactions, vec![normal.clone()]);
+                published_normal = true;
+            }
+            _ => {}
+        }
+    }
+}
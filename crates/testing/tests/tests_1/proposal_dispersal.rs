@@ -0,0 +1,109 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use hotshot_example_types::node_types::{EpochsTestVersions, MemoryImpl, TestTypes};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    helpers::build_system_handle,
+    test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::EpochNumber,
+    proposal_dispersal::{ProposalChunk, ProposalChunkCollector},
+    signature_key::BLSPubKey,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+    vid::vid_recovery_threshold,
+};
+
+/// Number of quorum members the test proposal is dispersed among
+const NUM_NODES: usize = 10;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_proposal_recovered_from_chunks() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, EpochsTestVersions>(1)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let view = generator.next().await.unwrap();
+    let proposal = view.quorum_proposal;
+
+    let (leader, leader_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let impostor = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+
+    assert!(!ProposalChunk::should_disperse(&proposal, None));
+    assert!(ProposalChunk::should_disperse(&proposal, Some(0)));
+    assert!(!ProposalChunk::should_disperse(&proposal, Some(u64::MAX)));
+
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let signing_prefix = upgrade_lock.signing_prefix(proposal.data.view_number).await;
+    let chunks = ProposalChunk::disperse(
+        &proposal,
+        EpochNumber::genesis(),
+        NUM_NODES,
+        &leader_key,
+        &signing_prefix,
+    )
+    .unwrap();
+    assert_eq!(chunks.len(), NUM_NODES);
+    for chunk in &chunks {
+        assert!(chunk.is_valid(&leader, upgrade_lock).await);
+        assert!(!chunk.is_valid(&impostor, upgrade_lock).await);
+    }
+
+    // A chunk claiming another view no longer matches the leader's signature
+    let mut relabelled = chunks[0].clone();
+    relabelled.view_number = relabelled.view_number + 1;
+    assert!(!relabelled.is_valid(&leader, upgrade_lock).await);
+
+    let threshold = vid_recovery_threshold(NUM_NODES);
+    let mut collector = ProposalChunkCollector::<TestTypes>::default();
+    for chunk in &chunks[..threshold - 1] {
+        assert!(collector.insert(chunk).is_none());
+    }
+    // A share forwarded again under another index does not count towards the threshold
+    let mut duplicate = chunks[0].clone();
+    duplicate.index = chunks[threshold - 1].index;
+    assert!(collector.insert(&duplicate).is_none());
+    assert_eq!(
+        collector.chunk_count(proposal.data.view_number, &chunks[0].commitment),
+        threshold - 1
+    );
+
+    let recoverable = collector.insert(&chunks[threshold - 1]).unwrap();
+    assert_eq!(recoverable.recover().unwrap(), proposal);
+
+    // The proposal is only recovered once
+    assert!(collector.insert(&chunks[threshold]).is_none());
+}
+
+// Every proposal is dispersed in chunks, and the network still makes progress
+cross_tests!(
+    TestName: test_success_with_proposal_dispersal,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [EpochsTestVersions],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            proposal_dispersal_min_bytes: Some(0),
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            ..TestDescription::default()
+        }
+    },
+);
@@ -0,0 +1,114 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{net::Ipv4Addr, sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use futures::future::join_all;
+use hotshot::traits::implementations::PushCdnNetwork;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    signature_key::BLSPubKey,
+    traits::{
+        election::Membership,
+        network::{BroadcastDelay, ConnectedNetwork, Topic},
+        node_implementation::NodeType,
+    },
+    ValidatorConfig,
+};
+use tokio::time::{sleep, timeout};
+
+/// Number of nodes on the network
+const NUM_NODES: u64 = 3;
+
+/// The longest a message may take to arrive
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A committee of `NUM_NODES` nodes, of which only `da_node` is on the DA committee
+fn membership(da_node: u64) -> Arc<RwLock<<TestTypes as NodeType>::Membership>> {
+    let peer = |node_id: u64| {
+        ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed(
+            [0u8; 32],
+            node_id,
+            1,
+            node_id == da_node,
+        )
+        .public_config()
+    };
+    Arc::new(RwLock::new(<TestTypes as NodeType>::Membership::new(
+        (0..NUM_NODES).map(peer).collect(),
+        vec![peer(da_node)],
+    )))
+}
+
+// Nodes follow their DA committee membership with their CDN subscriptions as the view changes, so
+// DA broadcasts only reach the committee
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_push_cdn_topics_follow_roles() {
+    hotshot::helpers::initialize_logging();
+
+    // Node 0 starts out on the DA topic
+    let generator =
+        PushCdnNetwork::<BLSPubKey>::generator_with_loopback(1, Ipv4Addr::LOCALHOST.into());
+    let mut networks = Vec::new();
+    for node_id in 0..NUM_NODES {
+        networks.push(generator(node_id).await);
+    }
+    join_all(networks.iter().map(|network| network.wait_for_ready())).await;
+
+    // The DA committee moves to node 2
+    let membership = membership(2);
+    for network in &networks {
+        network
+            .update_view::<TestTypes>(1, 0, Arc::clone(&membership))
+            .await;
+    }
+    sleep(Duration::from_secs(1)).await;
+
+    // Node 2 receives DA broadcasts once its subscription reaches the brokers
+    timeout(MESSAGE_TIMEOUT, async {
+        loop {
+            networks[1]
+                .broadcast_message(b"da".to_vec(), Topic::Da, BroadcastDelay::None)
+                .await
+                .unwrap();
+            if timeout(Duration::from_millis(500), networks[2].recv_message())
+                .await
+                .is_ok_and(|message| message.unwrap() == b"da")
+            {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("node 2 did not join the DA topic");
+
+    // Node 0 has left the DA topic, and only receives the global broadcast which follows
+    networks[1]
+        .broadcast_message(b"late da".to_vec(), Topic::Da, BroadcastDelay::None)
+        .await
+        .unwrap();
+    networks[1]
+        .broadcast_message(b"global".to_vec(), Topic::Global, BroadcastDelay::None)
+        .await
+        .unwrap();
+    timeout(MESSAGE_TIMEOUT, async {
+        loop {
+            let message = networks[0].recv_message().await.unwrap();
+            assert_ne!(message, b"late da", "node 0 is still on the DA topic");
+            if message == b"global" {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("node 0 did not receive the global broadcast");
+
+    for network in &networks {
+        network.shut_down().await;
+    }
+}
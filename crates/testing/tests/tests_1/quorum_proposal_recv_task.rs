@@ -183,9 +183,15 @@ async fn test_quorum_proposal_recv_task_liveness_check() {
     };
 
     // make the signed commitment
-    let signature =
-        <TestTypes as NodeType>::SignatureKey::sign(handle.private_key(), req.commit().as_ref())
-            .unwrap();
+    let signature = <TestTypes as NodeType>::SignatureKey::sign(
+        handle.private_key(),
+        &handle
+            .hotshot
+            .upgrade_lock
+            .signing_message(req.commit().as_ref(), req.view_number)
+            .await,
+    )
+    .unwrap();
 
     let expectations = vec![Expectations::from_outputs(all_predicates![
         exact(QuorumProposalPreliminarilyValidated(proposals[2].clone())),
@@ -235,3 +235,82 @@ async fn test_quorum_vote_task_incorrect_dependency() {
     };
     run_test![inputs, script].await;
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quorum_vote_task_vid_storage_failure() {
+    use hotshot_example_types::storage_types::{StorageFaults, StorageWrite};
+    use hotshot_task_impls::{events::HotShotEvent::*, quorum_vote::QuorumVoteTaskState};
+    use hotshot_testing::{
+        helpers::build_system_handle, predicates::event::exact, view_generator::TestViewGenerator,
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    // The VID share must be stored before voting, whether the write fails outright or the storage
+    // crashes while writing it
+    for faults in [
+        StorageFaults::default()
+            .targeting([StorageWrite::Vid])
+            .fail_every_nth_write(1),
+        StorageFaults::default()
+            .targeting([StorageWrite::Vid])
+            .crash_at_write(1),
+    ] {
+        let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+            .await
+            .0;
+        handle.storage().write().await.faults = faults;
+
+        let membership = Arc::clone(&handle.hotshot.memberships);
+
+        let mut generator = TestViewGenerator::generate(membership);
+
+        let mut proposals = Vec::new();
+        let mut leaves = Vec::new();
+        let mut dacs = Vec::new();
+        let mut vids = Vec::new();
+        let mut leaders = Vec::new();
+        let consensus = handle.hotshot.consensus().clone();
+        let mut consensus_writer = consensus.write().await;
+        for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+            leaders.push(view.leader_public_key);
+            proposals.push(view.quorum_proposal.clone());
+            leaves.push(view.leaf.clone());
+            dacs.push(view.da_certificate.clone());
+            vids.push(view.vid_proposal.clone());
+            consensus_writer
+                .update_leaf(
+                    Leaf2::from_quorum_proposal(&view.quorum_proposal.data),
+                    Arc::new(TestValidatedState::default()),
+                    None,
+                )
+                .unwrap();
+        }
+        drop(consensus_writer);
+
+        // All the dependencies are met, but the node can't store its VID share, so there's no vote.
+        let inputs = vec![random![
+            QuorumProposalValidated(proposals[1].clone(), leaves[0].clone()),
+            DaCertificateRecv(dacs[1].clone()),
+            VidShareRecv(leaders[1], vids[1].0[0].clone()),
+        ]];
+
+        let expectations = vec![Expectations::from_outputs(all_predicates![
+            exact(DaCertificateValidated(dacs[1].clone())),
+            exact(VidShareValidated(vids[1].0[0].clone())),
+            exact(ViewChange(ViewNumber::new(3), EpochNumber::new(0))),
+        ])];
+
+        let quorum_vote_state =
+            QuorumVoteTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle)
+                .await;
+
+        let mut script = TaskScript {
+            timeout: TIMEOUT,
+            state: quorum_vote_state,
+            expectations,
+        };
+        run_test![inputs, script].await;
+    }
+}
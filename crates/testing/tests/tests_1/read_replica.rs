@@ -0,0 +1,219 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use hotshot::types::{Event, EventType};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_testing::{
+    helpers::{build_system_handle, key_pair_for_id},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    event::LeafInfo,
+    read_replica::{ReadReplica, ReplicaFeedItem, ReplicaFeedMessage, SignedReplicaFeedMessage},
+    vote::HasViewNumber,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_read_replica_follows_validator_feed() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let feed = handle.read_replica_feed();
+
+    // The validator validates the proposals of views 1 to 3, and decides the leaves of views 1 and 2
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(4).collect::<Vec<_>>().await;
+    let decide_qc = views[2].quorum_proposal.data.justify_qc.clone();
+    let mut events = views[..3]
+        .iter()
+        .cloned()
+        .map(|view| EventType::QuorumProposal {
+            proposal: view.quorum_proposal,
+            sender: view.leader_public_key,
+        })
+        .collect::<Vec<_>>();
+    events.push(EventType::Decide {
+        leaf_chain: Arc::new(
+            [&views[1], &views[0]]
+                .into_iter()
+                .map(|view| {
+                    LeafInfo::new(
+                        view.leaf.clone(),
+                        Arc::new(TestValidatedState::default()),
+                        None,
+                        None,
+                    )
+                })
+                .collect(),
+        ),
+        qc: Arc::new(decide_qc.clone()),
+        block_size: None,
+    });
+    let sender = handle.external_channel_sender();
+    for event in events {
+        sender
+            .broadcast_direct(Event {
+                view_number: views[2].view_number,
+                event,
+            })
+            .await
+            .unwrap();
+    }
+
+    // Each proposal comes after its justify QC, and the decide last
+    let messages = tokio::time::timeout(Duration::from_secs(10), feed.take(7).collect::<Vec<_>>())
+        .await
+        .expect("the feed stopped yielding messages");
+    let views_and_kinds = messages
+        .iter()
+        .map(|signed| {
+            let kind = match signed.message.item {
+                ReplicaFeedItem::Proposal(_) => "proposal",
+                ReplicaFeedItem::Certificate(_) => "certificate",
+                ReplicaFeedItem::Decide { .. } => "decide",
+            };
+            (*signed.message.item.view_number(), kind)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        views_and_kinds,
+        vec![
+            (0, "certificate"),
+            (1, "proposal"),
+            (1, "certificate"),
+            (2, "proposal"),
+            (2, "certificate"),
+            (3, "proposal"),
+            (2, "decide"),
+        ]
+    );
+    for (sequence, signed) in (0..).zip(&messages) {
+        assert_eq!(signed.message.sequence, sequence);
+        assert_eq!(signed.signer, handle.public_key());
+        assert!(signed.is_valid(upgrade_lock).await);
+    }
+
+    let mut replica = ReadReplica::<TestTypes>::new(handle.public_key(), 10);
+    for signed in &messages {
+        replica.apply(signed, upgrade_lock).await.unwrap();
+    }
+    assert_eq!(replica.validator(), &handle.public_key());
+    assert_eq!(replica.decided_leaf(1), Some(&views[0].leaf));
+    assert_eq!(replica.last_decided_leaf(), Some(&views[1].leaf));
+    assert_eq!(replica.last_decided_qc(), Some(&decide_qc));
+    assert_eq!(replica.high_qc(), Some(&decide_qc));
+    assert_eq!(
+        replica
+            .latest_proposal()
+            .map(|proposal| proposal.data.view_number()),
+        Some(views[2].view_number)
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_read_replica_rejects_forged_and_out_of_sequence_messages() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let (private_key, public_key) = key_pair_for_id::<TestTypes>(1);
+    let (forger_private_key, forger_public_key) = key_pair_for_id::<TestTypes>(2);
+    assert_eq!(public_key, handle.public_key());
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+    let message = |feed: u64, sequence: u64, view: usize| ReplicaFeedMessage {
+        feed,
+        sequence,
+        item: ReplicaFeedItem::Proposal(views[view].quorum_proposal.clone()),
+    };
+    let sign = |message: ReplicaFeedMessage<TestTypes>| {
+        let public_key = handle.public_key();
+        let private_key = &private_key;
+        async move {
+            SignedReplicaFeedMessage::sign(message, public_key, private_key, upgrade_lock)
+                .await
+                .unwrap()
+        }
+    };
+
+    // Messages signed by another node are rejected, whoever they claim to be from
+    let mut replica = ReadReplica::<TestTypes>::new(handle.public_key(), 10);
+    let forged = SignedReplicaFeedMessage::sign(
+        message(5, 0, 0),
+        forger_public_key,
+        &forger_private_key,
+        upgrade_lock,
+    )
+    .await
+    .unwrap();
+    assert!(replica.apply(&forged, upgrade_lock).await.is_err());
+    let mut impersonated = forged;
+    impersonated.signer = handle.public_key();
+    assert!(replica.apply(&impersonated, upgrade_lock).await.is_err());
+
+    // As are messages changed after they were signed
+    let mut renumbered = sign(message(5, 1, 0)).await;
+    renumbered.message.sequence = 0;
+    assert!(replica.apply(&renumbered, upgrade_lock).await.is_err());
+
+    // A feed is only joined at its start
+    assert!(replica
+        .apply(&sign(message(5, 1, 0)).await, upgrade_lock)
+        .await
+        .is_err());
+    replica
+        .apply(&sign(message(5, 0, 0)).await, upgrade_lock)
+        .await
+        .unwrap();
+
+    // Then followed in sequence, without replays or gaps
+    let first = sign(message(5, 1, 1)).await;
+    replica.apply(&first, upgrade_lock).await.unwrap();
+    assert!(replica.apply(&first, upgrade_lock).await.is_err());
+    assert!(replica
+        .apply(&sign(message(5, 3, 2)).await, upgrade_lock)
+        .await
+        .is_err());
+    replica
+        .apply(&sign(message(5, 2, 2)).await, upgrade_lock)
+        .await
+        .unwrap();
+
+    // A newer feed can be joined at its start, but never an older one
+    assert!(replica
+        .apply(&sign(message(4, 0, 2)).await, upgrade_lock)
+        .await
+        .is_err());
+    replica
+        .apply(&sign(message(6, 0, 2)).await, upgrade_lock)
+        .await
+        .unwrap();
+    assert!(replica
+        .apply(&sign(message(5, 3, 2)).await, upgrade_lock)
+        .await
+        .is_err());
+
+    assert_eq!(
+        replica
+            .latest_proposal()
+            .map(|proposal| proposal.data.view_number()),
+        Some(views[2].view_number)
+    );
+}
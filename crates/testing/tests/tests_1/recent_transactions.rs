@@ -0,0 +1,124 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_broadcast::broadcast;
+use futures::StreamExt;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::{
+    events::HotShotEvent, recent_transactions::RecentTransactionFilter,
+    transactions::TransactionTaskState,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+
+/// A distinct transaction for every `id`
+fn transaction(id: u8) -> TestTransaction {
+    TestTransaction::new(vec![id; 8])
+}
+
+// Decided transactions are remembered for at least one further generation of insertions
+#[cfg(test)]
+#[test]
+fn test_recent_transaction_filter_generations() {
+    hotshot::helpers::initialize_logging();
+
+    let mut filter = RecentTransactionFilter::new(1 << 16, 2);
+    assert!(!filter.contains(&transaction(0)));
+
+    filter.insert(&transaction(0));
+    filter.insert(&transaction(1));
+    assert!(filter.contains(&transaction(0)));
+    assert!(filter.contains(&transaction(1)));
+    assert!(!filter.contains(&transaction(2)));
+
+    // Rotating keeps the full generation around for lookups
+    filter.insert(&transaction(2));
+    filter.insert(&transaction(3));
+    for id in 0..4 {
+        assert!(filter.contains(&transaction(id)));
+    }
+
+    // Rotating again forgets the oldest generation
+    filter.insert(&transaction(4));
+    assert!(!filter.contains(&transaction(0)));
+    assert!(!filter.contains(&transaction(1)));
+    for id in 2..5 {
+        assert!(filter.contains(&transaction(id)));
+    }
+}
+
+// A bloom filter hit on a transaction which was not decided, as is certain with a single bit, is
+// not taken for a decided transaction
+#[cfg(test)]
+#[test]
+fn test_recent_transaction_filter_false_positives_confirmed() {
+    hotshot::helpers::initialize_logging();
+
+    let mut filter = RecentTransactionFilter::new(1, 2);
+    filter.insert(&transaction(0));
+    assert!(filter.contains(&transaction(0)));
+    assert!(filter.might_contain(&transaction(1)));
+    assert!(!filter.contains(&transaction(1)));
+
+    // Decided transactions of the previous generation are still confirmed
+    filter.insert(&transaction(1));
+    filter.insert(&transaction(2));
+    assert!(filter.contains(&transaction(0)));
+    assert!(filter.contains(&transaction(2)));
+    assert!(!filter.contains(&transaction(3)));
+}
+
+// The transaction task records the transactions of decided leaves, so that they are kept out of
+// the blocks it proposes
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_task_records_decided_transactions() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+
+    let decided = vec![transaction(1), transaction(2)];
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let mut leaves = (&mut generator)
+        .take(1)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+    generator.add_transactions(decided.clone());
+    leaves.extend(
+        (&mut generator)
+            .take(1)
+            .map(|view| view.leaf)
+            .collect::<Vec<_>>()
+            .await,
+    );
+    leaves.reverse();
+
+    let (sender, receiver) = broadcast(16);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::LeavesDecided(leaves)),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+
+    for transaction in &decided {
+        assert!(state.recent_transactions.contains(transaction));
+    }
+    assert!(!state.recent_transactions.contains(&transaction(3)));
+}
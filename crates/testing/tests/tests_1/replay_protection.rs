@@ -0,0 +1,100 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::{TestTypes, TestVersions};
+use hotshot_testing::helpers::key_pair_for_id;
+use hotshot_types::{
+    constants::{REPLAY_WINDOW_FUTURE_VIEWS, REPLAY_WINDOW_PAST_VIEWS},
+    data::{EpochNumber, ViewNumber},
+    message::{
+        GeneralConsensusMessage, MessageKind, ReplayProtectionConfig, SequencingMessage,
+        UpgradeLock, ViewTolerance,
+    },
+    simple_vote::{TimeoutData2, TimeoutVote2, ViewSyncFinalizeData2, ViewSyncFinalizeVote2},
+    traits::node_implementation::ConsensusTime,
+};
+
+// A tolerance includes exactly the views up to its bounds on either side of the current view
+#[cfg(test)]
+#[test]
+fn test_view_tolerance_edges() {
+    hotshot::helpers::initialize_logging();
+
+    let tolerance = ViewTolerance::new(10, 5);
+    assert!(tolerance.contains(100, 100));
+    assert!(tolerance.contains(90, 100));
+    assert!(!tolerance.contains(89, 100));
+    assert!(tolerance.contains(105, 100));
+    assert!(!tolerance.contains(106, 100));
+
+    // The past bound saturates at genesis, and the future bound at the last view
+    assert!(tolerance.contains(0, 3));
+    assert!(tolerance.contains(u64::MAX, u64::MAX - 5));
+    assert!(!tolerance.contains(u64::MAX, u64::MAX - 6));
+    assert!(ViewTolerance::new(0, u64::MAX).contains(u64::MAX, 1));
+
+    // An empty tolerance only includes the current view
+    let exact = ViewTolerance::new(0, 0);
+    assert!(exact.contains(7, 7));
+    assert!(!exact.contains(6, 7));
+    assert!(!exact.contains(8, 7));
+}
+
+// View sync messages are accepted however far ahead of us they are, since view sync is how we
+// catch up to the network, while other votes are held to the replay window
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_exempt_from_future_window() {
+    hotshot::helpers::initialize_logging();
+
+    let (private_key, public_key) = key_pair_for_id::<TestTypes>(1);
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let config = ReplayProtectionConfig::default();
+    let cur_view = 10;
+    let far_view = ViewNumber::new(cur_view + REPLAY_WINDOW_FUTURE_VIEWS + 1);
+    let epoch = EpochNumber::new(0);
+
+    let view_sync_vote = ViewSyncFinalizeVote2::<TestTypes>::create_signed_vote(
+        ViewSyncFinalizeData2 {
+            relay: 0,
+            round: far_view,
+            epoch,
+        },
+        far_view,
+        &public_key,
+        &private_key,
+        &upgrade_lock,
+    )
+    .await
+    .unwrap();
+    let view_sync = MessageKind::<TestTypes>::Consensus(SequencingMessage::General(
+        GeneralConsensusMessage::ViewSyncFinalizeVote2(view_sync_vote),
+    ));
+    let tolerance = config.tolerance(&view_sync).unwrap();
+    assert!(tolerance.contains(*far_view, cur_view));
+    assert!(tolerance.contains(u64::MAX, cur_view));
+    // Stale view sync messages are still rejected
+    assert!(!tolerance.contains(0, REPLAY_WINDOW_PAST_VIEWS + 1));
+
+    let timeout_vote = TimeoutVote2::<TestTypes>::create_signed_vote(
+        TimeoutData2 {
+            view: far_view,
+            epoch,
+        },
+        far_view,
+        &public_key,
+        &private_key,
+        &upgrade_lock,
+    )
+    .await
+    .unwrap();
+    let timeout = MessageKind::<TestTypes>::Consensus(SequencingMessage::General(
+        GeneralConsensusMessage::TimeoutVote2(timeout_vote),
+    ));
+    let tolerance = config.tolerance(&timeout).unwrap();
+    assert!(!tolerance.contains(*far_view, cur_view));
+    assert!(tolerance.contains(*far_view - 1, cur_view));
+}
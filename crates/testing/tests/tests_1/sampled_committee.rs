@@ -0,0 +1,315 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use bitvec::bitvec;
+use committable::{Commitment, Committable};
+use hotshot::types::SignatureKey;
+use hotshot_example_types::node_types::{TestTypesSampledCommittee, TestVersions};
+use hotshot_testing::helpers::key_pair_for_id;
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    drb::{sampling_beacon, INITIAL_DRB_RESULT},
+    message::UpgradeLock,
+    simple_certificate::QuorumCertificate2,
+    simple_vote::{QuorumData2, QuorumVote2, VersionedVoteData},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::StakeTableEntryType,
+    },
+    vote::{Certificate, Vote},
+    ValidatorConfig,
+};
+use primitive_types::U256;
+
+type TYPES = TestTypesSampledCommittee;
+type Entry = <<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry;
+
+/// Number of members sampled for each view
+const SAMPLE_SIZE: usize = 7;
+
+/// A sampled committee over nodes `0..stakes.len()`, the i-th node staking `stakes[i]`
+fn membership(stakes: &[u64]) -> <TYPES as NodeType>::Membership {
+    let peers = stakes
+        .iter()
+        .zip(0..)
+        .map(|(stake, node_id)| {
+            ValidatorConfig::<<TYPES as NodeType>::SignatureKey>::generated_from_seed_indexed(
+                [0u8; 32], node_id, *stake, true,
+            )
+            .public_config()
+        })
+        .collect::<Vec<_>>();
+
+    <TYPES as NodeType>::Membership::new(peers.clone(), peers)
+}
+
+/// The public keys of the members sampled for `view`
+fn sampled_keys(
+    membership: &<TYPES as NodeType>::Membership,
+    view: u64,
+) -> Vec<<TYPES as NodeType>::SignatureKey> {
+    membership
+        .sampled_stake_table(ViewNumber::new(view), EpochNumber::new(0))
+        .iter()
+        .map(<<TYPES as NodeType>::SignatureKey as SignatureKey>::public_key)
+        .collect()
+}
+
+// Without a beacon the sample is the full committee, since a predictable sample could be targeted
+#[cfg(test)]
+#[test]
+fn test_sampled_committee_without_beacon() {
+    hotshot::helpers::initialize_logging();
+
+    let membership = membership(&[1; 20]);
+    let epoch = EpochNumber::new(0);
+    for view in 1..10 {
+        let view = ViewNumber::new(view);
+        assert_eq!(
+            membership.sampled_stake_table(view, epoch),
+            membership.stake_table(epoch)
+        );
+        assert_eq!(membership.sampled_total_nodes(view, epoch), 20);
+        assert_eq!(
+            membership.sampled_success_threshold(view, epoch),
+            membership.success_threshold(epoch)
+        );
+    }
+}
+
+// With a beacon, each view samples a fixed size committee determined by the beacon, view and epoch
+#[cfg(test)]
+#[test]
+fn test_sampled_committee_with_beacon() {
+    hotshot::helpers::initialize_logging();
+
+    let mut membership = membership(&[1; 20]);
+    membership.set_sampling_beacon(EpochNumber::new(0), [1; 32]);
+    let mut same_beacon = self::membership(&[1; 20]);
+    same_beacon.set_sampling_beacon(EpochNumber::new(0), [1; 32]);
+    let mut other_beacon = self::membership(&[1; 20]);
+    other_beacon.set_sampling_beacon(EpochNumber::new(0), [2; 32]);
+
+    let epoch = EpochNumber::new(0);
+    let samples = (1..20)
+        .map(|view| sampled_keys(&membership, view))
+        .collect::<Vec<_>>();
+    for (view, sample) in (1..).zip(&samples) {
+        assert_eq!(sample.len(), SAMPLE_SIZE);
+        assert_eq!(
+            membership.sampled_total_nodes(ViewNumber::new(view), epoch),
+            SAMPLE_SIZE
+        );
+        // Repeated and independent lookups agree
+        assert_eq!(&sampled_keys(&membership, view), sample);
+        assert_eq!(&sampled_keys(&same_beacon, view), sample);
+    }
+    assert!(samples.iter().any(|sample| sample != &samples[0]));
+    assert!((1..)
+        .zip(&samples)
+        .any(|(view, sample)| &sampled_keys(&other_beacon, view) != sample));
+
+    // The per-key lookups agree with the sampled stake table
+    for view in 1..20 {
+        let view_number = ViewNumber::new(view);
+        let sample = sampled_keys(&membership, view);
+        for node_id in 0..20 {
+            let key = key_pair_for_id::<TYPES>(node_id).1;
+            let sampled = sample.contains(&key);
+            assert_eq!(
+                membership.has_sampled_stake(&key, view_number, epoch),
+                sampled
+            );
+            assert_eq!(
+                membership.sampled_stake(&key, view_number, epoch).is_some(),
+                sampled
+            );
+        }
+    }
+}
+
+// A beacon applies from the epoch it is set for until the next one, so each epoch's samples are
+// drawn with the randomness decided for it, and epochs before the first beacon use the full
+// committee
+#[cfg(test)]
+#[test]
+fn test_sampled_committee_beacon_per_epoch() {
+    hotshot::helpers::initialize_logging();
+
+    let mut membership = membership(&[1; 20]);
+    membership.set_sampling_beacon(EpochNumber::new(2), [1; 32]);
+    membership.set_sampling_beacon(EpochNumber::new(4), [2; 32]);
+    let mut first = self::membership(&[1; 20]);
+    first.set_sampling_beacon(EpochNumber::new(0), [1; 32]);
+    let mut second = self::membership(&[1; 20]);
+    second.set_sampling_beacon(EpochNumber::new(0), [2; 32]);
+
+    let sample = |membership: &<TYPES as NodeType>::Membership, epoch, view| {
+        membership.sampled_stake_table(ViewNumber::new(view), EpochNumber::new(epoch))
+    };
+    for view in 1..10 {
+        assert_eq!(
+            sample(&membership, 1, view),
+            membership.stake_table(EpochNumber::new(1))
+        );
+        for epoch in 2..4 {
+            assert_eq!(
+                sample(&membership, epoch, view),
+                sample(&first, epoch, view)
+            );
+        }
+        for epoch in 4..6 {
+            assert_eq!(
+                sample(&membership, epoch, view),
+                sample(&second, epoch, view)
+            );
+        }
+    }
+    assert_eq!(membership.sampling_beacon(EpochNumber::new(1)), None);
+    assert_eq!(
+        membership.sampling_beacon(EpochNumber::new(3)),
+        Some([1; 32])
+    );
+    assert_eq!(
+        membership.sampling_beacon(EpochNumber::new(7)),
+        Some([2; 32])
+    );
+}
+
+// Decided DRB results give beacons, except the initial one, which everyone knows in advance
+#[cfg(test)]
+#[test]
+fn test_sampling_beacon_from_drb_result() {
+    hotshot::helpers::initialize_logging();
+
+    assert_eq!(sampling_beacon(&INITIAL_DRB_RESULT), None);
+    let beacon = sampling_beacon(&[3; 32]).unwrap();
+    assert_ne!(beacon, [3; 32]);
+    assert_eq!(sampling_beacon(&[3; 32]), Some(beacon));
+    assert_ne!(sampling_beacon(&[4; 32]), Some(beacon));
+}
+
+// Members are sampled in proportion to their stake, so light members cannot form a quorum alone
+#[cfg(test)]
+#[test]
+fn test_sampled_committee_weighted_by_stake() {
+    hotshot::helpers::initialize_logging();
+
+    let mut stakes = vec![1; 20];
+    stakes[3] = 1000;
+    stakes[11] = 1000;
+    let mut membership = membership(&stakes);
+    membership.set_sampling_beacon(EpochNumber::new(0), [7; 32]);
+
+    let epoch = EpochNumber::new(0);
+    let heavy = [
+        key_pair_for_id::<TYPES>(3).1,
+        key_pair_for_id::<TYPES>(11).1,
+    ];
+    for view in 1..100 {
+        let view_number = ViewNumber::new(view);
+        let table = membership.sampled_stake_table(view_number, epoch);
+        let light_stake = table
+            .iter()
+            .filter(|entry| !heavy.contains(&entry.public_key()))
+            .map(StakeTableEntryType::stake)
+            .fold(U256::zero(), |total, stake| total + stake);
+
+        for key in &heavy {
+            assert!(membership.has_sampled_stake(key, view_number, epoch));
+        }
+        assert!(
+            light_stake
+                < U256::from(u64::from(
+                    membership.sampled_success_threshold(view_number, epoch)
+                ))
+        );
+    }
+}
+
+/// A quorum certificate for `view` signed by every member sampled for it
+async fn sampled_qc(
+    membership: &<TYPES as NodeType>::Membership,
+    view: u64,
+    upgrade_lock: &UpgradeLock<TYPES, TestVersions>,
+) -> QuorumCertificate2<TYPES> {
+    let view_number = ViewNumber::new(view);
+    let epoch = EpochNumber::new(0);
+    let data = QuorumData2 {
+        leaf_commit: Commitment::from_raw([3; 32]),
+        epoch,
+    };
+    let table = membership.sampled_stake_table(view_number, epoch);
+    let mut signatures = Vec::new();
+    for key in sampled_keys(membership, view) {
+        let node_id = (0..)
+            .find(|node_id| key_pair_for_id::<TYPES>(*node_id).1 == key)
+            .unwrap();
+        let (private_key, public_key) = key_pair_for_id::<TYPES>(node_id);
+        let vote = QuorumVote2::<TYPES>::create_signed_vote(
+            data.clone(),
+            view_number,
+            &public_key,
+            &private_key,
+            upgrade_lock,
+        )
+        .await
+        .unwrap();
+        signatures.push(vote.signature());
+    }
+    let vote_commitment = VersionedVoteData::new(data.clone(), view_number, upgrade_lock)
+        .await
+        .unwrap()
+        .commit();
+    let public_parameter = <TYPES as NodeType>::SignatureKey::public_parameter(
+        table.clone(),
+        U256::from(u64::from(
+            membership.sampled_success_threshold(view_number, epoch),
+        )),
+    );
+    let signature = <TYPES as NodeType>::SignatureKey::assemble(
+        &public_parameter,
+        bitvec![1; table.len()].as_bitslice(),
+        &signatures,
+    );
+
+    QuorumCertificate2::create_signed_certificate(vote_commitment, data, signature, view_number)
+}
+
+/// Whether `qc` is valid against the members sampled for `view`
+async fn valid_for_view(
+    qc: &QuorumCertificate2<TYPES>,
+    membership: &<TYPES as NodeType>::Membership,
+    view: u64,
+    upgrade_lock: &UpgradeLock<TYPES, TestVersions>,
+) -> bool {
+    let view_number = ViewNumber::new(view);
+    let epoch = EpochNumber::new(0);
+    let table: Vec<Entry> = membership.sampled_stake_table(view_number, epoch);
+    let threshold: U256 = membership.sampled_success_threshold(view_number, epoch);
+
+    qc.is_valid_cert(table, threshold, upgrade_lock).await
+}
+
+// A quorum certificate signed by a view's sample is valid against that sample only
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sampled_committee_quorum_certificate() {
+    hotshot::helpers::initialize_logging();
+
+    let mut membership = membership(&[1; 20]);
+    membership.set_sampling_beacon(EpochNumber::new(0), [5; 32]);
+    let upgrade_lock = UpgradeLock::<TYPES, TestVersions>::new();
+
+    let qc = sampled_qc(&membership, 4, &upgrade_lock).await;
+    assert!(valid_for_view(&qc, &membership, 4, &upgrade_lock).await);
+
+    let other_view = (5..)
+        .find(|view| sampled_keys(&membership, *view) != sampled_keys(&membership, 4))
+        .unwrap();
+    assert!(!valid_for_view(&qc, &membership, other_view, &upgrade_lock).await);
+}
@@ -0,0 +1,77 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    num::NonZeroU64,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hotshot_task_impls::send_scheduler::{SendClass, SendScheduler};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_proposal_sent_before_background() {
+    hotshot::helpers::initialize_logging();
+
+    // 1000 bytes per second, so each 100 byte send holds the link for 100ms
+    let scheduler = Arc::new(SendScheduler::new(NonZeroU64::new(1000), Arc::default()));
+    let sent = Arc::new(Mutex::new(Vec::new()));
+
+    let send = |class: SendClass, delay: Duration| {
+        let scheduler = Arc::clone(&scheduler);
+        let sent = Arc::clone(&sent);
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            scheduler
+                .send(class, 100, async { sent.lock().unwrap().push(class) })
+                .await;
+        })
+    };
+
+    // The first background send takes the link, the second waits behind it until the proposal
+    // queued after it has been sent
+    let handles = [
+        send(SendClass::Background, Duration::ZERO),
+        send(SendClass::Background, Duration::from_millis(20)),
+        send(SendClass::Proposal, Duration::from_millis(40)),
+    ];
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    assert_eq!(
+        *sent.lock().unwrap(),
+        [
+            SendClass::Background,
+            SendClass::Proposal,
+            SendClass::Background
+        ]
+    );
+
+    let stats = scheduler.stats();
+    assert_eq!(stats[&SendClass::Background].sends, 2);
+    assert_eq!(stats[&SendClass::Background].bytes, 200);
+    assert_eq!(stats[&SendClass::Proposal].sends, 1);
+    assert!(stats[&SendClass::Background].max_time >= stats[&SendClass::Proposal].max_time);
+    assert!(!stats.contains_key(&SendClass::Control));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unpaced_sends_immediate() {
+    hotshot::helpers::initialize_logging();
+
+    let scheduler = SendScheduler::default();
+    assert!(scheduler.upload_budget().is_none());
+
+    let start = Instant::now();
+    for class in SendClass::ALL {
+        assert_eq!(scheduler.send(class, 1 << 30, async { 42 }).await, 42);
+    }
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(scheduler.stats().len(), SendClass::ALL.len());
+}
@@ -0,0 +1,145 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use async_broadcast::broadcast;
+use futures::StreamExt;
+use hotshot::types::SystemContextHandle;
+use hotshot_example_types::{
+    node_types::{EpochsUpgradeTestVersions, MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_task_impls::shadow_consensus::ShadowConsensusTaskState;
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    consensus::OuterConsensus,
+    data::{Leaf2, QuorumProposal2, ViewNumber},
+    event::{DecideOutcome, EventType},
+    traits::{
+        consensus_api::ConsensusApi,
+        node_implementation::{ConsensusTime, Versions},
+    },
+};
+use vbs::version::StaticVersionType;
+
+/// Store the proposals of views 1 to 4, which extend each other, the way the quorum vote task
+/// does before it votes, and return them
+async fn store_proposals<V: Versions>(
+    handle: &SystemContextHandle<TestTypes, MemoryImpl, V>,
+) -> Vec<QuorumProposal2<TestTypes>> {
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+    let mut proposals = Vec::new();
+    for view in (&mut generator).take(4).collect::<Vec<_>>().await {
+        consensus_writer
+            .update_leaf(
+                Leaf2::from_quorum_proposal(&view.quorum_proposal.data),
+                Arc::new(TestValidatedState::default()),
+                None,
+            )
+            .unwrap();
+        proposals.push(view.quorum_proposal.data);
+    }
+
+    proposals
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shadow_consensus_reports_divergent_decisions() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, EpochsUpgradeTestVersions>(1)
+        .await
+        .0;
+    let proposals = store_proposals(&handle).await;
+
+    let (output_sender, mut output_receiver) = broadcast(1024);
+    let mut state = ShadowConsensusTaskState::<TestTypes, EpochsUpgradeTestVersions> {
+        consensus: OuterConsensus::new(handle.hotshot.consensus()),
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        public_key: handle.public_key(),
+        output_event_stream: output_sender,
+        divergences: 0,
+        id: handle.hotshot.id,
+    };
+    state.rehearse(&proposals[3]).await.unwrap();
+    assert_eq!(state.divergences, 1);
+
+    // The three-chain rule decides view 1, while the two-chain rule of the upgrade already
+    // decides view 2
+    let event = output_receiver.recv_direct().await.unwrap();
+    assert_eq!(event.view_number, ViewNumber::new(4));
+    let EventType::ShadowDivergence {
+        live_version,
+        shadow_version,
+        live,
+        shadow,
+    } = event.event
+    else {
+        panic!("Expected a shadow divergence, got {:?}", event.event);
+    };
+    assert_eq!(
+        live_version,
+        <EpochsUpgradeTestVersions as Versions>::Base::VERSION
+    );
+    assert_eq!(
+        shadow_version,
+        <EpochsUpgradeTestVersions as Versions>::Upgrade::VERSION
+    );
+    assert_eq!(
+        live,
+        DecideOutcome {
+            locked_view: Some(ViewNumber::new(2)),
+            decided_views: vec![ViewNumber::new(1)],
+        }
+    );
+    assert_eq!(
+        shadow,
+        DecideOutcome {
+            locked_view: Some(ViewNumber::new(3)),
+            decided_views: vec![ViewNumber::new(2), ViewNumber::new(1)],
+        }
+    );
+
+    // The rehearsal leaves the live state alone
+    let consensus = handle.hotshot.consensus();
+    assert_eq!(
+        consensus.read().await.last_decided_view(),
+        ViewNumber::genesis()
+    );
+    assert_eq!(consensus.read().await.locked_view(), ViewNumber::genesis());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shadow_consensus_quiet_when_rules_agree() {
+    hotshot::helpers::initialize_logging();
+
+    // Neither the version we run nor the one we upgrade to has the two-chain rule
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let proposals = store_proposals(&handle).await;
+
+    let (output_sender, output_receiver) = broadcast(1024);
+    let mut state = ShadowConsensusTaskState::<TestTypes, TestVersions> {
+        consensus: OuterConsensus::new(handle.hotshot.consensus()),
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        public_key: handle.public_key(),
+        output_event_stream: output_sender,
+        divergences: 0,
+        id: handle.hotshot.id,
+    };
+    for proposal in &proposals {
+        state.rehearse(proposal).await.unwrap();
+    }
+
+    assert_eq!(state.divergences, 0);
+    assert!(output_receiver.is_empty());
+}
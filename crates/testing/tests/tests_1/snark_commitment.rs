@@ -0,0 +1,69 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    snark_commitment::{FinalityAccumulator, RescueScheme},
+    traits::commitment_scheme::CommitmentScheme,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_finality_accumulator() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let leaves = (&mut generator)
+        .take(4)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+
+    // Rescue commitments are deterministic and distinguish leaves
+    assert_eq!(
+        RescueScheme::commit(&leaves[0]),
+        RescueScheme::commit(&leaves[0])
+    );
+    assert_ne!(
+        RescueScheme::commit(&leaves[0]),
+        RescueScheme::commit(&leaves[1])
+    );
+
+    let mut accumulator = FinalityAccumulator::new();
+    assert!(accumulator.is_empty());
+    for leaf in &leaves {
+        accumulator.append_leaf(leaf);
+    }
+    assert_eq!(accumulator.len(), 4);
+
+    // The same leaves in the same order always give the same root
+    let mut replayed = FinalityAccumulator::new();
+    for leaf in &leaves {
+        replayed.append_leaf(leaf);
+    }
+    assert_eq!(replayed.root(), accumulator.root());
+
+    // Any other order, or a missing leaf, does not
+    let mut reordered = FinalityAccumulator::new();
+    for leaf in leaves.iter().rev() {
+        reordered.append_leaf(leaf);
+    }
+    assert_ne!(reordered.root(), accumulator.root());
+
+    let mut truncated = FinalityAccumulator::new();
+    for leaf in &leaves[..3] {
+        truncated.append_leaf(leaf);
+    }
+    assert_ne!(truncated.root(), accumulator.root());
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::{Leaf2, ViewNumber},
+    traits::{node_implementation::ConsensusTime, states::ValidatedState},
+    utils::Terminator,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_decided_leaves_are_served_cached_states() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let public_key = handle.public_key();
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let leaves: Vec<Leaf2<TestTypes>> = (&mut generator)
+        .take(4)
+        .map(|view| view.leaf)
+        .collect()
+        .await;
+
+    // The state map holds a state derived from the header for every view
+    let header_state = Arc::new(TestValidatedState::default());
+    let (executed_state, delta) = TestValidatedState::genesis(&TestInstanceState::default());
+    let executed_state = Arc::new(executed_state);
+    let delta = Some(Arc::new(delta));
+
+    let consensus = handle.hotshot.consensus();
+    let mut consensus = consensus.write().await;
+    for leaf in &leaves {
+        consensus
+            .update_leaf(leaf.clone(), Arc::clone(&header_state), None)
+            .unwrap();
+    }
+
+    // Nothing is cached unless speculative execution is enabled
+    consensus.cache_speculative_state(&leaves[1], Arc::clone(&executed_state), delta.clone());
+    assert!(consensus.speculative_state(&leaves[1].commit()).is_none());
+
+    consensus.enable_speculative_execution();
+    assert!(consensus.speculative_execution());
+    consensus.cache_speculative_state(&leaves[1], Arc::clone(&executed_state), delta.clone());
+
+    // The leaf we voted for is served the state computed for it, others the state map's
+    let info = consensus.parent_leaf_info(&leaves[2], &public_key).unwrap();
+    assert_eq!(info.leaf, leaves[1]);
+    assert!(Arc::ptr_eq(&info.state, &executed_state));
+    assert!(info.delta.is_some());
+    let info = consensus.parent_leaf_info(&leaves[1], &public_key).unwrap();
+    assert!(Arc::ptr_eq(&info.state, &header_state));
+    assert!(info.delta.is_none());
+
+    // Leaf chain traversals see the cached state too
+    let mut visited = Vec::new();
+    consensus
+        .visit_leaf_ancestors(
+            leaves[3].view_number(),
+            Terminator::Inclusive(leaves[0].view_number()),
+            true,
+            |leaf, state, _| {
+                visited.push((leaf.view_number(), Arc::ptr_eq(&state, &executed_state)));
+                true
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        visited,
+        leaves
+            .iter()
+            .rev()
+            .map(|leaf| (leaf.view_number(), leaf == &leaves[1]))
+            .collect::<Vec<_>>()
+    );
+
+    // Cached states are dropped along with their views once decided
+    consensus.collect_garbage(ViewNumber::genesis(), leaves[3].view_number());
+    assert!(consensus.speculative_state(&leaves[1].commit()).is_none());
+}
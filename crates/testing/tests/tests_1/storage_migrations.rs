@@ -0,0 +1,172 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    storage_types::TestStorage,
+};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    migration::{MigrationMode, MigrationStep, Migrations},
+    traits::storage::Storage,
+};
+
+/// A migration step which records that it ran, and fails while `fail` is set
+struct TestStep {
+    /// The version of the step
+    version: u32,
+    /// Versions of the steps applied so far, in the order they were applied
+    log: Arc<Mutex<Vec<u32>>>,
+    /// Whether the step fails
+    fail: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl MigrationStep<TestTypes, TestStorage<TestTypes>> for TestStep {
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn description(&self) -> &str {
+        "test step"
+    }
+
+    async fn apply(&self, _storage: &TestStorage<TestTypes>) -> Result<()> {
+        ensure!(!self.fail.load(Ordering::SeqCst), "step failed");
+        self.log.lock().unwrap().push(self.version);
+        Ok(())
+    }
+}
+
+/// Migrations with the steps `versions`, the log of the steps applied and a switch to make
+/// `failing` fail
+fn migrations(
+    versions: &[u32],
+    failing: u32,
+) -> (
+    Migrations<TestTypes, TestStorage<TestTypes>>,
+    Arc<Mutex<Vec<u32>>>,
+    Arc<AtomicBool>,
+) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let fail = Arc::new(AtomicBool::new(false));
+    let migrations = versions
+        .iter()
+        .fold(Migrations::default(), |migrations, version| {
+            migrations.with_step(TestStep {
+                version: *version,
+                log: Arc::clone(&log),
+                fail: if *version == failing {
+                    Arc::clone(&fail)
+                } else {
+                    Arc::new(AtomicBool::new(false))
+                },
+            })
+        });
+
+    (migrations, log, fail)
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_consensus_migrations_applied_once_at_startup() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let storage = handle.storage().read().await.clone();
+
+    let migrations = Migrations::<TestTypes, TestStorage<TestTypes>>::consensus();
+    let versions: BTreeSet<_> = migrations.versions().into_iter().collect();
+    assert_eq!(storage.load_applied_migrations().await.unwrap(), versions);
+
+    // Nothing is left to apply when the node restarts
+    let report = migrations
+        .run(&storage, MigrationMode::Apply)
+        .await
+        .unwrap();
+    assert_eq!(report.previously_applied, versions);
+    assert!(report.applied.is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_migrations_applied_in_order_and_resumed() {
+    hotshot::helpers::initialize_logging();
+
+    let storage = TestStorage::<TestTypes>::default();
+    let (migrations, log, fail) = migrations(&[1, 2, 5], 5);
+
+    // A dry run reports the pending steps without applying them
+    let report = migrations
+        .run(&storage, MigrationMode::DryRun)
+        .await
+        .unwrap();
+    assert_eq!(report.applied, vec![1, 2, 5]);
+    assert!(log.lock().unwrap().is_empty());
+    assert!(storage.load_applied_migrations().await.unwrap().is_empty());
+
+    // A failing step stops the run, leaving the steps before it applied
+    fail.store(true, Ordering::SeqCst);
+    assert!(migrations
+        .run(&storage, MigrationMode::Apply)
+        .await
+        .is_err());
+    assert_eq!(*log.lock().unwrap(), vec![1, 2]);
+    assert_eq!(
+        storage.load_applied_migrations().await.unwrap(),
+        BTreeSet::from([1, 2])
+    );
+
+    // And the next run picks up from the failed step
+    fail.store(false, Ordering::SeqCst);
+    let report = migrations
+        .run(&storage, MigrationMode::Apply)
+        .await
+        .unwrap();
+    assert_eq!(report.previously_applied, BTreeSet::from([1, 2]));
+    assert_eq!(report.applied, vec![5]);
+    assert_eq!(*log.lock().unwrap(), vec![1, 2, 5]);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_migration_added_out_of_order_rejected() {
+    hotshot::helpers::initialize_logging();
+
+    let storage = TestStorage::<TestTypes>::default();
+    storage.record_migration(3).await.unwrap();
+
+    // Step 2 was added after step 3 had already been applied
+    let (migrations, log, _) = migrations(&[2, 3], 0);
+    assert!(migrations
+        .run(&storage, MigrationMode::DryRun)
+        .await
+        .is_err());
+    assert!(migrations
+        .run(&storage, MigrationMode::Apply)
+        .await
+        .is_err());
+    assert!(log.lock().unwrap().is_empty());
+}
+
+#[cfg(test)]
+#[test]
+#[should_panic(expected = "must come before")]
+fn test_migration_steps_must_be_ordered() {
+    let _ = migrations(&[1, 3, 2], 0);
+}
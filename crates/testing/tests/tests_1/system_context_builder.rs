@@ -0,0 +1,134 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{num::NonZeroUsize, sync::Arc};
+
+use async_lock::RwLock;
+use hotshot::{
+    builder::{Keys, SystemContextBuilder},
+    traits::{election::static_committee::StaticCommittee, NodeImplementation},
+    HotShotError, HotShotInitializer, SystemContext,
+};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestInstanceState,
+};
+use hotshot_testing::{test_builder::TestDescription, test_launcher::TestLauncher};
+use hotshot_types::{
+    signature_key::BLSPubKey,
+    traits::{election::Membership, node_implementation::ConsensusTime},
+    HotShotConfig, ValidatorConfig,
+};
+
+/// A builder given the components every node must be given, for node `node_id` of the default
+/// test network
+async fn builder(
+    launcher: &TestLauncher<TestTypes, MemoryImpl, TestVersions>,
+    node_id: u64,
+) -> SystemContextBuilder<
+    TestTypes,
+    MemoryImpl,
+    TestVersions,
+    Keys<TestTypes>,
+    HotShotConfig<BLSPubKey>,
+    Arc<<MemoryImpl as NodeImplementation<TestTypes>>::Network>,
+    HotShotInitializer<TestTypes>,
+    <MemoryImpl as NodeImplementation<TestTypes>>::Storage,
+> {
+    let validator_config =
+        ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, true);
+    let initializer =
+        HotShotInitializer::<TestTypes>::from_genesis::<TestVersions>(TestInstanceState::default())
+            .await
+            .unwrap();
+
+    SystemContext::<TestTypes, MemoryImpl, TestVersions>::builder()
+        .node_id(node_id)
+        .keys(validator_config.public_key, validator_config.private_key)
+        .config(launcher.resource_generator.config.clone())
+        .network((launcher.resource_generator.channel_generator)(node_id).await)
+        .initializer(initializer)
+        .storage((launcher.resource_generator.storage)(node_id))
+}
+
+fn launcher() -> TestLauncher<TestTypes, MemoryImpl, TestVersions> {
+    TestDescription::<TestTypes, MemoryImpl, TestVersions>::default().gen_launcher(0)
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_builder_with_defaults() {
+    hotshot::helpers::initialize_logging();
+
+    let launcher = launcher();
+    let hotshot = builder(&launcher, 0).await.build().await.unwrap();
+
+    // The memberships are derived from the config
+    let config = &launcher.resource_generator.config;
+    assert_eq!(
+        hotshot
+            .memberships
+            .read()
+            .await
+            .total_nodes(ConsensusTime::genesis()),
+        config.num_nodes_with_stake.get()
+    );
+    assert_eq!(
+        hotshot.marketplace_config.fallback_builder_url,
+        *config.builder_urls.first()
+    );
+
+    let (handle, _, _) = builder(&launcher, 1)
+        .await
+        .marketplace_config((launcher.resource_generator.marketplace_config)(1))
+        .init()
+        .await
+        .unwrap();
+    assert_eq!(handle.hotshot.id, 1);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_builder_rejects_inconsistent_components() {
+    hotshot::helpers::initialize_logging();
+
+    let launcher = launcher();
+
+    // Keys which do not pair
+    let other_key = ValidatorConfig::generated_from_seed_indexed([0u8; 32], 1, 1, true).private_key;
+    let validator_config = ValidatorConfig::generated_from_seed_indexed([0u8; 32], 0, 1, true);
+    let result = builder(&launcher, 0)
+        .await
+        .keys(validator_config.public_key, other_key)
+        .build()
+        .await;
+    assert!(matches!(result, Err(HotShotError::InvalidConfig(_))));
+
+    // A config which expects more nodes than it lists
+    let mut config = launcher.resource_generator.config.clone();
+    config.num_nodes_with_stake =
+        NonZeroUsize::new(config.known_nodes_with_stake.len() + 1).unwrap();
+    let result = builder(&launcher, 0).await.config(config).build().await;
+    assert!(matches!(result, Err(HotShotError::InvalidConfig(_))));
+
+    // Memberships with a node the config does not know
+    let config = &launcher.resource_generator.config;
+    let mut nodes = config.known_nodes_with_stake.clone();
+    nodes.push(
+        ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([1u8; 32], 0, 1, false)
+            .public_config(),
+    );
+    let memberships = Arc::new(RwLock::new(StaticCommittee::<TestTypes>::new(
+        nodes,
+        config.da_committee(),
+    )));
+    let result = builder(&launcher, 0)
+        .await
+        .memberships(memberships)
+        .build()
+        .await;
+    assert!(matches!(result, Err(HotShotError::InvalidConfig(_))));
+}
@@ -10,7 +10,7 @@ use hotshot_example_types::{
     node_types::{
         CombinedImpl, EpochsTestVersions, Libp2pImpl, MemoryImpl, PushCdnImpl,
         TestConsecutiveLeaderTypes, TestTwoStakeTablesTypes, TestTypes, TestTypesRandomizedLeader,
-        TestVersions,
+        TestTypesSampledCommittee, TestVersions,
     },
     testable_delay::{DelayConfig, DelayOptions, DelaySettings, SupportedTraitTypesForAsyncDelay},
 };
@@ -22,11 +22,12 @@ use hotshot_testing::{
     test_builder::TestDescription,
     view_sync_task::ViewSyncTaskDescription,
 };
+use hotshot_types::DataAvailabilityMode;
 
 cross_tests!(
     TestName: test_success,
     Impls: [MemoryImpl, Libp2pImpl, PushCdnImpl],
-    Types: [TestTypes, TestTypesRandomizedLeader],
+    Types: [TestTypes, TestTypesRandomizedLeader, TestTypesSampledCommittee],
     Versions: [TestVersions],
     Ignore: false,
     Metadata: {
@@ -42,6 +43,26 @@ cross_tests!(
     },
 );
 
+cross_tests!(
+    TestName: test_success_full_replication,
+    Impls: [MemoryImpl, Libp2pImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            data_availability: DataAvailabilityMode::FullReplication,
+            ..TestDescription::default()
+        }
+    },
+);
+
 cross_tests!(
     TestName: test_success_with_epochs,
     Impls: [Libp2pImpl, PushCdnImpl, CombinedImpl],
@@ -323,3 +344,62 @@ cross_tests!(
         metadata
     },
 );
+
+// With the small network thresholds, a 3 node network keeps deciding while one node is down
+cross_tests!(
+    TestName: test_small_network_with_failure,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let num_nodes_with_stake = 3;
+        let mut metadata = TestDescription {
+            num_nodes_with_stake,
+            start_nodes: num_nodes_with_stake,
+            num_bootstrap_nodes: num_nodes_with_stake,
+            da_staked_committee_size: num_nodes_with_stake,
+            small_network: true,
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            view_sync_properties: ViewSyncTaskDescription::Threshold(0, num_nodes_with_stake),
+            ..TestDescription::default()
+        };
+        let dead_nodes = vec![
+            ChangeNode {
+                idx: 2,
+                updown: NodeAction::Down,
+            },
+        ];
+        metadata.spinning_properties = SpinningTaskDescription {
+            node_changes: vec![(5, dead_nodes)]
+        };
+        // every third view is led by the node that is down
+        metadata.overall_safety_properties.num_failed_views = 15;
+        metadata.overall_safety_properties.num_successful_views = 10;
+        metadata
+    },
+);
+
+// Decided leaves are served the state cached when voting for them
+cross_tests!(
+    TestName: test_success_with_speculative_execution,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            speculative_execution: true,
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            ..TestDescription::default()
+        }
+    },
+);
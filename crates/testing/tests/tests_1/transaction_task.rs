@@ -87,3 +87,63 @@ async fn test_transaction_task_leader_two_views_in_a_row() {
         .await;
     run_harness(input, output, transaction_state, false).await;
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_task_fetches_block_before_timeout_certificate() {
+    hotshot::helpers::initialize_logging();
+
+    // Build the API for node 2, which leads view 4.
+    let node_id = 2;
+    let handle =
+        build_system_handle::<TestConsecutiveLeaderTypes, MemoryImpl, TestVersions>(node_id)
+            .await
+            .0;
+    let total_nodes = handle
+        .hotshot
+        .memberships
+        .read()
+        .await
+        .total_nodes(EpochNumber::new(0));
+
+    let mut input = Vec::new();
+    let mut output = Vec::new();
+
+    // The timeout votes for view 3 are close to a certificate, so we fetch the block for view 4,
+    // but only send it on once view 4 starts
+    let current_view = ViewNumber::new(4);
+    input.push(HotShotEvent::TimeoutVoteProgress(
+        ViewNumber::new(3),
+        EpochNumber::new(1),
+        70,
+    ));
+    input.push(HotShotEvent::ViewChange(current_view, EpochNumber::new(1)));
+    input.push(HotShotEvent::Shutdown);
+
+    let (_, precompute_data) = precompute_vid_commitment(&[], total_nodes);
+    output.push(HotShotEvent::BlockRecv(PackedBundle::new(
+        vec![].into(),
+        TestMetadata {
+            num_transactions: 0,
+        },
+        current_view,
+        EpochNumber::new(1),
+        vec1::vec1![
+            null_block::builder_fee::<TestConsecutiveLeaderTypes, TestVersions>(
+                total_nodes,
+                <TestVersions as Versions>::Base::VERSION,
+                *current_view,
+            )
+            .unwrap()
+        ],
+        Some(precompute_data),
+        None,
+    )));
+
+    let transaction_state =
+        TransactionTaskState::<TestConsecutiveLeaderTypes, MemoryImpl, TestVersions>::create_from(
+            &handle,
+        )
+        .await;
+    run_harness(input, output, transaction_state, false).await;
+}
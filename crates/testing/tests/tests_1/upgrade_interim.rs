@@ -0,0 +1,204 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot::{tasks::task_state::CreateTaskState, types::SystemContextHandle};
+use hotshot_example_types::{
+    block_types::{TestBlockPayload, TestMetadata, TestTransaction},
+    node_types::{MemoryImpl, TestConsecutiveLeaderTypes, TestVersions},
+};
+use hotshot_task_impls::{
+    events::HotShotEvent, harness::run_harness, helpers::ensure_null_block_while_upgrading,
+    transactions::TransactionTaskState,
+};
+use hotshot_testing::helpers::{build_cert, build_system_handle, da_payload_commitment};
+use hotshot_types::{
+    data::{null_block, EpochNumber, PackedBundle, ViewNumber},
+    simple_certificate::UpgradeCertificate,
+    simple_vote::{UpgradeProposalData, UpgradeVote},
+    traits::{
+        block_contents::precompute_vid_commitment,
+        consensus_api::ConsensusApi,
+        election::Membership,
+        node_implementation::{ConsensusTime, Versions},
+    },
+};
+use vbs::version::StaticVersionType;
+
+/// Decide an upgrade for `handle` whose old version ends in `old_version_last_view` and whose new
+/// version starts in `new_version_first_view`, leaving the views in between to the null block
+async fn decide_upgrade(
+    handle: &SystemContextHandle<TestConsecutiveLeaderTypes, MemoryImpl, TestVersions>,
+    old_version_last_view: u64,
+    new_version_first_view: u64,
+) {
+    let data = UpgradeProposalData {
+        old_version: <TestVersions as Versions>::Base::VERSION,
+        new_version: <TestVersions as Versions>::Upgrade::VERSION,
+        decide_by: ViewNumber::new(old_version_last_view),
+        new_version_hash: [0u8; 12].to_vec(),
+        old_version_last_view: ViewNumber::new(old_version_last_view),
+        new_version_first_view: ViewNumber::new(new_version_first_view),
+    };
+    let cert = build_cert::<
+        TestConsecutiveLeaderTypes,
+        TestVersions,
+        UpgradeProposalData<TestConsecutiveLeaderTypes>,
+        UpgradeVote<TestConsecutiveLeaderTypes>,
+        UpgradeCertificate<TestConsecutiveLeaderTypes>,
+    >(
+        data,
+        &handle.hotshot.memberships,
+        ViewNumber::new(1),
+        EpochNumber::new(1),
+        &handle.public_key(),
+        handle.private_key(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await;
+
+    *handle
+        .hotshot
+        .upgrade_lock
+        .decided_upgrade_certificate
+        .write()
+        .await = Some(cert);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_leader_proposes_null_block_between_versions() {
+    hotshot::helpers::initialize_logging();
+
+    // Node 2 leads views 4 and 5, which are both between versions
+    let node_id = 2;
+    let handle =
+        build_system_handle::<TestConsecutiveLeaderTypes, MemoryImpl, TestVersions>(node_id)
+            .await
+            .0;
+    decide_upgrade(&handle, 3, 6).await;
+    let total_nodes = handle
+        .hotshot
+        .memberships
+        .read()
+        .await
+        .total_nodes(EpochNumber::new(0));
+
+    // A block injected for our next view is held back until the new version starts
+    handle
+        .inject_block(
+            TestBlockPayload {
+                transactions: vec![TestTransaction::new(b"injected".to_vec())],
+            },
+            TestMetadata {
+                num_transactions: 1,
+            },
+        )
+        .await;
+
+    let current_view = ViewNumber::new(4);
+    let input = vec![
+        HotShotEvent::ViewChange(current_view, EpochNumber::new(1)),
+        HotShotEvent::ViewChange(current_view + 1, EpochNumber::new(1)),
+        HotShotEvent::Shutdown,
+    ];
+
+    let (_, precompute_data) = precompute_vid_commitment(&[], total_nodes);
+    let null_bundle = |view: ViewNumber| {
+        PackedBundle::new(
+            vec![].into(),
+            TestMetadata {
+                num_transactions: 0,
+            },
+            view,
+            EpochNumber::new(1),
+            vec1::vec1![
+                null_block::builder_fee::<TestConsecutiveLeaderTypes, TestVersions>(
+                    total_nodes,
+                    <TestVersions as Versions>::Base::VERSION,
+                    *view,
+                )
+                .unwrap()
+            ],
+            Some(precompute_data.clone()),
+            None,
+        )
+    };
+    let output = vec![
+        HotShotEvent::BlockRecv(null_bundle(current_view)),
+        HotShotEvent::BlockRecv(null_bundle(current_view + 1)),
+    ];
+
+    let transaction_state =
+        TransactionTaskState::<TestConsecutiveLeaderTypes, MemoryImpl, TestVersions>::create_from(
+            &handle,
+        )
+        .await;
+    run_harness(input, output, transaction_state, false).await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_only_null_block_allowed_between_versions() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestConsecutiveLeaderTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let membership = &handle.hotshot.memberships;
+    let epoch = EpochNumber::new(1);
+    let total_nodes = membership.read().await.total_nodes(epoch);
+    let null_commitment = null_block::commitment(total_nodes).unwrap();
+    let block_commitment = da_payload_commitment::<TestConsecutiveLeaderTypes>(
+        membership,
+        vec![TestTransaction::new(vec![1, 2, 3])],
+        epoch,
+    )
+    .await;
+
+    // Without a decided upgrade any block may be proposed
+    assert!(ensure_null_block_while_upgrading(
+        upgrade_lock,
+        ViewNumber::new(5),
+        block_commitment,
+        total_nodes
+    )
+    .await
+    .is_ok());
+
+    decide_upgrade(&handle, 4, 8).await;
+    for view in 5..8 {
+        let view = ViewNumber::new(view);
+        assert!(ensure_null_block_while_upgrading(
+            upgrade_lock,
+            view,
+            null_commitment,
+            total_nodes
+        )
+        .await
+        .is_ok());
+        assert!(ensure_null_block_while_upgrading(
+            upgrade_lock,
+            view,
+            block_commitment,
+            total_nodes
+        )
+        .await
+        .is_err());
+    }
+
+    // The last view of the old version and the views of the new one take any block
+    for view in [4, 8, 9] {
+        assert!(ensure_null_block_while_upgrading(
+            upgrade_lock,
+            ViewNumber::new(view),
+            block_commitment,
+            total_nodes
+        )
+        .await
+        .is_ok());
+    }
+}
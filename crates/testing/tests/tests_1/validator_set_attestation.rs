@@ -0,0 +1,55 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    stake_table::StakeTableCommitment,
+    traits::{election::Membership, signature_key::StakeTableEntryType},
+};
+use primitive_types::U256;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_validator_set_attestation() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let attestation = handle.validator_set_attestation().await.unwrap();
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    assert_eq!(attestation.signer, handle.public_key());
+    assert!(attestation.is_valid(upgrade_lock).await);
+
+    // The export is the stake table of consensus, and matches the commitment in block headers
+    let epoch = handle.cur_epoch().await;
+    assert_eq!(attestation.sets.epoch, epoch);
+    let membership = handle.hotshot.memberships.read().await;
+    let stake_table = membership.stake_table(epoch);
+    assert!(!stake_table.is_empty());
+    assert_eq!(attestation.sets.current.len(), stake_table.len());
+    for (exported, entry) in attestation.sets.current.iter().zip(&stake_table) {
+        assert_eq!(exported.stake_key, entry.public_key());
+        assert_eq!(exported.stake_amount, entry.stake());
+    }
+    assert_eq!(
+        attestation.sets.stake_table_commitment(),
+        StakeTableCommitment::new::<TestTypes>(&membership, epoch)
+    );
+
+    // Neither the sets nor the signer can be changed without invalidating the signature
+    let mut inflated = attestation.clone();
+    inflated.sets.current[0].stake_amount += U256::from(1);
+    assert!(!inflated.is_valid(upgrade_lock).await);
+
+    let other = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(3)
+        .await
+        .0;
+    let mut impersonated = attestation;
+    impersonated.signer = other.public_key();
+    assert!(!impersonated.is_valid(upgrade_lock).await);
+}
@@ -13,6 +13,7 @@ use hotshot_example_types::{
     state_types::{TestInstanceState, TestValidatedState},
 };
 use hotshot_macros::{run_test, test_scripts};
+use hotshot_task::sharded::ViewSharded;
 use hotshot_task_impls::{events::HotShotEvent::*, vid::VidTaskState};
 use hotshot_testing::{
     helpers::{build_system_handle, vid_scheme_from_view_number},
@@ -71,7 +72,11 @@ async fn test_vid_task() {
 
     let signature = <TestTypes as NodeType>::SignatureKey::sign(
         handle.private_key(),
-        payload_commitment.as_ref(),
+        &handle
+            .hotshot
+            .upgrade_lock
+            .signing_message(payload_commitment.as_ref(), ViewNumber::new(2))
+            .await,
     )
     .expect("Failed to sign block payload!");
     let proposal: DaProposal<TestTypes> = DaProposal {
@@ -148,10 +153,11 @@ async fn test_vid_task() {
         ]),
     ];
 
-    let vid_state = VidTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let vid_state = VidTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
     let mut script = TaskScript {
-        timeout: std::time::Duration::from_millis(35),
-        state: vid_state,
+        // The dispersal is computed in a subtask, after the event has been handed over
+        timeout: std::time::Duration::from_millis(500),
+        state: ViewSharded::new(vid_state),
         expectations,
     };
 
@@ -0,0 +1,242 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use hotshot::{tasks::task_state::CreateTaskState, types::SystemContextHandle};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+    storage_types::TestStorage,
+};
+use hotshot_macros::{run_test, test_scripts};
+use hotshot_task_impls::{
+    events::HotShotEvent::*, helpers::prune_view_change_evidence,
+    quorum_proposal_recv::QuorumProposalRecvTaskState,
+};
+use hotshot_testing::{
+    helpers::{build_cert, build_system_handle},
+    predicates::event::exact,
+    script::{Expectations, InputOrder, TaskScript},
+    serial,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::{EpochNumber, Leaf2, ViewChangeEvidence, ViewNumber},
+    simple_certificate::TimeoutCertificate2,
+    simple_vote::{TimeoutData2, TimeoutVote2},
+    traits::{consensus_api::ConsensusApi, node_implementation::ConsensusTime, storage::Storage},
+};
+
+/// A timeout certificate for `view`, signed by the committee of `handle`
+async fn timeout_evidence(
+    handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+    view: u64,
+) -> ViewChangeEvidence<TestTypes> {
+    let view = ViewNumber::new(view);
+    let epoch = EpochNumber::new(0);
+    ViewChangeEvidence::Timeout(
+        build_cert::<
+            TestTypes,
+            TestVersions,
+            TimeoutData2<TestTypes>,
+            TimeoutVote2<TestTypes>,
+            TimeoutCertificate2<TestTypes>,
+        >(
+            TimeoutData2 { view, epoch },
+            &handle.hotshot.memberships,
+            view,
+            epoch,
+            &handle.public_key(),
+            handle.private_key(),
+            &handle.hotshot.upgrade_lock,
+        )
+        .await,
+    )
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_proposal_justified_by_ancient_timeout_certificate_is_rejected() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(3)
+        .await
+        .0;
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let mut proposals = Vec::new();
+    let mut leaders = Vec::new();
+    for view in (&mut generator).take(3).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaders.push(view.leader_public_key);
+        consensus_writer
+            .update_leaf(
+                Leaf2::from_quorum_proposal(&view.quorum_proposal.data),
+                Arc::new(TestValidatedState::default()),
+                None,
+            )
+            .unwrap();
+    }
+    drop(consensus_writer);
+
+    // The proposal for view 3 extends view 2 and needs no evidence, but its leader attaches a
+    // genuine timeout certificate for view 1, which the leader's signature does not cover
+    let mut proposal = proposals[2].clone();
+    assert_eq!(
+        proposal.data.justify_qc.view_number,
+        ViewNumber::new(2),
+        "the proposal should extend the previous view"
+    );
+    proposal.data.view_change_evidence = Some(timeout_evidence(&handle, 1).await);
+
+    let inputs = vec![serial![QuorumProposalRecv(proposal, leaders[2])]];
+    let expectations = vec![Expectations::from_outputs(vec![])];
+
+    let state =
+        QuorumProposalRecvTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle)
+            .await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+
+    // Nor is the stale evidence archived as the reason the view advanced
+    assert!(handle
+        .storage()
+        .read()
+        .await
+        .load_view_change_evidence(ViewNumber::new(3), ViewNumber::new(3))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stale_view_change_evidence_is_pruned() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let storage = TestStorage::<TestTypes>::default();
+
+    // The timeout of view 1 justifies the proposal for view 2, but that of view 3 cannot justify
+    // the proposal for view 6
+    let valid = timeout_evidence(&handle, 1).await;
+    let stale = timeout_evidence(&handle, 3).await;
+    let undecided = timeout_evidence(&handle, 2).await;
+    for (view, evidence) in [(2, &valid), (6, &stale), (9, &undecided)] {
+        storage
+            .append_view_change_evidence(ViewNumber::new(view), evidence)
+            .await
+            .unwrap();
+    }
+
+    // Only the views which were decided are cleaned up
+    let pruned = prune_view_change_evidence(&storage, ViewNumber::new(1), ViewNumber::new(8))
+        .await
+        .unwrap();
+    assert_eq!(pruned, 1);
+
+    let remaining = storage
+        .load_view_change_evidence(ViewNumber::genesis(), ViewNumber::new(10))
+        .await
+        .unwrap();
+    assert_eq!(
+        remaining.into_iter().collect::<Vec<_>>(),
+        vec![(ViewNumber::new(2), valid), (ViewNumber::new(9), undecided)]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_change_evidence_is_archived_and_queried() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+
+    // View 1 times out, so the proposal for view 2 carries the timeout certificate
+    let mut generator = TestViewGenerator::generate(Arc::clone(&handle.hotshot.memberships));
+    let mut views = (&mut generator).take(1).collect::<Vec<_>>().await;
+    generator.add_timeout(TimeoutData2 {
+        view: ViewNumber::new(1),
+        epoch: EpochNumber::new(0),
+    });
+    views.extend((&mut generator).take(1).collect::<Vec<_>>().await);
+    consensus_writer
+        .update_leaf(
+            Leaf2::from_quorum_proposal(&views[0].quorum_proposal.data),
+            Arc::new(TestValidatedState::default()),
+            None,
+        )
+        .unwrap();
+    drop(consensus_writer);
+
+    let proposal = views[1].quorum_proposal.clone();
+    let evidence = proposal.data.view_change_evidence.clone().unwrap();
+    assert!(matches!(evidence, ViewChangeEvidence::Timeout(_)));
+
+    let inputs = vec![serial![QuorumProposalRecv(
+        proposal.clone(),
+        views[1].leader_public_key
+    )]];
+    let expectations = vec![Expectations::from_outputs(vec![
+        exact(QuorumProposalPreliminarilyValidated(proposal.clone())),
+        exact(QuorumProposalValidated(proposal, views[0].leaf.clone())),
+        exact(ViewChange(ViewNumber::new(2), EpochNumber::new(0))),
+    ])];
+
+    let state =
+        QuorumProposalRecvTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle)
+            .await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+
+    // The evidence is archived under the view of the proposal it justified, and found again from
+    // the decided leaf
+    let archived = handle
+        .storage()
+        .read()
+        .await
+        .load_view_change_evidence(ViewNumber::new(2), ViewNumber::new(2))
+        .await
+        .unwrap();
+    assert_eq!(
+        archived.into_iter().collect::<Vec<_>>(),
+        vec![(ViewNumber::new(2), evidence.clone())]
+    );
+
+    let chain = handle
+        .view_change_evidence_chain(&views[1].leaf)
+        .await
+        .unwrap();
+    assert_eq!(
+        chain.into_iter().collect::<Vec<_>>(),
+        vec![(ViewNumber::new(2), evidence)]
+    );
+
+    // Leaves which extend their parent's view directly have no evidence
+    assert!(handle
+        .view_change_evidence_chain(&views[0].leaf)
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::{Duration, Instant};
+
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::ViewNumber,
+    signature_key::BLSPubKey,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+    view_history::ViewHistory,
+};
+
+#[cfg(test)]
+#[test]
+fn test_view_history_is_bounded() {
+    let start = Instant::now();
+    let mut history = ViewHistory::<TestTypes>::new(3);
+    for view in 1..=5 {
+        history.enter(
+            ViewNumber::new(view),
+            None,
+            start + Duration::from_secs(view),
+        );
+    }
+
+    let recent = history.recent(10);
+    assert_eq!(
+        recent.iter().map(|stats| *stats.view).collect::<Vec<_>>(),
+        vec![3, 4, 5]
+    );
+    assert!(history.get(ViewNumber::new(2)).is_none());
+
+    // Each view lasts until the next is entered; the view we are in has no duration yet
+    assert_eq!(recent[0].duration, Some(Duration::from_secs(1)));
+    assert_eq!(recent[2].duration, None);
+
+    // Views are entered in order only
+    history.enter(ViewNumber::new(4), None, start + Duration::from_secs(6));
+    assert_eq!(history.recent(1)[0].view, ViewNumber::new(5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_view_history_summary() {
+    let start = Instant::now();
+    let mut history = ViewHistory::<TestTypes>::default();
+
+    // Views 1 to 6 take two seconds each; view 3 times out, and 1, 2, 4 and 5 are decided
+    for view in 1..=6 {
+        history.enter(
+            ViewNumber::new(view),
+            Some(BLSPubKey::generated_from_seed_indexed([0u8; 32], view).0),
+            start + Duration::from_secs(2 * view),
+        );
+    }
+    history.record_timeout(ViewNumber::new(3));
+    for (view, block_size) in [(1, Some(10)), (2, None), (4, Some(20)), (5, Some(30))] {
+        history.record_decided(ViewNumber::new(view), block_size);
+    }
+    // Views we don't hold are ignored
+    history.record_timeout(ViewNumber::new(100));
+
+    let now = start + Duration::from_secs(13);
+    let summary = history.summary(Duration::from_secs(60), now);
+    assert_eq!(summary.views, 6);
+    assert_eq!(summary.decided, 4);
+    assert_eq!(summary.timed_out, 1);
+    assert_eq!(summary.mean_duration, Some(Duration::from_secs(2)));
+    assert_eq!(summary.views_per_decide, Some(1.5));
+    assert_eq!(summary.mean_block_size, Some(20.0));
+
+    // Only the views entered in the period count: views 4 to 6, entered 5, 3 and 1 seconds ago
+    let summary = history.summary(Duration::from_secs(5), now);
+    assert_eq!(summary.views, 3);
+    assert_eq!(summary.decided, 2);
+    assert_eq!(summary.timed_out, 0);
+    assert_eq!(summary.mean_block_size, Some(25.0));
+
+    assert_eq!(
+        history.summary(Duration::ZERO, now + Duration::from_secs(1)),
+        Default::default()
+    );
+}
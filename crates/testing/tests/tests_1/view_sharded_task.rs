@@ -0,0 +1,232 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_broadcast::{broadcast, Receiver, Sender};
+use async_trait::async_trait;
+use hotshot_task::{
+    sharded::{ViewSharded, ViewShardedTaskState},
+    task::{TaskEvent, TaskState},
+};
+use utils::anytrace::Result;
+
+/// Events of the test task
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TestEvent {
+    /// The `n`th event for a view, which takes `delay_ms` to handle
+    View { view: u64, n: u64, delay_ms: u64 },
+    /// A change to a new view
+    ViewChange(u64),
+    /// An event for every view
+    Barrier,
+    /// Shut the task down
+    Shutdown,
+}
+
+impl TaskEvent for TestEvent {
+    fn shutdown_event() -> Self {
+        Self::Shutdown
+    }
+}
+
+/// Records the order its events finish being handled in
+#[derive(Default)]
+struct RecordingState {
+    /// Events in the order they were handled
+    handled: Arc<Mutex<Vec<TestEvent>>>,
+    /// The latest view changed to
+    cur_view: AtomicU64,
+}
+
+#[async_trait]
+impl ViewShardedTaskState for RecordingState {
+    type Event = TestEvent;
+    type View = u64;
+
+    fn event_view(&self, event: &TestEvent) -> Option<u64> {
+        match event {
+            TestEvent::View { view, .. } => Some(*view),
+            _ => None,
+        }
+    }
+
+    async fn handle_view_event(
+        &self,
+        _view: u64,
+        event: Arc<TestEvent>,
+        _sender: &Sender<Arc<TestEvent>>,
+    ) -> Result<()> {
+        if let TestEvent::View { view, n, delay_ms } = *event {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            tracing::debug!("Handled event {n} of view {view}");
+        }
+        self.handled.lock().unwrap().push((*event).clone());
+        Ok(())
+    }
+
+    fn is_shared_event(&self, event: &TestEvent) -> bool {
+        matches!(event, TestEvent::ViewChange(_))
+    }
+
+    async fn handle_shared_event(
+        &self,
+        event: Arc<TestEvent>,
+        _sender: &Sender<Arc<TestEvent>>,
+    ) -> Result<()> {
+        if let TestEvent::ViewChange(view) = *event {
+            self.cur_view.fetch_max(view, Ordering::AcqRel);
+        }
+        self.handled.lock().unwrap().push((*event).clone());
+        Ok(())
+    }
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<TestEvent>,
+        _sender: &Sender<Arc<TestEvent>>,
+        _receiver: &Receiver<Arc<TestEvent>>,
+    ) -> Result<()> {
+        self.handled.lock().unwrap().push((*event).clone());
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
+
+/// Hand `events` to a sharded task in order, returning the order they were handled in
+async fn handle_all(events: &[TestEvent]) -> Vec<TestEvent> {
+    let state = RecordingState::default();
+    let handled = Arc::clone(&state.handled);
+    let mut task = ViewSharded::new(state);
+    let (sender, receiver) = broadcast(16);
+
+    for event in events {
+        task.handle_event(Arc::new(event.clone()), &sender, &receiver)
+            .await
+            .unwrap();
+    }
+    // Handled only once every earlier event has been
+    task.handle_event(Arc::new(TestEvent::Barrier), &sender, &receiver)
+        .await
+        .unwrap();
+    assert_eq!(task.views_in_flight(), 0);
+
+    let mut handled = handled.lock().unwrap().clone();
+    assert_eq!(handled.pop(), Some(TestEvent::Barrier));
+    handled
+}
+
+fn view(view: u64, n: u64, delay_ms: u64) -> TestEvent {
+    TestEvent::View { view, n, delay_ms }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_slow_view_does_not_block_later_views() {
+    hotshot::helpers::initialize_logging();
+
+    let handled = handle_all(&[view(1, 0, 300), view(2, 0, 0), view(3, 0, 0)]).await;
+
+    assert_eq!(handled.len(), 3);
+    assert_eq!(handled.last(), Some(&view(1, 0, 300)));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_events_of_a_view_handled_in_order() {
+    hotshot::helpers::initialize_logging();
+
+    let handled = handle_all(&[
+        view(1, 0, 200),
+        view(1, 1, 0),
+        view(2, 0, 100),
+        view(1, 2, 0),
+    ])
+    .await;
+
+    assert_eq!(
+        handled,
+        [
+            view(2, 0, 100),
+            view(1, 0, 200),
+            view(1, 1, 0),
+            view(1, 2, 0)
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shared_events_wait_for_earlier_events() {
+    hotshot::helpers::initialize_logging();
+
+    let handled = handle_all(&[
+        view(1, 0, 200),
+        TestEvent::Barrier,
+        view(2, 0, 0),
+        view(1, 1, 0),
+    ])
+    .await;
+
+    assert_eq!(handled[..2], [view(1, 0, 200), TestEvent::Barrier]);
+    assert_eq!(handled.len(), 4);
+}
+
+// A view change doesn't wait for the events of older views, so an event for the new view is
+// handled while an old view's handler is still running
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_change_does_not_wait_for_old_views() {
+    hotshot::helpers::initialize_logging();
+
+    let state = RecordingState::default();
+    let handled = Arc::clone(&state.handled);
+    let mut task = ViewSharded::new(state);
+    let (sender, receiver) = broadcast(16);
+
+    for event in [view(1, 0, 500), TestEvent::ViewChange(2), view(2, 0, 0)] {
+        task.handle_event(Arc::new(event), &sender, &receiver)
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        task.state().read().await.cur_view.load(Ordering::Acquire),
+        2
+    );
+
+    tokio::time::timeout(Duration::from_millis(250), async {
+        while handled.lock().unwrap().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("Events of the new view waited for the old view");
+    assert_eq!(
+        *handled.lock().unwrap(),
+        [TestEvent::ViewChange(2), view(2, 0, 0)]
+    );
+    assert_eq!(task.views_in_flight(), 1);
+
+    task.handle_event(Arc::new(TestEvent::Barrier), &sender, &receiver)
+        .await
+        .unwrap();
+    assert_eq!(
+        *handled.lock().unwrap(),
+        [
+            TestEvent::ViewChange(2),
+            view(2, 0, 0),
+            view(1, 0, 500),
+            TestEvent::Barrier
+        ]
+    );
+}
@@ -0,0 +1,174 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::view_sync::ViewSyncPhase;
+use hotshot_testing::{
+    helpers::build_system_handle,
+    view_sync_network::{FormedCertificate, LossyViewSyncNetwork, RelayLoss},
+};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+/// Number of nodes in the network. A pre-commit certificate needs 4 of their votes, and commit and
+/// finalize certificates 7.
+const NUM_NODES: u64 = 10;
+
+/// The round being synced
+fn round() -> ViewNumber {
+    ViewNumber::new(4)
+}
+
+/// The view sync tasks of every node, and the view sync timeout of the round
+async fn network() -> (
+    LossyViewSyncNetwork<TestTypes, MemoryImpl, TestVersions>,
+    Duration,
+) {
+    let mut handles = Vec::new();
+    for node_id in 0..NUM_NODES {
+        handles.push(
+            build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id)
+                .await
+                .0,
+        );
+    }
+    let view_sync_timeout = handles[0].hotshot.config.view_sync_timeout;
+
+    (LossyViewSyncNetwork::new(handles), view_sync_timeout)
+}
+
+/// The certificates of every phase formed by relay number `relay`, which is `relay_node`
+fn certificates(relay: u64, relay_node: u64) -> Vec<FormedCertificate> {
+    [
+        ViewSyncPhase::PreCommit,
+        ViewSyncPhase::Commit,
+        ViewSyncPhase::Finalize,
+    ]
+    .into_iter()
+    .map(|phase| FormedCertificate::new(phase, relay, relay_node))
+    .collect()
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_without_loss() {
+    hotshot::helpers::initialize_logging();
+
+    let (network, view_sync_timeout) = network().await;
+    let relay_node = network.relay_node(round(), 0).await;
+    let run = network.run(round(), view_sync_timeout).await;
+
+    assert!(run.completed(NUM_NODES as usize), "{run:?}");
+    assert_eq!(run.certificates, certificates(0, relay_node));
+    assert!(run.timed_out_relays.is_empty());
+    assert_eq!(run.dropped_votes, 0);
+}
+
+// The first relay drops as many votes of each phase as it can while still forming its
+// certificates, so the round completes on the first relay.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_tolerates_loss_up_to_thresholds() {
+    hotshot::helpers::initialize_logging();
+
+    let (network, view_sync_timeout) = network().await;
+    let relay_node = network.relay_node(round(), 0).await;
+    let network = network.with_lossy_relay(
+        relay_node,
+        RelayLoss {
+            pre_commit: 0.6,
+            commit: 0.3,
+            finalize: 0.3,
+        },
+    );
+    let run = network.run(round(), view_sync_timeout).await;
+
+    assert!(run.completed(NUM_NODES as usize), "{run:?}");
+    assert_eq!(run.certificates, certificates(0, relay_node));
+    assert!(run.timed_out_relays.is_empty());
+    assert!(run.dropped_votes > 0);
+    assert!(run.elapsed < view_sync_timeout);
+}
+
+// The first relay forms a pre-commit certificate but drops every commit vote, so the nodes time
+// out on it and fail over to the second relay, which completes the round from the start.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_fails_over_from_lossy_relay() {
+    hotshot::helpers::initialize_logging();
+
+    let (network, view_sync_timeout) = network().await;
+    let first_relay = network.relay_node(round(), 0).await;
+    let second_relay = network.relay_node(round(), 1).await;
+    let network = network.with_lossy_relay(
+        first_relay,
+        RelayLoss {
+            commit: 1.0,
+            ..RelayLoss::default()
+        },
+    );
+    let run = network.run(round(), view_sync_timeout).await;
+
+    assert!(run.completed(NUM_NODES as usize), "{run:?}");
+    let mut expected = vec![FormedCertificate::new(
+        ViewSyncPhase::PreCommit,
+        0,
+        first_relay,
+    )];
+    expected.extend(certificates(1, second_relay));
+    assert_eq!(run.certificates, expected);
+    assert_eq!(run.timed_out_relays, BTreeSet::from([0]));
+    assert!(run.elapsed < view_sync_timeout);
+}
+
+// The first two relays receive too few pre-commit votes to certify anything, so the round only
+// completes on the third, still within the view sync timeout, which allows for three relays.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_escalates_within_timeout_budget() {
+    hotshot::helpers::initialize_logging();
+
+    let (network, view_sync_timeout) = network().await;
+    let third_relay = network.relay_node(round(), 2).await;
+    let mut network = network;
+    for relay in 0..2 {
+        let relay_node = network.relay_node(round(), relay).await;
+        network = network.with_lossy_relay(
+            relay_node,
+            RelayLoss {
+                pre_commit: 0.7,
+                ..RelayLoss::default()
+            },
+        );
+    }
+    let run = network.run(round(), view_sync_timeout * 2).await;
+
+    assert!(run.completed(NUM_NODES as usize), "{run:?}");
+    assert_eq!(run.certificates, certificates(2, third_relay));
+    assert_eq!(run.timed_out_relays, BTreeSet::from([0, 1]));
+    assert_eq!(run.dropped_votes, 7 + 7);
+    assert!(run.elapsed < view_sync_timeout);
+}
+
+// Every relay drops most votes, so no certificate ever forms and no node moves on: view sync keeps
+// failing over instead of completing with too few votes.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_never_completes_without_enough_votes() {
+    hotshot::helpers::initialize_logging();
+
+    let (mut network, view_sync_timeout) = network().await;
+    for node in 0..NUM_NODES {
+        network = network.with_lossy_relay(node, RelayLoss::uniform(0.7));
+    }
+    let run = network.run(round(), view_sync_timeout).await;
+
+    assert!(!run.completed(NUM_NODES as usize));
+    assert!(run.certificates.is_empty());
+    assert!(run.synced.is_empty());
+    assert!(run.timed_out_relays.contains(&0));
+}
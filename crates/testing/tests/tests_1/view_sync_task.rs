@@ -4,16 +4,26 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
+use std::collections::HashSet;
+
 use hotshot::tasks::task_state::CreateTaskState;
 use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
 use hotshot_task_impls::{
-    events::HotShotEvent, harness::run_harness, view_sync::ViewSyncTaskState,
+    events::HotShotEvent, harness::run_harness, helpers::view_sync_relay,
+    view_sync::ViewSyncTaskState,
 };
-use hotshot_testing::helpers::build_system_handle;
+use hotshot_testing::helpers::{build_cert, build_system_handle};
 use hotshot_types::{
-    data::{EpochNumber, ViewNumber},
-    simple_vote::ViewSyncPreCommitData2,
-    traits::node_implementation::ConsensusTime,
+    data::{EpochNumber, ViewNumber, ViewSyncCertificate},
+    simple_certificate::{ViewSyncCommitCertificate2, ViewSyncPreCommitCertificate2},
+    simple_vote::{
+        ViewSyncCommitData2, ViewSyncCommitVote2, ViewSyncFinalizeData2, ViewSyncFinalizeVote2,
+        ViewSyncPreCommitData2, ViewSyncPreCommitVote2,
+    },
+    traits::{
+        consensus_api::ConsensusApi, election::Membership, node_implementation::ConsensusTime,
+        storage::Storage,
+    },
 };
 
 #[cfg(test)]
@@ -63,6 +73,139 @@ async fn test_view_sync_task() {
     ));
     output.push(HotShotEvent::ViewSyncPreCommitVoteSend(vote.clone()));
 
-    let view_sync_state = ViewSyncTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    let view_sync_state = ViewSyncTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
     run_harness(input, output, view_sync_state, false).await;
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_relays() {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let membership = handle.hotshot.memberships.read().await;
+    let epoch = EpochNumber::new(0);
+    let round = ViewNumber::new(4);
+    let num_nodes = membership.total_nodes(epoch) as u64;
+
+    // The first relay is the leader of the round
+    assert_eq!(
+        view_sync_relay::<TestTypes>(&membership, round, 0, epoch).unwrap(),
+        membership.leader(round, epoch).unwrap()
+    );
+
+    // Failing over always moves to another node, until every node has been a relay
+    let relays = (0..num_nodes)
+        .map(|relay| view_sync_relay::<TestTypes>(&membership, round, relay, epoch).unwrap())
+        .collect::<HashSet<_>>();
+    assert_eq!(relays.len() as u64, num_nodes);
+
+    assert_eq!(
+        view_sync_relay::<TestTypes>(&membership, round, num_nodes, epoch).unwrap(),
+        view_sync_relay::<TestTypes>(&membership, round, 0, epoch).unwrap()
+    );
+
+    // Each relay gets its share of the view sync timeout
+    let config = &handle.hotshot.config;
+    assert_eq!(
+        config.view_sync_relay_timeout(),
+        config.view_sync_timeout / config.view_sync_relays_per_round
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_resumes_from_persisted_certificate() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(5)
+        .await
+        .0;
+    let round = ViewNumber::new(4);
+    let epoch = EpochNumber::new(0);
+
+    let pre_commit_cert = build_cert::<
+        TestTypes,
+        TestVersions,
+        ViewSyncPreCommitData2<TestTypes>,
+        ViewSyncPreCommitVote2<TestTypes>,
+        ViewSyncPreCommitCertificate2<TestTypes>,
+    >(
+        ViewSyncPreCommitData2 {
+            relay: 0,
+            round,
+            epoch,
+        },
+        &handle.hotshot.memberships,
+        round,
+        epoch,
+        &handle.public_key(),
+        handle.private_key(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await;
+    let commit_cert = build_cert::<
+        TestTypes,
+        TestVersions,
+        ViewSyncCommitData2<TestTypes>,
+        ViewSyncCommitVote2<TestTypes>,
+        ViewSyncCommitCertificate2<TestTypes>,
+    >(
+        ViewSyncCommitData2 {
+            relay: 0,
+            round,
+            epoch,
+        },
+        &handle.hotshot.memberships,
+        round,
+        epoch,
+        &handle.public_key(),
+        handle.private_key(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await;
+
+    // An earlier phase of the same round does not replace the persisted certificate
+    let storage = handle.storage();
+    for certificate in [
+        ViewSyncCertificate::Commit(commit_cert.clone()),
+        ViewSyncCertificate::PreCommit(pre_commit_cert),
+    ] {
+        storage
+            .read()
+            .await
+            .update_view_sync_certificate(&certificate)
+            .await
+            .unwrap();
+    }
+
+    let view_sync_state =
+        ViewSyncTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    assert_eq!(
+        view_sync_state.restored_certificate,
+        Some(ViewSyncCertificate::Commit(commit_cert))
+    );
+
+    // Having seen the commit certificate before the restart, we carry on with the finalize vote
+    let finalize_vote = ViewSyncFinalizeVote2::<TestTypes>::create_signed_vote(
+        ViewSyncFinalizeData2 {
+            relay: 0,
+            round,
+            epoch,
+        },
+        round,
+        &handle.public_key(),
+        handle.private_key(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .unwrap();
+
+    run_harness(
+        vec![HotShotEvent::ViewChange(ViewNumber::new(1), epoch)],
+        vec![HotShotEvent::ViewSyncFinalizeVoteSend(finalize_vote)],
+        view_sync_state,
+        true,
+    )
+    .await;
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::ViewNumber,
+    traits::node_implementation::ConsensusTime,
+    view_tracker::{TrackedTask, ViewTracker},
+};
+
+// A task may stay in its view or move forward, but never move back
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_tracker_monotonicity() {
+    hotshot::helpers::initialize_logging();
+
+    let tracker = ViewTracker::<TestTypes>::default();
+    assert_eq!(tracker.view(TrackedTask::Consensus).await, None);
+
+    tracker
+        .update(TrackedTask::Consensus, ViewNumber::new(3))
+        .await
+        .unwrap();
+    tracker
+        .update(TrackedTask::Consensus, ViewNumber::new(3))
+        .await
+        .unwrap();
+    tracker
+        .update(TrackedTask::Consensus, ViewNumber::new(5))
+        .await
+        .unwrap();
+
+    // Moving back is rejected without recording the older view
+    assert!(tracker
+        .update(TrackedTask::Consensus, ViewNumber::new(4))
+        .await
+        .is_err());
+    assert_eq!(
+        tracker.view(TrackedTask::Consensus).await,
+        Some(ViewNumber::new(5))
+    );
+
+    // Each task is checked against its own view only
+    tracker
+        .update(TrackedTask::Da, ViewNumber::new(1))
+        .await
+        .unwrap();
+    assert_eq!(
+        tracker.views().await.into_iter().collect::<Vec<_>>(),
+        vec![
+            (TrackedTask::Consensus, ViewNumber::new(5)),
+            (TrackedTask::Da, ViewNumber::new(1)),
+        ]
+    );
+}
+
+// Tasks more than the maximum lag behind the most advanced task are flagged until they catch up
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_tracker_lag() {
+    hotshot::helpers::initialize_logging();
+
+    let tracker = ViewTracker::<TestTypes>::new(Some(2));
+    for task in [
+        TrackedTask::Consensus,
+        TrackedTask::Da,
+        TrackedTask::Vid,
+        TrackedTask::ViewSync,
+    ] {
+        tracker.update(task, ViewNumber::new(1)).await.unwrap();
+    }
+    assert!(tracker.lagging().await.is_empty());
+
+    // Exactly the maximum lag behind is tolerated
+    tracker
+        .update(TrackedTask::Consensus, ViewNumber::new(3))
+        .await
+        .unwrap();
+    assert!(tracker.lagging().await.is_empty());
+
+    tracker
+        .update(TrackedTask::Consensus, ViewNumber::new(4))
+        .await
+        .unwrap();
+    tracker
+        .update(TrackedTask::Da, ViewNumber::new(4))
+        .await
+        .unwrap();
+    tracker
+        .update(TrackedTask::Vid, ViewNumber::new(2))
+        .await
+        .unwrap();
+    assert_eq!(
+        tracker.lagging().await,
+        vec![(TrackedTask::ViewSync, ViewNumber::new(1))]
+    );
+
+    tracker
+        .update(TrackedTask::ViewSync, ViewNumber::new(4))
+        .await
+        .unwrap();
+    assert!(tracker.lagging().await.is_empty());
+
+    // Without a maximum lag, no task is ever flagged
+    let tracker = ViewTracker::<TestTypes>::new(None);
+    tracker
+        .update(TrackedTask::Consensus, ViewNumber::new(100))
+        .await
+        .unwrap();
+    tracker
+        .update(TrackedTask::Da, ViewNumber::new(1))
+        .await
+        .unwrap();
+    assert!(tracker.lagging().await.is_empty());
+}
@@ -95,9 +95,11 @@ async fn test_vote_dependency_handle() {
                 view_number,
                 sender: event_sender.clone(),
                 receiver: event_receiver.clone().deactivate(),
+                output_event_stream: handle.external_channel_sender(),
                 upgrade_lock: handle.hotshot.upgrade_lock.clone(),
                 id: handle.hotshot.id,
                 epoch_height: handle.hotshot.config.epoch_height,
+                work_scheduler: Arc::default(),
             };
 
         vote_dependency_handle_state
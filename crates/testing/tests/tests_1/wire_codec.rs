@@ -0,0 +1,135 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::BTreeMap;
+
+use hotshot_example_types::node_types::{TestTypes, TestVersions};
+use hotshot_types::{
+    message::{Message, MessageKind, UpgradeLock},
+    signature_key::BLSPubKey,
+    traits::signature_key::SignatureKey,
+    wire_codec::{BincodeCodec, CborCodec, WireCodec, WireFormat},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// A message exercising the shapes of data `serde` can describe
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct FuzzMessage {
+    number: u64,
+    signed: i32,
+    flag: bool,
+    bytes: Vec<u8>,
+    text: String,
+    optional: Option<u16>,
+    map: BTreeMap<u8, Vec<u64>>,
+    variant: FuzzVariant,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum FuzzVariant {
+    Unit,
+    Tuple(u8, u128),
+    Struct { inner: Vec<Option<bool>> },
+}
+
+fn random_message(rng: &mut StdRng) -> FuzzMessage {
+    let bytes_len = rng.gen_range(0..256);
+    let text_len = rng.gen_range(0..32);
+    let map_len = rng.gen_range(0..8);
+
+    FuzzMessage {
+        number: rng.gen(),
+        signed: rng.gen(),
+        flag: rng.gen(),
+        bytes: (0..bytes_len).map(|_| rng.gen()).collect(),
+        text: (0..text_len).map(|_| rng.gen::<char>()).collect(),
+        optional: rng.gen::<bool>().then(|| rng.gen()),
+        map: (0..map_len)
+            .map(|_| (rng.gen(), (0..rng.gen_range(0..4)).map(|_| rng.gen()).collect()))
+            .collect(),
+        variant: match rng.gen_range(0..3) {
+            0 => FuzzVariant::Unit,
+            1 => FuzzVariant::Tuple(rng.gen(), rng.gen()),
+            _ => FuzzVariant::Struct {
+                inner: (0..rng.gen_range(0..8))
+                    .map(|_| rng.gen::<bool>().then(|| rng.gen()))
+                    .collect(),
+            },
+        },
+    }
+}
+
+/// Round trip random messages through `C`, and check that truncated encodings don't decode
+fn fuzz_codec<C: WireCodec>(seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for _ in 0..1000 {
+        let message = random_message(&mut rng);
+        let encoded = C::encode(&message).unwrap();
+        assert_eq!(C::decode::<FuzzMessage>(&encoded).unwrap(), message);
+        assert_eq!(C::FORMAT.decode::<FuzzMessage>(&encoded).unwrap(), message);
+
+        let truncated = rng.gen_range(0..encoded.len());
+        assert!(C::decode::<FuzzMessage>(&encoded[..truncated]).is_err());
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_bincode_codec_round_trip() {
+    fuzz_codec::<BincodeCodec>(0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_cbor_codec_round_trip() {
+    fuzz_codec::<CborCodec>(1);
+}
+
+#[cfg(test)]
+#[test]
+fn test_wire_format_names() {
+    for format in [WireFormat::Bincode, WireFormat::Cbor] {
+        assert_eq!(format.to_string().parse::<WireFormat>().unwrap(), format);
+    }
+    assert!("protobuf".parse::<WireFormat>().is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upgrade_lock_wire_formats() {
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let sender = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0).0;
+    let mut rng = StdRng::seed_from_u64(2);
+
+    for _ in 0..100 {
+        let payload_len = rng.gen_range(0..1024);
+        let message = Message::<TestTypes> {
+            sender,
+            kind: MessageKind::External((0..payload_len).map(|_| rng.gen()).collect()),
+        };
+
+        // The bincode format is the one messages have always been serialized in
+        let bincode = upgrade_lock
+            .serialize_with(&message, WireFormat::Bincode)
+            .await
+            .unwrap();
+        assert_eq!(bincode, upgrade_lock.serialize(&message).await.unwrap());
+
+        for format in [WireFormat::Bincode, WireFormat::Cbor] {
+            let serialized = upgrade_lock.serialize_with(&message, format).await.unwrap();
+            // Every format carries the same version prefix
+            assert_eq!(serialized[..4], bincode[..4]);
+
+            let deserialized: Message<TestTypes> = upgrade_lock
+                .deserialize_with(&serialized, format)
+                .await
+                .unwrap();
+            assert_eq!(deserialized, message);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use hotshot_task_impls::work_scheduler::{WorkClass, WorkScheduler};
+use hotshot_types::BackgroundWorkBudget;
+use tokio::time::sleep;
+
+/// Run a storage flush on its own task, returning whether it has run yet
+fn spawn_flush(scheduler: &Arc<WorkScheduler>) -> (Arc<AtomicBool>, tokio::task::JoinHandle<()>) {
+    let scheduler = Arc::clone(scheduler);
+    let flushed = Arc::new(AtomicBool::new(false));
+    let handle = tokio::spawn({
+        let flushed = Arc::clone(&flushed);
+        async move {
+            scheduler
+                .run(WorkClass::StorageFlush, async {
+                    flushed.store(true, Ordering::SeqCst);
+                })
+                .await;
+        }
+    });
+
+    (flushed, handle)
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_low_priority_work_deferred_once_budget_spent() {
+    hotshot::helpers::initialize_logging();
+
+    // Background work may take up 100ms of each one second view
+    let scheduler = Arc::new(WorkScheduler::new(
+        Some(BackgroundWorkBudget {
+            percent_of_view: 10,
+            deadline_margin: Duration::from_millis(10),
+        }),
+        Arc::default(),
+    ));
+    scheduler.start_view(1, Duration::from_secs(1));
+    assert_eq!(scheduler.remaining_budget(), Duration::from_millis(100));
+
+    // Applying a block spends the whole budget
+    scheduler
+        .run(
+            WorkClass::StateApplication,
+            sleep(Duration::from_millis(150)),
+        )
+        .await;
+    assert!(scheduler.remaining_budget().is_zero());
+
+    // So a storage flush waits for the next view
+    let (flushed, handle) = spawn_flush(&scheduler);
+    sleep(Duration::from_millis(100)).await;
+    assert!(!flushed.load(Ordering::SeqCst));
+    assert_eq!(scheduler.deferred()[&WorkClass::StorageFlush], 1);
+
+    // While work the vote path waits on still runs straight away, including our own VID share
+    // under full replication, unlike the optimistic one
+    assert_eq!(
+        scheduler
+            .run(WorkClass::StateApplication, async { 42 })
+            .await,
+        42
+    );
+    assert_eq!(
+        scheduler
+            .run(WorkClass::ReplicatedVidEncoding, async { 42 })
+            .await,
+        42
+    );
+    assert!(!WorkClass::ReplicatedVidEncoding.is_deferrable());
+    assert!(WorkClass::OptimisticVidEncoding.is_deferrable());
+
+    scheduler.start_view(2, Duration::from_secs(1));
+    handle.await.unwrap();
+    assert!(flushed.load(Ordering::SeqCst));
+    assert_eq!(scheduler.deferred()[&WorkClass::StorageFlush], 0);
+
+    let stats = scheduler.stats();
+    assert_eq!(stats[&WorkClass::StateApplication].runs, 2);
+    assert_eq!(stats[&WorkClass::StateApplication].deferrals, 0);
+    assert!(stats[&WorkClass::StateApplication].max_time >= Duration::from_millis(150));
+    assert_eq!(stats[&WorkClass::StorageFlush].runs, 1);
+    assert_eq!(stats[&WorkClass::StorageFlush].deferrals, 1);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_low_priority_work_deferred_near_deadline() {
+    hotshot::helpers::initialize_logging();
+
+    let scheduler = Arc::new(WorkScheduler::new(
+        Some(BackgroundWorkBudget {
+            percent_of_view: 100,
+            deadline_margin: Duration::from_millis(500),
+        }),
+        Arc::default(),
+    ));
+
+    // The view times out within the margin, so the flush waits despite the budget left
+    scheduler.start_view(5, Duration::from_millis(400));
+    let (flushed, handle) = spawn_flush(&scheduler);
+    sleep(Duration::from_millis(100)).await;
+    assert!(!flushed.load(Ordering::SeqCst));
+
+    // Restarting an older view does not release it
+    scheduler.start_view(4, Duration::from_secs(10));
+    sleep(Duration::from_millis(100)).await;
+    assert!(!flushed.load(Ordering::SeqCst));
+
+    // Work waits for one view at most, even if the next view is just as short
+    scheduler.start_view(6, Duration::from_millis(400));
+    handle.await.unwrap();
+    assert!(flushed.load(Ordering::SeqCst));
+    assert_eq!(scheduler.stats()[&WorkClass::StorageFlush].deferrals, 1);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unbudgeted_work_immediate() {
+    hotshot::helpers::initialize_logging();
+
+    let scheduler = WorkScheduler::default();
+    assert!(scheduler.budget().is_none());
+    scheduler.start_view(1, Duration::ZERO);
+
+    for class in WorkClass::ALL {
+        assert_eq!(scheduler.run(class, async { 42 }).await, 42);
+    }
+    let stats = scheduler.stats();
+    assert_eq!(stats.len(), WorkClass::ALL.len());
+    assert!(stats.values().all(|stats| stats.deferrals == 0));
+    assert!(scheduler.deferred().is_empty());
+}
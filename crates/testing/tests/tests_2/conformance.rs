@@ -0,0 +1,79 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_testing::conformance::{
+    check, export, parse_log, render_log, Action, ConformanceErr, NodeState,
+};
+
+const TRACE: &str = "\
+node 0: view 1: proposal of block 1 justified by QC for view 0
+node 0: view 2: proposal of block 2 justified by QC for view 1
+node 0: view 3: proposal of block 3 justified by QC for view 2
+node 0: view 1: decide views 1 with QC for view 2
+node 0: view 4: timeout
+node 0: view 5: proposal of block 4 justified by QC for view 3, timeout certificate for view 4
+node 1: view 1: proposal of block 1 justified by QC for view 0
+node 1: view 2: proposal of block 2 justified by QC for view 1
+node 1: view 1: decide views 1 with QC for view 2
+";
+
+#[cfg(test)]
+#[test]
+fn test_conformance_export_and_check() {
+    let log = export(TRACE).unwrap();
+    assert_eq!(log.len(), 9);
+
+    let last = log.iter().rfind(|transition| transition.node == 0).unwrap();
+    assert_eq!(
+        last.action,
+        Action::Propose { view: 5, parent: 3 }
+    );
+    assert_eq!(
+        last.state,
+        NodeState {
+            view: 5,
+            high_qc: 3,
+            locked_view: 3,
+            decided_view: 1,
+        }
+    );
+
+    check(&log).unwrap();
+    assert_eq!(parse_log(&render_log(&log)).unwrap(), log);
+}
+
+#[cfg(test)]
+#[test]
+fn test_conformance_catches_violations() {
+    // A state which does not follow from the action
+    let mut log = export(TRACE).unwrap();
+    log[1].state.locked_view = 0;
+    assert!(matches!(
+        check(&log),
+        Err(ConformanceErr::InvalidTransition { node: 0, step: 1, .. })
+    ));
+
+    // Two nodes deciding different blocks
+    let trace = TRACE.replace(
+        "node 1: view 1: decide views 1 with QC for view 2",
+        "node 1: view 2: decide views 2 with QC for view 3",
+    );
+    assert!(matches!(
+        check(&export(&trace).unwrap()),
+        Err(ConformanceErr::Disagreement { .. })
+    ));
+
+    // A decided block which doesn't extend the previously decided one
+    let trace = format!("{TRACE}node 1: view 2: decide views 2 with QC for view 3\n")
+        .replace(
+            "node 1: view 2: proposal of block 2 justified by QC for view 1",
+            "node 1: view 2: proposal of block 2 justified by QC for view 0",
+        );
+    assert!(matches!(
+        check(&export(&trace).unwrap()),
+        Err(ConformanceErr::BrokenChain { node: 1, view: 2, .. })
+    ));
+}
@@ -0,0 +1,51 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    golden_trace::{GoldenTraceDescription, GoldenTraceMode},
+    test_builder::TestDescription,
+};
+
+fn metadata() -> TestDescription<TestTypes, MemoryImpl, TestVersions> {
+    TestDescription {
+        // allow more time to pass in CI
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(60),
+            },
+        ),
+        ..TestDescription::default()
+    }
+}
+
+// Record the trace of a run, then check that a second run of the same test follows it exactly.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_golden_trace_is_reproducible() {
+    hotshot::helpers::initialize_logging();
+
+    let path = std::env::temp_dir().join(format!("hotshot-golden-trace-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    for mode in [GoldenTraceMode::Record, GoldenTraceMode::Compare] {
+        let golden_trace = GoldenTraceDescription {
+            mode,
+            ..GoldenTraceDescription::new(&path, 10)
+        };
+        metadata()
+            .gen_launcher_with_tasks(0, vec![Box::new(golden_trace)])
+            .launch()
+            .run_test::<SimpleBuilderImplementation>()
+            .await;
+    }
+
+    let _ = std::fs::remove_file(&path);
+}
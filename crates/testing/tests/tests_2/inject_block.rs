@@ -0,0 +1,54 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use hotshot::types::EventType;
+use hotshot_example_types::block_types::{TestBlockPayload, TestMetadata, TestTransaction};
+use hotshot_testing::quick_network;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_injected_block_is_decided() {
+    hotshot::helpers::initialize_logging();
+
+    let network = quick_network(4).await;
+    let mut events = network.handle(0).event_stream();
+
+    // Node 1 proposes exactly this block the next time it leads, whatever its builders offer
+    let transaction = TestTransaction::new(b"injected".to_vec());
+    network
+        .handle(1)
+        .inject_block(
+            TestBlockPayload {
+                transactions: vec![transaction.clone()],
+            },
+            TestMetadata {
+                num_transactions: 1,
+            },
+        )
+        .await;
+
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while let Some(event) = events.next().await {
+            if let EventType::Decide { leaf_chain, .. } = event.event {
+                if leaf_chain.iter().any(|leaf_info| {
+                    leaf_info
+                        .leaf
+                        .block_payload()
+                        .is_some_and(|payload| payload.transactions == vec![transaction.clone()])
+                }) {
+                    return;
+                }
+            }
+        }
+    })
+    .await
+    .expect("the injected block was not decided");
+
+    network.shut_down().await;
+}
@@ -0,0 +1,123 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::{
+    MemoryImpl, TestConsecutiveLeaderTypes, TestTypes, TestTypesRandomizedLeader, TestVersions,
+};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    leader_schedule::{schedule_load, LeaderLoad, LeaderScheduleTaskDescription},
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::TestDescription,
+};
+use hotshot_types::{
+    traits::{election::Membership, node_implementation::NodeType},
+    ValidatorConfig,
+};
+
+/// The load the `Membership` of `TYPES` puts on each of 10 equally staked leaders over 1000 views
+fn load<TYPES: NodeType>() -> LeaderLoad {
+    let validators = (0..10)
+        .map(|node_id| {
+            ValidatorConfig::<TYPES::SignatureKey>::generated_from_seed_indexed(
+                [0u8; 32], node_id, 1, true,
+            )
+        })
+        .collect::<Vec<_>>();
+    let peers = validators
+        .iter()
+        .map(ValidatorConfig::public_config)
+        .collect::<Vec<_>>();
+    let keys = validators
+        .iter()
+        .map(|validator| validator.public_key.clone())
+        .collect::<Vec<_>>();
+    let membership = TYPES::Membership::new(peers.clone(), peers);
+
+    schedule_load::<TYPES>(&membership, &keys, 1000)
+}
+
+#[cfg(test)]
+#[test]
+fn test_leader_schedule_load() {
+    hotshot::helpers::initialize_logging();
+
+    // Round robin spreads the views perfectly evenly, one view at a time
+    let round_robin = load::<TestTypes>();
+    assert_eq!(round_robin.views_led.len(), 10);
+    assert!(round_robin.views_led.values().all(|views| *views == 100));
+    assert_eq!(round_robin.longest_run, 1);
+
+    // Just as evenly when each leader leads two views in a row
+    let two_views = load::<TestConsecutiveLeaderTypes>();
+    assert!(two_views.views_led.values().all(|views| *views == 100));
+    assert_eq!(two_views.longest_run, 2);
+
+    // A randomized schedule reaches every leader, but unevenly
+    let randomized = load::<TestTypesRandomizedLeader>();
+    assert_eq!(randomized.total(), 1000);
+    assert!(randomized.min() > 0);
+    assert!(randomized.coefficient_of_variation() > 0.0);
+    assert!(randomized.max() > 100);
+
+    tracing::info!("Round robin: {round_robin}");
+    tracing::info!("Round robin, two views each: {two_views}");
+    tracing::info!("Randomized: {randomized}");
+}
+
+/// The workload every strategy is measured under: 10 nodes, one of which goes down at view 5
+fn workload<TYPES: NodeType>() -> TestDescription<TYPES, MemoryImpl, TestVersions> {
+    let mut metadata = TestDescription::default_multiple_rounds();
+    metadata.spinning_properties = SpinningTaskDescription {
+        node_changes: vec![(
+            5,
+            vec![ChangeNode {
+                idx: 9,
+                updown: NodeAction::Down,
+            }],
+        )],
+    };
+    // How many views fail depends on how often the strategy picks the node which is down
+    metadata.overall_safety_properties.num_failed_views = 20;
+
+    metadata
+}
+
+// Run the same workload under round robin and randomized leader selection and compare them.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_leader_schedule_comparison() {
+    hotshot::helpers::initialize_logging();
+
+    let round_robin = LeaderScheduleTaskDescription::new("round robin");
+    let round_robin_report = round_robin.report();
+    workload::<TestTypes>()
+        .gen_launcher_with_tasks(0, vec![Box::new(round_robin)])
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+
+    let randomized = LeaderScheduleTaskDescription::new("randomized");
+    let randomized_report = randomized.report();
+    workload::<TestTypesRandomizedLeader>()
+        .gen_launcher_with_tasks(0, vec![Box::new(randomized)])
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+
+    for report in [round_robin_report, randomized_report] {
+        let report = report
+            .read()
+            .await
+            .clone()
+            .expect("The leader schedule task did not report");
+        tracing::info!("{report}");
+
+        assert!(report.decide_latency(50).is_some());
+        assert_eq!(report.leader_load.views_led.len(), 10);
+        assert!(report.timeout_frequency() < 1.0);
+    }
+}
@@ -0,0 +1,38 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use hotshot::types::EventType;
+use hotshot_testing::quick_network;
+use hotshot_types::traits::block_contents::BlockHeader;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quick_network_decides() {
+    hotshot::helpers::initialize_logging();
+
+    let network = quick_network(4).await;
+    assert_eq!(network.len(), 4);
+
+    // Every node sees blocks being decided
+    for mut events in network.event_streams() {
+        tokio::time::timeout(Duration::from_secs(30), async {
+            while let Some(event) = events.next().await {
+                if let EventType::Decide { leaf_chain, .. } = event.event {
+                    if leaf_chain[0].leaf.block_header().block_number() >= 3 {
+                        return;
+                    }
+                }
+            }
+        })
+        .await
+        .expect("node did not decide a block in time");
+    }
+
+    network.shut_down().await;
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_testing::reference_model::DifferentialHarness;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reference_model_happy_path() {
+    hotshot::helpers::initialize_logging();
+
+    let mut harness = DifferentialHarness::new().await.unwrap();
+    let mut parent = 1;
+    for _ in 0..10 {
+        parent = harness.propose(parent).await.unwrap();
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reference_model_lost_proposals() {
+    hotshot::helpers::initialize_logging();
+
+    // 1 - 2 - 3 - (4) - 5 - 6 - 7 - 8, where the proposal for view 4 is lost and 5 extends 3:
+    // 6 decides nothing, since 3 and 5 are not consecutive, and 7 decides both 3 and 5
+    let mut harness = DifferentialHarness::new().await.unwrap();
+    let two = harness.propose(1).await.unwrap();
+    let three = harness.propose(two).await.unwrap();
+    harness.lose(three).await.unwrap();
+    let five = harness.propose(three).await.unwrap();
+    let six = harness.propose(five).await.unwrap();
+    let seven = harness.propose(six).await.unwrap();
+    harness.propose(seven).await.unwrap();
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reference_model_forks() {
+    hotshot::helpers::initialize_logging();
+
+    // Two competing branches off view 2, where the second overtakes the first: the locked view
+    // moves to the new branch, and the abandoned branch is never decided
+    let mut harness = DifferentialHarness::new().await.unwrap();
+    let two = harness.propose(1).await.unwrap();
+    let three = harness.propose(two).await.unwrap();
+    let four = harness.propose(two).await.unwrap();
+    harness.propose(three).await.unwrap();
+    let six = harness.propose(four).await.unwrap();
+    let seven = harness.propose(six).await.unwrap();
+    let eight = harness.propose(seven).await.unwrap();
+    harness.propose(eight).await.unwrap();
+}
@@ -0,0 +1,43 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    test_builder::TestDescription,
+    vid_distribution_task::VidDistributionTaskDescription,
+};
+
+// Check that every node receives its VID share for every view before deciding it.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_vid_share_distribution() {
+    hotshot::helpers::initialize_logging();
+
+    let metadata: TestDescription<TestTypes, MemoryImpl, TestVersions> = TestDescription {
+        // allow more time to pass in CI
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(60),
+            },
+        ),
+        ..TestDescription::default()
+    };
+    let vid_distribution = VidDistributionTaskDescription {
+        num_staked_nodes: metadata.num_nodes_with_stake,
+        // tolerate a single view without a share, so only systematic misses fail the test
+        max_missing_shares_per_node: 1,
+    };
+
+    metadata
+        .gen_launcher_with_tasks(0, vec![Box::new(vid_distribution)])
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
@@ -18,6 +18,7 @@ use hotshot_testing::{
         DoubleProposeVote,
     },
     completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    scenario::Scenario,
     test_builder::{Behaviour, TestDescription},
 };
 use hotshot_types::{
@@ -249,3 +250,19 @@ cross_tests!(
         metadata
     },
 );
+
+cross_tests!(
+    TestName: equivocating_leader_with_partition,
+    Impls: [PushCdnImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        Scenario::nodes(10)
+            .equivocate(3, 5)
+            .partition(8..=12, [7, 8])
+            .expect_decides(20)
+            .expect_no_safety_violation()
+            .build()
+    },
+);
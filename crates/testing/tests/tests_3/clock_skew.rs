@@ -0,0 +1,41 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, time::Duration};
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    test_builder::TestDescription,
+};
+
+// Some nodes run minutes ahead of or behind the others, so leaders produce timestamps which
+// jump back and forth, and the nodes disagree on when views start.
+cross_tests!(
+    TestName: test_clock_skew,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription::default_multiple_rounds();
+        metadata.clock_skews = HashMap::from([
+            (1, 120_000),
+            (2, -120_000),
+            (4, 1_500),
+            (7, -800),
+        ]);
+        metadata.completion_task_description =
+            CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(60),
+                },
+            );
+        metadata
+    },
+);
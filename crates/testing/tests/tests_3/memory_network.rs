@@ -5,7 +5,10 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 #![allow(clippy::panic)]
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use hotshot::{
     traits::{
@@ -27,7 +30,11 @@ use hotshot_types::{
     message::{DataMessage, Message, MessageKind, UpgradeLock},
     signature_key::{BLSPubKey, BuilderKey},
     traits::{
-        network::{BroadcastDelay, ConnectedNetwork, TestableNetworkingImplementation, Topic},
+        commitment_scheme::Keccak256Scheme,
+        network::{
+            BandwidthLimitedNetwork, BroadcastDelay, ConnectedNetwork, NetworkReliability,
+            TestableNetworkingImplementation, Topic,
+        },
         node_implementation::{ConsensusTime, NodeType},
     },
 };
@@ -65,6 +72,7 @@ impl NodeType for Test {
     type InstanceState = TestInstanceState;
     type Membership = StaticCommittee<Test>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = Keccak256Scheme;
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Hash, PartialEq, Eq)]
@@ -354,3 +362,51 @@ async fn memory_network_test_in_flight_message_count() {
         Some(0)
     );
 }
+
+// Check that messages queue up on a node's upload link
+
+#[tokio::test(flavor = "multi_thread")]
+#[instrument]
+async fn memory_network_bandwidth_limited() {
+    hotshot::helpers::initialize_logging();
+    // 1000 byte messages take 100ms to upload at 10kB/s
+    let reliability_config: Box<dyn NetworkReliability> = Box::new(BandwidthLimitedNetwork::new(
+        Some(10_000),
+        None,
+        None,
+    ));
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(&pub_key_1, &group, &[], Some(reliability_config.clone()));
+    let network2 = MemoryNetwork::new(
+        &pubkey(),
+        &group,
+        &[Topic::Global],
+        Some(reliability_config.clone()),
+    );
+    let network3 = MemoryNetwork::new(
+        &pubkey(),
+        &group,
+        &[Topic::Global],
+        Some(reliability_config),
+    );
+
+    let start = Instant::now();
+    network1
+        .broadcast_message(vec![0; 1000], Topic::Global, BroadcastDelay::None)
+        .await
+        .expect("Failed to broadcast message");
+
+    network2
+        .recv_message()
+        .await
+        .expect("Failed to receive message");
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // The second copy is only uploaded once the first one is
+    network3
+        .recv_message()
+        .await
+        .expect("Failed to receive message");
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
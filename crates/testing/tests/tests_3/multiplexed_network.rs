@@ -0,0 +1,108 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot::{
+    pool::SystemContextPool,
+    traits::implementations::{MasterMap, MemoryNetwork, SharedNetwork},
+    types::SignatureKey,
+};
+use hotshot_example_types::node_types::{
+    MemoryImpl, TestTypes, TestTypesRandomizedLeader, TestVersions,
+};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    signature_key::BLSPubKey,
+    traits::network::{BroadcastDelay, ConnectedNetwork, Topic},
+};
+use tokio::time::timeout;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shared_network_keeps_chains_apart() {
+    hotshot::helpers::initialize_logging();
+
+    let group = MasterMap::new();
+    let key_1 = BLSPubKey::generated_from_seed_indexed([0; 32], 1).0;
+    let key_2 = BLSPubKey::generated_from_seed_indexed([0; 32], 2).0;
+    let shared_1 = SharedNetwork::new(MemoryNetwork::new(&key_1, &group, &[Topic::Global], None));
+    let shared_2 = SharedNetwork::new(MemoryNetwork::new(&key_2, &group, &[Topic::Global], None));
+
+    let chain_a_1 = shared_1.chain(1).unwrap();
+    let chain_b_1 = shared_1.chain(2).unwrap();
+    let chain_a_2 = shared_2.chain(1).unwrap();
+    let chain_b_2 = shared_2.chain(2).unwrap();
+    assert!(shared_1.chain(1).is_err());
+    assert_eq!(shared_1.num_chains(), 2);
+
+    chain_a_1
+        .direct_message(b"chain a".to_vec(), key_2)
+        .await
+        .unwrap();
+    chain_b_1
+        .broadcast_message(b"chain b".to_vec(), Topic::Global, BroadcastDelay::None)
+        .await
+        .unwrap();
+
+    assert_eq!(chain_a_2.recv_message().await.unwrap(), b"chain a".to_vec());
+    assert_eq!(chain_b_2.recv_message().await.unwrap(), b"chain b".to_vec());
+    assert!(timeout(Duration::from_millis(500), chain_a_2.recv_message())
+        .await
+        .is_err());
+
+    // Shutting down one chain leaves the other connected
+    chain_a_2.shut_down().await;
+    assert!(chain_a_2.recv_message().await.is_err());
+    chain_b_1
+        .direct_message(b"still here".to_vec(), key_2)
+        .await
+        .unwrap();
+    assert_eq!(
+        chain_b_2.recv_message().await.unwrap(),
+        b"still here".to_vec()
+    );
+
+    shared_1.shut_down().await;
+    shared_2.shut_down().await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_system_context_pool() {
+    let handle_a = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let handle_b = build_system_handle::<TestTypesRandomizedLeader, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let duplicate = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+
+    let mut pool = SystemContextPool::new();
+    pool.insert(1, handle_a).unwrap();
+    pool.insert(2, handle_b).unwrap();
+    assert!(pool.insert(1, duplicate).is_err());
+    assert_eq!(pool.chain_ids().collect::<Vec<_>>(), vec![1, 2]);
+
+    assert!(pool.get::<TestTypes, MemoryImpl, TestVersions>(1).is_some());
+    assert!(pool
+        .get::<TestTypesRandomizedLeader, MemoryImpl, TestVersions>(2)
+        .is_some());
+    // The handle is only returned with the types of its chain
+    assert!(pool.get::<TestTypes, MemoryImpl, TestVersions>(2).is_none());
+    assert!(pool.get::<TestTypes, MemoryImpl, TestVersions>(3).is_none());
+
+    pool.start_all().await;
+
+    let mut removed = pool.remove(2).unwrap();
+    assert_eq!(pool.len(), 1);
+    removed.shut_down().await;
+
+    pool.shut_down_all().await;
+    assert!(pool.is_empty());
+}
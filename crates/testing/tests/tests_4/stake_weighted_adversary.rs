@@ -0,0 +1,51 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestVersions},
+    state_types::TestTypes,
+};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{block_builder::SimpleBuilderImplementation, scenario::Scenario};
+
+// Nodes 7 to 9 hold just under a third of the stake and withhold every vote. The honest nodes
+// hold just enough stake for a quorum between them, so every view still decides, and safely.
+cross_tests!(
+    TestName: adversary_stake_just_below_one_third,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        Scenario::nodes(10)
+            .stake_weighted_adversary([7, 8, 9], (333, 1000))
+            .expect_decides(10)
+            .expect_no_safety_violation()
+            .build()
+    },
+);
+
+// Nodes 7 to 9 hold just over a third of the stake and withhold every vote. The honest nodes are
+// short of a quorum, so views stop deciding and no decides are expected: withholding votes costs
+// liveness, not safety, which is all this checks. An adversary this strong which also voted for
+// conflicting proposals could break safety as well.
+cross_tests!(
+    TestName: adversary_stake_just_above_one_third,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        Scenario::nodes(10)
+            .stake_weighted_adversary([7, 8, 9], (334, 1000))
+            .expect_decides(0)
+            .expect_no_safety_violation()
+            .within(Duration::from_secs(20))
+            .build()
+    },
+);
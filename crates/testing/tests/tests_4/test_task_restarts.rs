@@ -0,0 +1,48 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot::tasks::RestartableTask;
+use hotshot_example_types::node_types::{Libp2pImpl, MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    spinning_task::TaskRestartChaos,
+    test_builder::TestDescription,
+};
+
+// Restart the DA, VID and view sync tasks of random nodes, and check that the network keeps
+// deciding.
+cross_tests!(
+    TestName: test_with_task_restarts,
+    Impls: [MemoryImpl, Libp2pImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription {
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            ..TestDescription::default()
+        };
+        metadata.task_restart_chaos = Some(TaskRestartChaos {
+            nodes: (0..metadata.num_nodes_with_stake).collect(),
+            tasks: vec![RestartableTask::Da, RestartableTask::Vid, RestartableTask::ViewSync],
+            views: 5..25,
+            num_restarts: 10,
+            seed: 0,
+        });
+        // A leader whose DA or VID task restarts may fail to propose
+        metadata.overall_safety_properties.num_failed_views = 5;
+        metadata.overall_safety_properties.num_successful_views = 25;
+        metadata
+    },
+);
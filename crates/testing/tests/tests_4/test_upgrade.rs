@@ -0,0 +1,112 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{CombinedImpl, MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::TestDescription,
+    upgrade_task::UpgradeTaskDescription,
+};
+use hotshot_types::constants::{
+    UPGRADE_BEGIN_OFFSET, UPGRADE_FINISH_OFFSET, UPGRADE_PROPOSE_OFFSET,
+};
+
+// Drive a full upgrade across the network, restarting nodes both after the upgrade certificate
+// is proposed and during the null block period before the new version activates, and check
+// that every node ends up on the new version.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upgrade_with_restarts() {
+    hotshot::helpers::initialize_logging();
+
+    let upgrade_view = 5;
+    let mut metadata: TestDescription<TestTypes, CombinedImpl, TestVersions> = TestDescription {
+        num_nodes_with_stake: 10,
+        start_nodes: 10,
+        num_bootstrap_nodes: 10,
+        da_staked_committee_size: 10,
+        upgrade_view: Some(upgrade_view),
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(180),
+            },
+        ),
+        ..TestDescription::default()
+    };
+
+    metadata.spinning_properties = SpinningTaskDescription {
+        node_changes: vec![
+            // Restart while the upgrade certificate is on its way to a decide
+            (
+                upgrade_view + UPGRADE_PROPOSE_OFFSET + 1,
+                vec![ChangeNode {
+                    idx: 2,
+                    updown: NodeAction::RestartDown(0),
+                }],
+            ),
+            // Restart during the null block period, coming back after the new version activates
+            (
+                upgrade_view + UPGRADE_BEGIN_OFFSET + 1,
+                vec![
+                    ChangeNode {
+                        idx: 5,
+                        updown: NodeAction::RestartDown(0),
+                    },
+                    ChangeNode {
+                        idx: 7,
+                        updown: NodeAction::RestartDown(UPGRADE_FINISH_OFFSET - UPGRADE_BEGIN_OFFSET),
+                    },
+                ],
+            ),
+        ],
+    };
+    metadata.view_sync_properties =
+        hotshot_testing::view_sync_task::ViewSyncTaskDescription::Threshold(0, 10);
+
+    // Keep deciding well past the activation view, so every node runs the new version
+    metadata.overall_safety_properties.num_successful_views =
+        usize::try_from(upgrade_view + UPGRADE_FINISH_OFFSET + 10).unwrap();
+    metadata.overall_safety_properties.num_failed_views = 15;
+
+    metadata
+        .gen_launcher_with_tasks(0, vec![Box::new(UpgradeTaskDescription)])
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
+
+// Drive a full upgrade across a healthy network and check that no view is wasted on the way: the
+// leaders of the views between versions propose the null block, which the replicas vote for.
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upgrade_wastes_no_views() {
+    hotshot::helpers::initialize_logging();
+
+    let upgrade_view = 5;
+    let mut metadata: TestDescription<TestTypes, MemoryImpl, TestVersions> = TestDescription {
+        upgrade_view: Some(upgrade_view),
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(120),
+            },
+        ),
+        ..TestDescription::default()
+    };
+
+    metadata.overall_safety_properties.num_successful_views =
+        usize::try_from(upgrade_view + UPGRADE_FINISH_OFFSET + 10).unwrap();
+    metadata.overall_safety_properties.num_failed_views = 0;
+
+    metadata
+        .gen_launcher_with_tasks(0, vec![Box::new(UpgradeTaskDescription)])
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
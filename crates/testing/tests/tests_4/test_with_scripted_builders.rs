@@ -0,0 +1,83 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, time::Duration};
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::{BuilderBehaviour, ScriptedBuilderImplementation},
+    test_builder::{BuilderChange, BuilderDescription, TestDescription},
+    txn_task::TxnTaskDescription,
+};
+
+// A builder whose fee signatures never verify is skipped in favour of the honest one
+cross_tests!(
+    TestName: test_with_invalid_builder_fee_signatures,
+    Impls: [MemoryImpl],
+    BuilderImpls: [ScriptedBuilderImplementation],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription::default_multiple_rounds();
+        // Every block should contain at least one transaction, from the honest builder
+        metadata.overall_safety_properties.transaction_threshold = 1;
+        metadata.txn_description = TxnTaskDescription::RoundRobinTimeBased(Duration::from_millis(1));
+
+        metadata.builders = vec1::vec1![
+            BuilderDescription {
+                changes: HashMap::from([(0, BuilderChange::Behave(BuilderBehaviour::InvalidFeeSignature))]),
+            },
+            BuilderDescription::default(),
+        ];
+        metadata
+    }
+);
+
+// Two builders which are each either unavailable or too slow in turn, but never at the same time
+cross_tests!(
+    TestName: test_with_intermittent_builders,
+    Impls: [MemoryImpl],
+    BuilderImpls: [ScriptedBuilderImplementation],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription::default_multiple_rounds();
+        metadata.overall_safety_properties.transaction_threshold = 1;
+        metadata.txn_description = TxnTaskDescription::RoundRobinTimeBased(Duration::from_millis(1));
+        let too_slow = metadata.timing_data.builder_timeout * 2;
+
+        // view 1st          2nd
+        // 0    Unavailable  Honest
+        // 1    Honest       Honest
+        // 2    Honest       Slow
+        // 3    Honest       Honest
+        // ...
+        let views = 0..metadata.overall_safety_properties.num_successful_views as u64 * 2;
+        let first_builder = views.clone().filter_map(|view_num| match view_num % 4 {
+            0 => Some((view_num, BuilderChange::Behave(BuilderBehaviour::Unavailable))),
+            1 => Some((view_num, BuilderChange::Behave(BuilderBehaviour::Honest))),
+            _ => None,
+        }).collect();
+        let second_builder = views.filter_map(|view_num| match view_num % 4 {
+            2 => Some((view_num, BuilderChange::Behave(BuilderBehaviour::Slow(too_slow)))),
+            3 => Some((view_num, BuilderChange::Behave(BuilderBehaviour::Honest))),
+            _ => None,
+        }).collect();
+
+        metadata.builders = vec1::vec1![
+            BuilderDescription {
+                changes: first_builder,
+            },
+            BuilderDescription {
+                changes: second_builder,
+            },
+        ];
+        metadata
+    }
+);
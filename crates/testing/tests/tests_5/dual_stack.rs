@@ -0,0 +1,133 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::future::join_all;
+use hotshot::traits::implementations::{GossipConfig, Libp2pNetwork, PushCdnNetwork};
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    signature_key::BLSPubKey,
+    traits::{
+        network::{BroadcastDelay, ConnectedNetwork, Topic},
+        signature_key::SignatureKey,
+    },
+};
+use tokio::time::{sleep, timeout};
+
+/// Number of nodes in each network
+const NUM_NODES: usize = 5;
+
+/// The longest a message may take to arrive
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Wait until `network` receives `message`, ignoring any other messages
+async fn receive<N: ConnectedNetwork<BLSPubKey>>(network: &N, message: &[u8]) {
+    timeout(MESSAGE_TIMEOUT, async {
+        while network.recv_message().await.unwrap() != message {}
+    })
+    .await
+    .expect("message did not arrive");
+}
+
+/// Start `NUM_NODES` libp2p nodes listening on each of `loopback_ips`
+async fn libp2p_networks(loopback_ips: Vec<IpAddr>) -> Vec<Arc<Libp2pNetwork<TestTypes>>> {
+    let generator = Libp2pNetwork::<TestTypes>::generator_with_config(
+        NUM_NODES,
+        NUM_NODES,
+        NUM_NODES,
+        None,
+        GossipConfig::default(),
+        loopback_ips,
+    );
+
+    let mut networks = Vec::new();
+    for node_id in 0..NUM_NODES as u64 {
+        networks.push(generator(node_id).await);
+    }
+    join_all(networks.iter().map(|network| network.wait_for_ready())).await;
+
+    // Give the gossip mesh a few heartbeats to form after the initial delay
+    sleep(Duration::from_secs(8)).await;
+
+    networks
+}
+
+/// Check that a broadcast from the first node and a direct message to it arrive
+async fn check_libp2p_messages(networks: &[Arc<Libp2pNetwork<TestTypes>>]) {
+    let broadcast = b"broadcast over libp2p".to_vec();
+    networks[0]
+        .broadcast_message(broadcast.clone(), Topic::Global, BroadcastDelay::None)
+        .await
+        .unwrap();
+    join_all(
+        networks[1..]
+            .iter()
+            .map(|network| receive(&**network, &broadcast)),
+    )
+    .await;
+
+    let direct = b"direct message over libp2p".to_vec();
+    let recipient = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0).0;
+    networks[1]
+        .direct_message(direct.clone(), recipient)
+        .await
+        .unwrap();
+    receive(&*networks[0], &direct).await;
+
+    for network in networks {
+        network.shut_down().await;
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_libp2p_ipv6_only() {
+    hotshot::helpers::initialize_logging();
+
+    let networks = libp2p_networks(vec![Ipv6Addr::LOCALHOST.into()]).await;
+    check_libp2p_messages(&networks).await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_libp2p_dual_stack() {
+    hotshot::helpers::initialize_logging();
+
+    let networks =
+        libp2p_networks(vec![Ipv4Addr::LOCALHOST.into(), Ipv6Addr::LOCALHOST.into()]).await;
+    check_libp2p_messages(&networks).await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_push_cdn_ipv6_only() {
+    hotshot::helpers::initialize_logging();
+
+    let generator =
+        PushCdnNetwork::<BLSPubKey>::generator_with_loopback(NUM_NODES, Ipv6Addr::LOCALHOST.into());
+    let mut networks = Vec::new();
+    for node_id in 0..NUM_NODES as u64 {
+        networks.push(generator(node_id).await);
+    }
+    join_all(networks.iter().map(|network| network.wait_for_ready())).await;
+
+    let broadcast = b"broadcast over the push CDN".to_vec();
+    networks[0]
+        .broadcast_message(broadcast.clone(), Topic::Global, BroadcastDelay::None)
+        .await
+        .unwrap();
+    join_all(
+        networks[1..]
+            .iter()
+            .map(|network| receive(&**network, &broadcast)),
+    )
+    .await;
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use hotshot::traits::implementations::{GossipConfig, Libp2pNetwork};
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    network::GossipPreset,
+    traits::network::{BroadcastDelay, ConnectedNetwork, Topic},
+};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use tokio::time::{sleep, timeout};
+
+/// Number of nodes in each network
+const NUM_NODES: usize = 10;
+
+/// Number of proposals broadcast on each network
+const NUM_PROPOSALS: usize = 5;
+
+/// Size of a proposal, which is large enough to take several gossip hops to matter
+const PROPOSAL_SIZE: usize = 512 * 1024;
+
+/// The longest a proposal may take to reach every node
+const MAX_PROPAGATION_LATENCY: Duration = Duration::from_secs(2);
+
+/// Broadcast proposals from one node of a network tuned with `preset`, returning how long each
+/// took to reach every other node
+async fn proposal_propagation_latencies(preset: GossipPreset) -> Vec<Duration> {
+    let tuning = preset.tuning();
+    let generator = Libp2pNetwork::<TestTypes>::generator_with_config(
+        NUM_NODES,
+        NUM_NODES,
+        NUM_NODES,
+        None,
+        GossipConfig::from(tuning),
+        vec![Ipv4Addr::LOCALHOST.into()],
+    );
+
+    let mut networks = Vec::new();
+    for node_id in 0..NUM_NODES as u64 {
+        networks.push(generator(node_id).await);
+    }
+    join_all(networks.iter().map(|network| network.wait_for_ready())).await;
+
+    // Give the meshes a few heartbeats to form after the initial delay
+    sleep(Duration::from_secs(5) + 4 * tuning.heartbeat_interval).await;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut latencies = Vec::new();
+    for _ in 0..NUM_PROPOSALS {
+        // Random contents, so that proposals neither compress nor share a gossip message ID
+        let mut proposal = vec![0u8; PROPOSAL_SIZE];
+        rng.fill_bytes(&mut proposal);
+
+        let start = Instant::now();
+        let received = join_all(networks[1..].iter().map(|network| async {
+            loop {
+                let message = network.recv_message().await.unwrap();
+                if message == proposal {
+                    return start.elapsed();
+                }
+            }
+        }));
+        let (sent, received) = futures::join!(
+            networks[0].broadcast_message(proposal.clone(), Topic::Global, BroadcastDelay::None),
+            timeout(10 * MAX_PROPAGATION_LATENCY, received),
+        );
+        sent.unwrap();
+        let received = received
+            .unwrap_or_else(|_| panic!("{preset:?}: proposal did not reach every node"));
+
+        latencies.push(received.into_iter().max().unwrap());
+    }
+
+    for network in &networks {
+        network.shut_down().await;
+    }
+
+    latencies
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_gossip_preset_proposal_latency() {
+    hotshot::helpers::initialize_logging();
+
+    for preset in [GossipPreset::Small, GossipPreset::Medium, GossipPreset::Large] {
+        let latencies = proposal_propagation_latencies(preset).await;
+        let worst = latencies.iter().max().unwrap();
+        tracing::info!("{preset:?} preset: proposal propagation latencies {latencies:?}");
+
+        assert!(
+            *worst <= MAX_PROPAGATION_LATENCY,
+            "{preset:?} preset took {worst:?} to propagate a proposal"
+        );
+    }
+}
@@ -14,7 +14,8 @@ use hotshot_testing::{
     test_builder::{TestDescription, TimingData},
 };
 use hotshot_types::traits::network::{
-    AsynchronousNetwork, ChaosNetwork, PartiallySynchronousNetwork, SynchronousNetwork,
+    AsynchronousNetwork, BandwidthLimitedNetwork, ChaosNetwork, PartiallySynchronousNetwork,
+    SynchronousNetwork,
 };
 use tracing::instrument;
 
@@ -292,6 +293,41 @@ async fn test_memory_network_chaos() {
         .await;
 }
 
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_memory_network_bandwidth_limited() {
+    use std::time::Duration;
+
+    use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
+    use hotshot_testing::{
+        completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+        test_builder::TestDescription,
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    let metadata: TestDescription<TestTypes, MemoryImpl, TestVersions> = TestDescription {
+        // allow more time to pass in CI
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(240),
+            },
+        ),
+        // 1MB/s per node, with every node sharing a 4MB/s link
+        unreliable_network: Some(Box::new(BandwidthLimitedNetwork::new(
+            Some(1_000_000),
+            Some(1_000_000),
+            Some(4_000_000),
+        ))),
+        ..TestDescription::default()
+    };
+    metadata
+        .gen_launcher(0)
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
+
 #[tokio::test(flavor = "multi_thread")]
 #[ignore]
 #[instrument]
@@ -0,0 +1,172 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Alerts on conditions which need the attention of the node's operator.
+//!
+//! Operators register [`AlertSink`]s on the `SystemContext` to be handed every [`Alert`] as a
+//! structured callback, so that they can route them to a paging system instead of scraping logs.
+//! Alerts are raised for safety violations, repeated timeouts, storage failures and peer bans. The
+//! [`AlertDetector`] derives all but bans from the node's events; bans are raised by the
+//! [`PeerScoreboard`](crate::peer_score::PeerScoreboard) as it makes them.
+
+use std::{fmt::Debug, marker::PhantomData, sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use async_trait::async_trait;
+
+use crate::{error::ConsensusError, event::EventType, traits::node_implementation::NodeType};
+
+/// A condition which needs the attention of the node's operator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Alert<TYPES: NodeType> {
+    /// A safety assertion failed: the consensus state is internally inconsistent
+    SafetyViolation {
+        /// What was found to be inconsistent
+        reason: String,
+    },
+    /// Views keep timing out without a decide in between
+    RepeatedTimeouts {
+        /// The last view which timed out
+        view: TYPES::View,
+        /// Number of views which timed out since the last decide
+        consecutive: u64,
+    },
+    /// Writing to storage failed
+    StorageFailure {
+        /// Why it failed
+        reason: String,
+    },
+    /// A peer was banned for misbehaving
+    PeerBanned {
+        /// The banned peer
+        peer: TYPES::SignatureKey,
+        /// How long the ban lasts
+        cooldown: Duration,
+        /// Number of times the peer has been banned, including this ban
+        bans: u64,
+    },
+}
+
+impl<TYPES: NodeType> Alert<TYPES> {
+    /// Short name of the kind of alert, for routing alerts
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::SafetyViolation { .. } => "safety_violation",
+            Self::RepeatedTimeouts { .. } => "repeated_timeouts",
+            Self::StorageFailure { .. } => "storage_failure",
+            Self::PeerBanned { .. } => "peer_banned",
+        }
+    }
+}
+
+/// Receives the alerts of a node
+#[async_trait]
+pub trait AlertSink<TYPES: NodeType>: Send + Sync + 'static {
+    /// Handle an alert.
+    ///
+    /// Sinks are called one after the other, by the task which raised the alert, so a sink should
+    /// hand the alert off rather than wait on a remote system.
+    async fn alert(&self, alert: &Alert<TYPES>);
+}
+
+/// The alert sinks registered on a node, shared by everything which raises alerts
+pub struct AlertSinks<TYPES: NodeType> {
+    /// The registered sinks, in order of registration
+    sinks: Arc<RwLock<Vec<Arc<dyn AlertSink<TYPES>>>>>,
+}
+
+impl<TYPES: NodeType> Clone for AlertSinks<TYPES> {
+    fn clone(&self) -> Self {
+        Self {
+            sinks: Arc::clone(&self.sinks),
+        }
+    }
+}
+
+impl<TYPES: NodeType> Default for AlertSinks<TYPES> {
+    fn default() -> Self {
+        Self {
+            sinks: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+}
+
+impl<TYPES: NodeType> Debug for AlertSinks<TYPES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlertSinks").finish_non_exhaustive()
+    }
+}
+
+impl<TYPES: NodeType> AlertSinks<TYPES> {
+    /// Register `sink` to receive every alert raised from now on
+    pub async fn add(&self, sink: Arc<dyn AlertSink<TYPES>>) {
+        self.sinks.write().await.push(sink);
+    }
+
+    /// Hand `alert` to every registered sink
+    pub async fn raise(&self, alert: Alert<TYPES>) {
+        tracing::error!("Raising {} alert: {:?}", alert.kind(), alert);
+        // Sinks may be registered while others handle the alert
+        let sinks = self.sinks.read().await.clone();
+        for sink in sinks {
+            sink.alert(&alert).await;
+        }
+    }
+}
+
+/// Derives alerts from the events of a node
+#[derive(Clone, Debug)]
+pub struct AlertDetector<TYPES: NodeType> {
+    /// Number of consecutive timeouts which raise an alert, or zero to not alert on timeouts
+    timeout_threshold: u64,
+    /// Number of views which timed out since the last decide
+    consecutive_timeouts: u64,
+    /// Phantom for the node type
+    _pd: PhantomData<TYPES>,
+}
+
+impl<TYPES: NodeType> AlertDetector<TYPES> {
+    /// A detector which alerts after every `timeout_threshold` views in a row time out
+    #[must_use]
+    pub fn new(timeout_threshold: u64) -> Self {
+        Self {
+            timeout_threshold,
+            consecutive_timeouts: 0,
+            _pd: PhantomData,
+        }
+    }
+
+    /// The alert `event` raises, if any
+    pub fn observe(&mut self, event: &EventType<TYPES>) -> Option<Alert<TYPES>> {
+        match event {
+            EventType::Decide { .. } => {
+                self.consecutive_timeouts = 0;
+                None
+            }
+            EventType::ViewTimeout { view_number } => {
+                self.consecutive_timeouts += 1;
+                (self.timeout_threshold > 0
+                    && self.consecutive_timeouts % self.timeout_threshold == 0)
+                    .then(|| Alert::RepeatedTimeouts {
+                        view: *view_number,
+                        consecutive: self.consecutive_timeouts,
+                    })
+            }
+            EventType::ConsensusError {
+                error: ConsensusError::InconsistentState(reason),
+            } => Some(Alert::SafetyViolation {
+                reason: reason.clone(),
+            }),
+            EventType::ConsensusError {
+                error: ConsensusError::StorageFailure(reason),
+            } => Some(Alert::StorageFailure {
+                reason: reason.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Anti-entropy exchange of certificates.
+//!
+//! Certificates normally reach every node by broadcast, but a node that misses one may stall
+//! until it times out. Every so often each node sends a [`CertificateDigest`] of the views of
+//! its highest certificates to a random peer, which answers with a [`CertificateSync`] holding
+//! any certificates newer than those in the digest.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    simple_certificate::{DaCertificate2, QuorumCertificate2, TimeoutCertificate2},
+    traits::node_implementation::NodeType,
+    vote::HasViewNumber,
+};
+
+/// The views of the highest certificates a node holds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct CertificateDigest<TYPES: NodeType> {
+    /// The view the sender is in
+    pub view: TYPES::View,
+    /// View of the sender's high QC
+    pub high_qc: TYPES::View,
+    /// View of the highest timeout certificate the sender has seen, if any
+    pub timeout_cert: Option<TYPES::View>,
+    /// View of the highest DA certificate the sender holds, if any
+    pub da_cert: Option<TYPES::View>,
+}
+
+/// Certificates newer than those of a peer's [`CertificateDigest`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct CertificateSync<TYPES: NodeType> {
+    /// The view the sender is in
+    pub view: TYPES::View,
+    /// The sender's high QC, if newer than the peer's
+    pub high_qc: Option<QuorumCertificate2<TYPES>>,
+    /// The highest timeout certificate the sender has seen, if newer than the peer's
+    pub timeout_cert: Option<TimeoutCertificate2<TYPES>>,
+    /// The highest DA certificate the sender holds, if newer than the peer's
+    pub da_cert: Option<DaCertificate2<TYPES>>,
+}
+
+impl<TYPES: NodeType> CertificateSync<TYPES> {
+    /// The certificates among `high_qc`, `timeout_cert` and `da_cert` which are newer than those
+    /// of `digest`, sent from `view`
+    #[must_use]
+    pub fn newer_than(
+        digest: &CertificateDigest<TYPES>,
+        view: TYPES::View,
+        high_qc: &QuorumCertificate2<TYPES>,
+        timeout_cert: Option<&TimeoutCertificate2<TYPES>>,
+        da_cert: Option<&DaCertificate2<TYPES>>,
+    ) -> Self {
+        Self {
+            view,
+            high_qc: (high_qc.view_number() > digest.high_qc).then(|| high_qc.clone()),
+            timeout_cert: timeout_cert
+                .filter(|cert| Some(cert.view_number()) > digest.timeout_cert)
+                .cloned(),
+            da_cert: da_cert
+                .filter(|cert| Some(cert.view_number()) > digest.da_cert)
+                .cloned(),
+        }
+    }
+
+    /// Whether there are no certificates to send
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.high_qc.is_none() && self.timeout_cert.is_none() && self.da_cert.is_none()
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Catchup of the decided leaves a node missed.
+//!
+//! A node which restarts, joins late, or falls too far behind the view it is in asks a peer for
+//! the leaves decided after its own decided leaf with a [`CatchupRequest`] for a range of views.
+//! The peer answers with a [`CatchupPage`]: at most a page of its decided leaves in chain order,
+//! the [`DecideProof`] which shows the last of them is decided, and the cursor from which to
+//! request the next page. The first leaf of each page extends the last leaf the requester already
+//! has, and every leaf is certified by the QC of the next, so the requester checks with
+//! [`CatchupPage::check`] that the page continues its chain. The last leaf is decided by the same
+//! rule consensus decides by: it is extended by a leaf in the next view which is itself certified.
+//! Its ancestors on the page are then decided too. A peer can withhold leaves, but cannot forge
+//! them, nor pass off leaves which are certified but not decided.
+//!
+//! Pages travel as [`DataMessage`](crate::message::DataMessage)s over the same network as
+//! consensus messages.
+//!
+//! Serving catchup must not starve live consensus, so each node serves at most a few pages per
+//! second, to each peer and to all of them together, as its [`CatchupRateLimiter`] allows.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use committable::Committable;
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    data::Leaf2, simple_certificate::QuorumCertificate2, traits::node_implementation::NodeType,
+    vote::HasViewNumber,
+};
+
+/// When a node catches up, how large the pages it is sent are, and how many it serves
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CatchupConfig {
+    /// Number of views our last decided view may fall behind the view we are in before we catch
+    /// up on the leaves in between from a peer
+    pub max_lag: u64,
+    /// Most leaves sent in one page
+    pub page_size: u64,
+    /// How long we wait for a page before asking another peer for it
+    pub request_timeout: Duration,
+    /// Most pages we serve per second, to all peers together
+    pub max_pages_per_second: u64,
+    /// Most pages we serve a single peer per second
+    pub max_pages_per_peer_per_second: u64,
+    /// Number of QCs of a page each parallel verification task checks the signatures of
+    pub verify_batch_size: u64,
+}
+
+impl Default for CatchupConfig {
+    fn default() -> Self {
+        Self {
+            max_lag: 20,
+            page_size: 50,
+            request_timeout: Duration::from_secs(2),
+            max_pages_per_second: 20,
+            max_pages_per_peer_per_second: 5,
+            verify_batch_size: 16,
+        }
+    }
+}
+
+/// A request for the decided leaves in a range of views
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct CatchupRequest<TYPES: NodeType> {
+    /// The view the sender is in
+    pub view: TYPES::View,
+    /// First view of the range
+    pub start: TYPES::View,
+    /// Last view of the range
+    pub end: TYPES::View,
+    /// Height of the first leaf to send: one more than that of the last leaf the sender has
+    pub cursor: u64,
+}
+
+/// Proof that a leaf is decided: a leaf which extends it in the next view, and a QC for that leaf
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct DecideProof<TYPES: NodeType> {
+    /// The leaf which extends the decided leaf in the view after it, with a QC for it
+    pub child: Leaf2<TYPES>,
+    /// The QC which certifies `child`
+    pub qc: QuorumCertificate2<TYPES>,
+}
+
+impl<TYPES: NodeType> DecideProof<TYPES> {
+    /// Check that this proves `leaf` decided: the child extends it in the next view with a QC for
+    /// it, and the proof's QC is for the child. The signatures of the QCs are not checked.
+    ///
+    /// # Errors
+    /// If the proof is not for `leaf`, or the child is not in the view after it
+    pub fn check(&self, leaf: &Leaf2<TYPES>) -> Result<()> {
+        let child = &self.child;
+        ensure!(
+            child.parent_commitment() == leaf.commit() && child.height() == leaf.height() + 1,
+            "The leaf for view {} does not extend the leaf for view {}",
+            child.view_number(),
+            leaf.view_number()
+        );
+        ensure!(
+            child.view_number() == leaf.view_number() + 1,
+            "The leaf for view {} is not in the view after the leaf for view {} it extends",
+            child.view_number(),
+            leaf.view_number()
+        );
+        let justify_qc = child.justify_qc();
+        ensure!(
+            justify_qc.view_number() == leaf.view_number()
+                && justify_qc.data.leaf_commit == leaf.commit(),
+            "The QC of the leaf for view {} does not certify its parent",
+            child.view_number()
+        );
+        ensure!(
+            self.qc.view_number() == child.view_number()
+                && self.qc.data.leaf_commit == child.commit(),
+            "The QC of the decide proof does not certify the leaf for view {}",
+            child.view_number()
+        );
+
+        Ok(())
+    }
+
+    /// The QCs of the proof, whose signatures are left to check
+    #[must_use]
+    pub fn qcs(&self) -> [QuorumCertificate2<TYPES>; 2] {
+        [self.child.justify_qc(), self.qc.clone()]
+    }
+}
+
+/// A page of decided leaves answering a [`CatchupRequest`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct CatchupPage<TYPES: NodeType> {
+    /// The view the sender is in
+    pub view: TYPES::View,
+    /// The request this page answers
+    pub request: CatchupRequest<TYPES>,
+    /// Decided leaves of the requested range from the cursor on, in chain order
+    pub leaves: Vec<Leaf2<TYPES>>,
+    /// The proof that the last leaf is decided, if there are leaves
+    pub decide_proof: Option<DecideProof<TYPES>>,
+    /// Cursor of the next page, if the range has leaves left
+    pub next_cursor: Option<u64>,
+}
+
+impl<TYPES: NodeType> CatchupPage<TYPES> {
+    /// The page answering `request` from `view`, out of `decided`: the decided leaves from the
+    /// request's cursor on, in order of height.
+    ///
+    /// The page ends with the newest leaf within the page size shown to be decided. A decided
+    /// leaf followed in the next view by two more decided leaves is proven by them; for the last
+    /// decided leaves, which are not, the proof is looked up with `decide_proof`. Leaves after
+    /// the last one proven are left to the next page.
+    #[must_use]
+    pub fn answer(
+        request: CatchupRequest<TYPES>,
+        view: TYPES::View,
+        decided: Vec<Leaf2<TYPES>>,
+        page_size: u64,
+        decide_proof: impl Fn(&Leaf2<TYPES>) -> Option<DecideProof<TYPES>>,
+    ) -> Self {
+        let page_size = usize::try_from(page_size).unwrap_or(usize::MAX);
+        let decided: Vec<_> = decided
+            .into_iter()
+            .filter(|leaf| leaf.height() >= request.cursor && leaf.view_number() >= request.start)
+            .collect();
+        let in_range = decided
+            .iter()
+            .take_while(|leaf| leaf.view_number() <= request.end)
+            .count();
+
+        let proven = (0..in_range.min(page_size)).rev().find_map(|i| {
+            let proof = match (decided.get(i + 1), decided.get(i + 2)) {
+                (Some(child), Some(grandchild)) => {
+                    (child.view_number() == decided[i].view_number() + 1).then(|| DecideProof {
+                        child: child.clone(),
+                        qc: grandchild.justify_qc(),
+                    })
+                }
+                _ => decide_proof(&decided[i]),
+            };
+            proof.map(|proof| (i, proof))
+        });
+
+        let Some((last, proof)) = proven else {
+            return Self {
+                view,
+                request,
+                leaves: Vec::new(),
+                decide_proof: None,
+                next_cursor: None,
+            };
+        };
+        let next_cursor = (last + 1 < in_range).then(|| decided[last + 1].height());
+        let mut leaves = decided;
+        leaves.truncate(last + 1);
+
+        Self {
+            view,
+            request,
+            leaves,
+            decide_proof: Some(proof),
+            next_cursor,
+        }
+    }
+
+    /// Check that this page answers `request` and continues the chain which ends in `parent`,
+    /// the last leaf we have: its leaves are in the requested range, the first extends `parent`,
+    /// each extends the one before it with a QC for it, and the page's decide proof is for the
+    /// last leaf. The signatures of the QCs are not checked.
+    ///
+    /// # Errors
+    /// If the page does not answer `request`, does not continue the chain from `parent`, or does
+    /// not prove its last leaf decided
+    pub fn check(&self, request: &CatchupRequest<TYPES>, parent: &Leaf2<TYPES>) -> Result<()> {
+        ensure!(
+            self.request == *request,
+            "The page answers a request other than ours"
+        );
+        ensure!(
+            self.leaves.is_empty() == self.decide_proof.is_none(),
+            "The page must have a decide proof exactly if it has leaves"
+        );
+
+        let mut parent = parent;
+        for leaf in &self.leaves {
+            ensure!(
+                leaf.view_number() >= request.start && leaf.view_number() <= request.end,
+                "The leaf for view {} is not in the requested range",
+                leaf.view_number()
+            );
+            ensure!(
+                leaf.parent_commitment() == parent.commit() && leaf.height() == parent.height() + 1,
+                "The leaf for view {} does not extend the leaf for view {}",
+                leaf.view_number(),
+                parent.view_number()
+            );
+            let justify_qc = leaf.justify_qc();
+            ensure!(
+                justify_qc.view_number() == parent.view_number()
+                    && justify_qc.data.leaf_commit == parent.commit(),
+                "The QC of the leaf for view {} does not certify its parent",
+                leaf.view_number()
+            );
+            parent = leaf;
+        }
+
+        if let Some(proof) = &self.decide_proof {
+            proof.check(parent)?;
+        }
+        if let Some(next_cursor) = self.next_cursor {
+            ensure!(
+                next_cursor == parent.height() + 1,
+                "The next cursor {next_cursor} does not follow the last leaf of the page"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of pages served in the current one second window
+#[derive(Clone, Copy, Debug)]
+struct RateWindow {
+    /// When the window started
+    start: Instant,
+    /// Pages served in the window
+    pages: u64,
+}
+
+impl RateWindow {
+    /// Count a page served at `now` against `limit`, returning whether it is within the limit
+    fn admit(&mut self, now: Instant, limit: u64) -> bool {
+        if now.duration_since(self.start) >= Duration::from_secs(1) {
+            *self = Self {
+                start: now,
+                pages: 0,
+            };
+        }
+        if self.pages >= limit {
+            return false;
+        }
+        self.pages += 1;
+
+        true
+    }
+}
+
+/// Limits on the catchup pages we serve, to each peer and to all of them together
+#[derive(Clone, Debug)]
+pub struct CatchupRateLimiter<KEY> {
+    /// Most pages we serve per second, to all peers together
+    max_pages_per_second: u64,
+    /// Most pages we serve a single peer per second
+    max_pages_per_peer_per_second: u64,
+    /// Pages served to all peers in the current window
+    total: Option<RateWindow>,
+    /// Pages served to each peer in its current window
+    peers: HashMap<KEY, RateWindow>,
+}
+
+impl<KEY: Clone + Eq + Hash> CatchupRateLimiter<KEY> {
+    /// A rate limiter with the limits of `config`
+    #[must_use]
+    pub fn new(config: &CatchupConfig) -> Self {
+        Self {
+            max_pages_per_second: config.max_pages_per_second,
+            max_pages_per_peer_per_second: config.max_pages_per_peer_per_second,
+            total: None,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Whether to serve `peer` a page at `now`, counting it if so. A page refused to a peer over
+    /// its own limit doesn't count against the limit of all peers.
+    pub fn admit(&mut self, peer: &KEY, now: Instant) -> bool {
+        let window = RateWindow {
+            start: now,
+            pages: 0,
+        };
+        let peer_window = self.peers.entry(peer.clone()).or_insert(window);
+        let mut peer_counted = *peer_window;
+        if !peer_counted.admit(now, self.max_pages_per_peer_per_second) {
+            return false;
+        }
+        if !self
+            .total
+            .get_or_insert(window)
+            .admit(now, self.max_pages_per_second)
+        {
+            return false;
+        }
+        *peer_window = peer_counted;
+
+        // Forget the peers whose windows have ended, so that the map doesn't grow without bound
+        self.peers
+            .retain(|_, window| now.duration_since(window.start) < Duration::from_secs(1));
+
+        true
+    }
+}
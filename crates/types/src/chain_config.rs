@@ -0,0 +1,105 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Chain configuration which affects the validity of blocks.
+//!
+//! Nodes that disagree on these parameters accept different blocks, and therefore fail to reach
+//! quorum without an obvious cause. Leaders commit to their [`ChainConfig`] in the headers they
+//! build, so that replicas detect a mismatch when validating the proposal. Timeouts are not
+//! included, since they only affect liveness and may differ between nodes.
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fee_market::BaseFeeParams,
+    traits::{node_implementation::NodeType, states::InstanceState},
+};
+
+/// Configuration parameters every node of a chain must agree on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// Number of blocks in an epoch, zero means there are no epochs
+    pub epoch_height: u64,
+    /// Maximum size of a block, see
+    /// [`InstanceState::max_block_size`](crate::traits::states::InstanceState::max_block_size)
+    pub max_block_size: Option<u64>,
+    /// Parameters of the base fee, if the chain has a fee market
+    pub base_fee_params: Option<BaseFeeParams>,
+}
+
+impl ChainConfig {
+    /// The chain configuration of a node with the given instance state
+    #[must_use]
+    pub fn new<TYPES: NodeType>(instance_state: &TYPES::InstanceState) -> Self {
+        Self {
+            epoch_height: TYPES::EPOCH_HEIGHT,
+            max_block_size: instance_state.max_block_size(),
+            base_fee_params: instance_state.base_fee_params(),
+        }
+    }
+}
+
+impl Committable for ChainConfig {
+    fn commit(&self) -> Commitment<Self> {
+        let builder = RawCommitmentBuilder::new("Chain Config")
+            .u64_field("epoch height", self.epoch_height)
+            .u64_field(
+                "has max block size",
+                u64::from(self.max_block_size.is_some()),
+            )
+            .u64_field("max block size", self.max_block_size.unwrap_or_default());
+
+        match self.base_fee_params {
+            Some(params) => builder
+                .u64_field("has base fee", 1)
+                .u64_field("initial base fee", params.initial_base_fee)
+                .u64_field("min base fee", params.min_base_fee)
+                .u64_field("target usage", params.target_usage)
+                .u64_field("max change denominator", params.max_change_denominator),
+            None => builder.u64_field("has base fee", 0),
+        }
+        .finalize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commitment_covers_every_parameter() {
+        let config = ChainConfig {
+            epoch_height: 10,
+            max_block_size: None,
+            base_fee_params: None,
+        };
+        let with_max_block_size = ChainConfig {
+            max_block_size: Some(0),
+            ..config
+        };
+        let with_base_fee = ChainConfig {
+            base_fee_params: Some(BaseFeeParams {
+                initial_base_fee: 1,
+                min_base_fee: 1,
+                target_usage: 1,
+                max_change_denominator: 8,
+            }),
+            ..config
+        };
+
+        assert_ne!(config.commit(), with_max_block_size.commit());
+        assert_ne!(config.commit(), with_base_fee.commit());
+        assert_ne!(
+            config.commit(),
+            ChainConfig {
+                epoch_height: 0,
+                ..config
+            }
+            .commit()
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Capacity and occupancy of the channels between tasks.
+//!
+//! The channels are bounded, and once one fills up every task sending on it stalls. Their
+//! capacities are set by [`ChannelCapacities`], and a [`ChannelDepth`] follows how many events a
+//! channel holds each time it is sampled, remembering the most it ever held and flagging when it
+//! stays close to full for too long.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::constants::{
+    EVENT_CHANNEL_SIZE, EXTERNAL_EVENT_CHANNEL_SIZE, MAX_CHANNEL_CAPACITY,
+    NETWORK_INBOUND_CHANNEL_SIZE,
+};
+
+/// Number of messages each bounded channel holds before its senders wait
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelCapacities {
+    /// The event stream the consensus tasks share
+    pub internal_events: usize,
+    /// The event stream from HotShot to the application
+    pub output_events: usize,
+    /// The queue of messages received from the network, waiting to be handled by the network
+    /// task
+    pub network_inbound: usize,
+}
+
+impl Default for ChannelCapacities {
+    fn default() -> Self {
+        Self {
+            internal_events: EVENT_CHANNEL_SIZE,
+            output_events: EXTERNAL_EVENT_CHANNEL_SIZE,
+            network_inbound: NETWORK_INBOUND_CHANNEL_SIZE,
+        }
+    }
+}
+
+impl ChannelCapacities {
+    /// Check that every channel can hold at least one message and no more than
+    /// [`MAX_CHANNEL_CAPACITY`]
+    ///
+    /// # Errors
+    /// If a capacity is zero or too large
+    pub fn validate(&self) -> Result<()> {
+        for (channel, capacity) in [
+            ("internal event", self.internal_events),
+            ("output event", self.output_events),
+            ("network inbound", self.network_inbound),
+        ] {
+            ensure!(
+                capacity > 0,
+                "The {} channel must be able to hold at least one message",
+                channel
+            );
+            ensure!(
+                capacity <= MAX_CHANNEL_CAPACITY,
+                "The {} channel capacity of {} is above the maximum of {}",
+                channel,
+                capacity,
+                MAX_CHANNEL_CAPACITY
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Samples of how many events a channel holds
+#[derive(Clone, Debug)]
+pub struct ChannelDepth {
+    /// Name of the channel, for logging and metrics
+    name: &'static str,
+
+    /// Number of events the channel holds when full
+    capacity: usize,
+
+    /// Number of events above which the channel is close to full
+    warning_depth: usize,
+
+    /// How long the channel may stay close to full before we warn about it
+    warning_delay: Duration,
+
+    /// The most events the channel has held
+    high_water_mark: usize,
+
+    /// When the channel last became close to full, if it still is
+    above_warning_since: Option<Instant>,
+
+    /// Whether we warned about the channel since it last became close to full
+    warned: bool,
+}
+
+impl ChannelDepth {
+    /// Follow the channel `name` of `capacity` events, which is close to full above
+    /// `warning_fraction` of its capacity
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    pub fn new(
+        name: &'static str,
+        capacity: usize,
+        warning_fraction: f64,
+        warning_delay: Duration,
+    ) -> Self {
+        Self {
+            name,
+            capacity,
+            warning_depth: (capacity as f64 * warning_fraction.clamp(0.0, 1.0)) as usize,
+            warning_delay,
+            high_water_mark: 0,
+            above_warning_since: None,
+            warned: false,
+        }
+    }
+
+    /// Name of the channel
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The most events the channel has held
+    #[must_use]
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    /// Record that the channel held `depth` events at `now`. Returns whether it has now been
+    /// close to full for longer than the warning delay, once each time it fills up.
+    pub fn sample(&mut self, depth: usize, now: Instant) -> bool {
+        self.high_water_mark = self.high_water_mark.max(depth);
+
+        if depth <= self.warning_depth {
+            self.above_warning_since = None;
+            self.warned = false;
+            return false;
+        }
+
+        let since = *self.above_warning_since.get_or_insert(now);
+        if self.warned || now.saturating_duration_since(since) < self.warning_delay {
+            return false;
+        }
+        self.warned = true;
+
+        tracing::warn!(
+            "The {} event channel has held over {} of its {} events for {:?}",
+            self.name,
+            self.warning_depth,
+            self.capacity,
+            now.saturating_duration_since(since)
+        );
+        true
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A node's reading of the wall clock.
+//!
+//! Nodes never agree exactly on the current time. Everything which reads the wall clock, such as
+//! block timestamps, view durations and the upgrade time windows, goes through a [`Clock`], so
+//! tests can skew the clock of individual nodes. Timers only measure durations, so they are not
+//! affected by a constant skew, but any deadline derived from the wall clock is.
+
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+/// The wall clock of a node, offset from real time by a fixed skew.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Clock {
+    /// Offset from real time in milliseconds, positive if the clock runs ahead
+    skew_ms: i64,
+}
+
+impl Clock {
+    /// A clock which runs `skew_ms` milliseconds ahead of real time, or behind it if negative
+    #[must_use]
+    pub fn with_skew(skew_ms: i64) -> Self {
+        Self { skew_ms }
+    }
+
+    /// Offset of this clock from real time in milliseconds
+    #[must_use]
+    pub fn skew_ms(&self) -> i64 {
+        self.skew_ms
+    }
+
+    /// The current time according to this clock
+    #[must_use]
+    pub fn now(&self) -> SystemTime {
+        let now = SystemTime::now();
+        let skew = Duration::from_millis(self.skew_ms.unsigned_abs());
+        if self.skew_ms >= 0 {
+            now + skew
+        } else {
+            now.checked_sub(skew).unwrap_or(SystemTime::UNIX_EPOCH)
+        }
+    }
+
+    /// The current time according to this clock, in seconds since the Unix epoch
+    #[must_use]
+    pub fn unix_timestamp(&self) -> i64 {
+        self.now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since_epoch| {
+                i64::try_from(since_epoch.as_secs()).unwrap_or(i64::MAX)
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn skew_offsets_the_time() {
+        let ahead = Clock::with_skew(60_000).unix_timestamp();
+        let behind = Clock::with_skew(-60_000).unix_timestamp();
+        let real = Clock::default().unix_timestamp();
+
+        // Allow for the seconds ticking over between the readings
+        assert!((ahead - real - 60).abs() <= 1);
+        assert!((real - behind - 60).abs() <= 1);
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Compact encoding of quorum votes.
+//!
+//! A [`QuorumVote2`] carries the voter's full public key and the commitment to the leaf it votes
+//! for. Neither needs to be on the wire: the voter is identified by its index in the known stake
+//! table, and the leaf is the one proposed in the view of the vote, which the collecting leader
+//! has already seen. A [`CompactQuorumVote`] keeps only the view, the index and the signature,
+//! and a [`CompactVoteDecoder`] rebuilds full votes from them once the proposal is known.
+//!
+//! The view is kept whole, since the version a message is decoded with is derived from it.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use committable::Committable;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{Leaf2, QuorumProposal2},
+    simple_vote::{QuorumData2, QuorumVote2, SimpleVote},
+    traits::{node_implementation::NodeType, signature_key::SignatureKey},
+    vote::{HasViewNumber, Vote},
+};
+
+/// Number of views for which proposals and unresolved votes are retained
+const RETAINED_VIEWS: usize = 16;
+
+/// A quorum vote without the voter's key or the data voted for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+#[serde(bound(deserialize = ""))]
+pub struct CompactQuorumVote<TYPES: NodeType> {
+    /// The view this vote was cast for
+    pub view_number: TYPES::View,
+    /// Index of the voter in the known stake table
+    pub signer_index: u32,
+    /// The voter's signature over the vote data
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> CompactQuorumVote<TYPES> {
+    /// Compact `vote`, or return `None` if its signer is not in `stake_table`
+    #[must_use]
+    pub fn compact(vote: &QuorumVote2<TYPES>, stake_table: &[TYPES::SignatureKey]) -> Option<Self> {
+        let signing_key = vote.signing_key();
+        let signer_index = stake_table.iter().position(|key| *key == signing_key)?;
+
+        Some(Self {
+            view_number: vote.view_number(),
+            signer_index: u32::try_from(signer_index).ok()?,
+            signature: vote.signature(),
+        })
+    }
+
+    /// Rebuild the full vote for `data`, or return `None` if the signer index is not in
+    /// `stake_table`
+    #[must_use]
+    pub fn expand(
+        &self,
+        data: QuorumData2<TYPES>,
+        stake_table: &[TYPES::SignatureKey],
+    ) -> Option<QuorumVote2<TYPES>> {
+        let signing_key = stake_table.get(usize::try_from(self.signer_index).ok()?)?;
+
+        Some(SimpleVote {
+            signature: (signing_key.clone(), self.signature.clone()),
+            data,
+            view_number: self.view_number,
+        })
+    }
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for CompactQuorumVote<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
+
+/// Rebuilds full quorum votes from compact ones, holding back votes whose proposal hasn't been
+/// seen yet
+#[derive(Clone, Debug)]
+pub struct CompactVoteDecoder<TYPES: NodeType> {
+    /// The known stake table, which compact votes index into
+    stake_table: Arc<Vec<TYPES::SignatureKey>>,
+
+    /// The data of each leaf proposed in a recent view. There is normally one per view, but a
+    /// leader may equivocate.
+    proposed_leaves: BTreeMap<TYPES::View, Vec<QuorumData2<TYPES>>>,
+
+    /// Compact votes received before the proposal of their view
+    pending: BTreeMap<TYPES::View, Vec<CompactQuorumVote<TYPES>>>,
+}
+
+impl<TYPES: NodeType> CompactVoteDecoder<TYPES> {
+    /// Create a decoder for votes indexing into `stake_table`
+    #[must_use]
+    pub fn new(stake_table: Arc<Vec<TYPES::SignatureKey>>) -> Self {
+        Self {
+            stake_table,
+            proposed_leaves: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Whether a proposal has been seen for `view`
+    #[must_use]
+    pub fn knows_view(&self, view: TYPES::View) -> bool {
+        self.proposed_leaves.contains_key(&view)
+    }
+
+    /// Record the leaf of `proposal`, returning the votes for it that were waiting on it
+    pub fn add_proposal(&mut self, proposal: &QuorumProposal2<TYPES>) -> Vec<QuorumVote2<TYPES>> {
+        let leaf = Leaf2::from_quorum_proposal(proposal);
+        let data = QuorumData2 {
+            leaf_commit: leaf.commit(),
+            epoch: leaf.epoch(),
+        };
+
+        let leaves = self
+            .proposed_leaves
+            .entry(proposal.view_number)
+            .or_default();
+        if leaves.contains(&data) {
+            return Vec::new();
+        }
+        leaves.push(data.clone());
+        prune(&mut self.proposed_leaves);
+
+        self.pending
+            .remove(&proposal.view_number)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|vote| vote.expand(data.clone(), &self.stake_table))
+            .collect()
+    }
+
+    /// Rebuild `vote` for each leaf proposed in its view. If no proposal has been seen for the
+    /// view, the vote is held back until one is.
+    pub fn add_vote(&mut self, vote: CompactQuorumVote<TYPES>) -> Vec<QuorumVote2<TYPES>> {
+        let Some(leaves) = self.proposed_leaves.get(&vote.view_number) else {
+            let pending = self.pending.entry(vote.view_number).or_default();
+            // Each voter votes once a view
+            if pending.len() < self.stake_table.len() {
+                pending.push(vote);
+            }
+            prune(&mut self.pending);
+            return Vec::new();
+        };
+
+        leaves
+            .iter()
+            .filter_map(|data| vote.expand(data.clone(), &self.stake_table))
+            .collect()
+    }
+}
+
+/// Drop the oldest views from `map` until at most `RETAINED_VIEWS` are left
+fn prune<K: Ord, V>(map: &mut BTreeMap<K, V>) {
+    while map.len() > RETAINED_VIEWS {
+        map.pop_first();
+    }
+}
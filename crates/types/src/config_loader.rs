@@ -0,0 +1,116 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Loading of a [`NetworkConfigFile`] from a file, overridden by environment variables.
+//!
+//! The file may be TOML, YAML or JSON, told apart by its extension. Any field of it, including
+//! every field of the [`HotShotConfig`](crate::HotShotConfig) under `config`, can then be
+//! overridden by an environment variable named after the path to the field: [`ENV_PREFIX`], an
+//! underscore, and the names on the path joined by [`ENV_SEPARATOR`], in any case. For example
+//! `HOTSHOT_CONFIG__NEXT_VIEW_TIMEOUT=5000` overrides the view timeout,
+//! `HOTSHOT_CONFIG__BUILDER_TIMEOUT__SECS=2` the seconds of the builder timeout and
+//! `HOTSHOT_BUILDER=External` the builder the example nodes use. Fields which are lists, such as
+//! `HOTSHOT_CONFIG__BUILDER_URLS`, take comma-separated values.
+
+use std::{collections::HashMap, path::Path};
+
+use config::{Config, ConfigError, Environment, File};
+use thiserror::Error;
+
+use crate::{
+    config_validation::ConfigViolations,
+    network::{NetworkConfig, NetworkConfigFile},
+    traits::signature_key::SignatureKey,
+};
+
+/// Prefix of the environment variables which override config fields
+pub const ENV_PREFIX: &str = "HOTSHOT";
+
+/// Separator between the field names on the path to a field, in an environment variable
+pub const ENV_SEPARATOR: &str = "__";
+
+/// Fields which are lists of values, given comma-separated in environment variables
+const LIST_FIELDS: [&str; 1] = ["config.builder_urls"];
+
+/// Error loading a config
+#[derive(Debug, Error)]
+pub enum ConfigLoadError {
+    /// The file could not be read, or it or an override could not be parsed
+    #[error("Failed to load config: {0}")]
+    Load(#[from] ConfigError),
+
+    /// The config cannot run a network
+    #[error("{0}")]
+    Invalid(#[from] ConfigViolations),
+
+    /// The resolved config could not be serialized
+    #[error("Failed to serialize config: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// Load the config in `path`, overridden by the environment variables of this process
+///
+/// # Errors
+/// If the file cannot be read, or it or an override cannot be parsed
+pub fn load_network_config_file<KEY: SignatureKey>(
+    path: &Path,
+) -> Result<NetworkConfigFile<KEY>, ConfigLoadError> {
+    load_network_config_file_with_env(path, None)
+}
+
+/// Load the config in `path`, overridden by the variables in `env`, or by the environment
+/// variables of this process if `None`
+///
+/// # Errors
+/// If the file cannot be read, or it or an override cannot be parsed
+pub fn load_network_config_file_with_env<KEY: SignatureKey>(
+    path: &Path,
+    env: Option<HashMap<String, String>>,
+) -> Result<NetworkConfigFile<KEY>, ConfigLoadError> {
+    let mut environment = Environment::with_prefix(ENV_PREFIX)
+        .prefix_separator("_")
+        .separator(ENV_SEPARATOR)
+        .try_parsing(true)
+        .list_separator(",")
+        .source(env);
+    for field in LIST_FIELDS {
+        environment = environment.with_list_parse_key(field);
+    }
+
+    Ok(Config::builder()
+        .add_source(File::from(path))
+        .add_source(environment)
+        .build()?
+        .try_deserialize()?)
+}
+
+/// Resolve the config in `path` as the orchestrator would run it, with overrides from
+/// `env` as in [`load_network_config_file_with_env`], and check it
+///
+/// # Errors
+/// If the config cannot be loaded, or it cannot run a network
+pub fn resolve_network_config<KEY: SignatureKey>(
+    path: &Path,
+    env: Option<HashMap<String, String>>,
+) -> Result<NetworkConfig<KEY>, ConfigLoadError> {
+    let mut config: NetworkConfig<KEY> = load_network_config_file_with_env(path, env)?.into();
+    config.apply_public_keys();
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// Check the config in `path` with the overrides of this process's environment, and print it
+/// fully resolved, as JSON. Backs a `config check` command.
+///
+/// # Errors
+/// If the config cannot be loaded, or it cannot run a network
+pub fn check_config<KEY: SignatureKey>(path: &Path) -> Result<(), ConfigLoadError> {
+    let config = resolve_network_config::<KEY>(path, None)?;
+    println!("{}", serde_json::to_string_pretty(&config)?);
+
+    Ok(())
+}
@@ -0,0 +1,285 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Validation of a [`HotShotConfig`] before a node starts.
+//!
+//! Many inconsistent configs are accepted when a node is created and only show up once it runs,
+//! as a node which never forms a certificate or views which always time out.
+//! [`HotShotConfig::validate`] checks for them up front and returns every [`ConfigViolation`] it
+//! finds, each naming the fields to change.
+
+use std::{collections::HashSet, fmt, time::Duration};
+
+use primitive_types::U256;
+use thiserror::Error;
+
+use crate::{
+    constants::SMALL_NETWORK_MAX_NODES, stake_table::total_stake,
+    traits::signature_key::SignatureKey, HotShotConfig,
+};
+
+/// A way in which a [`HotShotConfig`] cannot run a network
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ConfigViolation {
+    /// The number of nodes does not match the stake table
+    #[error("num_nodes_with_stake is {expected} but known_nodes_with_stake lists {listed} nodes")]
+    NodeCount {
+        /// `num_nodes_with_stake`
+        expected: usize,
+        /// Length of `known_nodes_with_stake`
+        listed: usize,
+    },
+
+    /// A node appears more than once in the stake table
+    #[error("Node {0} appears more than once in known_nodes_with_stake")]
+    DuplicateNode(String),
+
+    /// A node in the stake table has no stake
+    #[error("Node {0} has no stake in known_nodes_with_stake")]
+    ZeroStakeNode(String),
+
+    /// The nodes have no stake between them, so no certificate can form
+    #[error(
+        "The nodes in known_nodes_with_stake have no stake between them, so no quorum can form"
+    )]
+    NoStake,
+
+    /// Small network thresholds are enabled for a network too large for them
+    #[error(
+        "small_network is enabled but the network has {nodes} nodes; it only applies to at most {max}. Disable it"
+    )]
+    SmallNetworkTooLarge {
+        /// Number of nodes in the network
+        nodes: usize,
+        /// [`SMALL_NETWORK_MAX_NODES`]
+        max: usize,
+    },
+
+    /// The DA committee size is not between one and the number of nodes
+    #[error("da_staked_committee_size is {size} but must be between 1 and num_nodes_with_stake ({nodes})")]
+    DaCommitteeSize {
+        /// `da_staked_committee_size`
+        size: usize,
+        /// `num_nodes_with_stake`
+        nodes: usize,
+    },
+
+    /// A DA node is not in the stake table
+    #[error("DA node {0} is in known_da_nodes but not in known_nodes_with_stake")]
+    UnstakedDaNode(String),
+
+    /// The DA committee has no stake between its members, so no DA certificate can form
+    #[error("The DA committee has no stake between its members, so no DA certificate can form")]
+    NoDaStake,
+
+    /// A timeout which must be positive is zero
+    #[error("{0} must be greater than zero")]
+    ZeroTimeout(&'static str),
+
+    /// A delay within a view is longer than the view timeout
+    #[error("{field} of {delay:?} is longer than next_view_timeout of {view_timeout:?}")]
+    DelayExceedsViewTimeout {
+        /// Name of the delay
+        field: &'static str,
+        /// The delay
+        delay: Duration,
+        /// The view timeout
+        view_timeout: Duration,
+    },
+
+    /// The start threshold is not a fraction of at most one
+    #[error("start_threshold of {numerator}/{denominator} must be a fraction between 0 and 1 with a nonzero denominator")]
+    StartThreshold {
+        /// Numerator of the threshold
+        numerator: u64,
+        /// Denominator of the threshold
+        denominator: u64,
+    },
+
+    /// A percentage is above 100
+    #[error("{field} of {percent} is above 100")]
+    Percentage {
+        /// Name of the percentage
+        field: &'static str,
+        /// The percentage
+        percent: u64,
+    },
+
+    /// The event channel warning fraction is not in (0, 1]
+    #[error("event_channel_warning_fraction of {0} must be above 0 and at most 1")]
+    WarningFraction(String),
+
+    /// A channel capacity is unusable
+    #[error("channel_capacities: {0}")]
+    ChannelCapacity(String),
+}
+
+/// Every way in which a [`HotShotConfig`] cannot run a network, one per line
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub struct ConfigViolations(pub Vec<ConfigViolation>);
+
+impl fmt::Display for ConfigViolations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} problem(s) with the config:", self.0.len())?;
+        for violation in &self.0 {
+            write!(f, "\n  - {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<KEY: SignatureKey> HotShotConfig<KEY> {
+    /// Check that the config can run a network: the stake table is consistent with the number of
+    /// nodes and can form quorums, the DA committee fits in the network and can form DA
+    /// certificates, the delays within a view are shorter than the view timeout, and the channel
+    /// capacities are usable.
+    ///
+    /// # Errors
+    /// Every violation found, if there are any
+    pub fn validate(&self) -> Result<(), ConfigViolations> {
+        let mut violations = self.parameter_violations();
+        violations.extend(self.stake_table_violations());
+        into_result(violations)
+    }
+
+    /// Check everything [`HotShotConfig::validate`] does except the stake tables, for configs
+    /// whose nodes are yet to register.
+    ///
+    /// # Errors
+    /// Every violation found, if there are any
+    pub fn validate_parameters(&self) -> Result<(), ConfigViolations> {
+        into_result(self.parameter_violations())
+    }
+
+    /// The violations which do not depend on the stake tables
+    fn parameter_violations(&self) -> Vec<ConfigViolation> {
+        let mut violations = Vec::new();
+        let nodes = self.num_nodes_with_stake.get();
+
+        if self.small_network && nodes > SMALL_NETWORK_MAX_NODES {
+            violations.push(ConfigViolation::SmallNetworkTooLarge {
+                nodes,
+                max: SMALL_NETWORK_MAX_NODES,
+            });
+        }
+
+        // Under full replication the DA committee is every node, whatever its configured size
+        if !self.full_replication() && !(1..=nodes).contains(&self.da_staked_committee_size) {
+            violations.push(ConfigViolation::DaCommitteeSize {
+                size: self.da_staked_committee_size,
+                nodes,
+            });
+        }
+
+        let view_timeout = Duration::from_millis(self.next_view_timeout);
+        if view_timeout.is_zero() {
+            violations.push(ConfigViolation::ZeroTimeout("next_view_timeout"));
+        }
+        if self.view_sync_timeout.is_zero() {
+            violations.push(ConfigViolation::ZeroTimeout("view_sync_timeout"));
+        }
+        // A leader which waits for a builder, or a node which waits before requesting data,
+        // for longer than the view times out before it gets anywhere
+        if self.builder_timeout > view_timeout {
+            violations.push(ConfigViolation::DelayExceedsViewTimeout {
+                field: "builder_timeout",
+                delay: self.builder_timeout,
+                view_timeout,
+            });
+        }
+        if self.data_request_delay > view_timeout {
+            violations.push(ConfigViolation::DelayExceedsViewTimeout {
+                field: "data_request_delay",
+                delay: self.data_request_delay,
+                view_timeout,
+            });
+        }
+
+        let (numerator, denominator) = self.start_threshold;
+        if denominator == 0 || numerator > denominator {
+            violations.push(ConfigViolation::StartThreshold {
+                numerator,
+                denominator,
+            });
+        }
+        if self.high_priority_reserved_percent > 100 {
+            violations.push(ConfigViolation::Percentage {
+                field: "high_priority_reserved_percent",
+                percent: self.high_priority_reserved_percent,
+            });
+        }
+        let fraction = self.event_channel_warning_fraction;
+        let usable_fraction = fraction > 0.0 && fraction <= 1.0;
+        if !usable_fraction {
+            violations.push(ConfigViolation::WarningFraction(fraction.to_string()));
+        }
+        if let Err(e) = self.channel_capacities.validate() {
+            violations.push(ConfigViolation::ChannelCapacity(e.to_string()));
+        }
+
+        violations
+    }
+
+    /// The violations in the stake tables
+    fn stake_table_violations(&self) -> Vec<ConfigViolation> {
+        let mut violations = Vec::new();
+
+        if self.known_nodes_with_stake.len() != self.num_nodes_with_stake.get() {
+            violations.push(ConfigViolation::NodeCount {
+                expected: self.num_nodes_with_stake.get(),
+                listed: self.known_nodes_with_stake.len(),
+            });
+        }
+
+        let mut staked = HashSet::new();
+        for peer in &self.known_nodes_with_stake {
+            let key = KEY::public_key(&peer.stake_table_entry);
+            if peer.stake_table_entry.stake().is_zero() {
+                violations.push(ConfigViolation::ZeroStakeNode(key.to_string()));
+            }
+            if !staked.insert(key.clone()) {
+                violations.push(ConfigViolation::DuplicateNode(key.to_string()));
+            }
+        }
+        let stake_table: Vec<_> = self
+            .known_nodes_with_stake
+            .iter()
+            .map(|peer| peer.stake_table_entry.clone())
+            .collect();
+        if total_stake::<KEY, _>(&stake_table) == U256::zero() {
+            violations.push(ConfigViolation::NoStake);
+        }
+
+        if !self.full_replication() {
+            for peer in &self.known_da_nodes {
+                let key = KEY::public_key(&peer.stake_table_entry);
+                if !staked.contains(&key) {
+                    violations.push(ConfigViolation::UnstakedDaNode(key.to_string()));
+                }
+            }
+        }
+        let da_stake_table: Vec<_> = self
+            .da_committee()
+            .into_iter()
+            .map(|peer| peer.stake_table_entry)
+            .collect();
+        if total_stake::<KEY, _>(&da_stake_table) == U256::zero() {
+            violations.push(ConfigViolation::NoDaStake);
+        }
+
+        violations
+    }
+}
+
+/// `Ok` if there are no `violations`
+fn into_result(violations: Vec<ConfigViolation>) -> Result<(), ConfigViolations> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigViolations(violations))
+    }
+}
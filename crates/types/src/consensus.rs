@@ -11,9 +11,11 @@ use std::{
     mem::ManuallyDrop,
     ops::{Deref, DerefMut},
     sync::Arc,
+    time::Instant,
 };
 
 use async_lock::{RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
+use bitvec::slice::BitSlice;
 use committable::{Commitment, Committable};
 use tracing::instrument;
 use utils::anytrace::*;
@@ -24,12 +26,17 @@ use crate::{
     data::{Leaf2, QuorumProposal2, VidDisperse, VidDisperseShare2},
     error::HotShotError,
     event::{HotShotAction, LeafInfo},
-    message::Proposal,
+    fork_choice::{ForkChoice, HighestQc},
+    message::{Proposal, UpgradeLock},
+    participation::ParticipationTracker,
     simple_certificate::{DaCertificate2, NextEpochQuorumCertificate2, QuorumCertificate2},
     traits::{
         block_contents::BuilderFee,
-        metrics::{Counter, Gauge, Histogram, Metrics, NoMetrics},
-        node_implementation::{ConsensusTime, NodeType},
+        metrics::{
+            Counter, CounterFamily, Gauge, GaugeFamily, Histogram, HistogramFamily, Metrics,
+            MetricsFamily, NoMetrics,
+        },
+        node_implementation::{ConsensusTime, NodeType, Versions},
         signature_key::SignatureKey,
         BlockPayload, ValidatedState,
     },
@@ -38,14 +45,16 @@ use crate::{
         StateAndDelta, Terminator,
     },
     vid::VidCommitment,
+    view_history::ViewHistory,
+    view_map::ViewMap,
     vote::{Certificate, HasViewNumber},
 };
 
 /// A type alias for `HashMap<Commitment<T>, T>`
 pub type CommitmentMap<T> = HashMap<Commitment<T>, T>;
 
-/// A type alias for `BTreeMap<T::Time, HashMap<T::SignatureKey, Proposal<T, VidDisperseShare<T>>>>`
-pub type VidShares<TYPES> = BTreeMap<
+/// A type alias for `ViewMap<T::Time, HashMap<T::SignatureKey, Proposal<T, VidDisperseShare<T>>>>`
+pub type VidShares<TYPES> = ViewMap<
     <TYPES as NodeType>::View,
     HashMap<<TYPES as NodeType>::SignatureKey, Proposal<TYPES, VidDisperseShare2<TYPES>>>,
 >;
@@ -269,6 +278,18 @@ impl<T: ConsensusTime> HotShotActionViews<T> {
         }
     }
 }
+
+/// The validated state computed for a proposed leaf when we voted for it
+#[derive(Clone, Debug)]
+pub struct SpeculativeState<TYPES: NodeType> {
+    /// View of the leaf
+    pub view_number: TYPES::View,
+    /// The validated state after the leaf's block
+    pub state: Arc<TYPES::ValidatedState>,
+    /// The changes the block made to the state
+    pub delta: Option<Arc<<TYPES::ValidatedState as ValidatedState<TYPES>>::Delta>>,
+}
+
 /// A reference to the consensus algorithm
 ///
 /// This will contain the state of all rounds.
@@ -278,11 +299,12 @@ pub struct Consensus<TYPES: NodeType> {
     validated_state_map: BTreeMap<TYPES::View, View<TYPES>>,
 
     /// All the VID shares we've received for current and future views.
+    /// Updated through the read lock, see [`ViewMap`].
     vid_shares: VidShares<TYPES>,
 
     /// All the DA certs we've received for current and future views.
-    /// view -> DA cert
-    saved_da_certs: HashMap<TYPES::View, DaCertificate2<TYPES>>,
+    /// view -> DA cert. Updated through the read lock, see [`ViewMap`].
+    saved_da_certs: ViewMap<TYPES::View, DaCertificate2<TYPES>>,
 
     /// View number that is currently on.
     cur_view: TYPES::View,
@@ -321,6 +343,19 @@ pub struct Consensus<TYPES: NodeType> {
     /// The high QC for the next epoch
     next_epoch_high_qc: Option<NextEpochQuorumCertificate2<TYPES>>,
 
+    /// Rule for choosing the high QC among competing QCs
+    fork_choice: Arc<dyn ForkChoice<TYPES>>,
+
+    /// Per-validator participation in decided certificates and proposals
+    participation: ParticipationTracker<TYPES::SignatureKey>,
+
+    /// Statistics on the most recent views
+    view_history: ViewHistory<TYPES>,
+
+    /// States computed for the leaves we voted for, by leaf commitment, if speculative execution
+    /// is enabled
+    speculative_states: Option<HashMap<LeafCommitment<TYPES>, SpeculativeState<TYPES>>>,
+
     /// A reference to the metrics trait
     pub metrics: Arc<ConsensusMetricsValue>,
 
@@ -361,6 +396,36 @@ pub struct ConsensusMetricsValue {
     pub number_of_empty_blocks_proposed: Box<dyn Counter>,
     /// Number of events in the hotshot event queue
     pub internal_event_queue_len: Box<dyn Gauge>,
+    /// Number of events held by each event channel, by channel
+    pub event_channel_depth: Box<dyn GaugeFamily>,
+    /// The most events each event channel has held, by channel
+    pub event_channel_high_water_mark: Box<dyn GaugeFamily>,
+    /// Participation score of each validator, in percent
+    pub validator_participation: Box<dyn GaugeFamily>,
+    /// Number of errors while handling consensus events, by kind
+    pub consensus_errors: Box<dyn CounterFamily>,
+    /// Number of proposals whose block failed to apply to the validated state, by leader
+    pub invalid_state_transitions: Box<dyn CounterFamily>,
+    /// Seconds from receiving a transaction to its block being decided, by priority class
+    pub transaction_inclusion_latency: Box<dyn HistogramFamily>,
+    /// Number of messages dropped on receipt because they had expired, by message class
+    pub expired_messages: Box<dyn CounterFamily>,
+    /// Number of messages rejected by replay protection, by message class
+    pub replay_rejected_messages: Box<dyn CounterFamily>,
+    /// Number of peers whose heartbeats keep up with the current view
+    pub live_peers: Box<dyn Gauge>,
+    /// View of the latest heartbeat of each peer, by peer
+    pub peer_heartbeat_view: Box<dyn GaugeFamily>,
+    /// Number of view sync relays which formed a certificate or timed out, by outcome
+    pub view_sync_relay_outcomes: Box<dyn CounterFamily>,
+    /// Seconds from a send being queued to it being handed to the network, by send class
+    pub send_completion_time: Box<dyn HistogramFamily>,
+    /// Seconds background work took to run, by work class
+    pub background_work_time: Box<dyn HistogramFamily>,
+    /// Number of background work items deferred to a later view and not run yet, by work class
+    pub deferred_background_work: Box<dyn GaugeFamily>,
+    /// Views caught up on per second since the current catchup started
+    pub catchup_views_per_second: Box<dyn Gauge>,
 }
 
 impl ConsensusMetricsValue {
@@ -392,6 +457,59 @@ impl ConsensusMetricsValue {
                 .create_counter(String::from("number_of_empty_blocks_proposed"), None),
             internal_event_queue_len: metrics
                 .create_gauge(String::from("internal_event_queue_len"), None),
+            event_channel_depth: metrics.gauge_family(
+                String::from("event_channel_depth"),
+                vec![String::from("channel")],
+            ),
+            event_channel_high_water_mark: metrics.gauge_family(
+                String::from("event_channel_high_water_mark"),
+                vec![String::from("channel")],
+            ),
+            validator_participation: metrics.gauge_family(
+                String::from("validator_participation"),
+                vec![String::from("validator")],
+            ),
+            consensus_errors: metrics
+                .counter_family(String::from("consensus_errors"), vec![String::from("kind")]),
+            invalid_state_transitions: metrics.counter_family(
+                String::from("invalid_state_transitions"),
+                vec![String::from("leader")],
+            ),
+            transaction_inclusion_latency: metrics.histogram_family(
+                String::from("transaction_inclusion_latency"),
+                vec![String::from("priority")],
+            ),
+            expired_messages: metrics.counter_family(
+                String::from("expired_messages"),
+                vec![String::from("class")],
+            ),
+            replay_rejected_messages: metrics.counter_family(
+                String::from("replay_rejected_messages"),
+                vec![String::from("class")],
+            ),
+            live_peers: metrics.create_gauge(String::from("live_peers"), None),
+            peer_heartbeat_view: metrics.gauge_family(
+                String::from("peer_heartbeat_view"),
+                vec![String::from("peer")],
+            ),
+            view_sync_relay_outcomes: metrics.counter_family(
+                String::from("view_sync_relay_outcomes"),
+                vec![String::from("outcome")],
+            ),
+            send_completion_time: metrics.histogram_family(
+                String::from("send_completion_time"),
+                vec![String::from("class")],
+            ),
+            background_work_time: metrics.histogram_family(
+                String::from("background_work_time"),
+                vec![String::from("class")],
+            ),
+            deferred_background_work: metrics.gauge_family(
+                String::from("deferred_background_work"),
+                vec![String::from("class")],
+            ),
+            catchup_views_per_second: metrics
+                .create_gauge(String::from("catchup_views_per_second"), None),
         }
     }
 }
@@ -422,8 +540,8 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     ) -> Self {
         Consensus {
             validated_state_map,
-            vid_shares: BTreeMap::new(),
-            saved_da_certs: HashMap::new(),
+            vid_shares: ViewMap::new(),
+            saved_da_certs: ViewMap::new(),
             cur_view,
             cur_epoch,
             last_decided_view,
@@ -434,6 +552,10 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             saved_payloads,
             high_qc,
             next_epoch_high_qc,
+            fork_choice: Arc::new(HighestQc),
+            participation: ParticipationTracker::default(),
+            view_history: ViewHistory::default(),
+            speculative_states: None,
             metrics,
             epoch_height,
         }
@@ -464,6 +586,72 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         &self.high_qc
     }
 
+    /// Get the rule for choosing the high QC.
+    pub fn fork_choice(&self) -> &Arc<dyn ForkChoice<TYPES>> {
+        &self.fork_choice
+    }
+
+    /// Replace the rule for choosing the high QC. All nodes must use the same rule.
+    pub fn set_fork_choice(&mut self, fork_choice: Arc<dyn ForkChoice<TYPES>>) {
+        self.fork_choice = fork_choice;
+    }
+
+    /// Cache the state computed for each leaf we vote for by leaf commitment, and serve decided
+    /// leaves their state from the cache, falling back to the state map.
+    ///
+    /// The state map holds one state per view, which need not be that of the leaf decided in the
+    /// view, e.g. if its leader equivocated, and is only derived from the header for proposals we
+    /// did not get to execute.
+    pub fn enable_speculative_execution(&mut self) {
+        self.speculative_states.get_or_insert_with(HashMap::new);
+    }
+
+    /// Whether speculative execution is enabled.
+    pub fn speculative_execution(&self) -> bool {
+        self.speculative_states.is_some()
+    }
+
+    /// Cache the state computed for `leaf` when voting for it, if speculative execution is
+    /// enabled.
+    pub fn cache_speculative_state(
+        &mut self,
+        leaf: &Leaf2<TYPES>,
+        state: Arc<TYPES::ValidatedState>,
+        delta: Option<Arc<<TYPES::ValidatedState as ValidatedState<TYPES>>::Delta>>,
+    ) {
+        if let Some(states) = &mut self.speculative_states {
+            states.insert(
+                leaf.commit(),
+                SpeculativeState {
+                    view_number: leaf.view_number(),
+                    state,
+                    delta,
+                },
+            );
+        }
+    }
+
+    /// The state computed for the leaf with commitment `leaf` when voting for it, if cached.
+    pub fn speculative_state(
+        &self,
+        leaf: &LeafCommitment<TYPES>,
+    ) -> Option<&SpeculativeState<TYPES>> {
+        self.speculative_states.as_ref()?.get(leaf)
+    }
+
+    /// The state after the leaf with commitment `leaf` in `view_number`: the state computed for the
+    /// leaf when voting for it if cached, or else the state in the state map for the view.
+    pub fn leaf_state_and_delta(
+        &self,
+        view_number: TYPES::View,
+        leaf: &LeafCommitment<TYPES>,
+    ) -> StateAndDelta<TYPES> {
+        match self.speculative_state(leaf) {
+            Some(cached) => (Some(Arc::clone(&cached.state)), cached.delta.clone()),
+            None => self.state_and_delta(view_number),
+        }
+    }
+
     /// Get the next epoch high QC.
     pub fn next_epoch_high_qc(&self) -> Option<&NextEpochQuorumCertificate2<TYPES>> {
         self.next_epoch_high_qc.as_ref()
@@ -489,11 +677,90 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         &self.vid_shares
     }
 
+    /// Get the VID share for `view` whose recipient is `public_key`.
+    pub fn vid_share(
+        &self,
+        view: TYPES::View,
+        public_key: &TYPES::SignatureKey,
+    ) -> Option<Proposal<TYPES, VidDisperseShare2<TYPES>>> {
+        self.vid_shares
+            .with(&view, |shares| shares.get(public_key).cloned())
+            .flatten()
+    }
+
     /// Get the saved DA certs.
-    pub fn saved_da_certs(&self) -> &HashMap<TYPES::View, DaCertificate2<TYPES>> {
+    pub fn saved_da_certs(&self) -> &ViewMap<TYPES::View, DaCertificate2<TYPES>> {
         &self.saved_da_certs
     }
 
+    /// Get the validator participation tracker.
+    pub fn participation(&self) -> &ParticipationTracker<TYPES::SignatureKey> {
+        &self.participation
+    }
+
+    /// Record which validators in `stake_table` voted for a decided certificate.
+    pub fn record_certificate_participation(
+        &mut self,
+        stake_table: &[TYPES::SignatureKey],
+        signers: &BitSlice,
+    ) {
+        for key in self.participation.record_certificate(stake_table, signers) {
+            tracing::warn!("Validator {key} dropped below the participation threshold");
+        }
+        for key in stake_table {
+            self.update_participation_metric(key);
+        }
+    }
+
+    /// Record whether the proposal of the leader of a view ended up being decided.
+    pub fn record_leader_participation(&mut self, leader: &TYPES::SignatureKey, decided: bool) {
+        if self.participation.record_leader_view(leader, decided) {
+            tracing::warn!("Validator {leader} dropped below the participation threshold");
+        }
+        self.update_participation_metric(leader);
+    }
+
+    /// Record that a proposal of `leader` failed to apply to the validated state.
+    pub fn record_invalid_state_transition(&mut self, leader: &TYPES::SignatureKey) {
+        let count = self.participation.record_invalid_state_transition(leader);
+        tracing::warn!("Validator {leader} proposed an invalid state transition, {count} so far");
+        self.metrics
+            .invalid_state_transitions
+            .create(vec![leader.to_string()])
+            .add(1);
+    }
+
+    /// Publish the current participation score of `key` to the metrics.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn update_participation_metric(&self, key: &TYPES::SignatureKey) {
+        if let Some(score) = self.participation.score(key) {
+            self.metrics
+                .validator_participation
+                .create(vec![key.to_string()])
+                .set((score * 100.0).round() as usize);
+        }
+    }
+
+    /// Get the statistics on the most recent views.
+    pub fn view_history(&self) -> &ViewHistory<TYPES> {
+        &self.view_history
+    }
+
+    /// Record that we entered `view`, led by `leader`.
+    pub fn record_view_entered(&mut self, view: TYPES::View, leader: Option<TYPES::SignatureKey>) {
+        self.view_history.enter(view, leader, Instant::now());
+    }
+
+    /// Record that `view` timed out.
+    pub fn record_view_timeout(&mut self, view: TYPES::View) {
+        self.view_history.record_timeout(view);
+    }
+
+    /// Record that the leaf of `view` was decided, with `block_size` transactions if known.
+    pub fn record_decided_view(&mut self, view: TYPES::View, block_size: Option<u64>) {
+        self.view_history.record_decided(view, block_size);
+    }
+
     /// Get the map of our recent proposals
     pub fn last_proposals(
         &self,
@@ -524,15 +791,13 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         let parent_leaf = self
             .saved_leaves
             .get(&leaf.justify_qc().data().leaf_commit)?;
-        let parent_state_and_delta = self.state_and_delta(parent_view_number);
+        let parent_state_and_delta =
+            self.leaf_state_and_delta(parent_view_number, &leaf.justify_qc().data().leaf_commit);
         let (Some(state), delta) = parent_state_and_delta else {
             return None;
         };
         let parent_vid = self
-            .vid_shares()
-            .get(&parent_view_number)?
-            .get(public_key)
-            .cloned()
+            .vid_share(parent_view_number, public_key)
             .map(|prop| prop.data);
 
         Some(LeafInfo {
@@ -737,13 +1002,14 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         Ok(())
     }
 
-    /// Update the high QC if given a newer one.
+    /// Update the high QC if the fork choice rule prefers the given one.
     /// # Errors
-    /// Can return an error when the provided high_qc is not newer than the existing entry.
+    /// Can return an error when the fork choice rule doesn't prefer the provided high_qc over the
+    /// existing entry.
     pub fn update_high_qc(&mut self, high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
         ensure!(
-            high_qc.view_number > self.high_qc.view_number || high_qc == self.high_qc,
-            debug!("High QC with an equal or higher view exists.")
+            high_qc == self.high_qc || self.fork_choice.prefers(&high_qc, &self.high_qc),
+            debug!("A preferred high QC with an equal or higher view exists.")
         );
         tracing::debug!("Updating high QC");
         self.high_qc = high_qc;
@@ -773,20 +1039,19 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         Ok(())
     }
 
-    /// Add a new entry to the vid_shares map.
+    /// Add a new entry to the vid_shares map. Only needs the read lock.
     pub fn update_vid_shares(
-        &mut self,
+        &self,
         view_number: TYPES::View,
         disperse: Proposal<TYPES, VidDisperseShare2<TYPES>>,
     ) {
-        self.vid_shares
-            .entry(view_number)
-            .or_default()
-            .insert(disperse.data.recipient_key.clone(), disperse);
+        self.vid_shares.update(view_number, |shares| {
+            shares.insert(disperse.data.recipient_key.clone(), disperse);
+        });
     }
 
-    /// Add a new entry to the da_certs map.
-    pub fn update_saved_da_certs(&mut self, view_number: TYPES::View, cert: DaCertificate2<TYPES>) {
+    /// Add a new entry to the da_certs map. Only needs the read lock.
+    pub fn update_saved_da_certs(&self, view_number: TYPES::View, cert: DaCertificate2<TYPES>) {
         self.saved_da_certs.insert(view_number, cert);
     }
 
@@ -821,7 +1086,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
 
         while let Some(leaf) = self.saved_leaves.get(&next_leaf) {
             let view = leaf.view_number();
-            if let (Some(state), delta) = self.state_and_delta(view) {
+            if let (Some(state), delta) = self.leaf_state_and_delta(view, &next_leaf) {
                 if let Terminator::Exclusive(stop_before) = terminator {
                     if stop_before == view {
                         if ok_when_finished {
@@ -856,7 +1121,9 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// # Panics
     /// On inconsistent stored entries
     pub fn collect_garbage(&mut self, old_anchor_view: TYPES::View, new_anchor_view: TYPES::View) {
-        let gc_view = TYPES::View::new(new_anchor_view.saturating_sub(1));
+        let gc_view = new_anchor_view
+            .checked_sub(1)
+            .unwrap_or_else(TYPES::View::genesis);
         // state check
         let anchor_entry = self
             .validated_state_map
@@ -869,8 +1136,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             );
         }
         // perform gc
-        self.saved_da_certs
-            .retain(|view_number, _| *view_number >= old_anchor_view);
+        self.saved_da_certs.remove_before(&old_anchor_view);
         self.validated_state_map
             .range(old_anchor_view..gc_view)
             .filter_map(|(_view_number, view)| view.leaf_commitment())
@@ -879,8 +1145,11 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             });
         self.validated_state_map = self.validated_state_map.split_off(&gc_view);
         self.saved_payloads = self.saved_payloads.split_off(&gc_view);
-        self.vid_shares = self.vid_shares.split_off(&gc_view);
+        self.vid_shares.remove_before(&gc_view);
         self.last_proposals = self.last_proposals.split_off(&gc_view);
+        if let Some(states) = &mut self.speculative_states {
+            states.retain(|_, cached| cached.view_number >= gc_view);
+        }
     }
 
     /// Gets the last decided leaf.
@@ -935,11 +1204,12 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// and updates `vid_shares` map with the signed `VidDisperseShare` proposals.
     /// Returned `Option` indicates whether the update has actually happened or not.
     #[instrument(skip_all, target = "Consensus", fields(view = *view))]
-    pub async fn calculate_and_update_vid(
+    pub async fn calculate_and_update_vid<V: Versions>(
         consensus: OuterConsensus<TYPES>,
         view: <TYPES as NodeType>::View,
         membership: Arc<RwLock<TYPES::Membership>>,
         private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Option<()> {
         let txns = Arc::clone(consensus.read().await.saved_payloads().get(&view)?);
         let epoch = consensus
@@ -952,12 +1222,16 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         let vid =
             VidDisperse::calculate_vid_disperse(txns, &membership, view, epoch, epoch, None).await;
         let shares = VidDisperseShare2::from_vid_disperse(vid);
-        let mut consensus_writer = consensus.write().await;
+        let mut proposals = Vec::with_capacity(shares.len());
         for share in shares {
-            if let Some(prop) = share.to_proposal(private_key) {
-                consensus_writer.update_vid_shares(view, prop);
+            if let Some(prop) = share.to_proposal(private_key, upgrade_lock).await {
+                proposals.push(prop);
             }
         }
+        let consensus_reader = consensus.read().await;
+        for prop in proposals {
+            consensus_reader.update_vid_shares(view, prop);
+        }
         Some(())
     }
 
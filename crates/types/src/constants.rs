@@ -17,6 +17,29 @@ pub const BUNDLE_FETCH_TIMEOUT: Duration = Duration::from_millis(500);
 /// the number of views to gather information for ahead of time
 pub const LOOK_AHEAD: u64 = 5;
 
+/// the default number of upcoming views whose leaders we connect to ahead of time
+pub const CONNECTION_WARM_UP_VIEWS: u64 = LOOK_AHEAD;
+
+/// the default interval between anti-entropy exchanges of certificates with a random peer
+pub const ANTI_ENTROPY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// the number of heartbeats a peer may miss before we no longer consider it live
+pub const HEARTBEAT_MISSED_BEATS: u64 = 3;
+
+/// the default number of relays a replica tries in each view sync timeout, failing over to the
+/// next after its share of the timeout
+pub const VIEW_SYNC_RELAYS_PER_ROUND: u32 = 3;
+
+/// the largest network that may run with the small network thresholds
+pub const SMALL_NETWORK_MAX_NODES: usize = 3;
+
+/// share of the timeout certificate threshold, in percent, at which the next leader starts
+/// fetching its block, ahead of the certificate forming
+pub const TIMEOUT_PREPLAN_PERCENT: u64 = 66;
+
+/// the time to wait before handing a block which failed to execute to the execution hook again
+pub const EXECUTION_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 /// the default kademlia record republication interval (in seconds)
 pub const KAD_DEFAULT_REPUB_INTERVAL_SEC: u64 = 28800;
 
@@ -35,12 +58,35 @@ pub const COMBINED_NETWORK_DELAY_DURATION: u64 = 5000;
 /// The default network data request delay in milliseconds
 pub const REQUEST_DATA_DELAY: u64 = 5000;
 
+/// Default number of views before the current view for which we still accept messages
+pub const REPLAY_WINDOW_PAST_VIEWS: u64 = 100;
+
+/// Default number of views after the current view for which we already accept messages
+pub const REPLAY_WINDOW_FUTURE_VIEWS: u64 = 1000;
+
+/// Default number of views after the current view for which we hold state for messages; messages
+/// for views beyond are dropped, and we catch up instead
+pub const MAX_OUTSTANDING_VIEWS: u64 = 100;
+
 /// Default channel size for consensus event sharing
 pub const EVENT_CHANNEL_SIZE: usize = 100_000;
 
 /// Default channel size for HotShot -> application communication
 pub const EXTERNAL_EVENT_CHANNEL_SIZE: usize = 100_000;
 
+/// Default capacity of the queue of messages received from the network
+pub const NETWORK_INBOUND_CHANNEL_SIZE: usize = 1000;
+
+/// Largest capacity a channel may be configured with. Channels are allocated up front, so this
+/// guards against a misconfigured capacity exhausting memory.
+pub const MAX_CHANNEL_CAPACITY: usize = 10_000_000;
+
+/// Default fraction of an event channel's capacity above which it is close to full
+pub const EVENT_CHANNEL_WARNING_FRACTION: f64 = 0.8;
+
+/// How long an event channel may stay close to full before we warn about it
+pub const EVENT_CHANNEL_WARNING_DELAY: Duration = Duration::from_secs(5);
+
 /// The offset for how far in the future we will send out a `QuorumProposal` with an `UpgradeCertificate` we form. This is also how far in advance of sending a `QuorumProposal` we begin collecting votes on an `UpgradeProposal`.
 pub const UPGRADE_PROPOSE_OFFSET: u64 = 5;
 
@@ -76,3 +122,6 @@ pub const ORCHESTRATOR_DEFAULT_NUM_ROUNDS: usize = 100;
 pub const ORCHESTRATOR_DEFAULT_TRANSACTIONS_PER_ROUND: usize = 10;
 /// default size of transactions
 pub const ORCHESTRATOR_DEFAULT_TRANSACTION_SIZE: usize = 100;
+
+/// Number of views in a row which must time out, without a decide, to raise an alert
+pub const REPEATED_TIMEOUT_ALERT_THRESHOLD: u64 = 3;
@@ -24,7 +24,6 @@ use jf_vid::{precomputable::Precomputable, VidDisperse as JfVidDisperse, VidSche
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::task::spawn_blocking;
 use tracing::error;
 use utils::anytrace::*;
 use vec1::Vec1;
@@ -35,7 +34,8 @@ use crate::{
     message::{Proposal, UpgradeLock},
     simple_certificate::{
         NextEpochQuorumCertificate2, QuorumCertificate, QuorumCertificate2, TimeoutCertificate2,
-        UpgradeCertificate, ViewSyncFinalizeCertificate2,
+        UpgradeCertificate, ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate2,
+        ViewSyncPreCommitCertificate2,
     },
     simple_vote::{HasEpoch, QuorumData, QuorumData2, UpgradeProposalData, VersionedVoteData},
     traits::{
@@ -49,7 +49,7 @@ use crate::{
         states::TestableState,
         BlockPayload,
     },
-    utils::{bincode_opts, epoch_from_block_number},
+    utils::{bincode_opts, epoch_from_block_number, run_blocking},
     vid::{vid_scheme, VidCommitment, VidCommon, VidPrecomputeData, VidSchemeType, VidShare},
     vote::{Certificate, HasViewNumber},
 };
@@ -273,7 +273,7 @@ impl<TYPES: NodeType> VidDisperse<TYPES> {
         let num_nodes = membership.read().await.total_nodes(target_epoch);
 
         let txns_clone = Arc::clone(&txns);
-        let vid_disperse = spawn_blocking(move || {
+        let vid_disperse = run_blocking(move || {
             precompute_data
                 .map_or_else(
                     || vid_scheme(num_nodes).disperse(&txns_clone),
@@ -285,15 +285,11 @@ impl<TYPES: NodeType> VidDisperse<TYPES> {
             None
         } else {
             let data_epoch_num_nodes = membership.read().await.total_nodes(data_epoch);
-            Some(spawn_blocking(move || {
+            Some(run_blocking(move || {
                 vid_scheme(data_epoch_num_nodes).commit_only(&txns)
                     .unwrap_or_else(|err| panic!("VID commit_only failure:(num_storage nodes,payload_byte_len)=({num_nodes},{}) error: {err}", txns.len()))
             }).await)
         };
-        // Unwrap here will just propagate any panic from the spawned task, it's not a new place we can panic.
-        let vid_disperse = vid_disperse.unwrap();
-        let data_epoch_payload_commitment =
-            data_epoch_payload_commitment.map(|result| result.unwrap());
 
         Self::from_membership(
             view,
@@ -319,11 +315,56 @@ pub enum ViewChangeEvidence<TYPES: NodeType> {
 }
 
 impl<TYPES: NodeType> ViewChangeEvidence<TYPES> {
+    /// The only view whose proposal the evidence can justify: the view after the one a timeout
+    /// certificate is for, or the round of a view sync certificate.
+    pub fn justified_view(&self) -> TYPES::View {
+        match self {
+            ViewChangeEvidence::Timeout(timeout_cert) => timeout_cert.data().view + 1,
+            ViewChangeEvidence::ViewSync(view_sync_cert) => view_sync_cert.view_number,
+        }
+    }
+
     /// Check that the given ViewChangeEvidence is relevant to the current view.
     pub fn is_valid_for_view(&self, view: &TYPES::View) -> bool {
+        self.justified_view() == *view
+    }
+}
+
+/// The latest certificate a node has seen in view sync, from which it resumes view sync after a
+/// restart.
+#[derive(derive_more::Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub enum ViewSyncCertificate<TYPES: NodeType> {
+    /// Holds a view sync pre-commit certificate.
+    PreCommit(ViewSyncPreCommitCertificate2<TYPES>),
+    /// Holds a view sync commit certificate.
+    Commit(ViewSyncCommitCertificate2<TYPES>),
+    /// Holds a view sync finalize certificate.
+    Finalize(ViewSyncFinalizeCertificate2<TYPES>),
+}
+
+impl<TYPES: NodeType> ViewSyncCertificate<TYPES> {
+    /// The view sync round of the certificate
+    pub fn round(&self) -> TYPES::View {
         match self {
-            ViewChangeEvidence::Timeout(timeout_cert) => timeout_cert.data().view == *view - 1,
-            ViewChangeEvidence::ViewSync(view_sync_cert) => view_sync_cert.view_number == *view,
+            ViewSyncCertificate::PreCommit(cert) => cert.view_number,
+            ViewSyncCertificate::Commit(cert) => cert.view_number,
+            ViewSyncCertificate::Finalize(cert) => cert.view_number,
+        }
+    }
+
+    /// Whether the certificate is further along in view sync than `other`, by its round and
+    /// then by its phase
+    pub fn supersedes(&self, other: &Self) -> bool {
+        (self.round(), self.phase()) > (other.round(), other.phase())
+    }
+
+    /// The phase of view sync the certificate ends, in order
+    fn phase(&self) -> u8 {
+        match self {
+            ViewSyncCertificate::PreCommit(_) => 0,
+            ViewSyncCertificate::Commit(_) => 1,
+            ViewSyncCertificate::Finalize(_) => 2,
         }
     }
 }
@@ -360,13 +401,17 @@ impl<TYPES: NodeType> VidDisperseShare<TYPES> {
     }
 
     /// Consume `self` and return a `Proposal`
-    pub fn to_proposal(
+    pub async fn to_proposal<V: Versions>(
         self,
         private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Option<Proposal<TYPES, Self>> {
-        let Ok(signature) =
-            TYPES::SignatureKey::sign(private_key, self.payload_commitment.as_ref())
-        else {
+        let Ok(signature) = TYPES::SignatureKey::sign(
+            private_key,
+            &upgrade_lock
+                .signing_message(self.payload_commitment.as_ref(), self.view_number)
+                .await,
+        ) else {
             error!("VID: failed to sign dispersal share payload");
             return None;
         };
@@ -516,13 +561,17 @@ impl<TYPES: NodeType> VidDisperseShare2<TYPES> {
     }
 
     /// Consume `self` and return a `Proposal`
-    pub fn to_proposal(
+    pub async fn to_proposal<V: Versions>(
         self,
         private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Option<Proposal<TYPES, Self>> {
-        let Ok(signature) =
-            TYPES::SignatureKey::sign(private_key, self.payload_commitment.as_ref())
-        else {
+        let Ok(signature) = TYPES::SignatureKey::sign(
+            private_key,
+            &upgrade_lock
+                .signing_message(self.payload_commitment.as_ref(), self.view_number)
+                .await,
+        ) else {
             error!("VID: failed to sign dispersal share payload");
             return None;
         };
@@ -905,6 +954,43 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
             drb_result: [0; 32],
         }
     }
+
+    /// The genesis leaf of a new chain which carries on from `leaf`, the last leaf decided by an
+    /// existing chain.
+    ///
+    /// The new leaf keeps the block of `leaf`, so the new chain continues from its height and
+    /// state, but is in the genesis view and epoch like any other genesis leaf. It has `leaf` as
+    /// its parent, so its commitment differs between chains restarted from different leaves.
+    #[must_use]
+    pub fn genesis_from_snapshot(leaf: &Self) -> Self {
+        let null_quorum_data = QuorumData2 {
+            leaf_commit: Commitment::<Leaf2<TYPES>>::default_commitment_no_preimage(),
+            epoch: TYPES::Epoch::genesis(),
+        };
+
+        let justify_qc = QuorumCertificate2::new(
+            null_quorum_data.clone(),
+            null_quorum_data.commit(),
+            <TYPES::View as ConsensusTime>::genesis(),
+            None,
+            PhantomData,
+        );
+
+        Self {
+            view_number: TYPES::View::genesis(),
+            justify_qc,
+            next_epoch_justify_qc: None,
+            parent_commitment: leaf.commit(),
+            upgrade_certificate: None,
+            block_header: leaf.block_header.clone(),
+            block_payload: leaf.block_payload.clone(),
+            epoch: TYPES::Epoch::genesis(),
+            view_change_evidence: None,
+            drb_seed: leaf.drb_seed,
+            drb_result: leaf.drb_result,
+        }
+    }
+
     /// Time when this leaf was created.
     pub fn view_number(&self) -> TYPES::View {
         self.view_number
@@ -1036,14 +1122,13 @@ impl<TYPES: NodeType> Committable for Leaf2<TYPES> {
 }
 
 impl<TYPES: NodeType> Leaf<TYPES> {
-    #[allow(clippy::unused_async)]
     /// Calculate the leaf commitment,
-    /// which is gated on the version to include the block header.
+    /// which is gated on the version to use `TYPES::CommitmentScheme`.
     pub async fn commit<V: Versions>(
         &self,
-        _upgrade_lock: &UpgradeLock<TYPES, V>,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Commitment<Self> {
-        <Self as Committable>::commit(self)
+        upgrade_lock.commit(self, self.view_number).await
     }
 }
 
@@ -1158,15 +1243,20 @@ impl<TYPES: NodeType> QuorumCertificate2<TYPES> {
         validated_state: &TYPES::ValidatedState,
         instance_state: &TYPES::InstanceState,
     ) -> Self {
+        Self::for_genesis_leaf::<V>(&Leaf2::genesis(validated_state, instance_state).await).await
+    }
+
+    #[must_use]
+    /// Create the certificate for `leaf`, the genesis leaf of a chain. Like the certificate of the
+    /// built-in genesis leaf, it is in the genesis view and carries no signatures.
+    pub async fn for_genesis_leaf<V: Versions>(leaf: &Leaf2<TYPES>) -> Self {
         // since this is genesis, we should never have a decided upgrade certificate.
         let upgrade_lock = UpgradeLock::<TYPES, V>::new();
 
         let genesis_view = <TYPES::View as ConsensusTime>::genesis();
 
         let data = QuorumData2 {
-            leaf_commit: Leaf2::genesis(validated_state, instance_state)
-                .await
-                .commit(),
+            leaf_commit: leaf.commit(),
             epoch: TYPES::Epoch::genesis(),
         };
 
@@ -28,6 +28,14 @@ pub const INITIAL_DRB_SEED_INPUT: [u8; 32] = [0; 32];
 /// DRB result for epoch 1 and 2.
 pub const INITIAL_DRB_RESULT: [u8; 32] = [0; 32];
 
+/// Number of epochs after the one whose leaves decide a DRB result that it is the sampling beacon
+/// of. Every node has decided a leaf of an epoch well before the epoch after next starts, so they
+/// all draw that epoch's samples with the same beacon.
+pub const SAMPLING_BEACON_EPOCH_LAG: u64 = 2;
+
+/// Domain separator for deriving sampling beacons from DRB results
+const SAMPLING_BEACON_DOMAIN: &[u8] = b"HotShot sampling beacon";
+
 /// Alias for DRB seed input for `compute_drb_result`, serialized from the QC signature.
 pub type DrbSeedInput = [u8; 32];
 
@@ -66,6 +74,20 @@ pub fn compute_drb_result<TYPES: NodeType>(drb_seed_input: DrbSeedInput) -> DrbR
     drb_result
 }
 
+/// The beacon which quorum samples are drawn with, derived from a decided DRB result.
+///
+/// Returns `None` for [`INITIAL_DRB_RESULT`], which is known in advance and so no beacon at all.
+#[must_use]
+pub fn sampling_beacon(drb_result: &DrbResult) -> Option<[u8; 32]> {
+    if *drb_result == INITIAL_DRB_RESULT {
+        return None;
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(SAMPLING_BEACON_DOMAIN);
+    hasher.update(drb_result);
+    Some(hasher.finalize().into())
+}
+
 /// Use the DRB result to get the leader.
 ///
 /// The DRB result is the output of a spawned `compute_drb_result` call.
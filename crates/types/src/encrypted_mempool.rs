@@ -0,0 +1,150 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Types for the threshold-encrypted mempool.
+//!
+//! Clients encrypt transactions to the threshold key of the DA committee, so blocks only commit
+//! to ciphertexts and the leader can't frontrun their contents. Once a block is decided, the DA
+//! committee members release their signed decryption shares for its encrypted transactions, and
+//! any node which collects enough valid shares can decrypt them.
+//!
+//! The threshold encryption scheme and its key material are provided by the application through
+//! [`InstanceState::threshold_decryption`](crate::traits::states::InstanceState::threshold_decryption).
+
+use std::fmt::Debug;
+
+use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    message::UpgradeLock,
+    traits::{
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+};
+
+/// A threshold encryption scheme along with this node's key share.
+///
+/// Keys and ciphertexts are opaque to `HotShot`. Epochs are passed as plain numbers, since the
+/// DA committee, and therefore its threshold key, may change with every epoch.
+pub trait ThresholdDecryption: Debug + Send + Sync {
+    /// Threshold encryption key of the DA committee for `epoch`, which clients encrypt their
+    /// transactions to.
+    fn encryption_key(&self, epoch: u64) -> Option<Vec<u8>>;
+
+    /// Number of valid decryption shares needed to decrypt a ciphertext for `epoch`.
+    fn threshold(&self, epoch: u64) -> usize;
+
+    /// This node's decryption share for `ciphertext`, or `None` if it holds no key share for
+    /// `epoch`.
+    fn decryption_share(&self, epoch: u64, ciphertext: &[u8]) -> Option<Vec<u8>>;
+
+    /// Whether `share` is a valid decryption share for `ciphertext` by the committee member with
+    /// the serialized public key `sender`.
+    fn verify_share(&self, epoch: u64, sender: &[u8], ciphertext: &[u8], share: &[u8]) -> bool;
+
+    /// Combine valid shares, given as pairs of serialized public key and share, into the
+    /// plaintext of `ciphertext`. Returns `None` if there are not enough shares.
+    fn combine_shares(
+        &self,
+        epoch: u64,
+        ciphertext: &[u8],
+        shares: &[(Vec<u8>, Vec<u8>)],
+    ) -> Option<Vec<u8>>;
+}
+
+/// Decryption shares released by a DA committee member for the encrypted transactions of a
+/// decided block, signed by the member.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct DecryptionShares<TYPES: NodeType> {
+    /// View of the decided block
+    pub view_number: TYPES::View,
+
+    /// Epoch of the decided block, which determines the threshold key
+    pub epoch: TYPES::Epoch,
+
+    /// Committee member which released the shares
+    pub sender: TYPES::SignatureKey,
+
+    /// Decryption shares, by the position of the encrypted transaction in the block
+    pub shares: Vec<(u64, Vec<u8>)>,
+
+    /// Signature of the sender on the shares
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> Committable for DecryptionShares<TYPES> {
+    /// Commitment to everything but the signature, which is what the sender signs
+    fn commit(&self) -> Commitment<Self> {
+        Self::commitment(self.view_number, self.epoch, &self.sender, &self.shares)
+    }
+}
+
+impl<TYPES: NodeType> DecryptionShares<TYPES> {
+    /// Commitment to the decryption `shares` of `sender` for the block decided in `view_number`
+    fn commitment(
+        view_number: TYPES::View,
+        epoch: TYPES::Epoch,
+        sender: &TYPES::SignatureKey,
+        shares: &[(u64, Vec<u8>)],
+    ) -> Commitment<Self> {
+        let mut builder = committable::RawCommitmentBuilder::new("DecryptionShares")
+            .u64_field("view number", *view_number)
+            .u64_field("epoch", *epoch)
+            .var_size_field("sender", &sender.to_bytes())
+            .u64_field("shares", u64::try_from(shares.len()).unwrap_or(u64::MAX));
+        for (index, share) in shares {
+            builder = builder
+                .u64_field("index", *index)
+                .var_size_field("share", share);
+        }
+        builder.finalize()
+    }
+
+    /// Sign the decryption `shares` of `sender` for the block decided in `view_number`
+    ///
+    /// # Errors
+    /// If signing fails
+    pub async fn sign<V: Versions>(
+        view_number: TYPES::View,
+        epoch: TYPES::Epoch,
+        sender: TYPES::SignatureKey,
+        shares: Vec<(u64, Vec<u8>)>,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<Self> {
+        let commitment = Self::commitment(view_number, epoch, &sender, &shares);
+        let signature = TYPES::SignatureKey::sign(
+            private_key,
+            &upgrade_lock
+                .signing_message(commitment.as_ref(), view_number)
+                .await,
+        )
+        .wrap()
+        .context(error!("Failed to sign decryption shares"))?;
+
+        Ok(Self {
+            view_number,
+            epoch,
+            sender,
+            shares,
+            signature,
+        })
+    }
+
+    /// Whether the shares were signed by their sender
+    pub async fn is_signed<V: Versions>(&self, upgrade_lock: &UpgradeLock<TYPES, V>) -> bool {
+        self.sender.validate(
+            &self.signature,
+            &upgrade_lock
+                .signing_message(self.commit().as_ref(), self.view_number)
+                .await,
+        )
+    }
+}
@@ -7,11 +7,14 @@
 //! Error type for `HotShot`
 //!
 //! This module provides [`HotShotError`], which is an enum representing possible faults that can
-//! occur while interacting with this crate.
+//! occur while interacting with this crate, [`ConsensusError`], which classifies the failures
+//! of consensus task handlers, and [`BlockValidationError`], why a candidate block would be
+//! rejected.
 
 use committable::Commitment;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use utils::anytrace::{self, Level};
 
 use crate::{data::Leaf2, traits::node_implementation::NodeType};
 
@@ -35,6 +38,10 @@ pub enum HotShotError<TYPES: NodeType> {
     #[error("Failed to deserialize: {0}")]
     FailedToDeserialize(String),
 
+    /// The configuration is invalid
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
     /// The view timed out
     #[error("View {view_number} timed out: {state:?}")]
     ViewTimedOut {
@@ -72,3 +79,129 @@ pub enum RoundTimedoutState {
     /// HotShot-testing tried to collect round events, but it timed out
     TestCollectRoundEventsTimedOut,
 }
+
+/// Structured reason a consensus task failed to handle an event
+#[derive(Debug, Clone, Error, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ConsensusError {
+    /// The message is for a view we have already moved past
+    #[error("Stale message: {0}")]
+    StaleMessage(String),
+
+    /// A proposal failed validation
+    #[error("Invalid proposal: {0}")]
+    ProposalInvalid(String),
+
+    /// The block of a proposal failed to apply to the validated state of its parent
+    #[error("Invalid state transition: {0}")]
+    InvalidStateTransition(String),
+
+    /// A certificate attached to a message failed validation
+    #[error("Invalid certificate: {0}")]
+    CertificateInvalid(String),
+
+    /// A message was not signed by the expected key
+    #[error("Invalid signature: {0}")]
+    SignatureInvalid(String),
+
+    /// The parent of a proposal is not known to us yet
+    #[error("Missing parent: {0}")]
+    ParentMissing(String),
+
+    /// Writing to storage failed
+    #[error("Storage failure: {0}")]
+    StorageFailure(String),
+
+    /// The consensus state is internally inconsistent
+    #[error("Inconsistent state: {0}")]
+    InconsistentState(String),
+}
+
+impl ConsensusError {
+    /// Short name of the kind of error, used to label metrics
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::StaleMessage(_) => "stale_message",
+            Self::ProposalInvalid(_) => "proposal_invalid",
+            Self::InvalidStateTransition(_) => "invalid_state_transition",
+            Self::CertificateInvalid(_) => "certificate_invalid",
+            Self::SignatureInvalid(_) => "signature_invalid",
+            Self::ParentMissing(_) => "parent_missing",
+            Self::StorageFailure(_) => "storage_failure",
+            Self::InconsistentState(_) => "inconsistent_state",
+        }
+    }
+
+    /// Whether the operator of the node can do something about this error, as opposed to it
+    /// being caused by other nodes or the network.
+    #[must_use]
+    pub fn is_user_actionable(&self) -> bool {
+        matches!(self, Self::StorageFailure(_) | Self::InconsistentState(_))
+    }
+
+    /// The level this error should be logged at
+    #[must_use]
+    pub fn level(&self) -> Level {
+        match self {
+            Self::StaleMessage(_) | Self::ParentMissing(_) => Level::Debug,
+            Self::ProposalInvalid(_)
+            | Self::InvalidStateTransition(_)
+            | Self::CertificateInvalid(_)
+            | Self::SignatureInvalid(_) => Level::Warn,
+            Self::StorageFailure(_) | Self::InconsistentState(_) => Level::Error,
+        }
+    }
+}
+
+impl From<ConsensusError> for anytrace::Error {
+    fn from(error: ConsensusError) -> Self {
+        Self {
+            level: error.level(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Reason a candidate block would be rejected if it were proposed
+#[derive(Debug, Clone, Error, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum BlockValidationError {
+    /// We have no leaf and state for the parent view
+    #[error("No leaf and state for parent view {0}")]
+    UnknownParent(u64),
+
+    /// The block number does not follow the parent's
+    #[error("Block number {actual} does not follow the parent's, expected {expected}")]
+    BlockNumber {
+        /// The block number of the parent plus one
+        expected: u64,
+        /// The block number in the header
+        actual: u64,
+    },
+
+    /// The header's payload commitment is not the VID commitment of the payload
+    #[error("Header's payload commitment {header} does not match the payload's {payload}")]
+    PayloadCommitment {
+        /// The commitment in the header
+        header: String,
+        /// The commitment computed from the payload
+        payload: String,
+    },
+
+    /// The header's builder commitment is not that of the payload
+    #[error("Header's builder commitment {header} does not match the payload's {payload}")]
+    BuilderCommitment {
+        /// The commitment in the header
+        header: String,
+        /// The commitment computed from the payload
+        payload: String,
+    },
+
+    /// The header does not extend the parent
+    #[error("Block rejected: {0}")]
+    Rejected(String),
+    /// The block fails to apply to the validated state of the parent
+    #[error("Invalid state transition: {0}")]
+    InvalidStateTransition(String),
+}
@@ -8,14 +8,20 @@
 
 use std::sync::Arc;
 
+use bitvec::vec::BitVec;
+use committable::Commitment;
 use serde::{Deserialize, Serialize};
+use vbs::version::Version;
 
 use crate::{
+    chain_config::ChainConfig,
     data::{DaProposal2, Leaf2, QuorumProposal2, UpgradeProposal, VidDisperseShare2},
-    error::HotShotError,
+    error::{ConsensusError, HotShotError},
+    governance::ParameterChange,
+    header_relay::RelayHeader,
     message::Proposal,
     simple_certificate::QuorumCertificate2,
-    traits::{node_implementation::NodeType, ValidatedState},
+    traits::{block_contents::TransactionPriority, node_implementation::NodeType, ValidatedState},
 };
 
 /// A status event emitted by a `HotShot` instance
@@ -148,6 +154,12 @@ pub enum EventType<TYPES: NodeType> {
         /// The list of transactions
         transactions: Vec<TYPES::Transaction>,
     },
+    /// New transactions tagged with a priority class were received from the network
+    /// or submitted to the network by us
+    PrioritizedTransactions {
+        /// The list of transactions along with their priority
+        transactions: Vec<(TYPES::Transaction, TransactionPriority)>,
+    },
     /// DA proposal was received from the network
     /// or submitted to the network by us
     DaProposal {
@@ -164,6 +176,11 @@ pub enum EventType<TYPES: NodeType> {
         /// Public key of the leader submitting the proposal
         sender: TYPES::SignatureKey,
     },
+    /// A VID share addressed to us was received and validated
+    VidShareReceived {
+        /// The view the share is for
+        view_number: TYPES::View,
+    },
     /// Upgrade proposal was received from the network
     /// or submitted to the network by us
     UpgradeProposal {
@@ -180,6 +197,109 @@ pub enum EventType<TYPES: NodeType> {
         /// Serialized data of the message
         data: Vec<u8>,
     },
+
+    /// Accounting record for a single decided block.
+    ///
+    /// One of these is emitted for every leaf in the chain of a `Decide` event, right after the
+    /// `Decide` itself, so that reward systems don't have to re-derive participation.
+    BlockRewardInfo {
+        /// Height of the decided block
+        block_height: u64,
+        /// Public key of the leader who proposed the block
+        leader: TYPES::SignatureKey,
+        /// Fee claimed by the builder of the block, if the header records one
+        builder_fee: Option<u64>,
+        /// Public keys of the DA committee members who signed the DA certificate, if we saw it
+        da_signers: Vec<TYPES::SignatureKey>,
+        /// Bitmap over the committee sampled for the view of the QC certifying the block, of the
+        /// nodes whose votes are in that QC
+        vote_participation: BitVec,
+    },
+    /// A decided block, packaged for relay to bridge and light client contracts.
+    ///
+    /// One of these is emitted for every leaf in the chain of a `Decide` event, oldest first,
+    /// after the `Decide` itself. See [`crate::header_relay`].
+    HeaderRelay {
+        /// The header of the block, the QC certifying it and the commitment to its stake tables
+        header: RelayHeader<TYPES>,
+        /// The canonical serialization of `header`, to submit to a contract
+        encoded: Vec<u8>,
+    },
+    /// Availability sampling of decided blocks has failed repeatedly.
+    ///
+    /// Emitted by nodes outside the DA committee when they were unable to retrieve and verify
+    /// randomly sampled VID shares for several decided blocks in a row.
+    AvailabilitySamplingFailed {
+        /// Number of consecutive decided blocks for which sampling failed
+        consecutive_failures: u64,
+        /// Recipients of the VID shares we could not retrieve or verify for this view
+        unavailable_shares: Vec<TYPES::SignatureKey>,
+    },
+
+    /// Handling a consensus event failed in a way the operator of this node should act on, e.g.
+    /// because storage is failing.
+    ConsensusError {
+        /// The error
+        error: ConsensusError,
+    },
+
+    /// Encrypted transactions of a decided block were decrypted
+    TransactionsDecrypted {
+        /// The decrypted transactions, in the order they appear in the block
+        transactions: Vec<TYPES::Transaction>,
+    },
+
+    /// A leader proposed a block built with a different chain configuration than ours
+    ConfigMismatch {
+        /// The leader which proposed the block
+        leader: TYPES::SignatureKey,
+        /// Commitment to our chain configuration
+        expected: Commitment<ChainConfig>,
+        /// Commitment to the chain configuration in the proposed header
+        proposed: Commitment<ChainConfig>,
+    },
+
+    /// The block of a proposal failed to apply to the validated state of its parent, so we did
+    /// not vote for it
+    InvalidStateTransition {
+        /// The leader which proposed the block
+        leader: TYPES::SignatureKey,
+        /// The proposed leaf
+        leaf: Leaf2<TYPES>,
+        /// The error the application returned
+        error: String,
+    },
+
+    /// A decided governance certificate scheduled a change to runtime parameters
+    ParameterChangeScheduled {
+        /// The new values of the parameters
+        change: ParameterChange,
+        /// The first view in which the new values are in effect
+        activation_view: TYPES::View,
+    },
+
+    /// The decide rule of the version we are set to upgrade to, rehearsed in shadow mode, reached
+    /// a different decision from a proposal than the rule of the version we run
+    ShadowDivergence {
+        /// The version we run
+        live_version: Version,
+        /// The version we are set to upgrade to
+        shadow_version: Version,
+        /// What the rule of the version we run decided
+        live: DecideOutcome<TYPES>,
+        /// What the rule of the version we are set to upgrade to decided
+        shadow: DecideOutcome<TYPES>,
+    },
+}
+
+/// What a decide rule concluded from a proposal
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct DecideOutcome<TYPES: NodeType> {
+    /// The view we would lock on
+    pub locked_view: Option<TYPES::View>,
+    /// The views we would decide, newest first
+    pub decided_views: Vec<TYPES::View>,
 }
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 /// A list of actions that we track for nodes
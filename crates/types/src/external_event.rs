@@ -0,0 +1,457 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A stable representation of the events a `HotShot` instance emits, for consumers outside the
+//! node.
+//!
+//! [`Event`] and [`EventType`] carry the internal types of consensus, whose shape changes whenever
+//! consensus does. An [`ExternalEvent`] holds only plain fields, the application's own types and
+//! commitments as strings, and is tagged with the version of its schema, so that the API can stay
+//! put while consensus changes underneath it.
+//!
+//! Within a version, the schema only grows: events may gain optional fields, which older consumers
+//! ignore, and new events may be added, which older consumers read as
+//! [`ExternalEventType::Unknown`]. Any other change makes a new [`EXTERNAL_EVENT_VERSION`], and
+//! events of a version newer than a consumer knows fail to deserialize. The representation is
+//! meant for self-describing formats such as JSON.
+
+use committable::Committable;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    data::Leaf2,
+    event::{Event, EventType, LeafInfo},
+    governance::ParameterChange,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        block_contents::{BlockPayload, TransactionPriority},
+        node_implementation::NodeType,
+    },
+    vote::HasViewNumber,
+};
+
+/// Version of the schema of [`ExternalEvent`]
+pub const EXTERNAL_EVENT_VERSION: u16 = 1;
+
+/// Deserialize a schema version, rejecting those newer than [`EXTERNAL_EVENT_VERSION`]
+fn supported_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    let version = u16::deserialize(deserializer)?;
+    if version == 0 || version > EXTERNAL_EVENT_VERSION {
+        return Err(D::Error::custom(format!(
+            "unsupported event schema version {version}, expected at most {EXTERNAL_EVENT_VERSION}"
+        )));
+    }
+
+    Ok(version)
+}
+
+/// An event emitted by a `HotShot` instance, in the stable external schema
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct ExternalEvent<TYPES: NodeType> {
+    /// Version of the schema the event follows
+    #[serde(deserialize_with = "supported_version")]
+    pub version: u16,
+    /// The view the event originates from
+    pub view_number: u64,
+    /// The event proper
+    pub event: ExternalEventType<TYPES>,
+}
+
+/// A decided leaf, in the stable external schema
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct ExternalLeaf<TYPES: NodeType> {
+    /// Height of the block
+    pub height: u64,
+    /// View the leaf was proposed in
+    pub view_number: u64,
+    /// Commitment to the leaf
+    pub commitment: String,
+    /// Commitment to the parent leaf
+    pub parent_commitment: String,
+    /// Header of the block
+    pub block_header: TYPES::BlockHeader,
+    /// Payload of the block, if the node has it
+    pub block_payload: Option<TYPES::BlockPayload>,
+}
+
+impl<TYPES: NodeType> From<&Leaf2<TYPES>> for ExternalLeaf<TYPES> {
+    fn from(leaf: &Leaf2<TYPES>) -> Self {
+        Self {
+            height: leaf.height(),
+            view_number: *leaf.view_number(),
+            commitment: leaf.commit().to_string(),
+            parent_commitment: leaf.parent_commitment().to_string(),
+            block_header: leaf.block_header().clone(),
+            block_payload: leaf.block_payload(),
+        }
+    }
+}
+
+/// A quorum certificate, in the stable external schema
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalQuorumCertificate {
+    /// View of the certified leaf
+    pub view_number: u64,
+    /// Commitment to the certified leaf
+    pub leaf_commitment: String,
+}
+
+impl<TYPES: NodeType> From<&QuorumCertificate2<TYPES>> for ExternalQuorumCertificate {
+    fn from(qc: &QuorumCertificate2<TYPES>) -> Self {
+        Self {
+            view_number: *qc.view_number(),
+            leaf_commitment: qc.data.leaf_commit.to_string(),
+        }
+    }
+}
+
+/// A transaction with its priority class
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct PrioritizedTransaction<TYPES: NodeType> {
+    /// The transaction
+    pub transaction: TYPES::Transaction,
+    /// Its priority class
+    pub priority: TransactionPriority,
+}
+
+/// The type and contents of an [`ExternalEvent`]
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""), tag = "type", rename_all = "snake_case")]
+pub enum ExternalEventType<TYPES: NodeType> {
+    /// A view encountered an error and was interrupted
+    Error {
+        /// The error
+        message: String,
+    },
+    /// Blocks were decided
+    Decide {
+        /// The decided leaves, in order of height
+        leaves: Vec<ExternalLeaf<TYPES>>,
+        /// The certificate for the newest of `leaves`
+        qc: ExternalQuorumCertificate,
+        /// Number of transactions in the newest block, if known
+        block_size: Option<u64>,
+    },
+    /// A replica task was canceled by a timeout interrupt
+    ReplicaViewTimeout {
+        /// The view that timed out
+        view_number: u64,
+    },
+    /// A view finished
+    ViewFinished {
+        /// The view that finished
+        view_number: u64,
+    },
+    /// A view timed out
+    ViewTimeout {
+        /// The view that timed out
+        view_number: u64,
+    },
+    /// Transactions were received from the network or submitted by us
+    Transactions {
+        /// The transactions
+        transactions: Vec<TYPES::Transaction>,
+    },
+    /// Transactions tagged with a priority class were received from the network or submitted by us
+    PrioritizedTransactions {
+        /// The transactions with their priority classes
+        transactions: Vec<PrioritizedTransaction<TYPES>>,
+    },
+    /// A DA proposal was received from the network or submitted by us
+    DaProposal {
+        /// View of the proposal
+        view_number: u64,
+        /// Epoch of the proposal
+        epoch: u64,
+        /// The leader which proposed it
+        sender: TYPES::SignatureKey,
+        /// The encoded transactions of the block
+        encoded_transactions: Vec<u8>,
+        /// Metadata of the block payload
+        metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    },
+    /// A quorum proposal was received from the network or submitted by us
+    QuorumProposal {
+        /// View of the proposal
+        view_number: u64,
+        /// The leader which proposed it
+        sender: TYPES::SignatureKey,
+        /// Header of the proposed block
+        block_header: TYPES::BlockHeader,
+        /// The certificate the proposal extends
+        justify_qc: ExternalQuorumCertificate,
+    },
+    /// A VID share addressed to us was received and validated
+    VidShareReceived {
+        /// The view the share is for
+        view_number: u64,
+    },
+    /// An upgrade proposal was received from the network or submitted by us
+    UpgradeProposal {
+        /// View of the proposal
+        view_number: u64,
+        /// The leader which proposed it
+        sender: TYPES::SignatureKey,
+        /// The version upgraded from
+        old_version: String,
+        /// The version upgraded to
+        new_version: String,
+        /// The last view the upgrade can be decided in
+        decide_by: u64,
+        /// The first view of the new version
+        new_version_first_view: u64,
+    },
+    /// A message for external listeners was received
+    ExternalMessageReceived {
+        /// The sender of the message
+        sender: TYPES::SignatureKey,
+        /// The message
+        data: Vec<u8>,
+    },
+    /// Accounting record for a decided block
+    BlockRewardInfo {
+        /// Height of the block
+        block_height: u64,
+        /// The leader which proposed the block
+        leader: TYPES::SignatureKey,
+        /// Fee claimed by the builder of the block, if the header records one
+        builder_fee: Option<u64>,
+        /// The DA committee members who signed the DA certificate, if we saw it
+        da_signers: Vec<TYPES::SignatureKey>,
+        /// For each node of the committee sampled for the view of the certificate of the block,
+        /// whether its vote is in that certificate
+        vote_participation: Vec<bool>,
+    },
+    /// A decided block, packaged for relay to bridge and light client contracts
+    HeaderRelay {
+        /// Height of the block
+        block_height: u64,
+        /// The canonical serialization of the header, the QC certifying it and the commitment to
+        /// its stake tables
+        encoded: Vec<u8>,
+    },
+    /// Availability sampling of decided blocks failed repeatedly
+    AvailabilitySamplingFailed {
+        /// Number of consecutive decided blocks sampling failed for
+        consecutive_failures: u64,
+        /// Recipients of the VID shares which could not be retrieved or verified
+        unavailable_shares: Vec<TYPES::SignatureKey>,
+    },
+    /// Handling a consensus event failed in a way the operator should act on
+    ConsensusError {
+        /// The kind of error
+        kind: String,
+        /// The error
+        message: String,
+    },
+    /// Encrypted transactions of a decided block were decrypted
+    TransactionsDecrypted {
+        /// The decrypted transactions, in block order
+        transactions: Vec<TYPES::Transaction>,
+    },
+    /// A leader proposed a block built with a different chain configuration than ours
+    ConfigMismatch {
+        /// The leader which proposed the block
+        leader: TYPES::SignatureKey,
+        /// Commitment to our chain configuration
+        expected: String,
+        /// Commitment to the chain configuration in the proposed header
+        proposed: String,
+    },
+    /// The block of a proposal failed to apply to the validated state of its parent
+    InvalidStateTransition {
+        /// The leader which proposed the block
+        leader: TYPES::SignatureKey,
+        /// The proposed leaf
+        leaf: ExternalLeaf<TYPES>,
+        /// The error the application returned
+        error: String,
+    },
+    /// A decided governance certificate scheduled a change to runtime parameters
+    ParameterChangeScheduled {
+        /// The new values of the parameters
+        change: ParameterChange,
+        /// The first view in which the new values are in effect
+        activation_view: u64,
+    },
+    /// The decide rule of the version we are set to upgrade to reached a different decision from
+    /// a proposal than the rule of the version we run
+    ShadowDivergence {
+        /// The version we run
+        live_version: String,
+        /// The version we are set to upgrade to
+        shadow_version: String,
+        /// The view the rule of the version we run would lock on
+        live_locked_view: Option<u64>,
+        /// The views the rule of the version we run would decide, newest first
+        live_decided_views: Vec<u64>,
+        /// The view the rule of the version we are set to upgrade to would lock on
+        shadow_locked_view: Option<u64>,
+        /// The views the rule of the version we are set to upgrade to would decide, newest first
+        shadow_decided_views: Vec<u64>,
+    },
+    /// An event added to the schema after the version this was built with
+    #[serde(other)]
+    Unknown,
+}
+
+impl<TYPES: NodeType> From<&Event<TYPES>> for ExternalEvent<TYPES> {
+    fn from(event: &Event<TYPES>) -> Self {
+        Self {
+            version: EXTERNAL_EVENT_VERSION,
+            view_number: *event.view_number,
+            event: ExternalEventType::from(&event.event),
+        }
+    }
+}
+
+impl<TYPES: NodeType> From<&EventType<TYPES>> for ExternalEventType<TYPES> {
+    fn from(event: &EventType<TYPES>) -> Self {
+        match event {
+            EventType::Error { error } => Self::Error {
+                message: error.to_string(),
+            },
+            EventType::Decide {
+                leaf_chain,
+                qc,
+                block_size,
+            } => Self::Decide {
+                leaves: leaf_chain
+                    .iter()
+                    .rev()
+                    .map(|LeafInfo { leaf, .. }| ExternalLeaf::from(leaf))
+                    .collect(),
+                qc: ExternalQuorumCertificate::from(qc.as_ref()),
+                block_size: *block_size,
+            },
+            EventType::ReplicaViewTimeout { view_number } => Self::ReplicaViewTimeout {
+                view_number: **view_number,
+            },
+            EventType::ViewFinished { view_number } => Self::ViewFinished {
+                view_number: **view_number,
+            },
+            EventType::ViewTimeout { view_number } => Self::ViewTimeout {
+                view_number: **view_number,
+            },
+            EventType::Transactions { transactions } => Self::Transactions {
+                transactions: transactions.clone(),
+            },
+            EventType::PrioritizedTransactions { transactions } => Self::PrioritizedTransactions {
+                transactions: transactions
+                    .iter()
+                    .map(|(transaction, priority)| PrioritizedTransaction {
+                        transaction: transaction.clone(),
+                        priority: *priority,
+                    })
+                    .collect(),
+            },
+            EventType::DaProposal { proposal, sender } => Self::DaProposal {
+                view_number: *proposal.data.view_number,
+                epoch: *proposal.data.epoch,
+                sender: sender.clone(),
+                encoded_transactions: proposal.data.encoded_transactions.to_vec(),
+                metadata: proposal.data.metadata.clone(),
+            },
+            EventType::QuorumProposal { proposal, sender } => Self::QuorumProposal {
+                view_number: *proposal.data.view_number,
+                sender: sender.clone(),
+                block_header: proposal.data.block_header.clone(),
+                justify_qc: ExternalQuorumCertificate::from(&proposal.data.justify_qc),
+            },
+            EventType::VidShareReceived { view_number } => Self::VidShareReceived {
+                view_number: **view_number,
+            },
+            EventType::UpgradeProposal { proposal, sender } => {
+                let upgrade = &proposal.data.upgrade_proposal;
+                Self::UpgradeProposal {
+                    view_number: *proposal.data.view_number,
+                    sender: sender.clone(),
+                    old_version: upgrade.old_version.to_string(),
+                    new_version: upgrade.new_version.to_string(),
+                    decide_by: *upgrade.decide_by,
+                    new_version_first_view: *upgrade.new_version_first_view,
+                }
+            }
+            EventType::ExternalMessageReceived { sender, data } => Self::ExternalMessageReceived {
+                sender: sender.clone(),
+                data: data.clone(),
+            },
+            EventType::BlockRewardInfo {
+                block_height,
+                leader,
+                builder_fee,
+                da_signers,
+                vote_participation,
+            } => Self::BlockRewardInfo {
+                block_height: *block_height,
+                leader: leader.clone(),
+                builder_fee: *builder_fee,
+                da_signers: da_signers.clone(),
+                vote_participation: vote_participation.iter().by_vals().collect(),
+            },
+            EventType::HeaderRelay { header, encoded } => Self::HeaderRelay {
+                block_height: header.height(),
+                encoded: encoded.clone(),
+            },
+            EventType::AvailabilitySamplingFailed {
+                consecutive_failures,
+                unavailable_shares,
+            } => Self::AvailabilitySamplingFailed {
+                consecutive_failures: *consecutive_failures,
+                unavailable_shares: unavailable_shares.clone(),
+            },
+            EventType::ConsensusError { error } => Self::ConsensusError {
+                kind: error.kind().to_string(),
+                message: error.to_string(),
+            },
+            EventType::TransactionsDecrypted { transactions } => Self::TransactionsDecrypted {
+                transactions: transactions.clone(),
+            },
+            EventType::ConfigMismatch {
+                leader,
+                expected,
+                proposed,
+            } => Self::ConfigMismatch {
+                leader: leader.clone(),
+                expected: expected.to_string(),
+                proposed: proposed.to_string(),
+            },
+            EventType::InvalidStateTransition {
+                leader,
+                leaf,
+                error,
+            } => Self::InvalidStateTransition {
+                leader: leader.clone(),
+                leaf: ExternalLeaf::from(leaf),
+                error: error.clone(),
+            },
+            EventType::ParameterChangeScheduled {
+                change,
+                activation_view,
+            } => Self::ParameterChangeScheduled {
+                change: *change,
+                activation_view: **activation_view,
+            },
+            EventType::ShadowDivergence {
+                live_version,
+                shadow_version,
+                live,
+                shadow,
+            } => Self::ShadowDivergence {
+                live_version: live_version.to_string(),
+                shadow_version: shadow_version.to_string(),
+                live_locked_view: live.locked_view.map(|view| *view),
+                live_decided_views: live.decided_views.iter().map(|view| **view).collect(),
+                shadow_locked_view: shadow.locked_view.map(|view| *view),
+                shadow_decided_views: shadow.decided_views.iter().map(|view| **view).collect(),
+            },
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Per-block base fee, adjusted EIP-1559-style from the fullness of the parent block.
+//!
+//! Chains opt in by returning [`BaseFeeParams`] from
+//! [`InstanceState::base_fee_params`](crate::traits::states::InstanceState::base_fee_params).
+//! The leader records the base fee in the header it builds, and replicas check it against
+//! [`expected_base_fee`] when validating the proposal.
+
+use serde::{Deserialize, Serialize};
+
+use crate::traits::{
+    block_contents::BlockHeader, node_implementation::NodeType, states::InstanceState,
+};
+
+/// Parameters of the base fee adjustment for a chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BaseFeeParams {
+    /// Base fee of a block whose parent has no base fee, e.g. the first block after genesis
+    pub initial_base_fee: u64,
+    /// The base fee never drops below this value
+    pub min_base_fee: u64,
+    /// Block usage at which the base fee stays the same, in the unit of
+    /// [`BlockHeader::block_usage`]
+    pub target_usage: u64,
+    /// The base fee changes by at most `1 / max_change_denominator` between blocks
+    pub max_change_denominator: u64,
+}
+
+impl BaseFeeParams {
+    /// Base fee of a block whose parent had `parent_base_fee` and used `parent_usage`.
+    ///
+    /// The fee rises if the parent was fuller than the target and falls if it was emptier,
+    /// proportionally to the distance from the target.
+    #[must_use]
+    pub fn next_base_fee(&self, parent_base_fee: Option<u64>, parent_usage: u64) -> u64 {
+        let Some(base_fee) = parent_base_fee else {
+            return self.initial_base_fee.max(self.min_base_fee);
+        };
+        if self.target_usage == 0 || self.max_change_denominator == 0 {
+            return base_fee.max(self.min_base_fee);
+        }
+
+        let target = u128::from(self.target_usage);
+        let usage = u128::from(parent_usage);
+        let denominator = target * u128::from(self.max_change_denominator);
+
+        let next = if usage > target {
+            let delta = (u128::from(base_fee) * (usage - target) / denominator).max(1);
+            u64::try_from(u128::from(base_fee) + delta).unwrap_or(u64::MAX)
+        } else {
+            let delta = u128::from(base_fee) * (target - usage) / denominator;
+            // `delta` is at most `base_fee`, since `target - usage <= target`
+            base_fee - u64::try_from(delta).unwrap_or(base_fee)
+        };
+
+        next.max(self.min_base_fee)
+    }
+}
+
+/// Base fee a block extending `parent_header` must carry, or `None` if the chain has no fee
+/// market.
+#[must_use]
+pub fn expected_base_fee<TYPES: NodeType>(
+    instance_state: &TYPES::InstanceState,
+    parent_header: &TYPES::BlockHeader,
+) -> Option<u64> {
+    instance_state
+        .base_fee_params()
+        .map(|params| params.next_base_fee(parent_header.base_fee(), parent_header.block_usage()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PARAMS: BaseFeeParams = BaseFeeParams {
+        initial_base_fee: 1000,
+        min_base_fee: 10,
+        target_usage: 100,
+        max_change_denominator: 8,
+    };
+
+    #[test]
+    fn starts_at_initial_base_fee() {
+        assert_eq!(PARAMS.next_base_fee(None, 500), 1000);
+    }
+
+    #[test]
+    fn tracks_parent_fullness() {
+        assert_eq!(PARAMS.next_base_fee(Some(1000), 100), 1000);
+        assert_eq!(PARAMS.next_base_fee(Some(1000), 200), 1125);
+        assert_eq!(PARAMS.next_base_fee(Some(1000), 0), 875);
+        assert_eq!(PARAMS.next_base_fee(Some(1000), 50), 938);
+    }
+
+    #[test]
+    fn respects_bounds() {
+        // A full block always raises the fee, even if the proportional change rounds to zero
+        assert_eq!(PARAMS.next_base_fee(Some(10), 101), 11);
+        assert_eq!(PARAMS.next_base_fee(Some(10), 0), 10);
+        assert_eq!(PARAMS.next_base_fee(Some(u64::MAX), u64::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Fork choice for the QC a proposal builds on.
+//!
+//! After restarts, a node may know several QCs for the same view, certifying different leaves.
+//! The [`ForkChoice`] rule decides which of them becomes the high QC, and therefore which one
+//! the leader uses to justify its proposal. Replicas apply the same rule to check the leader's
+//! choice, so all nodes must be configured with the same rule.
+
+use std::{fmt::Debug, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{simple_certificate::QuorumCertificate2, traits::node_implementation::NodeType};
+
+/// The built-in fork choice rules, to pick one in the config
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ForkChoiceRule {
+    /// [`HighestQc`]
+    #[default]
+    HighestQc,
+    /// [`HighestView`]
+    HighestView,
+}
+
+impl ForkChoiceRule {
+    /// The rule to apply
+    #[must_use]
+    pub fn fork_choice<TYPES: NodeType>(self) -> Arc<dyn ForkChoice<TYPES>> {
+        match self {
+            Self::HighestQc => Arc::new(HighestQc),
+            Self::HighestView => Arc::new(HighestView),
+        }
+    }
+}
+
+/// Rule for choosing between QCs to build on.
+pub trait ForkChoice<TYPES: NodeType>: Debug + Send + Sync {
+    /// Whether `candidate` should be built on instead of `current`.
+    fn prefers(
+        &self,
+        candidate: &QuorumCertificate2<TYPES>,
+        current: &QuorumCertificate2<TYPES>,
+    ) -> bool;
+}
+
+/// The default rule: build on the QC with the highest view, breaking ties by the higher leaf
+/// commitment.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HighestQc;
+
+impl<TYPES: NodeType> ForkChoice<TYPES> for HighestQc {
+    fn prefers(
+        &self,
+        candidate: &QuorumCertificate2<TYPES>,
+        current: &QuorumCertificate2<TYPES>,
+    ) -> bool {
+        candidate.view_number > current.view_number
+            || (candidate.view_number == current.view_number
+                && <[u8; 32]>::from(candidate.data.leaf_commit)
+                    > <[u8; 32]>::from(current.data.leaf_commit))
+    }
+}
+
+/// Build on the QC with the highest view, keeping the one we already have when another is for the
+/// same view. Replicas don't check which of the QCs for a view the leader built on.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HighestView;
+
+impl<TYPES: NodeType> ForkChoice<TYPES> for HighestView {
+    fn prefers(
+        &self,
+        candidate: &QuorumCertificate2<TYPES>,
+        current: &QuorumCertificate2<TYPES>,
+    ) -> bool {
+        candidate.view_number > current.view_number
+    }
+}
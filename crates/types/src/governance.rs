@@ -0,0 +1,169 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Runtime protocol parameters which a quorum can change without restarting the network.
+//!
+//! Beyond version upgrades, a few parameters are worth tuning on a live network: the base view
+//! timeout, the maximum block size and the size of the DA committee. A change to them is a
+//! [`GovernanceCertificate`] over the new values and the view they take effect in, signed by a
+//! quorum of stake. Applications carry it in a governance transaction, and lift it into the header
+//! of the block that includes it (see [`BlockHeader::governance_certificate`]), so that every node
+//! finds it in the leaves it decides, whether or not it holds the block payload.
+//!
+//! Once the leaf is decided, each node schedules the change in its [`GovernedParameters`], which the
+//! tasks consult for the parameters of the view they are working on, so that the new values apply
+//! from the same view on every node. Changes must take effect at least
+//! [`MIN_ACTIVATION_DELAY`] views after the leaf carrying them, so that they are decided well
+//! before they apply. The size of the DA committee is scheduled in the membership as well, which
+//! keeps the DA committee of each view (see [`Membership::set_da_committee_size`]).
+//!
+//! Scheduled changes are persisted with [`Storage::append_parameter_change`], and scheduled again
+//! when a node restarts.
+//!
+//! [`GovernanceCertificate`]: crate::simple_certificate::GovernanceCertificate
+//! [`Membership::set_da_committee_size`]: crate::traits::election::Membership::set_da_committee_size
+//! [`Storage::append_parameter_change`]: crate::traits::storage::Storage::append_parameter_change
+//! [`BlockHeader::governance_certificate`]: crate::traits::block_contents::BlockHeader::governance_certificate
+
+use std::{
+    collections::BTreeMap,
+    num::NonZeroUsize,
+    sync::{PoisonError, RwLock},
+};
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::traits::node_implementation::NodeType;
+
+/// The fewest views after the leaf carrying a change that the change may take effect
+pub const MIN_ACTIVATION_DELAY: u64 = 10;
+
+/// New values of runtime parameters, leaving those which are `None` as they are
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ParameterChange {
+    /// Base timeout of a view, in milliseconds
+    pub view_timeout: Option<u64>,
+    /// Maximum size of a block, in the unit of
+    /// [`Transaction::minimum_block_size`](crate::traits::block_contents::Transaction::minimum_block_size)
+    pub max_block_size: Option<u64>,
+    /// Number of DA nodes on the DA committee
+    pub da_committee_size: Option<NonZeroUsize>,
+}
+
+impl ParameterChange {
+    /// Whether the change leaves every parameter as it is
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Committable for ParameterChange {
+    fn commit(&self) -> Commitment<Self> {
+        let da_committee_size = self.da_committee_size.map(|size| size.get() as u64);
+        RawCommitmentBuilder::new("Parameter Change")
+            .u64_field("has view timeout", u64::from(self.view_timeout.is_some()))
+            .u64_field("view timeout", self.view_timeout.unwrap_or_default())
+            .u64_field(
+                "has max block size",
+                u64::from(self.max_block_size.is_some()),
+            )
+            .u64_field("max block size", self.max_block_size.unwrap_or_default())
+            .u64_field(
+                "has DA committee size",
+                u64::from(da_committee_size.is_some()),
+            )
+            .u64_field("DA committee size", da_committee_size.unwrap_or_default())
+            .finalize()
+    }
+}
+
+/// The values of the runtime parameters in a view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuntimeParameters {
+    /// Base timeout of a view, in milliseconds
+    pub view_timeout: u64,
+    /// Maximum size of a block, or `None` if blocks are unbounded
+    pub max_block_size: Option<u64>,
+    /// Number of DA nodes on the DA committee, or `None` for all of them
+    pub da_committee_size: Option<NonZeroUsize>,
+}
+
+impl RuntimeParameters {
+    /// Apply `change` to the parameters
+    pub fn apply(&mut self, change: &ParameterChange) {
+        if let Some(view_timeout) = change.view_timeout {
+            self.view_timeout = view_timeout;
+        }
+        if let Some(max_block_size) = change.max_block_size {
+            self.max_block_size = Some(max_block_size);
+        }
+        if let Some(da_committee_size) = change.da_committee_size {
+            self.da_committee_size = Some(da_committee_size);
+        }
+    }
+}
+
+/// The runtime parameters a node started with, and the decided changes to them, shared by every
+/// task
+#[derive(Debug)]
+pub struct GovernedParameters<TYPES: NodeType> {
+    /// The parameters the node started with
+    base: RuntimeParameters,
+    /// Decided changes, by the view they take effect in
+    changes: RwLock<BTreeMap<TYPES::View, ParameterChange>>,
+}
+
+impl<TYPES: NodeType> GovernedParameters<TYPES> {
+    /// Parameters which start out as `base`
+    #[must_use]
+    pub fn new(base: RuntimeParameters) -> Self {
+        Self {
+            base,
+            changes: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// The parameters the node started with
+    #[must_use]
+    pub fn base(&self) -> RuntimeParameters {
+        self.base
+    }
+
+    /// The parameters in effect in `view`
+    #[must_use]
+    pub fn at(&self, view: TYPES::View) -> RuntimeParameters {
+        let changes = self.changes.read().unwrap_or_else(PoisonError::into_inner);
+        changes
+            .range(..=view)
+            .fold(self.base, |mut parameters, (_, change)| {
+                parameters.apply(change);
+                parameters
+            })
+    }
+
+    /// Apply `change` from `activation_view` on, after any change decided earlier for the same
+    /// view
+    pub fn schedule(&self, activation_view: TYPES::View, change: ParameterChange) {
+        let mut changes = self.changes.write().unwrap_or_else(PoisonError::into_inner);
+        let scheduled = changes.entry(activation_view).or_default();
+        *scheduled = ParameterChange {
+            view_timeout: change.view_timeout.or(scheduled.view_timeout),
+            max_block_size: change.max_block_size.or(scheduled.max_block_size),
+            da_committee_size: change.da_committee_size.or(scheduled.da_committee_size),
+        };
+    }
+
+    /// The decided changes, by the view they take effect in
+    #[must_use]
+    pub fn scheduled(&self) -> BTreeMap<TYPES::View, ParameterChange> {
+        self.changes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
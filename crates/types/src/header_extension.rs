@@ -0,0 +1,89 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Application-defined fields carried in block headers.
+//!
+//! Applications often need a few extra consensus-validated values in every header, e.g. the L1
+//! block the chain has synced to or the root of a bridge's exit tree. Rather than forking their
+//! header type for each of them, they build a [`HeaderExtension`] in
+//! [`InstanceState::build_header_extension`] when leading, and check the leader's in
+//! [`InstanceState::validate_header_extension`] when voting.
+//!
+//! [`InstanceState::build_header_extension`]: crate::traits::states::InstanceState::build_header_extension
+//! [`InstanceState::validate_header_extension`]: crate::traits::states::InstanceState::validate_header_extension
+
+use std::collections::BTreeMap;
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Versioned set of named, application-defined header fields.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HeaderExtension {
+    /// Version of the extension's layout, defined by the application
+    pub version: u16,
+    /// The fields, by name
+    pub fields: BTreeMap<String, Vec<u8>>,
+}
+
+impl HeaderExtension {
+    /// An empty extension with the given layout version
+    #[must_use]
+    pub fn new(version: u16) -> Self {
+        Self {
+            version,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Set the field `name` to `value`
+    #[must_use]
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) -> Self {
+        self.fields.insert(name.into(), value.into());
+        self
+    }
+
+    /// The value of the field `name`, if it is set
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&[u8]> {
+        self.fields.get(name).map(Vec::as_slice)
+    }
+}
+
+impl Committable for HeaderExtension {
+    fn commit(&self) -> Commitment<Self> {
+        let builder = RawCommitmentBuilder::new("Header Extension")
+            .u64_field("version", u64::from(self.version))
+            .u64_field("fields", self.fields.len() as u64);
+
+        self.fields
+            .iter()
+            .fold(builder, |builder, (name, value)| {
+                builder
+                    .var_size_field("name", name.as_bytes())
+                    .var_size_field("value", value)
+            })
+            .finalize()
+    }
+}
+
+/// Reasons a proposed header extension is rejected.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum HeaderExtensionError {
+    /// The header carries an extension, but the application does not expect one
+    #[error("Header carries an unexpected extension")]
+    Unexpected,
+    /// The application expects an extension, but the header does not carry one
+    #[error("Header is missing its extension")]
+    Missing,
+    /// The extension's layout version is not one the application accepts
+    #[error("Header extension has unsupported version {0}")]
+    UnsupportedVersion(u16),
+    /// The extension does not match what the application expects
+    #[error("Header extension is invalid: {0}")]
+    Invalid(String),
+}
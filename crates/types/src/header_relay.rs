@@ -0,0 +1,155 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Decided headers packaged for relay to bridge and light client contracts.
+//!
+//! For every block it decides, a node emits a `HeaderRelay` event with a [`RelayHeader`]: just the
+//! block header, the QC which certifies it, and the commitment to the stake tables of the block's
+//! epoch and the next, against which a contract checks the QC. [`RelayHeader::encode`] gives the
+//! canonical serialization to submit: bincode with fixed width little endian integers, as
+//! [`bincode_opts`] configures it.
+//!
+//! A relayer which submits in batches feeds the headers to a [`HeaderRelayBatcher`], which packs
+//! every K consecutive headers into one [`HeaderRelayBatch`].
+
+use std::num::NonZeroUsize;
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    simple_certificate::QuorumCertificate2,
+    stake_table::StakeTableCommitment,
+    traits::{block_contents::BlockHeader, node_implementation::NodeType},
+    utils::bincode_opts,
+};
+
+/// A decided block header, with what a contract needs to check that it was decided
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct RelayHeader<TYPES: NodeType> {
+    /// The header of the decided block
+    pub header: TYPES::BlockHeader,
+    /// The QC which certifies the leaf of the block
+    pub qc: QuorumCertificate2<TYPES>,
+    /// Commitment to the stake tables of the block's epoch and the next
+    pub stake_table_commitment: StakeTableCommitment,
+}
+
+impl<TYPES: NodeType> RelayHeader<TYPES> {
+    /// Height of the block
+    #[must_use]
+    pub fn height(&self) -> u64 {
+        self.header.block_number()
+    }
+
+    /// The canonical serialization of the header, to submit to a contract
+    ///
+    /// # Errors
+    /// If the header cannot be serialized
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        bincode_opts()
+            .serialize(self)
+            .wrap()
+            .context(error!("Failed to encode relay header"))
+    }
+}
+
+/// Consecutive decided headers, submitted to a contract as one message
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct HeaderRelayBatch<TYPES: NodeType> {
+    /// The headers, in order of height
+    pub headers: Vec<RelayHeader<TYPES>>,
+}
+
+impl<TYPES: NodeType> HeaderRelayBatch<TYPES> {
+    /// A batch of `headers`
+    ///
+    /// # Errors
+    /// If there are no headers, or they are not consecutive in order of height
+    pub fn new(headers: Vec<RelayHeader<TYPES>>) -> Result<Self> {
+        ensure!(
+            !headers.is_empty(),
+            "A relay batch needs at least one header"
+        );
+        for pair in headers.windows(2) {
+            ensure!(
+                pair[1].height() == pair[0].height() + 1,
+                "Header at height {} does not follow the header at height {}",
+                pair[1].height(),
+                pair[0].height()
+            );
+        }
+
+        Ok(Self { headers })
+    }
+
+    /// The canonical serialization of the batch, to submit to a contract
+    ///
+    /// # Errors
+    /// If the batch cannot be serialized
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        bincode_opts()
+            .serialize(self)
+            .wrap()
+            .context(error!("Failed to encode relay batch"))
+    }
+}
+
+/// Packs decided headers into batches of a fixed number of consecutive headers
+#[derive(Clone, Debug)]
+pub struct HeaderRelayBatcher<TYPES: NodeType> {
+    /// Number of headers in a batch
+    size: NonZeroUsize,
+    /// Headers of the batch being filled, in order of height
+    pending: Vec<RelayHeader<TYPES>>,
+}
+
+impl<TYPES: NodeType> HeaderRelayBatcher<TYPES> {
+    /// A batcher which packs every `size` consecutive headers into a batch
+    #[must_use]
+    pub fn new(size: NonZeroUsize) -> Self {
+        Self {
+            size,
+            pending: Vec::with_capacity(size.get()),
+        }
+    }
+
+    /// Add the next decided header, returning the batch it completes, if any.
+    ///
+    /// A header which doesn't follow the last one added, as when some were missed, starts a new
+    /// batch, and the headers before it are dropped; [`Self::flush`] them first to keep them.
+    pub fn push(&mut self, header: RelayHeader<TYPES>) -> Option<HeaderRelayBatch<TYPES>> {
+        if let Some(last) = self.pending.last() {
+            if header.height() != last.height() + 1 {
+                tracing::warn!(
+                    "Header at height {} does not follow the header at height {}; dropping {} headers",
+                    header.height(),
+                    last.height(),
+                    self.pending.len()
+                );
+                self.pending.clear();
+            }
+        }
+        self.pending.push(header);
+
+        (self.pending.len() == self.size.get()).then(|| self.take())
+    }
+
+    /// The headers added since the last full batch, as a batch of their own, if there are any
+    pub fn flush(&mut self) -> Option<HeaderRelayBatch<TYPES>> {
+        (!self.pending.is_empty()).then(|| self.take())
+    }
+
+    /// Take the pending headers as a batch
+    fn take(&mut self) -> HeaderRelayBatch<TYPES> {
+        HeaderRelayBatch {
+            headers: std::mem::replace(&mut self.pending, Vec::with_capacity(self.size.get())),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Signed liveness beacons.
+//!
+//! When heartbeats are enabled, every validator broadcasts a [`SignedHeartbeat`] once every few
+//! views, carrying the view it is in and the view of its high QC. Receivers keep the latest
+//! heartbeat of each peer in a [`PeerLiveness`] tracker, and consider a peer live while its
+//! heartbeats keep up with the current view.
+
+use std::{collections::HashMap, time::Instant};
+
+use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    message::UpgradeLock,
+    traits::{
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+};
+
+/// The progress of a validator, as it reports it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct Heartbeat<TYPES: NodeType> {
+    /// The view the validator is in
+    pub view: TYPES::View,
+    /// View of the validator's high QC
+    pub high_qc_view: TYPES::View,
+}
+
+impl<TYPES: NodeType> Committable for Heartbeat<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        committable::RawCommitmentBuilder::new("Heartbeat")
+            .u64_field("view", *self.view)
+            .u64_field("high qc view", *self.high_qc_view)
+            .finalize()
+    }
+}
+
+/// A [`Heartbeat`] signed by the validator it is from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct SignedHeartbeat<TYPES: NodeType> {
+    /// The heartbeat
+    pub heartbeat: Heartbeat<TYPES>,
+    /// Signature of the validator on the heartbeat
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> SignedHeartbeat<TYPES> {
+    /// Sign `heartbeat` with `private_key`
+    ///
+    /// # Errors
+    /// If signing fails
+    pub async fn sign<V: Versions>(
+        heartbeat: Heartbeat<TYPES>,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<Self> {
+        let signature = TYPES::SignatureKey::sign(
+            private_key,
+            &upgrade_lock
+                .signing_message(heartbeat.commit().as_ref(), heartbeat.view)
+                .await,
+        )
+        .wrap()
+        .context(error!("Failed to sign heartbeat"))?;
+
+        Ok(Self {
+            heartbeat,
+            signature,
+        })
+    }
+
+    /// Whether the heartbeat was signed by `key`
+    pub async fn is_signed_by<V: Versions>(
+        &self,
+        key: &TYPES::SignatureKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> bool {
+        key.validate(
+            &self.signature,
+            &upgrade_lock
+                .signing_message(self.heartbeat.commit().as_ref(), self.heartbeat.view)
+                .await,
+        )
+    }
+}
+
+/// The latest heartbeat of a peer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerStatus<TYPES: NodeType> {
+    /// The latest heartbeat the peer sent
+    pub heartbeat: Heartbeat<TYPES>,
+    /// When we received it
+    pub received_at: Instant,
+}
+
+/// The latest heartbeat of each peer
+#[derive(Clone, Debug)]
+pub struct PeerLiveness<TYPES: NodeType> {
+    /// The status of each peer we heard from
+    peers: HashMap<TYPES::SignatureKey, PeerStatus<TYPES>>,
+}
+
+impl<TYPES: NodeType> Default for PeerLiveness<TYPES> {
+    fn default() -> Self {
+        Self {
+            peers: HashMap::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> PeerLiveness<TYPES> {
+    /// Record a heartbeat from `peer`, received at `now`. Returns whether it was newer than the
+    /// latest we had from the peer, and so was kept.
+    pub fn record(
+        &mut self,
+        peer: TYPES::SignatureKey,
+        heartbeat: Heartbeat<TYPES>,
+        now: Instant,
+    ) -> bool {
+        if self
+            .peers
+            .get(&peer)
+            .is_some_and(|status| status.heartbeat.view >= heartbeat.view)
+        {
+            return false;
+        }
+
+        self.peers.insert(
+            peer,
+            PeerStatus {
+                heartbeat,
+                received_at: now,
+            },
+        );
+        true
+    }
+
+    /// The latest heartbeat of `peer`, if we heard from it
+    #[must_use]
+    pub fn status(&self, peer: &TYPES::SignatureKey) -> Option<&PeerStatus<TYPES>> {
+        self.peers.get(peer)
+    }
+
+    /// Whether the latest heartbeat of `peer` is at most `window` views behind `cur_view`
+    #[must_use]
+    pub fn is_live(&self, peer: &TYPES::SignatureKey, cur_view: TYPES::View, window: u64) -> bool {
+        self.peers
+            .get(peer)
+            .is_some_and(|status| status.heartbeat.view.is_within(cur_view, window))
+    }
+
+    /// The peers whose latest heartbeat is at most `window` views behind `cur_view`
+    pub fn live_peers(
+        &self,
+        cur_view: TYPES::View,
+        window: u64,
+    ) -> impl Iterator<Item = &TYPES::SignatureKey> + '_ {
+        self.peers
+            .iter()
+            .filter(move |(_, status)| status.heartbeat.view.is_within(cur_view, window))
+            .map(|(peer, _)| peer)
+    }
+}
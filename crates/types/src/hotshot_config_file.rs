@@ -4,14 +4,30 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{num::NonZeroUsize, time::Duration};
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+    time::Duration,
+};
 
 use url::Url;
 use vec1::Vec1;
 
 use crate::{
-    constants::REQUEST_DATA_DELAY, traits::signature_key::SignatureKey,
-    upgrade_config::UpgradeConfig, HotShotConfig, PeerConfig, ValidatorConfig,
+    catchup::CatchupConfig,
+    channel_depth::ChannelCapacities,
+    constants::{
+        ANTI_ENTROPY_INTERVAL, CONNECTION_WARM_UP_VIEWS, EVENT_CHANNEL_WARNING_FRACTION,
+        REQUEST_DATA_DELAY, VIEW_SYNC_RELAYS_PER_ROUND,
+    },
+    fork_choice::ForkChoiceRule,
+    message::ReplayProtectionConfig,
+    message_ttl::MessageTtlConfig,
+    network::{GossipPreset, GossipTuning},
+    peer_score::PeerScoreConfig,
+    traits::signature_key::SignatureKey,
+    upgrade_config::UpgradeConfig,
+    BackgroundWorkBudget, HotShotConfig, PeerConfig, ValidatorConfig,
 };
 
 /// Default builder URL, used as placeholder
@@ -55,6 +71,83 @@ pub struct HotShotConfigFile<KEY: SignatureKey> {
     pub upgrade: UpgradeConfig,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// Directory to write post-mortem bundles to
+    #[serde(default)]
+    pub post_mortem_dir: Option<PathBuf>,
+    /// Replay protection window for each type of message
+    #[serde(default)]
+    pub replay_protection: ReplayProtectionConfig,
+    /// Number of views a task may lag behind the others before a warning is logged
+    #[serde(default)]
+    pub max_task_view_lag: Option<u64>,
+    /// Percentage of each block reserved for high-priority transactions
+    #[serde(default)]
+    pub high_priority_reserved_percent: u64,
+    /// Named gossipsub mesh parameters for the size of the network
+    #[serde(default)]
+    pub gossip_preset: GossipPreset,
+    /// Gossipsub mesh parameters, which replace those of the preset if given
+    #[serde(default)]
+    pub gossip: Option<GossipTuning>,
+    /// Number of upcoming views whose leaders we connect to ahead of time
+    #[serde(default)]
+    pub connection_warm_up_views: Option<u64>,
+    /// How long each class of message stays relevant
+    #[serde(default)]
+    pub message_ttl: MessageTtlConfig,
+    /// Interval between anti-entropy exchanges of certificates with a random peer
+    #[serde(default)]
+    pub anti_entropy_interval: Option<Duration>,
+    /// Fraction of an event channel's capacity above which we warn if it stays that full
+    #[serde(default)]
+    pub event_channel_warning_fraction: Option<f64>,
+    /// Number of messages each bounded channel holds
+    #[serde(default)]
+    pub channel_capacities: ChannelCapacities,
+    /// Identifier of the chain, which signatures on votes and proposals are bound to from the
+    /// `Versions::ChainId` version on
+    #[serde(default)]
+    pub chain_id: u64,
+    /// Number of views between the signed heartbeats each validator broadcasts, if any
+    #[serde(default)]
+    pub heartbeat_interval: Option<NonZeroU64>,
+    /// Number of relays a view sync replica tries within one view sync timeout
+    #[serde(default)]
+    pub view_sync_relays_per_round: Option<u32>,
+    /// How block payloads are made available
+    #[serde(default)]
+    pub data_availability: DataAvailabilityMode,
+    /// Whether to use majority thresholds for a development network of at most three nodes
+    #[serde(default)]
+    pub small_network: bool,
+    /// Randomness beacon for committees which sample per-view quorums
+    #[serde(default)]
+    pub committee_sampling_beacon: Option<[u8; 32]>,
+    /// Penalties for misbehaving peers and when they are banned for them
+    #[serde(default)]
+    pub peer_scoring: PeerScoreConfig,
+    /// Minimum size of the quorum proposals which are erasure coded among the quorum instead of
+    /// broadcast
+    #[serde(default)]
+    pub proposal_dispersal_min_bytes: Option<u64>,
+    /// Upload bandwidth in bytes per second which our sends are paced within
+    #[serde(default)]
+    pub upload_budget: Option<NonZeroU64>,
+    /// Whether to serve decided leaves the state computed when voting for them
+    #[serde(default)]
+    pub speculative_execution: bool,
+    /// Rule for choosing among QCs for the same view
+    #[serde(default)]
+    pub fork_choice: ForkChoiceRule,
+    /// CPU time background work may take up in each view
+    #[serde(default)]
+    pub background_work_budget: Option<BackgroundWorkBudget>,
+    /// Whether to rehearse the decide rule of the version we are set to upgrade to
+    #[serde(default)]
+    pub shadow_upgrade: bool,
+    /// When to catch up on missed decided leaves, and how much catchup to serve peers
+    #[serde(default)]
+    pub catchup: CatchupConfig,
 }
 
 impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
@@ -83,6 +176,36 @@ impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
             start_voting_time: val.upgrade.start_voting_time,
             stop_voting_time: val.upgrade.stop_voting_time,
             epoch_height: val.epoch_height,
+            post_mortem_dir: val.post_mortem_dir,
+            replay_protection: val.replay_protection,
+            max_task_view_lag: val.max_task_view_lag,
+            high_priority_reserved_percent: val.high_priority_reserved_percent,
+            gossip: val.gossip.unwrap_or_else(|| val.gossip_preset.tuning()),
+            connection_warm_up_views: val
+                .connection_warm_up_views
+                .unwrap_or(CONNECTION_WARM_UP_VIEWS),
+            message_ttl: val.message_ttl,
+            anti_entropy_interval: val.anti_entropy_interval.unwrap_or(ANTI_ENTROPY_INTERVAL),
+            event_channel_warning_fraction: val
+                .event_channel_warning_fraction
+                .unwrap_or(EVENT_CHANNEL_WARNING_FRACTION),
+            channel_capacities: val.channel_capacities,
+            chain_id: val.chain_id,
+            heartbeat_interval: val.heartbeat_interval,
+            view_sync_relays_per_round: val
+                .view_sync_relays_per_round
+                .unwrap_or(VIEW_SYNC_RELAYS_PER_ROUND),
+            data_availability: val.data_availability,
+            small_network: val.small_network,
+            committee_sampling_beacon: val.committee_sampling_beacon,
+            peer_scoring: val.peer_scoring,
+            proposal_dispersal_min_bytes: val.proposal_dispersal_min_bytes,
+            upload_budget: val.upload_budget,
+            speculative_execution: val.speculative_execution,
+            fork_choice: val.fork_choice,
+            background_work_budget: val.background_work_budget,
+            shadow_upgrade: val.shadow_upgrade,
+            catchup: val.catchup,
         }
     }
 }
@@ -128,6 +251,62 @@ impl<KEY: SignatureKey> HotShotConfigFile<KEY> {
             builder_urls: default_builder_urls(),
             upgrade: UpgradeConfig::default(),
             epoch_height: 0,
+            post_mortem_dir: None,
+            replay_protection: ReplayProtectionConfig::default(),
+            max_task_view_lag: None,
+            high_priority_reserved_percent: 0,
+            gossip_preset: GossipPreset::default(),
+            gossip: None,
+            connection_warm_up_views: None,
+            message_ttl: MessageTtlConfig::default(),
+            anti_entropy_interval: None,
+            event_channel_warning_fraction: None,
+            channel_capacities: ChannelCapacities::default(),
+            chain_id: 0,
+            heartbeat_interval: None,
+            view_sync_relays_per_round: None,
+            data_availability: DataAvailabilityMode::default(),
+            small_network: false,
+            committee_sampling_beacon: None,
+            peer_scoring: PeerScoreConfig::default(),
+            proposal_dispersal_min_bytes: None,
+            upload_budget: None,
+            speculative_execution: false,
+            fork_choice: ForkChoiceRule::default(),
+            background_work_budget: None,
+            shadow_upgrade: false,
+            catchup: CatchupConfig::default(),
+        }
+    }
+
+    /// Creates a `HotShotConfigFile` for a local development chain run by `validator_config`
+    /// alone, with timers as short as a single node allows.
+    ///
+    /// The node leads every view and its vote alone forms each certificate, so views advance as
+    /// fast as the node can build blocks. The builder timeout is short so that the node quickly
+    /// falls back to building blocks itself when no builder runs at `builder_url`.
+    ///
+    /// # Panics
+    ///
+    /// Cannot panic, but will if `NonZeroUsize` is somehow an error.
+    #[must_use]
+    pub fn dev_single_node(validator_config: &ValidatorConfig<KEY>, builder_url: Url) -> Self {
+        let peer_config = validator_config.public_config();
+
+        Self {
+            num_nodes_with_stake: NonZeroUsize::new(1).unwrap(),
+            start_threshold: (1, 1),
+            known_nodes_with_stake: vec![peer_config.clone()],
+            staked_da_nodes: 1,
+            known_da_nodes: vec![peer_config],
+            fixed_leader_for_gpuvid: 0,
+            next_view_timeout: 1000,
+            view_sync_timeout: Duration::from_millis(500),
+            num_bootstrap: 1,
+            builder_timeout: Duration::from_millis(100),
+            data_request_delay: Some(Duration::from_millis(10)),
+            builder_urls: vec1::vec1![builder_url],
+            ..Self::hotshot_config_5_nodes_10_da()
         }
     }
 }
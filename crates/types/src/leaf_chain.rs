@@ -0,0 +1,98 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Independent verification of a chain of leaves.
+//!
+//! A service ingesting decide events can check for itself that the leaves it was given are
+//! decided, knowing only the membership, with [`verify_leaf_chain`].
+
+use async_lock::RwLock;
+use committable::Committable;
+use utils::anytrace::*;
+
+use crate::{
+    data::Leaf2,
+    message::UpgradeLock,
+    traits::{
+        election::Membership,
+        node_implementation::{NodeType, Versions},
+    },
+    vote::{Certificate, HasViewNumber},
+};
+
+/// Verify a chain of leaves against `membership`, returning how many of them, from the first on,
+/// are shown to be decided.
+///
+/// `leaves` are in chain order, oldest first; decide events list their leaves newest first. Each
+/// leaf must extend the one before it, with a valid QC for it, signed by the quorum sampled for
+/// the QC's view, up to the membership's success threshold over that quorum.
+/// A leaf is decided once it starts a three-chain: it is followed by two leaves in the next two
+/// views, and the second of those is itself certified by the QC of a later leaf. Every leaf up to
+/// the last which starts a three-chain is decided, so the chain must end with the three leaves
+/// which follow the last decided leaf.
+///
+/// # Errors
+/// If a leaf does not extend the one before it, a QC is invalid, or no leaf is shown to be decided
+pub async fn verify_leaf_chain<TYPES: NodeType, V: Versions>(
+    leaves: &[Leaf2<TYPES>],
+    membership: &RwLock<TYPES::Membership>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+) -> Result<usize> {
+    ensure!(!leaves.is_empty(), "The leaf chain is empty");
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        let justify_qc = leaf.justify_qc();
+        let membership_reader = membership.read().await;
+        let stake_table =
+            membership_reader.sampled_stake_table(justify_qc.view_number(), justify_qc.data.epoch);
+        let threshold = membership_reader
+            .sampled_success_threshold(justify_qc.view_number(), justify_qc.data.epoch);
+        drop(membership_reader);
+        ensure!(
+            justify_qc
+                .is_valid_cert(stake_table, threshold, upgrade_lock)
+                .await,
+            "The QC of the leaf for view {} is invalid",
+            leaf.view_number()
+        );
+
+        let Some(parent) = i.checked_sub(1).map(|parent| &leaves[parent]) else {
+            continue;
+        };
+        ensure!(
+            leaf.parent_commitment() == parent.commit(),
+            "The leaf for view {} does not extend the leaf for view {}",
+            leaf.view_number(),
+            parent.view_number()
+        );
+        ensure!(
+            leaf.height() == parent.height() + 1,
+            "The leaf for view {} has height {}, but its parent has height {}",
+            leaf.view_number(),
+            leaf.height(),
+            parent.height()
+        );
+        ensure!(
+            justify_qc.view_number() == parent.view_number()
+                && justify_qc.data.leaf_commit == parent.commit(),
+            "The QC of the leaf for view {} does not certify its parent",
+            leaf.view_number()
+        );
+    }
+
+    // The leaf certified by the QC of the last leaf ends the newest possible three-chain
+    let last_decided = (0..leaves.len().saturating_sub(3))
+        .rev()
+        .find(|&i| {
+            leaves[i + 1].view_number() == leaves[i].view_number() + 1
+                && leaves[i + 2].view_number() == leaves[i + 1].view_number() + 1
+        })
+        .context(error!(
+            "No leaf in the chain starts a three-chain, so none is shown to be decided"
+        ))?;
+
+    Ok(last_decided + 1)
+}
@@ -5,7 +5,14 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! Types and Traits for the `HotShot` consensus module
-use std::{fmt::Debug, future::Future, num::NonZeroUsize, pin::Pin, time::Duration};
+use std::{
+    fmt::Debug,
+    future::Future,
+    num::{NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+    pin::Pin,
+    time::Duration,
+};
 
 use bincode::Options;
 use displaydoc::Display;
@@ -15,27 +22,58 @@ use traits::signature_key::SignatureKey;
 use url::Url;
 use vec1::Vec1;
 
-use crate::utils::bincode_opts;
+use crate::{
+    catchup::CatchupConfig, channel_depth::ChannelCapacities, fork_choice::ForkChoiceRule,
+    message::ReplayProtectionConfig, message_ttl::MessageTtlConfig, network::GossipTuning,
+    peer_score::PeerScoreConfig, stake_table::ThresholdConfig, utils::bincode_opts,
+};
+pub mod alert;
+pub mod anti_entropy;
 pub mod bundle;
+pub mod catchup;
+pub mod chain_config;
+pub mod channel_depth;
+pub mod clock;
+pub mod compact_vote;
+pub mod config_loader;
+pub mod config_validation;
 pub mod consensus;
 pub mod constants;
 pub mod data;
 /// Holds the types and functions for DRB computation.
 pub mod drb;
+pub mod encrypted_mempool;
 pub mod error;
 pub mod event;
+pub mod external_event;
+pub mod fee_market;
+pub mod fork_choice;
+pub mod governance;
+pub mod header_extension;
+pub mod header_relay;
+pub mod heartbeat;
 /// Holds the configuration file specification for a HotShot node.
 pub mod hotshot_config_file;
+pub mod leaf_chain;
 pub mod light_client;
 pub mod message;
+pub mod message_ttl;
+pub mod migration;
+pub mod participation;
+pub mod peer_score;
+pub mod persistence;
+pub mod proposal_dispersal;
 
 /// Holds the network configuration specification for HotShot nodes.
 pub mod network;
 pub mod qc;
+pub mod read_replica;
 pub mod request_response;
 pub mod signature_key;
 pub mod simple_certificate;
 pub mod simple_vote;
+pub mod snapshot;
+pub mod snark_commitment;
 pub mod stake_table;
 pub mod traits;
 
@@ -44,8 +82,13 @@ pub mod upgrade_config;
 pub mod utils;
 /// Holds the validator configuration specification for HotShot nodes.
 pub mod validator_config;
+pub mod validator_set;
 pub mod vid;
+pub mod view_history;
+pub mod view_map;
+pub mod view_tracker;
 pub mod vote;
+pub mod wire_codec;
 
 /// Pinned future that is Send and Sync
 pub type BoxSyncFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + Sync + 'a>>;
@@ -208,9 +251,194 @@ pub struct HotShotConfig<KEY: SignatureKey> {
     pub stop_voting_time: u64,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// Directory to write post-mortem bundles to when the node stalls or a safety check fails.
+    /// No bundles are collected if this is unset.
+    #[serde(default)]
+    pub post_mortem_dir: Option<PathBuf>,
+    /// How far in the past or future of the current view we accept each type of message
+    #[serde(default)]
+    pub replay_protection: ReplayProtectionConfig,
+    /// Debug mode: warn when a task's view falls more than this many views behind the most
+    /// advanced task. Lagging tasks are not checked for if this is unset.
+    #[serde(default)]
+    pub max_task_view_lag: Option<u64>,
+    /// Percentage of each block the leader reserves for high-priority transactions, when it
+    /// assembles the block itself and the chain has a maximum block size
+    #[serde(default)]
+    pub high_priority_reserved_percent: u64,
+    /// Gossipsub mesh parameters of libp2p networks
+    #[serde(default)]
+    pub gossip: GossipTuning,
+    /// Number of upcoming views whose leaders the network task connects to ahead of time, so that
+    /// votes to them don't wait on connection setup
+    #[serde(default = "default_connection_warm_up_views")]
+    pub connection_warm_up_views: u64,
+    /// How long each class of message stays relevant, after which receivers drop it unread
+    #[serde(default)]
+    pub message_ttl: MessageTtlConfig,
+    /// Interval between anti-entropy exchanges, in which we send the views of our highest
+    /// certificates to a random peer and it sends back any newer ones
+    #[serde(default = "default_anti_entropy_interval")]
+    pub anti_entropy_interval: Duration,
+    /// Fraction of an event channel's capacity above which we warn if it stays that full
+    #[serde(default = "default_event_channel_warning_fraction")]
+    pub event_channel_warning_fraction: f64,
+    /// Number of messages each bounded channel holds
+    #[serde(default)]
+    pub channel_capacities: ChannelCapacities,
+    /// Identifier of the chain, which signatures on votes and proposals are bound to from the
+    /// `Versions::ChainId` version on, so that messages signed for another chain with the same
+    /// keys are rejected
+    #[serde(default)]
+    pub chain_id: u64,
+    /// Number of views between the signed heartbeats each validator broadcasts, or `None` to not
+    /// send or track heartbeats
+    #[serde(default)]
+    pub heartbeat_interval: Option<NonZeroU64>,
+    /// Number of relays a view sync replica tries within one view sync timeout. Each relay gets
+    /// an equal share of the timeout before the replica fails over to the next.
+    #[serde(default = "default_view_sync_relays_per_round")]
+    pub view_sync_relays_per_round: u32,
+    /// How block payloads are made available. Chosen at genesis; every node must agree on it.
+    #[serde(default)]
+    pub data_availability: DataAvailabilityMode,
+    /// Development mode for networks of at most three nodes: certificates need a majority of the
+    /// stake instead of two thirds of it, so that a node going down does not stop the network.
+    /// Only crash faults are tolerated in this mode.
+    #[serde(default)]
+    pub small_network: bool,
+    /// Randomness beacon which committees sampling per-view quorums draw their samples with from
+    /// genesis, until one is derived from the DRB result decided in an epoch for the epoch after
+    /// next. It must be the same on every node and unpredictable before it is set; committees
+    /// which sample use their full membership as every view's quorum, with a warning, until a
+    /// beacon applies.
+    #[serde(default)]
+    pub committee_sampling_beacon: Option<[u8; 32]>,
+    /// Penalties for misbehaving peers and when they are banned for them
+    #[serde(default)]
+    pub peer_scoring: PeerScoreConfig,
+    /// Quorum proposals at least this many bytes long are erasure coded among the quorum, with
+    /// each member forwarding its chunk, instead of broadcast by the leader. Proposals are always
+    /// broadcast if this is unset.
+    #[serde(default)]
+    pub proposal_dispersal_min_bytes: Option<u64>,
+    /// Upload bandwidth in bytes per second which our sends are paced within, proposals first,
+    /// then control messages, DA proposals and VID shares. Sends are not paced if this is unset.
+    #[serde(default)]
+    pub upload_budget: Option<NonZeroU64>,
+    /// Cache the validated state computed for each proposal we vote for by leaf commitment, and
+    /// serve decided leaves their state from the cache
+    #[serde(default)]
+    pub speculative_execution: bool,
+    /// Rule for choosing which of the QCs we know for the same view to build on. Every node must
+    /// use the same rule, as replicas check the leader's choice against it.
+    #[serde(default)]
+    pub fork_choice: ForkChoiceRule,
+    /// CPU time background work such as optimistic VID encoding and storage flushes may take up
+    /// in each view. Background work is never deferred if this is unset.
+    #[serde(default)]
+    pub background_work_budget: Option<BackgroundWorkBudget>,
+    /// Rehearse an upgrade: alongside the live protocol, run the decide rule of the version we
+    /// are set to upgrade to against the same proposals, and report where the two diverge. The
+    /// rehearsal sends no messages and changes no state.
+    #[serde(default)]
+    pub shadow_upgrade: bool,
+    /// When we catch up on the decided leaves we missed from a peer, and how much catchup we
+    /// serve peers
+    #[serde(default)]
+    pub catchup: CatchupConfig,
+}
+
+/// How much of each view background work may take up, and when it is deferred to the next view
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BackgroundWorkBudget {
+    /// Share of the view timeout, in percent, that background work may take up
+    pub percent_of_view: u8,
+    /// Low priority background work is deferred to the next view once the view deadline is this
+    /// close, or the budget of the view is spent
+    pub deadline_margin: Duration,
+}
+
+/// How block payloads are made available to the network
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum DataAvailabilityMode {
+    /// The leader sends the full payload to the DA committee, and disperses VID shares of it to
+    /// every node
+    #[default]
+    Committee,
+    /// Every node is in the DA committee and receives the full payload. The leader sends no VID
+    /// shares; each node derives its own from the payload instead.
+    FullReplication,
+}
+
+/// Connect to the leaders of as many views ahead as we look up leaders for
+fn default_connection_warm_up_views() -> u64 {
+    constants::CONNECTION_WARM_UP_VIEWS
+}
+
+/// Exchange certificates with a peer at the default interval
+fn default_anti_entropy_interval() -> Duration {
+    constants::ANTI_ENTROPY_INTERVAL
+}
+
+/// Warn about event channels filled past the default fraction of their capacity
+fn default_event_channel_warning_fraction() -> f64 {
+    constants::EVENT_CHANNEL_WARNING_FRACTION
+}
+
+/// Try the default number of view sync relays in each view sync timeout
+fn default_view_sync_relays_per_round() -> u32 {
+    constants::VIEW_SYNC_RELAYS_PER_ROUND
 }
 
 impl<KEY: SignatureKey> HotShotConfig<KEY> {
+    /// How long a view sync replica waits on one relay before failing over to the next
+    #[must_use]
+    pub fn view_sync_relay_timeout(&self) -> Duration {
+        self.view_sync_timeout / self.view_sync_relays_per_round.max(1)
+    }
+
+    /// The threshold functions every membership of this node computes certificate thresholds
+    /// with, if the configuration overrides them. Only small network mode does, and only if the
+    /// network has at most [`constants::SMALL_NETWORK_MAX_NODES`] nodes; otherwise each membership
+    /// keeps its own thresholds.
+    #[must_use]
+    pub fn thresholds(&self) -> Option<ThresholdConfig> {
+        if !self.small_network {
+            return None;
+        }
+
+        if self.known_nodes_with_stake.len() > constants::SMALL_NETWORK_MAX_NODES {
+            error!(
+                "Small network mode is enabled, but the network has {} nodes, more than {}. Keeping the membership's thresholds.",
+                self.known_nodes_with_stake.len(),
+                constants::SMALL_NETWORK_MAX_NODES
+            );
+            return None;
+        }
+
+        Some(ThresholdConfig::small_network())
+    }
+
+    /// Whether every node stores every payload, instead of running a DA committee
+    #[must_use]
+    pub fn full_replication(&self) -> bool {
+        self.data_availability == DataAvailabilityMode::FullReplication
+    }
+
+    /// The DA committee: every known node with stake under full replication, and the known DA
+    /// nodes otherwise
+    #[must_use]
+    pub fn da_committee(&self) -> Vec<PeerConfig<KEY>> {
+        if self.full_replication() {
+            self.known_nodes_with_stake.clone()
+        } else {
+            self.known_da_nodes.clone()
+        }
+    }
+
     /// Update a hotshot config to have a view-based upgrade.
     pub fn set_view_upgrade(&mut self, view: u64) {
         self.start_proposing_view = view;
@@ -222,4 +450,13 @@ impl<KEY: SignatureKey> HotShotConfig<KEY> {
         self.start_voting_time = 0;
         self.stop_voting_time = u64::MAX;
     }
+
+    /// The keys of the known nodes with stake, in stake table order
+    #[must_use]
+    pub fn known_stake_keys(&self) -> Vec<KEY> {
+        self.known_nodes_with_stake
+            .iter()
+            .map(|peer| KEY::public_key(&peer.stake_table_entry))
+            .collect()
+    }
 }
@@ -16,7 +16,7 @@ use std::{
 };
 
 use async_lock::RwLock;
-use committable::Committable;
+use committable::{Commitment, Committable};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use utils::anytrace::*;
 use vbs::{
@@ -25,10 +25,17 @@ use vbs::{
 };
 
 use crate::{
+    anti_entropy::{CertificateDigest, CertificateSync},
+    catchup::{CatchupPage, CatchupRequest},
+    compact_vote::CompactQuorumVote,
+    constants::{MAX_OUTSTANDING_VIEWS, REPLAY_WINDOW_FUTURE_VIEWS, REPLAY_WINDOW_PAST_VIEWS},
     data::{
         DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, UpgradeProposal,
         VidDisperseShare, VidDisperseShare2,
     },
+    encrypted_mempool::DecryptionShares,
+    heartbeat::SignedHeartbeat,
+    proposal_dispersal::ProposalChunk,
     request_response::ProposalRequestPayload,
     simple_certificate::{
         DaCertificate, DaCertificate2, QuorumCertificate2, UpgradeCertificate,
@@ -42,13 +49,15 @@ use crate::{
     },
     traits::{
         block_contents::BlockHeader,
+        commitment_scheme::CommitmentScheme,
         election::Membership,
-        network::{DataRequest, ResponseMessage, ViewMessage},
+        network::{DataRequest, ResponseMessage, TransmitType, ViewMessage},
         node_implementation::{ConsensusTime, NodeType, Versions},
         signature_key::SignatureKey,
     },
     utils::{epoch_from_block_number, mnemonic},
     vote::HasViewNumber,
+    wire_codec::WireFormat,
 };
 
 /// Incoming message
@@ -78,6 +87,24 @@ impl<TYPES: NodeType> HasViewNumber<TYPES> for Message<TYPES> {
     }
 }
 
+/// A safety-critical message on its way out, which is persisted until it is sent so that a node
+/// which restarts before sending it can send it then
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(bound(deserialize = "", serialize = ""))]
+pub struct OutboundMessage<TYPES: NodeType> {
+    /// The message
+    pub message: Message<TYPES>,
+
+    /// Who the message is for
+    pub transmit: TransmitType<TYPES>,
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for OutboundMessage<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.message.view_number()
+    }
+}
+
 /// A wrapper type for implementing `PassType` on a vector of `Message`.
 #[derive(Clone, Debug)]
 pub struct Messages<TYPES: NodeType>(pub Vec<Message<TYPES>>);
@@ -156,8 +183,16 @@ impl<TYPES: NodeType> ViewMessage<TYPES> for MessageKind<TYPES> {
     fn view_number(&self) -> TYPES::View {
         match &self {
             MessageKind::Consensus(message) => message.view_number(),
-            MessageKind::Data(DataMessage::SubmitTransaction(_, v)) => *v,
+            MessageKind::Data(
+                DataMessage::SubmitTransaction(_, v)
+                | DataMessage::SubmitPrioritizedTransaction(_, _, v),
+            ) => *v,
             MessageKind::Data(DataMessage::RequestData(msg)) => msg.view,
+            MessageKind::Data(DataMessage::CertificateDigest(digest)) => digest.view,
+            MessageKind::Data(DataMessage::CertificateSync(sync)) => sync.view,
+            MessageKind::Data(DataMessage::CatchupRequest(request)) => request.view,
+            MessageKind::Data(DataMessage::CatchupPage(page)) => page.view,
+            MessageKind::Data(DataMessage::Heartbeat(heartbeat)) => heartbeat.heartbeat.view,
             MessageKind::Data(DataMessage::DataResponse(msg)) => match msg {
                 ResponseMessage::Found(m) => m.view_number(),
                 ResponseMessage::NotFound | ResponseMessage::Denied => TYPES::View::new(1),
@@ -167,6 +202,220 @@ impl<TYPES: NodeType> ViewMessage<TYPES> for MessageKind<TYPES> {
     }
 }
 
+/// A range of views around the current view
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewTolerance {
+    /// How many views before the current view are included
+    pub past: u64,
+    /// How many views after the current view are included
+    pub future: u64,
+}
+
+impl ViewTolerance {
+    /// Create a new tolerance of `past` views before and `future` views after the current view
+    #[must_use]
+    pub const fn new(past: u64, future: u64) -> Self {
+        Self { past, future }
+    }
+
+    /// Whether `view` is within this tolerance of `cur_view`
+    #[must_use]
+    pub fn contains(&self, view: u64, cur_view: u64) -> bool {
+        view.saturating_add(self.past) >= cur_view && view <= cur_view.saturating_add(self.future)
+    }
+}
+
+impl Default for ViewTolerance {
+    fn default() -> Self {
+        Self::new(REPLAY_WINDOW_PAST_VIEWS, REPLAY_WINDOW_FUTURE_VIEWS)
+    }
+}
+
+/// Replay protection window: how far in the past or future of the current view a message of each
+/// type may be before it is rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayProtectionConfig {
+    /// Quorum, DA and upgrade proposals
+    pub proposal: ViewTolerance,
+    /// Quorum, timeout, DA and upgrade votes
+    pub vote: ViewTolerance,
+    /// High QCs and DA certificates
+    pub certificate: ViewTolerance,
+    /// View sync votes and certificates. By default they may be for any view after the current
+    /// one, since view sync is how we move ahead to the view the network is in.
+    pub view_sync: ViewTolerance,
+    /// VID shares and requests for them. Shares for the next view are sent one view early.
+    pub vid: ViewTolerance,
+    /// How many views after the current view we accept any message tied to a view other than for
+    /// view sync. This bounds the per-view state we hold however far ahead the network is after
+    /// an outage: rather than buffer every view we missed, we move ahead with view sync and catch
+    /// up on the leaves decided meanwhile.
+    pub max_outstanding_views: u64,
+}
+
+impl Default for ReplayProtectionConfig {
+    fn default() -> Self {
+        Self {
+            proposal: ViewTolerance::default(),
+            vote: ViewTolerance::default(),
+            certificate: ViewTolerance::default(),
+            view_sync: ViewTolerance::new(REPLAY_WINDOW_PAST_VIEWS, u64::MAX),
+            vid: ViewTolerance::new(REPLAY_WINDOW_PAST_VIEWS, 1),
+            max_outstanding_views: MAX_OUTSTANDING_VIEWS,
+        }
+    }
+}
+
+impl ReplayProtectionConfig {
+    /// The tolerance for a message, or `None` if it is not subject to replay protection.
+    ///
+    /// Responses to proposal and data requests are exempt, since we request them for old views,
+    /// as are transactions and external messages, which are not tied to a view.
+    #[must_use]
+    pub fn tolerance<TYPES: NodeType>(&self, kind: &MessageKind<TYPES>) -> Option<ViewTolerance> {
+        match MessageClass::of(kind) {
+            MessageClass::Proposal => Some(self.proposal),
+            MessageClass::Vote => Some(self.vote),
+            MessageClass::Certificate => Some(self.certificate),
+            MessageClass::ViewSync => Some(self.view_sync),
+            MessageClass::Vid => Some(self.vid),
+            MessageClass::Unbound => None,
+        }
+    }
+
+    /// Whether a message for `view` is too far after `cur_view` for us to hold state for.
+    ///
+    /// View sync messages are exempt, since view sync is how we move ahead to the view the network
+    /// is in, as are messages not subject to replay protection.
+    #[must_use]
+    pub fn beyond_horizon<TYPES: NodeType>(
+        &self,
+        kind: &MessageKind<TYPES>,
+        view: u64,
+        cur_view: u64,
+    ) -> bool {
+        !matches!(
+            MessageClass::of(kind),
+            MessageClass::ViewSync | MessageClass::Unbound
+        ) && view > cur_view.saturating_add(self.max_outstanding_views)
+    }
+}
+
+/// The class of a message, which decides how long it stays relevant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum MessageClass {
+    /// Quorum, DA and upgrade proposals
+    Proposal = 0,
+    /// Quorum, timeout, DA and upgrade votes
+    Vote = 1,
+    /// High QCs and DA certificates
+    Certificate = 2,
+    /// View sync votes and certificates
+    ViewSync = 3,
+    /// VID shares, decryption shares and requests for data
+    Vid = 4,
+    /// Responses to requests, transactions and external messages, which are not tied to the
+    /// current view
+    Unbound = 5,
+}
+
+impl MessageClass {
+    /// Every class of message
+    pub const ALL: [Self; 6] = [
+        Self::Proposal,
+        Self::Vote,
+        Self::Certificate,
+        Self::ViewSync,
+        Self::Vid,
+        Self::Unbound,
+    ];
+
+    /// The class of `kind`
+    #[must_use]
+    pub fn of<TYPES: NodeType>(kind: &MessageKind<TYPES>) -> Self {
+        match kind {
+            MessageKind::Consensus(SequencingMessage::General(message)) => match message {
+                GeneralConsensusMessage::Proposal(_)
+                | GeneralConsensusMessage::Proposal2(_)
+                | GeneralConsensusMessage::ProposalChunk(_)
+                | GeneralConsensusMessage::UpgradeProposal(_) => Self::Proposal,
+                GeneralConsensusMessage::Vote(_)
+                | GeneralConsensusMessage::Vote2(_)
+                | GeneralConsensusMessage::CompactVote(_)
+                | GeneralConsensusMessage::TimeoutVote(_)
+                | GeneralConsensusMessage::TimeoutVote2(_)
+                | GeneralConsensusMessage::UpgradeVote(_) => Self::Vote,
+                GeneralConsensusMessage::HighQc(_) => Self::Certificate,
+                GeneralConsensusMessage::ViewSyncPreCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote2(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate2(_) => Self::ViewSync,
+                GeneralConsensusMessage::ProposalRequested(..)
+                | GeneralConsensusMessage::ProposalResponse(_)
+                | GeneralConsensusMessage::ProposalResponse2(_) => Self::Unbound,
+            },
+            MessageKind::Consensus(SequencingMessage::Da(message)) => match message {
+                DaConsensusMessage::DaProposal(_) | DaConsensusMessage::DaProposal2(_) => {
+                    Self::Proposal
+                }
+                DaConsensusMessage::DaVote(_) | DaConsensusMessage::DaVote2(_) => Self::Vote,
+                DaConsensusMessage::DaCertificate(_) | DaConsensusMessage::DaCertificate2(_) => {
+                    Self::Certificate
+                }
+                DaConsensusMessage::VidDisperseMsg(_)
+                | DaConsensusMessage::VidDisperseMsg2(_)
+                | DaConsensusMessage::DecryptionShares(_) => Self::Vid,
+            },
+            MessageKind::Data(DataMessage::RequestData(_)) => Self::Vid,
+            MessageKind::Data(
+                DataMessage::SubmitTransaction(..)
+                | DataMessage::SubmitPrioritizedTransaction(..)
+                | DataMessage::DataResponse(_)
+                | DataMessage::CertificateDigest(_)
+                | DataMessage::CertificateSync(_)
+                | DataMessage::CatchupRequest(_)
+                | DataMessage::CatchupPage(_)
+                | DataMessage::Heartbeat(_),
+            )
+            | MessageKind::External(_) => Self::Unbound,
+        }
+    }
+}
+
+impl fmt::Display for MessageClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Proposal => "proposal",
+            Self::Vote => "vote",
+            Self::Certificate => "certificate",
+            Self::ViewSync => "view_sync",
+            Self::Vid => "vid",
+            Self::Unbound => "unbound",
+        })
+    }
+}
+
+impl TryFrom<u8> for MessageClass {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|class| *class as u8 == value)
+            .context(info!("Unknown message class {value}"))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 #[serde(bound(deserialize = "", serialize = ""))]
 /// Messages related to both validating and sequencing consensus.
@@ -245,6 +494,12 @@ pub enum GeneralConsensusMessage<TYPES: NodeType> {
 
     /// Message with a Timeout vote
     TimeoutVote2(TimeoutVote2<TYPES>),
+
+    /// Message with a quorum vote in its compact encoding
+    CompactVote(CompactQuorumVote<TYPES>),
+
+    /// Message with one erasure-coded chunk of a quorum proposal
+    ProposalChunk(ProposalChunk<TYPES>),
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Hash, Eq)]
@@ -278,6 +533,9 @@ pub enum DaConsensusMessage<TYPES: NodeType> {
     ///
     /// Like [`DaProposal`]. Use `Msg` suffix to distinguish from `VidDisperse`.
     VidDisperseMsg2(Proposal<TYPES, VidDisperseShare2<TYPES>>),
+
+    /// Decryption shares for the encrypted transactions of a decided block
+    DecryptionShares(DecryptionShares<TYPES>),
 }
 
 /// Messages for sequencing consensus.
@@ -316,6 +574,10 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                     }
                     GeneralConsensusMessage::Vote(vote_message) => vote_message.view_number(),
                     GeneralConsensusMessage::Vote2(vote_message) => vote_message.view_number(),
+                    GeneralConsensusMessage::CompactVote(vote_message) => {
+                        vote_message.view_number()
+                    }
+                    GeneralConsensusMessage::ProposalChunk(chunk) => chunk.view_number(),
                     GeneralConsensusMessage::TimeoutVote(message) => message.view_number(),
                     GeneralConsensusMessage::ViewSyncPreCommitVote(message) => {
                         message.view_number()
@@ -371,6 +633,7 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                     }
                     DaConsensusMessage::DaVote2(vote_message) => vote_message.view_number(),
                     DaConsensusMessage::DaCertificate2(cert) => cert.view_number,
+                    DaConsensusMessage::DecryptionShares(shares) => shares.view_number,
                 }
             }
         }
@@ -387,10 +650,22 @@ pub enum DataMessage<TYPES: NodeType> {
     /// TODO rethink this when we start to send these messages
     /// we only need the view number for broadcast
     SubmitTransaction(TYPES::Transaction, TYPES::View),
+    /// Contains a transaction to be submitted, tagged with a priority class
+    SubmitPrioritizedTransaction(TYPES::Transaction, TransactionPriority, TYPES::View),
     /// A request for data
     RequestData(DataRequest<TYPES>),
     /// A response to a data request
     DataResponse(ResponseMessage<TYPES>),
+    /// The views of the sender's highest certificates, for anti-entropy
+    CertificateDigest(CertificateDigest<TYPES>),
+    /// Certificates newer than those of a digest we were sent
+    CertificateSync(CertificateSync<TYPES>),
+    /// A request for the decided leaves in a range of views, to catch up on them
+    CatchupRequest(CatchupRequest<TYPES>),
+    /// A page of decided leaves answering a catchup request
+    CatchupPage(CatchupPage<TYPES>),
+    /// A validator's signed report of its progress
+    Heartbeat(SignedHeartbeat<TYPES>),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
@@ -447,7 +722,12 @@ where
         ensure!(
             view_leader_key.validate(
                 &self.signature,
-                proposed_leaf.commit(upgrade_lock).await.as_ref()
+                &upgrade_lock
+                    .signing_message(
+                        proposed_leaf.commit(upgrade_lock).await.as_ref(),
+                        view_number
+                    )
+                    .await
             ),
             "Proposal signature is invalid."
         );
@@ -463,10 +743,11 @@ where
     /// Checks that the signature of the quorum proposal is valid.
     /// # Errors
     /// Returns an error when the proposal signature is invalid.
-    pub fn validate_signature(
+    pub async fn validate_signature<V: Versions>(
         &self,
         membership: &TYPES::Membership,
         epoch_height: u64,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Result<()> {
         let view_number = self.data.view_number();
         let proposal_epoch = TYPES::Epoch::new(epoch_from_block_number(
@@ -477,7 +758,12 @@ where
         let proposed_leaf = Leaf2::from_quorum_proposal(&self.data);
 
         ensure!(
-            view_leader_key.validate(&self.signature, proposed_leaf.commit().as_ref()),
+            view_leader_key.validate(
+                &self.signature,
+                &upgrade_lock
+                    .signing_message(proposed_leaf.commit().as_ref(), view_number)
+                    .await
+            ),
             "Proposal signature is invalid."
         );
 
@@ -491,6 +777,10 @@ pub struct UpgradeLock<TYPES: NodeType, V: Versions> {
     /// a shared lock to an upgrade certificate decided by consensus
     pub decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
 
+    /// the chain this node runs, which the signatures it makes or accepts are bound to from the
+    /// `ChainId` version on
+    pub chain_id: u64,
+
     /// phantom data for the `Versions` trait
     pub _pd: PhantomData<V>,
 }
@@ -501,6 +791,7 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
     pub fn new() -> Self {
         Self {
             decided_upgrade_certificate: Arc::new(RwLock::new(None)),
+            chain_id: 0,
             _pd: PhantomData::<V>,
         }
     }
@@ -510,10 +801,35 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
     pub fn from_certificate(certificate: &Option<UpgradeCertificate<TYPES>>) -> Self {
         Self {
             decided_upgrade_certificate: Arc::new(RwLock::new(certificate.clone())),
+            chain_id: 0,
             _pd: PhantomData::<V>,
         }
     }
 
+    /// Bind the signatures made and accepted with this lock to the chain `chain_id`
+    #[must_use]
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// The bytes to sign for `message`, which belongs to `view`. If the version applied in `view`
+    /// binds signatures to the chain, this is the message prefixed with our chain ID, so that the
+    /// signature is only valid on this chain, even if the key is also used on another.
+    pub async fn signing_message(&self, message: &[u8], view: TYPES::View) -> Vec<u8> {
+        [self.signing_prefix(view).await.as_slice(), message].concat()
+    }
+
+    /// The prefix of the bytes to sign for messages which belong to `view`, for checking
+    /// signatures where we can't wait on the upgrade lock, such as in event filters
+    pub async fn signing_prefix(&self, view: TYPES::View) -> Vec<u8> {
+        if self.version_infallible(view).await >= V::ChainId::VERSION {
+            self.chain_id.to_le_bytes().to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
     /// Calculate the version applied in a view, based on the provided upgrade lock.
     ///
     /// # Errors
@@ -557,6 +873,26 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
         }
     }
 
+    /// Whether `view` falls between the last view of the version we upgrade from and the first
+    /// view of the version we upgrade to, in which only the null block may be proposed
+    pub async fn upgrading_in(&self, view: TYPES::View) -> bool {
+        self.decided_upgrade_certificate
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|cert| cert.upgrading_in(view))
+    }
+
+    /// Commit to `value`, which belongs to `view`, with `TYPES::CommitmentScheme` if the version
+    /// applied in `view` has switched to it, and with the native commitment otherwise.
+    pub async fn commit<T: Committable>(&self, value: &T, view: TYPES::View) -> Commitment<T> {
+        if self.version_infallible(view).await >= V::CommitmentScheme::VERSION {
+            TYPES::CommitmentScheme::commit(value)
+        } else {
+            value.commit()
+        }
+    }
+
     /// Serialize a message with a version number, using `message.view_number()` and an optional decided upgrade certificate to determine the message's version.
     ///
     /// # Errors
@@ -619,4 +955,67 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
 
         Ok(deserialized_message)
     }
+
+    /// Serialize a message like [`Self::serialize`], but with the message itself encoded in `format`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if serialization fails.
+    pub async fn serialize_with<M: HasViewNumber<TYPES> + Serialize>(
+        &self,
+        message: &M,
+        format: WireFormat,
+    ) -> Result<Vec<u8>> {
+        if format == WireFormat::Bincode {
+            return self.serialize(message).await;
+        }
+
+        let version = self.version(message.view_number()).await?;
+
+        // The version prefix is the same in every format
+        let mut serialized_message =
+            [version.major.to_le_bytes(), version.minor.to_le_bytes()].concat();
+        serialized_message.extend(format.encode(message)?);
+
+        Ok(serialized_message)
+    }
+
+    /// Deserialize a message like [`Self::deserialize`], but with the message itself encoded in `format`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if deserialization fails.
+    pub async fn deserialize_with<M: HasViewNumber<TYPES> + for<'a> Deserialize<'a>>(
+        &self,
+        message: &[u8],
+        format: WireFormat,
+    ) -> Result<M> {
+        if format == WireFormat::Bincode {
+            return self.deserialize(message).await;
+        }
+
+        let (actual_version, encoded_message) = Version::deserialize(message)
+            .wrap()
+            .context(info!("Failed to read message version!"))?;
+        ensure!(
+            actual_version == V::Base::VERSION || actual_version == V::Upgrade::VERSION,
+            "Cannot deserialize message with stated version {}",
+            actual_version
+        );
+
+        let deserialized_message: M = format
+            .decode(encoded_message)
+            .context(info!("Failed to deserialize message!"))?;
+
+        let view = deserialized_message.view_number();
+
+        let expected_version = self.version(view).await?;
+
+        ensure!(
+            actual_version == expected_version,
+            "Message has invalid version number for its view. Expected: {expected_version}, Actual: {actual_version}, View: {view:?}"
+        );
+
+        Ok(deserialized_message)
+    }
 }
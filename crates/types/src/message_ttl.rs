@@ -0,0 +1,175 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Expiry of messages on the wire.
+//!
+//! Every message is sent behind a fixed-size [`TtlHeader`], which carries the [`MessageClass`] of
+//! the message and when it expires, both as a view and as a wall-clock time. Receivers read the
+//! header before anything else and drop expired messages without decoding them, so a late
+//! proposal or VID share costs no more than reading its first few bytes.
+
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    message::{MessageClass, MessageKind},
+    traits::{network::ViewMessage, node_implementation::NodeType},
+};
+
+/// How long a message stays relevant after it is sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageTtl {
+    /// Number of views after the view of the message in which it is still delivered
+    pub views: u64,
+    /// Time after sending during which the message is still delivered
+    pub duration: Duration,
+}
+
+impl MessageTtl {
+    /// Create a new TTL of `views` views and `duration` of wall-clock time
+    #[must_use]
+    pub const fn new(views: u64, duration: Duration) -> Self {
+        Self { views, duration }
+    }
+}
+
+/// How long each class of message stays relevant. Messages expire once either their view or
+/// wall-clock TTL has passed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageTtlConfig {
+    /// Quorum, DA and upgrade proposals
+    pub proposal: MessageTtl,
+    /// Quorum, timeout, DA and upgrade votes, which are only of use to the next leader
+    pub vote: MessageTtl,
+    /// High QCs and DA certificates
+    pub certificate: MessageTtl,
+    /// View sync votes and certificates, which stay relevant over the many views view sync spans
+    pub view_sync: MessageTtl,
+    /// VID shares and requests for them
+    pub vid: MessageTtl,
+}
+
+impl Default for MessageTtlConfig {
+    fn default() -> Self {
+        Self {
+            proposal: MessageTtl::new(2, Duration::from_secs(60)),
+            vote: MessageTtl::new(1, Duration::from_secs(30)),
+            certificate: MessageTtl::new(2, Duration::from_secs(60)),
+            view_sync: MessageTtl::new(20, Duration::from_secs(300)),
+            vid: MessageTtl::new(2, Duration::from_secs(60)),
+        }
+    }
+}
+
+impl MessageTtlConfig {
+    /// The TTL of messages of `class`, or `None` if they never expire
+    #[must_use]
+    pub fn ttl(&self, class: MessageClass) -> Option<MessageTtl> {
+        match class {
+            MessageClass::Proposal => Some(self.proposal),
+            MessageClass::Vote => Some(self.vote),
+            MessageClass::Certificate => Some(self.certificate),
+            MessageClass::ViewSync => Some(self.view_sync),
+            MessageClass::Vid => Some(self.vid),
+            MessageClass::Unbound => None,
+        }
+    }
+}
+
+/// The header in front of every message on the wire, saying when the message expires
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TtlHeader {
+    /// The class of the message
+    pub class: MessageClass,
+    /// The last view in which the message is delivered
+    pub expires_after_view: u64,
+    /// Milliseconds since the Unix epoch after which the message is no longer delivered
+    pub expires_at_ms: u64,
+}
+
+impl TtlHeader {
+    /// Length of an encoded header: the class, then the view and time it expires, little-endian
+    pub const LEN: usize = 17;
+
+    /// The header for a message of `kind` sent at `now`
+    #[must_use]
+    pub fn new<TYPES: NodeType>(
+        kind: &MessageKind<TYPES>,
+        config: &MessageTtlConfig,
+        now: SystemTime,
+    ) -> Self {
+        let class = MessageClass::of(kind);
+        let Some(ttl) = config.ttl(class) else {
+            return Self {
+                class,
+                expires_after_view: u64::MAX,
+                expires_at_ms: u64::MAX,
+            };
+        };
+
+        Self {
+            class,
+            expires_after_view: kind.view_number().saturating_add(ttl.views),
+            expires_at_ms: unix_ms(now)
+                .saturating_add(u64::try_from(ttl.duration.as_millis()).unwrap_or(u64::MAX)),
+        }
+    }
+
+    /// Put this header in front of `message`
+    #[must_use]
+    pub fn stamp(&self, message: Vec<u8>) -> Vec<u8> {
+        let mut stamped = Vec::with_capacity(Self::LEN + message.len());
+        stamped.push(self.class as u8);
+        stamped.extend(self.expires_after_view.to_le_bytes());
+        stamped.extend(self.expires_at_ms.to_le_bytes());
+        stamped.extend(message);
+        stamped
+    }
+
+    /// Read the header off the front of `bytes`, returning it and the message behind it
+    ///
+    /// # Errors
+    /// If `bytes` doesn't start with a header
+    pub fn split(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        ensure!(
+            bytes.len() >= Self::LEN,
+            "Message of {} bytes is too short for its TTL header",
+            bytes.len()
+        );
+        let (header, message) = bytes.split_at(Self::LEN);
+        let read_u64 = |at: usize| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&header[at..at + 8]);
+            u64::from_le_bytes(word)
+        };
+
+        Ok((
+            Self {
+                class: MessageClass::try_from(header[0])?,
+                expires_after_view: read_u64(1),
+                expires_at_ms: read_u64(9),
+            },
+            message,
+        ))
+    }
+
+    /// Whether the message has expired, once we are in `cur_view` at time `now`
+    #[must_use]
+    pub fn is_expired(&self, cur_view: u64, now: SystemTime) -> bool {
+        cur_view > self.expires_after_view || unix_ms(now) > self.expires_at_ms
+    }
+}
+
+/// Milliseconds from the Unix epoch to `time`
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| {
+            u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX)
+        })
+}
@@ -0,0 +1,190 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Migrations of persisted consensus data between releases.
+//!
+//! Each change to what a node persists comes with a [`MigrationStep`], numbered after the steps
+//! before it. At startup the node runs the [`Migrations`] of its release against its [`Storage`],
+//! which applies the steps it has not applied yet, in order, and records each one as applied once
+//! it succeeds. A node which stopped partway through is left with the earlier steps recorded, and
+//! picks up from the step that failed when it restarts, so operators never need to wipe its state.
+//!
+//! A dry run reports which steps would be applied without touching the storage.
+
+use std::{collections::BTreeSet, marker::PhantomData};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use tracing::info;
+
+use crate::{
+    data::{Leaf2, QuorumProposal, QuorumProposal2},
+    message::convert_proposal,
+    traits::{node_implementation::NodeType, storage::Storage},
+};
+
+/// One change to the persisted data, applied once to every node's storage
+#[async_trait]
+pub trait MigrationStep<TYPES: NodeType, S: Storage<TYPES>>: Send + Sync {
+    /// The number of the step, greater than that of every step before it
+    fn version(&self) -> u32;
+
+    /// What the step changes, for the logs
+    fn description(&self) -> &str;
+
+    /// Apply the step to `storage`
+    ///
+    /// # Errors
+    /// If the storage fails to apply it
+    async fn apply(&self, storage: &S) -> Result<()>;
+}
+
+/// Whether to apply pending migrations, or only report them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MigrationMode {
+    /// Apply the pending migrations and record them
+    Apply,
+    /// Report the pending migrations, leaving the storage as it is
+    DryRun,
+}
+
+/// The outcome of running the migrations against a storage
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Migrations the storage had applied before this run
+    pub previously_applied: BTreeSet<u32>,
+    /// Migrations this run applied, or would have applied in a dry run, in order
+    pub applied: Vec<u32>,
+}
+
+/// The ordered migration steps of a release
+pub struct Migrations<TYPES: NodeType, S: Storage<TYPES>> {
+    /// The steps, in order of version
+    steps: Vec<Box<dyn MigrationStep<TYPES, S>>>,
+}
+
+impl<TYPES: NodeType, S: Storage<TYPES>> Default for Migrations<TYPES, S> {
+    fn default() -> Self {
+        Self { steps: Vec::new() }
+    }
+}
+
+impl<TYPES: NodeType, S: Storage<TYPES> + 'static> Migrations<TYPES, S> {
+    /// The migrations of persisted consensus data every node applies
+    #[must_use]
+    pub fn consensus() -> Self {
+        Self::default().with_step(Leaf2Migration(PhantomData))
+    }
+}
+
+impl<TYPES: NodeType, S: Storage<TYPES>> Migrations<TYPES, S> {
+    /// Add `step`, to be applied after the steps added so far
+    ///
+    /// # Panics
+    /// If the version of `step` is not greater than that of the last step
+    #[must_use]
+    pub fn with_step(mut self, step: impl MigrationStep<TYPES, S> + 'static) -> Self {
+        if let Some(last) = self.steps.last() {
+            assert!(
+                step.version() > last.version(),
+                "Migration {} must come before migration {}",
+                step.version(),
+                last.version()
+            );
+        }
+        self.steps.push(Box::new(step));
+
+        self
+    }
+
+    /// The versions of the steps, in order
+    #[must_use]
+    pub fn versions(&self) -> Vec<u32> {
+        self.steps.iter().map(|step| step.version()).collect()
+    }
+
+    /// Apply the steps `storage` has not applied yet, in order, recording each as it succeeds. In
+    /// a dry run, only report which steps would be applied.
+    ///
+    /// # Errors
+    /// If a step fails, or the storage has applied a later step than one which is pending, which
+    /// means a step was added out of order
+    pub async fn run(&self, storage: &S, mode: MigrationMode) -> Result<MigrationReport> {
+        let previously_applied = storage
+            .load_applied_migrations()
+            .await
+            .context("Failed to load the applied storage migrations")?;
+        let pending: Vec<_> = self
+            .steps
+            .iter()
+            .filter(|step| !previously_applied.contains(&step.version()))
+            .collect();
+        if let (Some(first), Some(latest)) = (pending.first(), previously_applied.last()) {
+            if first.version() < *latest {
+                bail!(
+                    "Storage migration {} is pending, but the later migration {latest} has already been applied",
+                    first.version()
+                );
+            }
+        }
+
+        let mut applied = Vec::with_capacity(pending.len());
+        for step in pending {
+            let version = step.version();
+            if mode == MigrationMode::DryRun {
+                info!(
+                    "Would apply storage migration {version}: {}",
+                    step.description()
+                );
+            } else {
+                info!(
+                    "Applying storage migration {version}: {}",
+                    step.description()
+                );
+                step.apply(storage).await.with_context(|| {
+                    format!(
+                        "Storage migration {version} ({}) failed",
+                        step.description()
+                    )
+                })?;
+                storage
+                    .record_migration(version)
+                    .await
+                    .with_context(|| format!("Failed to record storage migration {version}"))?;
+            }
+            applied.push(version);
+        }
+
+        Ok(MigrationReport {
+            previously_applied,
+            applied,
+        })
+    }
+}
+
+/// Migrates leaves from `Leaf` to `Leaf2`, and proposals from `QuorumProposal` to
+/// `QuorumProposal2`
+struct Leaf2Migration<TYPES, S>(PhantomData<fn() -> (TYPES, S)>);
+
+#[async_trait]
+impl<TYPES: NodeType, S: Storage<TYPES>> MigrationStep<TYPES, S> for Leaf2Migration<TYPES, S> {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn description(&self) -> &str {
+        "migrate leaves to `Leaf2` and quorum proposals to `QuorumProposal2`"
+    }
+
+    async fn apply(&self, storage: &S) -> Result<()> {
+        storage
+            .migrate_consensus(
+                Into::<Leaf2<TYPES>>::into,
+                convert_proposal::<TYPES, QuorumProposal<TYPES>, QuorumProposal2<TYPES>>,
+            )
+            .await
+    }
+}
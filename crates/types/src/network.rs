@@ -14,6 +14,7 @@ use thiserror::Error;
 use tracing::error;
 
 use crate::{
+    config_validation::ConfigViolations,
     constants::{
         ORCHESTRATOR_DEFAULT_NUM_ROUNDS, ORCHESTRATOR_DEFAULT_TRANSACTIONS_PER_ROUND,
         ORCHESTRATOR_DEFAULT_TRANSACTION_SIZE, REQUEST_DATA_DELAY,
@@ -21,7 +22,8 @@ use crate::{
     hotshot_config_file::HotShotConfigFile,
     light_client::StateVerKey,
     traits::signature_key::SignatureKey,
-    HotShotConfig, ValidatorConfig,
+    wire_codec::WireFormat,
+    HotShotConfig, PeerConfig, ValidatorConfig,
 };
 
 /// Configuration describing a libp2p node
@@ -29,6 +31,9 @@ use crate::{
 pub struct Libp2pConfig {
     /// The bootstrap nodes to connect to (multiaddress, serialized public key)
     pub bootstrap_nodes: Vec<(PeerId, Multiaddr)>,
+    /// The designated relays, which nodes behind a NAT are reached through
+    #[serde(default)]
+    pub relay_nodes: Vec<(PeerId, Multiaddr)>,
 }
 
 /// configuration for combined network
@@ -38,6 +43,78 @@ pub struct CombinedNetworkConfig {
     pub delay_duration: Duration,
 }
 
+/// Named gossipsub mesh parameters for networks of different sizes
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum GossipPreset {
+    /// Up to a few dozen nodes. A smaller mesh is enough to reach everyone in a hop or two, and
+    /// faster heartbeats repair it quickly after churn.
+    Small,
+    /// Up to a few hundred nodes, with the mesh parameters Ethereum uses, which are also the
+    /// libp2p defaults
+    #[default]
+    Medium,
+    /// Thousands of nodes. A denser mesh keeps the number of hops down, and publishing to the mesh
+    /// rather than flooding every peer keeps the leader's bandwidth bounded.
+    Large,
+}
+
+impl GossipPreset {
+    /// The mesh parameters of this preset
+    #[must_use]
+    pub fn tuning(self) -> GossipTuning {
+        match self {
+            Self::Small => GossipTuning {
+                mesh_n: 6,
+                mesh_n_low: 4,
+                mesh_n_high: 8,
+                heartbeat_interval: Duration::from_millis(500),
+                flood_publish: true,
+            },
+            Self::Medium => GossipTuning {
+                mesh_n: 8,
+                mesh_n_low: 6,
+                mesh_n_high: 12,
+                heartbeat_interval: Duration::from_secs(1),
+                flood_publish: true,
+            },
+            Self::Large => GossipTuning {
+                mesh_n: 12,
+                mesh_n_low: 8,
+                mesh_n_high: 16,
+                heartbeat_interval: Duration::from_secs(1),
+                flood_publish: false,
+            },
+        }
+    }
+}
+
+/// The gossipsub mesh parameters of libp2p networks which can be tuned to the size of the
+/// network. Parameters left out of a config file are taken from the [`GossipPreset::Medium`]
+/// preset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct GossipTuning {
+    /// The target number of peers in the mesh
+    pub mesh_n: usize,
+    /// The minimum number of peers in the mesh
+    pub mesh_n_low: usize,
+    /// The maximum number of peers in the mesh
+    pub mesh_n_high: usize,
+    /// The time between heartbeats, at which the mesh is maintained and gossip is emitted
+    pub heartbeat_interval: Duration,
+    /// Whether to send our own messages to every subscribed peer, rather than just to the mesh
+    pub flood_publish: bool,
+}
+
+impl Default for GossipTuning {
+    fn default() -> Self {
+        GossipPreset::default().tuning()
+    }
+}
+
 /// a network configuration error
 #[derive(Error, Debug)]
 pub enum NetworkConfigError {
@@ -155,6 +232,9 @@ pub struct NetworkConfig<KEY: SignatureKey> {
     pub random_builder: Option<RandomBuilderConfig>,
     /// The list of public keys that are allowed to connect to the orchestrator
     pub public_keys: Vec<PeerConfigKeys<KEY>>,
+    /// the encoding of messages on the network
+    #[serde(default)]
+    pub wire_format: WireFormat,
 }
 
 /// the source of the network config
@@ -265,6 +345,35 @@ impl<K: SignatureKey> NetworkConfig<K> {
             Err(e) => Err(NetworkConfigError::WriteToFileError(e)),
         }
     }
+
+    /// Make the listed public keys the stake table, and those of DA nodes the DA committee. With
+    /// no public keys listed, both are left empty for nodes to register in.
+    pub fn apply_public_keys(&mut self) {
+        let peer_config = |keys: &PeerConfigKeys<K>| PeerConfig {
+            stake_table_entry: keys.stake_table_key.stake_table_entry(keys.stake),
+            state_ver_key: keys.state_ver_key.clone(),
+        };
+        self.config.known_nodes_with_stake = self.public_keys.iter().map(peer_config).collect();
+        self.config.known_da_nodes = self
+            .public_keys
+            .iter()
+            .filter(|keys| keys.da)
+            .map(peer_config)
+            .collect();
+    }
+
+    /// Check the HotShot config, leaving out the stake tables if nodes register dynamically
+    /// because no public keys are listed
+    ///
+    /// # Errors
+    /// Every violation found, if there are any
+    pub fn validate(&self) -> Result<(), ConfigViolations> {
+        if self.public_keys.is_empty() {
+            self.config.validate_parameters()
+        } else {
+            self.config.validate()
+        }
+    }
 }
 
 impl<K: SignatureKey> Default for NetworkConfig<K> {
@@ -291,6 +400,7 @@ impl<K: SignatureKey> Default for NetworkConfig<K> {
             builder: BuilderType::default(),
             random_builder: None,
             public_keys: vec![],
+            wire_format: WireFormat::default(),
         }
     }
 }
@@ -354,6 +464,12 @@ pub struct NetworkConfigFile<KEY: SignatureKey> {
     /// If nonempty, this list becomes the stake table and is used to determine DA membership (ignoring the node's request).
     #[serde(default)]
     pub public_keys: Vec<PeerConfigKeys<KEY>>,
+    /// the encoding of messages on the network
+    #[serde(default)]
+    pub wire_format: WireFormat,
+    /// the designated libp2p relays, which nodes behind a NAT are reached through
+    #[serde(default)]
+    pub libp2p_relay_nodes: Vec<(PeerId, Multiaddr)>,
 }
 
 impl<K: SignatureKey> From<NetworkConfigFile<K>> for NetworkConfig<K> {
@@ -376,6 +492,7 @@ impl<K: SignatureKey> From<NetworkConfigFile<K>> for NetworkConfig<K> {
             transaction_size: val.transaction_size,
             libp2p_config: Some(Libp2pConfig {
                 bootstrap_nodes: Vec::new(),
+                relay_nodes: val.libp2p_relay_nodes,
             }),
             config: val.config.into(),
             key_type_name: std::any::type_name::<K>().to_string(),
@@ -385,6 +502,7 @@ impl<K: SignatureKey> From<NetworkConfigFile<K>> for NetworkConfig<K> {
             builder: val.builder,
             random_builder: val.random_builder,
             public_keys: val.public_keys,
+            wire_format: val.wire_format,
         }
     }
 }
@@ -0,0 +1,231 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Per-validator participation tracking and liveness scoring.
+//!
+//! The tracker is fed from decided blocks: every QC certifying a decided leaf tells us which
+//! validators voted, and every decided (or skipped) view tells us whether its leader managed to
+//! get a proposal decided.
+
+use std::collections::HashMap;
+
+use bitvec::slice::BitSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::traits::signature_key::SignatureKey;
+
+/// Default participation score below which we start warning about a validator.
+pub const DEFAULT_PARTICIPATION_WARN_THRESHOLD: f64 = 0.5;
+
+/// Minimum number of observations of a validator before we warn about its score.
+pub const MIN_PARTICIPATION_SAMPLES: u64 = 10;
+
+/// Raw participation counters for a single validator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorParticipation {
+    /// Number of certificates formed while this validator was in the stake table
+    pub certificates_seen: u64,
+    /// Number of those certificates which include this validator's vote
+    pub votes_included: u64,
+    /// Number of views this validator was leader for
+    pub leader_views: u64,
+    /// Number of those views for which this validator's proposal was decided
+    pub proposals_decided: u64,
+    /// Number of this validator's proposals whose block failed to apply to the validated state
+    #[serde(default)]
+    pub invalid_state_transitions: u64,
+}
+
+impl ValidatorParticipation {
+    /// Fraction of certificates which include this validator's vote, if any were seen.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn vote_rate(&self) -> Option<f64> {
+        (self.certificates_seen > 0)
+            .then(|| self.votes_included as f64 / self.certificates_seen as f64)
+    }
+
+    /// Fraction of leader views in which this validator's proposal was decided, if it led any.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn proposal_rate(&self) -> Option<f64> {
+        (self.leader_views > 0).then(|| self.proposals_decided as f64 / self.leader_views as f64)
+    }
+
+    /// Overall liveness score in `[0, 1]`: the mean of the vote and proposal rates we have data
+    /// for. Returns `None` if we haven't observed the validator at all.
+    #[must_use]
+    pub fn score(&self) -> Option<f64> {
+        match (self.vote_rate(), self.proposal_rate()) {
+            (Some(votes), Some(proposals)) => Some((votes + proposals) / 2.0),
+            (Some(rate), None) | (None, Some(rate)) => Some(rate),
+            (None, None) => None,
+        }
+    }
+
+    /// Total number of observations backing the score.
+    #[must_use]
+    pub fn samples(&self) -> u64 {
+        self.certificates_seen + self.leader_views
+    }
+}
+
+/// Tracks participation of every validator we have seen in a decided certificate.
+#[derive(Clone, Debug)]
+pub struct ParticipationTracker<KEY: SignatureKey> {
+    /// Counters per validator
+    validators: HashMap<KEY, ValidatorParticipation>,
+    /// Score below which a validator is considered to be under-participating
+    warn_threshold: f64,
+}
+
+impl<KEY: SignatureKey> Default for ParticipationTracker<KEY> {
+    fn default() -> Self {
+        Self::new(DEFAULT_PARTICIPATION_WARN_THRESHOLD)
+    }
+}
+
+impl<KEY: SignatureKey> ParticipationTracker<KEY> {
+    /// Create an empty tracker that warns when a validator's score drops below `warn_threshold`.
+    #[must_use]
+    pub fn new(warn_threshold: f64) -> Self {
+        Self {
+            validators: HashMap::new(),
+            warn_threshold,
+        }
+    }
+
+    /// Record a certificate formed over `stake_table`, where `signers` is the signer bitmap
+    /// in stake table order.
+    ///
+    /// Returns the validators which dropped below the warning threshold as a result.
+    pub fn record_certificate(&mut self, stake_table: &[KEY], signers: &BitSlice) -> Vec<KEY> {
+        let mut newly_low = Vec::new();
+        for (index, key) in stake_table.iter().enumerate() {
+            let voted = signers.get(index).is_some_and(|bit| *bit);
+            let entry = self.validators.entry(key.clone()).or_default();
+            let was_low = Self::is_low(entry, self.warn_threshold);
+            entry.certificates_seen += 1;
+            if voted {
+                entry.votes_included += 1;
+            }
+            if !was_low && Self::is_low(entry, self.warn_threshold) {
+                newly_low.push(key.clone());
+            }
+        }
+        newly_low
+    }
+
+    /// Record that `leader` was the leader of a view, and whether its proposal got decided.
+    ///
+    /// Returns `true` if the leader dropped below the warning threshold as a result.
+    pub fn record_leader_view(&mut self, leader: &KEY, decided: bool) -> bool {
+        let entry = self.validators.entry(leader.clone()).or_default();
+        let was_low = Self::is_low(entry, self.warn_threshold);
+        entry.leader_views += 1;
+        if decided {
+            entry.proposals_decided += 1;
+        }
+        !was_low && Self::is_low(entry, self.warn_threshold)
+    }
+
+    /// Record that a proposal of `leader` failed to apply to the validated state.
+    ///
+    /// Returns the number of such proposals seen from `leader`.
+    pub fn record_invalid_state_transition(&mut self, leader: &KEY) -> u64 {
+        let entry = self.validators.entry(leader.clone()).or_default();
+        entry.invalid_state_transitions += 1;
+        entry.invalid_state_transitions
+    }
+
+    /// Participation counters for a single validator.
+    #[must_use]
+    pub fn participation(&self, key: &KEY) -> Option<&ValidatorParticipation> {
+        self.validators.get(key)
+    }
+
+    /// Liveness score for a single validator.
+    #[must_use]
+    pub fn score(&self, key: &KEY) -> Option<f64> {
+        self.validators
+            .get(key)
+            .and_then(ValidatorParticipation::score)
+    }
+
+    /// Counters for every tracked validator.
+    #[must_use]
+    pub fn all(&self) -> &HashMap<KEY, ValidatorParticipation> {
+        &self.validators
+    }
+
+    /// Validators currently below the warning threshold.
+    #[must_use]
+    pub fn low_participation(&self) -> Vec<KEY> {
+        self.validators
+            .iter()
+            .filter(|(_, entry)| Self::is_low(entry, self.warn_threshold))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Whether `entry` has enough samples and a score below `threshold`.
+    fn is_low(entry: &ValidatorParticipation, threshold: f64) -> bool {
+        entry.samples() >= MIN_PARTICIPATION_SAMPLES
+            && entry.score().is_some_and(|score| score < threshold)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod test {
+    use bitvec::bitvec;
+
+    use super::*;
+    use crate::signature_key::BLSPubKey;
+
+    fn keys(n: u64) -> Vec<BLSPubKey> {
+        (0..n)
+            .map(|i| BLSPubKey::generated_from_seed_indexed([0u8; 32], i).0)
+            .collect()
+    }
+
+    #[test]
+    fn vote_rate_tracks_signers() {
+        let keys = keys(3);
+        let mut tracker = ParticipationTracker::default();
+        for _ in 0..4 {
+            tracker.record_certificate(&keys, &bitvec![1, 1, 0]);
+        }
+        tracker.record_certificate(&keys, &bitvec![1, 0, 1]);
+
+        assert_eq!(tracker.score(&keys[0]), Some(1.0));
+        assert_eq!(tracker.score(&keys[1]), Some(0.8));
+        assert_eq!(tracker.score(&keys[2]), Some(0.2));
+    }
+
+    #[test]
+    fn warns_once_when_dropping_below_threshold() {
+        let keys = keys(2);
+        let mut tracker = ParticipationTracker::new(0.5);
+        let mut warned = Vec::new();
+        for _ in 0..MIN_PARTICIPATION_SAMPLES * 2 {
+            warned.extend(tracker.record_certificate(&keys, &bitvec![1, 0]));
+        }
+
+        assert_eq!(warned, vec![keys[1].clone()]);
+        assert_eq!(tracker.low_participation(), vec![keys[1].clone()]);
+    }
+
+    #[test]
+    fn score_combines_votes_and_proposals() {
+        let keys = keys(1);
+        let mut tracker = ParticipationTracker::default();
+        tracker.record_certificate(&keys, &bitvec![1]);
+        tracker.record_leader_view(&keys[0], false);
+
+        assert_eq!(tracker.score(&keys[0]), Some(0.5));
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Scoring and banning of misbehaving peers.
+//!
+//! The sender a message claims is not authenticated, and anyone can replay a peer's old messages
+//! or attach its key to a bad signature, so peers are only penalized for misbehavior their own
+//! signature proves: signing two different votes of the same kind in the same view. Each penalty
+//! adds to the peer's score in the node's [`PeerScoreboard`], which decays over time. A peer whose
+//! score reaches the ban threshold is banned for a cooldown, during which the network message task
+//! drops everything claiming to be from it unread. Each ban raises an [`Alert::PeerBanned`] to the
+//! node's alert sinks.
+//!
+//! Messages beyond a sender's rate limit are dropped, but not penalized, since the limit is kept
+//! per claimed sender.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use async_lock::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alert::{Alert, AlertSinks},
+    traits::node_implementation::NodeType,
+};
+
+/// A kind of misbehavior a peer is penalized for, each proven by the peer's own signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Misbehavior {
+    /// The peer signed two different votes of the same kind in the same view
+    Equivocation,
+}
+
+impl Display for Misbehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Misbehavior::Equivocation => write!(f, "equivocation"),
+        }
+    }
+}
+
+/// Penalties, ban threshold, score decay and rate limit of the peer scoreboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerScoreConfig {
+    /// Penalty for each pair of conflicting votes
+    pub equivocation_penalty: u64,
+    /// Score at which a peer is banned, or zero to never ban peers
+    pub ban_threshold: u64,
+    /// How long a ban lasts
+    pub ban_cooldown: Duration,
+    /// How long it takes a peer's score to decay by one, or zero for scores not to decay
+    pub score_decay_interval: Duration,
+    /// Number of messages a peer may send per second, or `None` for no limit
+    pub max_messages_per_second: Option<u64>,
+}
+
+impl Default for PeerScoreConfig {
+    fn default() -> Self {
+        Self {
+            equivocation_penalty: 100,
+            ban_threshold: 100,
+            ban_cooldown: Duration::from_secs(300),
+            score_decay_interval: Duration::from_secs(10),
+            max_messages_per_second: None,
+        }
+    }
+}
+
+impl PeerScoreConfig {
+    /// The penalty for `misbehavior`
+    #[must_use]
+    pub fn penalty(&self, misbehavior: Misbehavior) -> u64 {
+        match misbehavior {
+            Misbehavior::Equivocation => self.equivocation_penalty,
+        }
+    }
+}
+
+/// What we hold against a peer
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeerRecord {
+    /// Penalties accumulated since the peer was last banned, less their decay
+    pub score: u64,
+    /// Number of pairs of conflicting votes
+    pub equivocations: u64,
+    /// Number of times the peer was banned
+    pub bans: u64,
+    /// When the peer's current ban ends, if it is banned
+    pub banned_until: Option<Instant>,
+    /// When `score` last decayed or was penalized
+    scored_at: Option<Instant>,
+}
+
+impl PeerRecord {
+    /// Whether the peer is banned at `now`
+    #[must_use]
+    pub fn is_banned_at(&self, now: Instant) -> bool {
+        self.banned_until.is_some_and(|until| now < until)
+    }
+
+    /// Take one off the score for every `interval` since it last changed, up to `now`
+    fn decay(&mut self, now: Instant, interval: Duration) {
+        let Some(scored_at) = self.scored_at else {
+            return;
+        };
+        if interval.is_zero() {
+            return;
+        }
+        let steps = now.saturating_duration_since(scored_at).as_nanos() / interval.as_nanos();
+        if steps > 0 {
+            self.score = self
+                .score
+                .saturating_sub(u64::try_from(steps).unwrap_or(u64::MAX));
+            self.scored_at = Some(now);
+        }
+    }
+}
+
+/// Messages counted against a peer's rate limit in the current one second window
+#[derive(Clone, Copy, Debug)]
+struct RateWindow {
+    /// When the window started
+    start: Instant,
+    /// Messages received in the window
+    messages: u64,
+}
+
+/// Scores of every peer which misbehaved, and the peers banned for it
+#[derive(Debug)]
+pub struct PeerScoreboard<TYPES: NodeType> {
+    /// Penalties, ban threshold and rate limit
+    config: PeerScoreConfig,
+
+    /// Record of every peer which misbehaved
+    records: RwLock<HashMap<TYPES::SignatureKey, PeerRecord>>,
+
+    /// Rate limit window of every peer we received a message from, if rate limiting
+    windows: RwLock<HashMap<TYPES::SignatureKey, RateWindow>>,
+
+    /// Sinks to alert of bans
+    alerts: AlertSinks<TYPES>,
+}
+
+impl<TYPES: NodeType> Default for PeerScoreboard<TYPES> {
+    fn default() -> Self {
+        Self::new(PeerScoreConfig::default())
+    }
+}
+
+impl<TYPES: NodeType> PeerScoreboard<TYPES> {
+    /// Create an empty scoreboard
+    #[must_use]
+    pub fn new(config: PeerScoreConfig) -> Self {
+        Self {
+            config,
+            records: RwLock::new(HashMap::new()),
+            windows: RwLock::new(HashMap::new()),
+            alerts: AlertSinks::default(),
+        }
+    }
+
+    /// Alert `alerts` of every ban
+    #[must_use]
+    pub fn with_alerts(mut self, alerts: AlertSinks<TYPES>) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// The scoreboard's configuration
+    #[must_use]
+    pub fn config(&self) -> &PeerScoreConfig {
+        &self.config
+    }
+
+    /// Penalize `peer` for `misbehavior`, banning it if its score reaches the ban threshold.
+    /// Returns whether the peer is banned.
+    pub async fn report(&self, peer: &TYPES::SignatureKey, misbehavior: Misbehavior) -> bool {
+        let now = Instant::now();
+        let mut records = self.records.write().await;
+        let record = records.entry(peer.clone()).or_default();
+        match misbehavior {
+            Misbehavior::Equivocation => record.equivocations += 1,
+        }
+
+        if record.is_banned_at(now) {
+            return true;
+        }
+        record.decay(now, self.config.score_decay_interval);
+        record.score = record
+            .score
+            .saturating_add(self.config.penalty(misbehavior));
+        record.scored_at = Some(now);
+        if self.config.ban_threshold == 0 || record.score < self.config.ban_threshold {
+            return false;
+        }
+
+        record.score = 0;
+        record.bans += 1;
+        record.banned_until = Some(now + self.config.ban_cooldown);
+        tracing::warn!(
+            "Banning peer {} for {:?} after its {}; banned {} times so far",
+            peer,
+            self.config.ban_cooldown,
+            misbehavior,
+            record.bans
+        );
+        let alert = Alert::PeerBanned {
+            peer: peer.clone(),
+            cooldown: self.config.ban_cooldown,
+            bans: record.bans,
+        };
+        drop(records);
+        self.alerts.raise(alert).await;
+
+        true
+    }
+
+    /// Whether `peer` is currently banned
+    pub async fn is_banned(&self, peer: &TYPES::SignatureKey) -> bool {
+        self.records
+            .read()
+            .await
+            .get(peer)
+            .is_some_and(|record| record.is_banned_at(Instant::now()))
+    }
+
+    /// Whether to accept a message claiming to be from `peer`: it must not be banned, and the
+    /// message must be within its rate limit.
+    pub async fn admit(&self, peer: &TYPES::SignatureKey) -> bool {
+        if self.is_banned(peer).await {
+            return false;
+        }
+        let Some(max_messages_per_second) = self.config.max_messages_per_second else {
+            return true;
+        };
+
+        let now = Instant::now();
+        let mut windows = self.windows.write().await;
+        let window = windows.entry(peer.clone()).or_insert(RateWindow {
+            start: now,
+            messages: 0,
+        });
+        if now.duration_since(window.start) >= Duration::from_secs(1) {
+            *window = RateWindow {
+                start: now,
+                messages: 0,
+            };
+        }
+        window.messages += 1;
+
+        window.messages <= max_messages_per_second
+    }
+
+    /// The record of every peer which misbehaved, with scores decayed to now
+    pub async fn records(&self) -> HashMap<TYPES::SignatureKey, PeerRecord> {
+        let now = Instant::now();
+        self.records
+            .read()
+            .await
+            .iter()
+            .map(|(peer, record)| {
+                let mut record = record.clone();
+                record.decay(now, self.config.score_decay_interval);
+                (peer.clone(), record)
+            })
+            .collect()
+    }
+
+    /// Every currently banned peer, with how long its ban still lasts
+    pub async fn banned_peers(&self) -> Vec<(TYPES::SignatureKey, Duration)> {
+        let now = Instant::now();
+        self.records
+            .read()
+            .await
+            .iter()
+            .filter_map(|(peer, record)| {
+                let until = record.banned_until.filter(|until| now < *until)?;
+                Some((peer.clone(), until - now))
+            })
+            .collect()
+    }
+
+    /// Lift the ban on `peer` and reset its score. Returns whether it was banned.
+    pub async fn clear_ban(&self, peer: &TYPES::SignatureKey) -> bool {
+        let now = Instant::now();
+        let mut records = self.records.write().await;
+        let Some(record) = records.get_mut(peer) else {
+            return false;
+        };
+        let was_banned = record.is_banned_at(now);
+        record.score = 0;
+        record.banned_until = None;
+        record.scored_at = None;
+
+        was_banned
+    }
+
+    /// Lift every ban and reset every score
+    pub async fn clear_all_bans(&self) {
+        for record in self.records.write().await.values_mut() {
+            record.score = 0;
+            record.banned_until = None;
+            record.scored_at = None;
+        }
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! The format of records a node persists.
+//!
+//! Leaves, quorum certificates, VID shares and chain snapshots are written to disk in an envelope
+//! which identifies them and the version of the format they were written with:
+//!
+//! | bytes       | contents                                                   |
+//! |-------------|------------------------------------------------------------|
+//! | 4           | [`MAGIC`]                                                  |
+//! | 2           | format version, little endian                              |
+//! | 1           | [`RecordKind`]                                             |
+//! | 8           | length of the payload, little endian                       |
+//! | length      | the record, as `bincode`                                   |
+//! | 32          | SHA-256 of everything before it                            |
+//!
+//! A node writes [`FORMAT_VERSION`] and reads every version since [`OLDEST_READABLE_VERSION`],
+//! upgrading records of older versions as it reads them, so that a node upgrade never requires a
+//! resync. The versions are:
+//!
+//! 1. The records from before epochs: [`Leaf`], [`QuorumCertificate`] and [`VidDisperseShare`].
+//!    There are no snapshots.
+//! 2. [`Leaf2`], [`QuorumCertificate2`], [`VidDisperseShare2`] and [`ChainSnapshot`].
+//!
+//! A version which changes how a record is encoded gets a new number, and its readers keep
+//! decoding the older ones.
+
+use std::fmt::{self, Display};
+
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use utils::anytrace::*;
+
+use crate::{
+    data::{Leaf, Leaf2, VidDisperseShare, VidDisperseShare2},
+    simple_certificate::{QuorumCertificate, QuorumCertificate2},
+    snapshot::ChainSnapshot,
+    traits::node_implementation::NodeType,
+};
+
+/// The bytes every persisted record starts with
+pub const MAGIC: [u8; 4] = *b"HSPR";
+
+/// The version of the format records are written with
+pub const FORMAT_VERSION: u16 = 2;
+
+/// The oldest version of the format records can be read from
+pub const OLDEST_READABLE_VERSION: u16 = 1;
+
+/// Length of the envelope before the payload
+const HEADER_LEN: usize = 4 + 2 + 1 + 8;
+
+/// Length of the checksum after the payload
+const CHECKSUM_LEN: usize = 32;
+
+/// What a persisted record holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum RecordKind {
+    /// A leaf
+    Leaf = 0,
+    /// A quorum certificate
+    QuorumCertificate = 1,
+    /// A VID share
+    VidShare = 2,
+    /// A chain snapshot
+    Snapshot = 3,
+}
+
+impl RecordKind {
+    /// The kind with tag `tag`, if there is one
+    #[must_use]
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Self::Leaf),
+            1 => Some(Self::QuorumCertificate),
+            2 => Some(Self::VidShare),
+            3 => Some(Self::Snapshot),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RecordKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Leaf => "leaf",
+            Self::QuorumCertificate => "quorum certificate",
+            Self::VidShare => "VID share",
+            Self::Snapshot => "snapshot",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A persisted record whose envelope has been checked, before its payload is decoded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    /// Version of the format the record was written with
+    pub version: u16,
+    /// What the record holds
+    pub kind: RecordKind,
+    /// The encoded record
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// The bytes of the record, checksum included
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.payload.len() + CHECKSUM_LEN);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.push(self.kind as u8);
+        bytes.extend_from_slice(&(self.payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.payload);
+        let checksum = Sha256::digest(&bytes);
+        bytes.extend_from_slice(&checksum);
+
+        bytes
+    }
+
+    /// Read the envelope of a record, checking its magic, length and checksum
+    ///
+    /// # Errors
+    /// If `bytes` is not a whole persisted record, or is corrupt
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() >= HEADER_LEN + CHECKSUM_LEN,
+            "Persisted record is {} bytes, shorter than its envelope",
+            bytes.len()
+        );
+        ensure!(bytes[..4] == MAGIC, "Not a persisted record: bad magic");
+
+        let (contents, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        ensure!(
+            Sha256::digest(contents).as_slice() == checksum,
+            "Persisted record is corrupt: checksum mismatch"
+        );
+
+        let version = u16::from_le_bytes([contents[4], contents[5]]);
+        let kind = RecordKind::from_tag(contents[6])
+            .context(error!("Persisted record of unknown kind {}", contents[6]))?;
+        let mut length = [0; 8];
+        length.copy_from_slice(&contents[7..HEADER_LEN]);
+        let length = u64::from_le_bytes(length);
+        let payload = &contents[HEADER_LEN..];
+        ensure!(
+            payload.len() as u64 == length,
+            "Persisted record has a payload of {} bytes, but its envelope says {length}",
+            payload.len()
+        );
+
+        Ok(Self {
+            version,
+            kind,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// A type which is persisted
+pub trait PersistedRecord: Serialize + DeserializeOwned {
+    /// What records of this type hold
+    const KIND: RecordKind;
+
+    /// Decode the payload of a record of version 1 of the format, which held the type this one
+    /// replaced
+    ///
+    /// # Errors
+    /// If the payload cannot be decoded, or there were no such records in version 1
+    fn decode_v1(payload: &[u8]) -> Result<Self>;
+}
+
+/// Decode a `bincode` payload of a record
+fn decode_payload<T: DeserializeOwned>(payload: &[u8], kind: RecordKind) -> Result<T> {
+    bincode::deserialize(payload)
+        .wrap()
+        .context(error!("Failed to decode persisted {kind}"))
+}
+
+impl<TYPES: NodeType> PersistedRecord for Leaf2<TYPES> {
+    const KIND: RecordKind = RecordKind::Leaf;
+
+    fn decode_v1(payload: &[u8]) -> Result<Self> {
+        decode_payload::<Leaf<TYPES>>(payload, Self::KIND).map(Leaf2::from)
+    }
+}
+
+impl<TYPES: NodeType> PersistedRecord for QuorumCertificate2<TYPES> {
+    const KIND: RecordKind = RecordKind::QuorumCertificate;
+
+    fn decode_v1(payload: &[u8]) -> Result<Self> {
+        decode_payload::<QuorumCertificate<TYPES>>(payload, Self::KIND)
+            .map(QuorumCertificate::to_qc2)
+    }
+}
+
+impl<TYPES: NodeType> PersistedRecord for VidDisperseShare2<TYPES> {
+    const KIND: RecordKind = RecordKind::VidShare;
+
+    fn decode_v1(payload: &[u8]) -> Result<Self> {
+        decode_payload::<VidDisperseShare<TYPES>>(payload, Self::KIND).map(VidDisperseShare2::from)
+    }
+}
+
+impl<TYPES: NodeType> PersistedRecord for ChainSnapshot<TYPES> {
+    const KIND: RecordKind = RecordKind::Snapshot;
+
+    fn decode_v1(_payload: &[u8]) -> Result<Self> {
+        bail!("Snapshots were not persisted before version 2 of the format")
+    }
+}
+
+/// The bytes to persist `record` as, in the current version of the format
+///
+/// # Errors
+/// If the record cannot be encoded
+pub fn encode<R: PersistedRecord>(record: &R) -> Result<Vec<u8>> {
+    let payload = bincode::serialize(record)
+        .wrap()
+        .context(error!("Failed to encode {} for persistence", R::KIND))?;
+
+    Ok(Envelope {
+        version: FORMAT_VERSION,
+        kind: R::KIND,
+        payload,
+    }
+    .encode())
+}
+
+/// Read a record persisted with any version of the format since [`OLDEST_READABLE_VERSION`]
+///
+/// # Errors
+/// If `bytes` is not a record of type `R`, is corrupt, or was written with a version of the format
+/// this node cannot read
+pub fn decode<R: PersistedRecord>(bytes: &[u8]) -> Result<R> {
+    let envelope = Envelope::decode(bytes)?;
+    ensure!(
+        envelope.kind == R::KIND,
+        "Expected a persisted {}, found a {}",
+        R::KIND,
+        envelope.kind
+    );
+
+    match envelope.version {
+        1 => R::decode_v1(&envelope.payload),
+        FORMAT_VERSION => decode_payload(&envelope.payload, R::KIND),
+        version => bail!(
+            "Persisted {} has format version {version}, but only versions {OLDEST_READABLE_VERSION} to {FORMAT_VERSION} can be read",
+            R::KIND
+        ),
+    }
+}
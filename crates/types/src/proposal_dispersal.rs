@@ -0,0 +1,280 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Erasure-coded dissemination of large quorum proposals.
+//!
+//! Broadcasting a proposal makes the leader upload it once to every quorum member, which becomes
+//! the bottleneck of a view once proposals are large. Instead, the leader can erasure code the
+//! proposal with the VID scheme and send each member a single [`ProposalChunk`]. Every member
+//! forwards the chunk it was sent to the rest of the quorum, and recovers the proposal with a
+//! [`ProposalChunkCollector`] as soon as it holds enough chunks. The leader then uploads about
+//! `n / k` copies of the proposal rather than `n`, where `k` is the VID recovery threshold.
+
+use std::collections::{BTreeMap, HashMap};
+
+use bincode::Options;
+use jf_vid::VidScheme;
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    data::QuorumProposal2,
+    message::{Proposal, UpgradeLock},
+    traits::{
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    utils::bincode_opts,
+    vid::{vid_recovery_threshold, vid_scheme, VidCommitment, VidCommon, VidSchemeType, VidShare},
+    vote::HasViewNumber,
+};
+
+/// One erasure-coded chunk of a quorum proposal, for the quorum member at `index`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+#[serde(bound(deserialize = ""))]
+pub struct ProposalChunk<TYPES: NodeType> {
+    /// The view of the proposal
+    pub view_number: TYPES::View,
+    /// The epoch whose quorum the proposal is dispersed among
+    pub epoch: TYPES::Epoch,
+    /// Index of the quorum member which forwards this chunk
+    pub index: u32,
+    /// Commitment to the encoded proposal
+    pub commitment: VidCommitment,
+    /// Data common to every chunk, needed to check and recover them
+    pub common: VidCommon,
+    /// This chunk's share of the encoded proposal
+    pub share: VidShare,
+    /// The leader's signature over the view and commitment
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> ProposalChunk<TYPES> {
+    /// Whether `proposal` is large enough to be dispersed in chunks rather than broadcast, given
+    /// the configured minimum size `min_bytes`
+    #[must_use]
+    pub fn should_disperse(
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+        min_bytes: Option<u64>,
+    ) -> bool {
+        min_bytes.is_some_and(|min_bytes| {
+            bincode_opts()
+                .serialized_size(proposal)
+                .is_ok_and(|len| len >= min_bytes)
+        })
+    }
+
+    /// The data the leader signs for the chunks of a proposal
+    fn signed_data(view_number: TYPES::View, commitment: &VidCommitment) -> Vec<u8> {
+        let mut data = (*view_number).to_le_bytes().to_vec();
+        data.extend_from_slice(commitment.as_ref());
+        data
+    }
+
+    /// Erasure code `proposal` among a quorum of `num_nodes`, returning the chunk for each
+    /// member in order. As encoding is blocking, the chunks are signed with the
+    /// [`UpgradeLock::signing_prefix`] of the proposal's view rather than the lock itself.
+    ///
+    /// # Errors
+    /// If the proposal cannot be serialized, encoded or signed
+    pub fn disperse(
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+        epoch: TYPES::Epoch,
+        num_nodes: usize,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signing_prefix: &[u8],
+    ) -> Result<Vec<Self>> {
+        ensure!(num_nodes > 0, "Cannot disperse a proposal among no nodes");
+        let view_number = proposal.data.view_number();
+        let encoded = bincode_opts()
+            .serialize(proposal)
+            .wrap()
+            .context(error!("Failed to serialize proposal"))?;
+        let disperse = vid_scheme(num_nodes)
+            .disperse(encoded)
+            .wrap()
+            .context(error!("Failed to erasure code proposal"))?;
+        let signature = TYPES::SignatureKey::sign(
+            private_key,
+            &[
+                signing_prefix,
+                Self::signed_data(view_number, &disperse.commit).as_slice(),
+            ]
+            .concat(),
+        )
+        .wrap()
+        .context(error!("Failed to sign proposal chunks"))?;
+
+        disperse
+            .shares
+            .into_iter()
+            .enumerate()
+            .map(|(index, share)| {
+                Ok(Self {
+                    view_number,
+                    epoch,
+                    index: u32::try_from(index).wrap()?,
+                    commitment: disperse.commit,
+                    common: disperse.common.clone(),
+                    share,
+                    signature: signature.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Check that the chunk was signed by `leader` and is consistent with its commitment
+    pub async fn is_valid<V: Versions>(
+        &self,
+        leader: &TYPES::SignatureKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> bool {
+        let signing_message = upgrade_lock
+            .signing_message(
+                &Self::signed_data(self.view_number, &self.commitment),
+                self.view_number,
+            )
+            .await;
+        if !leader.validate(&self.signature, &signing_message) {
+            return false;
+        }
+        let num_nodes = VidSchemeType::get_num_storage_nodes(&self.common);
+        if num_nodes == 0 || self.index >= num_nodes {
+            return false;
+        }
+        let Ok(num_nodes) = usize::try_from(num_nodes) else {
+            return false;
+        };
+
+        VidSchemeType::is_consistent(&self.commitment, &self.common).is_ok()
+            && vid_scheme(num_nodes)
+                .verify_share(&self.share, &self.common, &self.commitment)
+                .is_ok_and(|verified| verified.is_ok())
+    }
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for ProposalChunk<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
+
+/// Enough chunks of a proposal to recover it
+#[derive(Clone, Debug)]
+pub struct RecoverableProposal<TYPES: NodeType> {
+    /// The view of the proposal
+    view_number: TYPES::View,
+    /// Data common to every chunk
+    common: VidCommon,
+    /// The chunks' shares
+    shares: Vec<VidShare>,
+}
+
+impl<TYPES: NodeType> RecoverableProposal<TYPES> {
+    /// Decode the proposal from its chunks. This is expensive, so should be run off the task's
+    /// event loop.
+    ///
+    /// # Errors
+    /// If the chunks do not decode to a proposal for their view
+    pub fn recover(self) -> Result<Proposal<TYPES, QuorumProposal2<TYPES>>> {
+        let num_nodes =
+            usize::try_from(VidSchemeType::get_num_storage_nodes(&self.common)).wrap()?;
+        let encoded = vid_scheme(num_nodes)
+            .recover_payload(&self.shares, &self.common)
+            .wrap()
+            .context(warn!("Failed to recover proposal from its chunks"))?;
+        let proposal: Proposal<TYPES, QuorumProposal2<TYPES>> = bincode_opts()
+            .deserialize(&encoded)
+            .wrap()
+            .context(warn!("Recovered proposal chunks are not a proposal"))?;
+        ensure!(
+            proposal.data.view_number() == self.view_number,
+            warn!(
+                "Chunks for view {:?} recovered to a proposal for view {:?}",
+                self.view_number,
+                proposal.data.view_number()
+            )
+        );
+
+        Ok(proposal)
+    }
+}
+
+/// Chunks received for a proposal we have not recovered yet
+#[derive(Debug)]
+struct PendingProposal {
+    /// Data common to every chunk
+    common: VidCommon,
+    /// The shares received so far, by chunk index
+    shares: BTreeMap<u32, VidShare>,
+    /// Whether enough chunks were received to recover the proposal
+    complete: bool,
+}
+
+/// Collects the chunks of dispersed proposals until each can be recovered
+#[derive(Debug)]
+pub struct ProposalChunkCollector<TYPES: NodeType> {
+    /// Chunks received so far, by view and commitment
+    pending: BTreeMap<TYPES::View, HashMap<VidCommitment, PendingProposal>>,
+}
+
+impl<TYPES: NodeType> Default for ProposalChunkCollector<TYPES> {
+    fn default() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> ProposalChunkCollector<TYPES> {
+    /// Add a chunk, which must already have been checked with [`ProposalChunk::is_valid`].
+    /// Returns the proposal's chunks once there are enough of them to recover it, exactly once.
+    pub fn insert(&mut self, chunk: &ProposalChunk<TYPES>) -> Option<RecoverableProposal<TYPES>> {
+        let pending = self
+            .pending
+            .entry(chunk.view_number)
+            .or_default()
+            .entry(chunk.commitment)
+            .or_insert_with(|| PendingProposal {
+                common: chunk.common.clone(),
+                shares: BTreeMap::new(),
+                complete: false,
+            });
+        // A share forwarded under a second index would count twice towards the threshold but
+        // not help recover the proposal
+        if pending.complete || pending.shares.values().any(|share| *share == chunk.share) {
+            return None;
+        }
+        pending.shares.insert(chunk.index, chunk.share.clone());
+
+        let num_nodes =
+            usize::try_from(VidSchemeType::get_num_storage_nodes(&pending.common)).ok()?;
+        if pending.shares.len() < vid_recovery_threshold(num_nodes) {
+            return None;
+        }
+        pending.complete = true;
+
+        Some(RecoverableProposal {
+            view_number: chunk.view_number,
+            common: pending.common.clone(),
+            shares: pending.shares.values().cloned().collect(),
+        })
+    }
+
+    /// Number of chunks received for the proposal of `view` with `commitment`
+    #[must_use]
+    pub fn chunk_count(&self, view: TYPES::View, commitment: &VidCommitment) -> usize {
+        self.pending
+            .get(&view)
+            .and_then(|proposals| proposals.get(commitment))
+            .map_or(0, |pending| pending.shares.len())
+    }
+
+    /// Drop the chunks of every view before `view`
+    pub fn gc(&mut self, view: TYPES::View) {
+        self.pending = self.pending.split_off(&view);
+    }
+}
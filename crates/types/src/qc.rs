@@ -5,18 +5,19 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! Implementation for `BitVectorQc` that uses BLS signature + Bit vector.
-//! See more details in hotshot paper.
+//! See more details in hotshot paper. The checks themselves are in `hotshot_cert_verify`, which
+//! builds without the standard library.
 
 use ark_std::{
     fmt::Debug,
     format,
     marker::PhantomData,
     rand::{CryptoRng, RngCore},
-    vec,
     vec::Vec,
 };
 use bitvec::prelude::*;
 use digest::generic_array::GenericArray;
+use hotshot_cert_verify::{signed_stake, signer_keys, verify_certificate};
 use jf_signature::{AggregateableSignatureSchemes, SignatureError};
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
@@ -74,37 +75,8 @@ where
         signers: &BitSlice,
         sigs: &[A::Signature],
     ) -> Result<Self::Qc, SignatureError> {
-        if signers.len() != qc_pp.stake_entries.len() {
-            return Err(SignatureError::ParameterError(format!(
-                "bit vector len {} != the number of stake entries {}",
-                signers.len(),
-                qc_pp.stake_entries.len(),
-            )));
-        }
-        let total_weight: U256 =
-            qc_pp
-                .stake_entries
-                .iter()
-                .zip(signers.iter())
-                .fold(U256::zero(), |acc, (entry, b)| {
-                    if *b {
-                        acc + entry.stake_amount
-                    } else {
-                        acc
-                    }
-                });
-        if total_weight < qc_pp.threshold {
-            return Err(SignatureError::ParameterError(format!(
-                "total_weight {} less than threshold {}",
-                total_weight, qc_pp.threshold,
-            )));
-        }
-        let mut ver_keys = vec![];
-        for (entry, b) in qc_pp.stake_entries.iter().zip(signers.iter()) {
-            if *b {
-                ver_keys.push(entry.stake_key.clone());
-            }
-        }
+        signed_stake(&qc_pp.stake_entries, signers, qc_pp.threshold)?;
+        let ver_keys = signer_keys(&qc_pp.stake_entries, signers);
         if ver_keys.len() != sigs.len() {
             return Err(SignatureError::ParameterError(format!(
                 "the number of ver_keys {} != the number of partial signatures {}",
@@ -123,40 +95,14 @@ where
         qc: &Self::Qc,
     ) -> Result<Self::QuorumSize, SignatureError> {
         let (sig, signers) = qc;
-        if signers.len() != qc_vp.stake_entries.len() {
-            return Err(SignatureError::ParameterError(format!(
-                "signers bit vector len {} != the number of stake entries {}",
-                signers.len(),
-                qc_vp.stake_entries.len(),
-            )));
-        }
-        let total_weight: U256 =
-            qc_vp
-                .stake_entries
-                .iter()
-                .zip(signers.iter())
-                .fold(U256::zero(), |acc, (entry, b)| {
-                    if *b {
-                        acc + entry.stake_amount
-                    } else {
-                        acc
-                    }
-                });
-        if total_weight < qc_vp.threshold {
-            return Err(SignatureError::ParameterError(format!(
-                "total_weight {} less than threshold {}",
-                total_weight, qc_vp.threshold,
-            )));
-        }
-        let mut ver_keys = vec![];
-        for (entry, b) in qc_vp.stake_entries.iter().zip(signers.iter()) {
-            if *b {
-                ver_keys.push(entry.stake_key.clone());
-            }
-        }
-        A::multi_sig_verify(&qc_vp.agg_sig_pp, &ver_keys[..], message, sig)?;
-
-        Ok(total_weight)
+        Ok(verify_certificate::<A, _, _>(
+            &qc_vp.agg_sig_pp,
+            &qc_vp.stake_entries,
+            qc_vp.threshold,
+            message,
+            sig,
+            signers,
+        )?)
     }
 
     fn trace(
@@ -165,24 +111,9 @@ where
         qc: &Self::Qc,
     ) -> Result<Vec<<A>::VerificationKey>, SignatureError> {
         let (_sig, signers) = qc;
-        if signers.len() != qc_vp.stake_entries.len() {
-            return Err(SignatureError::ParameterError(format!(
-                "signers bit vector len {} != the number of stake entries {}",
-                signers.len(),
-                qc_vp.stake_entries.len(),
-            )));
-        }
-
         Self::check(qc_vp, message, qc)?;
 
-        let signer_pks: Vec<_> = qc_vp
-            .stake_entries
-            .iter()
-            .zip(signers.iter())
-            .filter(|(_, b)| **b)
-            .map(|(pk, _)| pk.stake_key.clone())
-            .collect();
-        Ok(signer_pks)
+        Ok(signer_keys(&qc_vp.stake_entries, signers))
     }
 }
 
@@ -0,0 +1,326 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Read replicas fed by a validator.
+//!
+//! A read replica follows consensus without taking part in it, so that operators can serve reads
+//! from as many processes as they need without adding consensus participants. The validator it is
+//! attached to streams it a feed of [`SignedReplicaFeedMessage`]s: the quorum proposals the
+//! validator validated, each new highest certificate it saw, and its decides. The messages are
+//! serializable, to be carried over whatever channel the operator runs between the processes.
+//!
+//! The channel is authenticated by the messages themselves: each is signed by the validator, and
+//! numbered in sequence within its feed. A [`ReadReplica`] only applies messages signed by the
+//! validator it is attached to, in sequence, so it notices messages which are forged, replayed,
+//! reordered or lost. A feed is identified by the time it was opened, so a replica can move on to
+//! a newer feed after the validator restarts, but never back to an older one. The signature is
+//! over the chain id, as eight little endian bytes, followed by the feed and sequence number, as
+//! eight little endian bytes each, and the bincode serialization of the item.
+
+use std::collections::BTreeMap;
+
+use bincode::Options;
+use committable::Committable;
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    data::{Leaf2, QuorumProposal2},
+    message::{Proposal, UpgradeLock},
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    utils::bincode_opts,
+    vote::HasViewNumber,
+};
+
+/// What a validator tells its read replicas
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(bound(deserialize = ""))]
+#[allow(clippy::large_enum_variant)]
+pub enum ReplicaFeedItem<TYPES: NodeType> {
+    /// A quorum proposal the validator validated
+    Proposal(Proposal<TYPES, QuorumProposal2<TYPES>>),
+    /// A certificate for a higher view than any the validator saw before
+    Certificate(QuorumCertificate2<TYPES>),
+    /// Leaves the validator decided
+    Decide {
+        /// The decided leaves, newest first
+        leaves: Vec<Leaf2<TYPES>>,
+        /// The QC which certifies the newest leaf
+        qc: QuorumCertificate2<TYPES>,
+    },
+}
+
+impl<TYPES: NodeType> ReplicaFeedItem<TYPES> {
+    /// The view the item is for
+    #[must_use]
+    pub fn view_number(&self) -> TYPES::View {
+        match self {
+            Self::Proposal(proposal) => proposal.data.view_number(),
+            Self::Certificate(qc) | Self::Decide { qc, .. } => qc.view_number(),
+        }
+    }
+}
+
+/// An item of a validator's feed, numbered in sequence within the feed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(bound(deserialize = ""))]
+pub struct ReplicaFeedMessage<TYPES: NodeType> {
+    /// The feed, identified by when it was opened, in milliseconds since the Unix epoch
+    pub feed: u64,
+    /// Number of messages sent on the feed before this one
+    pub sequence: u64,
+    /// What the validator tells its replicas
+    pub item: ReplicaFeedItem<TYPES>,
+}
+
+impl<TYPES: NodeType> ReplicaFeedMessage<TYPES> {
+    /// The bytes the validator signs, before the chain id is prepended
+    ///
+    /// # Errors
+    /// If the item cannot be serialized
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        let item = bincode_opts()
+            .serialize(&self.item)
+            .wrap()
+            .context(error!("Failed to serialize read replica feed item"))?;
+        let mut bytes = Vec::with_capacity(2 * std::mem::size_of::<u64>() + item.len());
+        bytes.extend_from_slice(&self.feed.to_le_bytes());
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.extend_from_slice(&item);
+
+        Ok(bytes)
+    }
+}
+
+/// A [`ReplicaFeedMessage`] signed by the validator which sent it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(bound(deserialize = ""))]
+pub struct SignedReplicaFeedMessage<TYPES: NodeType> {
+    /// The message
+    pub message: ReplicaFeedMessage<TYPES>,
+    /// The validator which sent the message
+    pub signer: TYPES::SignatureKey,
+    /// Signature of the validator on the message
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> SignedReplicaFeedMessage<TYPES> {
+    /// Sign `message` as `signer`, with its `private_key`
+    ///
+    /// # Errors
+    /// If the message cannot be serialized or signing fails
+    pub async fn sign<V: Versions>(
+        message: ReplicaFeedMessage<TYPES>,
+        signer: TYPES::SignatureKey,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<Self> {
+        let signature = TYPES::SignatureKey::sign(
+            private_key,
+            &upgrade_lock
+                .signing_message(&message.signed_bytes()?, message.item.view_number())
+                .await,
+        )
+        .wrap()
+        .context(error!("Failed to sign read replica feed message"))?;
+
+        Ok(Self {
+            message,
+            signer,
+            signature,
+        })
+    }
+
+    /// Whether the signature is that of the signer on the message
+    pub async fn is_valid<V: Versions>(&self, upgrade_lock: &UpgradeLock<TYPES, V>) -> bool {
+        let Ok(bytes) = self.message.signed_bytes() else {
+            return false;
+        };
+        let signing_message = upgrade_lock
+            .signing_message(&bytes, self.message.item.view_number())
+            .await;
+
+        self.signer.validate(&self.signature, &signing_message)
+    }
+}
+
+/// The state of a read replica, built from the feed of the validator it is attached to
+#[derive(Clone, Debug)]
+pub struct ReadReplica<TYPES: NodeType> {
+    /// The validator the replica is attached to
+    validator: TYPES::SignatureKey,
+    /// Number of the most recent decided leaves kept
+    retained_leaves: usize,
+    /// The feed being followed, and the sequence number of the next message expected on it
+    position: Option<(u64, u64)>,
+    /// The most recent decided leaves, by height
+    decided: BTreeMap<u64, Leaf2<TYPES>>,
+    /// The QC which certifies the last decided leaf
+    last_decided_qc: Option<QuorumCertificate2<TYPES>>,
+    /// The certificate for the highest view seen
+    high_qc: Option<QuorumCertificate2<TYPES>>,
+    /// The proposal for the highest view seen
+    latest_proposal: Option<Proposal<TYPES, QuorumProposal2<TYPES>>>,
+}
+
+impl<TYPES: NodeType> ReadReplica<TYPES> {
+    /// A replica of `validator` which keeps its `retained_leaves` most recent decided leaves, and
+    /// always at least the last one
+    #[must_use]
+    pub fn new(validator: TYPES::SignatureKey, retained_leaves: usize) -> Self {
+        Self {
+            validator,
+            retained_leaves: retained_leaves.max(1),
+            position: None,
+            decided: BTreeMap::new(),
+            last_decided_qc: None,
+            high_qc: None,
+            latest_proposal: None,
+        }
+    }
+
+    /// Apply the next message of the validator's feed.
+    ///
+    /// # Errors
+    /// If the message is not signed by the validator, is not the next message of the feed being
+    /// followed or the first of a newer feed, or decides leaves which do not extend the leaves
+    /// decided before. A message which is rejected for what it decides is still consumed, so the
+    /// replica stays in sequence with the feed.
+    pub async fn apply<V: Versions>(
+        &mut self,
+        signed: &SignedReplicaFeedMessage<TYPES>,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<()> {
+        ensure!(
+            signed.signer == self.validator,
+            warn!(
+                "Read replica feed message from {} rather than the validator",
+                signed.signer
+            )
+        );
+        ensure!(
+            signed.is_valid(upgrade_lock).await,
+            warn!("Read replica feed message with an invalid signature")
+        );
+
+        let ReplicaFeedMessage {
+            feed,
+            sequence,
+            ref item,
+        } = signed.message;
+        match self.position {
+            Some((current, next)) if feed == current => ensure!(
+                sequence == next,
+                warn!("Expected message {next} of feed {feed}, got message {sequence}")
+            ),
+            Some((current, _)) if feed < current => {
+                bail!(warn!("Message of feed {feed}, older than feed {current}"));
+            }
+            _ => ensure!(
+                sequence == 0,
+                warn!("Joined feed {feed} at message {sequence} rather than at its start")
+            ),
+        }
+        self.position = Some((feed, sequence + 1));
+
+        match item {
+            ReplicaFeedItem::Proposal(proposal) => {
+                if self.latest_proposal.as_ref().map_or(true, |latest| {
+                    latest.data.view_number() < proposal.data.view_number()
+                }) {
+                    self.latest_proposal = Some(proposal.clone());
+                }
+            }
+            ReplicaFeedItem::Certificate(qc) => self.update_high_qc(qc),
+            ReplicaFeedItem::Decide { leaves, qc } => {
+                // Leaves are newest first
+                for leaf in leaves.iter().rev() {
+                    self.push_decided_leaf(leaf)?;
+                }
+                self.update_high_qc(qc);
+                self.last_decided_qc = Some(qc.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record a decided leaf, unless it is older than the last one
+    fn push_decided_leaf(&mut self, leaf: &Leaf2<TYPES>) -> Result<()> {
+        if let Some(last) = self.last_decided_leaf() {
+            if leaf.height() <= last.height() {
+                return Ok(());
+            }
+            // A decide may skip leaves while the validator catches up, so only consecutive leaves
+            // can be checked to extend each other
+            ensure!(
+                leaf.height() > last.height() + 1 || leaf.parent_commitment() == last.commit(),
+                error!(
+                    "Decided leaf at height {} does not extend the last decided leaf",
+                    leaf.height()
+                )
+            );
+        }
+
+        self.decided.insert(leaf.height(), leaf.clone());
+        while self.decided.len() > self.retained_leaves {
+            self.decided.pop_first();
+        }
+
+        Ok(())
+    }
+
+    /// Replace the high QC with `qc` if it is for a higher view
+    fn update_high_qc(&mut self, qc: &QuorumCertificate2<TYPES>) {
+        if self
+            .high_qc
+            .as_ref()
+            .map_or(true, |high_qc| high_qc.view_number() < qc.view_number())
+        {
+            self.high_qc = Some(qc.clone());
+        }
+    }
+
+    /// The validator the replica is attached to
+    #[must_use]
+    pub fn validator(&self) -> &TYPES::SignatureKey {
+        &self.validator
+    }
+
+    /// The decided leaf at `height`, if it is among the leaves kept
+    #[must_use]
+    pub fn decided_leaf(&self, height: u64) -> Option<&Leaf2<TYPES>> {
+        self.decided.get(&height)
+    }
+
+    /// The last decided leaf
+    #[must_use]
+    pub fn last_decided_leaf(&self) -> Option<&Leaf2<TYPES>> {
+        self.decided.last_key_value().map(|(_, leaf)| leaf)
+    }
+
+    /// The QC which certifies the last decided leaf
+    #[must_use]
+    pub fn last_decided_qc(&self) -> Option<&QuorumCertificate2<TYPES>> {
+        self.last_decided_qc.as_ref()
+    }
+
+    /// The certificate for the highest view seen
+    #[must_use]
+    pub fn high_qc(&self) -> Option<&QuorumCertificate2<TYPES>> {
+        self.high_qc.as_ref()
+    }
+
+    /// The proposal for the highest view seen
+    #[must_use]
+    pub fn latest_proposal(&self) -> Option<&Proposal<TYPES, QuorumProposal2<TYPES>>> {
+        self.latest_proposal.as_ref()
+    }
+}
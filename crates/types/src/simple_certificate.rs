@@ -10,7 +10,6 @@ use std::{
     fmt::{self, Debug, Display, Formatter},
     hash::Hash,
     marker::PhantomData,
-    num::NonZeroU64,
     sync::Arc,
 };
 
@@ -24,10 +23,10 @@ use crate::{
     data::serialize_signature2,
     message::UpgradeLock,
     simple_vote::{
-        DaData, DaData2, NextEpochQuorumData2, QuorumData, QuorumData2, QuorumMarker, TimeoutData,
-        TimeoutData2, UpgradeProposalData, VersionedVoteData, ViewSyncCommitData,
-        ViewSyncCommitData2, ViewSyncFinalizeData, ViewSyncFinalizeData2, ViewSyncPreCommitData,
-        ViewSyncPreCommitData2, Voteable,
+        DaData, DaData2, GovernanceData, NextEpochQuorumData2, QuorumData, QuorumData2,
+        QuorumMarker, TimeoutData, TimeoutData2, UpgradeProposalData, VersionedVoteData,
+        ViewSyncCommitData, ViewSyncCommitData2, ViewSyncFinalizeData, ViewSyncFinalizeData2,
+        ViewSyncPreCommitData, ViewSyncPreCommitData2, Voteable,
     },
     traits::{
         election::Membership,
@@ -42,8 +41,37 @@ pub trait Threshold<TYPES: NodeType> {
     /// Calculate a threshold based on the membership
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> U256;
+
+    /// The voters whose stake counts towards this threshold
+    fn stake_table<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        _view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64;
+    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        membership.stake_table(epoch)
+    }
+
+    /// The stake table entry of `pub_key`, if it is one of the voters for this threshold
+    fn stake_table_entry<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        pub_key: &TYPES::SignatureKey,
+        _view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        membership.stake(pub_key, epoch)
+    }
+
+    /// The number of voters for this threshold
+    fn total_nodes<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        _view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> usize {
+        membership.total_nodes(epoch)
+    }
 }
 
 /// Defines a threshold which is 2f + 1 (Amount needed for Quorum)
@@ -53,9 +81,49 @@ pub struct SuccessThreshold {}
 impl<TYPES: NodeType> Threshold<TYPES> for SuccessThreshold {
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        _view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64 {
-        membership.success_threshold(epoch).into()
+    ) -> U256 {
+        membership.success_threshold(epoch)
+    }
+}
+
+/// Defines a threshold which is 2f + 1 over the quorum voters sampled for the certificate's view
+#[derive(Serialize, Deserialize, Eq, Hash, PartialEq, Debug, Clone)]
+pub struct SampledSuccessThreshold {}
+
+impl<TYPES: NodeType> Threshold<TYPES> for SampledSuccessThreshold {
+    fn threshold<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> U256 {
+        membership.sampled_success_threshold(view, epoch)
+    }
+
+    fn stake_table<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        membership.sampled_stake_table(view, epoch)
+    }
+
+    fn stake_table_entry<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        membership.sampled_stake(pub_key, view, epoch)
+    }
+
+    fn total_nodes<MEMBERSHIP: Membership<TYPES>>(
+        membership: &MEMBERSHIP,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> usize {
+        membership.sampled_total_nodes(view, epoch)
     }
 }
 
@@ -66,9 +134,10 @@ pub struct OneHonestThreshold {}
 impl<TYPES: NodeType> Threshold<TYPES> for OneHonestThreshold {
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        _view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64 {
-        membership.failure_threshold(epoch).into()
+    ) -> U256 {
+        membership.failure_threshold(epoch)
     }
 }
 
@@ -79,9 +148,10 @@ pub struct UpgradeThreshold {}
 impl<TYPES: NodeType> Threshold<TYPES> for UpgradeThreshold {
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        _view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64 {
-        membership.upgrade_threshold(epoch).into()
+    ) -> U256 {
+        membership.upgrade_threshold(epoch)
     }
 }
 
@@ -167,16 +237,14 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData>
     async fn is_valid_cert<V: Versions>(
         &self,
         stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
-        threshold: NonZeroU64,
+        threshold: U256,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> bool {
         if self.view_number == TYPES::View::genesis() {
             return true;
         }
-        let real_qc_pp = <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            stake_table,
-            U256::from(u64::from(threshold)),
-        );
+        let real_qc_pp =
+            <TYPES::SignatureKey as SignatureKey>::public_parameter(stake_table, threshold);
         let Ok(commit) = self.data_commitment(upgrade_lock).await else {
             return false;
         };
@@ -190,30 +258,34 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData>
     fn stake_table_entry<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        membership.da_stake(pub_key, epoch)
+        membership.da_stake_at(pub_key, view, epoch)
     }
 
-    /// Proxy's to `Membership.da_stake_table`
+    /// Proxy's to `Membership.da_stake_table_at`
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        membership.da_stake_table(epoch)
+        membership.da_stake_table_at(view, epoch)
     }
-    /// Proxy's to `Membership.da_total_nodes`
+    /// Proxy's to `Membership.da_total_nodes_at`
     fn total_nodes<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> usize {
-        membership.da_total_nodes(epoch)
+        membership.da_total_nodes_at(view, epoch)
     }
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64 {
-        membership.da_success_threshold(epoch).into()
+    ) -> U256 {
+        membership.da_success_threshold_at(view, epoch)
     }
     fn data(&self) -> &Self::Voteable {
         &self.data
@@ -255,16 +327,14 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData2<TY
     async fn is_valid_cert<V: Versions>(
         &self,
         stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
-        threshold: NonZeroU64,
+        threshold: U256,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> bool {
         if self.view_number == TYPES::View::genesis() {
             return true;
         }
-        let real_qc_pp = <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            stake_table,
-            U256::from(u64::from(threshold)),
-        );
+        let real_qc_pp =
+            <TYPES::SignatureKey as SignatureKey>::public_parameter(stake_table, threshold);
         let Ok(commit) = self.data_commitment(upgrade_lock).await else {
             return false;
         };
@@ -278,30 +348,34 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData2<TY
     fn stake_table_entry<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        membership.da_stake(pub_key, epoch)
+        membership.da_stake_at(pub_key, view, epoch)
     }
 
-    /// Proxy's to `Membership.da_stake_table`
+    /// Proxy's to `Membership.da_stake_table_at`
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        membership.da_stake_table(epoch)
+        membership.da_stake_table_at(view, epoch)
     }
-    /// Proxy's to `Membership.da_total_nodes`
+    /// Proxy's to `Membership.da_total_nodes_at`
     fn total_nodes<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> usize {
-        membership.da_total_nodes(epoch)
+        membership.da_total_nodes_at(view, epoch)
     }
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64 {
-        membership.da_success_threshold(epoch).into()
+    ) -> U256 {
+        membership.da_success_threshold_at(view, epoch)
     }
     fn data(&self) -> &Self::Voteable {
         &self.data
@@ -346,16 +420,14 @@ impl<
     async fn is_valid_cert<V: Versions>(
         &self,
         stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
-        threshold: NonZeroU64,
+        threshold: U256,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> bool {
         if self.view_number == TYPES::View::genesis() {
             return true;
         }
-        let real_qc_pp = <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            stake_table,
-            U256::from(u64::from(threshold)),
-        );
+        let real_qc_pp =
+            <TYPES::SignatureKey as SignatureKey>::public_parameter(stake_table, threshold);
         let Ok(commit) = self.data_commitment(upgrade_lock).await else {
             return false;
         };
@@ -367,32 +439,36 @@ impl<
     }
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64 {
-        THRESHOLD::threshold(membership, epoch)
+    ) -> U256 {
+        THRESHOLD::threshold(membership, view, epoch)
     }
 
     fn stake_table_entry<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        membership.stake(pub_key, epoch)
+        THRESHOLD::stake_table_entry(membership, pub_key, view, epoch)
     }
 
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        membership.stake_table(epoch)
+        THRESHOLD::stake_table(membership, view, epoch)
     }
 
     /// Proxy's to `Membership.total_nodes`
     fn total_nodes<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> usize {
-        membership.total_nodes(epoch)
+        THRESHOLD::total_nodes(membership, view, epoch)
     }
 
     fn data(&self) -> &Self::Voteable {
@@ -483,6 +559,39 @@ impl<TYPES: NodeType> UpgradeCertificate<TYPES> {
     }
 }
 
+impl<TYPES: NodeType> GovernanceCertificate<TYPES> {
+    /// Validate a governance certificate against the stake table of `epoch`.
+    /// # Errors
+    /// Returns an error when the governance certificate is invalid.
+    pub async fn validate<V: Versions>(
+        &self,
+        membership: &RwLock<TYPES::Membership>,
+        epoch: TYPES::Epoch,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<()> {
+        let membership_reader = membership.read().await;
+        let membership_stake_table = membership_reader.stake_table(epoch);
+        let membership_success_threshold = membership_reader.success_threshold(epoch);
+        drop(membership_reader);
+
+        ensure!(
+            self.view_number != TYPES::View::genesis() && self.signatures.is_some(),
+            "Governance certificate is not signed."
+        );
+        ensure!(
+            self.is_valid_cert(
+                membership_stake_table,
+                membership_success_threshold,
+                upgrade_lock
+            )
+            .await,
+            "Invalid governance certificate."
+        );
+
+        Ok(())
+    }
+}
+
 impl<TYPES: NodeType> QuorumCertificate<TYPES> {
     /// Convert a `QuorumCertificate` into a `QuorumCertificate2`
     pub fn to_qc2(self) -> QuorumCertificate2<TYPES> {
@@ -738,12 +847,14 @@ impl<TYPES: NodeType> TimeoutCertificate2<TYPES> {
 }
 
 /// Type alias for a `QuorumCertificate`, which is a `SimpleCertificate` over `QuorumData`
-pub type QuorumCertificate<TYPES> = SimpleCertificate<TYPES, QuorumData<TYPES>, SuccessThreshold>;
+pub type QuorumCertificate<TYPES> =
+    SimpleCertificate<TYPES, QuorumData<TYPES>, SampledSuccessThreshold>;
 /// Type alias for a `QuorumCertificate2`, which is a `SimpleCertificate` over `QuorumData2`
-pub type QuorumCertificate2<TYPES> = SimpleCertificate<TYPES, QuorumData2<TYPES>, SuccessThreshold>;
+pub type QuorumCertificate2<TYPES> =
+    SimpleCertificate<TYPES, QuorumData2<TYPES>, SampledSuccessThreshold>;
 /// Type alias for a `QuorumCertificate2`, which is a `SimpleCertificate` over `QuorumData2`
 pub type NextEpochQuorumCertificate2<TYPES> =
-    SimpleCertificate<TYPES, NextEpochQuorumData2<TYPES>, SuccessThreshold>;
+    SimpleCertificate<TYPES, NextEpochQuorumData2<TYPES>, SampledSuccessThreshold>;
 /// Type alias for a `DaCertificate`, which is a `SimpleCertificate` over `DaData`
 pub type DaCertificate<TYPES> = SimpleCertificate<TYPES, DaData, SuccessThreshold>;
 /// Type alias for a `DaCertificate2`, which is a `SimpleCertificate` over `DaData2`
@@ -774,3 +885,6 @@ pub type ViewSyncFinalizeCertificate2<TYPES> =
 /// Type alias for a `UpgradeCertificate`, which is a `SimpleCertificate` of `UpgradeProposalData`
 pub type UpgradeCertificate<TYPES> =
     SimpleCertificate<TYPES, UpgradeProposalData<TYPES>, UpgradeThreshold>;
+/// Type alias for a `GovernanceCertificate`, which is a `SimpleCertificate` of `GovernanceData`
+pub type GovernanceCertificate<TYPES> =
+    SimpleCertificate<TYPES, GovernanceData<TYPES>, SuccessThreshold>;
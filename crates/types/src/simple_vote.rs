@@ -16,12 +16,14 @@ use std::{
 use committable::{Commitment, Committable};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use utils::anytrace::*;
-use vbs::version::Version;
+use vbs::version::{StaticVersionType, Version};
 
 use crate::{
     data::{Leaf, Leaf2},
+    governance::ParameterChange,
     message::UpgradeLock,
     traits::{
+        commitment_scheme::CommitmentScheme,
         node_implementation::{ConsensusTime, NodeType, Versions},
         signature_key::SignatureKey,
     },
@@ -164,6 +166,16 @@ pub struct UpgradeData2<TYPES: NodeType> {
     pub epoch: TYPES::Epoch,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+/// Data used for a vote to change runtime protocol parameters.
+#[serde(bound(deserialize = ""))]
+pub struct GovernanceData<TYPES: NodeType> {
+    /// The new values of the parameters
+    pub change: ParameterChange,
+    /// The first view in which the new values are in effect
+    pub activation_view: TYPES::View,
+}
+
 /// Marker trait for data or commitments that can be voted on.
 /// Only structs in this file can implement voteable.  This is enforced with the `Sealed` trait
 /// Sealing this trait prevents creating new vote types outside this file.
@@ -205,6 +217,7 @@ impl<T: NodeType> QuorumMarker for ViewSyncPreCommitData2<T> {}
 impl<T: NodeType> QuorumMarker for ViewSyncCommitData2<T> {}
 impl<T: NodeType> QuorumMarker for ViewSyncFinalizeData2<T> {}
 impl<T: NodeType + DeserializeOwned> QuorumMarker for UpgradeProposalData<T> {}
+impl<T: NodeType> QuorumMarker for GovernanceData<T> {}
 
 /// A simple yes vote over some votable type.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
@@ -290,6 +303,9 @@ pub struct VersionedVoteData<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions
     /// version applied to the view number
     version: Version,
 
+    /// chain the vote is cast in
+    chain_id: u64,
+
     /// phantom data
     _pd: PhantomData<V>,
 }
@@ -311,6 +327,7 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> VersionedVoteData<TYPE
             data,
             view,
             version,
+            chain_id: upgrade_lock.chain_id,
             _pd: PhantomData,
         })
     }
@@ -329,6 +346,7 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> VersionedVoteData<TYPE
             data,
             view,
             version,
+            chain_id: upgrade_lock.chain_id,
             _pd: PhantomData,
         }
     }
@@ -338,10 +356,20 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> Committable
     for VersionedVoteData<TYPES, DATA, V>
 {
     fn commit(&self) -> Commitment<Self> {
-        committable::RawCommitmentBuilder::new("Vote")
+        let builder = committable::RawCommitmentBuilder::new("Vote")
             .var_size_bytes(self.data.commit().as_ref())
-            .u64(*self.view)
-            .finalize()
+            .u64(*self.view);
+        let commitment = if self.version >= V::ChainId::VERSION {
+            builder.u64_field("chain id", self.chain_id).finalize()
+        } else {
+            builder.finalize()
+        };
+
+        if self.version >= V::CommitmentScheme::VERSION {
+            TYPES::CommitmentScheme::recommit(commitment)
+        } else {
+            commitment
+        }
     }
 }
 
@@ -449,6 +477,15 @@ impl<TYPES: NodeType> Committable for UpgradeData2<TYPES> {
     }
 }
 
+impl<TYPES: NodeType> Committable for GovernanceData<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        committable::RawCommitmentBuilder::new("Governance data")
+            .field("change", self.change.commit())
+            .u64(*self.activation_view)
+            .finalize()
+    }
+}
+
 /// This implements commit for all the types which contain a view and relay public key.
 fn view_and_relay_commit<TYPES: NodeType, T: Committable>(
     view: TYPES::View,
@@ -832,6 +869,8 @@ pub type ViewSyncCommitVote2<TYPES> = SimpleVote<TYPES, ViewSyncCommitData2<TYPE
 pub type UpgradeVote<TYPES> = SimpleVote<TYPES, UpgradeProposalData<TYPES>>;
 /// Upgrade proposal 2 vote
 pub type UpgradeVote2<TYPES> = SimpleVote<TYPES, UpgradeData2<TYPES>>;
+/// Runtime parameter change vote
+pub type GovernanceVote<TYPES> = SimpleVote<TYPES, GovernanceData<TYPES>>;
 
 impl<TYPES: NodeType> Deref for NextEpochQuorumData2<TYPES> {
     type Target = QuorumData2<TYPES>;
@@ -0,0 +1,107 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Snapshots of an existing chain to start a new one from.
+//!
+//! A chain migration or hard-fork restart starts a new network whose state carries on from the
+//! last leaf an existing chain decided. Every node of the new network is given the same
+//! [`ChainSnapshot`] and derives its genesis from it, in place of the built-in genesis: the
+//! genesis leaf keeps the block of the snapshot leaf, so the new chain continues from its height
+//! and state, and the stake table of the new network is the one in the snapshot.
+
+use std::{collections::HashSet, num::NonZeroUsize};
+
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    data::Leaf2,
+    traits::{node_implementation::NodeType, signature_key::SignatureKey},
+    HotShotConfig, PeerConfig,
+};
+
+/// The state of an existing chain which a new chain starts from
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(bound(deserialize = ""))]
+pub struct ChainSnapshot<TYPES: NodeType> {
+    /// The last leaf decided by the existing chain
+    pub leaf: Leaf2<TYPES>,
+    /// The state after `leaf`. If not given, an incomplete state is rebuilt from the header of
+    /// `leaf`, which the application catches up on.
+    pub validated_state: Option<TYPES::ValidatedState>,
+    /// The nodes staked in the new chain
+    pub stake_table: Vec<PeerConfig<TYPES::SignatureKey>>,
+    /// The nodes of the DA committee of the new chain
+    pub da_stake_table: Vec<PeerConfig<TYPES::SignatureKey>>,
+}
+
+impl<TYPES: NodeType> ChainSnapshot<TYPES> {
+    /// The genesis leaf of the new chain
+    #[must_use]
+    pub fn genesis_leaf(&self) -> Leaf2<TYPES> {
+        Leaf2::genesis_from_snapshot(&self.leaf)
+    }
+
+    /// Check that the stake tables can run a chain: neither is empty, no node appears twice in
+    /// either, and every DA node is staked.
+    ///
+    /// # Errors
+    /// If either stake table is unusable
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.stake_table.is_empty(),
+            "The snapshot has an empty stake table"
+        );
+        ensure!(
+            !self.da_stake_table.is_empty(),
+            "The snapshot has an empty DA stake table"
+        );
+
+        let staked = unique_keys::<TYPES>(&self.stake_table)
+            .context(error!("Invalid stake table in snapshot"))?;
+        let da_members = unique_keys::<TYPES>(&self.da_stake_table)
+            .context(error!("Invalid DA stake table in snapshot"))?;
+        ensure!(
+            da_members.is_subset(&staked),
+            "The snapshot has DA nodes which are not staked"
+        );
+
+        Ok(())
+    }
+
+    /// Replace the stake tables of `config` with those of the snapshot
+    ///
+    /// # Errors
+    /// If the snapshot's stake tables are unusable
+    pub fn apply_stake_table(&self, config: &mut HotShotConfig<TYPES::SignatureKey>) -> Result<()> {
+        self.validate()?;
+
+        config.num_nodes_with_stake = NonZeroUsize::new(self.stake_table.len())
+            .context(error!("The snapshot has an empty stake table"))?;
+        config.known_nodes_with_stake.clone_from(&self.stake_table);
+        config.known_da_nodes.clone_from(&self.da_stake_table);
+        config.da_staked_committee_size = self.da_stake_table.len();
+
+        Ok(())
+    }
+}
+
+/// The keys of `peers`, or an error if a key appears twice
+fn unique_keys<TYPES: NodeType>(
+    peers: &[PeerConfig<TYPES::SignatureKey>],
+) -> Result<HashSet<TYPES::SignatureKey>> {
+    let mut keys = HashSet::new();
+    for peer in peers {
+        let key = TYPES::SignatureKey::public_key(&peer.stake_table_entry);
+        ensure!(
+            keys.insert(key.clone()),
+            "Node {} appears more than once",
+            key
+        );
+    }
+
+    Ok(keys)
+}
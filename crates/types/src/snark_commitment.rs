@@ -0,0 +1,125 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Circuit-friendly commitments to headers and QCs
+//!
+//! Proving `HotShot` finality inside a SNARK is expensive if the circuit has to re-hash Keccak
+//! or SHA-256 commitments. [`RescueScheme`] is a [`CommitmentScheme`] over the Rescue hash in the
+//! light client's circuit field, and [`FinalityAccumulator`] folds the Rescue commitments of the
+//! header and QC of each decided leaf into a single field element, so that a prover only has to
+//! open the accumulator instead of a whole chain of leaves.
+
+use ark_ff::{BigInteger, PrimeField};
+use committable::Committable;
+use jf_rescue::crhf::VariableLengthRescueCRHF;
+
+use crate::{
+    data::Leaf2,
+    light_client::CircuitField,
+    traits::{commitment_scheme::CommitmentScheme, node_implementation::NodeType},
+};
+
+/// Number of bytes packed into each field element, so that every chunk fits below the modulus
+const BYTES_PER_ELEMENT: usize = 31;
+
+/// Pack `bytes` into circuit field elements, little endian, `BYTES_PER_ELEMENT` bytes at a time
+fn bytes_to_fields(bytes: &[u8]) -> Vec<CircuitField> {
+    bytes
+        .chunks(BYTES_PER_ELEMENT)
+        .map(CircuitField::from_le_bytes_mod_order)
+        .collect()
+}
+
+/// The little endian encoding of `field`
+fn field_to_bytes(field: CircuitField) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    let encoded = field.into_bigint().to_bytes_le();
+    bytes[..encoded.len()].copy_from_slice(&encoded);
+    bytes
+}
+
+/// The Rescue hash of `inputs`
+fn rescue(inputs: &[CircuitField]) -> CircuitField {
+    // The variable length CRHF pads its input itself, so it cannot fail
+    VariableLengthRescueCRHF::<CircuitField, 1>::evaluate(inputs)
+        .expect("variable length Rescue hash cannot fail")[0]
+}
+
+/// Rescue over the light client's circuit field
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RescueScheme;
+
+impl RescueScheme {
+    /// The Rescue commitment to `value`, as a field element a circuit can use directly
+    #[must_use]
+    pub fn commit_to_field<T: Committable>(value: &T) -> CircuitField {
+        CircuitField::from_le_bytes_mod_order(Self::commit(value).as_ref())
+    }
+}
+
+impl CommitmentScheme for RescueScheme {
+    const NAME: &'static str = "rescue";
+
+    fn rehash(native: &[u8; 32]) -> [u8; 32] {
+        let mut inputs = bytes_to_fields(Self::NAME.as_bytes());
+        inputs.extend(bytes_to_fields(native));
+
+        field_to_bytes(rescue(&inputs))
+    }
+}
+
+/// Incremental accumulator over the headers and QCs of decided leaves
+///
+/// Each appended leaf updates the root to `Rescue(root, height, header, qc)`, where `header` and
+/// `qc` are the Rescue commitments to the leaf's block header and justify QC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FinalityAccumulator {
+    /// The accumulated root, zero before any leaf is appended
+    root: CircuitField,
+    /// Number of leaves appended so far
+    len: u64,
+}
+
+impl FinalityAccumulator {
+    /// An empty accumulator
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The accumulated root
+    #[must_use]
+    pub fn root(&self) -> CircuitField {
+        self.root
+    }
+
+    /// Number of leaves appended so far
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether no leaf has been appended yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append a decided leaf. Leaves must be appended in the order they were decided.
+    pub fn append_leaf<TYPES: NodeType>(&mut self, leaf: &Leaf2<TYPES>) {
+        self.append(
+            leaf.height(),
+            RescueScheme::commit_to_field(leaf.block_header()),
+            RescueScheme::commit_to_field(&leaf.justify_qc()),
+        );
+    }
+
+    /// Append the Rescue commitments `header` and `qc` of the leaf at `height`
+    pub fn append(&mut self, height: u64, header: CircuitField, qc: CircuitField) {
+        self.root = rescue(&[self.root, CircuitField::from(height), header, qc]);
+        self.len += 1;
+    }
+}
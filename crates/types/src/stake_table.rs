@@ -6,10 +6,17 @@
 
 //! Types and structs related to the stake table
 
+use committable::{Commitment, Committable, RawCommitmentBuilder};
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
 
-use crate::traits::signature_key::{SignatureKey, StakeTableEntryType};
+use crate::traits::{
+    election::Membership,
+    node_implementation::NodeType,
+    signature_key::{SignatureKey, StakeTableEntryType},
+};
 
 /// Stake table entry
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Hash, Eq)]
@@ -40,4 +47,398 @@ impl<K: SignatureKey> StakeTableEntry<K> {
     }
 }
 
-// TODO(Chengyu): add stake table snapshot here
+impl<K: SignatureKey> hotshot_cert_verify::StakeEntry for StakeTableEntry<K> {
+    type Key = K;
+
+    fn key(&self) -> &K {
+        &self.stake_key
+    }
+
+    fn stake(&self) -> U256 {
+        self.stake_amount
+    }
+}
+
+/// Sum of the stake of every entry in a stake table.
+pub fn total_stake<K, E: StakeTableEntryType<K>>(stake_table: &[E]) -> U256 {
+    stake_table
+        .iter()
+        .fold(U256::zero(), |total, entry| total + entry.stake())
+}
+
+/// A fraction of the stake of a committee, from none of it to all of it
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Hash, Eq)]
+#[serde(try_from = "UncheckedStakeFraction")]
+pub struct StakeFraction {
+    /// Numerator of the fraction
+    numerator: u64,
+    /// Denominator of the fraction, never zero
+    denominator: u64,
+}
+
+/// A [`StakeFraction`] as deserialized, before it is checked
+#[derive(Deserialize)]
+struct UncheckedStakeFraction {
+    /// Numerator of the fraction
+    numerator: u64,
+    /// Denominator of the fraction
+    denominator: u64,
+}
+
+/// A fraction of the stake which no committee could reach
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+#[error(
+    "A stake fraction of {numerator}/{denominator} must be between 0 and 1 with a nonzero denominator"
+)]
+pub struct InvalidStakeFraction {
+    /// Numerator of the fraction
+    pub numerator: u64,
+    /// Denominator of the fraction
+    pub denominator: u64,
+}
+
+impl StakeFraction {
+    /// All of the stake
+    pub const ALL: Self = Self {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    /// The fraction `numerator / denominator` of the stake
+    ///
+    /// # Errors
+    /// If `denominator` is zero or smaller than `numerator`, since no committee could reach the
+    /// threshold
+    pub fn new(numerator: u64, denominator: u64) -> Result<Self, InvalidStakeFraction> {
+        if denominator == 0 || numerator > denominator {
+            return Err(InvalidStakeFraction {
+                numerator,
+                denominator,
+            });
+        }
+
+        Ok(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// Numerator of the fraction
+    #[must_use]
+    pub fn numerator(&self) -> u64 {
+        self.numerator
+    }
+
+    /// Denominator of the fraction
+    #[must_use]
+    pub fn denominator(&self) -> u64 {
+        self.denominator
+    }
+}
+
+impl TryFrom<UncheckedStakeFraction> for StakeFraction {
+    type Error = InvalidStakeFraction;
+
+    fn try_from(fraction: UncheckedStakeFraction) -> Result<Self, Self::Error> {
+        Self::new(fraction.numerator, fraction.denominator)
+    }
+}
+
+/// A function from the total stake of a committee to the stake needed to reach a threshold.
+///
+/// All variants are computed over stake weight, not node count, so that committees with uneven
+/// stake distributions get correct thresholds. With unit stake they reduce to the familiar
+/// node-count thresholds.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Hash, Eq)]
+pub enum ThresholdFunction {
+    /// More than two thirds of the stake, i.e. `2f + 1` by stake
+    TwoThirds,
+    /// More than one third of the stake, i.e. `f + 1` by stake
+    OneThird,
+    /// 90% of the stake, but never less than [`ThresholdFunction::TwoThirds`]
+    Upgrade,
+    /// More than 90% of the stake, the upgrade threshold of
+    /// `StaticCommitteeLeaderForTwoViews`
+    MoreThanNineTenths,
+    /// More than half of the stake. Only crash faults are tolerated with these quorums, see
+    /// [`ThresholdConfig::small_network`].
+    Majority,
+    /// A fixed fraction of the stake, rounded up
+    Fraction(StakeFraction),
+}
+
+impl ThresholdFunction {
+    /// The fixed fraction `numerator / denominator` of the stake
+    ///
+    /// # Errors
+    /// If the fraction is not between 0 and 1 with a nonzero denominator
+    pub fn fraction(numerator: u64, denominator: u64) -> Result<Self, InvalidStakeFraction> {
+        StakeFraction::new(numerator, denominator).map(Self::Fraction)
+    }
+
+    /// The stake needed to reach this threshold, given the total stake of the committee.
+    ///
+    /// The result is always at least 1.
+    #[must_use]
+    pub fn threshold(&self, total_stake: U256) -> U256 {
+        let two_thirds = total_stake * 2 / 3 + 1;
+        let threshold = match *self {
+            Self::TwoThirds => two_thirds,
+            Self::OneThird => total_stake / 3 + 1,
+            Self::Upgrade => std::cmp::max(total_stake * 9 / 10, two_thirds),
+            Self::MoreThanNineTenths => total_stake * 9 / 10 + 1,
+            Self::Majority => total_stake / 2 + 1,
+            Self::Fraction(fraction) => {
+                let scaled = total_stake * fraction.numerator();
+                let denominator = U256::from(fraction.denominator());
+                (scaled + denominator - 1) / denominator
+            }
+        };
+        std::cmp::max(threshold, U256::one())
+    }
+
+    /// The stake needed to reach this threshold over the given stake table.
+    #[must_use]
+    pub fn for_stake_table<K, E: StakeTableEntryType<K>>(&self, stake_table: &[E]) -> U256 {
+        self.threshold(total_stake(stake_table))
+    }
+}
+
+/// The threshold functions used by a `Membership` for each kind of certificate.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Hash, Eq)]
+pub struct ThresholdConfig {
+    /// Threshold for quorum, timeout and view sync commit/finalize certificates
+    pub success: ThresholdFunction,
+    /// Threshold for DA certificates, over the DA stake table
+    pub da_success: ThresholdFunction,
+    /// Threshold for view sync pre-commit certificates
+    pub failure: ThresholdFunction,
+    /// Threshold for upgrade certificates
+    pub upgrade: ThresholdFunction,
+}
+
+impl Default for ThresholdConfig {
+    fn default() -> Self {
+        Self {
+            success: ThresholdFunction::TwoThirds,
+            da_success: ThresholdFunction::TwoThirds,
+            failure: ThresholdFunction::OneThird,
+            upgrade: ThresholdFunction::Upgrade,
+        }
+    }
+}
+
+impl ThresholdConfig {
+    /// Thresholds for development networks of at most
+    /// [`SMALL_NETWORK_MAX_NODES`](crate::constants::SMALL_NETWORK_MAX_NODES) nodes.
+    ///
+    /// Two thirds of one, two or three nodes is all of them, so a single node going down stops
+    /// such a network. These thresholds only need a majority instead, which keeps a three node
+    /// network deciding with one node down, at the cost of only tolerating crash faults. Upgrades
+    /// still need every node.
+    #[must_use]
+    pub fn small_network() -> Self {
+        Self {
+            success: ThresholdFunction::Majority,
+            da_success: ThresholdFunction::Majority,
+            failure: ThresholdFunction::OneThird,
+            upgrade: ThresholdFunction::Fraction(StakeFraction::ALL),
+        }
+    }
+}
+
+/// Digest of a stake table: SHA-256 over each entry's public key and stake, in table order.
+pub fn stake_table_digest<K: SignatureKey, E: StakeTableEntryType<K>>(
+    stake_table: &[E],
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for entry in stake_table {
+        let key = entry.public_key().to_bytes();
+        let mut stake = [0u8; 32];
+        entry.stake().to_little_endian(&mut stake);
+
+        hasher.update((key.len() as u64).to_le_bytes());
+        hasher.update(&key);
+        hasher.update(stake);
+    }
+    hasher.finalize().into()
+}
+
+/// Commitment to the stake table active in the epoch of a block, and to the table of the epoch
+/// after it, carried in block headers so that light clients can follow validator set changes
+/// from headers alone.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Hash, Eq)]
+pub struct StakeTableCommitment {
+    /// Digest of the stake table of the block's epoch
+    pub current: [u8; 32],
+    /// Digest of the stake table of the next epoch, if it is already known
+    pub next: Option<[u8; 32]>,
+}
+
+impl StakeTableCommitment {
+    /// The commitment for a block in `epoch`, from the stake tables in `membership`
+    pub fn new<TYPES: NodeType>(membership: &TYPES::Membership, epoch: TYPES::Epoch) -> Self {
+        let current = membership.stake_table(epoch);
+        let next = membership.stake_table(epoch + 1);
+
+        Self {
+            current: stake_table_digest::<TYPES::SignatureKey, _>(&current),
+            next: (!next.is_empty()).then(|| stake_table_digest::<TYPES::SignatureKey, _>(&next)),
+        }
+    }
+}
+
+impl Committable for StakeTableCommitment {
+    fn commit(&self) -> Commitment<Self> {
+        let builder = RawCommitmentBuilder::new("Stake table commitment")
+            .fixed_size_field("current", &self.current);
+        match self.next {
+            Some(next) => builder.fixed_size_field("next", &next),
+            None => builder,
+        }
+        .finalize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::signature_key::BLSPubKey;
+
+    fn table(stakes: &[u64]) -> Vec<StakeTableEntry<BLSPubKey>> {
+        (0u64..)
+            .zip(stakes)
+            .map(|(i, stake)| StakeTableEntry {
+                stake_key: BLSPubKey::generated_from_seed_indexed([0u8; 32], i).0,
+                stake_amount: U256::from(*stake),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unit_stake_matches_node_count_thresholds() {
+        let table = table(&[1; 10]);
+        assert_eq!(
+            ThresholdFunction::TwoThirds
+                .for_stake_table(&table)
+                .as_u64(),
+            7
+        );
+        assert_eq!(
+            ThresholdFunction::OneThird.for_stake_table(&table).as_u64(),
+            4
+        );
+        assert_eq!(
+            ThresholdFunction::Upgrade.for_stake_table(&table).as_u64(),
+            9
+        );
+    }
+
+    #[test]
+    fn thresholds_are_weighted_by_stake() {
+        let table = table(&[100, 1, 1, 1]);
+        assert_eq!(
+            ThresholdFunction::TwoThirds
+                .for_stake_table(&table)
+                .as_u64(),
+            69
+        );
+        assert_eq!(
+            ThresholdFunction::fraction(1, 2)
+                .unwrap()
+                .for_stake_table(&table)
+                .as_u64(),
+            52
+        );
+    }
+
+    #[test]
+    fn upgrade_thresholds() {
+        for (nodes, upgrade, more_than_nine_tenths) in [(10, 9, 10), (4, 3, 4), (20, 18, 19)] {
+            let table = table(&vec![1; nodes]);
+            assert_eq!(
+                ThresholdFunction::Upgrade.for_stake_table(&table).as_u64(),
+                upgrade
+            );
+            assert_eq!(
+                ThresholdFunction::MoreThanNineTenths
+                    .for_stake_table(&table)
+                    .as_u64(),
+                more_than_nine_tenths
+            );
+        }
+    }
+
+    #[test]
+    fn wei_denominated_thresholds_are_not_clamped() {
+        // 400 ETH in total, far more than fits in a `u64` of wei
+        let stake = U256::exp10(20);
+        let table: Vec<_> = table(&[1; 4])
+            .into_iter()
+            .map(|entry| StakeTableEntry {
+                stake_amount: stake,
+                ..entry
+            })
+            .collect();
+        let total = total_stake(&table);
+        assert!(total > U256::from(u64::MAX));
+        assert_eq!(
+            ThresholdFunction::TwoThirds.for_stake_table(&table),
+            total * 2 / 3 + 1
+        );
+        assert_eq!(
+            ThresholdFunction::OneThird.for_stake_table(&table),
+            total / 3 + 1
+        );
+    }
+
+    #[test]
+    fn unreachable_fractions_are_rejected() {
+        assert!(ThresholdFunction::fraction(3, 2).is_err());
+        assert!(ThresholdFunction::fraction(1, 0).is_err());
+        assert!(ThresholdFunction::fraction(0, 0).is_err());
+        assert!(ThresholdFunction::fraction(0, 1).is_ok());
+        assert_eq!(
+            ThresholdFunction::fraction(1, 1),
+            Ok(ThresholdFunction::Fraction(StakeFraction::ALL))
+        );
+
+        // Deserializing checks the fraction too
+        let valid: StakeFraction =
+            serde_json::from_str(r#"{"numerator": 2, "denominator": 3}"#).unwrap();
+        assert_eq!(valid, StakeFraction::new(2, 3).unwrap());
+        assert!(
+            serde_json::from_str::<StakeFraction>(r#"{"numerator": 4, "denominator": 3}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn small_network_thresholds_need_a_majority() {
+        let config = ThresholdConfig::small_network();
+        for (nodes, success, failure, upgrade) in [(1, 1, 1, 1), (2, 2, 1, 2), (3, 2, 2, 3)] {
+            let table = table(&vec![1; nodes]);
+            assert_eq!(config.success.for_stake_table(&table).as_u64(), success);
+            assert_eq!(config.da_success.for_stake_table(&table).as_u64(), success);
+            assert_eq!(config.failure.for_stake_table(&table).as_u64(), failure);
+            assert_eq!(config.upgrade.for_stake_table(&table).as_u64(), upgrade);
+        }
+    }
+
+    #[test]
+    fn stake_table_digest_covers_keys_stakes_and_order() {
+        let digest = stake_table_digest::<BLSPubKey, _>(&table(&[1, 2, 3]));
+        assert_eq!(
+            digest,
+            stake_table_digest::<BLSPubKey, _>(&table(&[1, 2, 3]))
+        );
+        assert_ne!(
+            digest,
+            stake_table_digest::<BLSPubKey, _>(&table(&[1, 2, 4]))
+        );
+        assert_ne!(digest, stake_table_digest::<BLSPubKey, _>(&table(&[1, 2])));
+
+        let mut reordered = table(&[1, 2, 3]);
+        reordered.swap(0, 1);
+        assert_ne!(digest, stake_table_digest::<BLSPubKey, _>(&reordered));
+    }
+}
@@ -7,6 +7,7 @@
 //! Common traits for the `HotShot` protocol
 pub mod auction_results_provider;
 pub mod block_contents;
+pub mod commitment_scheme;
 pub mod consensus_api;
 pub mod election;
 pub mod metrics;
@@ -25,7 +25,11 @@ use vbs::version::Version;
 
 use super::signature_key::BuilderSignatureKey;
 use crate::{
+    chain_config::ChainConfig,
     data::Leaf2,
+    header_extension::HeaderExtension,
+    simple_certificate::GovernanceCertificate,
+    stake_table::StakeTableCommitment,
     traits::{node_implementation::NodeType, states::InstanceState, ValidatedState},
     utils::BuilderCommitment,
     vid::{vid_scheme, VidCommitment, VidCommon, VidSchemeType},
@@ -46,6 +50,36 @@ pub trait Transaction:
     /// Since each new namespace adds overhead
     /// just ignore this parameter by default and use it when needed
     fn minimum_block_size(&self) -> u64;
+
+    /// The ciphertext of this transaction, if it was encrypted to the DA committee's threshold
+    /// key. Such transactions are decrypted after their block is decided.
+    fn ciphertext(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+/// Priority class a transaction was submitted with.
+///
+/// Leaders reserve part of each block they assemble for high-priority transactions, and builders
+/// are told about the priority of the transactions they receive.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum TransactionPriority {
+    /// Regular transactions
+    #[default]
+    Normal,
+    /// Application-critical transactions
+    High,
+}
+
+impl Display for TransactionPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionPriority::Normal => write!(f, "normal"),
+            TransactionPriority::High => write!(f, "high"),
+        }
+    }
 }
 
 /// Abstraction over the full contents of a block
@@ -248,4 +282,60 @@ pub trait BlockHeader<TYPES: NodeType>:
 
     /// Get the results of the auction for this Header. Only used in post-marketplace versions
     fn get_auction_results(&self) -> Option<TYPES::AuctionResult>;
+
+    /// Get the total fee claimed by the builder(s) of this block, if the header records it.
+    fn builder_fee_amount(&self) -> Option<u64> {
+        None
+    }
+
+    /// Get the base fee of this block, if the chain has a fee market.
+    fn base_fee(&self) -> Option<u64> {
+        None
+    }
+
+    /// Get how much of the block's capacity is used, which determines the base fee of its
+    /// child. See [`BaseFeeParams`](crate::fee_market::BaseFeeParams).
+    fn block_usage(&self) -> u64 {
+        0
+    }
+
+    /// Get the commitment to the chain configuration of the leader which built this block, if
+    /// the header commits to one. See [`ChainConfig`].
+    fn chain_config_commitment(&self) -> Option<Commitment<ChainConfig>> {
+        None
+    }
+
+    /// Get the commitment to the stake tables of this block's epoch and the next, if the header
+    /// carries one. See [`StakeTableCommitment`].
+    fn stake_table_commitment(&self) -> Option<StakeTableCommitment> {
+        None
+    }
+
+    /// Attach the commitment to the stake tables of this block's epoch and the next. Called by
+    /// the leader on every header it proposes; headers which do not carry the commitment ignore
+    /// it.
+    #[must_use]
+    fn with_stake_table_commitment(self, _commitment: StakeTableCommitment) -> Self {
+        self
+    }
+
+    /// Get the certificate of the change to runtime parameters decided with this block, if the
+    /// block includes a governance transaction. See [`crate::governance`].
+    fn governance_certificate(&self) -> Option<GovernanceCertificate<TYPES>> {
+        None
+    }
+
+    /// Get the application-defined extension of this header, if it carries one. See
+    /// [`HeaderExtension`].
+    fn header_extension(&self) -> Option<&HeaderExtension> {
+        None
+    }
+
+    /// Attach the application-defined extension built by
+    /// [`InstanceState::build_header_extension`]. Headers which cannot carry an extension ignore
+    /// it, and then fail validation on replicas which expect one.
+    #[must_use]
+    fn with_header_extension(self, _extension: HeaderExtension) -> Self {
+        self
+    }
 }
@@ -0,0 +1,87 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Selectable hash functions for leaf and certificate commitments
+//!
+//! Every `Committable` type in `HotShot` commits with `committable`'s native Keccak-256 builder.
+//! A [`CommitmentScheme`] re-commits those native commitments under another hash function, so a
+//! deployment can move its leaves, QCs and DA certificates to e.g. SHA-256 or a SNARK-friendly
+//! hash without touching every `Committable` implementation. The scheme only takes effect from
+//! [`Versions::CommitmentScheme`](super::node_implementation::Versions::CommitmentScheme) on,
+//! so that all nodes switch over at the same view.
+
+use std::fmt::Debug;
+
+use committable::{Commitment, Committable};
+use sha2::{Digest, Sha256};
+
+/// A hash function that leaf and certificate commitments are computed with
+pub trait CommitmentScheme: Clone + Copy + Debug + Send + Sync + 'static {
+    /// A short, stable name for the scheme, for logs and configuration
+    const NAME: &'static str;
+
+    /// Derive this scheme's digest from a native commitment's digest `native`
+    fn rehash(native: &[u8; 32]) -> [u8; 32];
+
+    /// Re-commit the native commitment `commitment` under this scheme
+    #[must_use]
+    fn recommit<T: Committable>(commitment: Commitment<T>) -> Commitment<T> {
+        let mut native = [0u8; 32];
+        native.copy_from_slice(commitment.as_ref());
+
+        Commitment::from_raw(Self::rehash(&native))
+    }
+
+    /// Commit to `value` under this scheme
+    #[must_use]
+    fn commit<T: Committable>(value: &T) -> Commitment<T> {
+        Self::recommit(value.commit())
+    }
+}
+
+/// `committable`'s native Keccak-256 commitments, left as they are
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Keccak256Scheme;
+
+impl CommitmentScheme for Keccak256Scheme {
+    const NAME: &'static str = "keccak256";
+
+    fn rehash(native: &[u8; 32]) -> [u8; 32] {
+        *native
+    }
+}
+
+/// SHA-256 over the native commitment
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Sha256Scheme;
+
+impl CommitmentScheme for Sha256Scheme {
+    const NAME: &'static str = "sha256";
+
+    fn rehash(native: &[u8; 32]) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(Self::NAME)
+            .chain_update(native)
+            .finalize()
+            .into()
+    }
+}
+
+/// BLAKE3 over the native commitment
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Blake3Scheme;
+
+impl CommitmentScheme for Blake3Scheme {
+    const NAME: &'static str = "blake3";
+
+    fn rehash(native: &[u8; 32]) -> [u8; 32] {
+        *blake3::Hasher::new()
+            .update(Self::NAME.as_bytes())
+            .update(native)
+            .finalize()
+            .as_bytes()
+    }
+}
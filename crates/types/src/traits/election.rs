@@ -5,12 +5,13 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! The election trait, used to decide which node is the leader and determine if a vote is valid.
-use std::{collections::BTreeSet, fmt::Debug, num::NonZeroU64};
+use std::{collections::BTreeSet, fmt::Debug, num::NonZeroUsize};
 
+use primitive_types::U256;
 use utils::anytrace::Result;
 
 use super::node_implementation::NodeType;
-use crate::{traits::signature_key::SignatureKey, PeerConfig};
+use crate::{stake_table::ThresholdConfig, traits::signature_key::SignatureKey, PeerConfig};
 
 /// A protocol for determining membership in and participating in a committee.
 pub trait Membership<TYPES: NodeType>: Debug + Send + Sync {
@@ -25,6 +26,24 @@ pub trait Membership<TYPES: NodeType>: Debug + Send + Sync {
         da_committee_members: Vec<PeerConfig<TYPES::SignatureKey>>,
     ) -> Self;
 
+    /// Replace the threshold functions used to size certificates, if the committee supports it
+    fn set_thresholds(&mut self, _thresholds: ThresholdConfig) {}
+
+    /// Set the randomness beacon which per-view quorum samples are drawn with from `from_epoch` on,
+    /// until one is set for a later epoch, if the committee samples them. The beacon must be the
+    /// same on every node and unpredictable before it is set.
+    fn set_sampling_beacon(&mut self, _from_epoch: TYPES::Epoch, _beacon: [u8; 32]) {}
+
+    /// The beacon samples of `epoch` are drawn with, if the committee samples them and one is set
+    fn sampling_beacon(&self, _epoch: TYPES::Epoch) -> Option<[u8; 32]> {
+        None
+    }
+
+    /// Limit the DA committee to its first `size` members from view `from_view` on, or restore
+    /// all of them if `size` is `None`, if the committee supports it. Views before `from_view`
+    /// keep the committee they had.
+    fn set_da_committee_size(&mut self, _from_view: TYPES::View, _size: Option<NonZeroUsize>) {}
+
     /// Get all participants in the committee (including their stake) for a specific epoch
     fn stake_table(
         &self,
@@ -115,14 +134,98 @@ pub trait Membership<TYPES: NodeType>: Debug + Send + Sync {
     fn da_total_nodes(&self, epoch: TYPES::Epoch) -> usize;
 
     /// Returns the threshold for a specific `Membership` implementation
-    fn success_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64;
+    fn success_threshold(&self, epoch: TYPES::Epoch) -> U256;
 
     /// Returns the DA threshold for a specific `Membership` implementation
-    fn da_success_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64;
+    fn da_success_threshold(&self, epoch: TYPES::Epoch) -> U256;
 
     /// Returns the threshold for a specific `Membership` implementation
-    fn failure_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64;
+    fn failure_threshold(&self, epoch: TYPES::Epoch) -> U256;
 
     /// Returns the threshold required to upgrade the network protocol
-    fn upgrade_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64;
+    fn upgrade_threshold(&self, epoch: TYPES::Epoch) -> U256;
+
+    /// Get the quorum voters (including their stake) sampled for view `view` in `epoch`.
+    ///
+    /// Memberships which don't sample per-view committees return the full stake table.
+    fn sampled_stake_table(
+        &self,
+        _view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.stake_table(epoch)
+    }
+
+    /// Get the stake table entry for a public key if it was sampled as a quorum voter for view
+    /// `view` in `epoch`, returns `None` otherwise
+    fn sampled_stake(
+        &self,
+        pub_key: &TYPES::SignatureKey,
+        _view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.stake(pub_key, epoch)
+    }
+
+    /// See if a node was sampled as a quorum voter for view `view` in `epoch`
+    fn has_sampled_stake(
+        &self,
+        pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> bool {
+        self.sampled_stake(pub_key, view, epoch).is_some()
+    }
+
+    /// Returns the number of quorum voters sampled for view `view` in `epoch`
+    fn sampled_total_nodes(&self, view: TYPES::View, epoch: TYPES::Epoch) -> usize {
+        self.sampled_stake_table(view, epoch).len()
+    }
+
+    /// Returns the quorum threshold over the voters sampled for view `view` in `epoch`
+    fn sampled_success_threshold(&self, _view: TYPES::View, epoch: TYPES::Epoch) -> U256 {
+        self.success_threshold(epoch)
+    }
+
+    /// Get the DA committee (including their stake) for view `view` in `epoch`.
+    ///
+    /// Memberships whose DA committee doesn't change within an epoch return the DA stake table.
+    fn da_stake_table_at(
+        &self,
+        _view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.da_stake_table(epoch)
+    }
+
+    /// Get the DA stake table entry for a public key if it is on the DA committee for view
+    /// `view` in `epoch`, returns `None` otherwise
+    fn da_stake_at(
+        &self,
+        pub_key: &TYPES::SignatureKey,
+        _view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.da_stake(pub_key, epoch)
+    }
+
+    /// See if a node is on the DA committee for view `view` in `epoch`
+    fn has_da_stake_at(
+        &self,
+        pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> bool {
+        self.da_stake_at(pub_key, view, epoch).is_some()
+    }
+
+    /// Returns the number of DA nodes on the DA committee for view `view` in `epoch`
+    fn da_total_nodes_at(&self, view: TYPES::View, epoch: TYPES::Epoch) -> usize {
+        self.da_stake_table_at(view, epoch).len()
+    }
+
+    /// Returns the DA threshold over the DA committee for view `view` in `epoch`
+    fn da_success_threshold_at(&self, _view: TYPES::View, epoch: TYPES::Epoch) -> U256 {
+        self.da_success_threshold(epoch)
+    }
 }
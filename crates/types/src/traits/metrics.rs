@@ -232,6 +232,7 @@ dyn_clone::clone_trait_object!(Metrics);
 dyn_clone::clone_trait_object!(Gauge);
 dyn_clone::clone_trait_object!(Counter);
 dyn_clone::clone_trait_object!(Histogram);
+dyn_clone::clone_trait_object!(GaugeFamily);
 
 #[cfg(test)]
 mod test {
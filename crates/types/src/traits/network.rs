@@ -13,8 +13,8 @@ use std::{
     fmt::{Debug, Display},
     hash::Hash,
     pin::Pin,
-    sync::Arc,
-    time::Duration,
+    sync::{Arc, PoisonError},
+    time::{Duration, Instant},
 };
 
 use async_lock::RwLock;
@@ -27,10 +27,11 @@ use rand::{
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+#[cfg(feature = "runtime")]
 use tokio::{sync::mpsc::error::TrySendError, time::sleep};
 
 use super::{node_implementation::NodeType, signature_key::SignatureKey};
-use crate::{data::ViewNumber, message::SequencingMessage, BoxSyncFuture};
+use crate::{data::ViewNumber, message::SequencingMessage, wire_codec::WireFormat, BoxSyncFuture};
 
 /// Centralized server specific errors
 #[derive(Debug, Error, Serialize, Deserialize)]
@@ -142,6 +143,10 @@ pub enum RequestKind<TYPES: NodeType> {
     DaProposal(TYPES::View),
     /// Request for quorum proposal for a view
     Proposal(TYPES::View),
+    /// Request the VID share of another node, used for availability sampling
+    VidSample(TYPES::View, TYPES::SignatureKey),
+    /// Request the DA certificate for a view
+    DaCertificate(TYPES::View),
 }
 
 /// A response for a request.  `SequencingMessage` is the same as other network messages
@@ -249,6 +254,7 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     ///
     /// # Errors
     /// Does not error.
+    #[cfg(feature = "runtime")]
     fn queue_node_lookup(
         &self,
         _view_number: ViewNumber,
@@ -269,10 +275,21 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     {
     }
 
+    /// Establish connections to `peers` ahead of time, so that direct messages to them don't
+    /// wait on connection setup. Used to connect to the leaders of upcoming views.
+    ///
+    /// Networks without per-peer connections need not do anything.
+    fn warm_up_connections(&self, _peers: Vec<K>) {}
+
     /// Is primary network down? Makes sense only for combined network
     fn is_primary_down(&self) -> bool {
         false
     }
+
+    /// The encoding of messages sent over this network
+    fn wire_format(&self) -> WireFormat {
+        WireFormat::default()
+    }
 }
 
 /// A channel generator for types that need asynchronous execution
@@ -340,6 +357,13 @@ pub trait NetworkReliability: Debug + Sync + std::marker::Send + DynClone + 'sta
         1
     }
 
+    /// how long a message of `len` bytes from `sender` to `recipient` takes to be transmitted,
+    /// including the time it waits for earlier messages on the links it crosses.
+    /// `sender` and `recipient` are the bytes of the nodes' public keys.
+    fn transmission_delay(&self, _sender: &[u8], _recipient: &[u8], _len: usize) -> Duration {
+        Duration::ZERO
+    }
+
     /// given a message and a way to send the message,
     /// decide whether or not to send the message
     /// how long to delay the message
@@ -349,6 +373,7 @@ pub trait NetworkReliability: Debug + Sync + std::marker::Send + DynClone + 'sta
     /// note: usually self is stored in a rwlock
     /// so instead of doing the sending part, we just fiddle with the message
     /// then return a future that does the sending and delaying
+    #[cfg(feature = "runtime")]
     fn chaos_send_msg(
         &self,
         msg: Vec<u8>,
@@ -580,6 +605,94 @@ impl NetworkReliability for ChaosNetwork {
     }
 }
 
+/// A network whose links have limited bandwidth.
+///
+/// Every node has an upload and a download link of its own, and every message also crosses a link
+/// shared by the whole network. A message occupies each link it crosses for its length divided by
+/// the bandwidth of the link, and waits for the messages sent before it to clear a busy link. A
+/// burst of large messages, such as a VID disperse, therefore queues up the way it would on a
+/// saturated network. Only the memory network applies transmission delays.
+#[derive(Clone, Debug)]
+pub struct BandwidthLimitedNetwork {
+    /// upload bandwidth of every node in bytes per second, unlimited if `None`
+    pub upload_bytes_per_sec: Option<u64>,
+    /// download bandwidth of every node in bytes per second, unlimited if `None`
+    pub download_bytes_per_sec: Option<u64>,
+    /// bandwidth of the link shared by all nodes in bytes per second, unlimited if `None`
+    pub shared_bytes_per_sec: Option<u64>,
+    /// when each link finishes transmitting the messages queued on it, shared between clones
+    links: Arc<std::sync::Mutex<BandwidthLinks>>,
+}
+
+/// When the links of a [`BandwidthLimitedNetwork`] become free
+#[derive(Debug, Default)]
+struct BandwidthLinks {
+    /// upload link of each node
+    uploads: HashMap<Vec<u8>, Instant>,
+    /// download link of each node
+    downloads: HashMap<Vec<u8>, Instant>,
+    /// link shared by all nodes
+    shared: Option<Instant>,
+}
+
+impl BandwidthLimitedNetwork {
+    /// create new `BandwidthLimitedNetwork`
+    #[must_use]
+    pub fn new(
+        upload_bytes_per_sec: Option<u64>,
+        download_bytes_per_sec: Option<u64>,
+        shared_bytes_per_sec: Option<u64>,
+    ) -> Self {
+        BandwidthLimitedNetwork {
+            upload_bytes_per_sec,
+            download_bytes_per_sec,
+            shared_bytes_per_sec,
+            links: Arc::default(),
+        }
+    }
+
+    /// Queue `len` bytes on a link which is free from `free_at`, once they are available at
+    /// `start`. Returns when the link has transmitted them.
+    fn reserve(
+        free_at: &mut Option<Instant>,
+        start: Instant,
+        len: usize,
+        bytes_per_sec: Option<u64>,
+    ) -> Instant {
+        let Some(bytes_per_sec) = bytes_per_sec else {
+            return start;
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let duration = Duration::from_secs_f64(len as f64 / bytes_per_sec.max(1) as f64);
+        let end = free_at.map_or(start, |free_at| free_at.max(start)) + duration;
+        *free_at = Some(end);
+
+        end
+    }
+}
+
+impl NetworkReliability for BandwidthLimitedNetwork {
+    fn transmission_delay(&self, sender: &[u8], recipient: &[u8], len: usize) -> Duration {
+        let now = Instant::now();
+        let mut links = self.links.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut upload = links.uploads.get(sender).copied();
+        let uploaded = Self::reserve(&mut upload, now, len, self.upload_bytes_per_sec);
+        let shared = Self::reserve(&mut links.shared, uploaded, len, self.shared_bytes_per_sec);
+        let mut download = links.downloads.get(recipient).copied();
+        let delivered = Self::reserve(&mut download, shared, len, self.download_bytes_per_sec);
+
+        if let Some(upload) = upload {
+            links.uploads.insert(sender.to_vec(), upload);
+        }
+        if let Some(download) = download {
+            links.downloads.insert(recipient.to_vec(), download);
+        }
+
+        delivered - now
+    }
+}
+
 /// Used when broadcasting messages
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Topic {
@@ -26,6 +26,7 @@ use vbs::version::StaticVersionType;
 use super::{
     auction_results_provider::AuctionResultsProvider,
     block_contents::{BlockHeader, TestableBlock, Transaction},
+    commitment_scheme::CommitmentScheme,
     network::{
         AsyncGenerator, ConnectedNetwork, NetworkReliability, TestableNetworkingImplementation,
     },
@@ -187,6 +188,25 @@ pub trait ConsensusTime:
     fn new(val: u64) -> Self;
     /// Get the u64 format of time
     fn u64(&self) -> u64;
+
+    /// The time `rhs` units before this one, or `None` if that would be before genesis
+    #[must_use]
+    fn checked_sub(&self, rhs: u64) -> Option<Self> {
+        self.u64().checked_sub(rhs).map(Self::new)
+    }
+
+    /// Number of units from `earlier` to this time, or zero if `earlier` is not earlier
+    #[must_use]
+    fn saturating_distance(&self, earlier: Self) -> u64 {
+        self.u64().saturating_sub(earlier.u64())
+    }
+
+    /// Whether this time is at most `window` units before `reference`. Times after `reference`
+    /// are always within the window.
+    #[must_use]
+    fn is_within(&self, reference: Self, window: u64) -> bool {
+        reference.saturating_distance(*self) <= window
+    }
 }
 
 /// Trait with all the type definitions that are used in the current hotshot setup.
@@ -254,6 +274,10 @@ pub trait NodeType:
 
     /// The type builder uses to sign its messages
     type BuilderSignatureKey: BuilderSignatureKey;
+
+    /// The hash function leaves and certificates are committed with from
+    /// [`Versions::CommitmentScheme`] on.
+    type CommitmentScheme: CommitmentScheme;
 }
 
 /// Version information for HotShot
@@ -272,4 +296,16 @@ pub trait Versions: Clone + Copy + Debug + Send + Sync + 'static {
 
     /// The version at which to switch over to epochs logic
     type Epochs: StaticVersionType;
+
+    /// The version from which quorum votes are sent in their compact encoding. Compact votes
+    /// carry epoch-era vote data, so this only takes effect from the `Epochs` version on.
+    type CompactVotes: StaticVersionType;
+
+    /// The version from which leaf and certificate commitments are computed with
+    /// `NodeType::CommitmentScheme` instead of the native Keccak-256 commitments.
+    type CommitmentScheme: StaticVersionType;
+
+    /// The version from which signatures and vote commitments are bound to the chain ID of the
+    /// `UpgradeLock`, so that they are not valid on another chain run with the same keys.
+    type ChainId: StaticVersionType;
 }
@@ -10,14 +10,18 @@
 //! compatibilities over the current network state, which is modified by the transactions contained
 //! within blocks.
 
-use std::{error::Error, fmt::Debug, future::Future};
+use std::{error::Error, fmt::Debug, future::Future, sync::Arc};
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use vbs::version::Version;
 
 use super::block_contents::TestableBlock;
 use crate::{
+    clock::Clock,
     data::Leaf2,
+    encrypted_mempool::ThresholdDecryption,
+    fee_market::BaseFeeParams,
+    header_extension::{HeaderExtension, HeaderExtensionError},
     traits::{
         node_implementation::{ConsensusTime, NodeType},
         BlockPayload,
@@ -26,7 +30,74 @@ use crate::{
 };
 
 /// Instance-level state, which allows us to fetch missing validated state.
-pub trait InstanceState: Debug + Clone + Send + Sync {}
+pub trait InstanceState: Debug + Clone + Send + Sync {
+    /// Parameters of the chain's base fee, or `None` if blocks don't carry a base fee.
+    fn base_fee_params(&self) -> Option<BaseFeeParams> {
+        None
+    }
+
+    /// Threshold decryption for the encrypted mempool, or `None` if transactions are not
+    /// encrypted.
+    fn threshold_decryption(&self) -> Option<Arc<dyn ThresholdDecryption>> {
+        None
+    }
+
+    /// Maximum size of a block, in the unit of [`Transaction::minimum_block_size`], or `None` if
+    /// blocks are unbounded.
+    ///
+    /// [`Transaction::minimum_block_size`]: crate::traits::block_contents::Transaction::minimum_block_size
+    fn max_block_size(&self) -> Option<u64> {
+        None
+    }
+
+    /// This node's wall clock.
+    fn clock(&self) -> Clock {
+        Clock::default()
+    }
+
+    /// The extension a leader attaches to the header of the block at `block_number`, given the
+    /// extension `parent` of its parent's header, or `None` if headers carry no extension.
+    fn build_header_extension(
+        &self,
+        _parent: Option<&HeaderExtension>,
+        _block_number: u64,
+        _version: Version,
+    ) -> Option<HeaderExtension> {
+        None
+    }
+
+    /// Check the extension `proposed` in the header of the block at `block_number`, given the
+    /// extension `parent` of its parent's header.
+    ///
+    /// By default, the header must carry exactly the extension this node would have built.
+    ///
+    /// # Errors
+    ///
+    /// If the proposed extension is missing, unexpected or invalid.
+    fn validate_header_extension(
+        &self,
+        parent: Option<&HeaderExtension>,
+        proposed: Option<&HeaderExtension>,
+        block_number: u64,
+        version: Version,
+    ) -> Result<(), HeaderExtensionError> {
+        match (
+            self.build_header_extension(parent, block_number, version),
+            proposed,
+        ) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(HeaderExtensionError::Unexpected),
+            (Some(_), None) => Err(HeaderExtensionError::Missing),
+            (Some(expected), Some(proposed)) if expected == *proposed => Ok(()),
+            (Some(expected), Some(proposed)) if expected.version != proposed.version => {
+                Err(HeaderExtensionError::UnsupportedVersion(proposed.version))
+            }
+            (Some(_), Some(_)) => Err(HeaderExtensionError::Invalid(
+                "fields differ from the expected extension".to_string(),
+            )),
+        }
+    }
+}
 
 /// Application-specific state delta, which will be used to store a list of merkle tree entries.
 pub trait StateDelta:
@@ -9,7 +9,7 @@
 //! This modules provides the [`Storage`] trait.
 //!
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -20,10 +20,11 @@ use crate::{
     consensus::{CommitmentMap, View},
     data::{
         DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare,
-        VidDisperseShare2,
+        VidDisperseShare2, ViewChangeEvidence, ViewSyncCertificate,
     },
     event::HotShotAction,
-    message::Proposal,
+    governance::ParameterChange,
+    message::{OutboundMessage, Proposal},
     simple_certificate::{
         NextEpochQuorumCertificate2, QuorumCertificate, QuorumCertificate2, UpgradeCertificate,
     },
@@ -60,6 +61,112 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
         &self,
         proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
     ) -> Result<()>;
+    /// Record the timeout or view sync certificate which justified the proposal for `view`.
+    async fn append_view_change_evidence(
+        &self,
+        view: TYPES::View,
+        evidence: &ViewChangeEvidence<TYPES>,
+    ) -> Result<()>;
+    /// Load the recorded view change evidence for the proposals of the views in `start..=end`.
+    async fn load_view_change_evidence(
+        &self,
+        start: TYPES::View,
+        end: TYPES::View,
+    ) -> Result<BTreeMap<TYPES::View, ViewChangeEvidence<TYPES>>>;
+    /// Remove the recorded view change evidence for the proposal of `view`. Storage which doesn't
+    /// record view change evidence can leave this as a no-op.
+    async fn remove_view_change_evidence(&self, _view: TYPES::View) -> Result<()> {
+        Ok(())
+    }
+    /// Persist a safety-critical message before it is sent, so that it can be sent after a
+    /// restart if the node goes down first. Storage which doesn't persist the outbound queue can
+    /// leave this as a no-op.
+    async fn append_outbound_message(&self, _message: &OutboundMessage<TYPES>) -> Result<()> {
+        Ok(())
+    }
+    /// Forget a persisted outbound message, once it has been sent.
+    async fn remove_outbound_message(&self, _message: &OutboundMessage<TYPES>) -> Result<()> {
+        Ok(())
+    }
+    /// Load the persisted outbound messages for views from `start` on, oldest first. Messages for
+    /// earlier views are too old to be worth sending, and may be discarded.
+    async fn load_outbound_messages(
+        &self,
+        _start: TYPES::View,
+    ) -> Result<Vec<OutboundMessage<TYPES>>> {
+        Ok(Vec::new())
+    }
+    /// Persist the latest view sync certificate we have seen, so that view sync can resume from it
+    /// after a restart. Storage which doesn't persist view sync progress can leave this as a
+    /// no-op.
+    async fn update_view_sync_certificate(
+        &self,
+        _certificate: &ViewSyncCertificate<TYPES>,
+    ) -> Result<()> {
+        Ok(())
+    }
+    /// Load the persisted view sync certificate, if any.
+    async fn load_view_sync_certificate(&self) -> Result<Option<ViewSyncCertificate<TYPES>>> {
+        Ok(None)
+    }
+    /// Persist the height of the last decided block the application's execution hook has
+    /// executed. Storage which doesn't persist the execution cursor can leave this as a no-op, in
+    /// which case the hook starts over from the decided leaf after a restart.
+    async fn update_execution_cursor(&self, _height: u64) -> Result<()> {
+        Ok(())
+    }
+    /// Load the persisted height of the last executed block, if any.
+    async fn load_execution_cursor(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+    /// Persist decided leaves, before the decide is reported, so that a stream of decided leaves
+    /// can backfill from them. Storage which doesn't keep decided leaves can leave this as a
+    /// no-op, in which case such a stream only carries leaves decided after it starts.
+    async fn append_decided_leaves(&self, _leaves: &[Leaf2<TYPES>]) -> Result<()> {
+        Ok(())
+    }
+    /// Load the persisted decided leaves from `height` on, in order of height.
+    async fn load_decided_leaves(&self, _height: u64) -> Result<Vec<Leaf2<TYPES>>> {
+        Ok(Vec::new())
+    }
+    /// Persist a decided change to the runtime parameters which takes effect in
+    /// `activation_view`. Storage which doesn't persist parameter changes can leave this as a
+    /// no-op, in which case a restarted node only applies the changes decided after it restarts.
+    async fn append_parameter_change(
+        &self,
+        _activation_view: TYPES::View,
+        _change: ParameterChange,
+    ) -> Result<()> {
+        Ok(())
+    }
+    /// Load the persisted parameter changes with the views they take effect in, in the order
+    /// they were decided.
+    async fn load_parameter_changes(&self) -> Result<Vec<(TYPES::View, ParameterChange)>> {
+        Ok(Vec::new())
+    }
+    /// Persist the sampling beacon derived from a decided DRB result, which quorum samples are
+    /// drawn with from `from_epoch` on. Storage which doesn't persist beacons can leave this as a
+    /// no-op, in which case a restarted node only has the beacons decided after it restarts.
+    async fn append_sampling_beacon(
+        &self,
+        _from_epoch: TYPES::Epoch,
+        _beacon: [u8; 32],
+    ) -> Result<()> {
+        Ok(())
+    }
+    /// Load the persisted sampling beacons with the epochs they apply from.
+    async fn load_sampling_beacons(&self) -> Result<BTreeMap<TYPES::Epoch, [u8; 32]>> {
+        Ok(BTreeMap::new())
+    }
+    /// Record a proposed leaf whose block failed to apply to the validated state of its parent,
+    /// with the application's error, so the failure can be investigated later.
+    async fn record_invalid_state_transition(
+        &self,
+        _leaf: &Leaf2<TYPES>,
+        _error: &str,
+    ) -> Result<()> {
+        Ok(())
+    }
     /// Record a HotShotAction taken.
     async fn record_action(&self, view: TYPES::View, action: HotShotAction) -> Result<()>;
     /// Update the current high QC in storage.
@@ -90,6 +197,16 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
         &self,
         decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
     ) -> Result<()>;
+    /// Load the versions of the storage migrations which have been applied. Storage which doesn't
+    /// record its migrations can leave this as a no-op, in which case every migration is applied
+    /// again at each startup, and must be idempotent.
+    async fn load_applied_migrations(&self) -> Result<BTreeSet<u32>> {
+        Ok(BTreeSet::new())
+    }
+    /// Record that the storage migration `version` has been applied.
+    async fn record_migration(&self, _version: u32) -> Result<()> {
+        Ok(())
+    }
     /// Migrate leaves from `Leaf` to `Leaf2`, and proposals from `QuorumProposal` to `QuorumProposal2`
     async fn migrate_consensus(
         &self,
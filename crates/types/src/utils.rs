@@ -279,3 +279,25 @@ pub fn is_last_block_in_epoch(block_number: u64, epoch_height: u64) -> bool {
         block_number % epoch_height == 0
     }
 }
+
+/// Run the CPU heavy `f` on a thread where blocking is acceptable, or in place in builds without
+/// the `runtime` feature, which have no threads to spare
+///
+/// # Panics
+/// If `f` panics
+#[cfg_attr(not(feature = "runtime"), allow(clippy::unused_async))]
+pub async fn run_blocking<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    #[cfg(feature = "runtime")]
+    {
+        // Unwrap here will just propagate any panic from the spawned task, it's not a new place we can panic.
+        tokio::task::spawn_blocking(f).await.unwrap()
+    }
+    #[cfg(not(feature = "runtime"))]
+    {
+        f()
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Attested exports of the validator sets consensus runs with.
+//!
+//! External systems which follow the validator set, such as bridges and staking dashboards, can
+//! ask a node for a [`ValidatorSetAttestation`] rather than rebuild the stake tables themselves:
+//! the validators and stakes of the current epoch and of the next, as the node's membership has
+//! them, signed by the node. The digests of the exported sets are those of the
+//! [`StakeTableCommitment`] carried in block headers, so an export can also be checked against a
+//! decided header.
+//!
+//! The signature is over the chain id, as eight little endian bytes, followed by the commitment
+//! of the [`ValidatorSets`].
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+use utils::anytrace::*;
+
+use crate::{
+    message::UpgradeLock,
+    stake_table::{stake_table_digest, StakeTableCommitment, StakeTableEntry},
+    traits::{
+        election::Membership,
+        node_implementation::{NodeType, Versions},
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+};
+
+/// The validators of the current and next epochs, with their stakes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct ValidatorSets<TYPES: NodeType> {
+    /// The view the node was in when it exported the sets
+    pub view: TYPES::View,
+    /// The epoch the node was in when it exported the sets
+    pub epoch: TYPES::Epoch,
+    /// The validators of `epoch`, in stake table order
+    pub current: Vec<StakeTableEntry<TYPES::SignatureKey>>,
+    /// The validators of the epoch after `epoch`, if they are already known
+    pub next: Option<Vec<StakeTableEntry<TYPES::SignatureKey>>>,
+}
+
+impl<TYPES: NodeType> ValidatorSets<TYPES> {
+    /// The validator sets of `epoch` and the epoch after it in `membership`, exported in `view`
+    #[must_use]
+    pub fn new(membership: &TYPES::Membership, view: TYPES::View, epoch: TYPES::Epoch) -> Self {
+        let entries = |epoch| {
+            membership
+                .stake_table(epoch)
+                .iter()
+                .map(|entry| StakeTableEntry {
+                    stake_key: entry.public_key(),
+                    stake_amount: entry.stake(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let next = entries(epoch + 1);
+
+        Self {
+            view,
+            epoch,
+            current: entries(epoch),
+            next: (!next.is_empty()).then_some(next),
+        }
+    }
+
+    /// The commitment to the stake tables of the exported sets, as carried in block headers
+    #[must_use]
+    pub fn stake_table_commitment(&self) -> StakeTableCommitment {
+        StakeTableCommitment {
+            current: stake_table_digest::<TYPES::SignatureKey, _>(&self.current),
+            next: self
+                .next
+                .as_ref()
+                .map(|next| stake_table_digest::<TYPES::SignatureKey, _>(next)),
+        }
+    }
+}
+
+impl<TYPES: NodeType> Committable for ValidatorSets<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("Validator sets")
+            .u64_field("view", *self.view)
+            .u64_field("epoch", *self.epoch)
+            .field("stake tables", self.stake_table_commitment().commit())
+            .finalize()
+    }
+}
+
+/// [`ValidatorSets`] signed by the node which exported them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct ValidatorSetAttestation<TYPES: NodeType> {
+    /// The exported validator sets
+    pub sets: ValidatorSets<TYPES>,
+    /// The node which exported the sets
+    pub signer: TYPES::SignatureKey,
+    /// Signature of the node on the sets
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> ValidatorSetAttestation<TYPES> {
+    /// Sign `sets` as `signer`, with its `private_key`
+    ///
+    /// # Errors
+    /// If signing fails
+    pub async fn sign<V: Versions>(
+        sets: ValidatorSets<TYPES>,
+        signer: TYPES::SignatureKey,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<Self> {
+        let signature = TYPES::SignatureKey::sign(
+            private_key,
+            &upgrade_lock
+                .signing_message(sets.commit().as_ref(), sets.view)
+                .await,
+        )
+        .wrap()
+        .context(error!("Failed to sign validator sets"))?;
+
+        Ok(Self {
+            sets,
+            signer,
+            signature,
+        })
+    }
+
+    /// Whether the signature is that of the signer on the sets
+    pub async fn is_valid<V: Versions>(&self, upgrade_lock: &UpgradeLock<TYPES, V>) -> bool {
+        self.signer.validate(
+            &self.signature,
+            &upgrade_lock
+                .signing_message(self.sets.commit().as_ref(), self.sets.view)
+                .await,
+        )
+    }
+}
@@ -31,6 +31,7 @@ use jf_vid::{
     precomputable::Precomputable,
     VidDisperse, VidResult, VidScheme,
 };
+#[cfg(any(feature = "runtime", feature = "test-srs"))]
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
@@ -62,10 +63,7 @@ use crate::{
 #[must_use]
 #[memoize::memoize(SharedCache, Capacity: 10)]
 pub fn vid_scheme(num_storage_nodes: usize) -> VidSchemeType {
-    // recovery_threshold is currently num_storage_nodes rounded down to a power of two
-    // TODO recovery_threshold should be a function of the desired erasure code rate
-    // https://github.com/EspressoSystems/HotShot/issues/2152
-    let recovery_threshold = 1 << num_storage_nodes.ilog2();
+    let recovery_threshold = vid_recovery_threshold(num_storage_nodes);
 
     #[allow(clippy::panic)]
     let num_storage_nodes = u32::try_from(num_storage_nodes).unwrap_or_else(|err| {
@@ -78,18 +76,30 @@ pub fn vid_scheme(num_storage_nodes: usize) -> VidSchemeType {
     // TODO panic, return `Result`, or make `new` infallible upstream (eg. by panicking)?
     #[allow(clippy::panic)]
     VidSchemeType(
-        Advz::new(num_storage_nodes, recovery_threshold, &*KZG_SRS).unwrap_or_else(|err| {
+        Advz::new(num_storage_nodes, recovery_threshold, kzg_srs()).unwrap_or_else(|err| {
               panic!("advz construction failure: (num_storage nodes,recovery_threshold)=({num_storage_nodes},{recovery_threshold}); \
                       error: {err}")
         })
     )
 }
 
+/// Number of shares out of `num_storage_nodes` from which [`vid_scheme()`] recovers a payload.
+///
+/// # Panics
+/// When `num_storage_nodes` is zero.
+#[must_use]
+pub fn vid_recovery_threshold(num_storage_nodes: usize) -> usize {
+    // recovery_threshold is currently num_storage_nodes rounded down to a power of two
+    // TODO recovery_threshold should be a function of the desired erasure code rate
+    // https://github.com/EspressoSystems/HotShot/issues/2152
+    1 << num_storage_nodes.ilog2()
+}
+
 /// Similar to [`vid_scheme()`], but with `KZG_SRS_TEST` for testing purpose only.
 #[cfg(feature = "test-srs")]
 #[memoize::memoize(SharedCache, Capacity: 10)]
 pub fn vid_scheme_for_test(num_storage_nodes: usize) -> VidSchemeType {
-    let recovery_threshold = 1 << num_storage_nodes.ilog2();
+    let recovery_threshold = vid_recovery_threshold(num_storage_nodes);
     #[allow(clippy::panic)]
     let num_storage_nodes = u32::try_from(num_storage_nodes).unwrap_or_else(|err| {
         panic!("num_storage_nodes {num_storage_nodes} should fit into u32; error: {err}")
@@ -173,6 +183,7 @@ lazy_static! {
 }
 
 // By default, use SRS from Aztec's ceremony
+#[cfg(feature = "runtime")]
 lazy_static! {
     /// SRS comment
     static ref KZG_SRS: UnivariateUniversalParams<E> = {
@@ -187,6 +198,41 @@ lazy_static! {
     };
 }
 
+/// The SRS installed with [`install_kzg_srs`], in builds without the `runtime` feature
+#[cfg(not(feature = "runtime"))]
+static INSTALLED_KZG_SRS: std::sync::OnceLock<UnivariateUniversalParams<E>> =
+    std::sync::OnceLock::new();
+
+/// Install the SRS of [`vid_scheme`]. Builds without the `runtime` feature cannot download the SRS
+/// of Aztec's ceremony, so they are given it here before the first VID scheme is constructed. It
+/// must be that SRS for commitments to match those of the nodes.
+///
+/// # Errors
+/// If an SRS is already installed, in which case `srs` is returned
+#[cfg(not(feature = "runtime"))]
+pub fn install_kzg_srs(
+    srs: UnivariateUniversalParams<Bn254>,
+) -> Result<(), UnivariateUniversalParams<Bn254>> {
+    INSTALLED_KZG_SRS.set(srs)
+}
+
+/// The SRS of [`vid_scheme`]
+///
+/// # Panics
+/// Without the `runtime` feature, if no SRS has been installed with [`install_kzg_srs`]
+fn kzg_srs() -> &'static UnivariateUniversalParams<E> {
+    #[cfg(feature = "runtime")]
+    {
+        &KZG_SRS
+    }
+    #[cfg(not(feature = "runtime"))]
+    {
+        INSTALLED_KZG_SRS
+            .get()
+            .expect("the KZG SRS must be installed with `install_kzg_srs` before VID is used")
+    }
+}
+
 /// Private type alias for the EC pairing type parameter for [`Advz`].
 type E = Bn254;
 /// Private type alias for the hash type parameter for [`Advz`].
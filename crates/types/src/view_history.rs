@@ -0,0 +1,182 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! In-process history of recent views.
+//!
+//! Metrics report how consensus is doing now. The [`ViewHistory`] keeps statistics on each of the
+//! most recent views, so that operators can ask how it did over a past period, such as the number
+//! of views per decide over the last hour, without external tooling. It holds a fixed number of
+//! views and drops the oldest as new ones are entered, so its memory is bounded.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::traits::node_implementation::NodeType;
+
+/// Default number of views a [`ViewHistory`] holds, a few hours of views at a second a view
+pub const VIEW_HISTORY_CAPACITY: usize = 10_000;
+
+/// What happened in a view
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViewStats<TYPES: NodeType> {
+    /// The view
+    pub view: TYPES::View,
+    /// The leader of the view, if we knew it
+    pub leader: Option<TYPES::SignatureKey>,
+    /// When we entered the view
+    pub entered_at: Instant,
+    /// How long we were in the view, or `None` if we still are
+    pub duration: Option<Duration>,
+    /// Whether the view timed out
+    pub timed_out: bool,
+    /// Whether the leaf of the view has been decided
+    pub decided: bool,
+    /// Number of transactions in the block of the view, if it was decided and we had its payload
+    pub block_size: Option<u64>,
+}
+
+/// Statistics over the views of a period
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ViewSummary {
+    /// Number of views entered
+    pub views: u64,
+    /// Number of views whose leaves have been decided
+    pub decided: u64,
+    /// Number of views which timed out
+    pub timed_out: u64,
+    /// Mean time spent in a view, over the views we have left
+    pub mean_duration: Option<Duration>,
+    /// Number of views entered per decided view
+    pub views_per_decide: Option<f64>,
+    /// Mean number of transactions in a decided block, over the blocks whose payloads we had
+    pub mean_block_size: Option<f64>,
+}
+
+/// Statistics on the most recent views, oldest first
+#[derive(Clone, Debug)]
+pub struct ViewHistory<TYPES: NodeType> {
+    /// Most views held
+    capacity: usize,
+    /// The views held, in the order we entered them
+    views: VecDeque<ViewStats<TYPES>>,
+}
+
+impl<TYPES: NodeType> Default for ViewHistory<TYPES> {
+    fn default() -> Self {
+        Self::new(VIEW_HISTORY_CAPACITY)
+    }
+}
+
+impl<TYPES: NodeType> ViewHistory<TYPES> {
+    /// An empty history which holds up to `capacity` views
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            views: VecDeque::with_capacity(capacity.min(VIEW_HISTORY_CAPACITY)),
+        }
+    }
+
+    /// Record that we entered `view`, led by `leader`, at `now`, which ends the view we were in
+    pub fn enter(&mut self, view: TYPES::View, leader: Option<TYPES::SignatureKey>, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some(last) = self.views.back_mut() {
+            if view <= last.view {
+                return;
+            }
+            last.duration = Some(now.duration_since(last.entered_at));
+        }
+        if self.views.len() == self.capacity {
+            self.views.pop_front();
+        }
+
+        self.views.push_back(ViewStats {
+            view,
+            leader,
+            entered_at: now,
+            duration: None,
+            timed_out: false,
+            decided: false,
+            block_size: None,
+        });
+    }
+
+    /// Record that `view` timed out
+    pub fn record_timeout(&mut self, view: TYPES::View) {
+        if let Some(stats) = self.get_mut(view) {
+            stats.timed_out = true;
+        }
+    }
+
+    /// Record that the leaf of `view` was decided, with `block_size` transactions if known
+    pub fn record_decided(&mut self, view: TYPES::View, block_size: Option<u64>) {
+        if let Some(stats) = self.get_mut(view) {
+            stats.decided = true;
+            stats.block_size = block_size;
+        }
+    }
+
+    /// The statistics of `view`, if it is held
+    #[must_use]
+    pub fn get(&self, view: TYPES::View) -> Option<&ViewStats<TYPES>> {
+        let index = self.views.partition_point(|stats| stats.view < view);
+        self.views.get(index).filter(|stats| stats.view == view)
+    }
+
+    /// The statistics of `view` to update, if it is held
+    fn get_mut(&mut self, view: TYPES::View) -> Option<&mut ViewStats<TYPES>> {
+        let index = self.views.partition_point(|stats| stats.view < view);
+        self.views.get_mut(index).filter(|stats| stats.view == view)
+    }
+
+    /// The statistics of the `count` most recent views, oldest first
+    #[must_use]
+    pub fn recent(&self, count: usize) -> Vec<ViewStats<TYPES>> {
+        let skip = self.views.len().saturating_sub(count);
+        self.views.iter().skip(skip).cloned().collect()
+    }
+
+    /// Statistics over the views entered in the `period` up to `now`
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn summary(&self, period: Duration, now: Instant) -> ViewSummary {
+        let start = match now.checked_sub(period) {
+            Some(since) => self.views.partition_point(|stats| stats.entered_at < since),
+            None => 0,
+        };
+        let period = self.views.range(start..);
+
+        let mut summary = ViewSummary::default();
+        let mut total_duration = Duration::ZERO;
+        let mut finished = 0u32;
+        let mut total_block_size = 0u64;
+        let mut blocks = 0u64;
+        for stats in period {
+            summary.views += 1;
+            summary.decided += u64::from(stats.decided);
+            summary.timed_out += u64::from(stats.timed_out);
+            if let Some(duration) = stats.duration {
+                total_duration += duration;
+                finished += 1;
+            }
+            if let Some(block_size) = stats.block_size {
+                total_block_size += block_size;
+                blocks += 1;
+            }
+        }
+
+        summary.mean_duration = (finished > 0).then(|| total_duration / finished);
+        summary.views_per_decide =
+            (summary.decided > 0).then(|| summary.views as f64 / summary.decided as f64);
+        summary.mean_block_size = (blocks > 0).then(|| total_block_size as f64 / blocks as f64);
+
+        summary
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Maps keyed by view which can be updated through a shared reference.
+//!
+//! The VID shares and DA certificates in [`Consensus`](crate::consensus::Consensus) arrive for
+//! every view, from several tasks at once. Keeping them in a [`ViewMap`] lets those tasks insert
+//! them while holding only the read lock on consensus, so they no longer wait for, or hold up,
+//! every other reader. The map is split into shards by view, each behind its own lock which is
+//! only held for the duration of a single operation, so updates for distinct views rarely
+//! contend either.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::traits::node_implementation::ConsensusTime;
+
+/// Number of shards of a [`ViewMap`]. Consecutive views are in distinct shards.
+const NUM_SHARDS: u64 = 16;
+
+/// A map from views to values, sharded by view
+pub struct ViewMap<VIEW, V> {
+    /// The entries of each shard
+    shards: Vec<RwLock<BTreeMap<VIEW, V>>>,
+}
+
+impl<VIEW: ConsensusTime, V> Default for ViewMap<VIEW, V> {
+    fn default() -> Self {
+        Self {
+            shards: (0..NUM_SHARDS)
+                .map(|_| RwLock::new(BTreeMap::new()))
+                .collect(),
+        }
+    }
+}
+
+impl<VIEW: ConsensusTime, V: Clone> Clone for ViewMap<VIEW, V> {
+    fn clone(&self) -> Self {
+        Self {
+            shards: self
+                .shards
+                .iter()
+                .map(|shard| RwLock::new(read(shard).clone()))
+                .collect(),
+        }
+    }
+}
+
+impl<VIEW: ConsensusTime, V: Debug> Debug for ViewMap<VIEW, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for shard in &self.shards {
+            map.entries(read(shard).iter());
+        }
+        map.finish()
+    }
+}
+
+/// Read a shard, which is never left inconsistent by a panic
+fn read<VIEW, V>(shard: &RwLock<BTreeMap<VIEW, V>>) -> RwLockReadGuard<'_, BTreeMap<VIEW, V>> {
+    shard.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Write a shard, which is never left inconsistent by a panic
+fn write<VIEW, V>(shard: &RwLock<BTreeMap<VIEW, V>>) -> RwLockWriteGuard<'_, BTreeMap<VIEW, V>> {
+    shard.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<VIEW: ConsensusTime, V> ViewMap<VIEW, V> {
+    /// Create an empty map
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shard holding `view`
+    fn shard(&self, view: &VIEW) -> &RwLock<BTreeMap<VIEW, V>> {
+        // The index is below `NUM_SHARDS`, so it fits in a `usize`
+        #[allow(clippy::cast_possible_truncation)]
+        let index = (**view % NUM_SHARDS) as usize;
+        &self.shards[index]
+    }
+
+    /// Apply `f` to the value for `view`, if there is one
+    pub fn with<R>(&self, view: &VIEW, f: impl FnOnce(&V) -> R) -> Option<R> {
+        read(self.shard(view)).get(view).map(f)
+    }
+
+    /// Whether there is a value for `view`
+    #[must_use]
+    pub fn contains_key(&self, view: &VIEW) -> bool {
+        read(self.shard(view)).contains_key(view)
+    }
+
+    /// Set the value for `view`, returning the previous one
+    pub fn insert(&self, view: VIEW, value: V) -> Option<V> {
+        write(self.shard(&view)).insert(view, value)
+    }
+
+    /// Update the value for `view` with `f`, starting from the default value if there is none
+    pub fn update<R>(&self, view: VIEW, f: impl FnOnce(&mut V) -> R) -> R
+    where
+        V: Default,
+    {
+        f(write(self.shard(&view)).entry(view).or_default())
+    }
+
+    /// Remove the value for `view`
+    pub fn remove(&self, view: &VIEW) -> Option<V> {
+        write(self.shard(view)).remove(view)
+    }
+
+    /// Remove every value for a view before `view`
+    pub fn remove_before(&self, view: &VIEW) {
+        for shard in &self.shards {
+            let mut shard = write(shard);
+            *shard = shard.split_off(view);
+        }
+    }
+
+    /// The views with a value, in ascending order
+    #[must_use]
+    pub fn keys(&self) -> Vec<VIEW> {
+        let mut views: Vec<VIEW> = self
+            .shards
+            .iter()
+            .flat_map(|shard| read(shard).keys().copied().collect::<Vec<_>>())
+            .collect();
+        views.sort();
+        views
+    }
+
+    /// The highest view with a value
+    #[must_use]
+    pub fn max_view(&self) -> Option<VIEW> {
+        self.shards
+            .iter()
+            .filter_map(|shard| read(shard).keys().next_back().copied())
+            .max()
+    }
+
+    /// Number of views with a value
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    /// Whether there is no value for any view
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| read(shard).is_empty())
+    }
+}
+
+impl<VIEW: ConsensusTime, V: Clone> ViewMap<VIEW, V> {
+    /// A copy of the value for `view`
+    #[must_use]
+    pub fn get(&self, view: &VIEW) -> Option<V> {
+        self.with(view, V::clone)
+    }
+
+    /// A copy of the value for the highest view with one
+    #[must_use]
+    pub fn last(&self) -> Option<(VIEW, V)> {
+        let view = self.max_view()?;
+        // Another task may have removed the value since
+        self.get(&view).map(|value| (view, value))
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Shared record of the view each consensus task is in.
+//!
+//! The consensus, DA, VID and view sync tasks each track their own current view. They report every
+//! view change to a [`ViewTracker`], which checks that no task ever moves back to an older view
+//! and, when configured with a maximum lag, flags tasks that fall too far behind the others.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use async_lock::RwLock;
+use utils::anytrace::*;
+
+use crate::traits::node_implementation::NodeType;
+
+/// A task whose current view is tracked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TrackedTask {
+    /// The consensus task, which drives view changes
+    Consensus,
+    /// The DA task
+    Da,
+    /// The VID task
+    Vid,
+    /// The view sync task
+    ViewSync,
+}
+
+impl Display for TrackedTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackedTask::Consensus => write!(f, "consensus"),
+            TrackedTask::Da => write!(f, "DA"),
+            TrackedTask::Vid => write!(f, "VID"),
+            TrackedTask::ViewSync => write!(f, "view sync"),
+        }
+    }
+}
+
+/// The current view of every tracked task.
+#[derive(Debug)]
+pub struct ViewTracker<TYPES: NodeType> {
+    /// Latest view reported by each task
+    views: RwLock<BTreeMap<TrackedTask, TYPES::View>>,
+
+    /// Number of views a task may fall behind the most advanced task before it is flagged, or
+    /// `None` to not check for lagging tasks
+    max_lag: Option<u64>,
+}
+
+impl<TYPES: NodeType> Default for ViewTracker<TYPES> {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl<TYPES: NodeType> ViewTracker<TYPES> {
+    /// Create a new tracker, flagging tasks which lag more than `max_lag` views behind if given
+    #[must_use]
+    pub fn new(max_lag: Option<u64>) -> Self {
+        Self {
+            views: RwLock::new(BTreeMap::new()),
+            max_lag,
+        }
+    }
+
+    /// Record that `task` entered `view`.
+    ///
+    /// # Errors
+    /// Returns an error, without recording the view, if `task` already reported a later view.
+    pub async fn update(&self, task: TrackedTask, view: TYPES::View) -> Result<()> {
+        let mut views = self.views.write().await;
+        if let Some(current) = views.get(&task) {
+            ensure!(
+                view >= *current,
+                error!(
+                    "The {} task moved back from view {:?} to view {:?}",
+                    task, current, view
+                )
+            );
+        }
+        views.insert(task, view);
+
+        for (lagging, lagging_view, highest) in self.lagging_tasks(&views) {
+            tracing::warn!(
+                "The {} task is in view {:?}, more than {} views behind view {:?}",
+                lagging,
+                lagging_view,
+                self.max_lag.unwrap_or_default(),
+                highest
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The tasks more than the maximum lag behind the most advanced task, with their view
+    pub async fn lagging(&self) -> Vec<(TrackedTask, TYPES::View)> {
+        self.lagging_tasks(&*self.views.read().await)
+            .into_iter()
+            .map(|(task, view, _)| (task, view))
+            .collect()
+    }
+
+    /// The tasks in `views` more than the maximum lag behind the most advanced one, with their
+    /// view and the view of the most advanced task
+    fn lagging_tasks(
+        &self,
+        views: &BTreeMap<TrackedTask, TYPES::View>,
+    ) -> Vec<(TrackedTask, TYPES::View, TYPES::View)> {
+        let (Some(max_lag), Some(highest)) = (self.max_lag, views.values().max().copied()) else {
+            return Vec::new();
+        };
+
+        views
+            .iter()
+            .filter(|(_, view)| highest.saturating_sub(***view) > max_lag)
+            .map(|(task, view)| (*task, *view, highest))
+            .collect()
+    }
+
+    /// The latest view reported by `task`, if it reported any
+    pub async fn view(&self, task: TrackedTask) -> Option<TYPES::View> {
+        self.views.read().await.get(&task).copied()
+    }
+
+    /// The latest view reported by every task
+    pub async fn views(&self) -> BTreeMap<TrackedTask, TYPES::View> {
+        self.views.read().await.clone()
+    }
+}
@@ -9,7 +9,6 @@
 use std::{
     collections::{BTreeMap, HashMap},
     marker::PhantomData,
-    num::NonZeroU64,
     sync::Arc,
 };
 
@@ -78,25 +77,28 @@ pub trait Certificate<TYPES: NodeType, T>: HasViewNumber<TYPES> {
     fn is_valid_cert<V: Versions>(
         &self,
         stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
-        threshold: NonZeroU64,
+        threshold: U256,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> impl std::future::Future<Output = bool>;
     /// Returns the amount of stake needed to create this certificate
     // TODO: Make this a static ratio of the total stake of `Membership`
     fn threshold<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
-    ) -> u64;
+    ) -> U256;
 
     /// Get  Stake Table from Membership implementation.
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>;
 
     /// Get Total Nodes from Membership implementation.
     fn total_nodes<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> usize;
 
@@ -104,6 +106,7 @@ pub trait Certificate<TYPES: NodeType, T>: HasViewNumber<TYPES> {
     fn stake_table_entry<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         pub_key: &TYPES::SignatureKey,
+        view: TYPES::View,
         epoch: TYPES::Epoch,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>;
 
@@ -149,6 +152,8 @@ pub struct VoteAccumulator<
     pub phantom: PhantomData<(TYPES, VOTE, CERT)>,
     /// version information
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+    /// Keys of the peers which signed votes for two different commitments, not yet reported
+    pub equivocators: Vec<TYPES::SignatureKey>,
 }
 
 impl<
@@ -188,14 +193,16 @@ impl<
             return Either::Left(());
         }
 
+        let view = vote.view_number();
         let membership_reader = membership.read().await;
-        let Some(stake_table_entry) = CERT::stake_table_entry(&*membership_reader, &key, epoch)
+        let Some(stake_table_entry) =
+            CERT::stake_table_entry(&*membership_reader, &key, view, epoch)
         else {
             return Either::Left(());
         };
-        let stake_table = CERT::stake_table(&*membership_reader, epoch);
-        let total_nodes = CERT::total_nodes(&*membership_reader, epoch);
-        let threshold = CERT::threshold(&*membership_reader, epoch);
+        let stake_table = CERT::stake_table(&*membership_reader, view, epoch);
+        let total_nodes = CERT::total_nodes(&*membership_reader, view, epoch);
+        let threshold = CERT::threshold(&*membership_reader, view, epoch);
         drop(membership_reader);
 
         let Some(vote_node_id) = stake_table
@@ -205,6 +212,15 @@ impl<
             return Either::Left(());
         };
 
+        // A valid signature on a vote for another commitment proves the signer equivocated
+        if self.vote_outcomes.iter().any(|(commitment, (_, votes))| {
+            *commitment != vote_commitment && votes.contains_key(&key)
+        }) {
+            error!("Node {} voted for two different commitments", key);
+            self.equivocators.push(key);
+            return Either::Left(());
+        }
+
         let original_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType =
             vote.signature();
 
@@ -231,13 +247,10 @@ impl<
         *total_stake_casted += stake_table_entry.stake();
         total_vote_map.insert(key, (vote.signature(), vote_commitment));
 
-        if *total_stake_casted >= threshold.into() {
+        if *total_stake_casted >= threshold {
             // Assemble QC
             let real_qc_pp: <<TYPES as NodeType>::SignatureKey as SignatureKey>::QcParams =
-                <TYPES::SignatureKey as SignatureKey>::public_parameter(
-                    stake_table,
-                    U256::from(threshold),
-                );
+                <TYPES::SignatureKey as SignatureKey>::public_parameter(stake_table, threshold);
 
             let real_qc_sig = <TYPES::SignatureKey as SignatureKey>::assemble(
                 &real_qc_pp,
@@ -255,6 +268,20 @@ impl<
         }
         Either::Left(())
     }
+
+    /// The stake accumulated so far behind the best supported vote commitment, as a percentage of
+    /// `threshold`
+    #[must_use]
+    pub fn percent_of_threshold(&self, threshold: U256) -> u64 {
+        let stake_casted = self
+            .vote_outcomes
+            .values()
+            .map(|(stake_casted, _)| *stake_casted)
+            .max()
+            .unwrap_or_default();
+
+        (stake_casted * 100 / threshold.max(U256::one())).low_u64()
+    }
 }
 
 /// Mapping of commitments to vote tokens by key.
@@ -0,0 +1,134 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Encodings of messages on the wire.
+//!
+//! Every message on the wire starts with its [`TtlHeader`](crate::message_ttl::TtlHeader), then
+//! the version of the protocol it belongs to, followed by the message in the [`WireFormat`] the
+//! network is configured with. The version prefix stays the same in every format, so a node can
+//! always tell which version a message is for, and a future format can be introduced together
+//! with a version upgrade.
+//!
+//! New formats implement [`WireCodec`] and get a [`WireFormat`] variant. Schema-based formats,
+//! such as protobuf, don't fit this mould, as they cannot encode arbitrary `serde` types without
+//! a schema for every message.
+
+use std::{fmt, str::FromStr};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use utils::anytrace::*;
+
+/// An encoding of messages, without the version prefix
+pub trait WireCodec {
+    /// The format this codec implements
+    const FORMAT: WireFormat;
+
+    /// Encode `message`
+    ///
+    /// # Errors
+    /// If the message cannot be encoded
+    fn encode<M: Serialize>(message: &M) -> Result<Vec<u8>>;
+
+    /// Decode a message from `bytes`
+    ///
+    /// # Errors
+    /// If `bytes` is not an encoded message
+    fn decode<M: DeserializeOwned>(bytes: &[u8]) -> Result<M>;
+}
+
+/// The binary format HotShot has always used, which is `bincode` with its default options
+pub struct BincodeCodec;
+
+impl WireCodec for BincodeCodec {
+    const FORMAT: WireFormat = WireFormat::Bincode;
+
+    fn encode<M: Serialize>(message: &M) -> Result<Vec<u8>> {
+        bincode::serialize(message)
+            .wrap()
+            .context(info!("Failed to encode message as bincode"))
+    }
+
+    fn decode<M: DeserializeOwned>(bytes: &[u8]) -> Result<M> {
+        bincode::deserialize(bytes)
+            .wrap()
+            .context(info!("Failed to decode bincode message"))
+    }
+}
+
+/// CBOR (RFC 8949), which is self-describing and has implementations in most languages
+pub struct CborCodec;
+
+impl WireCodec for CborCodec {
+    const FORMAT: WireFormat = WireFormat::Cbor;
+
+    fn encode<M: Serialize>(message: &M) -> Result<Vec<u8>> {
+        cbor4ii::serde::to_vec(Vec::new(), message)
+            .wrap()
+            .context(info!("Failed to encode message as CBOR"))
+    }
+
+    fn decode<M: DeserializeOwned>(bytes: &[u8]) -> Result<M> {
+        cbor4ii::serde::from_slice(bytes)
+            .wrap()
+            .context(info!("Failed to decode CBOR message"))
+    }
+}
+
+/// The encoding a network uses for messages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    /// [`BincodeCodec`]
+    #[default]
+    Bincode,
+    /// [`CborCodec`]
+    Cbor,
+}
+
+impl WireFormat {
+    /// Encode `message` in this format
+    ///
+    /// # Errors
+    /// If the message cannot be encoded
+    pub fn encode<M: Serialize>(self, message: &M) -> Result<Vec<u8>> {
+        match self {
+            Self::Bincode => BincodeCodec::encode(message),
+            Self::Cbor => CborCodec::encode(message),
+        }
+    }
+
+    /// Decode a message in this format from `bytes`
+    ///
+    /// # Errors
+    /// If `bytes` is not a message in this format
+    pub fn decode<M: DeserializeOwned>(self, bytes: &[u8]) -> Result<M> {
+        match self {
+            Self::Bincode => BincodeCodec::decode(bytes),
+            Self::Cbor => CborCodec::decode(bytes),
+        }
+    }
+}
+
+impl fmt::Display for WireFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Bincode => "bincode",
+            Self::Cbor => "cbor",
+        })
+    }
+}
+
+impl FromStr for WireFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bincode" => Ok(Self::Bincode),
+            "cbor" => Ok(Self::Cbor),
+            _ => bail!("Unknown wire format: {s}"),
+        }
+    }
+}